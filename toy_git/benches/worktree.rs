@@ -0,0 +1,159 @@
+// addとstatusでファイルを読んでhashする速さを測る
+// cargo bench --bench worktree [-- <ファイルの数>]
+// core.preloadIndexで1threadにしたときとCPUの数のthreadのとき、stat情報が使えるときと使えないときを比べる
+// また、addとstatusがthreadに分けて行うファイルのhashと保存を、threadの数を変えて比べる
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use toy_git::cmd;
+use toy_git::diff;
+use toy_git::index::Index;
+use toy_git::object::ObjectKind;
+use toy_git::parallel;
+use toy_git::repository::Repository;
+use toy_git::store;
+
+const FILE_SIZE: usize = 16 * 1024;
+
+fn main() -> io::Result<()> {
+	let count = env::args()
+		.skip(1)
+		.find_map(|x| x.parse().ok())
+		.unwrap_or(5000);
+	let dir = env::temp_dir().join(format!("toy_git_bench_{}", std::process::id()));
+	println!(
+		"{} files of {} bytes, {} threads available",
+		count,
+		FILE_SIZE,
+		std::thread::available_parallelism().map_or(1, |x| x.get())
+	);
+
+	for preload in [false, true] {
+		let repo = setup(&dir, count, preload)?;
+		let add = time(|| add_all(&repo))?;
+		let readd = time(|| add_all(&repo))?;
+		let warm = time(|| status(&repo))?;
+		// mtimeを変えるとstat情報が合わなくなり、全てのファイルを読み直す
+		touch(&dir, count)?;
+		let cold = time(|| status(&repo))?;
+		println!(
+			"core.preloadIndex={:<5}  add: {:>8.1?}  add again: {:>8.1?}  status(stat cache): {:>8.1?}  status(rehash): {:>8.1?}",
+			preload, add, readd, warm, cold
+		);
+		fs::remove_dir_all(&dir)?;
+	}
+
+	// 1threadのときに比べてどれだけ速いか
+	let repo = setup(&dir, count, true)?;
+	let files: Vec<PathBuf> = (0..count).map(|i| file(&dir, i)).collect();
+	let mut base = None;
+	for threads in [1, 2, 4, 8] {
+		let hash = time(|| hash_files(&repo, &files, threads))?;
+		let write = time(|| write_files(&repo, &files, threads))?;
+		let (hash_base, write_base) = *base.get_or_insert((hash, write));
+		println!(
+			"threads={:<2}  hash: {:>8.1?} (x{:.2})  hash and write: {:>8.1?} (x{:.2})",
+			threads,
+			hash,
+			hash_base.as_secs_f64() / hash.as_secs_f64(),
+			write,
+			write_base.as_secs_f64() / write.as_secs_f64()
+		);
+		fs::remove_dir_all(repo.objects_dir())?;
+		fs::create_dir_all(repo.objects_dir())?;
+	}
+	fs::remove_dir_all(&dir)?;
+	Ok(())
+}
+
+// count個のファイルがあるworking directory
+fn setup(dir: &Path, count: usize, preload: bool) -> io::Result<Repository> {
+	let _ = fs::remove_dir_all(dir);
+	fs::create_dir_all(dir)?;
+	let opts = cmd::InitOptions {
+		bare: false,
+		template: Some(String::new()),
+		initial_branch: None,
+		quiet: true,
+		object_format: None,
+	};
+	cmd::init(dir, &opts)?;
+	let config = dir.join(".git/config");
+	let mut content = fs::read_to_string(&config)?;
+	content.push_str(&format!("[core]\n\tpreloadIndex = {}\n", preload));
+	fs::write(&config, content)?;
+
+	for i in 0..count {
+		let path = file(dir, i);
+		fs::create_dir_all(path.parent().unwrap())?;
+		let line = format!("line of file {}\n", i);
+		fs::write(&path, line.repeat(FILE_SIZE / line.len()))?;
+	}
+	touch(dir, count)?;
+	env::set_current_dir(dir)?;
+	Repository::discover(dir)
+}
+
+// 100個ずつディレクトリに分ける
+fn file(dir: &Path, i: usize) -> PathBuf {
+	dir.join(format!("d{}", i / 100)).join(format!("f{}", i))
+}
+
+// indexを書く前の時刻にして、racyとみなされないようにする
+fn touch(dir: &Path, count: usize) -> io::Result<()> {
+	let past = SystemTime::now() - Duration::from_secs(10);
+	for i in 0..count {
+		fs::File::options()
+			.write(true)
+			.open(file(dir, i))?
+			.set_modified(past)?;
+	}
+	Ok(())
+}
+
+fn add_all(repo: &Repository) -> io::Result<()> {
+	let opts = cmd::AddOptions {
+		dry_run: false,
+		force: false,
+	};
+	cmd::add(repo, &[String::from(".")], &opts).map(|_| ())
+}
+
+fn status(repo: &Repository) -> io::Result<()> {
+	let index = Index::load(repo)?;
+	diff::worktree_entries(repo, &index).map(|_| ())
+}
+
+// statusがstat情報の合わないファイルに行うこと
+fn hash_files(repo: &Repository, files: &[PathBuf], threads: usize) -> io::Result<()> {
+	parallel::map(threads, files, |path| {
+		let size = fs::metadata(path)?.len() as usize;
+		store::hash_stream(
+			repo.hash_algo,
+			ObjectKind::Blob,
+			size,
+			&mut fs::File::open(path)?,
+		)
+	})
+	.map(|_| ())
+}
+
+// addがファイルごとに行うこと
+fn write_files(repo: &Repository, files: &[PathBuf], threads: usize) -> io::Result<()> {
+	let writer = store::LooseWriter::new(repo)?;
+	parallel::map(threads, files, |path| {
+		let size = fs::metadata(path)?.len() as usize;
+		writer.write_stream(ObjectKind::Blob, size, &mut fs::File::open(path)?)
+	})
+	.map(|_| ())
+}
+
+fn time(f: impl FnOnce() -> io::Result<()>) -> io::Result<Duration> {
+	let start = Instant::now();
+	f()?;
+	Ok(start.elapsed())
+}
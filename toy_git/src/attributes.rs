@@ -0,0 +1,182 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::repository::Repository;
+use crate::wildmatch::wildmatch;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+	Set,          // "text"
+	Unset,        // "-text"
+	Text(String), // "filter=lfs"
+	Unspecified,  // "!text" (それより前の指定を取り消す)
+}
+
+// .gitattributesの1行 "<pattern> <attr> <attr>..."
+pub struct Rule {
+	pub base: String, // 書かれていた.gitattributesのあるディレクトリ。patternはここからのpath
+	pub pattern: String,
+	pub attrs: Vec<(String, Value)>,
+}
+
+// 優先度の低い順に並べたruleと、"[attr]<name> <attr>..." で定義したmacro
+// macroを設定したpathには、そのmacroの後に定義のattrを書いたのと同じように扱う
+pub struct Attributes {
+	pub rules: Vec<Rule>,
+	pub macros: Vec<(String, Vec<(String, Value)>)>,
+}
+
+// gitに組み込まれているmacro
+const BUILTIN_MACROS: &str = "[attr]binary -diff -merge -text";
+
+// macroの中のmacroを展開する深さ
+const MAX_MACRO_DEPTH: usize = 8;
+
+impl Attributes {
+	pub fn from(content: &str) -> Self {
+		let mut attributes = Self::new();
+		attributes.parse(content, "");
+		attributes
+	}
+
+	fn new() -> Self {
+		let mut attributes = Self {
+			rules: Vec::new(),
+			macros: Vec::new(),
+		};
+		attributes.parse(BUILTIN_MACROS, "");
+		attributes
+	}
+
+	// working directoryにある.gitattributes(深いディレクトリのものほど優先)と
+	// .git/info/attributes(最も優先)を読む
+	pub fn load(repo: &Repository) -> io::Result<Self> {
+		let mut attributes = Self::new();
+		if !repo.bare {
+			attributes.load_dir(&repo.workdir, "")?;
+		}
+		if let Some(content) = read(&repo.commondir.join("info").join("attributes"))? {
+			attributes.parse(&content, "");
+		}
+		Ok(attributes)
+	}
+
+	// pathに対するattrの値。後に書かれた行が優先される
+	pub fn get(&self, path: &str, attr: &str) -> Value {
+		self.rules
+			.iter()
+			.rev()
+			.filter(|rule| {
+				let path = match rule.base.as_str() {
+					"" => Some(path),
+					base => path.strip_prefix(base).and_then(|x| x.strip_prefix('/')),
+				};
+				path.is_some_and(|path| matches(&rule.pattern, path))
+			})
+			.find_map(|rule| {
+				let attrs = self.expand(&rule.attrs, 0);
+				attrs.into_iter().rev().find(|(name, _)| name == attr)
+			})
+			.map(|(_, value)| value)
+			.unwrap_or(Value::Unspecified)
+	}
+
+	// 設定したmacroの直後にその定義のattrを並べる
+	fn expand(&self, attrs: &[(String, Value)], depth: usize) -> Vec<(String, Value)> {
+		let mut expanded = Vec::new();
+		for (name, value) in attrs {
+			expanded.push((name.clone(), value.clone()));
+			let Some((_, definition)) = self.macros.iter().rev().find(|(x, _)| x == name) else {
+				continue;
+			};
+			if *value == Value::Set && depth < MAX_MACRO_DEPTH {
+				expanded.extend(self.expand(definition, depth + 1));
+			}
+		}
+		expanded
+	}
+
+	// gitと同じく、macroはtop levelの.gitattributesとinfo/attributesでしか定義できない
+	fn parse(&mut self, content: &str, base: &str) {
+		let lines = content
+			.lines()
+			.map(str::trim)
+			.filter(|x| !x.is_empty() && !x.starts_with('#'));
+		for line in lines {
+			let mut iter = line.split_whitespace();
+			let Some(pattern) = iter.next() else {
+				continue;
+			};
+			let attrs = iter.map(parse_attr).collect();
+			match pattern.strip_prefix("[attr]") {
+				Some(name) if base.is_empty() => self.macros.push((String::from(name), attrs)),
+				Some(_) => {}
+				None => self.rules.push(Rule {
+					base: String::from(base),
+					pattern: String::from(pattern),
+					attrs,
+				}),
+			}
+		}
+	}
+
+	// dirの.gitattributesを読んでから、その下のディレクトリを名前の順に辿る
+	// 親のruleが子のruleより前に並ぶ
+	fn load_dir(&mut self, dir: &Path, base: &str) -> io::Result<()> {
+		if let Some(content) = read(&dir.join(".gitattributes"))? {
+			self.parse(&content, base);
+		}
+
+		let mut children = Vec::new();
+		for entry in fs::read_dir(dir)? {
+			let entry = entry?;
+			let name = entry.file_name().to_string_lossy().into_owned();
+			if name != ".git" && entry.file_type()?.is_dir() {
+				children.push(name);
+			}
+		}
+		children.sort();
+
+		for name in children {
+			let child = match base {
+				"" => name.clone(),
+				base => format!("{}/{}", base, name),
+			};
+			self.load_dir(&dir.join(&name), &child)?;
+		}
+		Ok(())
+	}
+}
+
+fn read(path: &Path) -> io::Result<Option<String>> {
+	match fs::read_to_string(path) {
+		Ok(content) => Ok(Some(content)),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+		Err(e) => Err(e),
+	}
+}
+
+fn parse_attr(attr: &str) -> (String, Value) {
+	if let Some(name) = attr.strip_prefix('-') {
+		return (String::from(name), Value::Unset);
+	}
+	if let Some(name) = attr.strip_prefix('!') {
+		return (String::from(name), Value::Unspecified);
+	}
+	match attr.split_once('=') {
+		Some((name, value)) => (String::from(name), Value::Text(String::from(value))),
+		None => (String::from(attr), Value::Set),
+	}
+}
+
+// "/" を含まないpatternはファイル名だけと比べる。含む場合はpath全体と比べる
+fn matches(pattern: &str, path: &str) -> bool {
+	match pattern.trim_start_matches('/').contains('/') || pattern.starts_with('/') {
+		true => wildmatch(pattern.trim_start_matches('/'), path, true),
+		false => {
+			let name = path.rsplit('/').next().unwrap_or(path);
+			wildmatch(pattern, name, true)
+		}
+	}
+}
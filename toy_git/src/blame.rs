@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+
+use crate::diff::{lines, myers_diff, Edit};
+use crate::hash::Hash;
+use crate::object::ObjectKind;
+use crate::repository::Repository;
+use crate::revparse;
+use crate::store;
+use crate::walker::CommitWalker;
+
+// ファイルの1行と、それを入れたcommit
+pub struct Line {
+	pub commit: Option<Hash>, // Noneはまだcommitされていないworking directoryの行
+	pub content: Vec<u8>,
+}
+
+// まだ誰のものか決まっていない行を持っているcommit
+struct Suspect {
+	blob: Hash,
+	lines: Vec<(usize, usize)>, // (最後の内容での行番号, このcommitのblobでの行番号)
+}
+
+// revのpathの各行を、それを入れたcommitに割り当てる
+// revがNoneならworking directoryのファイルをHEADと比べ、HEADに無い行はcommitされていないものにする
+// commitをcommitterの日付の新しい順に見て、parentと同じ行はparentのものとし、どのparentにも無い行をそのcommitのものにする
+pub fn blame(repo: &Repository, rev: Option<&str>, path: &str) -> io::Result<Vec<Line>> {
+	let commit = revparse::resolve(repo, rev.unwrap_or("HEAD"))?;
+	// gitと同じく、revを書いたときだけpathを括らない
+	let blob = blob_at(repo, &commit, path)?.ok_or_else(|| {
+		io::Error::other(match rev {
+			Some(rev) => format!("no such path {} in {}", path, rev),
+			None => format!("no such path '{}' in HEAD", path),
+		})
+	})?;
+
+	// bare repositoryではrevが無くてもHEADのものを見る
+	let worktree = rev.is_none() && !repo.bare;
+	let content = match worktree {
+		true => fs::read(repo.workdir.join(path))?,
+		false => store::read_object(repo, &blob.0)?.1,
+	};
+	let mut result: Vec<Line> = lines(&content)
+		.iter()
+		.map(|x| Line {
+			commit: None,
+			content: x.to_vec(),
+		})
+		.collect();
+
+	// working directoryの行はHEADと同じものだけをHEADに渡す
+	let mut pending = (0..result.len()).map(|i| (i, i)).collect();
+	if worktree {
+		let head = store::read_object(repo, &blob.0)?.1;
+		pending = matching(&head, &content, &mut pending);
+	}
+
+	let mut suspects: HashMap<Hash, Suspect> = HashMap::new();
+	let mut walker = CommitWalker::new(repo);
+	walker.push(commit.clone())?;
+	suspects.insert(
+		commit,
+		Suspect {
+			blob,
+			lines: pending,
+		},
+	);
+
+	while let Some((hash, _)) = walker.pop() {
+		let Some(suspect) = suspects.remove(&hash) else {
+			continue;
+		};
+		let mut rest = suspect.lines;
+		let commit = store::read_commit(repo, &hash.0)?;
+
+		let mut parents = Vec::new();
+		for parent in &commit.parents {
+			let parent = Hash::from_hex(parent)
+				.ok_or_else(|| io::Error::other(format!("bad parent {} in {}", parent, hash)))?;
+			if let Some(blob) = blob_at(repo, &parent, path)? {
+				parents.push((parent, blob));
+			}
+		}
+
+		// 同じ内容のparentがあれば、全ての行をそれに渡す
+		// 無ければそれぞれのparentとの差分で同じ行を順に渡していく
+		let passes: Vec<(Hash, Suspect)> =
+			match parents.iter().find(|(_, blob)| *blob == suspect.blob) {
+				Some((parent, blob)) => vec![(
+					parent.clone(),
+					Suspect {
+						blob: blob.clone(),
+						lines: std::mem::take(&mut rest),
+					},
+				)],
+				None => {
+					let content = store::read_object(repo, &suspect.blob.0)?.1;
+					let mut passes = Vec::new();
+					for (parent, blob) in parents {
+						if rest.is_empty() {
+							break;
+						}
+						let old = store::read_object(repo, &blob.0)?.1;
+						let passed = matching(&old, &content, &mut rest);
+						passes.push((
+							parent,
+							Suspect {
+								blob,
+								lines: passed,
+							},
+						));
+					}
+					passes
+				}
+			};
+
+		for (parent, pass) in passes {
+			if pass.lines.is_empty() {
+				continue;
+			}
+			match suspects.get_mut(&parent) {
+				Some(x) => x.lines.extend(pass.lines),
+				None => {
+					walker.push_again(parent.clone())?;
+					suspects.insert(parent, pass);
+				}
+			}
+		}
+		for (line, _) in rest {
+			result[line].commit = Some(hash.clone());
+		}
+	}
+	Ok(result)
+}
+
+// commitのtreeでpathにあるblob
+fn blob_at(repo: &Repository, commit: &Hash, path: &str) -> io::Result<Option<Hash>> {
+	let tree = store::read_commit(repo, &commit.0)?.tree;
+	let tree = Hash::from_hex(&tree)
+		.ok_or_else(|| io::Error::other(format!("bad tree {} in {}", tree, commit)))?;
+	match revparse::tree_path(repo, tree, path)? {
+		Some(hash) if store::read_object_header(repo, &hash.0)?.0 == ObjectKind::Blob => {
+			Ok(Some(hash))
+		}
+		_ => Ok(None),
+	}
+}
+
+// linesのうちoldにもある行を取り出し、oldでの行番号にして返す。linesには無い行が残る
+fn matching(old: &[u8], new: &[u8], lines: &mut Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+	let mut old_of_new = HashMap::new();
+	for edit in myers_diff(&self::lines(old), &self::lines(new)) {
+		if let Edit::Equal(a, b) = edit {
+			old_of_new.insert(b, a);
+		}
+	}
+	let mut passed = Vec::new();
+	lines.retain(|&(line, index)| match old_of_new.get(&index) {
+		Some(&old) => {
+			passed.push((line, old));
+			false
+		}
+		None => true,
+	});
+	passed
+}
@@ -0,0 +1,107 @@
+use crate::error::GitError;
+
+// index、.idx、commit-graphなどのbinaryのファイルの一部分
+// 決まった位置の値を読み、足りなければファイルの先頭からの位置を付けたErrorにする
+#[derive(Clone, Copy)]
+pub struct Bytes<'a> {
+	pub data: &'a [u8],
+	pub base: usize, // dataの先頭のファイルでの位置
+}
+
+impl<'a> Bytes<'a> {
+	pub fn new(data: &'a [u8]) -> Self {
+		Self { data, base: 0 }
+	}
+
+	pub fn len(&self) -> usize {
+		self.data.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.data.is_empty()
+	}
+
+	// posの位置のmessageのError
+	pub fn error(&self, pos: usize, message: &'static str) -> GitError {
+		GitError::Corrupt {
+			offset: self.base + pos,
+			message,
+		}
+	}
+
+	pub fn get(&self, pos: usize, len: usize) -> Result<&'a [u8], GitError> {
+		pos.checked_add(len)
+			.and_then(|end| self.data.get(pos..end))
+			.ok_or_else(|| self.error(pos.min(self.data.len()), "unexpected end of data"))
+	}
+
+	// start..endの部分。位置はそのまま元のファイルでのもの
+	pub fn slice(&self, start: usize, end: usize) -> Result<Bytes<'a>, GitError> {
+		let len = end
+			.checked_sub(start)
+			.ok_or_else(|| self.error(start, "bad range"))?;
+		Ok(Bytes {
+			data: self.get(start, len)?,
+			base: self.base + start,
+		})
+	}
+
+	pub fn rest(&self, start: usize) -> Result<Bytes<'a>, GitError> {
+		self.slice(start, self.data.len().max(start))
+	}
+
+	pub fn u8(&self, pos: usize) -> Result<u8, GitError> {
+		Ok(self.get(pos, 1)?[0])
+	}
+
+	pub fn u16(&self, pos: usize) -> Result<u16, GitError> {
+		let b = self.get(pos, 2)?;
+		Ok(u16::from_be_bytes([b[0], b[1]]))
+	}
+
+	pub fn u32(&self, pos: usize) -> Result<u32, GitError> {
+		let b = self.get(pos, 4)?;
+		Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+	}
+
+	pub fn u64(&self, pos: usize) -> Result<u64, GitError> {
+		let mut b = [0; 8];
+		b.copy_from_slice(self.get(pos, 8)?);
+		Ok(u64::from_be_bytes(b))
+	}
+}
+
+// commit-graphやmulti-pack-indexのchunkの表
+// posから (4byteのid, 8byteのoffset) がcount + 1組並び、最後の組のoffsetが最後のchunkの終わり
+pub struct Chunks<'a> {
+	bytes: Bytes<'a>,
+	table: Vec<(&'a [u8], usize)>,
+	pos: usize,
+}
+
+impl<'a> Chunks<'a> {
+	pub fn read(bytes: Bytes<'a>, pos: usize, count: usize) -> Result<Self, GitError> {
+		let mut table = Vec::with_capacity(count + 1);
+		for i in 0..=count {
+			let entry = pos + i * 12;
+			table.push((bytes.get(entry, 4)?, bytes.u64(entry + 4)? as usize));
+		}
+		Ok(Self { bytes, table, pos })
+	}
+
+	pub fn find(&self, id: &[u8]) -> Result<Option<Bytes<'a>>, GitError> {
+		let count = self.table.len() - 1;
+		match self.table[..count].iter().position(|(x, _)| *x == id) {
+			Some(i) => Ok(Some(
+				self.bytes.slice(self.table[i].1, self.table[i + 1].1)?,
+			)),
+			None => Ok(None),
+		}
+	}
+
+	// 無ければ読めないchunk
+	pub fn require(&self, id: &[u8]) -> Result<Bytes<'a>, GitError> {
+		self.find(id)?
+			.ok_or_else(|| self.bytes.error(self.pos, "missing required chunk"))
+	}
+}
@@ -0,0 +1,189 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::filter::Filters;
+use crate::ignore::Ignore;
+use crate::index::{self, Entry, Index};
+use crate::object::{Blob, ObjectKind};
+use crate::parallel;
+use crate::repository::Repository;
+use crate::store;
+use crate::worktree::Worktree;
+
+pub struct Options {
+	pub dry_run: bool, // indexもobjectも書かず、登録されるpathを "add '<path>'" と出すだけ
+	pub force: bool,   // -f ignoreされたファイルも登録する
+}
+
+// filterを通さずにそのまま保存できるファイル。まとめてthreadに分けて書く
+struct Streamed {
+	path: String,
+	file: PathBuf, // working directoryでのpath
+	meta: fs::Metadata,
+}
+
+// 指定されたpath(ディレクトリの場合はその中のファイル全て)をindexに登録する
+// forceでなければ、indexに無いignoreされたファイルは登録しない
+// 指定したpath自体がignoreされていれば、他のpathを登録してからgitと同じメッセージを出してfalseを返す
+pub fn add(repo: &Repository, paths: &[String], opts: &Options) -> io::Result<bool> {
+	repo.require_worktree()?;
+	let mut index = Index::load(repo)?;
+	let worktree = Worktree::new(repo);
+	let filters = Filters::load(repo)?;
+	let ignore = match opts.force {
+		true => None,
+		false => Some(Ignore::load(repo)?),
+	};
+
+	let mut ignored = Vec::new();
+	let mut streamed = Vec::new();
+	for path in paths {
+		let path = repo.relative_path(Path::new(path))?;
+		let meta = worktree.stat(&path)?;
+		let prefix = match &ignore {
+			Some(ignore) if !path.is_empty() && index.find(&path).is_none() => {
+				ignored_prefix(ignore, &path, meta.is_dir())
+			}
+			_ => None,
+		};
+		if let Some(prefix) = prefix {
+			ignored.push(String::from(prefix));
+			continue;
+		}
+		add_path(
+			&worktree,
+			&filters,
+			ignore.as_ref(),
+			&mut index,
+			&path,
+			opts,
+			&mut streamed,
+		)?;
+	}
+	write_streamed(repo, &mut index, streamed)?;
+
+	if !opts.dry_run {
+		index.save(repo)?;
+	}
+	if ignored.is_empty() {
+		return Ok(true);
+	}
+	eprintln!("The following paths are ignored by one of your .gitignore files:");
+	for path in ignored {
+		eprintln!("{}", path);
+	}
+	if Config::load(repo)?.get_bool("advice.addIgnoredFile") != Some(false) {
+		eprintln!("hint: Use -f if you really want to add them.");
+		eprintln!("hint: Turn this message off by running");
+		eprintln!("hint: \"git config advice.addIgnoredFile false\"");
+	}
+	Ok(false)
+}
+
+// pathか、その親のディレクトリのうちignoreされている最も浅いもの
+fn ignored_prefix<'a>(ignore: &Ignore, path: &'a str, is_dir: bool) -> Option<&'a str> {
+	path.match_indices('/')
+		.map(|(i, _)| (&path[..i], true))
+		.chain([(path, is_dir)])
+		.find(|(x, is_dir)| ignore.is_ignored(x, *is_dir))
+		.map(|(x, _)| x)
+}
+
+fn add_path(
+	worktree: &Worktree,
+	filters: &Filters,
+	ignore: Option<&Ignore>,
+	index: &mut Index,
+	path: &str,
+	opts: &Options,
+	streamed: &mut Vec<Streamed>,
+) -> io::Result<()> {
+	let meta = worktree.stat(path)?;
+
+	if meta.is_dir() {
+		let mut names = fs::read_dir(worktree.path(path))?
+			.map(|x| x.map(|x| x.file_name().to_string_lossy().into_owned()))
+			.collect::<io::Result<Vec<_>>>()?;
+		names.sort();
+
+		for name in names.iter().filter(|x| *x != ".git") {
+			let child = if path.is_empty() {
+				name.clone()
+			} else {
+				format!("{}/{}", path, name)
+			};
+			// indexにあるファイルや、それを含むディレクトリはignoreされない
+			if let Some(ignore) = ignore {
+				let is_dir = worktree.stat(&child)?.is_dir();
+				let prefix = format!("{}/", child);
+				let tracked = match is_dir {
+					true => index.has_under(&prefix),
+					false => index.find(&child).is_some(),
+				};
+				if !tracked && ignore.is_ignored(&child, is_dir) {
+					continue;
+				}
+			}
+			add_path(worktree, filters, ignore, index, &child, opts, streamed)?;
+		}
+		return Ok(());
+	}
+
+	// stat情報がindexと同じなら内容も同じとみなして読み直さない
+	let unchanged = index
+		.find(path)
+		.is_some_and(|x| x.stage() == 0 && x.matches_stat(&meta) && !index.is_racy(x));
+	if unchanged {
+		return Ok(());
+	}
+
+	// filterも改行の変換も無い通常のファイルは、後でまとめて書く
+	if meta.file_type().is_file() && !opts.dry_run && filters.passes_through(path) {
+		streamed.push(Streamed {
+			path: String::from(path),
+			file: worktree.path(path),
+			meta,
+		});
+		return Ok(());
+	}
+
+	// symlinkのリンク先はfilterを通さない
+	let mut content = worktree.read_file(path)?;
+	if !meta.file_type().is_symlink() {
+		content = filters.clean(path, content)?;
+	}
+
+	let blob = Blob::new(content);
+	if opts.dry_run {
+		// indexと内容もmodeも同じなら何もしないことになる
+		let hash = store::hash_bytes(worktree.repo, &blob.as_bytes());
+		let mode = index::mode_from(&meta);
+		if !index
+			.find(path)
+			.is_some_and(|x| x.hash == hash && x.mode == mode)
+		{
+			writeln!(io::stdout().lock(), "add '{}'", path)?;
+		}
+		return Ok(());
+	}
+	let hash = store::write_object(worktree.repo, &blob.as_bytes())?;
+
+	index.add(Entry::new(String::from(path), hash, &meta));
+	Ok(())
+}
+
+// 大きくてもmemoryに載せずに読みながら保存する。ファイルごとにthreadに分けて読み、hashし、圧縮する
+fn write_streamed(repo: &Repository, index: &mut Index, streamed: Vec<Streamed>) -> io::Result<()> {
+	let writer = store::LooseWriter::new(repo)?;
+	let threads = parallel::threads(&Config::load(repo)?);
+	let hashes = parallel::map(threads, &streamed, |x| {
+		let mut file = fs::File::open(&x.file)?;
+		writer.write_stream(ObjectKind::Blob, x.meta.len() as usize, &mut file)
+	})?;
+	for (x, hash) in streamed.into_iter().zip(hashes) {
+		index.add(Entry::new(x.path, hash, &x.meta));
+	}
+	Ok(())
+}
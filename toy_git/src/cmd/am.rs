@@ -0,0 +1,242 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::diff;
+use crate::hash::Hash;
+use crate::ident;
+use crate::index::Index;
+use crate::mail::{self, Mail};
+use crate::object::{ObjectKind, User};
+use crate::refs;
+use crate::repository::Repository;
+use crate::store;
+
+use super::checkout::switch_tree;
+use super::commit::write_commit;
+use super::{apply, write_tree, ApplyOptions};
+
+// mboxのpatchを順にindexとworking directoryに適用し、元のauthorとdateでcommitする
+// gitと同じく "<gitdir>/rebase-apply" に1通ずつのmail("0001" ...)と "next"、"last" を置いて進める
+// 適用できないpatchがあればそこで止まり、am --continueかam --abortを待つ
+pub fn am(repo: &Repository, mbox: &[u8]) -> io::Result<()> {
+	repo.require_worktree()?;
+	let dir = state_dir(repo);
+	if dir.exists() {
+		return Err(io::Error::other(format!(
+			"previous rebase directory {} still exists but mbox given.",
+			dir.display()
+		)));
+	}
+	let mails = mail::split(mbox);
+	if mails.is_empty() {
+		return Err(io::Error::other("Patch format detection failed."));
+	}
+	check_index(repo)?;
+
+	fs::create_dir_all(&dir)?;
+	for (i, mail) in mails.iter().enumerate() {
+		fs::write(dir.join(format!("{:04}", i + 1)), mail)?;
+	}
+	fs::write(dir.join("last"), format!("{}\n", mails.len()))?;
+	fs::write(dir.join("next"), "1\n")?;
+	fs::write(dir.join("applying"), "")?;
+
+	// abortで戻る先。unbornならORIG_HEADは消しておく
+	let head = refs::resolve(repo, "HEAD")?;
+	match &head {
+		Some(head) => refs::update_ref(repo, "ORIG_HEAD", head)?,
+		None => refs::delete_ref(repo, "ORIG_HEAD")?,
+	}
+	fs::write(
+		dir.join("abort-safety"),
+		format!("{}\n", head.unwrap_or_default()),
+	)?;
+	resume(repo, &dir)
+}
+
+// 止まったpatchの変更をindexに登録したものとしてcommitし、残りのpatchを続ける
+pub fn am_continue(repo: &Repository) -> io::Result<()> {
+	repo.require_worktree()?;
+	let dir = in_progress(repo)?;
+	let (next, _) = position(&dir)?;
+	let mail = read_mail(&dir, next)?;
+
+	let tree = hex::encode(write_tree(repo, None)?);
+	let head = refs::resolve(repo, "HEAD")?;
+	if head_tree(repo, head.as_deref())? == tree {
+		return Err(io::Error::other(
+			"No changes - did you forget to use 'git add'?\nIf there is nothing left to stage, chances are that something else\nalready introduced the same changes; you might want to skip this patch.",
+		));
+	}
+	commit(repo, &dir, &mail, &tree, head)?;
+	fs::write(dir.join("next"), format!("{}\n", next + 1))?;
+	resume(repo, &dir)
+}
+
+// amをやめて、indexとworking directoryとbranchをamを始める前のHEADに戻す
+// gitと同じく、amの後にHEADが動かされていればHEADはそのままにする
+pub fn am_abort(repo: &Repository) -> io::Result<()> {
+	repo.require_worktree()?;
+	let dir = in_progress(repo)?;
+
+	let safety = fs::read_to_string(dir.join("abort-safety"))?;
+	let head = refs::resolve(repo, "HEAD")?;
+	if head.as_deref().unwrap_or_default() != safety.trim() {
+		eprintln!(
+			"You seem to have moved HEAD since the last 'am' failure.\nNot rewinding to ORIG_HEAD"
+		);
+		return fs::remove_dir_all(dir);
+	}
+
+	let orig = refs::resolve(repo, "ORIG_HEAD")?;
+	let tree = match &orig {
+		Some(orig) => hex::decode(head_tree(repo, Some(orig))?).unwrap_or_default(),
+		// unbornなbranchで始めたときは空のtreeに戻す
+		None => store::write_object(repo, &ObjectKind::Tree.encode(b""))?,
+	};
+	let mut index = Index::load(repo)?;
+	switch_tree(repo, &mut index, &tree)?;
+	index.save(repo)?;
+
+	let target = refs::head_target(repo)?;
+	match orig {
+		Some(orig) => refs::update_ref(repo, &target, &orig)?,
+		None => refs::delete_ref(repo, &target)?,
+	}
+	fs::remove_dir_all(dir)
+}
+
+fn state_dir(repo: &Repository) -> PathBuf {
+	repo.gitdir.join("rebase-apply")
+}
+
+fn in_progress(repo: &Repository) -> io::Result<PathBuf> {
+	let dir = state_dir(repo);
+	match dir.join("applying").exists() {
+		true => Ok(dir),
+		false => Err(io::Error::other(
+			"Resolve operation not in progress, we are not resuming.",
+		)),
+	}
+}
+
+// (次に適用するpatchの番号, patchの数)
+fn position(dir: &Path) -> io::Result<(usize, usize)> {
+	let read = |name: &str| -> io::Result<usize> {
+		fs::read_to_string(dir.join(name))?
+			.trim()
+			.parse()
+			.map_err(|_| io::Error::other(format!("invalid {} in {}", name, dir.display())))
+	};
+	Ok((read("next")?, read("last")?))
+}
+
+fn read_mail(dir: &Path, n: usize) -> io::Result<Mail> {
+	let name = format!("{:04}", n);
+	Mail::from(&fs::read(dir.join(&name))?).map_err(|e| {
+		io::Error::other(format!(
+			"Patch {} does not have a valid From: header ({})",
+			name, e
+		))
+	})
+}
+
+// "next" から "last" までのpatchを適用してcommitする。全て終われば "rebase-apply" を消す
+fn resume(repo: &Repository, dir: &Path) -> io::Result<()> {
+	let opts = ApplyOptions {
+		check: false,
+		reverse: false,
+		cached: false,
+		index: true,
+	};
+	let mut out = io::stdout().lock();
+	loop {
+		let (next, last) = position(dir)?;
+		if next > last {
+			return fs::remove_dir_all(dir);
+		}
+		let mail = read_mail(dir, next)?;
+		writeln!(out, "Applying: {}", mail.subject)?;
+
+		// 止まったときに見られるように、適用するpatchを置いておく
+		fs::write(dir.join("patch"), &mail.patch)?;
+		let applied = match mail.patch.is_empty() {
+			true => Err(io::Error::other("Patch is empty.")),
+			false => apply(repo, &mail.patch, &opts),
+		};
+		if let Err(e) = applied {
+			eprintln!("error: {}", e);
+			return Err(io::Error::other(format!(
+				"Patch failed at {:04} {}\nWhen you have resolved this problem, run \"git am --continue\".\nTo restore the original branch and stop patching, run \"git am --abort\".",
+				next, mail.subject
+			)));
+		}
+
+		let tree = hex::encode(write_tree(repo, None)?);
+		commit(repo, dir, &mail, &tree, refs::resolve(repo, "HEAD")?)?;
+		fs::write(dir.join("next"), format!("{}\n", next + 1))?;
+	}
+}
+
+// mailのauthorとdateでcommitし、HEADのbranchを進める。abortしてよいHEADとして記録する
+fn commit(
+	repo: &Repository,
+	dir: &Path,
+	mail: &Mail,
+	tree: &str,
+	head: Option<String>,
+) -> io::Result<()> {
+	let date = match mail.date {
+		Some(date) => date,
+		None => ident::author(repo)?.ts,
+	};
+	let author = User::new(mail.name.clone(), mail.email.clone(), date);
+	let parents = head.into_iter().collect();
+	let hash = hex::encode(write_commit(
+		repo,
+		tree,
+		parents,
+		author,
+		&mail.message(),
+		None,
+	)?);
+	refs::update_ref(repo, &refs::head_target(repo)?, &hash)?;
+	fs::write(dir.join("abort-safety"), format!("{}\n", hash))
+}
+
+// commitのtree。commitが無ければ空のtree
+fn head_tree(repo: &Repository, commit: Option<&str>) -> io::Result<String> {
+	match commit {
+		Some(commit) => {
+			let hash = Hash::from_hex(commit)
+				.ok_or_else(|| io::Error::other(format!("bad object name {}", commit)))?;
+			Ok(store::read_commit(repo, &hash.0)?.tree)
+		}
+		None => Ok(hex::encode(store::hash_bytes(
+			repo,
+			&ObjectKind::Tree.encode(b""),
+		))),
+	}
+}
+
+// gitと同じく、indexがHEADと違えば始めない
+fn check_index(repo: &Repository) -> io::Result<()> {
+	let head = refs::resolve(repo, "HEAD")?;
+	let tree = hex::decode(head_tree(repo, head.as_deref())?).unwrap_or_default();
+	let head = match head {
+		Some(_) => diff::tree_entries(repo, &tree)?,
+		None => Default::default(),
+	};
+	let dirty: Vec<String> = diff::compare(&head, &diff::index_entries(&Index::load(repo)?))
+		.into_iter()
+		.map(|x| x.path)
+		.collect();
+	match dirty.is_empty() {
+		true => Ok(()),
+		false => Err(io::Error::other(format!(
+			"Dirty index: cannot apply patches (dirty: {})",
+			dirty.join(" ")
+		))),
+	}
+}
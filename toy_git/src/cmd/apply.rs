@@ -0,0 +1,458 @@
+use std::io;
+
+use super::checkout::write_content;
+use crate::diff;
+use crate::filter::Filters;
+use crate::index::{self, Entry, Index};
+use crate::object::ObjectKind;
+use crate::repository::Repository;
+use crate::store;
+use crate::worktree::Worktree;
+
+pub struct Options {
+	pub check: bool,   // 適用できるか確かめるだけで何も変えない
+	pub reverse: bool, // -R 新しい側から古い側へ戻す
+	pub cached: bool,  // working directoryではなくindexに適用する
+	pub index: bool,   // indexとworking directoryの両方に適用する(--index)
+}
+
+// 1ファイル分のpatch。Noneのpathは作成または削除を表す
+struct FilePatch {
+	old_path: Option<String>,
+	new_path: Option<String>,
+	old_mode: Option<u32>,
+	new_mode: Option<u32>,
+	hunks: Vec<Hunk>,
+	binary: bool,
+}
+
+struct Hunk {
+	old_start: usize,
+	new_start: usize,
+	lines: Vec<(u8, Vec<u8>)>, // b' ', b'-', b'+' と改行を含む内容
+}
+
+// 1ファイル分のpatchを適用した結果
+struct Applied {
+	remove: Option<String>,                // 消すpath
+	write: Option<(String, Vec<u8>, u32)>, // 書き込むpath, 内容, mode
+}
+
+// unified diff(diff --gitの形式とそれ以外の "---", "+++" で始まるもの)をworking directoryかindexに適用する
+// 全てのファイルについて、各hunkの消す行とcontextが一致する位置を確かめてから書き込むので、
+// 1つでも適用できなければ何も変えない
+// hunkは書かれた行番号から最も近い位置で一致するところに適用する
+pub fn apply(repo: &Repository, patch: &[u8], opts: &Options) -> io::Result<()> {
+	if !opts.cached || opts.index {
+		repo.require_worktree()?;
+	}
+
+	let mut patches = parse(patch)?;
+	if patches.is_empty() {
+		return Err(io::Error::other("No valid patches in input"));
+	}
+	if opts.reverse {
+		patches.iter_mut().for_each(reverse);
+	}
+
+	let mut index = Index::load(repo)?;
+	let worktree = Worktree::new(repo);
+	let filters = Filters::load(repo)?;
+	let target = Target {
+		repo,
+		index: &index,
+		worktree: &worktree,
+		filters: &filters,
+		cached: opts.cached || opts.index,
+	};
+
+	let mut results = Vec::new();
+	for patch in patches.iter() {
+		if opts.index {
+			target.check_worktree(patch)?;
+		}
+		results.push(target.prepare(patch)?);
+	}
+	if opts.check {
+		return Ok(());
+	}
+
+	for result in results {
+		if let Some(path) = result.remove {
+			if opts.cached || opts.index {
+				index.remove(&path);
+			}
+			if !opts.cached {
+				worktree.remove_file(&path)?;
+			}
+		}
+		if let Some((path, content, mode)) = result.write {
+			match (opts.cached, opts.index) {
+				(true, _) => {
+					let hash = store::write_object(repo, &ObjectKind::Blob.encode(&content))?;
+					index.add(Entry::unmerged(path, hash, mode, 0));
+				}
+				(false, true) => {
+					let hash = store::write_object(repo, &ObjectKind::Blob.encode(&content))?;
+					write_content(&worktree, &filters, &path, content, mode)?;
+					let meta = worktree.stat(&path)?;
+					index.add(Entry::new(path, hash, &meta));
+				}
+				(false, false) => write_content(&worktree, &filters, &path, content, mode)?,
+			}
+		}
+	}
+	if opts.cached || opts.index {
+		index.save(repo)?;
+	}
+	Ok(())
+}
+
+struct Target<'a> {
+	repo: &'a Repository,
+	index: &'a Index,
+	worktree: &'a Worktree<'a>,
+	filters: &'a Filters<'a>,
+	cached: bool,
+}
+
+impl Target<'_> {
+	fn place(&self) -> &'static str {
+		match self.cached {
+			true => "index",
+			false => "working directory",
+		}
+	}
+
+	// pathの内容とmode。無ければNone
+	fn read(&self, path: &str) -> io::Result<Option<(Vec<u8>, u32)>> {
+		if self.cached {
+			return match self.index.find(path).filter(|x| x.stage() == 0) {
+				Some(entry) => Ok(Some((
+					store::read_object(self.repo, &entry.hash)?.1,
+					entry.mode,
+				))),
+				None => Ok(None),
+			};
+		}
+
+		let meta = match self.worktree.stat(path) {
+			Ok(meta) => meta,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+			Err(e) => return Err(e),
+		};
+		let mode = index::mode_from(&meta);
+		let content = self.worktree.read_file(path)?;
+		let content = match mode {
+			0o120000 => content,
+			_ => self.filters.clean(path, content)?,
+		};
+		Ok(Some((content, mode)))
+	}
+
+	// --indexでは、変更するファイルのworking directoryの内容がindexと同じでなければならない
+	fn check_worktree(&self, patch: &FilePatch) -> io::Result<()> {
+		let Some(path) = &patch.old_path else {
+			return Ok(());
+		};
+		let worktree = Target {
+			cached: false,
+			..*self
+		};
+		let staged = self.read(path)?;
+		if staged.is_some() && staged != worktree.read(path)? {
+			return Err(io::Error::other(format!("{}: does not match index", path)));
+		}
+		Ok(())
+	}
+
+	fn prepare(&self, patch: &FilePatch) -> io::Result<Applied> {
+		let name = patch
+			.new_path
+			.as_ref()
+			.or(patch.old_path.as_ref())
+			.cloned()
+			.unwrap_or_default();
+		if patch.binary {
+			return Err(io::Error::other(format!(
+				"cannot apply binary patch to '{}' without full index line",
+				name
+			)));
+		}
+
+		let old = match &patch.old_path {
+			Some(path) => Some(self.read(path)?.ok_or_else(|| {
+				io::Error::other(format!("{}: does not exist in {}", path, self.place()))
+			})?),
+			None => None,
+		};
+		if let Some(path) = patch
+			.new_path
+			.as_ref()
+			.filter(|x| patch.old_path.as_ref() != Some(*x))
+		{
+			if self.read(path)?.is_some() {
+				return Err(io::Error::other(format!(
+					"{}: already exists in {}",
+					path,
+					self.place()
+				)));
+			}
+		}
+
+		let (content, mode) = old.unwrap_or((Vec::new(), 0o100644));
+		let content = apply_hunks(&content, &patch.hunks).map_err(|line| {
+			let path = patch.old_path.as_ref().unwrap_or(&name);
+			eprintln!("error: patch failed: {}:{}", path, line);
+			io::Error::other(format!("{}: patch does not apply", path))
+		})?;
+
+		let Some(new_path) = patch.new_path.clone() else {
+			if !content.is_empty() {
+				return Err(io::Error::other(format!(
+					"removal patch leaves file contents: {}",
+					name
+				)));
+			}
+			return Ok(Applied {
+				remove: patch.old_path.clone(),
+				write: None,
+			});
+		};
+		Ok(Applied {
+			remove: patch.old_path.clone().filter(|x| *x != new_path),
+			write: Some((
+				new_path,
+				content,
+				patch.new_mode.or(patch.old_mode).unwrap_or(mode),
+			)),
+		})
+	}
+}
+
+// hunkを順に適用する。適用できなければそのhunkの行番号を返す
+fn apply_hunks(content: &[u8], hunks: &[Hunk]) -> Result<Vec<u8>, usize> {
+	let lines = diff::lines(content);
+	let mut out = Vec::new();
+	let mut pos = 0; // まだ出力していない最初の行
+	let mut offset = 0isize; // 前のhunkが書かれた位置からずれていた行数
+
+	for hunk in hunks {
+		let pre: Vec<&[u8]> = hunk
+			.lines
+			.iter()
+			.filter(|(mark, _)| *mark != b'+')
+			.map(|(_, line)| line.as_slice())
+			.collect();
+		let post = hunk.lines.iter().filter(|(mark, _)| *mark != b'-');
+
+		// ファイルの先頭からのhunkは先頭に、後ろにcontextの無いhunkは末尾に一致しなければならない
+		let match_beginning = hunk.old_start <= 1;
+		let match_end = hunk.lines.last().is_some_and(|(mark, _)| *mark != b' ');
+
+		let written = match pre.is_empty() {
+			true => hunk.old_start,
+			false => hunk.old_start.saturating_sub(1),
+		};
+		let expected = (written as isize + offset).max(pos as isize) as usize;
+		let fits = |at: usize| {
+			at >= pos
+				&& at + pre.len() <= lines.len()
+				&& lines[at..at + pre.len()] == pre[..]
+				&& (!match_beginning || at == 0)
+				&& (!match_end || at + pre.len() == lines.len())
+		};
+		let at = (0..=lines.len())
+			.flat_map(|d| [expected + d, expected.wrapping_sub(d)])
+			.find(|&at| at <= lines.len() && fits(at))
+			.ok_or(hunk.old_start)?;
+
+		for line in &lines[pos..at] {
+			out.extend_from_slice(line);
+		}
+		for (_, line) in post {
+			out.extend_from_slice(line);
+		}
+		pos = at + pre.len();
+		offset = at as isize - written as isize;
+	}
+
+	for line in &lines[pos..] {
+		out.extend_from_slice(line);
+	}
+	Ok(out)
+}
+
+fn reverse(patch: &mut FilePatch) {
+	std::mem::swap(&mut patch.old_path, &mut patch.new_path);
+	std::mem::swap(&mut patch.old_mode, &mut patch.new_mode);
+	for hunk in patch.hunks.iter_mut() {
+		std::mem::swap(&mut hunk.old_start, &mut hunk.new_start);
+		for (mark, _) in hunk.lines.iter_mut() {
+			*mark = match *mark {
+				b'-' => b'+',
+				b'+' => b'-',
+				mark => mark,
+			};
+		}
+	}
+}
+
+fn parse(patch: &[u8]) -> io::Result<Vec<FilePatch>> {
+	let lines = diff::lines(patch);
+	let mut patches: Vec<FilePatch> = Vec::new();
+	let mut i = 0;
+
+	while i < lines.len() {
+		let line = text(lines[i]);
+		i += 1;
+
+		if let Some(names) = line.strip_prefix("diff --git ") {
+			let (old, new) = names.split_once(" b/").unwrap_or((names, names));
+			let old = old.strip_prefix("a/").unwrap_or(old);
+			patches.push(FilePatch {
+				old_path: Some(String::from(old)),
+				new_path: Some(String::from(new)),
+				old_mode: None,
+				new_mode: None,
+				hunks: Vec::new(),
+				binary: false,
+			});
+			continue;
+		}
+
+		// "diff --git" の無いpatchは "---" と "+++" から始まる
+		if line.starts_with("--- ") && lines.get(i).is_some_and(|x| x.starts_with(b"+++ ")) {
+			let old = name(&line[4..]);
+			let new = name(&text(lines[i])[4..]);
+			i += 1;
+			match patches
+				.last_mut()
+				.filter(|x| x.hunks.is_empty() && !x.binary)
+			{
+				Some(patch) => {
+					patch.old_path = old;
+					patch.new_path = new;
+				}
+				None => patches.push(FilePatch {
+					old_path: old,
+					new_path: new,
+					old_mode: None,
+					new_mode: None,
+					hunks: Vec::new(),
+					binary: false,
+				}),
+			}
+			continue;
+		}
+
+		let Some(patch) = patches.last_mut() else {
+			continue;
+		};
+		if line.starts_with("@@ ") {
+			let hunk = parse_hunk(&line, &lines, &mut i)?;
+			patch.hunks.push(hunk);
+		} else if let Some(mode) = line.strip_prefix("new file mode ") {
+			patch.old_path = None;
+			patch.new_mode = parse_mode(mode);
+		} else if let Some(mode) = line.strip_prefix("deleted file mode ") {
+			patch.new_path = None;
+			patch.old_mode = parse_mode(mode);
+		} else if let Some(mode) = line.strip_prefix("old mode ") {
+			patch.old_mode = parse_mode(mode);
+		} else if let Some(mode) = line.strip_prefix("new mode ") {
+			patch.new_mode = parse_mode(mode);
+		} else if let Some(path) = line.strip_prefix("rename from ") {
+			patch.old_path = Some(String::from(path));
+		} else if let Some(path) = line.strip_prefix("rename to ") {
+			patch.new_path = Some(String::from(path));
+		} else if line.starts_with("Binary files ") || line.starts_with("GIT binary patch") {
+			patch.binary = true;
+		}
+	}
+	Ok(patches)
+}
+
+// "@@ -l,s +l,s @@" と、その後の消す行と加える行の数だけの行
+fn parse_hunk(header: &str, lines: &[&[u8]], i: &mut usize) -> io::Result<Hunk> {
+	let corrupt = |line: usize| {
+		io::Error::new(
+			io::ErrorKind::InvalidData,
+			format!("corrupt patch at line {}", line),
+		)
+	};
+	let mut ranges = header[3..].split(' ');
+	let mut range = |prefix: char| -> Option<(usize, usize)> {
+		let range = ranges.next()?.strip_prefix(prefix)?;
+		match range.split_once(',') {
+			Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+			None => Some((range.parse().ok()?, 1)),
+		}
+	};
+	let (old_start, mut old_count) = range('-').ok_or_else(|| corrupt(*i))?;
+	let (new_start, mut new_count) = range('+').ok_or_else(|| corrupt(*i))?;
+
+	let mut hunk = Hunk {
+		old_start,
+		new_start,
+		lines: Vec::new(),
+	};
+	while old_count > 0 || new_count > 0 {
+		let line = lines.get(*i).ok_or_else(|| corrupt(*i + 1))?;
+		*i += 1;
+		// 空白を削られた空のcontext行も受け付ける
+		let (mark, content) = match line.split_first() {
+			Some((b'\n', _)) => (b' ', &line[..]),
+			Some((&mark, content)) => (mark, content),
+			None => return Err(corrupt(*i)),
+		};
+		match mark {
+			b'\\' => {
+				no_newline(&mut hunk);
+				continue;
+			}
+			b' ' if old_count > 0 && new_count > 0 => {
+				old_count -= 1;
+				new_count -= 1;
+			}
+			b'-' if old_count > 0 => old_count -= 1,
+			b'+' if new_count > 0 => new_count -= 1,
+			_ => return Err(corrupt(*i)),
+		}
+		hunk.lines.push((mark, content.to_vec()));
+	}
+
+	if lines.get(*i).is_some_and(|x| x.starts_with(b"\\")) {
+		*i += 1;
+		no_newline(&mut hunk);
+	}
+	Ok(hunk)
+}
+
+// "\ No newline at end of file" は直前の行に改行が無いことを表す
+fn no_newline(hunk: &mut Hunk) {
+	if let Some((_, last)) = hunk.lines.last_mut() {
+		if last.ends_with(b"\n") {
+			last.pop();
+		}
+	}
+}
+
+// "a/<path>" か "/dev/null"。日時などが後ろにタブで区切られていれば除く
+fn name(s: &str) -> Option<String> {
+	let s = s.split('\t').next().unwrap_or(s);
+	if s == "/dev/null" {
+		return None;
+	}
+	Some(String::from(s.split_once('/').map(|(_, x)| x).unwrap_or(s)))
+}
+
+fn parse_mode(mode: &str) -> Option<u32> {
+	u32::from_str_radix(mode.trim(), 8).ok()
+}
+
+// 行末の改行を除いた文字列
+fn text(line: &[u8]) -> String {
+	let line = line.strip_suffix(b"\n").unwrap_or(line);
+	String::from_utf8_lossy(line).into_owned()
+}
@@ -0,0 +1,265 @@
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+
+use super::checkout::{checkout, switch_tree, Options as CheckoutOptions};
+use crate::hash::Hash;
+use crate::index::Index;
+use crate::merge;
+use crate::object::ObjectKind;
+use crate::pretty::{self, Format};
+use crate::refs;
+use crate::repository::Repository;
+use crate::revparse;
+use crate::store;
+
+// 始めたときのbranch(detached HEADならcommit)。これがあればbisectの途中
+const START: &str = "BISECT_START";
+// 行った操作。gitと同じく "git bisect ..." の行と "# " で始まる説明の行
+const LOG: &str = "BISECT_LOG";
+
+// bisectを始める。revsがあれば最初をbad、残りをgoodにする
+// 途中で始め直したときは、始めたときのbranchを残して印を全て消す
+pub fn bisect_start(repo: &Repository, revs: &[String]) -> io::Result<()> {
+	repo.require_worktree()?;
+	let commits = revs
+		.iter()
+		.map(|x| commit(repo, x))
+		.collect::<io::Result<Vec<_>>>()?;
+
+	if !repo.gitdir.join(START).exists() {
+		let head = refs::head_target(repo)?;
+		let start = match head.strip_prefix("refs/heads/") {
+			Some(branch) => String::from(branch),
+			None => refs::resolve(repo, "HEAD")?
+				.ok_or_else(|| io::Error::other("bad HEAD - I need a HEAD"))?,
+		};
+		fs::write(repo.gitdir.join(START), format!("{}\n", start))?;
+	}
+	clear_marks(repo)?;
+
+	let args: String = revs.iter().map(|x| format!(" '{}'", x)).collect();
+	fs::write(repo.gitdir.join(LOG), format!("git bisect start{}\n", args))?;
+	if let Some((bad, goods)) = commits.split_first() {
+		mark(repo, "bad", bad)?;
+		for good in goods {
+			mark(repo, "good", good)?;
+		}
+	}
+	next(repo)
+}
+
+// revs(無ければHEAD)をgoodにして、次に試すcommitをcheckoutする
+pub fn bisect_good(repo: &Repository, revs: &[String]) -> io::Result<()> {
+	let revs = match revs {
+		[] => vec![String::from("HEAD")],
+		revs => revs.to_vec(),
+	};
+	for rev in revs {
+		record(repo, "good", &rev)?;
+	}
+	next(repo)
+}
+
+// rev(無ければHEAD)をbadにして、次に試すcommitをcheckoutする
+pub fn bisect_bad(repo: &Repository, rev: Option<&str>) -> io::Result<()> {
+	record(repo, "bad", rev.unwrap_or("HEAD"))?;
+	next(repo)
+}
+
+// 始めたときのbranchに戻り、bisectの状態を全て消す
+pub fn bisect_reset(repo: &Repository) -> io::Result<()> {
+	let start = match fs::read_to_string(repo.gitdir.join(START)) {
+		Ok(content) => String::from(content.trim_end()),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => {
+			writeln!(io::stdout().lock(), "We are not bisecting.")?;
+			return Ok(());
+		}
+		Err(e) => return Err(e),
+	};
+
+	checkout(
+		repo,
+		&start,
+		&CheckoutOptions {
+			dry_run: false,
+			new_branch: None,
+			reset: false,
+			branch_only: false,
+			force: false,
+		},
+	)?;
+	clear_marks(repo)?;
+	for name in [START, LOG] {
+		match fs::remove_file(repo.gitdir.join(name)) {
+			Ok(()) => {}
+			Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+			Err(e) => return Err(e),
+		}
+	}
+	Ok(())
+}
+
+fn commit(repo: &Repository, rev: &str) -> io::Result<Hash> {
+	let hash = revparse::resolve(repo, rev)?;
+	revparse::peel(repo, hash, Some(ObjectKind::Commit))
+}
+
+// bisect_startの後でなければError。gitと同じくコマンドの行もlogに残す
+fn record(repo: &Repository, term: &str, rev: &str) -> io::Result<()> {
+	if !repo.gitdir.join(START).exists() {
+		return Err(io::Error::other(
+			"You need to start by \"git bisect start\"",
+		));
+	}
+	let hash = commit(repo, rev)?;
+	mark(repo, term, &hash)?;
+	log(repo, &format!("git bisect {} {}", term, hash))
+}
+
+// badは1つだけで、goodは "good-<hash>" として幾つでも置ける
+fn mark(repo: &Repository, term: &str, hash: &Hash) -> io::Result<()> {
+	let name = match term {
+		"bad" => String::from("refs/bisect/bad"),
+		_ => format!("refs/bisect/{}-{}", term, hash),
+	};
+	refs::update_ref(repo, &name, &hash.to_hex())?;
+	log(repo, &format!("# {}: {}", term, describe(repo, hash)?))
+}
+
+fn clear_marks(repo: &Repository) -> io::Result<()> {
+	for (name, _) in refs::list(repo, "refs/bisect")? {
+		refs::delete_ref(repo, &name)?;
+	}
+	Ok(())
+}
+
+fn log(repo: &Repository, line: &str) -> io::Result<()> {
+	let mut file = OpenOptions::new()
+		.create(true)
+		.append(true)
+		.open(repo.gitdir.join(LOG))?;
+	writeln!(file, "{}", line)
+}
+
+// "[<hash>] <subject>"
+fn describe(repo: &Repository, hash: &Hash) -> io::Result<String> {
+	let commit = store::read_commit(repo, &hash.0)?;
+	let subject = commit.message.lines().next().unwrap_or_default();
+	Ok(format!("[{}] {}", hash, subject))
+}
+
+// badから辿れてgoodからは辿れないcommitのうち、そこから辿れる数が全体の半分に最も近いものをcheckoutする
+// 残りがbadだけになれば、それが最初のbad commit
+fn next(repo: &Repository) -> io::Result<()> {
+	let bad = refs::resolve(repo, "refs/bisect/bad")?;
+	let goods: Vec<String> = refs::list(repo, "refs/bisect")?
+		.into_iter()
+		.filter(|(name, _)| name.starts_with("refs/bisect/good-"))
+		.map(|(_, hash)| hash)
+		.collect();
+	let mut out = io::stdout().lock();
+	let bad = match (bad, goods.len()) {
+		(Some(bad), n) if n > 0 => bad,
+		(bad, n) => {
+			let status = match (bad, n) {
+				(None, 0) => String::from("waiting for both good and bad commits"),
+				(Some(_), _) => String::from("waiting for good commit(s), bad commit known"),
+				(None, 1) => String::from("waiting for bad commit, 1 good commit known"),
+				(None, n) => format!("waiting for bad commit, {} good commits known", n),
+			};
+			writeln!(out, "status: {}", status)?;
+			return log(repo, &format!("# status: {}", status));
+		}
+	};
+
+	let parse = |hex: &str| {
+		Hash::from_hex(hex).ok_or_else(|| io::Error::other(format!("bad object name {}", hex)))
+	};
+	let bad = parse(&bad)?;
+	let mut excluded = HashSet::new();
+	for good in goods.iter() {
+		excluded.extend(merge::ancestors(repo, &parse(good)?)?);
+	}
+	let candidates: HashSet<Hash> = merge::ancestors(repo, &bad)?
+		.into_iter()
+		.filter(|x| !excluded.contains(x))
+		.collect();
+	if candidates.is_empty() {
+		return Err(io::Error::other(format!(
+			"the bad commit {} is an ancestor of a good commit",
+			bad
+		)));
+	}
+	if candidates.len() == 1 {
+		let commit = store::read_commit(repo, &bad.0)?;
+		writeln!(out, "{} is the first bad commit", bad)?;
+		write!(
+			out,
+			"{}",
+			pretty::format(&Format::Medium, &bad.to_hex(), &commit, "")
+		)?;
+		return log(
+			repo,
+			&format!("# first bad commit: {}", describe(repo, &bad)?),
+		);
+	}
+
+	// gitと同じく、半分に近さが同じなら新しいcommitを選ぶ
+	let mut ordered = Vec::new();
+	for hash in candidates.iter() {
+		let date = store::read_commit(repo, &hash.0)?.committer.ts.timestamp();
+		ordered.push((Reverse(date), hash.clone()));
+	}
+	ordered.sort();
+	let all = candidates.len();
+	let mut best = None;
+	for (_, hash) in ordered {
+		let reaches = merge::ancestors(repo, &hash)?
+			.iter()
+			.filter(|x| candidates.contains(*x))
+			.count();
+		let distance = reaches.min(all - reaches);
+		if best.as_ref().is_none_or(|(x, _, _)| distance > *x) {
+			best = Some((distance, reaches, hash));
+		}
+	}
+	let Some((_, reaches, hash)) = best else {
+		return Ok(());
+	};
+
+	let tree = revparse::peel(repo, hash.clone(), Some(ObjectKind::Tree))?;
+	let mut index = Index::load(repo)?;
+	switch_tree(repo, &mut index, &tree.0)?;
+	index.save(repo)?;
+	refs::update_ref(repo, "HEAD", &hash.to_hex())?;
+
+	let left = all - reaches - 1;
+	let steps = steps(all);
+	writeln!(
+		out,
+		"Bisecting: {} revision{} left to test after this (roughly {} step{})",
+		left,
+		if left == 1 { "" } else { "s" },
+		steps,
+		if steps == 1 { "" } else { "s" }
+	)?;
+	writeln!(out, "{}", describe(repo, &hash)?)?;
+	Ok(())
+}
+
+// 残りを調べ終えるまでにかかるおおよその回数(gitのestimate_bisect_stepsと同じ)
+fn steps(all: usize) -> usize {
+	if all < 3 {
+		return 0;
+	}
+	let n = all.ilog2() as usize;
+	let e = 1 << n;
+	let x = all - e;
+	if e < 3 * x {
+		n
+	} else {
+		n - 1
+	}
+}
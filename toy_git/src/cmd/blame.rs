@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::Path;
+
+use chrono::{DateTime, FixedOffset, Local};
+
+use crate::blame;
+use crate::hash::Hash;
+use crate::repository::Repository;
+use crate::store;
+
+// 各行を "<hash> (<author> <date> <行番号>) <内容>" の形で出す
+// rootのcommitは "^" と7桁、まだcommitされていない行は "00000000" と "Not Committed Yet" になる
+// authorと行番号の幅は全ての行で一番長いものに揃える
+pub fn blame(repo: &Repository, rev: Option<&str>, path: &str) -> io::Result<()> {
+	// pathは今のディレクトリから
+	let path = match repo.bare {
+		true => path.to_string(),
+		false => repo.relative_path(Path::new(path))?,
+	};
+	let lines = blame::blame(repo, rev, &path)?;
+
+	// commitごとの (hashの表示, author, 日付)
+	let now = Local::now().fixed_offset();
+	let mut commits: HashMap<Option<Hash>, (String, String, DateTime<FixedOffset>)> =
+		HashMap::new();
+	for line in &lines {
+		if commits.contains_key(&line.commit) {
+			continue;
+		}
+		let shown = match &line.commit {
+			Some(hash) => {
+				let commit = store::read_commit(repo, &hash.0)?;
+				let hex = hash.to_string();
+				let id = match commit.parents.is_empty() {
+					true => format!("^{}", &hex[..7]),
+					false => hex[..8].to_string(),
+				};
+				(id, commit.author.name, commit.author.ts)
+			}
+			None => (
+				String::from("00000000"),
+				String::from("Not Committed Yet"),
+				now,
+			),
+		};
+		commits.insert(line.commit.clone(), shown);
+	}
+	let width = commits
+		.values()
+		.map(|(_, author, _)| author.chars().count())
+		.max()
+		.unwrap_or(0);
+	let number_width = lines.len().to_string().len();
+
+	let stdout = io::stdout();
+	let mut out = stdout.lock();
+	for (i, line) in lines.iter().enumerate() {
+		let (id, author, date) = &commits[&line.commit];
+		write!(
+			out,
+			"{} ({:<width$} {} {:>number_width$}) ",
+			id,
+			author,
+			date.format("%Y-%m-%d %H:%M:%S %z"),
+			i + 1
+		)?;
+		out.write_all(&line.content)?;
+		if !line.content.ends_with(b"\n") {
+			writeln!(out)?;
+		}
+	}
+	Ok(())
+}
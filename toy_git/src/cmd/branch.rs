@@ -0,0 +1,202 @@
+use std::io::{self, Write};
+
+use crate::config;
+use crate::hash::Hash;
+use crate::merge;
+use crate::object::ObjectKind;
+use crate::refs::{self, RefChange, RefUpdate};
+use crate::repository::Repository;
+use crate::revparse;
+use crate::upstream::Upstream;
+
+// local branchを名前の順に出す(branch [--list])。HEADが指しているものには "* " を付ける
+// detached HEADなら最初に "* (HEAD detached at <hash>)" を出す
+pub fn branch_list(repo: &Repository) -> io::Result<()> {
+	let head = refs::head_target(repo)?;
+	let stdout = io::stdout();
+	let mut out = stdout.lock();
+	if head == "HEAD" {
+		if let Some(hash) = refs::resolve(repo, "HEAD")? {
+			writeln!(out, "* (HEAD detached at {})", &hash[..7])?;
+		}
+	}
+	for (name, _) in refs::list(repo, "refs/heads")? {
+		let mark = if name == head { '*' } else { ' ' };
+		writeln!(out, "{} {}", mark, &name["refs/heads/".len()..])?;
+	}
+	Ok(())
+}
+
+// branchを消す(branch (-d | -D) <name>...)。reflogとupstreamの設定も消し、packed-refsにあれば書き直す
+// HEADが指しているbranchは消せない。forceでなければHEADから辿れないbranchも消さない
+// 消せないものがあればそれを飛ばして続け、最後にfalseを返す
+pub fn branch_delete(repo: &Repository, names: &[String], force: bool) -> io::Result<bool> {
+	let head = refs::head_target(repo)?;
+	let merged = match refs::resolve(repo, "HEAD")?.and_then(|x| Hash::from_hex(&x)) {
+		Some(hash) => merge::ancestors(repo, &hash)?,
+		None => Default::default(),
+	};
+
+	let mut ok = true;
+	let mut out = io::stdout().lock();
+	for name in names {
+		let refname = format!("refs/heads/{}", name);
+		let Some(hash) = refs::resolve(repo, &refname)? else {
+			eprintln!("error: branch '{}' not found.", name);
+			ok = false;
+			continue;
+		};
+		if refname == head {
+			eprintln!(
+				"error: Cannot delete branch '{}' checked out at '{}'",
+				name,
+				repo.workdir.display()
+			);
+			ok = false;
+			continue;
+		}
+		if !force && !Hash::from_hex(&hash).is_some_and(|x| merged.contains(&x)) {
+			eprintln!("error: The branch '{}' is not fully merged.", name);
+			eprintln!(
+				"If you are sure you want to delete it, run 'git branch -D {}'.",
+				name
+			);
+			ok = false;
+			continue;
+		}
+
+		let update = RefUpdate {
+			name: refname,
+			change: RefChange::Delete,
+			old: Some(Some(hash.clone())),
+		};
+		refs::transaction(repo, &[update], "")?;
+		let config = repo.commondir.join("config");
+		for key in ["remote", "merge"] {
+			config::unset_file(&config, &format!("branch.{}.{}", name, key))?;
+		}
+		writeln!(out, "Deleted branch {} (was {}).", name, &hash[..7])?;
+	}
+	Ok(ok)
+}
+
+// startのcommitに新しいbranchを作る(branch [-f] <name> [<start>])。startが無ければHEAD
+// forceなら既にあるbranchも置き換えるが、今のbranchは置き換えない
+pub fn branch_create(
+	repo: &Repository,
+	name: &str,
+	start: Option<&str>,
+	force: bool,
+) -> io::Result<()> {
+	let start = start.unwrap_or("HEAD");
+	let commit = revparse::peel(
+		repo,
+		revparse::resolve(repo, start)?,
+		Some(ObjectKind::Commit),
+	)?;
+	if check_new_branch(repo, name, force)?
+		&& refs::head_target(repo)? == format!("refs/heads/{}", name)
+	{
+		return Err(io::Error::other(format!(
+			"cannot force update the branch '{}' checked out at '{}'",
+			name,
+			repo.workdir.display()
+		)));
+	}
+	create_branch(repo, name, start, &commit)
+}
+
+// nameが新しいbranchの名前として使えるか確かめ、既にあるbranchならtrueを返す
+// 既にあるbranchは、forceでなければError
+pub(super) fn check_new_branch(repo: &Repository, name: &str, force: bool) -> io::Result<bool> {
+	let refname = format!("refs/heads/{}", name);
+	// gitと同じく、optionと紛らわしい "-" で始まる名前も使えない
+	if name == "HEAD" || name.starts_with('-') || refs::validate_ref_name(&refname).is_err() {
+		return Err(io::Error::other(format!(
+			"'{}' is not a valid branch name",
+			name
+		)));
+	}
+	let exists = refs::read_ref(repo, &refname)?.is_some();
+	if exists && !force {
+		return Err(io::Error::other(format!(
+			"a branch named '{}' already exists",
+			name
+		)));
+	}
+	Ok(exists)
+}
+
+// branchをcommitに向ける。gitのbranch.autoSetupMergeの既定と同じく、
+// startがremote-tracking branchならそれをupstreamにする
+pub(super) fn create_branch(
+	repo: &Repository,
+	name: &str,
+	start: &str,
+	commit: &Hash,
+) -> io::Result<()> {
+	refs::update_ref_logged(
+		repo,
+		&format!("refs/heads/{}", name),
+		&commit.to_hex(),
+		&format!("branch: Created from {}", start),
+	)?;
+
+	let Some(full) = revparse::dwim(repo, start)?.filter(|x| x.starts_with("refs/remotes/")) else {
+		return Ok(());
+	};
+	if let Some(upstream) = Upstream::from_ref(repo, &full)? {
+		upstream.save(repo, name)?;
+		writeln!(
+			io::stdout().lock(),
+			"branch '{}' set up to track '{}'.",
+			name,
+			refs::shorten(&full)
+		)?;
+	}
+	Ok(())
+}
+
+// branchのupstreamをupstreamにする(branch --set-upstream-to)。branchがNoneなら今のbranch
+// "origin/main" のようなremote-tracking branchならそのremoteを、local branchなら "." をremoteとして記録する
+pub fn branch_set_upstream(
+	repo: &Repository,
+	branch: Option<&str>,
+	upstream: &str,
+) -> io::Result<()> {
+	let branch = match branch {
+		Some(branch) => String::from(branch),
+		None => match refs::head_target(repo)?.strip_prefix("refs/heads/") {
+			Some(branch) => String::from(branch),
+			None => {
+				return Err(io::Error::other(format!(
+					"could not set upstream of HEAD to {} when it does not point to any branch.",
+					upstream
+				)))
+			}
+		},
+	};
+	if refs::read_ref(repo, &format!("refs/heads/{}", branch))?.is_none() {
+		return Err(io::Error::other(format!(
+			"branch '{}' does not exist",
+			branch
+		)));
+	}
+
+	let missing = || {
+		io::Error::other(format!(
+			"the requested upstream branch '{}' does not exist",
+			upstream
+		))
+	};
+	let name = revparse::dwim(repo, upstream)?.ok_or_else(missing)?;
+	let config = Upstream::from_ref(repo, &name)?.ok_or_else(missing)?;
+	config.save(repo, &branch)?;
+	writeln!(
+		io::stdout().lock(),
+		"branch '{}' set up to track '{}'.",
+		branch,
+		refs::shorten(&name)
+	)?;
+	Ok(())
+}
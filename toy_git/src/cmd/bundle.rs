@@ -0,0 +1,197 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::hash::Hash;
+use crate::object::{Commit, ObjectKind};
+use crate::pack;
+use crate::progress::Progress;
+use crate::reachable;
+use crate::refs;
+use crate::repository::Repository;
+use crate::revparse;
+use crate::store;
+
+// bundleは "# v2 git bundle" の行、prerequisiteとrefの一覧、空行、packの順に並ぶ
+const SIGNATURE: &str = "# v2 git bundle";
+
+// revsから辿れるobjectをpackにしてbundleとして書き出す
+// "^<rev>" や "<a>..<b>" で除いた履歴はprerequisiteとして記録する
+pub fn bundle_create(
+	repo: &Repository,
+	out: &Path,
+	revs: &[String],
+	progress: &mut dyn Progress,
+) -> io::Result<()> {
+	let mut tips: Vec<(String, Hash)> = Vec::new();
+	let mut include = Vec::new();
+	let mut exclude = Vec::new();
+
+	let mut positive = Vec::new();
+	for rev in revs {
+		if rev == "--all" {
+			positive.push(String::from("HEAD"));
+			positive.extend(refs::list(repo, "refs")?.into_iter().map(|(name, _)| name));
+		} else if let Some((a, b)) = rev.split_once("..") {
+			exclude.push(revparse::resolve(
+				repo,
+				if a.is_empty() { "HEAD" } else { a },
+			)?);
+			positive.push(String::from(if b.is_empty() { "HEAD" } else { b }));
+		} else if let Some(x) = rev.strip_prefix('^') {
+			exclude.push(revparse::resolve(repo, x)?);
+		} else {
+			positive.push(rev.clone());
+		}
+	}
+
+	// refの名前として書けるのはbranchやtagなどの名前で指定されたものだけ
+	for rev in positive {
+		let hash = revparse::resolve(repo, &rev)?;
+		if let Some(name) = revparse::dwim(repo, &rev)? {
+			if !tips.iter().any(|(x, _)| *x == name) {
+				tips.push((name, hash.clone()));
+			}
+		}
+		include.push(hash);
+	}
+	if tips.is_empty() {
+		return Err(io::Error::other("Refusing to create empty bundle."));
+	}
+
+	let hashes = reachable::objects(repo, &include, &exclude)?;
+	let excluded: HashSet<Hash> = reachable::objects(repo, &exclude, &[])?
+		.into_iter()
+		.collect();
+
+	let mut objects = Vec::with_capacity(hashes.len());
+	let mut prerequisites = Vec::new();
+	for hash in hashes {
+		let (kind, body) = store::read_original(repo, &hash.0)?;
+
+		// 含めるcommitのparentのうち除いたものがprerequisite
+		if kind == ObjectKind::Commit {
+			let parents = Commit::from(&body)
+				.map_err(|e| e.context(format!("bad commit object {}", hash)))?
+				.parents;
+			for parent in parents.iter().filter_map(|x| Hash::from_hex(x)) {
+				if excluded.contains(&parent) && !prerequisites.contains(&parent) {
+					prerequisites.push(parent);
+				}
+			}
+		}
+		objects.push((kind, body));
+	}
+
+	let mut header = format!("{}\n", SIGNATURE);
+	for hash in prerequisites {
+		let commit = store::read_commit(repo, &hash.0)?;
+		let subject = commit.message.lines().next().unwrap_or_default();
+		header.push_str(&format!("-{} {}\n", hash, subject));
+	}
+	for (name, hash) in tips {
+		header.push_str(&format!("{} {}\n", hash, name));
+	}
+	header.push('\n');
+
+	let mut data = header.into_bytes();
+	data.extend_from_slice(&pack::write(repo, &objects, progress)?);
+	fs::write(out, data)
+}
+
+// bundleの先頭部分
+struct Header {
+	prerequisites: Vec<String>,
+	refs: Vec<(String, String)>, // (hash, refname)
+}
+
+// headerとpackの部分に分ける
+fn parse(data: &[u8]) -> Option<(Header, &[u8])> {
+	let mut header = Header {
+		prerequisites: Vec::new(),
+		refs: Vec::new(),
+	};
+
+	let mut pos = 0;
+	let mut next = || {
+		let end = pos + data[pos..].iter().position(|&b| b == b'\n')?;
+		let line = std::str::from_utf8(&data[pos..end]).ok();
+		pos = end + 1;
+		line
+	};
+
+	// v3のcapability ("@object-format=sha1" など) は読み飛ばす
+	match next()? {
+		SIGNATURE | "# v3 git bundle" => {}
+		_ => return None,
+	}
+
+	loop {
+		let line = next()?;
+		if line.is_empty() {
+			break;
+		}
+		if line.starts_with('@') {
+			continue;
+		}
+		match line.strip_prefix('-') {
+			Some(x) => header
+				.prerequisites
+				.push(String::from(x.split(' ').next()?)),
+			None => {
+				let (hash, name) = line.split_once(' ')?;
+				header.refs.push((String::from(hash), String::from(name)));
+			}
+		}
+	}
+
+	Some((header, &data[pos..]))
+}
+
+// bundleのpackを取り込んでrefを更新する
+// prerequisiteのcommitが全て揃っていなければ何もしない
+pub fn bundle_unbundle(
+	repo: &Repository,
+	bundle: &Path,
+	progress: &mut dyn Progress,
+) -> io::Result<()> {
+	let data = fs::read(bundle)?;
+	let (header, pack) = parse(&data).ok_or_else(|| {
+		io::Error::new(
+			io::ErrorKind::InvalidData,
+			format!(
+				"'{}' does not look like a v2 or v3 bundle file",
+				bundle.display()
+			),
+		)
+	})?;
+
+	let mut missing = Vec::new();
+	for hash in header.prerequisites.iter() {
+		let bytes = hex::decode(hash)
+			.map_err(|_| io::Error::other(format!("bad prerequisite {}", hash)))?;
+		match store::read_original(repo, &bytes) {
+			Ok(_) => {}
+			Err(e) if e.kind() == io::ErrorKind::NotFound => missing.push(format!("-{}", hash)),
+			Err(e) => return Err(e),
+		}
+	}
+	if !missing.is_empty() {
+		return Err(io::Error::other(format!(
+			"Repository lacks these prerequisite commits:\n{}",
+			missing.join("\n")
+		)));
+	}
+
+	pack::index_pack(repo, pack, progress)?;
+
+	for (hash, name) in header.refs {
+		// HEADは取り込む側のものを残す
+		if name.starts_with("refs/") {
+			refs::update_ref(repo, &name, &hash)?;
+		}
+		writeln!(io::stdout().lock(), "{} {}", hash, name)?;
+	}
+	Ok(())
+}
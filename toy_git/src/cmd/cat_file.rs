@@ -0,0 +1,179 @@
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, Read, Write};
+
+use crate::filter::Filters;
+use crate::hash::Hash;
+use crate::object::{ObjectKind, Tree};
+use crate::repository::Repository;
+use crate::revparse;
+use crate::store;
+
+// cat-fileで1つのobjectについて出すもの
+pub enum Query {
+	Type,            // -t
+	Size,            // -s
+	Pretty,          // -p
+	Filters(String), // --filters --path=<path>
+}
+
+// objectがあるか(-e)。何も出力しない
+pub fn cat_file_exists(repo: &Repository, rev: &str) -> io::Result<bool> {
+	let hash = revparse::resolve(repo, rev)?;
+	store::has_object(repo, &hash.0)
+}
+
+// objectのtype、size、内容のいずれかを出力する。typeとsizeはheaderだけを読む
+// Filtersではblobの内容をpathのsmudge filterと改行の変換に通し、working directoryに書かれる内容を出す
+// allow_unknown_typeなら展開したheaderをそのまま信じ、知らないtypeのobjectも内容をそのまま出す
+pub fn cat_file(
+	repo: &Repository,
+	rev: &str,
+	query: Query,
+	allow_unknown_type: bool,
+) -> io::Result<()> {
+	let hash = revparse::resolve(repo, rev)?;
+	let header = || match allow_unknown_type {
+		true => store::read_raw_header(repo, &hash.0),
+		false => {
+			store::read_object_header(repo, &hash.0).map(|(kind, size)| (kind.to_string(), size))
+		}
+	};
+
+	let stdout = io::stdout();
+	let mut out = stdout.lock();
+	let (kind, body) = match query {
+		Query::Type => return writeln!(out, "{}", header()?.0),
+		Query::Size => return writeln!(out, "{}", header()?.1),
+		// tree以外は大きなblobでもmemoryに載せずに展開しながら出す
+		Query::Pretty if !allow_unknown_type => {
+			let (kind, _, mut reader) = store::open_object(repo, &hash.0)?;
+			if kind != ObjectKind::Tree {
+				io::copy(&mut reader, &mut out)?;
+				return Ok(());
+			}
+			let mut body = Vec::new();
+			reader.read_to_end(&mut body)?;
+			(kind.to_string(), body)
+		}
+		Query::Pretty => read(repo, &hash, allow_unknown_type)?,
+		Query::Filters(path) => {
+			let (kind, body) = store::read_object(repo, &hash.0)?;
+			let body = match kind {
+				ObjectKind::Blob => Filters::load(repo)?.smudge(&path, body)?,
+				_ => body,
+			};
+			return out.write_all(&body);
+		}
+	};
+
+	match ObjectKind::from(&kind) {
+		Some(ObjectKind::Tree) => {
+			let tree = Tree::from(&body, repo.hash_algo)
+				.map_err(|e| e.context(format!("bad tree {}", hash)))?;
+			for file in tree.contents.iter() {
+				let kind = file.kind();
+				let hex = hex::encode(&file.hash);
+				writeln!(out, "{:06} {} {}\t{}", file.mode, kind, hex, file.name)?;
+			}
+			Ok(())
+		}
+		_ => out.write_all(&body),
+	}
+}
+
+// objectのtypeと内容
+fn read(repo: &Repository, hash: &Hash, allow_unknown_type: bool) -> io::Result<(String, Vec<u8>)> {
+	match allow_unknown_type {
+		true => {
+			let raw = store::read_raw(repo, &hash.0)?;
+			let nul = raw.iter().position(|&b| b == b'\0').ok_or_else(|| {
+				io::Error::new(
+					io::ErrorKind::InvalidData,
+					format!("object {} has no header", hash),
+				)
+			})?;
+			let header = String::from_utf8_lossy(&raw[..nul]);
+			let kind = header.split(' ').next().unwrap_or_default().to_string();
+			Ok((kind, raw[nul + 1..].to_vec()))
+		}
+		false => {
+			let (kind, body) = store::read_object(repo, &hash.0)?;
+			Ok((kind.to_string(), body))
+		}
+	}
+}
+
+// inputから1行に1つずつobjectの名前を読み、"<hash> <type> <size>" を出力する
+// contentがtrue(--batch)の場合はその後に内容も出力し、false(--batch-check)の場合はheaderだけ
+pub fn cat_file_batch<R: BufRead>(repo: &Repository, input: R, content: bool) -> io::Result<()> {
+	let stdout = io::stdout();
+	let mut out = stdout.lock();
+
+	for line in input.lines() {
+		let line = line?;
+		let name = line.trim();
+		if name.is_empty() {
+			continue;
+		}
+
+		// --batch-checkでは内容を展開しない
+		let object = revparse::resolve(repo, name).and_then(|hash| match content {
+			true => {
+				let (kind, body) = store::read_object(repo, &hash.0)?;
+				Ok((hash, kind, body.len(), Some(body)))
+			}
+			false => {
+				let (kind, size) = store::read_object_header(repo, &hash.0)?;
+				Ok((hash, kind, size, None))
+			}
+		});
+
+		match object {
+			Ok((hash, kind, size, body)) => write_batch(&mut out, &hash, kind, size, body)?,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => writeln!(out, "{} missing", name)?,
+			Err(e) => return Err(e),
+		}
+		// 対話的に使えるように1つずつflushする
+		out.flush()?;
+	}
+	Ok(())
+}
+
+// stdinから読む代わりに、repositoryにある全てのobjectをhash順に1度ずつcat_file_batchと同じ形で出力する
+// 置き換え(refs/replace/)はせず、objectは1つずつ読む
+pub fn cat_file_batch_all(repo: &Repository, content: bool) -> io::Result<()> {
+	let hashes: BTreeSet<Vec<u8>> = store::objects_iter(repo)?.collect();
+
+	let mut out = io::BufWriter::new(io::stdout().lock());
+	for hash in hashes {
+		let hash = Hash(hash);
+		match content {
+			true => {
+				let (kind, body) = store::read_original(repo, &hash.0)?;
+				let size = body.len();
+				write_batch(&mut out, &hash, kind, size, Some(body))?;
+			}
+			false => {
+				let (kind, size) = store::read_original_header(repo, &hash.0)?;
+				write_batch(&mut out, &hash, kind, size, None)?;
+			}
+		}
+	}
+	out.flush()
+}
+
+// "<hash> <type> <size>" の行と、bodyがあればその内容と改行
+fn write_batch<W: Write>(
+	out: &mut W,
+	hash: &Hash,
+	kind: ObjectKind,
+	size: usize,
+	body: Option<Vec<u8>>,
+) -> io::Result<()> {
+	writeln!(out, "{} {} {}", hash, kind, size)?;
+	if let Some(body) = body {
+		out.write_all(&body)?;
+		writeln!(out)?;
+	}
+	Ok(())
+}
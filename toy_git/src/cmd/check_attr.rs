@@ -0,0 +1,30 @@
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::attributes::{Attributes, Value};
+use crate::repository::Repository;
+
+// pathごとに各attrの値を "<path>: <attr>: <value>" の形で出力する
+// valueは "set", "unset", "unspecified" か、指定された文字列
+pub fn check_attr(repo: &Repository, attrs: &[String], paths: &[String]) -> io::Result<()> {
+	let attributes = Attributes::load(repo)?;
+
+	let mut out = io::stdout().lock();
+	for path in paths {
+		// bare repositoryではpathをそのままrepository内のpathとして扱う
+		let relative = match repo.bare {
+			true => path.clone(),
+			false => repo.relative_path(Path::new(path))?,
+		};
+		for attr in attrs {
+			let value = match attributes.get(&relative, attr) {
+				Value::Set => String::from("set"),
+				Value::Unset => String::from("unset"),
+				Value::Unspecified => String::from("unspecified"),
+				Value::Text(x) => x,
+			};
+			writeln!(out, "{}: {}: {}", path, attr, value)?;
+		}
+	}
+	Ok(())
+}
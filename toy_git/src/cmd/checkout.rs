@@ -0,0 +1,331 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use crate::diff::{self, Side};
+use crate::filter::Filters;
+use crate::hash::Hash;
+use crate::index::{Entry, Index};
+use crate::object::{Blob, ObjectKind};
+use crate::refs;
+use crate::repository::Repository;
+use crate::revparse;
+use crate::sparse::Sparse;
+use crate::store;
+use crate::upstream;
+use crate::worktree::Worktree;
+
+use super::branch::{check_new_branch, create_branch};
+use super::reset::reset_tree;
+
+pub struct Options {
+	pub dry_run: bool, // 切り替えたときの表示をするだけで、index、working directory、HEADを変えない
+	pub new_branch: Option<String>, // targetのcommitにこのbranchを作ってから切り替える(-b)
+	pub reset: bool,   // new_branchが既にあれば置き換える(-B)
+	pub branch_only: bool, // switchと同じく、branch以外ならdetached HEADにせずにError
+	pub force: bool,   // commitしていない変更やuntrackedなファイルがあっても上書きする
+}
+
+// branchかcommitに切り替えてindexとworking directoryをそのtreeに合わせる
+// branch名ならHEADをそのbranchに向け、それ以外はdetached HEADにする
+// new_branchがあれば、targetのcommitにそのbranchを作ってそこに切り替える
+// upstreamのあるbranchなら、gitと同じくupstreamとの差を表示する
+// forceでなければ、HEADとtargetで違うpathだけを書き換え、他のpathのcommitしていない変更は残して表示する
+// 書き換えるpathに変更があるかuntrackedなファイルがあればError
+pub fn checkout(repo: &Repository, target: &str, opts: &Options) -> io::Result<()> {
+	repo.require_worktree()?;
+	let (name, is_branch) = match &opts.new_branch {
+		Some(name) => (name.as_str(), true),
+		None => (
+			target,
+			refs::read_ref(repo, &format!("refs/heads/{}", target))?.is_some(),
+		),
+	};
+	let branch = format!("refs/heads/{}", name);
+
+	let commit = match (&opts.new_branch, is_branch) {
+		(None, true) => revparse::resolve(repo, &branch)?,
+		_ => revparse::resolve(repo, target).map_err(|e| match opts.branch_only {
+			true => io::Error::other(format!("invalid reference: {}", target)),
+			false => e,
+		})?,
+	};
+	if opts.branch_only && !is_branch {
+		let kind = match revparse::dwim(repo, target)? {
+			Some(x) if x.starts_with("refs/tags/") => "tag",
+			Some(x) if x.starts_with("refs/remotes/") => "remote branch",
+			_ => "commit",
+		};
+		return Err(io::Error::other(format!(
+			"a branch is expected, got {} '{}'\nhint: If you want to detach HEAD at the commit, try again with the --detach option.",
+			kind, target
+		)));
+	}
+	let commit = revparse::peel(repo, commit, Some(ObjectKind::Commit))?;
+	let tree = revparse::peel(repo, commit.clone(), Some(ObjectKind::Tree))?;
+	let existed = match &opts.new_branch {
+		Some(name) => check_new_branch(repo, name, opts.reset)?,
+		None => false,
+	};
+
+	let mut index = Index::load(repo)?;
+	let mut out = io::stdout().lock();
+	if opts.force {
+		if !opts.dry_run {
+			reset_tree(repo, &mut index, &tree.0)?;
+			index.save(repo)?;
+		}
+	} else {
+		let entries = carry_changes(repo, &index, &tree.0)?;
+		if !opts.dry_run {
+			switch_entries(repo, &mut index, &entries)?;
+			index.save(repo)?;
+			// 残した変更。gitと同じく、-bでHEADにbranchを作るだけのときは出さない
+			let head = refs::resolve(repo, "HEAD")?;
+			if opts.new_branch.is_none() || head.as_deref() != Some(commit.to_hex().as_str()) {
+				let new = diff::tree_entries(repo, &tree.0)?;
+				for change in diff::compare(&new, &diff::worktree_entries(repo, &index)?) {
+					writeln!(out, "{}\t{}", change.status(), change.path)?;
+				}
+			}
+		}
+	}
+
+	if is_branch {
+		let current = refs::head_target(repo)? == branch;
+		if !opts.dry_run {
+			if opts.new_branch.is_some() {
+				create_branch(repo, name, target, &commit)?;
+			}
+			move_head(repo, &format!("ref: {}", branch), &commit, name)?;
+		}
+		match (&opts.new_branch, existed) {
+			(None, _) => writeln!(out, "Switched to branch '{}'", name)?,
+			(Some(_), false) => writeln!(out, "Switched to a new branch '{}'", name)?,
+			(Some(_), true) if current => writeln!(out, "Reset branch '{}'", name)?,
+			(Some(_), true) => writeln!(out, "Switched to and reset branch '{}'", name)?,
+		}
+		// gitと同じく、新しく作ったbranchではupstreamとの差を表示しない
+		if opts.new_branch.is_none() || existed {
+			if let Some(info) = upstream::tracking_info(repo, name)? {
+				writeln!(out, "{}", info)?;
+			}
+		}
+	} else {
+		if !opts.dry_run {
+			move_head(repo, &commit.to_hex(), &commit, target)?;
+		}
+		writeln!(out, "HEAD is now at {}", &commit.to_hex()[..7])?;
+	}
+	Ok(())
+}
+
+// HEADをvalue("ref: <branch>" かcommitのhash)にし、gitと同じく
+// "checkout: moving from <前のbranchかcommit> to <to>" をHEADのreflogに記録する
+fn move_head(repo: &Repository, value: &str, commit: &Hash, to: &str) -> io::Result<()> {
+	let old = refs::resolve(repo, "HEAD")?;
+	let from = match refs::head_target(repo)?.strip_prefix("refs/heads/") {
+		Some(branch) => String::from(branch),
+		None => old.clone().unwrap_or_default(),
+	};
+	refs::update_ref(repo, "HEAD", value)?;
+	refs::append_reflog(
+		repo,
+		"HEAD",
+		&old.unwrap_or_else(|| repo.hash_algo.null_hex()),
+		&commit.to_hex(),
+		&format!("checkout: moving from {} to {}", from, to),
+	)
+}
+
+// indexとworking directoryをtreeの内容に合わせる。変わったpathだけを書き換える
+// conflictしているpathはtreeの内容で置き換える
+// sparse checkoutでは対象外のpathをskip-worktreeにしてworking directoryに置かない
+pub(super) fn switch_tree(repo: &Repository, index: &mut Index, tree: &[u8]) -> io::Result<()> {
+	switch_entries(repo, index, &diff::tree_entries(repo, tree)?)
+}
+
+// indexとworking directoryをentriesに合わせる
+fn switch_entries(
+	repo: &Repository,
+	index: &mut Index,
+	new: &BTreeMap<String, Side>,
+) -> io::Result<()> {
+	let worktree = Worktree::new(repo);
+	let filters = Filters::load(repo)?;
+	let sparse = Sparse::load(repo)?;
+
+	let mut old = diff::index_entries(index);
+	for path in index.unmerged() {
+		let side = Side {
+			mode: 0,
+			hash: Vec::new(),
+			worktree: false,
+		};
+		old.insert(String::from(path), side);
+	}
+
+	for change in diff::compare(&old, new) {
+		match change.new {
+			Some(side) if sparse.as_ref().is_some_and(|x| !x.includes(&change.path)) => {
+				index.add(skip_entry(change.path.clone(), side));
+			}
+			Some(side) => checkout_entry(&worktree, &filters, index, &change.path, side)?,
+			None => {
+				worktree.remove_file(&change.path)?;
+				index.remove(&change.path);
+			}
+		}
+	}
+
+	match sparse {
+		Some(sparse) => apply_sparse(&worktree, &filters, index, &sparse),
+		None => Ok(()),
+	}
+}
+
+// HEADのtreeからtreeへ切り替えた後のindexの内容。HEADとtreeで違うpathだけをtreeの内容にする
+// そのpathのindexかworking directoryに変更があるか、untrackedなファイルがあればError
+fn carry_changes(
+	repo: &Repository,
+	index: &Index,
+	tree: &[u8],
+) -> io::Result<BTreeMap<String, Side>> {
+	let unmerged = index.unmerged();
+	if !unmerged.is_empty() {
+		let list: String = unmerged
+			.iter()
+			.map(|x| format!("{}: needs merge\n", x))
+			.collect();
+		return Err(io::Error::other(format!(
+			"{}you need to resolve your current index first",
+			list
+		)));
+	}
+
+	let head = match refs::resolve(repo, "HEAD")? {
+		Some(hex) => {
+			let commit = store::read_commit(repo, &hex::decode(hex).unwrap_or_default())?;
+			diff::tree_entries(repo, &hex::decode(&commit.tree).unwrap_or_default())?
+		}
+		None => BTreeMap::new(),
+	};
+	let new = diff::tree_entries(repo, tree)?;
+	let staged = diff::index_entries(index);
+	let worktree = diff::worktree_entries(repo, index)?;
+	let same = |a: Option<&Side>, b: Option<&Side>| match (a, b) {
+		(Some(a), Some(b)) => a.mode == b.mode && a.hash == b.hash,
+		(a, b) => a.is_none() && b.is_none(),
+	};
+
+	let mut entries = staged.clone();
+	let mut dirty = Vec::new();
+	let mut untracked = Vec::new();
+	for change in diff::compare(&head, &new) {
+		let path = &change.path;
+		let (old, new) = (head.get(path), new.get(path));
+		let current = staged.get(path);
+		if same(current, new) {
+			continue;
+		}
+		// 消えているファイルは上書きしてよい
+		if !same(current, old) || worktree.get(path).is_some_and(|x| !same(current, Some(x))) {
+			dirty.push(path.clone());
+		} else if current.is_none() && Worktree::new(repo).stat(path).is_ok_and(|x| !x.is_dir()) {
+			untracked.push(path.clone());
+		}
+		match new {
+			Some(side) => entries.insert(path.clone(), side.clone()),
+			None => entries.remove(path),
+		};
+	}
+
+	let list =
+		|paths: &[String]| -> String { paths.iter().map(|x| format!("\t{}\n", x)).collect() };
+	if !dirty.is_empty() {
+		return Err(io::Error::other(format!(
+			"Your local changes to the following files would be overwritten by checkout:\n{}Please commit your changes or stash them before you switch branches.\nAborting",
+			list(&dirty)
+		)));
+	}
+	if !untracked.is_empty() {
+		return Err(io::Error::other(format!(
+			"The following untracked working tree files would be overwritten by checkout:\n{}Please move or remove them before you switch branches.\nAborting",
+			list(&untracked)
+		)));
+	}
+	Ok(entries)
+}
+
+// 変わっていないpathもsparse-checkoutの指定に合わせる
+// 対象外になったファイルは消してskip-worktreeにし、対象になったファイルは書き出す
+fn apply_sparse(
+	worktree: &Worktree,
+	filters: &Filters,
+	index: &mut Index,
+	sparse: &Sparse,
+) -> io::Result<()> {
+	let entries: Vec<(String, Side, bool)> = index
+		.entries
+		.iter()
+		.filter(|x| x.stage() == 0)
+		.map(|x| {
+			let side = Side {
+				mode: x.mode,
+				hash: x.hash.clone(),
+				worktree: false,
+			};
+			(x.path.clone(), side, x.skip_worktree())
+		})
+		.collect();
+
+	for (path, side, skip) in entries {
+		match (sparse.includes(&path), skip) {
+			(true, true) => checkout_entry(worktree, filters, index, &path, side)?,
+			(false, false) => {
+				worktree.remove_file(&path)?;
+				index.add(skip_entry(path, side));
+			}
+			_ => {}
+		}
+	}
+	Ok(())
+}
+
+// working directoryにファイルを置かないエントリ
+fn skip_entry(path: String, side: Side) -> Entry {
+	let mut entry = Entry::unmerged(path, side.hash, side.mode, 0);
+	entry.set_skip_worktree(true);
+	entry
+}
+
+// objectの内容をworking directoryに書き出してindexに登録する
+pub(super) fn checkout_entry(
+	worktree: &Worktree,
+	filters: &Filters,
+	index: &mut Index,
+	path: &str,
+	side: Side,
+) -> io::Result<()> {
+	let content = store::read_object(worktree.repo, &side.hash)?.1;
+	write_content(worktree, filters, path, content, side.mode)?;
+
+	let meta = worktree.stat(path)?;
+	index.add(Entry::new(String::from(path), side.hash, &meta));
+	Ok(())
+}
+
+// smudgeしてからworking directoryに書き出す
+pub(super) fn write_content(
+	worktree: &Worktree,
+	filters: &Filters,
+	path: &str,
+	content: Vec<u8>,
+	mode: u32,
+) -> io::Result<()> {
+	// symlinkのリンク先はfilterを通さない
+	let content = match mode {
+		0o120000 => content,
+		_ => filters.smudge(path, content)?,
+	};
+	worktree.write_file(path, &Blob::new(content), mode)
+}
@@ -0,0 +1,92 @@
+use std::collections::HashSet;
+use std::io::{self, Write};
+
+use crate::diff;
+use crate::hash::Hash;
+use crate::object::{Commit, ObjectKind};
+use crate::pretty;
+use crate::refs;
+use crate::repository::Repository;
+use crate::revparse;
+use crate::store;
+use crate::upstream::Upstream;
+
+use super::rev_list::{walk, Walked};
+
+// headにあってupstreamに無いcommitを古い順に "+ <hash>" の形で出す
+// 同じ変更(patch-id)のcommitがupstreamにあれば "-" を付ける。mergeは出さない
+// upstreamが無ければ今のbranchのupstream、limitがあればそこから辿れるcommitも出さない
+// verboseならhashの後にsubjectも出す
+pub fn cherry(
+	repo: &Repository,
+	upstream: Option<&str>,
+	head: &str,
+	limit: Option<&str>,
+	verbose: bool,
+) -> io::Result<()> {
+	let upstream = match upstream {
+		Some(x) => String::from(x),
+		None => default_upstream(repo)?.ok_or_else(|| {
+			io::Error::other(
+				"could not find a tracked remote branch, please specify <upstream> manually",
+			)
+		})?,
+	};
+	let commit = |rev: &str| {
+		let hash = revparse::resolve(repo, rev)?;
+		revparse::peel(repo, hash, Some(ObjectKind::Commit))
+	};
+	let (upstream, head) = (commit(&upstream)?, commit(head)?);
+	let mut excluded = vec![upstream.clone()];
+	if let Some(limit) = limit {
+		excluded.push(commit(limit)?);
+	}
+
+	let mut theirs = HashSet::new();
+	for (_, commit) in commits(repo, &upstream, std::slice::from_ref(&head))? {
+		theirs.insert(diff::patch_id(repo, &commit)?);
+	}
+
+	let mut out = io::BufWriter::new(io::stdout().lock());
+	for (hash, commit) in commits(repo, &head, &excluded)?.into_iter().rev() {
+		let mark = match theirs.contains(&diff::patch_id(repo, &commit)?) {
+			true => '-',
+			false => '+',
+		};
+		match verbose {
+			true => writeln!(
+				out,
+				"{} {} {}",
+				mark,
+				hash,
+				pretty::subject(&commit.message)
+			)?,
+			false => writeln!(out, "{} {}", mark, hash)?,
+		}
+	}
+	out.flush()
+}
+
+// tipから辿れてexcludedから辿れないmerge以外のcommitを新しい順に
+fn commits(repo: &Repository, tip: &Hash, excluded: &[Hash]) -> io::Result<Vec<(String, Commit)>> {
+	let Walked { commits, .. } = walk(repo, std::slice::from_ref(tip), excluded)?;
+	let mut found = Vec::new();
+	for (hash, parents, _) in commits {
+		if parents.len() > 1 {
+			continue;
+		}
+		let bad = || io::Error::other(format!("bad commit {}", hash));
+		let commit = store::read_commit(repo, &Hash::from_hex(&hash).ok_or_else(bad)?.0)?;
+		found.push((hash, commit));
+	}
+	Ok(found)
+}
+
+// 今のbranchのupstreamのremote-tracking ref
+fn default_upstream(repo: &Repository) -> io::Result<Option<String>> {
+	let target = refs::head_target(repo)?;
+	let Some(branch) = target.strip_prefix("refs/heads/") else {
+		return Ok(None);
+	};
+	Ok(Upstream::load(repo, branch)?.map(|x| x.tracking_ref()))
+}
@@ -0,0 +1,61 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::config::Config;
+use crate::ignore::Ignore;
+use crate::index::Index;
+use crate::repository::Repository;
+
+use super::status::untracked;
+
+pub struct Options {
+	pub dirs: bool,    // -d untrackedなディレクトリもまとめて消す
+	pub force: bool,   // -f clean.requireForceがtrueでも消す
+	pub dry_run: bool, // -n 消すものを表示するだけ
+	pub ignored: bool, // -x ignoreされたファイルも消す
+}
+
+// indexに無いファイルを消す。dirsでなければuntrackedなディレクトリの中は触らない
+// ignoredでなければignoreされたファイルは残し、それを含むディレクトリは中のファイルを1つずつ消す
+// gitと同じく、forceかdry_runでなければclean.requireForceがfalseのときしか消さない
+// 別のrepositoryのディレクトリは消さない
+pub fn clean(repo: &Repository, opts: &Options) -> io::Result<()> {
+	repo.require_worktree()?;
+	let require_force = Config::load(repo)?
+		.get_bool("clean.requireforce")
+		.unwrap_or(true);
+	if require_force && !opts.force && !opts.dry_run {
+		return Err(io::Error::other(
+			"clean.requireForce defaults to true and neither -i, -n, nor -f given; refusing to clean",
+		));
+	}
+
+	let index = Index::load(repo)?;
+	let ignore = match opts.ignored {
+		true => None,
+		false => Some(Ignore::load(repo)?),
+	};
+	let mut out = io::stdout().lock();
+	for path in untracked(repo, &index, ignore.as_ref(), opts.dirs)? {
+		let full = repo.workdir.join(&path);
+		if path.ends_with('/') && (!opts.dirs || is_repository(&full.join(".git"))) {
+			continue;
+		}
+		if opts.dry_run {
+			writeln!(out, "Would remove {}", path)?;
+			continue;
+		}
+		writeln!(out, "Removing {}", path)?;
+		match path.ends_with('/') {
+			true => fs::remove_dir_all(&full)?,
+			false => fs::remove_file(&full)?,
+		}
+	}
+	Ok(())
+}
+
+// ".git" がfileか、HEADのあるディレクトリならrepository
+fn is_repository(git: &Path) -> bool {
+	git.is_file() || git.join("HEAD").is_file()
+}
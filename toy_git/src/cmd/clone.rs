@@ -0,0 +1,216 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::config;
+use crate::hash::Hash;
+use crate::index::Index;
+use crate::progress::Progress;
+use crate::refs::{self, RefChange, RefUpdate};
+use crate::repository::Repository;
+use crate::store;
+use crate::transport;
+use crate::upstream::Upstream;
+
+use super::checkout::switch_tree;
+use super::init::{init, Options as InitOptions};
+
+// urlのrepositoryをdir(無ければurlの最後の部分から決める)に複製し、remoteを "origin" として記録する
+// remoteのbranchは refs/remotes/origin/ に、tagは refs/tags/ に置き、
+// branch(無ければremoteのHEADが指すもの)をlocal branchにしてcheckoutする
+// bareなら、remoteのbranchをそのまま refs/heads/ に置き、HEADはそのbranchを指すだけでcheckoutしない
+// 途中で失敗したら作ったディレクトリを消す
+pub fn clone(
+	url: &str,
+	dir: Option<&Path>,
+	branch: Option<&str>,
+	bare: bool,
+	progress: &mut dyn Progress,
+) -> io::Result<()> {
+	let dir = match (dir, bare) {
+		(Some(dir), _) => dir.to_path_buf(),
+		(None, false) => PathBuf::from(default_dir(url)),
+		(None, true) => PathBuf::from(format!("{}.git", default_dir(url))),
+	};
+	if dir.exists() && fs::read_dir(&dir).map_or(true, |mut x| x.next().is_some()) {
+		return Err(io::Error::other(format!(
+			"destination path '{}' already exists and is not an empty directory.",
+			dir.display()
+		)));
+	}
+	// localのrepositoryは絶対pathで記録する
+	let url = match url.contains("://") {
+		true => String::from(url),
+		false => Path::new(url)
+			.canonicalize()
+			.map_err(|_| io::Error::other(format!("repository '{}' does not exist", url)))?
+			.to_string_lossy()
+			.into_owned(),
+	};
+
+	eprintln!("Cloning into '{}'...", dir.display());
+	let created = !dir.exists();
+	let opts = InitOptions {
+		bare,
+		template: None,
+		initial_branch: None,
+		quiet: true,
+		object_format: None,
+	};
+	init(&dir, &opts)?;
+	let repo = match bare {
+		true => Repository::open_bare(&dir),
+		false => Repository::discover(&dir),
+	};
+	let result = repo.and_then(|repo| fill(&repo, &url, branch, progress));
+	if result.is_err() {
+		match (created, bare) {
+			(true, _) => fs::remove_dir_all(&dir).ok(),
+			(false, false) => fs::remove_dir_all(dir.join(".git")).ok(),
+			(false, true) => remove_contents(&dir).ok(),
+		};
+	}
+	result
+}
+
+// 元から空だったディレクトリに作ったものを消す
+fn remove_contents(dir: &Path) -> io::Result<()> {
+	for entry in fs::read_dir(dir)? {
+		let path = entry?.path();
+		match path.is_dir() {
+			true => fs::remove_dir_all(path)?,
+			false => fs::remove_file(path)?,
+		}
+	}
+	Ok(())
+}
+
+// "host:/path/repo.git/" や "/path/repo/.git" から "repo" を取り出す
+fn default_dir(url: &str) -> &str {
+	let url = url.trim_end_matches('/');
+	let url = url.strip_suffix("/.git").unwrap_or(url);
+	let name = url.rsplit(['/', ':']).next().unwrap_or(url);
+	name.strip_suffix(".git").unwrap_or(name)
+}
+
+fn fill(
+	repo: &Repository,
+	url: &str,
+	branch: Option<&str>,
+	progress: &mut dyn Progress,
+) -> io::Result<()> {
+	config::set(repo, "remote.origin.url", url)?;
+	// gitと同じく、bareではfetchのrefspecを記録しない
+	if !repo.bare {
+		config::set(
+			repo,
+			"remote.origin.fetch",
+			"+refs/heads/*:refs/remotes/origin/*",
+		)?;
+	}
+
+	// (localのref, remoteのref, hash)
+	let mut wanted = Vec::new();
+	let mut remote_head = None;
+	for (hash, name) in transport::list_refs(url)? {
+		let hash = Hash::from_hex(&hash).ok_or_else(|| {
+			io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!("{}: bad object name {}", url, hash),
+			)
+		})?;
+		let local = match (name.strip_prefix("refs/heads/"), name.as_str()) {
+			(Some(_), _) if repo.bare => name.clone(),
+			(Some(branch), _) => format!("refs/remotes/origin/{}", branch),
+			(None, "HEAD") => {
+				remote_head = Some(hash);
+				continue;
+			}
+			(None, x) if x.starts_with("refs/tags/") && !x.ends_with("^{}") => name.clone(),
+			_ => continue,
+		};
+		wanted.push((local, name, hash));
+	}
+	if wanted.is_empty() {
+		eprintln!("warning: You appear to have cloned an empty repository.");
+		return Ok(());
+	}
+
+	let wants: Vec<Hash> = wanted.iter().map(|(_, _, hash)| hash.clone()).collect();
+	transport::fetch_objects(repo, url, &wants, &[], progress)?;
+
+	let symref = transport::head_symref(url)?;
+	let head = match branch {
+		Some(branch) => Some(format!("refs/heads/{}", branch)),
+		None => symref.clone(),
+	};
+	// (checkoutするbranch, commit)。remoteがdetached HEADならbranchを作らない
+	let checkout = match (&head, remote_head) {
+		(Some(head), _) => match wanted.iter().find(|(_, name, _)| name == head) {
+			Some((_, _, hash)) => Some((Some(head.clone()), hash.clone())),
+			None if branch.is_some() => {
+				return Err(io::Error::other(format!(
+					"Remote branch {} not found in upstream origin",
+					refs::shorten(head)
+				)))
+			}
+			None => None,
+		},
+		(None, hash) => hash.map(|x| (None, x)),
+	};
+
+	let mut updates: Vec<RefUpdate> = wanted
+		.iter()
+		.map(|(local, _, hash)| RefUpdate {
+			name: local.clone(),
+			change: RefChange::Update(hash.to_hex()),
+			old: None,
+		})
+		.collect();
+	// bareではbranchは既にupdatesにあり、upstreamも記録しない
+	if let Some((Some(head), hash)) = &checkout {
+		refs::update_ref(repo, "HEAD", &format!("ref: {}", head))?;
+		if !repo.bare {
+			updates.push(RefUpdate {
+				name: head.clone(),
+				change: RefChange::Update(hash.to_hex()),
+				old: None,
+			});
+			Upstream {
+				remote: String::from("origin"),
+				merge: head.clone(),
+			}
+			.save(repo, refs::shorten(head))?;
+		}
+	}
+	// origin/HEADは-bに関わらずremoteのHEADと同じbranchを指す
+	let symref = symref.filter(|x| !repo.bare && wanted.iter().any(|(_, name, _)| name == x));
+	if let Some(branch) = symref
+		.as_deref()
+		.and_then(|x| x.strip_prefix("refs/heads/"))
+	{
+		refs::update_ref(
+			repo,
+			"refs/remotes/origin/HEAD",
+			&format!("ref: refs/remotes/origin/{}", branch),
+		)?;
+	}
+	refs::transaction(repo, &updates, &format!("clone: from {}", url))?;
+
+	let Some((head, hash)) = checkout else {
+		return Ok(());
+	};
+	if head.is_none() {
+		refs::update_ref(repo, "HEAD", &hash.to_hex())?;
+	}
+	if repo.bare {
+		return Ok(());
+	}
+	let commit = store::read_commit(repo, &hash.0)?;
+	let tree = Hash::from_hex(&commit.tree).ok_or_else(|| {
+		io::Error::new(io::ErrorKind::InvalidData, format!("bad commit {}", hash))
+	})?;
+	let mut index = Index::load(repo)?;
+	switch_tree(repo, &mut index, &tree.0)?;
+	index.save(repo)
+}
@@ -0,0 +1,276 @@
+use std::fs;
+use std::io::{self, Write};
+
+use crate::config::Config;
+use crate::gpg;
+use crate::hash::Hash;
+use crate::hook;
+use crate::ident;
+use crate::index::Index;
+use crate::merge;
+use crate::object::{Commit, ObjectKind, User};
+use crate::refs;
+use crate::repository::Repository;
+use crate::store;
+use crate::transaction::ObjectTransaction;
+
+use super::write_tree::write_entries;
+use super::{interpret_trailers, status, write_tree, StatusOptions};
+
+pub struct Options {
+	pub amend: bool,       // HEADのcommitを置き換える
+	pub allow_empty: bool, // treeが親と同じでもcommitする
+	pub no_verify: bool,   // pre-commitとcommit-msgのhookを実行しない
+	pub dry_run: bool,     // statusを出して、commitできるか確かめるだけ
+	pub signoff: bool,     // committerの "Signed-off-by" trailerをmessageに加える
+}
+
+// indexからtreeを作ってcommitし、HEADが指しているbranchを進める
+// signがSomeの場合はそのkeyで署名する(空文字列ならuser.signingkeyかcommitterを使う)
+// mergeの途中ならMERGE_HEADも親にしたmerge commitになり、messageが無ければMERGE_MSGを使う
+// amendの場合はHEADのcommitを置き換える。parentとauthorはそのまま、messageが無ければ元のものを使う
+// merge commit以外でtreeが親と同じなら、allow_emptyでなければcommitしない
+// signoffならmessageの最後に "Signed-off-by: <committer>" を加える(最後のtrailerが同じなら加えない)
+// no_verifyでなければ先にpre-commit hookを実行し、失敗したらcommitしない
+// messageはCOMMIT_EDITMSGに書き、no_verifyでなければcommit-msg hookに渡して、書き換えられたものを使う
+// refを更新した後にpost-commit hookを実行する。その終了コードは見ない
+// dry_runならhookは実行せず、statusを出してcommitできなければErrを返す。objectもrefもindexも書かない
+// 作ったcommitを "[<branch> <hash>] <subject>" の形で出す
+pub fn commit(
+	repo: &Repository,
+	message: Option<&str>,
+	sign: Option<&str>,
+	opts: &Options,
+) -> io::Result<()> {
+	let Some(hash) = create_commit(repo, message, sign, opts)? else {
+		return Ok(());
+	};
+	let commit = store::read_commit(repo, &hash.0)?;
+	let branch = refs::shorten(&refs::head_target(repo)?).to_string();
+	let root = match commit.parents.is_empty() {
+		true => " (root-commit)",
+		false => "",
+	};
+	let subject = commit
+		.message
+		.trim_start()
+		.lines()
+		.next()
+		.unwrap_or_default();
+	writeln!(
+		io::stdout().lock(),
+		"[{}{} {}] {}",
+		branch,
+		root,
+		&hash.to_hex()[..7],
+		subject
+	)?;
+	Ok(())
+}
+
+// commitと同じことをして何も出さずに、作ったcommitを返す。dry_runならNone
+pub fn create_commit(
+	repo: &Repository,
+	message: Option<&str>,
+	sign: Option<&str>,
+	opts: &Options,
+) -> io::Result<Option<Hash>> {
+	repo.require_worktree()?;
+	if !Index::load(repo)?.unmerged().is_empty() {
+		return Err(io::Error::other(
+			"Committing is not possible because you have unmerged files.",
+		));
+	}
+	if opts.dry_run {
+		let long = StatusOptions {
+			short: false,
+			branch: false,
+		};
+		status(repo, &long)?;
+	} else if !opts.no_verify {
+		hook::run(repo, "pre-commit", &[])?;
+	}
+
+	let merge_head = merge::merge_head(repo)?;
+	let target = refs::head_target(repo)?;
+	let head = refs::resolve(repo, "HEAD")?;
+
+	let replaced = match (opts.amend, &head) {
+		(false, _) => None,
+		(true, None) => return Err(io::Error::other("You have nothing to amend.")),
+		(true, Some(_)) if merge_head.is_some() => {
+			return Err(io::Error::other(
+				"You are in the middle of a merge -- cannot amend.",
+			))
+		}
+		(true, Some(hash)) => {
+			let hash = Hash::from_hex(hash).ok_or_else(|| {
+				io::Error::new(
+					io::ErrorKind::InvalidData,
+					format!("bad object name {}", hash),
+				)
+			})?;
+			Some(store::read_commit(repo, &hash.0)?)
+		}
+	};
+
+	let message = match (message, &replaced, &merge_head) {
+		(Some(message), _, _) => String::from(message),
+		(None, Some(replaced), _) => replaced.message.clone(),
+		// editorで開いたときと同じくコメント行は取り除く
+		(None, None, Some(_)) => merge::merge_msg(repo)?
+			.unwrap_or_default()
+			.lines()
+			.filter(|x| !x.starts_with('#'))
+			.map(|x| format!("{}\n", x))
+			.collect(),
+		(None, None, None) if opts.dry_run => String::new(),
+		(None, None, None) => return Err(io::Error::other("no commit message given (use -m)")),
+	};
+	let message = match opts.signoff {
+		true => {
+			let committer = ident::committer(repo)?;
+			let signoff = format!("{} <{}>", committer.name, committer.email);
+			interpret_trailers(&message, &[(String::from("Signed-off-by"), signoff)])
+		}
+		false => message,
+	};
+
+	let kind = match (opts.amend, &merge_head, &head) {
+		(true, _, _) => " (amend)",
+		(false, Some(_), _) => " (merge)",
+		(false, None, None) => " (initial)",
+		(false, None, Some(_)) => "",
+	};
+	let (parents, author) = match replaced {
+		Some(replaced) => (replaced.parents, replaced.author),
+		None => (
+			head.into_iter().chain(merge_head).collect(),
+			ident::author(repo)?,
+		),
+	};
+	// treeとcommitのobjectは、commitを作れたときだけ保存する
+	let transaction = ObjectTransaction::begin(repo)?;
+	let tree = match opts.dry_run {
+		false => hex::encode(write_tree(repo, None)?),
+		// indexのTREE extensionも書き換えないように、indexの内容から直接treeを作る
+		true => {
+			let index = Index::load(repo)?;
+			let entries: Vec<(&str, u32, &[u8])> = index
+				.entries
+				.iter()
+				.map(|x| (x.path.as_str(), x.mode, x.hash.as_slice()))
+				.collect();
+			hex::encode(write_entries(repo, &entries)?)
+		}
+	};
+	if !opts.allow_empty && parents.len() <= 1 && parent_tree(repo, parents.first())? == tree {
+		return Err(io::Error::other(match opts.amend {
+			true => "You asked to amend the most recent commit, but doing so would make it empty.",
+			false => "nothing to commit",
+		}));
+	}
+	if opts.dry_run {
+		return Ok(None);
+	}
+
+	// commit-msg hookはmessageのファイルを書き換えられる
+	let message_file = repo.gitdir.join("COMMIT_EDITMSG");
+	fs::write(&message_file, cleanup_message(&message))?;
+	if !opts.no_verify {
+		let shown = message_file
+			.strip_prefix(&repo.workdir)
+			.unwrap_or(&message_file);
+		hook::run(repo, "commit-msg", &[&shown.to_string_lossy()])?;
+	}
+	let message = fs::read_to_string(&message_file)?;
+	if message.trim().is_empty() {
+		return Err(io::Error::other(
+			"Aborting commit due to empty commit message.",
+		));
+	}
+
+	let hash = Hash(write_commit(repo, &tree, parents, author, &message, sign)?);
+	transaction.commit()?;
+	let subject = message.trim_start().lines().next().unwrap_or_default();
+	refs::update_ref_logged(
+		repo,
+		&target,
+		&hash.to_hex(),
+		&format!("commit{}: {}", kind, subject),
+	)?;
+	merge::clear_state(repo)?;
+	hook::run(repo, "post-commit", &[]).ok();
+	Ok(Some(hash))
+}
+
+// 親のtree。親が無ければ空のtree
+fn parent_tree(repo: &Repository, parent: Option<&String>) -> io::Result<String> {
+	match parent {
+		Some(parent) => {
+			let hash = Hash::from_hex(parent).ok_or_else(|| {
+				io::Error::new(
+					io::ErrorKind::InvalidData,
+					format!("bad object name {}", parent),
+				)
+			})?;
+			Ok(store::read_commit(repo, &hash.0)?.tree)
+		}
+		None => Ok(hex::encode(store::hash_bytes(
+			repo,
+			&ObjectKind::Tree.encode(b""),
+		))),
+	}
+}
+
+// treeとparentsからcommit objectを作って書き込む
+pub fn commit_tree(
+	repo: &Repository,
+	tree: &str,
+	parents: Vec<String>,
+	message: &str,
+	sign: Option<&str>,
+) -> io::Result<Vec<u8>> {
+	write_commit(repo, tree, parents, ident::author(repo)?, message, sign)
+}
+
+pub(super) fn write_commit(
+	repo: &Repository,
+	tree: &str,
+	parents: Vec<String>,
+	author: User,
+	message: &str,
+	sign: Option<&str>,
+) -> io::Result<Vec<u8>> {
+	let committer = ident::committer(repo)?;
+	let key = match sign {
+		Some("") => Some(
+			Config::load(repo)?
+				.get("user.signingkey")
+				.map(String::from)
+				.unwrap_or_else(|| format!("{} <{}>", committer.name, committer.email)),
+		),
+		x => x.map(String::from),
+	};
+
+	let mut commit = Commit::builder()
+		.tree(String::from(tree))
+		.parents(parents)
+		.author(author)
+		.committer(committer)
+		.message(cleanup_message(message))
+		.build()
+		.map_err(io::Error::other)?;
+
+	// 署名はgpgsig headerを除いた内容に対して行う
+	if let Some(key) = key {
+		commit.signature = Some(gpg::sign(repo, commit.payload().as_bytes(), &key)?);
+	}
+
+	store::write_object(repo, &commit.as_bytes())
+}
+
+// 末尾の空行を取り除き、最後に改行を1つだけつける
+fn cleanup_message(message: &str) -> String {
+	format!("{}\n", message.trim_end())
+}
@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::commit_graph::{CommitGraph, GraphCommit};
+use crate::hash::Hash;
+use crate::object::ObjectKind;
+use crate::refs;
+use crate::repository::Repository;
+use crate::revparse;
+use crate::store;
+use crate::tempfile::TempFile;
+
+// gitのtopologicalなgeneration numberの最大値
+const MAX_GENERATION: u32 = 0x3fff_ffff;
+
+// 全てのrefから辿れるcommitを objects/info/commit-graph に書く
+pub fn commit_graph_write(repo: &Repository) -> io::Result<()> {
+	repo.require_sha1("commit-graphs")?;
+	let mut stack = Vec::new();
+	for (_, hash) in refs::list(repo, "refs")? {
+		let Some(hash) = Hash::from_hex(&hash) else {
+			continue;
+		};
+		// commitを指していないtagなどは除く
+		if let Ok(commit) = revparse::peel(repo, hash, Some(ObjectKind::Commit)) {
+			stack.push(commit);
+		}
+	}
+
+	let mut commits: HashMap<Hash, GraphCommit> = HashMap::new();
+	while let Some(hash) = stack.pop() {
+		if commits.contains_key(&hash) {
+			continue;
+		}
+		let commit = store::read_commit(repo, &hash.0)?;
+		let bad = |x: &str| {
+			io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!("bad object name {} in commit {}", x, hash),
+			)
+		};
+		let parents = commit
+			.parents
+			.iter()
+			.map(|x| hex::decode(x).map_err(|_| bad(x)))
+			.collect::<io::Result<Vec<_>>>()?;
+		stack.extend(parents.iter().map(|x| Hash(x.clone())));
+		let entry = GraphCommit {
+			hash: hash.0.clone(),
+			tree: hex::decode(&commit.tree).map_err(|_| bad(&commit.tree))?,
+			parents,
+			generation: 0,
+			time: commit.committer.ts.timestamp(),
+		};
+		commits.insert(hash, entry);
+	}
+
+	// parentのgenerationが全て決まってから自分のものを決める
+	let mut generations: HashMap<Hash, u32> = HashMap::new();
+	for hash in commits.keys() {
+		let mut stack = vec![hash.clone()];
+		while let Some(hash) = stack.last().cloned() {
+			if generations.contains_key(&hash) {
+				stack.pop();
+				continue;
+			}
+			let parents: Vec<Hash> = commits[&hash]
+				.parents
+				.iter()
+				.map(|x| Hash(x.clone()))
+				.collect();
+			let pending: Vec<Hash> = parents
+				.iter()
+				.filter(|x| !generations.contains_key(*x))
+				.cloned()
+				.collect();
+			if pending.is_empty() {
+				let max = parents.iter().map(|x| generations[x]).max().unwrap_or(0);
+				generations.insert(hash, (max + 1).min(MAX_GENERATION));
+				stack.pop();
+			} else {
+				stack.extend(pending);
+			}
+		}
+	}
+
+	let commits = commits
+		.into_iter()
+		.map(|(hash, commit)| GraphCommit {
+			generation: generations[&hash],
+			..commit
+		})
+		.collect();
+	let dir = repo.objects_dir().join("info");
+	let mut file = TempFile::new(&dir, "tmp_commit_graph")?;
+	file.write_all(&CommitGraph { commits }.as_bytes())?;
+	file.persist(&dir.join("commit-graph"))
+}
@@ -0,0 +1,49 @@
+use std::io::{self, Write};
+
+use crate::config::{self, Config};
+use crate::repository::Repository;
+
+pub enum Action {
+	Get,         // 値を出力する
+	Set(String), // 値を書く。sectionが無ければ作る
+	Unset,       // keyの行を消す
+}
+
+// どの設定ファイルを読み書きするか
+pub enum Scope<'a> {
+	Default(&'a Repository), // 読むときはsystem、global、repositoryを重ねたもの、書くときはrepository
+	Local(&'a Repository),   // --local repositoryの.git/config
+	Global,                  // --global ~/.gitconfig
+	System,                  // --system /etc/gitconfig
+}
+
+// scopeの設定ファイルのkeyを読み書きする
+// Get、Unsetでkeyが無ければfalse
+pub fn config(scope: &Scope, key: &str, action: &Action) -> io::Result<bool> {
+	if let (Scope::Default(repo), Action::Get) = (scope, action) {
+		return get(&Config::load(repo)?, key);
+	}
+	let path = match scope {
+		Scope::Default(repo) | Scope::Local(repo) => repo.commondir.join("config"),
+		Scope::Global => config::global_path().ok_or_else(|| io::Error::other("$HOME not set"))?,
+		Scope::System => config::system_path().ok_or_else(|| {
+			io::Error::other("the system config is disabled by GIT_CONFIG_NOSYSTEM")
+		})?,
+	};
+
+	match action {
+		Action::Get => get(&Config::load_file(&path)?, key),
+		Action::Set(value) => config::set_file(&path, key, value).map(|_| true),
+		Action::Unset => config::unset_file(&path, key),
+	}
+}
+
+fn get(config: &Config, key: &str) -> io::Result<bool> {
+	match config.get(key) {
+		Some(value) => {
+			writeln!(io::stdout().lock(), "{}", value)?;
+			Ok(true)
+		}
+		None => Ok(false),
+	}
+}
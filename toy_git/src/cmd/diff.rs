@@ -0,0 +1,158 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::diff::{self, Renames, Side};
+use crate::hash::HashAlgorithm;
+use crate::index::{self, Index};
+use crate::object::{self, ObjectKind};
+use crate::repository::Repository;
+
+// 比較の片側
+pub enum Target {
+	Empty, // unbornなbranchのHEADなど
+	Tree(Vec<u8>),
+	Index,
+	Worktree,
+}
+
+pub enum Format {
+	Patch,
+	NameOnly,
+	NameStatus,
+}
+
+// patchの色の付け方と単語ごとの差分
+pub enum Color {
+	Never,
+	Lines,     // --color
+	Words,     // --color-words
+	Porcelain, // --word-diff=porcelain
+}
+
+impl Color {
+	fn apply(&self, patch: String) -> String {
+		match self {
+			Color::Never => patch,
+			Color::Lines => diff::color_lines(&patch),
+			Color::Words => diff::color_words(&patch),
+			Color::Porcelain => diff::word_diff_porcelain(&patch),
+		}
+	}
+}
+
+pub struct Options {
+	pub format: Format,
+	pub reverse: bool, // oldとnewを入れ替えて比べる(-R)
+	pub color: Color,
+	pub context: usize,           // hunkの前後に付ける行数(-U<n>)
+	pub renames: Option<Renames>, // Noneならrenameを探さない(--no-renames)
+}
+
+// oldからnewへの変更をopts.formatで出力する
+pub fn diff(repo: &Repository, old: Target, new: Target, opts: &Options) -> io::Result<()> {
+	let (old, new, prefix) = match opts.reverse {
+		false => (old, new, ("a", "b")),
+		true => (new, old, ("b", "a")),
+	};
+	let index = Index::load(repo)?;
+	let mut changes = diff::compare(&entries(repo, &index, &old)?, &entries(repo, &index, &new)?);
+	if let Some(renames) = &opts.renames {
+		changes = diff::detect_renames(repo, changes, renames)?;
+	}
+
+	let mut out = io::stdout().lock();
+	for change in changes.iter() {
+		match opts.format {
+			Format::Patch => write!(
+				out,
+				"{}",
+				opts.color
+					.apply(diff::patch(repo, change, prefix, opts.context)?)
+			)?,
+			Format::NameOnly => writeln!(out, "{}", change.path)?,
+			Format::NameStatus if change.old_path != change.path => writeln!(
+				out,
+				"{}{:03}\t{}\t{}",
+				change.status(),
+				change.similarity(),
+				change.old_path,
+				change.path
+			)?,
+			Format::NameStatus => writeln!(out, "{}\t{}", change.status(), change.path)?,
+		}
+	}
+	Ok(())
+}
+
+// repositoryを使わずに2つのファイルを比べる(diff --no-index)
+// reverseなら"a/", "b/"のprefixごと左右を入れ替える。formatとrenamesは使わない
+pub fn diff_no_index(old: &Path, new: &Path, opts: &Options) -> io::Result<()> {
+	let (old, new) = match opts.reverse {
+		false => (File::read("a", old)?, File::read("b", new)?),
+		true => (File::read("b", new)?, File::read("a", old)?),
+	};
+	if old.mode == new.mode && old.hash == new.hash {
+		return Ok(());
+	}
+
+	let mut out = format!("diff --git {} {}\n", old.name, new.name);
+	let mut mode = format!(" {:o}", old.mode);
+	if old.mode != new.mode {
+		out.push_str(&format!("old mode {:o}\nnew mode {:o}\n", old.mode, new.mode));
+		mode = String::new();
+	}
+
+	if old.hash != new.hash {
+		out.push_str(&format!(
+			"index {}..{}{}\n",
+			&hex::encode(&old.hash)[..7],
+			&hex::encode(&new.hash)[..7],
+			mode
+		));
+		if object::is_binary(&old.content) || object::is_binary(&new.content) {
+			out.push_str(&format!("Binary files {} and {} differ\n", old.name, new.name));
+		} else {
+			out.push_str(&format!("--- {}\n+++ {}\n", old.name, new.name));
+			out.push_str(&diff::unified(&old.content, &new.content, opts.context));
+		}
+	}
+	write!(io::stdout().lock(), "{}", opts.color.apply(out))
+}
+
+// --no-indexで比べるファイル。hashはblobとして計算する
+struct File {
+	name: String, // "a/<path>"
+	mode: u32,
+	hash: Vec<u8>,
+	content: Vec<u8>,
+}
+
+impl File {
+	fn read(prefix: &str, path: &Path) -> io::Result<Self> {
+		let access = |_| io::Error::other(format!("Could not access '{}'", path.display()));
+		let meta = fs::metadata(path).map_err(access)?;
+		let content = fs::read(path).map_err(access)?;
+
+		Ok(Self {
+			name: format!("{}/{}", prefix, path.display()),
+			mode: index::mode_from(&meta),
+			// repositoryの外なので、gitと同じくSHA-1にする
+			hash: HashAlgorithm::Sha1.digest(&ObjectKind::Blob.encode(&content)),
+			content,
+		})
+	}
+}
+
+fn entries(repo: &Repository, index: &Index, target: &Target) -> io::Result<BTreeMap<String, Side>> {
+	match target {
+		Target::Empty => Ok(BTreeMap::new()),
+		Target::Tree(hash) => diff::tree_entries(repo, hash),
+		Target::Index => Ok(diff::index_entries(index)),
+		Target::Worktree => {
+			repo.require_worktree()?;
+			diff::worktree_entries(repo, index)
+		}
+	}
+}
@@ -0,0 +1,74 @@
+use std::io::{self, Write};
+
+use crate::diff::{self, Side};
+use crate::hash::Hash;
+use crate::index::{self, Index};
+use crate::repository::Repository;
+use crate::worktree::Worktree;
+
+use super::diff_tree::raw;
+
+// treeをindex(cached)かworking directoryと比べて、diff-treeと同じ形で出力する
+// working directoryと比べるときは、indexと内容かmodeの違うファイルのhashは0になる
+// conflictしているpathは、cachedなら "U"、そうでなければworking directoryのファイルと比べる
+pub fn diff_index(repo: &Repository, tree: &Hash, cached: bool) -> io::Result<()> {
+	if !cached {
+		repo.require_worktree()?;
+	}
+	let index = Index::load(repo)?;
+	let unmerged = index.unmerged();
+	let old = diff::tree_entries(repo, &tree.0)?;
+	let staged = diff::index_entries(&index);
+
+	let new = match cached {
+		true => staged,
+		false => {
+			let null = |mode| Side {
+				mode,
+				hash: vec![0; repo.hash_algo.hash_len()],
+				worktree: true,
+			};
+			let mut new = diff::worktree_entries(repo, &index)?;
+			for (path, side) in new.iter_mut() {
+				if staged
+					.get(path)
+					.is_some_and(|x| x.hash != side.hash || x.mode != side.mode)
+				{
+					*side = null(side.mode);
+				}
+			}
+			let worktree = Worktree::new(repo);
+			for path in unmerged.iter() {
+				match worktree.stat(path) {
+					Ok(meta) => new.insert(String::from(*path), null(index::mode_from(&meta))),
+					Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+					Err(e) => return Err(e),
+				};
+			}
+			new
+		}
+	};
+
+	let mut lines: Vec<(String, String)> = diff::compare(&old, &new)
+		.into_iter()
+		.filter(|x| !cached || !unmerged.contains(&x.path.as_str()))
+		.map(|x| {
+			let line = raw(&x.old, &x.new, x.status(), &x.path);
+			(x.path, line)
+		})
+		.collect();
+	if cached {
+		for path in unmerged {
+			lines.push((
+				String::from(path),
+				raw(&old.get(path).cloned(), &None, 'U', path),
+			));
+		}
+		lines.sort();
+	}
+	let mut out = io::stdout().lock();
+	for (_, line) in lines {
+		writeln!(out, "{}", line)?;
+	}
+	Ok(())
+}
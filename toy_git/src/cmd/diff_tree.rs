@@ -0,0 +1,71 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use crate::diff::{self, Side};
+use crate::hash::Hash;
+use crate::repository::Repository;
+use crate::store;
+
+// 2つのtreeを比べて ":<mode> <mode> <hash> <hash> <status>\t<path>" の形で出力する
+// recursiveでなければ直下のentryだけを比べ、subtreeはまとめて1行になる
+pub fn diff_tree(repo: &Repository, a: &Hash, b: &Hash, recursive: bool) -> io::Result<()> {
+	let entries = |tree: &Hash| {
+		if recursive {
+			diff::tree_entries(repo, &tree.0)
+		} else {
+			top_level(repo, &tree.0)
+		}
+	};
+
+	let mut out = io::stdout().lock();
+	for change in diff::compare(&entries(a)?, &entries(b)?) {
+		writeln!(
+			out,
+			"{}",
+			raw(
+				&change.old,
+				&change.new,
+				change.status(),
+				change.path.trim_end_matches('/')
+			)
+		)?;
+	}
+	Ok(())
+}
+
+// ":<mode> <mode> <hash> <hash> <status>\t<path>" の1行。無い側はmodeもhashも0
+pub(super) fn raw(old: &Option<Side>, new: &Option<Side>, status: char, path: &str) -> String {
+	// 0のhashは有る側のhashと同じ長さにする
+	let len = old.as_ref().or(new.as_ref()).map_or(20, |x| x.hash.len());
+	let side = |x: &Option<Side>| match x {
+		Some(x) => (x.mode, hex::encode(&x.hash)),
+		None => (0, "0".repeat(len * 2)),
+	};
+	let (old_mode, old_hash) = side(old);
+	let (new_mode, new_hash) = side(new);
+	format!(
+		":{:06o} {:06o} {} {} {}\t{}",
+		old_mode, new_mode, old_hash, new_hash, status, path
+	)
+}
+
+// treeの直下のentry。gitと同じ順に並ぶようにsubtreeの名前には "/" を付ける
+fn top_level(repo: &Repository, tree: &[u8]) -> io::Result<BTreeMap<String, Side>> {
+	Ok(store::read_tree(repo, tree)?
+		.contents
+		.into_iter()
+		.map(|file| {
+			let name = if file.is_tree() {
+				format!("{}/", file.name)
+			} else {
+				file.name.clone()
+			};
+			let side = Side {
+				mode: file.mode_bits(),
+				hash: file.hash,
+				worktree: false,
+			};
+			(name, side)
+		})
+		.collect())
+}
@@ -0,0 +1,156 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, Write};
+
+use crate::diff::{self, Side};
+use crate::fast;
+use crate::object::{Commit, ObjectKind};
+use crate::refs;
+use crate::repository::Repository;
+use crate::revparse;
+use crate::store;
+
+// 全てのrefから辿れる履歴をgit fast-importの形式でoutに書く
+// commitはparentを先に出し、最初に辿ったrefの名前で出す。blobはそれを使うcommitの直前に出す
+// commitとblobには出した順に ":1" から始まるmarkを付け、parentやファイルの内容はmarkで指す
+// 最後に、自分の名前で出されなかったrefを "reset" で、annotated tagを "tag" で出す
+pub fn fast_export<W: Write>(repo: &Repository, mut out: W) -> io::Result<()> {
+	let mut tips = Vec::new();
+	let mut tags = Vec::new();
+	for (name, hash) in refs::list(repo, "refs")? {
+		let hash = revparse::resolve(repo, &hash)?;
+		let commit = revparse::peel(repo, hash.clone(), Some(ObjectKind::Commit))?.to_hex();
+		if store::read_object_header(repo, &hash.0)?.0 == ObjectKind::Tag {
+			tags.push((name.clone(), hash.0));
+		}
+		tips.push((name, commit));
+	}
+
+	let mut commits = HashMap::new();
+	let order = walk(repo, &tips, &mut commits)?;
+
+	let mut marks: HashMap<String, usize> = HashMap::new();
+	let mut named: HashMap<&str, &str> = HashMap::new(); // commitの出した名前
+	for (hash, name) in order.iter() {
+		let commit = &commits[hash];
+		let old = match commit.parents.first() {
+			Some(parent) => diff::tree_entries(repo, &tree(&commits[parent]))?,
+			None => BTreeMap::new(),
+		};
+		let new = diff::tree_entries(repo, &tree(commit))?;
+		let changes = diff::compare(&old, &new);
+
+		let files: Vec<(&String, &Side)> = changes
+			.iter()
+			.filter_map(|x| x.new.as_ref().map(|side| (&x.path, side)))
+			.collect();
+		for (_, side) in files.iter().filter(|(_, x)| x.mode != 0o160000) {
+			let blob = hex::encode(&side.hash);
+			if marks.contains_key(&blob) {
+				continue;
+			}
+			let mark = marks.len() + 1;
+			marks.insert(blob, mark);
+			let (_, content) = store::read_object(repo, &side.hash)?;
+			write!(out, "blob\nmark :{}\n", mark)?;
+			fast::write_data(&mut out, &content)?;
+			writeln!(out)?;
+		}
+
+		if commit.parents.is_empty() {
+			writeln!(out, "reset {}", name)?;
+		}
+		let mark = marks.len() + 1;
+		marks.insert(hash.clone(), mark);
+		named.insert(hash, name);
+		write!(
+			out,
+			"commit {}\nmark :{}\nauthor {}\ncommitter {}\n",
+			name, mark, commit.author, commit.committer
+		)?;
+		fast::write_data(&mut out, commit.message.as_bytes())?;
+		if !commit.message.ends_with('\n') {
+			writeln!(out)?;
+		}
+		for (i, parent) in commit.parents.iter().enumerate() {
+			let kind = if i == 0 { "from" } else { "merge" };
+			writeln!(out, "{} :{}", kind, marks[parent])?;
+		}
+		for change in changes.iter().filter(|x| x.new.is_none()) {
+			writeln!(out, "D {}", fast::quote(&change.path))?;
+		}
+		for (path, side) in files {
+			let data = match side.mode {
+				0o160000 => hex::encode(&side.hash),
+				_ => format!(":{}", marks[&hex::encode(&side.hash)]),
+			};
+			writeln!(out, "M {:06o} {} {}", side.mode, data, fast::quote(path))?;
+		}
+		writeln!(out)?;
+	}
+
+	for (name, hash) in tips.iter() {
+		if named.get(hash.as_str()) != Some(&name.as_str()) && !tags.iter().any(|(x, _)| x == name)
+		{
+			write!(out, "reset {}\nfrom :{}\n\n", name, marks[hash])?;
+		}
+	}
+
+	for (name, hash) in tags {
+		let tag = store::read_tag(repo, &hash)?;
+		if tag.kind != ObjectKind::Commit {
+			return Err(io::Error::other(format!(
+				"tag {} tags unexported {} object",
+				refs::shorten(&name),
+				tag.kind
+			)));
+		}
+		write!(
+			out,
+			"tag {}\nfrom :{}\n",
+			refs::shorten(&name),
+			marks[&tag.object]
+		)?;
+		if let Some(tagger) = tag.tagger {
+			writeln!(out, "tagger {}", tagger)?;
+		}
+		fast::write_data(&mut out, tag.message.as_bytes())?;
+		writeln!(out)?;
+	}
+	out.flush()
+}
+
+// 各refからparentを先にして辿った(commit, ref名)の順
+fn walk(
+	repo: &Repository,
+	tips: &[(String, String)],
+	commits: &mut HashMap<String, Commit>,
+) -> io::Result<Vec<(String, String)>> {
+	let mut order = Vec::new();
+	let mut seen = HashSet::new();
+	for (name, tip) in tips {
+		// trueは全てのparentを出し終えたこと
+		let mut stack = vec![(tip.clone(), false)];
+		while let Some((hash, done)) = stack.pop() {
+			if done {
+				order.push((hash, name.clone()));
+				continue;
+			}
+			if !seen.insert(hash.clone()) {
+				continue;
+			}
+			let commit = store::read_commit(repo, &hex::decode(&hash).unwrap_or_default())?;
+			stack.push((hash.clone(), true));
+			for parent in commit.parents.iter().rev() {
+				if !seen.contains(parent) {
+					stack.push((parent.clone(), false));
+				}
+			}
+			commits.insert(hash, commit);
+		}
+	}
+	Ok(order)
+}
+
+fn tree(commit: &Commit) -> Vec<u8> {
+	hex::decode(&commit.tree).unwrap_or_default()
+}
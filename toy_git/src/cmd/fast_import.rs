@@ -0,0 +1,369 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, BufRead, Write};
+
+use super::write_tree::write_entries;
+use crate::diff::{self, Side};
+use crate::fast::{self, Reader};
+use crate::hash::Hash;
+use crate::merge;
+use crate::object::{Commit, ObjectKind, User};
+use crate::refs::{self, RefChange, RefUpdate};
+use crate::repository::Repository;
+use crate::revparse;
+use crate::store;
+use crate::transaction::ObjectTransaction;
+
+// git fast-importの形式のstreamを読んでobjectを書き込み、最後にまとめてrefを更新する
+// "blob", "commit", "reset", "tag", "progress", "checkpoint", "feature", "option", "done" に対応する
+// 既にあるbranchは、新しい値が古い値を含むとき(fast-forward)だけ更新する
+pub fn fast_import<R: BufRead>(repo: &Repository, input: R) -> io::Result<()> {
+	let mut import = Import {
+		repo,
+		input: Reader::new(input),
+		marks: HashMap::new(),
+		branches: BTreeMap::new(),
+	};
+
+	let transaction = ObjectTransaction::begin(repo)?;
+	let mut out = io::stdout().lock();
+	while let Some(line) = import.input.next_line()? {
+		let (command, arg) = line.split_once(' ').unwrap_or((&line, ""));
+		match command {
+			"" => {}
+			"blob" => import.blob()?,
+			"commit" => import.commit(arg)?,
+			"reset" => import.reset(arg)?,
+			"tag" => import.tag(arg)?,
+			"progress" => writeln!(out, "progress {}", arg)?,
+			"checkpoint" | "feature" | "option" => {}
+			"done" => break,
+			_ => return Err(io::Error::other(format!("Unsupported command: {}", line))),
+		}
+	}
+	transaction.commit()?;
+
+	let mut updates = Vec::new();
+	for (name, hash) in import.branches.iter() {
+		let Some(hash) = hash else {
+			continue;
+		};
+		let old = refs::resolve(repo, name)?;
+		if let Some(old) = old.as_ref().filter(|x| *x != hash) {
+			if !contains(repo, hash, old)? {
+				eprintln!(
+					"warning: Not updating {} (new tip {} does not contain {})",
+					name, hash, old
+				);
+				continue;
+			}
+		}
+		updates.push(RefUpdate {
+			name: name.clone(),
+			change: RefChange::Update(hash.clone()),
+			old: Some(old),
+		});
+	}
+	refs::transaction(repo, &updates, "fast-import")
+}
+
+// newから辿れるcommitにoldが含まれるか。どちらかがcommitを指していなければfalse
+fn contains(repo: &Repository, new: &str, old: &str) -> io::Result<bool> {
+	let peel = |hash: &str| {
+		let hash =
+			Hash::from_hex(hash).ok_or_else(|| io::Error::other(format!("bad object {}", hash)))?;
+		revparse::peel(repo, hash, Some(ObjectKind::Commit))
+	};
+	match (peel(new), peel(old)) {
+		(Ok(new), Ok(old)) => Ok(merge::ancestors(repo, &new)?.contains(&old)),
+		_ => Ok(false),
+	}
+}
+
+struct Import<'a, R: BufRead> {
+	repo: &'a Repository,
+	input: Reader<R>,
+	marks: HashMap<usize, String>,
+	branches: BTreeMap<String, Option<String>>, // 最後に書き込むrefの値。Noneはresetされてまだcommitが無いもの
+}
+
+impl<R: BufRead> Import<'_, R> {
+	fn blob(&mut self) -> io::Result<()> {
+		let mark = self.mark()?;
+		self.input.next_if("original-oid ")?;
+		let data = self.input.data()?;
+		let hash = hex::encode(store::write_object(
+			self.repo,
+			&ObjectKind::Blob.encode(&data),
+		)?);
+		if let Some(mark) = mark {
+			self.marks.insert(mark, hash);
+		}
+		Ok(())
+	}
+
+	// commit <ref>
+	// mark, author, committer, data, from, merge の後に、ファイルの変更(M, D, C, R, deleteall)が続く
+	fn commit(&mut self, name: &str) -> io::Result<()> {
+		let mark = self.mark()?;
+		self.input.next_if("original-oid ")?;
+		let author = self.input.next_if("author ")?;
+		let committer = self.input.next_if("committer ")?.ok_or_else(|| {
+			io::Error::other(format!(
+				"Expected committer but didn't get one in commit {}",
+				name
+			))
+		})?;
+		self.input.next_if("encoding ")?;
+		let message = self.input.data()?;
+
+		let mut parents = Vec::new();
+		if let Some(from) = self.input.next_if("from ")? {
+			parents.push(self.commitish(&from)?);
+		} else if let Some(current) = self.current(name)? {
+			parents.push(current);
+		}
+		while let Some(merge) = self.input.next_if("merge ")? {
+			parents.push(self.commitish(&merge)?);
+		}
+
+		let mut files = match parents.first() {
+			Some(parent) => {
+				let commit =
+					store::read_commit(self.repo, &hex::decode(parent).unwrap_or_default())?;
+				diff::tree_entries(self.repo, &hex::decode(&commit.tree).unwrap_or_default())?
+			}
+			None => BTreeMap::new(),
+		};
+		while let Some(line) = self.input.peek()? {
+			let command = line.split(' ').next().unwrap_or_default();
+			if !matches!(command, "M" | "D" | "C" | "R" | "deleteall") {
+				break;
+			}
+			let line = self.input.next_line()?.unwrap_or_default();
+			self.file_change(&mut files, &line)?;
+		}
+
+		let tree = self.write_tree(&files)?;
+		let user = |line: &str| {
+			User::from(line.as_bytes())
+				.map_err(|e| io::Error::other(format!("invalid ident: {}: {}", line, e)))
+		};
+		let commit = Commit::builder()
+			.tree(tree)
+			.parents(parents)
+			.author(user(author.as_deref().unwrap_or(&committer))?)
+			.committer(user(&committer)?)
+			.message(String::from_utf8_lossy(&message).into_owned())
+			.build()
+			.map_err(io::Error::other)?;
+		let hash = hex::encode(store::write_object(self.repo, &commit.as_bytes())?);
+
+		if let Some(mark) = mark {
+			self.marks.insert(mark, hash.clone());
+		}
+		self.branches.insert(String::from(name), Some(hash));
+		Ok(())
+	}
+
+	// reset <ref> の後に省略できる "from <commit-ish>"
+	fn reset(&mut self, name: &str) -> io::Result<()> {
+		let from = match self.input.next_if("from ")? {
+			Some(from) => Some(self.commitish(&from)?),
+			None => None,
+		};
+		self.branches.insert(String::from(name), from);
+		Ok(())
+	}
+
+	// tag <name> の後に mark, from, tagger, data が続く
+	fn tag(&mut self, name: &str) -> io::Result<()> {
+		let mark = self.mark()?;
+		let from = self
+			.input
+			.next_if("from ")?
+			.ok_or_else(|| io::Error::other(format!("Expected from command in tag {}", name)))?;
+		let object = self.commitish(&from)?;
+		self.input.next_if("original-oid ")?;
+		let tagger = self.input.next_if("tagger ")?;
+		let message = self.input.data()?;
+
+		let kind =
+			store::read_object_header(self.repo, &hex::decode(&object).unwrap_or_default())?.0;
+		let mut body = format!("object {}\ntype {}\ntag {}\n", object, kind, name).into_bytes();
+		if let Some(tagger) = tagger {
+			body.extend_from_slice(format!("tagger {}\n", tagger).as_bytes());
+		}
+		body.push(b'\n');
+		body.extend_from_slice(&message);
+		let hash = hex::encode(store::write_object(
+			self.repo,
+			&ObjectKind::Tag.encode(&body),
+		)?);
+
+		if let Some(mark) = mark {
+			self.marks.insert(mark, hash.clone());
+		}
+		self.branches
+			.insert(format!("refs/tags/{}", name), Some(hash));
+		Ok(())
+	}
+
+	fn file_change(&mut self, files: &mut BTreeMap<String, Side>, line: &str) -> io::Result<()> {
+		let invalid = || io::Error::other(format!("invalid file change: {}", line));
+		let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+		match command {
+			"deleteall" => files.clear(),
+			"M" => {
+				let (mode, rest) = rest.split_once(' ').ok_or_else(invalid)?;
+				let (data, path) = rest.split_once(' ').ok_or_else(invalid)?;
+				let (path, _) = path_arg(path, true);
+				let mode = match mode {
+					"644" => 0o100644,
+					"755" => 0o100755,
+					mode => u32::from_str_radix(mode, 8).map_err(|_| invalid())?,
+				};
+				let hash = match data {
+					"inline" => {
+						let content = self.input.data()?;
+						store::write_object(self.repo, &ObjectKind::Blob.encode(&content))?
+					}
+					data => hex::decode(self.dataref(data)?).map_err(|_| invalid())?,
+				};
+
+				// ディレクトリならそのtreeの中身で置き換える
+				remove(files, &path);
+				if mode == 0o40000 {
+					for (name, side) in diff::tree_entries(self.repo, &hash)? {
+						files.insert(join(&path, &name), side);
+					}
+				} else {
+					files.insert(
+						path,
+						Side {
+							mode,
+							hash,
+							worktree: false,
+						},
+					);
+				}
+			}
+			"D" => {
+				let (path, _) = path_arg(rest, true);
+				remove(files, &path);
+			}
+			"C" | "R" => {
+				let (source, rest) = path_arg(rest, false);
+				let (dest, _) = path_arg(rest.strip_prefix(' ').ok_or_else(invalid)?, true);
+				let moved = take(files, &source, command == "R");
+				if moved.is_empty() {
+					return Err(io::Error::other(format!("Path {} not in branch", source)));
+				}
+				remove(files, &dest);
+				for (name, side) in moved {
+					files.insert(join(&dest, &name), side);
+				}
+			}
+			_ => return Err(invalid()),
+		}
+		Ok(())
+	}
+
+	fn write_tree(&self, files: &BTreeMap<String, Side>) -> io::Result<String> {
+		let entries: Vec<(&str, u32, &[u8])> = files
+			.iter()
+			.map(|(path, side)| (path.as_str(), side.mode, side.hash.as_slice()))
+			.collect();
+		Ok(hex::encode(write_entries(self.repo, &entries)?))
+	}
+
+	// 省略できる "mark :<n>"
+	fn mark(&mut self) -> io::Result<Option<usize>> {
+		match self.input.next_if("mark :")? {
+			Some(mark) => mark
+				.parse()
+				.map(Some)
+				.map_err(|_| io::Error::other(format!("invalid mark: :{}", mark))),
+			None => Ok(None),
+		}
+	}
+
+	// ":<mark>" か40桁のhash
+	fn dataref(&self, data: &str) -> io::Result<String> {
+		match data.strip_prefix(':') {
+			Some(mark) => mark
+				.parse()
+				.ok()
+				.and_then(|x: usize| self.marks.get(&x))
+				.cloned()
+				.ok_or_else(|| io::Error::other(format!("mark :{} not declared", mark))),
+			None if Hash::from_hex(data).is_some() => Ok(String::from(data)),
+			None => Err(io::Error::other(format!("invalid dataref: {}", data))),
+		}
+	}
+
+	// ":<mark>"、hash、このstreamで作ったbranch、repositoryのrevision
+	fn commitish(&self, spec: &str) -> io::Result<String> {
+		if spec.starts_with(':')
+			|| (spec.len() == self.repo.hash_algo.hex_len() && Hash::from_hex(spec).is_some())
+		{
+			return self.dataref(spec);
+		}
+		if let Some(Some(hash)) = self.branches.get(spec) {
+			return Ok(hash.clone());
+		}
+		Ok(revparse::resolve(self.repo, spec)?.to_hex())
+	}
+
+	// commitにfromが無いときのparent。streamの中で作ったbranchならその値、無ければrepositoryのref
+	fn current(&self, name: &str) -> io::Result<Option<String>> {
+		match self.branches.get(name) {
+			Some(hash) => Ok(hash.clone()),
+			None => refs::resolve(self.repo, name),
+		}
+	}
+}
+
+// "M" などのpath。'"' で始まればCの文字列、そうでなければlastなら行末まで、それ以外は空白まで
+fn path_arg(s: &str, last: bool) -> (String, &str) {
+	if let Some((path, rest)) = fast::unquote(s) {
+		return (path, rest);
+	}
+	match s.find(' ').filter(|_| !last) {
+		Some(end) => (String::from(&s[..end]), &s[end..]),
+		None => (String::from(s), ""),
+	}
+}
+
+fn join(dir: &str, name: &str) -> String {
+	match (dir.is_empty(), name.is_empty()) {
+		(true, _) => String::from(name),
+		(_, true) => String::from(dir),
+		_ => format!("{}/{}", dir, name),
+	}
+}
+
+fn within(path: &str, dir: &str) -> bool {
+	dir.is_empty() || path == dir || path.strip_prefix(dir).is_some_and(|x| x.starts_with('/'))
+}
+
+fn remove(files: &mut BTreeMap<String, Side>, path: &str) {
+	files.retain(|x, _| !within(x, path));
+}
+
+// pathのファイルか、pathの下のファイルを(pathからの相対path, ファイル)で返す。moveなら取り除く
+fn take(files: &mut BTreeMap<String, Side>, path: &str, moving: bool) -> Vec<(String, Side)> {
+	let found: Vec<(String, Side)> = files
+		.iter()
+		.filter(|(x, _)| within(x, path))
+		.map(|(x, side)| {
+			(
+				String::from(x[path.len()..].trim_start_matches('/')),
+				side.clone(),
+			)
+		})
+		.collect();
+	if moving {
+		remove(files, path);
+	}
+	found
+}
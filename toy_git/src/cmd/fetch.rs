@@ -0,0 +1,121 @@
+use std::io::{self, Write};
+
+use crate::config::Config;
+use crate::hash::Hash;
+use crate::merge;
+use crate::progress::Progress;
+use crate::refs;
+use crate::repository::Repository;
+use crate::transport;
+
+// 表示する "[new branch]" や "abc..def" の欄の幅
+pub(super) const SUMMARY_WIDTH: usize = 17;
+
+// remoteのbranchを取ってきて refs/remotes/<remote>/<branch> に記録する
+// pruneならremoteに無くなったbranchのremote-tracking refを消す(local branchは触らない)
+pub fn fetch(
+	repo: &Repository,
+	remote: &str,
+	prune: bool,
+	progress: &mut dyn Progress,
+) -> io::Result<()> {
+	let url = remote_url(repo, remote)?;
+
+	let prefix = format!("refs/remotes/{}/", remote);
+	let bad = |x: &str| {
+		io::Error::new(
+			io::ErrorKind::InvalidData,
+			format!("{}: bad object name {}", url, x),
+		)
+	};
+
+	// (remote-tracking ref, hash)
+	let mut updates = Vec::new();
+	for (hash, name) in transport::list_refs(&url)? {
+		if let Some(branch) = name.strip_prefix("refs/heads/") {
+			let hash = Hash::from_hex(&hash).ok_or_else(|| bad(&hash))?;
+			updates.push((format!("{}{}", prefix, branch), hash));
+		}
+	}
+
+	// symbolic refの "<remote>/HEAD" は消さない
+	let tracking: Vec<(String, String)> = refs::list(repo, &prefix)?
+		.into_iter()
+		.filter(|(name, _)| name.strip_prefix(&prefix) != Some("HEAD"))
+		.collect();
+	let stale: Vec<&String> = tracking
+		.iter()
+		.map(|(name, _)| name)
+		.filter(|name| prune && !updates.iter().any(|(x, _)| x == *name))
+		.collect();
+
+	let wants: Vec<Hash> = updates.iter().map(|(_, hash)| hash.clone()).collect();
+	let haves: Vec<Hash> = tracking
+		.iter()
+		.filter_map(|(_, hash)| Hash::from_hex(hash))
+		.collect();
+	transport::fetch_objects(repo, &url, &wants, &haves, progress)?;
+
+	let width = updates
+		.iter()
+		.map(|(name, _)| &name[prefix.len()..])
+		.chain(stale.iter().map(|_| "(none)"))
+		.map(str::len)
+		.fold(10, usize::max);
+	let mut header = Some(format!("From {}", url));
+	let mut out = io::stdout().lock();
+	let mut show = |flag: char, summary: &str, from: &str, to: &str, note: &str| {
+		if let Some(header) = header.take() {
+			writeln!(out, "{}", header)?;
+		}
+		writeln!(
+			out,
+			" {} {:<sw$} {:<w$} -> {}{}",
+			flag,
+			summary,
+			from,
+			refs::shorten(to),
+			note,
+			sw = SUMMARY_WIDTH,
+			w = width
+		)
+	};
+
+	// gitと同じく、消してから更新する
+	for name in stale {
+		refs::delete_ref(repo, name)?;
+		show('-', "[deleted]", "(none)", name, "")?;
+	}
+
+	for (name, hash) in &updates {
+		let branch = &name[prefix.len()..];
+		let old = refs::read_ref(repo, name)?.and_then(|x| Hash::from_hex(&x));
+		match old {
+			None => show('*', "[new branch]", branch, name, "")?,
+			Some(old) if old == *hash => continue,
+			Some(old) => {
+				let range =
+					|sep: &str| format!("{}{}{}", &old.to_hex()[..7], sep, &hash.to_hex()[..7]);
+				match merge::merge_base(repo, &old, hash)? {
+					Some(base) if base == old => show(' ', &range(".."), branch, name, "")?,
+					_ => show('+', &range("..."), branch, name, "  (forced update)")?,
+				}
+			}
+		}
+		refs::update_ref(repo, name, &hash.to_hex())?;
+	}
+	Ok(())
+}
+
+// remote.<remote>.url
+pub(super) fn remote_url(repo: &Repository, remote: &str) -> io::Result<String> {
+	Config::load(repo)?
+		.get(&format!("remote.{}.url", remote))
+		.map(String::from)
+		.ok_or_else(|| {
+			io::Error::other(format!(
+				"'{}' does not appear to be a git repository",
+				remote
+			))
+		})
+}
@@ -0,0 +1,96 @@
+use std::io::{self, Write};
+
+use crate::object::{ObjectKind, Tag};
+use crate::refs;
+use crate::repository::Repository;
+use crate::store;
+use crate::wildmatch::wildmatch;
+
+// patternに一致するrefを名前順にformatで整形して出力する
+// patternにglobの文字が無ければprefixとして扱う
+pub fn for_each_ref(repo: &Repository, pattern: Option<&str>, format: &str) -> io::Result<()> {
+	let mut out = io::stdout().lock();
+	for (name, hash) in refs::list(repo, "")? {
+		let hit = match pattern {
+			Some(x) if x.contains(['*', '?', '[']) => wildmatch(x, &name, false),
+			Some(x) => refs::list_match(&name, x),
+			None => true,
+		};
+		if hit {
+			writeln!(out, "{}", expand(repo, &name, &hash, format)?)?;
+		}
+	}
+	Ok(())
+}
+
+fn expand(repo: &Repository, name: &str, hash: &str, format: &str) -> io::Result<String> {
+	let mut out = String::new();
+	let mut rest = format;
+
+	while let Some(i) = rest.find('%') {
+		out.push_str(&rest[..i]);
+		rest = &rest[i..];
+
+		if let Some(tail) = rest.strip_prefix("%%") {
+			out.push('%');
+			rest = tail;
+			continue;
+		}
+
+		let atom = rest
+			.strip_prefix("%(")
+			.and_then(|x| x.split_once(')'))
+			.map(|(atom, tail)| {
+				rest = tail;
+				atom
+			});
+		match atom {
+			Some(atom) => out.push_str(&atom_value(repo, name, hash, atom)?),
+			None => {
+				out.push('%');
+				rest = &rest[1..];
+			}
+		}
+	}
+	out.push_str(rest);
+
+	Ok(out)
+}
+
+fn atom_value(repo: &Repository, name: &str, hash: &str, atom: &str) -> io::Result<String> {
+	// "*" で始まるatomはannotated tagが指すobjectの値になる(tag以外のrefでは空)
+	let (hash, atom) = match atom.strip_prefix('*') {
+		Some(atom) => match peel(repo, hash)? {
+			Some(peeled) => (peeled, atom),
+			None => return Ok(String::new()),
+		},
+		None => (String::from(hash), atom),
+	};
+
+	let value = match atom {
+		"refname" => String::from(name),
+		"refname:short" => String::from(refs::shorten(name)),
+		"objectname" => hash,
+		"objectname:short" => String::from(&hash[..7]),
+		"objecttype" => store::read_object(repo, &decode(&hash)?)?.0.to_string(),
+		"objectsize" => store::read_object(repo, &decode(&hash)?)?.1.len().to_string(),
+		_ => return Err(io::Error::other(format!("unknown field name: {}", atom))),
+	};
+	Ok(value)
+}
+
+// annotated tagが指しているobjectのhashを返す。tagでなければNone
+fn peel(repo: &Repository, hash: &str) -> io::Result<Option<String>> {
+	let (kind, body) = store::read_object(repo, &decode(hash)?)?;
+	if kind != ObjectKind::Tag {
+		return Ok(None);
+	}
+
+	Tag::from(&body)
+		.map(|x| Some(x.object))
+		.map_err(|e| e.context(format!("bad tag {}", hash)))
+}
+
+fn decode(hash: &str) -> io::Result<Vec<u8>> {
+	hex::decode(hash).map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("bad object name {}", hash)))
+}
@@ -0,0 +1,196 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use crate::diff;
+use crate::hash::Hash;
+use crate::mail;
+use crate::merge;
+use crate::object::{Commit, ObjectKind};
+use crate::pretty;
+use crate::repository::Repository;
+use crate::revparse;
+use crate::store;
+
+// gitと同じく、"From " の行のdateは固定の値
+const MAGIC_DATE: &str = "Mon Sep 17 00:00:00 2001";
+// gitのMAIL_DEFAULT_WRAP。diffstatはこの幅に収める
+const STAT_WIDTH: usize = 72;
+// ".patch" を含めたファイル名の長さの上限
+const NAME_MAX: usize = 64;
+
+pub struct Options {
+	pub output_dir: Option<PathBuf>, // -o: ファイルを書くディレクトリ
+	pub stdout: bool,                // ファイルに書かずに全てstdoutに出す
+}
+
+// rangeの各commitを、mailとして送れる "0001-<subject>.patch" のファイルにして、書いたpathを出力する
+// rangeは "<since>..<until>" か、"<since>" ("<since>..HEAD" と同じ)。merge commitは飛ばす
+pub fn format_patch(repo: &Repository, range: &str, opts: &Options) -> io::Result<()> {
+	let (since, until) = match range.split_once("..") {
+		Some((since, "")) => (since, "HEAD"),
+		Some((since, until)) => (since, until),
+		None => (range, "HEAD"),
+	};
+	let commit = |rev: &str| -> io::Result<Hash> {
+		let hash = revparse::resolve(repo, rev)?;
+		revparse::peel(repo, hash, Some(ObjectKind::Commit))
+	};
+	let hidden = merge::ancestors(repo, &commit(since)?)?;
+	let commits: Vec<(Hash, Commit)> = topo_order(repo, &commit(until)?, &hidden)?
+		.into_iter()
+		.filter(|(_, x)| x.parents.len() <= 1)
+		.collect();
+
+	let total = commits.len();
+	let mut out = io::stdout().lock();
+	for (i, (hash, commit)) in commits.iter().enumerate() {
+		let patch = patch(repo, hash, commit, i + 1, total)?;
+		if opts.stdout {
+			out.write_all(patch.as_bytes())?;
+			continue;
+		}
+		let name = file_name(i + 1, &pretty::subject(&commit.message));
+		let path = match &opts.output_dir {
+			Some(dir) => {
+				fs::create_dir_all(dir)?;
+				dir.join(name)
+			}
+			None => PathBuf::from(name),
+		};
+		fs::write(&path, patch)?;
+		writeln!(out, "{}", path.display())?;
+	}
+	Ok(())
+}
+
+// tipから辿れてhiddenに無いcommitを、parentが先になるように並べる
+fn topo_order(
+	repo: &Repository,
+	tip: &Hash,
+	hidden: &HashSet<Hash>,
+) -> io::Result<Vec<(Hash, Commit)>> {
+	let mut order = Vec::new();
+	let mut seen = HashSet::new();
+	// (commit, parentを積んだか)
+	let mut stack = vec![(tip.clone(), false)];
+	while let Some((hash, expanded)) = stack.pop() {
+		if expanded {
+			let commit = store::read_commit(repo, &hash.0)?;
+			order.push((hash, commit));
+			continue;
+		}
+		if hidden.contains(&hash) || !seen.insert(hash.clone()) {
+			continue;
+		}
+		let commit = store::read_commit(repo, &hash.0)?;
+		stack.push((hash, true));
+		for parent in commit.parents.iter().rev() {
+			let parent = Hash::from_hex(parent)
+				.ok_or_else(|| io::Error::other(format!("bad parent {}", parent)))?;
+			stack.push((parent, false));
+		}
+	}
+	Ok(order)
+}
+
+// "From <hash> <date>" から始まり、header、message、diffstat、patch、署名の順に並べる
+fn patch(
+	repo: &Repository,
+	hash: &Hash,
+	commit: &Commit,
+	n: usize,
+	total: usize,
+) -> io::Result<String> {
+	let mut out = format!("From {} {}\n", hash, MAGIC_DATE);
+	out.push_str(&mail::from_header(
+		&commit.author.name,
+		&commit.author.email,
+	));
+	out.push_str(&format!(
+		"Date: {}\n",
+		commit.author.ts.format("%a, %-d %b %Y %H:%M:%S %z")
+	));
+	let prefix = match total {
+		1 => String::from("[PATCH] "),
+		_ => format!("[PATCH {}/{}] ", n, total),
+	};
+	out.push_str(&mail::subject_header(
+		&prefix,
+		&pretty::subject(&commit.message),
+	));
+	if !commit.message.is_ascii() {
+		out.push_str("MIME-Version: 1.0\n");
+		out.push_str("Content-Type: text/plain; charset=UTF-8\n");
+		out.push_str("Content-Transfer-Encoding: 8bit\n");
+	}
+	out.push('\n');
+	out.push_str(&body(&commit.message));
+	out.push_str("---\n");
+
+	let tree = |hex: &str| -> io::Result<BTreeMap<String, diff::Side>> {
+		let tree = hex::decode(hex).map_err(|_| io::Error::other(format!("bad tree {}", hex)))?;
+		diff::tree_entries(repo, &tree)
+	};
+	let old = match commit.parents.first() {
+		Some(parent) => {
+			tree(&store::read_commit(repo, &hex::decode(parent).unwrap_or_default())?.tree)?
+		}
+		None => BTreeMap::new(),
+	};
+	let changes = diff::compare(&old, &tree(&commit.tree)?);
+	let changes = diff::detect_renames(repo, changes, &Default::default())?;
+	out.push_str(&diff::stat(repo, &changes, STAT_WIDTH)?);
+	out.push_str(&diff::summary(&changes));
+	out.push('\n');
+	for change in changes.iter() {
+		out.push_str(&diff::patch(repo, change, ("a", "b"), diff::CONTEXT)?);
+	}
+	out.push_str(&format!("-- \n{}\n\n", env!("CARGO_PKG_VERSION")));
+	Ok(out)
+}
+
+// 最初の段落より後ろ。前後の空行は除く
+fn body(message: &str) -> String {
+	let lines: Vec<&str> = message
+		.lines()
+		.skip_while(|x| x.trim().is_empty())
+		.skip_while(|x| !x.trim().is_empty())
+		.skip_while(|x| x.trim().is_empty())
+		.collect();
+	let end = lines
+		.iter()
+		.rposition(|x| !x.trim().is_empty())
+		.map_or(0, |x| x + 1);
+	lines[..end].iter().map(|x| format!("{}\n", x)).collect()
+}
+
+// gitのformat_sanitized_subjectと同じく、英数字と "." "_" 以外を "-" 1つにまとめる
+fn file_name(n: usize, subject: &str) -> String {
+	let mut name = format!("{:04}-", n);
+	let start = name.len();
+	// 最初は区切りを入れない
+	let mut space = false;
+	let mut chars = subject.chars().peekable();
+	while let Some(c) = chars.next() {
+		if c.is_ascii_alphanumeric() || c == '.' || c == '_' {
+			if space && name.len() > start {
+				name.push('-');
+			}
+			space = false;
+			name.push(c);
+			// ".." は "." にする
+			while c == '.' && chars.peek() == Some(&'.') {
+				chars.next();
+			}
+		} else {
+			space = true;
+		}
+	}
+	let trimmed = name[start..].trim_end_matches(['.', '-']).len();
+	name.truncate(start + trimmed);
+	name.truncate(NAME_MAX - ".patch".len() - 1);
+	name.push_str(".patch");
+	name
+}
@@ -0,0 +1,335 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::io::{self, Write};
+
+use super::gc::roots;
+use crate::error::GitError;
+use crate::hash::{Hash, HashAlgorithm};
+use crate::index::Index;
+use crate::object::{Commit, ObjectKind, Tag, Tree, TreeEntry};
+use crate::progress::Progress;
+use crate::refs;
+use crate::repository::Repository;
+use crate::store;
+
+// gitと同じく、問題の種類ごとのbitを合わせたものを終了コードにする
+const ERROR_OBJECT: i32 = 1; // 壊れたobject
+const ERROR_REACHABLE: i32 = 2; // 辿れるはずのobjectが無い
+
+// 指しているobject
+#[derive(Clone)]
+struct Link {
+	kind: ObjectKind,
+	hash: Hash,
+}
+
+pub struct Options {
+	pub unreachable: bool, // danglingでなく、辿れない全てのobjectを出す
+	pub dangling: bool,    // 他のどのobjectからも指されていないobjectを出す
+}
+
+// 全てのobject(looseとpack)を読み、hashが内容と合うか、commit、tree、tagとして読めるかを確かめる
+// refなどから辿って、指されているobjectが無ければ "missing"、辿れないobjectは "dangling" として出す
+// 問題があればその種類の終了コード、無ければ0
+pub fn fsck(repo: &Repository, opts: &Options, progress: &mut dyn Progress) -> io::Result<i32> {
+	let mut status = 0;
+
+	let all: HashSet<Hash> = store::objects_iter(repo)?.map(Hash).collect();
+	let mut hashes: Vec<&Hash> = all.iter().collect();
+	hashes.sort();
+
+	// objectとそれが指すobject
+	let mut objects: HashMap<Hash, (ObjectKind, Vec<Link>)> = HashMap::new();
+	progress.start("Checking objects", hashes.len());
+	for hash in hashes {
+		progress.inc(1);
+		let loose = store::object_path(repo, &hash.0);
+		let (kind, body) = match store::read_original(repo, &hash.0) {
+			Ok(x) => x,
+			Err(e) => {
+				eprintln!("error: {}: object corrupt or missing: {}", hash, e);
+				status |= ERROR_OBJECT;
+				continue;
+			}
+		};
+		if store::hash_bytes(repo, &kind.encode(&body)) != hash.0 {
+			match loose.exists() {
+				true => eprintln!(
+					"error: {}: hash-path mismatch, found at: {}",
+					hash,
+					relative(&loose)
+				),
+				false => eprintln!("error: packed {} is corrupt", hash),
+			}
+			status |= ERROR_OBJECT;
+			continue;
+		}
+		match links(kind, &body, repo.hash_algo) {
+			Ok(links) => {
+				if kind == ObjectKind::Tree {
+					for (error, message) in tree_problems(&body, repo.hash_algo) {
+						let level = if error { "error" } else { "warning" };
+						eprintln!("{} in tree {}: {}", level, hash, message);
+						if error {
+							status |= ERROR_OBJECT;
+						}
+					}
+				}
+				objects.insert(hash.clone(), (kind, links));
+			}
+			Err(e) => {
+				eprintln!("error in {} {}: {}", kind, hash, describe(&e));
+				status |= ERROR_OBJECT;
+			}
+		}
+	}
+	progress.finish();
+
+	// refとHEADが指すobjectがあるか
+	let mut tips = refs::list(repo, "")?;
+	match refs::resolve(repo, "HEAD")? {
+		Some(hash) => tips.push((String::from("HEAD"), hash)),
+		None => eprintln!(
+			"notice: HEAD points to an unborn branch ({})",
+			refs::shorten(&refs::head_target(repo)?)
+		),
+	}
+	for (name, hash) in &tips {
+		let exists = Hash::from_hex(hash).is_some_and(|x| all.contains(&x));
+		if !exists {
+			eprintln!("error: {}: invalid sha1 pointer {}", name, hash);
+			status |= ERROR_REACHABLE;
+		}
+	}
+
+	// (kind, hash)。辿れるobjectが指しているのに無いもの
+	let mut missing: HashMap<Hash, ObjectKind> = HashMap::new();
+	let mut reachable: HashSet<Hash> = HashSet::new();
+	// indexにあるblobはどのobjectからも指されていなくてよい
+	if !repo.bare {
+		for entry in Index::load(repo)?.entries {
+			let hash = Hash(entry.hash);
+			if entry.mode != 0o160000 && !all.contains(&hash) {
+				missing.insert(hash, ObjectKind::Blob);
+			}
+		}
+	}
+
+	// (指しているobject, 指されているobject)
+	let mut stack: Vec<(Option<Link>, Link)> = Vec::new();
+	for hash in roots(repo)? {
+		if let Some((kind, _)) = objects.get(&hash) {
+			stack.push((None, Link { kind: *kind, hash }));
+		}
+	}
+	let mut out = io::stdout().lock();
+	while let Some((from, Link { kind, hash })) = stack.pop() {
+		if reachable.contains(&hash) || missing.contains_key(&hash) {
+			continue;
+		}
+		// 壊れたobjectは既に出したので、その先は辿らない
+		let Some((_, links)) = objects.get(&hash) else {
+			if all.contains(&hash) {
+				reachable.insert(hash);
+				continue;
+			}
+			if let Some(from) = from {
+				writeln!(
+					out,
+					"broken link from {:>7} {}",
+					from.kind.as_str(),
+					from.hash
+				)?;
+				writeln!(out, "              to {:>7} {}", kind.as_str(), hash)?;
+			}
+			missing.insert(hash, kind);
+			continue;
+		};
+		let from = Link {
+			kind,
+			hash: hash.clone(),
+		};
+		for link in links.iter().rev() {
+			stack.push((Some(from.clone()), link.clone()));
+		}
+		reachable.insert(hash);
+	}
+	if !missing.is_empty() {
+		status |= ERROR_REACHABLE;
+	}
+
+	// 辿れないobjectが指しているものも "dangling" ではない
+	let linked: HashSet<&Hash> = objects
+		.values()
+		.flat_map(|(_, links)| links.iter().map(|x| &x.hash))
+		.collect();
+	let mut lines: Vec<(&Hash, &str, ObjectKind)> = missing
+		.iter()
+		.map(|(hash, kind)| (hash, "missing", *kind))
+		.collect();
+	for (hash, (kind, _)) in &objects {
+		if reachable.contains(hash) {
+			continue;
+		}
+		if opts.unreachable {
+			lines.push((hash, "unreachable", *kind));
+		} else if opts.dangling && !linked.contains(hash) {
+			lines.push((hash, "dangling", *kind));
+		}
+	}
+	// gitと同じく "missing" を先に出す
+	lines.sort_by_key(|(hash, what, _)| (*what != "missing", *hash));
+	for (hash, what, kind) in lines {
+		writeln!(out, "{} {} {}", what, kind, hash)?;
+	}
+	Ok(status)
+}
+
+// objectが指しているobject。commitはtreeとparent、treeはentry(submoduleを除く)、tagはそのobject
+fn links(kind: ObjectKind, body: &[u8], algo: HashAlgorithm) -> Result<Vec<Link>, GitError> {
+	let bad_hash = |x: &str| GitError::InvalidHeader {
+		offset: 0,
+		message: if x.is_empty() {
+			"missing hash"
+		} else {
+			"bad hash"
+		},
+	};
+	let link = |kind, x: &str| {
+		Hash::from_hex(x)
+			.map(|hash| Link { kind, hash })
+			.ok_or_else(|| bad_hash(x))
+	};
+	match kind {
+		ObjectKind::Blob => Ok(Vec::new()),
+		ObjectKind::Commit => {
+			let commit = Commit::from(body)?;
+			let mut links = vec![link(ObjectKind::Tree, &commit.tree)?];
+			for parent in &commit.parents {
+				links.push(link(ObjectKind::Commit, parent)?);
+			}
+			Ok(links)
+		}
+		ObjectKind::Tree => Ok(Tree::from(body, algo)?
+			.contents
+			.into_iter()
+			.filter(|x| x.mode != 160000)
+			.map(|x| Link {
+				kind: match x.is_tree() {
+					true => ObjectKind::Tree,
+					false => ObjectKind::Blob,
+				},
+				hash: Hash(x.hash),
+			})
+			.collect()),
+		ObjectKind::Tag => {
+			let tag = Tag::from(body)?;
+			Ok(vec![link(tag.kind, &tag.object)?])
+		}
+	}
+}
+
+// treeのentryの問題。(errorか, gitのfsckと同じ文)で、gitと同じ順に並べる
+// errorでないものはwarningとして出すだけで、終了コードには入れない
+fn tree_problems(body: &[u8], algo: HashAlgorithm) -> Vec<(bool, &'static str)> {
+	let Ok(tree) = Tree::from(body, algo) else {
+		return Vec::new();
+	};
+	// gitはmodeを8進数として読むので、8や9があれば読めない
+	let octal = |x: &TreeEntry| x.mode.to_string().bytes().all(|b| b < b'8');
+	if !tree.contents.iter().all(octal) {
+		return vec![(true, "badTree: cannot be parsed as a tree")];
+	}
+	// 0を前に付けたmodeはFileに読むと消えるので、元のbyte列で確かめる
+	let mut zero_padded = false;
+	let mut rest = body;
+	for _ in &tree.contents {
+		zero_padded |= rest[0] == b'0';
+		let nul = rest.iter().position(|&b| b == b'\0').unwrap_or(rest.len());
+		rest = &rest[nul + 1 + algo.hash_len()..];
+	}
+	// gitが受け付けるmode。100664は古いgitが書いていたもの
+	let modes = [100644, 100755, 100664, 120000, 40000, 160000];
+	let names: Vec<&str> = tree.contents.iter().map(|x| x.name.as_str()).collect();
+	let mut seen = HashSet::new();
+	// ディレクトリは名前の後ろに"/"があるものとして並ぶ
+	let keys: Vec<String> = tree
+		.contents
+		.iter()
+		.map(|x| match x.is_tree() {
+			true => format!("{}/", x.name),
+			false => x.name.clone(),
+		})
+		.collect();
+
+	let checks = [
+		(
+			tree.contents.iter().any(|x| x.hash.iter().all(|&b| b == 0)),
+			false,
+			"nullSha1: contains entries pointing to null sha1",
+		),
+		(
+			names.iter().any(|x| x.contains('/')),
+			false,
+			"fullPathname: contains full pathnames",
+		),
+		(
+			names.iter().any(|x| x.is_empty()),
+			false,
+			"emptyName: contains empty pathname",
+		),
+		(names.contains(&"."), false, "hasDot: contains '.'"),
+		(names.contains(&".."), false, "hasDotdot: contains '..'"),
+		(
+			names.iter().any(|x| x.eq_ignore_ascii_case(".git")),
+			false,
+			"hasDotgit: contains '.git'",
+		),
+		(
+			zero_padded,
+			false,
+			"zeroPaddedFilemode: contains zero-padded file modes",
+		),
+		(
+			tree.contents.iter().any(|x| !modes.contains(&x.mode)),
+			false,
+			"badFilemode: contains bad file modes",
+		),
+		(
+			!names.iter().all(|x| seen.insert(*x)),
+			true,
+			"duplicateEntries: contains duplicate file entries",
+		),
+		(
+			keys.windows(2).any(|x| x[0] > x[1]),
+			true,
+			"treeNotSorted: not properly sorted",
+		),
+	];
+	checks
+		.into_iter()
+		.filter(|(found, _, _)| *found)
+		.map(|(_, error, message)| (error, message))
+		.collect()
+}
+
+// gitのfsckと同じ書き方にできるものはそうする
+fn describe(e: &GitError) -> String {
+	match e {
+		GitError::BadTimestamp { .. } => {
+			String::from("badDate: invalid author/committer line - bad date")
+		}
+		GitError::BadIdent { .. } => {
+			String::from("missingEmail: invalid author/committer line - missing email")
+		}
+		e => e.to_string(),
+	}
+}
+
+// 今のディレクトリからのpath。その外なら絶対path
+fn relative(path: &std::path::Path) -> String {
+	let relative = env::current_dir()
+		.ok()
+		.and_then(|x| path.strip_prefix(x).ok().map(|x| x.to_path_buf()));
+	relative.as_deref().unwrap_or(path).display().to_string()
+}
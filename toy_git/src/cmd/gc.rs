@@ -0,0 +1,219 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::time::SystemTime;
+
+use chrono::{DateTime, FixedOffset, Utc};
+
+use super::multi_pack_index::multi_pack_index_write;
+use super::pack_objects::write_pack;
+use super::prune_packed::prune;
+use crate::config::Config;
+use crate::date;
+use crate::gc_lock::GcLock;
+use crate::hash::Hash;
+use crate::index::Index;
+use crate::pack;
+use crate::progress::Progress;
+use crate::reachable;
+use crate::refs;
+use crate::repository::Repository;
+use crate::store;
+
+// gitの既定値
+const AUTO_THRESHOLD: i64 = 6700;
+const AUTO_PACK_LIMIT: i64 = 50;
+const PRUNE_EXPIRE: &str = "2.weeks.ago";
+
+pub struct Options {
+	pub auto: bool,            // 多すぎるときだけまとめる
+	pub force: bool,           // 他のgcが動いていても行う
+	pub prune: Option<String>, // これより古い辿れないobjectを消す。無ければgc.pruneExpire
+}
+
+// refなどから辿れるobjectを1つのpackにまとめ、古いpackとpackに入ったlooseなobjectを消す
+// 辿れないobjectは、pruneの期限より古ければ消し、新しければlooseなobjectとして残す
+// packに入っていたものはpackのmtimeのlooseなobjectにする。期限が "never" なら全てpackに入れる
+// ".keep" のあるpackはそのまま残す
+// autoなら、looseなobjectがgc.autoより多いか、packがgc.autoPackLimitより多いときだけまとめる
+// (どちらも0ならその条件では行わない)
+// 他のgcが動いている間はError(autoなら何もしない)。forceなら構わず行う
+pub fn gc(repo: &Repository, opts: &Options, progress: &mut dyn Progress) -> io::Result<()> {
+	let (auto, force) = (opts.auto, opts.force);
+	let _lock = match GcLock::acquire(repo, force) {
+		Ok(lock) => lock,
+		Err(e) if auto && e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+		Err(e) => return Err(e),
+	};
+	let packs: Vec<pack::Pack> = pack::packs(repo)?
+		.into_iter()
+		.filter(|x| !x.path.with_extension("keep").exists())
+		.collect();
+	let loose = store::loose_objects(repo)?;
+	let config = Config::load(repo)?;
+
+	if auto {
+		let threshold = config.get_int("gc.auto")?.unwrap_or(AUTO_THRESHOLD);
+		let pack_limit = config
+			.get_int("gc.autoPackLimit")?
+			.unwrap_or(AUTO_PACK_LIMIT);
+		let too_many_loose = threshold > 0 && loose.len() as i64 > threshold;
+		let too_many_packs = pack_limit > 0 && packs.len() as i64 > pack_limit;
+		if !too_many_loose && !too_many_packs {
+			return Ok(());
+		}
+		eprintln!("Auto packing the repository for optimum performance.");
+		eprintln!("See \"git help gc\" for manual housekeeping.");
+	}
+
+	let expire = opts
+		.prune
+		.as_deref()
+		.or(config.get("gc.pruneExpire"))
+		.unwrap_or(PRUNE_EXPIRE);
+	let expire = date::parse_expiry(expire).ok_or_else(|| {
+		io::Error::other(format!("failed to parse prune expiry value {}", expire))
+	})?;
+
+	let mut hashes: Vec<Hash> = packs
+		.iter()
+		.flat_map(|x| x.index.entries.iter().map(|x| Hash(x.hash.clone())))
+		.chain(loose.iter().cloned().map(Hash))
+		.collect();
+	if hashes.is_empty() {
+		return Ok(());
+	}
+
+	if let Some(expire) = expire {
+		let reachable: HashSet<Hash> = reachable::objects(repo, &roots(repo)?, &[])?
+			.into_iter()
+			.collect();
+		let loose: HashSet<Hash> = loose.into_iter().map(Hash).collect();
+		let unreachable: HashSet<&Hash> =
+			hashes.iter().filter(|x| !reachable.contains(x)).collect();
+		for hash in unreachable {
+			match loose.contains(hash) {
+				true => expire_loose(repo, hash, expire)?,
+				false => loosen(repo, &packs, hash, expire)?,
+			}
+		}
+		hashes.retain(|x| reachable.contains(x));
+	}
+
+	let prefix = repo.objects_dir().join("pack").join("pack");
+	let name = match hashes.is_empty() {
+		true => String::new(),
+		false => write_pack(repo, &hashes, &prefix, progress)?,
+	};
+
+	// 同じ内容のpackは書いたpackそのものなので残す
+	let mut removed = false;
+	for old in packs {
+		if old.path.file_stem().and_then(|x| x.to_str()) == Some(&format!("pack-{}", name)) {
+			continue;
+		}
+		fs::remove_file(old.path.with_extension("idx"))?;
+		fs::remove_file(&old.path)?;
+		removed = true;
+	}
+	if removed {
+		update_multi_pack_index(repo)?;
+	}
+	prune(repo, false)
+}
+
+// packを消した後、multi-pack-indexがあれば残ったpackで書き直す。packが残っていなければ消す
+fn update_multi_pack_index(repo: &Repository) -> io::Result<()> {
+	let path = repo.objects_dir().join("pack").join("multi-pack-index");
+	if !path.exists() {
+		return Ok(());
+	}
+	match pack::packs(repo)?.is_empty() {
+		true => fs::remove_file(&path),
+		false => multi_pack_index_write(repo),
+	}
+}
+
+// 辿れるobjectを探し始めるところ。全てのref、HEAD、reflogに書かれたもの、indexにあるもの
+// linked worktreeのHEADとindexも含める
+pub(super) fn roots(repo: &Repository) -> io::Result<Vec<Hash>> {
+	let refs = refs::list(repo, "")?;
+	let mut roots: Vec<Hash> = refs
+		.iter()
+		.map(|(_, x)| x.clone())
+		.chain(refs::resolve(repo, "HEAD")?)
+		.filter_map(|x| Hash::from_hex(&x))
+		.collect();
+	for name in refs.iter().map(|(x, _)| x.as_str()).chain(["HEAD"]) {
+		for entry in refs::read_reflog(repo, name)? {
+			roots.push(entry.old);
+			roots.push(entry.new);
+		}
+	}
+	if !repo.bare {
+		roots.extend(Index::load(repo)?.entries.into_iter().map(|x| Hash(x.hash)));
+	}
+
+	let mut gitdirs = vec![repo.commondir.clone()];
+	match fs::read_dir(repo.commondir.join("worktrees")) {
+		Ok(entries) => {
+			for entry in entries {
+				gitdirs.push(entry?.path());
+			}
+		}
+		Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+		Err(e) => return Err(e),
+	}
+	for dir in gitdirs.iter().filter(|x| **x != repo.gitdir) {
+		// symbolic refならそのrefは既に含めている
+		if let Ok(head) = fs::read_to_string(dir.join("HEAD")) {
+			roots.extend(Hash::from_hex(head.trim_end()));
+		}
+		if let Ok(bytes) = fs::read(dir.join("index")) {
+			let index =
+				Index::from(&bytes, repo.hash_algo).map_err(|e| e.context("index file corrupt"))?;
+			roots.extend(index.entries.into_iter().map(|x| Hash(x.hash)));
+		}
+	}
+
+	// 0のhash(reflogでrefを作った、または消したことを表す)や、既に消えたobjectは除く
+	let mut found = Vec::new();
+	for hash in roots {
+		if store::has_object(repo, &hash.0)? {
+			found.push(hash);
+		}
+	}
+	Ok(found)
+}
+
+// 辿れないlooseなobjectを、期限より古ければ消す
+fn expire_loose(repo: &Repository, hash: &Hash, expire: DateTime<FixedOffset>) -> io::Result<()> {
+	let path = store::object_path(repo, &hash.0);
+	if DateTime::<Utc>::from(fs::metadata(&path)?.modified()?) < expire {
+		fs::remove_file(&path)?;
+	}
+	Ok(())
+}
+
+// 辿れないpackのobjectを、packが期限より新しければpackのmtimeのlooseなobjectにする
+// 古ければpackと一緒に消えるので何もしない
+fn loosen(
+	repo: &Repository,
+	packs: &[pack::Pack],
+	hash: &Hash,
+	expire: DateTime<FixedOffset>,
+) -> io::Result<()> {
+	let mut mtime = SystemTime::UNIX_EPOCH;
+	for pack in packs.iter().filter(|x| x.index.find(&hash.0).is_some()) {
+		mtime = mtime.max(fs::metadata(&pack.path)?.modified()?);
+	}
+	if DateTime::<Utc>::from(mtime) < expire {
+		return Ok(());
+	}
+	let (kind, body) = store::read_original(repo, &hash.0)?;
+	store::write_loose(repo, &hash.0, &kind.encode(&body))?;
+	fs::File::options()
+		.write(true)
+		.open(store::object_path(repo, &hash.0))?
+		.set_modified(mtime)
+}
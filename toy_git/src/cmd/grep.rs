@@ -0,0 +1,86 @@
+use std::io::{self, Write};
+
+use crate::diff::{self, Side};
+use crate::index::Index;
+use crate::object::{self, ObjectKind};
+use crate::regex::Regex;
+use crate::repository::Repository;
+use crate::revparse;
+use crate::store;
+use crate::worktree::Worktree;
+
+pub struct Options {
+	pub ignore_case: bool,        // -i
+	pub files_with_matches: bool, // -l ファイル名だけを出す
+	pub extended: bool,           // -E
+	pub cached: bool,             // working directoryではなくindexの内容を探す
+}
+
+// 追跡しているファイルの中からpatternに一致する行を "<path>:<lineno>:<line>" の形で出力する
+// revがあればそのcommitのtreeを探し、出力の先頭に "<rev>:" を付ける
+// binaryのファイルは飛ばす
+pub fn grep(repo: &Repository, pattern: &str, rev: Option<&str>, opts: &Options) -> io::Result<()> {
+	let regex = Regex::new(pattern, opts.extended, opts.ignore_case)
+		.ok_or_else(|| io::Error::other(format!("invalid regular expression: {}", pattern)))?;
+	if rev.is_none() && !opts.cached {
+		repo.require_worktree()?;
+	}
+
+	let (entries, prefix) = match rev {
+		Some(rev) => {
+			let hash = revparse::resolve(repo, rev)?;
+			let tree = revparse::peel(repo, hash, Some(ObjectKind::Tree))?;
+			(diff::tree_entries(repo, &tree.0)?, format!("{}:", rev))
+		}
+		None => (diff::index_entries(&Index::load(repo)?), String::new()),
+	};
+	let worktree = Worktree::new(repo);
+
+	for (path, side) in entries {
+		// gitlinkの中身はこのrepositoryには無い
+		if side.mode == 0o160000 {
+			continue;
+		}
+		let Some(content) = read(&worktree, &path, &side, rev.is_none() && !opts.cached)? else {
+			continue;
+		};
+		if object::is_binary(&content) {
+			continue;
+		}
+
+		let content = String::from_utf8_lossy(&content);
+		let mut lines = content
+			.lines()
+			.enumerate()
+			.filter(|(_, line)| regex.is_match(line));
+
+		let mut out = io::stdout().lock();
+		if opts.files_with_matches {
+			if lines.next().is_some() {
+				writeln!(out, "{}{}", prefix, path)?;
+			}
+			continue;
+		}
+		for (i, line) in lines {
+			writeln!(out, "{}{}:{}:{}", prefix, path, i + 1, line)?;
+		}
+	}
+	Ok(())
+}
+
+// working directoryから読む場合、消えているファイルはNone
+fn read(
+	worktree: &Worktree,
+	path: &str,
+	side: &Side,
+	from_worktree: bool,
+) -> io::Result<Option<Vec<u8>>> {
+	if !from_worktree {
+		return Ok(Some(store::read_object(worktree.repo, &side.hash)?.1));
+	}
+	match worktree.read_file(path) {
+		Ok(content) => Ok(Some(content)),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+		Err(e) => Err(e),
+	}
+}
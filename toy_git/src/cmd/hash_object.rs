@@ -0,0 +1,90 @@
+use std::fs;
+use std::io::{self, Read, Write};
+
+use crate::hash::HashAlgorithm;
+use crate::object::{GitObject, ObjectKind};
+use crate::repository::Repository;
+use crate::store;
+
+// stdinと各pathの内容をそれぞれobjectとしてhashし、1行に1つずつ出力する
+// repositoryの中ならそのhash(extensions.objectFormat)、外ならSHA-1を使い、writeなら(-w)objectとして書き込みも行う
+// literallyなら、gitと同じく壊れたobjectを作れるように、kindも内容も確かめない
+pub fn hash_object(
+	repo: Option<&Repository>,
+	write: bool,
+	kind: &str,
+	literally: bool,
+	stdin: bool,
+	paths: &[String],
+) -> io::Result<()> {
+	if !literally && ObjectKind::from(kind).is_none() {
+		return Err(io::Error::other(format!(
+			"invalid object type \"{}\"",
+			kind
+		)));
+	}
+
+	let algo = repo.map_or(HashAlgorithm::Sha1, |x| x.hash_algo);
+	let repo = repo.filter(|_| write);
+	let mut out = io::stdout().lock();
+	if stdin {
+		let mut body = Vec::new();
+		io::stdin().read_to_end(&mut body)?;
+		writeln!(
+			out,
+			"{}",
+			hex::encode(hash(repo, algo, kind, literally, &body)?)
+		)?;
+	}
+
+	for path in paths {
+		// blobは確かめる内容が無いので、大きなファイルでもmemoryに載せずに読みながらhashする
+		if kind == "blob" {
+			let mut file = fs::File::open(path)?;
+			let size = file.metadata()?.len() as usize;
+			let hash = match repo {
+				Some(repo) => store::write_stream(repo, ObjectKind::Blob, size, &mut file)?,
+				None => store::hash_stream(algo, ObjectKind::Blob, size, &mut file)?,
+			};
+			writeln!(out, "{}", hex::encode(hash))?;
+			continue;
+		}
+		let body = fs::read(path)?;
+		writeln!(
+			out,
+			"{}",
+			hex::encode(hash(repo, algo, kind, literally, &body)?)
+		)?;
+	}
+
+	Ok(())
+}
+
+// repoがあれば書き込む
+fn hash(
+	repo: Option<&Repository>,
+	algo: HashAlgorithm,
+	kind: &str,
+	literally: bool,
+	body: &[u8],
+) -> io::Result<Vec<u8>> {
+	// blob以外はその型としてparseできる内容でなければならない
+	let valid = match ObjectKind::from(kind) {
+		_ if literally => true,
+		Some(kind) => GitObject::from(kind, body, algo).is_ok(),
+		None => false,
+	};
+	if !valid {
+		return Err(io::Error::new(
+			io::ErrorKind::InvalidData,
+			format!("corrupt {}", kind),
+		));
+	}
+
+	let header = format!("{} {}\0", kind, body.len());
+	let bytes = [header.as_bytes(), body].concat();
+	match repo {
+		Some(repo) => store::write_object(repo, &bytes),
+		None => Ok(algo.digest(&bytes)),
+	}
+}
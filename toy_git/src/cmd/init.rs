@@ -0,0 +1,207 @@
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::hash::HashAlgorithm;
+use crate::refs;
+
+pub struct Options {
+	pub bare: bool,                     // pathそのものをrepositoryのディレクトリにする
+	pub template: Option<String>,       // 空文字列ならtemplateを使わない
+	pub initial_branch: Option<String>, // HEADが指すbranch。無ければinit.defaultBranchかmain
+	pub quiet: bool,                    // 作ったことを表示しない
+	pub object_format: Option<HashAlgorithm>, // --object-format。無ければGIT_DEFAULT_HASH、それも無ければSHA-1
+}
+
+// templateが無いときに置くファイル。hookは".sample"を付けて無効にしておく
+const DEFAULT_TEMPLATE: &[(&str, &str, u32)] = &[
+	(
+		"description",
+		"Unnamed repository; edit this file 'description' to name the repository.\n",
+		0o644,
+	),
+	(
+		"info/exclude",
+		"# git ls-files --others --exclude-from=.git/info/exclude\n\
+		 # Lines that start with '#' are comments.\n",
+		0o644,
+	),
+	(
+		"hooks/pre-commit.sample",
+		"#!/bin/sh\n\
+		 #\n\
+		 # To enable this hook, rename this file to \"pre-commit\".\n\
+		 # It is called by \"git commit\" with no arguments, and a non-zero exit\n\
+		 # status stops the commit.\n\
+		 \n\
+		 # Stop if a staged change adds trailing whitespace\n\
+		 exec git diff --cached --check --\n",
+		0o755,
+	),
+	(
+		"hooks/commit-msg.sample",
+		"#!/bin/sh\n\
+		 #\n\
+		 # To enable this hook, rename this file to \"commit-msg\".\n\
+		 # It is called with the name of the file that has the commit message, and\n\
+		 # a non-zero exit status stops the commit.\n\
+		 \n\
+		 # Stop if the message has the same Signed-off-by line twice\n\
+		 test \"\" = \"$(grep '^Signed-off-by: ' \"$1\" | sort | uniq -c | sed -e '/^[ \t]*1[ \t]/d')\" || {\n\
+		 \techo >&2 Duplicate Signed-off-by lines.\n\
+		 \texit 1\n\
+		 }\n",
+		0o755,
+	),
+];
+
+// pathにrepositoryを作る(bareでなければpath/.git)。既にあれば足りないtemplateのファイルだけを置く
+// templateは--template、GIT_TEMPLATE_DIR、init.templateDirの順に探し、どれも無ければ既定のものを使う
+// templateのファイルは既にあるファイルを上書きせずにそのままコピーする
+// 最初のbranchはinitial_branch、init.defaultBranch、mainの順に決める。既にあるrepositoryのHEADは変えない
+// SHA-1以外のhashではrepositoryformatversionを1にしてextensions.objectFormatに書く。既にあるrepositoryのhashは変えられない
+pub fn init(path: &Path, opts: &Options) -> io::Result<()> {
+	let branch = match &opts.initial_branch {
+		Some(branch) if refs::validate_ref_name(&format!("refs/heads/{}", branch)).is_err() => {
+			return Err(io::Error::other(format!(
+				"invalid initial branch name: '{}'",
+				branch
+			)));
+		}
+		Some(branch) => branch.clone(),
+		None => match Config::load_outside()?.get("init.defaultbranch") {
+			Some(branch) if refs::validate_ref_name(&format!("refs/heads/{}", branch)).is_err() => {
+				return Err(io::Error::other(format!(
+					"invalid branch name: init.defaultBranch = {}",
+					branch
+				)));
+			}
+			Some(branch) => String::from(branch),
+			None => String::from("main"),
+		},
+	};
+
+	fs::create_dir_all(path)?;
+	let path = path.canonicalize()?;
+	let gitdir = if opts.bare { path } else { path.join(".git") };
+	let reinit = gitdir.join("HEAD").is_file();
+
+	let algo = match opts.object_format {
+		Some(algo) => algo,
+		None => match env::var("GIT_DEFAULT_HASH") {
+			Ok(x) => HashAlgorithm::from_name(&x)
+				.ok_or_else(|| io::Error::other(format!("unknown hash algorithm '{}'", x)))?,
+			Err(_) => HashAlgorithm::Sha1,
+		},
+	};
+	if let (true, Some(algo)) = (reinit, opts.object_format) {
+		let config = Config::load_file(&gitdir.join("config"))?;
+		let current = config
+			.get("extensions.objectformat")
+			.and_then(HashAlgorithm::from_name)
+			.unwrap_or_default();
+		if algo != current {
+			return Err(io::Error::other(
+				"attempt to reinitialize repository with different hash",
+			));
+		}
+	}
+
+	let template = match &opts.template {
+		Some(x) => Some(x.clone()),
+		None => match env::var("GIT_TEMPLATE_DIR") {
+			Ok(x) => Some(x),
+			Err(_) => Config::load_outside()?
+				.get("init.templatedir")
+				.map(String::from),
+		},
+	};
+	match template.as_deref() {
+		Some("") => fs::create_dir_all(&gitdir)?,
+		Some(dir) if Path::new(dir).is_dir() => copy_template(Path::new(dir), &gitdir)?,
+		Some(dir) => {
+			eprintln!("warning: templates not found in {}", dir);
+			fs::create_dir_all(&gitdir)?;
+		}
+		None => {
+			for (name, content, mode) in DEFAULT_TEMPLATE {
+				create_file(&gitdir.join(name), content.as_bytes(), *mode)?;
+			}
+		}
+	}
+
+	for dir in ["objects/info", "objects/pack", "refs/heads", "refs/tags"] {
+		fs::create_dir_all(gitdir.join(dir))?;
+	}
+	if let (true, Some(branch)) = (reinit, &opts.initial_branch) {
+		eprintln!("warning: re-init: ignored --initial-branch={}", branch);
+	}
+	if !reinit {
+		fs::write(gitdir.join("HEAD"), format!("ref: refs/heads/{}\n", branch))?;
+
+		// templateのconfigがあれば、その後に書いて優先させる
+		let mut config = fs::OpenOptions::new()
+			.create(true)
+			.append(true)
+			.open(gitdir.join("config"))?;
+		let version = match algo {
+			HashAlgorithm::Sha1 => 0,
+			_ => 1,
+		};
+		write!(
+			config,
+			"[core]\n\trepositoryformatversion = {}\n\tfilemode = true\n\tbare = {}\n",
+			version, opts.bare
+		)?;
+		if !opts.bare {
+			writeln!(config, "\tlogallrefupdates = true")?;
+		}
+		if algo != HashAlgorithm::Sha1 {
+			write!(config, "[extensions]\n\tobjectformat = {}\n", algo.name())?;
+		}
+	}
+
+	let kind = if reinit {
+		"Reinitialized existing"
+	} else {
+		"Initialized empty"
+	};
+	if !opts.quiet {
+		writeln!(
+			io::stdout().lock(),
+			"{} Git repository in {}/",
+			kind,
+			gitdir.display()
+		)?;
+	}
+	Ok(())
+}
+
+// templateの中身をディレクトリごとdstにコピーする。ファイルの権限もそのまま残す
+fn copy_template(src: &Path, dst: &Path) -> io::Result<()> {
+	fs::create_dir_all(dst)?;
+	for entry in fs::read_dir(src)? {
+		let entry = entry?;
+		let target = dst.join(entry.file_name());
+		if entry.file_type()?.is_dir() {
+			copy_template(&entry.path(), &target)?;
+		} else if !target.exists() {
+			fs::copy(entry.path(), &target)?;
+		}
+	}
+	Ok(())
+}
+
+fn create_file(path: &Path, content: &[u8], mode: u32) -> io::Result<()> {
+	if path.exists() {
+		return Ok(());
+	}
+	if let Some(dir) = path.parent() {
+		fs::create_dir_all(dir)?;
+	}
+	fs::write(path, content)?;
+	fs::set_permissions(path, fs::Permissions::from_mode(mode))
+}
@@ -0,0 +1,101 @@
+// messageの最後の段落にある "Signed-off-by: X" のようなtrailerの後ろにtrailersを加える
+// 最後のtrailerとtokenもvalueも同じもの(大文字小文字は区別しない)は加えない
+// trailerの段落が無ければ空行を挟んで新しく作る。最初の段落はtitleなのでtrailerの段落にはならない
+// 末尾の空行とコメント行はtrailerの後ろに残す
+pub fn interpret_trailers(input: &str, trailers: &[(String, String)]) -> String {
+	let lines: Vec<&str> = input.lines().collect();
+	let end = lines.len()
+		- lines
+			.iter()
+			.rev()
+			.take_while(|x| x.trim().is_empty() || x.starts_with('#'))
+			.count();
+
+	let mut out = String::new();
+	let mut block = match block_start(&lines[..end]) {
+		Some(start) => {
+			lines[..start].iter().for_each(|x| push_line(&mut out, x));
+			parse(&lines[start..end])
+		}
+		None => {
+			lines[..end].iter().for_each(|x| push_line(&mut out, x));
+			if !trailers.is_empty() {
+				out.push('\n');
+			}
+			Vec::new()
+		}
+	};
+
+	for (token, value) in trailers {
+		let same = |x: &Line| match x {
+			Line::Trailer(t, v) => t.eq_ignore_ascii_case(token) && v.eq_ignore_ascii_case(value),
+			Line::Other(_) => false,
+		};
+		let last = block.iter().rev().find(|x| matches!(x, Line::Trailer(..)));
+		if !last.is_some_and(same) {
+			block.push(Line::Trailer(token.clone(), value.clone()));
+		}
+	}
+
+	for line in block {
+		match line {
+			Line::Trailer(token, value) => push_line(&mut out, &format!("{}: {}", token, value)),
+			Line::Other(line) => push_line(&mut out, &line),
+		}
+	}
+	lines[end..].iter().for_each(|x| push_line(&mut out, x));
+	out
+}
+
+fn push_line(out: &mut String, line: &str) {
+	out.push_str(line);
+	out.push('\n');
+}
+
+enum Line {
+	Trailer(String, String), // valueの続きの行は "\n" でつないで含める
+	Other(String),           // コメント行
+}
+
+// 最後の段落がすべてtrailer(とその続きの行、コメント行)ならその最初の行の位置
+fn block_start(lines: &[&str]) -> Option<usize> {
+	let start = lines.iter().rposition(|x| x.trim().is_empty())? + 1;
+	if !lines[..start].iter().any(|x| !x.trim().is_empty()) || start == lines.len() {
+		return None;
+	}
+	let mut trailer = false;
+	for line in lines[start..].iter() {
+		match split(line) {
+			Some(_) => trailer = true,
+			None if line.starts_with('#') => {}
+			None if trailer && line.starts_with([' ', '\t']) => {}
+			None => return None,
+		}
+	}
+	Some(start)
+}
+
+fn parse(lines: &[&str]) -> Vec<Line> {
+	let mut block: Vec<Line> = Vec::new();
+	for line in lines {
+		match (split(line), block.last_mut()) {
+			(Some((token, value)), _) => {
+				block.push(Line::Trailer(String::from(token), String::from(value)))
+			}
+			(None, _) if line.starts_with('#') => block.push(Line::Other(String::from(*line))),
+			(None, Some(Line::Trailer(_, value))) => value.push_str(&format!("\n{}", line)),
+			(None, _) => block.push(Line::Other(String::from(*line))),
+		}
+	}
+	block
+}
+
+// "Token: value" を(token, value)に分ける。tokenは英数字と "-" だけで、":" の前に空白があってもよい
+fn split(line: &str) -> Option<(&str, &str)> {
+	let (token, value) = line.split_once(':')?;
+	let token = token.trim_end();
+	if token.is_empty() || !token.chars().all(|x| x.is_ascii_alphanumeric() || x == '-') {
+		return None;
+	}
+	Some((token, value.trim()))
+}
@@ -0,0 +1,361 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+use chrono::{DateTime, FixedOffset};
+
+use crate::diff;
+use crate::graph::Graph;
+use crate::hash::Hash;
+use crate::notes;
+use crate::object::{Commit, ObjectKind};
+use crate::pretty::{self, Format};
+use crate::refs;
+use crate::regex::Regex;
+use crate::repository::Repository;
+use crate::revparse;
+use crate::store;
+use crate::walker::CommitWalker;
+
+use super::rev_list::{self, ref_tips, Order};
+
+pub struct Options {
+	pub format: Format,
+	pub graph: bool,                          // 左側にcommitのつながりを描く
+	pub author: Option<String>,               // "name <email>" がこの正規表現に一致するものだけ
+	pub since: Option<DateTime<FixedOffset>>, // committerの日付がこれ以降のものだけ
+	pub until: Option<DateTime<FixedOffset>>, // committerの日付がこれ以前のものだけ
+	pub patch: bool,                          // 各commitの後に最初のparentからのdiffを出す
+	pub paths: Vec<String>,                   // 空でなければ、これらのpathを変えたcommitだけ
+	pub follow: bool,                         // pathが1つのとき、renameされる前の名前も辿る
+	pub notes: bool,                          // messageの後にrefs/notes/commitsのnoteを付ける
+	pub order: Order,                         // graphでWalkならTopoにする
+	pub max_count: Option<usize>,             // 条件に合うcommitをこの数だけ出す
+}
+
+// revsから辿れるcommitを新しい順にformatで出力する。revsが空ならHEADから
+// --all、--branches、--tagsはそれらのrefの全てから辿る
+// 条件に合わないcommitも、そのparentは辿る
+// pathsがあれば、gitと同じくpathsの内容が同じparentがあるmergeはそのparentだけを辿る
+pub fn log(repo: &Repository, revs: &[String], opts: &Options) -> io::Result<()> {
+	if opts.follow && opts.paths.len() != 1 {
+		return Err(io::Error::other("--follow requires exactly one pathspec"));
+	}
+	let author = match &opts.author {
+		Some(x) => Some(
+			Regex::new(x, false, false)
+				.ok_or_else(|| io::Error::other(format!("invalid regular expression: {}", x)))?,
+		),
+		None => None,
+	};
+
+	let tips = match revs {
+		[] => match refs::resolve(repo, "HEAD")? {
+			Some(_) => vec![revparse::resolve(repo, "HEAD")?],
+			None => {
+				let branch = refs::shorten(&refs::head_target(repo)?).to_string();
+				return Err(io::Error::other(format!(
+					"your current branch '{}' does not have any commits yet",
+					branch
+				)));
+			}
+		},
+		revs => {
+			let mut tips = Vec::new();
+			for rev in revs {
+				match ref_tips(repo, rev)? {
+					// commitを指していないrefは無視する
+					Some(refs) => tips.extend(refs.into_iter().filter_map(|(_, hash)| {
+						revparse::peel(repo, hash, Some(ObjectKind::Commit)).ok()
+					})),
+					None => tips.push(revparse::resolve(repo, rev)?),
+				}
+			}
+			tips
+		}
+	};
+
+	let mut walk = Walk::new(repo, opts.paths.clone(), opts.follow);
+	for tip in tips {
+		walk.push(tip)?;
+	}
+	let mut entries = Vec::new();
+	while let Some(entry) = walk.next()? {
+		entries.push(entry);
+	}
+	if opts.graph && !opts.paths.is_empty() {
+		entries = rewrite_parents(entries);
+	}
+	match opts.order {
+		Order::Walk if opts.graph => entries = sort(entries, Order::Topo),
+		Order::Walk => {}
+		order => entries = sort(entries, order),
+	}
+
+	entries.retain(|entry| {
+		let (who, ts) = (&entry.commit.author, entry.commit.committer.ts);
+		entry.changed
+			&& author
+				.as_ref()
+				.is_none_or(|x| x.is_match(&format!("{} <{}>", who.name, who.email)))
+			&& opts.since.is_none_or(|x| ts >= x)
+			&& opts.until.is_none_or(|x| ts <= x)
+	});
+	if let Some(n) = opts.max_count {
+		entries.truncate(n);
+	}
+
+	let notes = match opts.notes {
+		true => notes::list(repo)?,
+		false => BTreeMap::new(),
+	};
+
+	let mut out = io::stdout().lock();
+	let format = &opts.format;
+	let mut graph = opts.graph.then(Graph::new);
+	for (i, entry) in entries.iter().enumerate() {
+		let mut text = pretty::format(format, &entry.hash, &entry.commit, &entry.header);
+		if let Some(blob) = notes.get(&entry.hash) {
+			let note = store::read_object(repo, blob)?.1;
+			text.push_str(&format_note(format, &String::from_utf8_lossy(&note)));
+		}
+		if opts.patch {
+			let patch = patch(repo, &entry.commit, &entry.paths)?;
+			if !patch.is_empty() {
+				if !text.ends_with('\n') {
+					text.push('\n');
+				}
+				// oneline以外はheaderとdiffの間を1行空ける
+				if !matches!(format, Format::Oneline) {
+					text.push('\n');
+				}
+				text.push_str(&patch);
+			}
+		}
+		let Some(graph) = graph.as_mut() else {
+			if i > 0 {
+				out.write_all(format.separator().as_bytes())?;
+			}
+			out.write_all(text.as_bytes())?;
+			continue;
+		};
+
+		// 線の横に1行ずつ出す。format:の区切りも改行として扱う
+		if i > 0 && !format.separator().is_empty() && !matches!(format, Format::Custom { .. }) {
+			writeln!(out, "{}", graph.padding())?;
+		}
+		let rows = graph.next(&entry.hash, &entry.commit.parents);
+		let lines: Vec<&str> = text.lines().collect();
+		for j in 0..lines.len().max(rows.len()) {
+			let prefix = rows.get(j).cloned().unwrap_or_else(|| graph.padding());
+			writeln!(out, "{}{}", prefix, lines.get(j).unwrap_or(&""))?;
+		}
+	}
+	Ok(())
+}
+
+// gitと同じく、onelineでは直後に、それ以外は1行空けて字下げしたnoteを出す
+// format:で指定した形式には付けない
+fn format_note(format: &Format, note: &str) -> String {
+	let lines: String = note.lines().map(|x| format!("    {}\n", x)).collect();
+	match format {
+		Format::Oneline => format!("Notes:\n{}\n", lines),
+		Format::Custom { .. } => String::new(),
+		_ => format!("\nNotes:\n{}", lines),
+	}
+}
+
+// 最初のparent(root commitなら空のtree)からの変更のpatch。mergeのcommitには出さない
+// pathsが空でなければ、そのどれかの変更だけ
+fn patch(repo: &Repository, commit: &Commit, paths: &[String]) -> io::Result<String> {
+	let tree = |hex: &str| -> io::Result<BTreeMap<String, diff::Side>> {
+		let commit = store::read_commit(repo, &hex::decode(hex).unwrap_or_default())?;
+		diff::tree_entries(repo, &hex::decode(&commit.tree).unwrap_or_default())
+	};
+	let old = match commit.parents.as_slice() {
+		[] => BTreeMap::new(),
+		[parent] => tree(parent)?,
+		_ => return Ok(String::new()),
+	};
+	let new = diff::tree_entries(repo, &hex::decode(&commit.tree).unwrap_or_default())?;
+
+	let mut out = String::new();
+	let changes = diff::detect_renames(repo, diff::compare(&old, &new), &Default::default())?;
+	for change in changes.iter() {
+		if !paths.is_empty() && !within(paths, &change.path) && !within(paths, &change.old_path) {
+			continue;
+		}
+		out.push_str(&diff::patch(repo, change, ("a", "b"), diff::CONTEXT)?);
+	}
+	Ok(out)
+}
+
+// pathがpathsのどれかと同じか、その下にあるか
+fn within(paths: &[String], path: &str) -> bool {
+	paths.iter().any(|x| {
+		x.is_empty()
+			|| path == x
+			|| path
+				.strip_prefix(x.as_str())
+				.is_some_and(|x| x.starts_with('/'))
+	})
+}
+
+// 出さないcommitを取り除き、parentをその先で最初に出すcommitにつなぎ直す(graphの線のため)
+fn rewrite_parents(entries: Vec<Entry>) -> Vec<Entry> {
+	let followed: HashMap<String, (bool, Vec<String>)> = entries
+		.iter()
+		.map(|x| (x.hash.clone(), (x.changed, x.parents.clone())))
+		.collect();
+
+	let mut entries: Vec<Entry> = entries.into_iter().filter(|x| x.changed).collect();
+	for entry in entries.iter_mut() {
+		let mut parents: Vec<String> = Vec::new();
+		for parent in entry.parents.iter() {
+			// 出さないcommitが辿るparentは1つ以下
+			let mut hash = parent;
+			let found = loop {
+				match followed.get(hash) {
+					Some((true, _)) => break Some(hash),
+					Some((false, next)) if !next.is_empty() => hash = &next[0],
+					_ => break None,
+				}
+			};
+			if let Some(hash) = found.filter(|x| !parents.contains(x)) {
+				parents.push(hash.clone());
+			}
+		}
+		entry.commit.parents = parents;
+	}
+	entries
+}
+
+// 辿った順に並べたentriesをorderの順に並べ替える
+fn sort(entries: Vec<Entry>, order: Order) -> Vec<Entry> {
+	let commits: Vec<(String, Vec<String>, i64)> = entries
+		.iter()
+		.map(|x| {
+			let ts = x.commit.committer.ts.timestamp();
+			(x.hash.clone(), x.commit.parents.clone(), ts)
+		})
+		.collect();
+	let mut entries: Vec<Option<Entry>> = entries.into_iter().map(Some).collect();
+	rev_list::sort(&commits, order)
+		.into_iter()
+		.filter_map(|i| entries[i].take())
+		.collect()
+}
+
+struct Entry {
+	hash: String,
+	commit: Commit,
+	header: String,       // commit objectのheader部分
+	changed: bool,        // pathsを変えたか(pathsが空なら常にtrue)
+	parents: Vec<String>, // 辿ったparent
+	paths: Vec<String>,   // このcommitを見たときのpaths(followで名前が変わる)
+}
+
+// committerの日付が新しいものから順に取り出す。同じ日付なら先に見つけたもの
+struct Walk<'a> {
+	repo: &'a Repository,
+	walker: CommitWalker<'a>,
+	paths: Vec<String>,
+	follow: bool,
+}
+
+impl<'a> Walk<'a> {
+	fn new(repo: &'a Repository, paths: Vec<String>, follow: bool) -> Self {
+		Self {
+			repo,
+			walker: CommitWalker::new(repo),
+			paths,
+			follow,
+		}
+	}
+
+	fn push(&mut self, hash: Hash) -> io::Result<()> {
+		let hash = revparse::peel(self.repo, hash, Some(ObjectKind::Commit))?;
+		self.push_hex(hash.to_hex())
+	}
+
+	fn push_hex(&mut self, hash: String) -> io::Result<()> {
+		let bad = || io::Error::new(io::ErrorKind::InvalidData, format!("bad commit {}", hash));
+		self.walker.push(Hash::from_hex(&hash).ok_or_else(bad)?)
+	}
+
+	fn next(&mut self) -> io::Result<Option<Entry>> {
+		let Some((hash, _)) = self.walker.pop() else {
+			return Ok(None);
+		};
+		let hash = hash.to_hex();
+		let (commit, header) = self.read(&hash)?;
+		let paths = self.paths.clone();
+		let (parents, changed) = match paths.is_empty() {
+			true => (commit.parents.clone(), true),
+			false => self.simplify(&commit)?,
+		};
+		for parent in parents.iter() {
+			self.push_hex(parent.clone())?;
+		}
+		Ok(Some(Entry {
+			hash,
+			commit,
+			header,
+			changed,
+			parents,
+			paths,
+		}))
+	}
+
+	// pathsの内容が同じparentがあれば、それだけを辿りcommitは出さない
+	// followしていて最初のparentにpathが無ければ、renameされる前の名前を辿るようにする
+	fn simplify(&mut self, commit: &Commit) -> io::Result<(Vec<String>, bool)> {
+		let new = self.entries(&commit.tree)?;
+
+		let mut parents = Vec::new();
+		for parent in commit.parents.iter() {
+			let old = self.entries(&self.read(parent)?.0.tree)?;
+			if diff::compare(&old, &new).is_empty() {
+				return Ok((vec![parent.clone()], false));
+			}
+			parents.push((parent.clone(), old));
+		}
+
+		if let (true, Some((parent, old))) = (self.follow, parents.first()) {
+			if old.is_empty() && !new.is_empty() {
+				let tree = |hex: &str| {
+					diff::tree_entries(self.repo, &hex::decode(hex).unwrap_or_default())
+				};
+				let old = tree(&self.read(parent)?.0.tree)?;
+				let new = tree(&commit.tree)?;
+				let renamed = diff::compare(&old, &new);
+				let renamed = diff::detect_renames(self.repo, renamed, &Default::default())?
+					.into_iter()
+					.find(|x| x.status() == 'R' && x.path == self.paths[0]);
+				if let Some(change) = renamed {
+					self.paths = vec![change.old_path];
+				}
+			}
+		}
+
+		let changed = !(parents.is_empty() && new.is_empty());
+		Ok((parents.into_iter().map(|x| x.0).collect(), changed))
+	}
+
+	// treeのうちpathsの下にあるファイル
+	fn entries(&self, tree: &str) -> io::Result<BTreeMap<String, diff::Side>> {
+		let mut entries = diff::tree_entries(self.repo, &hex::decode(tree).unwrap_or_default())?;
+		entries.retain(|path, _| within(&self.paths, path));
+		Ok(entries)
+	}
+
+	// commitと、そのobjectのheader部分
+	fn read(&self, hash: &str) -> io::Result<(Commit, String)> {
+		let bad = || io::Error::new(io::ErrorKind::InvalidData, format!("bad commit {}", hash));
+		let bytes = Hash::from_hex(hash).ok_or_else(bad)?;
+		let body = store::read_object(self.repo, &bytes.0)?.1;
+		let commit = Commit::from(&body).map_err(|e| e.context(bad()))?;
+		let content = String::from_utf8(body).map_err(|_| bad())?;
+		let header = content.split_once("\n\n").map(|x| x.0).unwrap_or(&content);
+		Ok((commit, String::from(header)))
+	}
+}
@@ -0,0 +1,37 @@
+use std::io::{self, Write};
+
+use super::ls_tree::current_prefix;
+use crate::fast;
+use crate::index::Index;
+use crate::repository::Repository;
+
+// indexのentryのpathをindexの順に出す。stageなら "<mode> <hash> <stage>\t<path>" の形にする
+// conflictしているpathはstageごとに出る
+// ls_treeと同じく今のディレクトリの中だけを、そこからのpathで出す
+pub fn ls_files(repo: &Repository, stage: bool) -> io::Result<()> {
+	let index = Index::load(repo)?;
+	let prefix = match current_prefix(repo) {
+		x if x.is_empty() => x,
+		x => format!("{}/", x),
+	};
+
+	let stdout = io::stdout();
+	let mut out = stdout.lock();
+	for entry in &index.entries {
+		let Some(path) = entry.path.strip_prefix(&prefix) else {
+			continue;
+		};
+		match stage {
+			true => writeln!(
+				out,
+				"{:06o} {} {}\t{}",
+				entry.mode,
+				hex::encode(&entry.hash),
+				entry.stage(),
+				fast::quote(path)
+			)?,
+			false => writeln!(out, "{}", fast::quote(path))?,
+		}
+	}
+	Ok(())
+}
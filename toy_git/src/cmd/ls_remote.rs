@@ -0,0 +1,22 @@
+use std::io::{self, Write};
+
+use crate::config::Config;
+use crate::repository::Repository;
+use crate::transport;
+
+// remoteのrefを "<hash>\t<refname>" の形で出力する。objectは取ってこない
+// repositoryの中ならremote.<name>.urlに設定された名前も使える
+pub fn ls_remote(repo: Option<&Repository>, remote: &str) -> io::Result<()> {
+	let url = match repo {
+		Some(repo) => Config::load(repo)?
+			.get(&format!("remote.{}.url", remote))
+			.map(String::from),
+		None => None,
+	};
+
+	let mut out = io::stdout().lock();
+	for (hash, name) in transport::list_refs(url.as_deref().unwrap_or(remote))? {
+		writeln!(out, "{}\t{}", hash, name)?;
+	}
+	Ok(())
+}
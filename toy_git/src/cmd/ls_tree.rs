@@ -0,0 +1,50 @@
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::fast;
+use crate::object::ObjectKind;
+use crate::repository::Repository;
+use crate::revparse;
+use crate::store;
+
+// tree-ishのentryを名前の順に "<mode> <type> <hash>\t<path>" の形で出す
+// gitと同じく今のディレクトリの中だけを、そこからのpathで出す
+// recursiveならsubtreeの中まで辿り、subtreeそのものは出さない
+pub fn ls_tree(repo: &Repository, rev: &str, recursive: bool) -> io::Result<()> {
+	let hash = revparse::resolve(repo, rev)?;
+	let tree = revparse::peel(repo, hash, Some(ObjectKind::Tree))
+		.map_err(|_| io::Error::other("not a tree object"))?;
+	// 今のディレクトリがtreeに無ければ何も出さない
+	let Some(tree) = revparse::tree_path(repo, tree, &current_prefix(repo))? else {
+		return Ok(());
+	};
+	if store::read_object_header(repo, &tree.0)?.0 != ObjectKind::Tree {
+		return Ok(());
+	}
+
+	let stdout = io::stdout();
+	let mut out = stdout.lock();
+	store::walk_tree(repo, &tree.0, recursive, &mut |path, file| {
+		let hex = hex::encode(&file.hash);
+		writeln!(
+			out,
+			"{:06} {} {}\t{}",
+			file.mode,
+			file.kind(),
+			hex,
+			fast::quote(&path)
+		)
+	})
+}
+
+// working directoryの中の今のディレクトリの、workdirからのpath
+// bare repositoryや.gitの中、working directoryの外では空
+pub(super) fn current_prefix(repo: &Repository) -> String {
+	if repo.bare {
+		return String::new();
+	}
+	match repo.relative_path(Path::new(".")) {
+		Ok(prefix) if prefix != ".git" && !prefix.starts_with(".git/") => prefix,
+		_ => String::new(),
+	}
+}
@@ -0,0 +1,345 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+
+use crate::diff::{self, Side};
+use crate::filter::Filters;
+use crate::hash::Hash;
+use crate::index::{Entry, Index};
+use crate::merge::{self, Resolution};
+use crate::object::{self, ObjectKind};
+use crate::refs;
+use crate::repository::Repository;
+use crate::revparse;
+use crate::store;
+use crate::transaction::ObjectTransaction;
+use crate::worktree::Worktree;
+
+use super::checkout::{checkout_entry, switch_tree, write_content};
+use super::{commit_tree, write_tree};
+
+pub enum Strategy {
+	Ort,
+	Ours, // 相手の変更を全て捨て、HEADのtreeのままmerge commitを作る
+}
+
+pub struct Options {
+	pub no_ff: bool, // fast-forwardできる場合もmerge commitを作る(topic branchの形を残す)
+	pub strategy: Strategy,
+	pub dry_run: bool, // 結果を表示するだけで、index、working directory、refを変えない
+}
+
+// revをHEADにmergeする
+// fast-forwardできればbranchを進めるだけ、そうでなければ3-way mergeしてmerge commitを作る
+// conflictした場合はMERGE_HEADなどを残し、commitかmerge --abortを待つ。そのときはfalse
+// dry_runなら同じ表示をするだけで何も書かない
+pub fn merge(repo: &Repository, rev: &str, opts: &Options) -> io::Result<bool> {
+	repo.require_worktree()?;
+	if merge::merge_head(repo)?.is_some() {
+		return Err(io::Error::other(
+			"You have not concluded your merge (MERGE_HEAD exists).",
+		));
+	}
+
+	let target = refs::head_target(repo)?;
+	let head = refs::resolve(repo, "HEAD")?
+		.and_then(|x| Hash::from_hex(&x))
+		.ok_or_else(|| io::Error::other("cannot merge into an unborn branch"))?;
+	let theirs = revparse::peel(
+		repo,
+		revparse::resolve(repo, rev)?,
+		Some(ObjectKind::Commit),
+	)?;
+
+	let bases = merge::merge_base_all(repo, &head, &theirs)?;
+	let mut out = io::stdout().lock();
+	if bases.contains(&theirs) {
+		writeln!(out, "Already up to date.")?;
+		return Ok(true);
+	}
+
+	let mut index = Index::load(repo)?;
+	let ours = diff::tree_entries(repo, &tree(repo, &head)?)?;
+	if let Strategy::Ours = opts.strategy {
+		merge_ours(repo, rev, &target, &ours, &head, &theirs, opts.dry_run)?;
+		return Ok(true);
+	}
+	let others = diff::tree_entries(repo, &tree(repo, &theirs)?)?;
+	// 最良の共通祖先が複数あれば、それらをmergeしたものをbaseにする
+	let base_entries = match bases.as_slice() {
+		[base] => diff::tree_entries(repo, &tree(repo, base)?)?,
+		bases => merge::virtual_base(repo, bases)?,
+	};
+	let results = merge::merge_trees(&base_entries, &ours, &others);
+
+	// 書き換えるpathに、commitされていない変更があれば止める
+	let touched: BTreeSet<String> = results
+		.iter()
+		.filter(|(path, x)| match x {
+			Resolution::Clean(side) => !merge::same(side.as_ref(), ours.get(*path)),
+			Resolution::Conflict { .. } => true,
+		})
+		.map(|(path, _)| path.clone())
+		.collect();
+	check_clean(repo, &index, &ours, &touched)?;
+
+	if !opts.dry_run {
+		refs::update_ref(repo, "ORIG_HEAD", &head.to_hex())?;
+	}
+
+	if !opts.no_ff && bases == [head.clone()] {
+		writeln!(
+			out,
+			"Updating {}..{}",
+			&head.to_hex()[..7],
+			&theirs.to_hex()[..7]
+		)?;
+		writeln!(out, "Fast-forward")?;
+		if opts.dry_run {
+			return Ok(true);
+		}
+		switch_tree(repo, &mut index, &tree(repo, &theirs)?)?;
+		index.save(repo)?;
+		refs::update_ref_logged(
+			repo,
+			&target,
+			&theirs.to_hex(),
+			&format!("merge {}: Fast-forward", rev),
+		)?;
+		return Ok(true);
+	}
+
+	let worktree = Worktree::new(repo);
+	let filters = Filters::load(repo)?;
+	let mut conflicts = Vec::new();
+
+	for (path, resolution) in results {
+		if !touched.contains(&path) {
+			continue;
+		}
+		match resolution {
+			Resolution::Clean(_) if opts.dry_run => {}
+			Resolution::Clean(Some(side)) => {
+				checkout_entry(&worktree, &filters, &mut index, &path, side)?
+			}
+			Resolution::Clean(None) => {
+				worktree.remove_file(&path)?;
+				index.remove(&path);
+			}
+			Resolution::Conflict { base, ours, theirs } => {
+				let read = |side: &Side| store::read_object(repo, &side.hash).map(|x| x.1);
+				match (&ours, &theirs) {
+					(Some(ours), Some(theirs)) => {
+						writeln!(out, "Auto-merging {}", path)?;
+						let (a, b) = (read(ours)?, read(theirs)?);
+
+						// binaryやsymlinkはmarkerを入れられないのでHEADの内容を残す
+						let (content, clean) = if object::is_binary(&a)
+							|| object::is_binary(&b) || ours.mode == 0o120000
+							|| theirs.mode == 0o120000
+						{
+							writeln!(out, "warning: Cannot merge binary files: {} (HEAD vs. {})", path, rev)?;
+							(a, false)
+						} else {
+							let base = match &base {
+								Some(base) => read(base)?,
+								None => Vec::new(),
+							};
+							let (content, conflicts) = merge::merge_content(&base, &a, &b, "HEAD", rev);
+							(content, conflicts == 0)
+						};
+
+						// 行ごとにmergeできれば、その結果をconflictにせずindexに入れる
+						// modeはbaseから変えた側のものにする
+						if clean {
+							if !opts.dry_run {
+								let hash = store::write_object(repo, &ObjectKind::Blob.encode(&content))?;
+								let mode = match &base {
+									Some(base) if base.mode == ours.mode => theirs.mode,
+									_ => ours.mode,
+								};
+								let side = Side {
+									mode,
+									hash,
+									worktree: false,
+								};
+								checkout_entry(&worktree, &filters, &mut index, &path, side)?;
+							}
+							continue;
+						}
+						if !opts.dry_run {
+							write_content(&worktree, &filters, &path, content, ours.mode)?;
+						}
+
+						let kind = if base.is_some() { "content" } else { "add/add" };
+						writeln!(out, "CONFLICT ({}): Merge conflict in {}", kind, path)?;
+					}
+					// 削除されなかった側の内容をworking directoryに残す
+					(Some(_), None) => writeln!(
+						out,
+						"CONFLICT (modify/delete): {} deleted in {} and modified in HEAD.  Version HEAD of {} left in tree.",
+						path, rev, path
+					)?,
+					(None, Some(theirs)) => {
+						if !opts.dry_run {
+							write_content(&worktree, &filters, &path, read(theirs)?, theirs.mode)?;
+						}
+						writeln!(
+							out,
+							"CONFLICT (modify/delete): {} deleted in HEAD and modified in {}.  Version {} of {} left in tree.",
+							path, rev, rev, path
+						)?;
+					}
+					(None, None) => {}
+				}
+
+				index.remove(&path);
+				for (stage, side) in [(1, &base), (2, &ours), (3, &theirs)] {
+					if let Some(side) = side {
+						let entry =
+							Entry::unmerged(path.clone(), side.hash.clone(), side.mode, stage);
+						index.add(entry);
+					}
+				}
+				conflicts.push(path);
+			}
+		}
+	}
+	if opts.dry_run {
+		match conflicts.is_empty() {
+			true => writeln!(out, "Merge made by the 'ort' strategy.")?,
+			false => writeln!(
+				out,
+				"Automatic merge failed; fix conflicts and then commit the result."
+			)?,
+		}
+		return Ok(conflicts.is_empty());
+	}
+	index.save(repo)?;
+
+	let message = message(repo, rev, &target)?;
+	if !conflicts.is_empty() {
+		let list: String = conflicts.iter().map(|x| format!("#\t{}\n", x)).collect();
+		merge::write_state(
+			repo,
+			&theirs,
+			&format!("{}\n\n# Conflicts:\n{}", message, list),
+		)?;
+		writeln!(
+			out,
+			"Automatic merge failed; fix conflicts and then commit the result."
+		)?;
+		return Ok(false);
+	}
+
+	let transaction = ObjectTransaction::begin(repo)?;
+	let tree = hex::encode(write_tree(repo, None)?);
+	let parents = vec![head.to_hex(), theirs.to_hex()];
+	let hash = commit_tree(repo, &tree, parents, &message, None)?;
+	transaction.commit()?;
+	let message = format!("merge {}: Merge made by the 'ort' strategy.", rev);
+	refs::update_ref_logged(repo, &target, &hex::encode(hash), &message)?;
+	writeln!(out, "Merge made by the 'ort' strategy.")?;
+	Ok(true)
+}
+
+// gitと同じく、oursではfast-forwardせず、working directoryの変更も気にしない
+fn merge_ours(
+	repo: &Repository,
+	rev: &str,
+	target: &str,
+	ours: &BTreeMap<String, Side>,
+	head: &Hash,
+	theirs: &Hash,
+	dry_run: bool,
+) -> io::Result<()> {
+	check_clean(repo, &Index::load(repo)?, ours, &BTreeSet::new())?;
+	let mut out = io::stdout().lock();
+	if dry_run {
+		writeln!(out, "Merge made by the 'ours' strategy.")?;
+		return Ok(());
+	}
+	refs::update_ref(repo, "ORIG_HEAD", &head.to_hex())?;
+
+	let tree = hex::encode(tree(repo, head)?);
+	let parents = vec![head.to_hex(), theirs.to_hex()];
+	let message = message(repo, rev, target)?;
+	let hash = commit_tree(repo, &tree, parents, &message, None)?;
+	let message = format!("merge {}: Merge made by the 'ours' strategy.", rev);
+	refs::update_ref_logged(repo, target, &hex::encode(hash), &message)?;
+	writeln!(out, "Merge made by the 'ours' strategy.")?;
+	Ok(())
+}
+
+// conflictしたmergeをやめて、indexとworking directoryをmerge前のHEADに戻す
+pub fn merge_abort(repo: &Repository) -> io::Result<()> {
+	repo.require_worktree()?;
+	if merge::merge_head(repo)?.is_none() {
+		return Err(io::Error::other(
+			"There is no merge to abort (MERGE_HEAD missing).",
+		));
+	}
+
+	let head = revparse::resolve(repo, "HEAD")?;
+	let mut index = Index::load(repo)?;
+	switch_tree(repo, &mut index, &tree(repo, &head)?)?;
+	index.save(repo)?;
+
+	merge::clear_state(repo)
+}
+
+fn tree(repo: &Repository, commit: &Hash) -> io::Result<Vec<u8>> {
+	Ok(revparse::peel(repo, commit.clone(), Some(ObjectKind::Tree))?.0)
+}
+
+// indexがHEADと違う場合と、書き換えるpathがworking directoryで変更されている場合は止める
+fn check_clean(
+	repo: &Repository,
+	index: &Index,
+	head: &BTreeMap<String, Side>,
+	touched: &BTreeSet<String>,
+) -> io::Result<()> {
+	let entries = diff::index_entries(index);
+	let mut dirty: Vec<String> = diff::compare(head, &entries)
+		.into_iter()
+		.map(|x| x.path)
+		.chain(index.unmerged().into_iter().map(String::from))
+		.collect();
+
+	dirty.extend(
+		diff::compare(&entries, &diff::worktree_entries(repo, index)?)
+			.into_iter()
+			.map(|x| x.path)
+			.filter(|x| touched.contains(x)),
+	);
+	dirty.sort();
+	dirty.dedup();
+
+	if dirty.is_empty() {
+		return Ok(());
+	}
+	let list: String = dirty.iter().map(|x| format!("\t{}\n", x)).collect();
+	Err(io::Error::other(format!(
+		"Your local changes to the following files would be overwritten by merge:\n{}Please commit your changes or stash them before you merge.",
+		list
+	)))
+}
+
+// "Merge branch 'topic'" のようなmerge commitのメッセージ
+fn message(repo: &Repository, rev: &str, target: &str) -> io::Result<String> {
+	let name = revparse::dwim(repo, rev)?;
+	let mut message = match name.as_deref() {
+		Some(x) if x.starts_with("refs/heads/") => format!("Merge branch '{}'", refs::shorten(x)),
+		Some(x) if x.starts_with("refs/tags/") => format!("Merge tag '{}'", refs::shorten(x)),
+		Some(x) if x.starts_with("refs/remotes/") => {
+			format!("Merge remote-tracking branch '{}'", refs::shorten(x))
+		}
+		_ => format!("Merge commit '{}'", rev),
+	};
+
+	// main/masterへのmergeでは "into <branch>" を省略する
+	let branch = refs::shorten(target);
+	if target != "HEAD" && branch != "main" && branch != "master" {
+		message.push_str(&format!(" into {}", branch));
+	}
+	Ok(message)
+}
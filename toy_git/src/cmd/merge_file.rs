@@ -0,0 +1,45 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::merge;
+use crate::object::is_binary;
+
+pub struct Options {
+	pub labels: Vec<String>, // -Lで指定したours, base, theirsのconflict markerの名前
+	pub stdout: bool,        // oursに書かずに結果を出力する
+}
+
+// baseからoursとtheirsへの変更を行ごとにmergeしてoursに書き、conflictの数を返す
+// conflict markerの名前は、-Lが無ければファイルの名前になる
+pub fn merge_file(ours: &Path, base: &Path, theirs: &Path, opts: &Options) -> io::Result<usize> {
+	let mut contents = Vec::new();
+	for path in [ours, base, theirs] {
+		let content = fs::read(path)?;
+		if is_binary(&content) {
+			return Err(io::Error::other(format!(
+				"Cannot merge binary files: {}",
+				path.display()
+			)));
+		}
+		contents.push(content);
+	}
+
+	let label = |i: usize, path: &Path| match opts.labels.get(i) {
+		Some(label) => label.clone(),
+		None => path.display().to_string(),
+	};
+	let (out, conflicts) = merge::merge_content(
+		&contents[1],
+		&contents[0],
+		&contents[2],
+		&label(0, ours),
+		&label(2, theirs),
+	);
+
+	match opts.stdout {
+		true => io::stdout().lock().write_all(&out)?,
+		false => fs::write(ours, out)?,
+	}
+	Ok(conflicts)
+}
@@ -0,0 +1,75 @@
+use std::io::{self, BufRead, Write};
+
+use crate::hash::Hash;
+use crate::object::{ObjectKind, User};
+use crate::repository::Repository;
+use crate::store;
+
+// inputからtag objectの内容を読み、gitのfsckと同じ順で確かめてから書き込んでhashを出力する
+// "object", "type", "tag", "tagger" の行がこの順に無ければError
+pub fn mktag<R: BufRead>(repo: &Repository, mut input: R) -> io::Result<()> {
+	let mut body = Vec::new();
+	input.read_to_end(&mut body)?;
+
+	let (object, kind) = check(&body).map_err(|reason| {
+		io::Error::new(
+			io::ErrorKind::InvalidData,
+			format!("tag input does not pass fsck: {}", reason),
+		)
+	})?;
+
+	// 指すobjectが存在し、typeが合っていなければならない
+	let actual = store::read_object(repo, &object.0)
+		.map_err(|_| io::Error::other(format!("could not read tagged object '{}'", object)))?
+		.0;
+	if actual != kind {
+		return Err(io::Error::other(format!(
+			"object '{}' tagged as '{}', but is a '{}' type",
+			object, kind, actual
+		)));
+	}
+
+	let hash = store::write_object(repo, &ObjectKind::Tag.encode(&body))?;
+	writeln!(io::stdout().lock(), "{}", hex::encode(hash))?;
+	Ok(())
+}
+
+// 問題があればgitのfsckと同じ "<id>: <説明>" を返す
+fn check(body: &[u8]) -> Result<(Hash, ObjectKind), &'static str> {
+	let content = std::str::from_utf8(body).map_err(|_| "badTagName: invalid UTF-8")?;
+	let header = match content.split_once("\n\n") {
+		Some((header, _)) => header,
+		None => content.strip_suffix('\n').unwrap_or(content),
+	};
+	let mut lines = header.split('\n');
+
+	let object = lines
+		.next()
+		.and_then(|x| x.strip_prefix("object "))
+		.ok_or("missingObject: invalid format - expected 'object' line")?;
+	let object =
+		Hash::from_hex(object).ok_or("badObjectSha1: invalid 'object' line format - bad sha1")?;
+
+	let kind = lines
+		.next()
+		.and_then(|x| x.strip_prefix("type "))
+		.ok_or("missingTypeEntry: invalid format - expected 'type' line")?;
+	let kind = ObjectKind::from(kind).ok_or("badType: invalid 'type' value")?;
+
+	lines
+		.next()
+		.and_then(|x| x.strip_prefix("tag "))
+		.ok_or("missingTagEntry: invalid format - expected 'tag' line")?;
+
+	let tagger = lines
+		.next()
+		.and_then(|x| x.strip_prefix("tagger "))
+		.ok_or("missingTaggerEntry: invalid format - expected 'tagger' line")?;
+	if !tagger.contains('<') || !tagger.contains('>') {
+		return Err("missingEmail: invalid author/committer line - missing email");
+	}
+	User::from(tagger.as_bytes())
+		.map_err(|_| "badDate: invalid author/committer line - bad date")?;
+
+	Ok((object, kind))
+}
@@ -0,0 +1,121 @@
+mod add;
+mod am;
+mod apply;
+mod bisect;
+mod blame;
+mod branch;
+mod bundle;
+mod cat_file;
+mod check_attr;
+mod checkout;
+mod cherry;
+mod clean;
+mod clone;
+mod commit;
+mod commit_graph;
+mod config;
+mod diff;
+mod diff_index;
+mod diff_tree;
+mod fast_export;
+mod fast_import;
+mod fetch;
+mod for_each_ref;
+mod format_patch;
+mod fsck;
+mod gc;
+mod grep;
+mod hash_object;
+mod init;
+mod interpret_trailers;
+mod log;
+mod ls_files;
+mod ls_remote;
+mod ls_tree;
+mod merge;
+mod merge_file;
+mod mktag;
+mod multi_pack_index;
+mod notes;
+mod pack_objects;
+mod prune_packed;
+mod push;
+mod read_tree;
+mod reflog;
+mod replace;
+mod reset;
+mod rev_list;
+mod rev_parse;
+mod rm;
+mod shortlog;
+mod show_branch;
+mod show_ref;
+mod stash;
+mod status;
+mod symbolic_ref;
+mod tag;
+mod update_ref;
+mod verify;
+mod worktree;
+mod write_tree;
+
+pub use add::{add, Options as AddOptions};
+pub use am::{am, am_abort, am_continue};
+pub use apply::{apply, Options as ApplyOptions};
+pub use bisect::{bisect_bad, bisect_good, bisect_reset, bisect_start};
+pub use blame::blame;
+pub use branch::{branch_create, branch_delete, branch_list, branch_set_upstream};
+pub use bundle::{bundle_create, bundle_unbundle};
+pub use cat_file::{cat_file, cat_file_batch, cat_file_batch_all, cat_file_exists, Query as CatFileQuery};
+pub use check_attr::check_attr;
+pub use checkout::{checkout, Options as CheckoutOptions};
+pub use cherry::cherry;
+pub use clean::{clean, Options as CleanOptions};
+pub use clone::clone;
+pub use commit::{commit, commit_tree, create_commit, Options as CommitOptions};
+pub use commit_graph::commit_graph_write;
+pub use config::{config, Action as ConfigAction, Scope as ConfigScope};
+pub use diff::{diff, diff_no_index, Color as DiffColor, Format as DiffFormat, Options as DiffOptions, Target as DiffTarget};
+pub use diff_index::diff_index;
+pub use diff_tree::diff_tree;
+pub use fast_export::fast_export;
+pub use fast_import::fast_import;
+pub use fetch::fetch;
+pub use for_each_ref::for_each_ref;
+pub use format_patch::{format_patch, Options as FormatPatchOptions};
+pub use fsck::{fsck, Options as FsckOptions};
+pub use gc::{gc, Options as GcOptions};
+pub use grep::{grep, Options as GrepOptions};
+pub use hash_object::hash_object;
+pub use init::{init, Options as InitOptions};
+pub use interpret_trailers::interpret_trailers;
+pub use log::{log, Options as LogOptions};
+pub use ls_files::ls_files;
+pub use ls_remote::ls_remote;
+pub use ls_tree::ls_tree;
+pub use merge::{merge, merge_abort, Options as MergeOptions, Strategy as MergeStrategy};
+pub use merge_file::{merge_file, Options as MergeFileOptions};
+pub use mktag::mktag;
+pub use multi_pack_index::multi_pack_index_write;
+pub use notes::{notes_add, notes_show};
+pub use pack_objects::pack_objects;
+pub use prune_packed::prune_packed;
+pub use push::{push, push_delete};
+pub use read_tree::read_tree;
+pub use reflog::{reflog_expire, reflog_show};
+pub use replace::replace;
+pub use reset::{reset, Mode as ResetMode};
+pub use rev_list::{rev_list, Options as RevListOptions, Order as RevListOrder};
+pub use rev_parse::rev_parse;
+pub use rm::{rm, Options as RmOptions};
+pub use shortlog::{shortlog, Options as ShortlogOptions};
+pub use show_branch::show_branch;
+pub use show_ref::{show_ref, Options as ShowRefOptions};
+pub use stash::{stash_apply, stash_drop, stash_list, stash_pop, stash_push};
+pub use status::{status, Options as StatusOptions};
+pub use symbolic_ref::{symbolic_ref, Action as SymbolicRefAction};
+pub use tag::{tag_create, tag_delete, tag_list, tag_verify, CreateOptions as TagCreateOptions, Options as TagOptions, Sort as TagSort};
+pub use update_ref::update_ref_stdin;
+pub use verify::{verify_commit, verify_tag};
+pub use worktree::worktree_add;
+pub use write_tree::write_tree;
@@ -0,0 +1,46 @@
+use std::collections::HashSet;
+use std::io::{self, Write};
+
+use crate::pack::{self, MidxEntry, MultiPackIndex};
+use crate::repository::Repository;
+use crate::tempfile::TempFile;
+
+// objects/packの全てのpackの.idxをまとめた objects/pack/multi-pack-index を書く
+// 複数のpackにあるobjectは、gitと同じく新しいpackのものを使う
+pub fn multi_pack_index_write(repo: &Repository) -> io::Result<()> {
+	repo.require_sha1("multi-pack-indexes")?;
+	let packs = pack::packs(repo)?;
+	let name = |pack: &pack::Pack| {
+		pack.path
+			.with_extension("idx")
+			.file_name()
+			.map(|x| x.to_string_lossy().into_owned())
+			.unwrap_or_default()
+	};
+	let mut names: Vec<String> = packs.iter().map(name).collect();
+	names.sort();
+
+	let mut seen = HashSet::new();
+	let mut entries = Vec::new();
+	for pack in packs.iter() {
+		let id = names.binary_search(&name(pack)).unwrap_or_default() as u32;
+		for entry in pack.index.entries.iter() {
+			if seen.insert(entry.hash.clone()) {
+				entries.push(MidxEntry {
+					hash: entry.hash.clone(),
+					pack: id,
+					offset: entry.offset,
+				});
+			}
+		}
+	}
+
+	let midx = MultiPackIndex {
+		packs: names,
+		entries,
+	};
+	let dir = repo.objects_dir().join("pack");
+	let mut file = TempFile::new(&dir, "tmp_midx")?;
+	file.write_all(&midx.as_bytes())?;
+	file.persist(&dir.join("multi-pack-index"))
+}
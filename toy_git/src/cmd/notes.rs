@@ -0,0 +1,73 @@
+use std::io::{self, Write};
+
+use super::commit::commit_tree;
+use super::write_tree;
+use crate::notes::{self, NOTES_REF};
+use crate::object::ObjectKind;
+use crate::refs::{self, RefChange, RefUpdate};
+use crate::repository::Repository;
+use crate::revparse;
+use crate::store;
+
+// revのobjectにmessageのnoteを付け、refs/notes/commitsにそのcommitを積む
+// 既にnoteがあればforceのときだけ置き換える。messageが空ならgitと同じくnoteを消す
+pub fn notes_add(repo: &Repository, rev: &str, message: &str, force: bool) -> io::Result<()> {
+	let object = revparse::resolve(repo, rev)?.to_hex();
+	let mut notes = notes::list(repo)?;
+	if notes.contains_key(&object) && !force {
+		return Err(io::Error::other(format!(
+			"Cannot add notes. Found existing notes for object {}. Use '-f' to overwrite existing notes",
+			object
+		)));
+	}
+
+	let message = message.trim_end();
+	if message.is_empty() {
+		if notes.remove(&object).is_none() {
+			return Ok(());
+		}
+		eprintln!("Removing note for object {}", object);
+	} else {
+		if notes.contains_key(&object) {
+			eprintln!("Overwriting existing notes for object {}", object);
+		}
+		let content = format!("{}\n", message);
+		let blob = store::write_object(repo, &ObjectKind::Blob.encode(content.as_bytes()))?;
+		notes.insert(object, blob);
+	}
+
+	// noteの名前はhashそのままで、ディレクトリには分けない
+	let entries: Vec<(&str, u32, &[u8])> = notes
+		.iter()
+		.map(|(object, blob)| (object.as_str(), 0o100644, blob.as_slice()))
+		.collect();
+	let tree = write_tree::write_entries(repo, &entries)?;
+
+	let parent = refs::resolve(repo, NOTES_REF)?;
+	let message = "Notes added by 'git notes add'";
+	let commit = commit_tree(
+		repo,
+		&hex::encode(tree),
+		parent.iter().cloned().collect(),
+		message,
+		None,
+	)?;
+	let update = RefUpdate {
+		name: String::from(NOTES_REF),
+		change: RefChange::Update(hex::encode(commit)),
+		old: Some(parent),
+	};
+	refs::transaction(repo, &[update], &format!("notes: {}", message))
+}
+
+// revのobjectに付けたnoteをそのまま出力する
+pub fn notes_show(repo: &Repository, rev: &str) -> io::Result<()> {
+	let object = revparse::resolve(repo, rev)?.to_hex();
+	match notes::read(repo, &object)? {
+		Some(note) => io::stdout().write_all(&note),
+		None => Err(io::Error::other(format!(
+			"no note found for object {}.",
+			object
+		))),
+	}
+}
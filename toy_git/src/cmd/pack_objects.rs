@@ -0,0 +1,54 @@
+use std::collections::HashSet;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use crate::hash::Hash;
+use crate::pack;
+use crate::progress::Progress;
+use crate::repository::Repository;
+use crate::store;
+
+// inputの各行の先頭のhashのobjectを1つのpackにし、<prefix>-<checksum>.{pack,idx} に書いてchecksumを出力する
+// 行はrev-list --objectsの出力の形で、hashの後ろのpathは使わない
+pub fn pack_objects<R: BufRead>(
+	repo: &Repository,
+	input: R,
+	prefix: &Path,
+	progress: &mut dyn Progress,
+) -> io::Result<()> {
+	let mut hashes = Vec::new();
+	for line in input.lines() {
+		let line = line?;
+		let hex = line.split(' ').next().unwrap_or_default();
+		if hex.is_empty() {
+			continue;
+		}
+		let hash = Hash::from_hex(hex).ok_or_else(|| {
+			io::Error::other(format!("expected object ID, got garbage:\n {}", line))
+		})?;
+		hashes.push(hash);
+	}
+
+	let name = write_pack(repo, &hashes, prefix, progress)?;
+	writeln!(io::stdout().lock(), "{}", name)?;
+	Ok(())
+}
+
+// hashesのobjectを、同じものは1度だけ入れたpackにして保存し、そのchecksumを返す
+pub(super) fn write_pack(
+	repo: &Repository,
+	hashes: &[Hash],
+	prefix: &Path,
+	progress: &mut dyn Progress,
+) -> io::Result<String> {
+	let mut seen = HashSet::new();
+	let mut objects = Vec::new();
+	for hash in hashes {
+		if seen.insert(hash) {
+			objects.push(store::read_original(repo, &hash.0)?);
+		}
+	}
+
+	let data = pack::write(repo, &objects, progress)?;
+	pack::index_pack_to(repo, &data, prefix, progress)
+}
@@ -0,0 +1,61 @@
+use std::fs;
+use std::io::{self, Write};
+
+use crate::gc_lock::GcLock;
+use crate::pack;
+use crate::repository::Repository;
+
+// packに入っているlooseなobjectを消す。空になったobjects/<xx>も消す
+// dry_runなら消さずに "rm -f <path>" を出力する。消すときはgcと同時に行わないようにlockする
+pub fn prune_packed(repo: &Repository, dry_run: bool) -> io::Result<()> {
+	let _lock = match dry_run {
+		true => None,
+		false => Some(GcLock::acquire(repo, false)?),
+	};
+	prune(repo, dry_run)
+}
+
+// lockを取ったgcからも使う
+pub(super) fn prune(repo: &Repository, dry_run: bool) -> io::Result<()> {
+	let packs = pack::packs(repo)?;
+	if packs.is_empty() {
+		return Ok(());
+	}
+
+	let mut out = io::stdout().lock();
+	for i in 0..=255u8 {
+		let dir = repo.objects_dir().join(format!("{:02x}", i));
+		let entries = match fs::read_dir(&dir) {
+			Ok(entries) => entries,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+			Err(e) => return Err(e),
+		};
+
+		for entry in entries {
+			let path = entry?.path();
+			let Some(name) = path.file_name().and_then(|x| x.to_str()) else {
+				continue;
+			};
+			// 書き込み途中の一時ファイルなどは残す
+			let Ok(hash) = hex::decode(format!("{:02x}{}", i, name)) else {
+				continue;
+			};
+			if hash.len() != repo.hash_algo.hash_len()
+				|| !packs.iter().any(|x| x.index.find(&hash).is_some())
+			{
+				continue;
+			}
+
+			match dry_run {
+				true => writeln!(out, "rm -f {}", path.display())?,
+				false => fs::remove_file(&path)?,
+			}
+		}
+
+		// 他のファイルが残っていれば消えないのでErrorは無視する
+		if !dry_run {
+			fs::remove_dir(&dir).ok();
+		}
+	}
+	Ok(())
+}
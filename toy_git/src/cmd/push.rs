@@ -0,0 +1,430 @@
+use std::io;
+
+use crate::hash::Hash;
+use crate::merge;
+use crate::object::ObjectKind;
+use crate::progress::Progress;
+use crate::refs;
+use crate::repository::Repository;
+use crate::revparse;
+use crate::store;
+use crate::transport::{self, RefCommand};
+use crate::upstream::Upstream;
+
+use super::fetch::{remote_url, SUMMARY_WIDTH};
+
+// 送るrefの1つ
+struct Target {
+	src: Option<String>, // 表示するlocalの名前。消す場合はNone
+	command: RefCommand,
+	rejected: Option<Rejected>, // 送る前に拒んだ理由
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Rejected {
+	FetchFirst,     // remoteのcommitをlocalに持っていない
+	NonFastForward, // remoteのcommitから辿れない
+	AlreadyExists,  // tagは--forceでなければ書き換えない
+}
+
+// refspecs("[+]<src>[:<dst>]")のsrcのcommitをremoteのdstに送り、remote-tracking refも更新する
+// refspecsが無ければ今のbranchをupstreamに送る。remoteが無ければupstreamのremoteか "origin"
+// forceか "+" が付いていなければfast-forwardでない更新は拒む。拒まれたものがあればfalse
+pub fn push(
+	repo: &Repository,
+	remote: Option<&str>,
+	refspecs: &[String],
+	force: bool,
+	progress: &mut dyn Progress,
+) -> io::Result<bool> {
+	let head = refs::head_target(repo)?;
+	let branch = head.strip_prefix("refs/heads/");
+	let upstream = match branch {
+		Some(branch) => Upstream::load(repo, branch)?,
+		None => None,
+	};
+	let remote = match (remote, &upstream) {
+		(Some(remote), _) => String::from(remote),
+		(None, Some(upstream)) if upstream.remote != "." => upstream.remote.clone(),
+		(None, _) => String::from("origin"),
+	};
+	let url = remote_url(repo, &remote)?;
+
+	let refspecs = match refspecs.is_empty() {
+		true => vec![default_refspec(branch, upstream.as_ref(), &remote)?],
+		false => refspecs.to_vec(),
+	};
+
+	let remote_refs = remote_refs(&url)?;
+	let mut targets = Vec::new();
+	for refspec in &refspecs {
+		let (forced, refspec) = match refspec.strip_prefix('+') {
+			Some(rest) => (true, rest),
+			None => (force, refspec.as_str()),
+		};
+		let (src, dst) = match refspec.split_once(':') {
+			Some((src, dst)) => (src, Some(dst)),
+			None => (refspec, None),
+		};
+		// ":<dst>" はdstを消す
+		if src.is_empty() {
+			let Some(dst) = dst.filter(|x| !x.is_empty()) else {
+				return Err(io::Error::other(format!("invalid refspec '{}'", refspec)));
+			};
+			match delete_target(&remote_refs, dst) {
+				Ok(target) => targets.push(target),
+				Err(e) => return failed(&url, &e),
+			}
+			continue;
+		}
+		match target(repo, &remote_refs, src, dst, forced)? {
+			Ok(target) => targets.push(target),
+			Err(e) => return failed(&url, &e),
+		}
+	}
+	send(repo, &remote, &url, &remote_refs, targets, progress)
+}
+
+// remoteのrefを消す(push --delete)。namesは "refs/" から始まる名前か、remoteのbranchかtagの名前
+// branchかtagの名前のどれかがremoteに無ければ何も消さない。消したbranchのremote-tracking refも消す
+// 拒まれたものがあればfalse
+pub fn push_delete(
+	repo: &Repository,
+	remote: &str,
+	names: &[String],
+	progress: &mut dyn Progress,
+) -> io::Result<bool> {
+	let url = remote_url(repo, remote)?;
+	let remote_refs = remote_refs(&url)?;
+
+	let mut targets = Vec::new();
+	for name in names {
+		match delete_target(&remote_refs, name) {
+			Ok(target) => targets.push(target),
+			Err(e) => return failed(&url, &e),
+		}
+	}
+	// remoteのrefと同じ順に出す
+	targets.sort_by(|a, b| a.command.name.cmp(&b.command.name));
+	targets.dedup_by(|a, b| a.command.name == b.command.name);
+	send(repo, remote, &url, &remote_refs, targets, progress)
+}
+
+// refspecを書かなかったときに送るもの(push.defaultの "simple")
+// upstreamと同じremoteならupstreamのbranchに、違うremoteなら同じ名前のbranchに送る
+fn default_refspec(
+	branch: Option<&str>,
+	upstream: Option<&Upstream>,
+	remote: &str,
+) -> io::Result<String> {
+	let Some(branch) = branch else {
+		return Err(io::Error::other(format!(
+			"You are not currently on a branch.\nTo push the history leading to the current (detached HEAD)\nstate now, use\n\n    git push {} HEAD:<name-of-remote-branch>\n",
+			remote
+		)));
+	};
+	let central = upstream.map_or("origin", |x| x.remote.as_str());
+	match upstream {
+		_ if central != remote => Ok(format!("refs/heads/{0}:refs/heads/{0}", branch)),
+		Some(upstream) => Ok(format!("refs/heads/{}:{}", branch, upstream.merge)),
+		None => Err(io::Error::other(format!(
+			"The current branch {0} has no upstream branch.\nTo push the current branch and set the remote as upstream, use\n\n    git push --set-upstream {1} {0}\n\nTo have this happen automatically for branches without a tracking\nupstream, see 'push.autoSetupRemote' in 'git help config'.\n",
+			branch, remote
+		))),
+	}
+}
+
+// remoteのref(peelした "^{}" は除く)
+fn remote_refs(url: &str) -> io::Result<Vec<(String, Hash)>> {
+	let mut list = Vec::new();
+	for (hash, name) in transport::list_refs(url)? {
+		if !name.starts_with("refs/") || name.ends_with("^{}") {
+			continue;
+		}
+		let hash = Hash::from_hex(&hash).ok_or_else(|| {
+			io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!("{}: bad object name {}", url, hash),
+			)
+		})?;
+		list.push((name, hash));
+	}
+	Ok(list)
+}
+
+// "error: ..." を出して何も送らずに終える
+fn failed(url: &str, message: &str) -> io::Result<bool> {
+	eprintln!("error: {}", message);
+	eprintln!("error: failed to push some refs to '{}'", url);
+	Ok(false)
+}
+
+// nameに一致するremoteのrefを消すTarget。Errは出すメッセージ
+fn delete_target(remote_refs: &[(String, Hash)], name: &str) -> Result<Target, String> {
+	let matched = match_remote(remote_refs, name)?;
+	let (name, old) = match (matched, name.starts_with("refs/")) {
+		(Some((name, hash)), _) => (name.clone(), Some(hash.clone())),
+		// "refs/" から始まる名前はremoteに無くてもそのまま送る
+		(None, true) => (String::from(name), None),
+		(None, false) => {
+			return Err(format!(
+				"unable to delete '{}': remote ref does not exist",
+				name
+			))
+		}
+	};
+	Ok(Target {
+		src: None,
+		command: RefCommand {
+			name,
+			old,
+			new: None,
+		},
+		rejected: None,
+	})
+}
+
+// nameそのもの、refs/heads/<name>、refs/tags/<name>のうちremoteにあるもの
+fn match_remote<'a>(
+	remote_refs: &'a [(String, Hash)],
+	name: &str,
+) -> Result<Option<&'a (String, Hash)>, String> {
+	let candidates: Vec<&(String, Hash)> = remote_refs
+		.iter()
+		.filter(|(x, _)| match name.starts_with("refs/") {
+			true => x == name,
+			false => *x == format!("refs/heads/{}", name) || *x == format!("refs/tags/{}", name),
+		})
+		.collect();
+	match candidates.as_slice() {
+		[] => Ok(None),
+		[x] => Ok(Some(*x)),
+		_ => Err(format!("dst refspec {} matches more than one", name)),
+	}
+}
+
+// srcのcommitをdstに送るTarget。dstが無ければsrcと同じ名前に送る
+// 送れないと分かったときのErrは出すメッセージ
+fn target(
+	repo: &Repository,
+	remote_refs: &[(String, Hash)],
+	src: &str,
+	dst: Option<&str>,
+	force: bool,
+) -> io::Result<Result<Target, String>> {
+	let no_match = || Ok(Err(format!("src refspec {} does not match any", src)));
+	// "HEAD~1" のようなrevisionは、元のrefでbranchかtagかを決める
+	let base = src.split(['~', '^']).next().unwrap_or(src);
+	let local = match revparse::dwim(repo, base)?.as_deref() {
+		Some("HEAD") => Some(refs::head_target(repo)?),
+		x => x.map(String::from),
+	};
+	let Ok(new) = revparse::resolve(repo, src) else {
+		return no_match();
+	};
+	if local.is_none() && dst.is_none() {
+		return no_match();
+	}
+
+	let dst = match dst {
+		Some(dst) => dst,
+		None => local.as_deref().unwrap_or(src),
+	};
+	let name = match match_remote(remote_refs, dst) {
+		Err(e) => return Ok(Err(e)),
+		Ok(Some((name, _))) => name.clone(),
+		Ok(None) if dst.starts_with("refs/") => String::from(dst),
+		Ok(None) => match local.as_deref().and_then(|x| x.strip_prefix("refs/")) {
+			Some(x) if x.starts_with("heads/") => format!("refs/heads/{}", dst),
+			Some(x) if x.starts_with("tags/") => format!("refs/tags/{}", dst),
+			_ => {
+				// commitならbranchにするよう勧める
+				let hint = match store::read_object_header(repo, &new.0)?.0 {
+					ObjectKind::Commit => format!("\nhint: The <src> part of the refspec is a commit object.\nhint: Did you mean to create a new branch by pushing to\nhint: '{}:refs/heads/{}'?", src, dst),
+					_ => String::new(),
+				};
+				return Ok(Err(format!(
+					"The destination you provided is not a full refname (i.e.,\nstarting with \"refs/\"). We tried to guess what you meant by:\n\n- Looking for a ref that matches '{}' on the remote side.\n- Checking if the <src> being pushed ('{}')\n  is a ref in \"refs/{{heads,tags}}/\". If so we add a corresponding\n  refs/{{heads,tags}}/ prefix on the remote side.\n\nNeither worked, so we gave up. You must fully qualify the ref.{}",
+					dst, src, hint
+				)));
+			}
+		},
+	};
+
+	let old = remote_refs
+		.iter()
+		.find(|(x, _)| *x == name)
+		.map(|(_, hash)| hash.clone());
+	let rejected = match &old {
+		Some(old) if force || *old == new => None,
+		Some(_) if name.starts_with("refs/tags/") => Some(Rejected::AlreadyExists),
+		Some(old) if !store::has_object(repo, &old.0)? => Some(Rejected::FetchFirst),
+		Some(old) => match merge::merge_base(repo, old, &new)? {
+			Some(base) if base == *old => None,
+			_ => Some(Rejected::NonFastForward),
+		},
+		None => None,
+	};
+	Ok(Ok(Target {
+		src: Some(String::from(refs::shorten(src))),
+		command: RefCommand {
+			name,
+			old,
+			new: Some(new),
+		},
+		rejected,
+	}))
+}
+
+// targetsのうち変わるものを送り、結果を出す
+fn send(
+	repo: &Repository,
+	remote: &str,
+	url: &str,
+	remote_refs: &[(String, Hash)],
+	targets: Vec<Target>,
+	progress: &mut dyn Progress,
+) -> io::Result<bool> {
+	// 既に同じものは送らず、表示もしない
+	let targets: Vec<Target> = targets
+		.into_iter()
+		.filter(|x| x.command.new.is_none() || x.command.old != x.command.new)
+		.collect();
+	let (sent, rejected): (Vec<&Target>, Vec<&Target>) =
+		targets.iter().partition(|x| x.rejected.is_none());
+	if targets.is_empty() {
+		eprintln!("Everything up-to-date");
+		return Ok(true);
+	}
+
+	let results = match sent.is_empty() {
+		true => Vec::new(),
+		false => {
+			let commands: Vec<RefCommand> = sent.iter().map(|x| x.command.clone()).collect();
+			// remoteのrefのうちlocalにもあるものはremoteも持っているので送らない
+			let mut haves = Vec::new();
+			for (_, hash) in remote_refs {
+				if store::has_object(repo, &hash.0)? {
+					haves.push(hash.clone());
+				}
+			}
+			transport::push(repo, url, &commands, &haves, progress)?
+		}
+	};
+
+	// remoteのメッセージは結果の前に出る
+	eprintln!("To {}", url);
+	let mut ok = rejected.is_empty();
+	let mut results = results.into_iter();
+	for target in &targets {
+		let command = &target.command;
+		let dst = refs::shorten(&command.name);
+		let line = |flag: char, summary: &str, note: &str| match &target.src {
+			Some(src) => eprintln!(
+				" {} {:<w$} {} -> {}{}",
+				flag,
+				summary,
+				src,
+				dst,
+				note,
+				w = SUMMARY_WIDTH
+			),
+			None => eprintln!(
+				" {} {:<w$} {}{}",
+				flag,
+				summary,
+				dst,
+				note,
+				w = SUMMARY_WIDTH
+			),
+		};
+		if let Some(rejected) = target.rejected {
+			let reason = match rejected {
+				Rejected::FetchFirst => "fetch first",
+				Rejected::NonFastForward => "non-fast-forward",
+				Rejected::AlreadyExists => "already exists",
+			};
+			line('!', "[rejected]", &format!(" ({})", reason));
+			continue;
+		}
+		if let Some(reason) = results.next().flatten() {
+			line('!', "[remote rejected]", &format!(" ({})", reason));
+			ok = false;
+			continue;
+		}
+
+		match (&command.old, &command.new) {
+			(_, None) => line('-', "[deleted]", ""),
+			(None, Some(_)) => {
+				let summary = match command.name.split('/').nth(1) {
+					Some("heads") => "[new branch]",
+					Some("tags") => "[new tag]",
+					_ => "[new reference]",
+				};
+				line('*', summary, "");
+			}
+			(Some(old), Some(new)) => {
+				let range =
+					|sep: &str| format!("{}{}{}", &old.to_hex()[..7], sep, &new.to_hex()[..7]);
+				match merge::merge_base(repo, old, new)? {
+					Some(base) if base == *old => line(' ', &range(".."), ""),
+					_ => line('+', &range("..."), " (forced update)"),
+				}
+			}
+		}
+		if let Some(branch) = command.name.strip_prefix("refs/heads/") {
+			let tracking = format!("refs/remotes/{}/{}", remote, branch);
+			match &command.new {
+				Some(new) => refs::update_ref(repo, &tracking, &new.to_hex())?,
+				None if refs::read_ref(repo, &tracking)?.is_some() => {
+					refs::delete_ref(repo, &tracking)?
+				}
+				None => {}
+			}
+		}
+	}
+	if !ok {
+		eprintln!("error: failed to push some refs to '{}'", url);
+		hint(repo, &targets)?;
+	}
+	Ok(ok)
+}
+
+// 送る前に拒んだ理由ごとのhint。gitと同じく1つだけ出す
+fn hint(repo: &Repository, targets: &[Target]) -> io::Result<()> {
+	let head = refs::head_target(repo)?;
+	let reasons: Vec<(Rejected, bool)> = targets
+		.iter()
+		.filter_map(|x| x.rejected.map(|reason| (reason, x.command.name == head)))
+		.collect();
+	let lines: &[&str] = if reasons.contains(&(Rejected::NonFastForward, true)) {
+		&[
+			"Updates were rejected because the tip of your current branch is behind",
+			"its remote counterpart. Integrate the remote changes (e.g.",
+			"'git pull ...') before pushing again.",
+		]
+	} else if reasons.iter().any(|(x, _)| *x == Rejected::NonFastForward) {
+		&[
+			"Updates were rejected because a pushed branch tip is behind its remote",
+			"counterpart. Check out this branch and integrate the remote changes",
+			"(e.g. 'git pull ...') before pushing again.",
+		]
+	} else if reasons.iter().any(|(x, _)| *x == Rejected::FetchFirst) {
+		&[
+			"Updates were rejected because the remote contains work that you do",
+			"not have locally. This is usually caused by another repository pushing",
+			"to the same ref. You may want to first integrate the remote changes",
+			"(e.g., 'git pull ...') before pushing again.",
+		]
+	} else if reasons.iter().any(|(x, _)| *x == Rejected::AlreadyExists) {
+		eprintln!("hint: Updates were rejected because the tag already exists in the remote.");
+		return Ok(());
+	} else {
+		return Ok(());
+	};
+	for line in lines {
+		eprintln!("hint: {}", line);
+	}
+	eprintln!("hint: See the 'Note about fast-forwards' in 'git push --help' for details.");
+	Ok(())
+}
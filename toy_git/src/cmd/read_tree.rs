@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+use std::io;
+
+use crate::diff::{self, Side};
+use crate::hash::Hash;
+use crate::index::{Entry, Index};
+use crate::merge::{self, Resolution};
+use crate::repository::Repository;
+
+// treeの内容をindexに読み込む。working directoryは触らない
+// mergeでなければindexをtreeの内容で置き換える
+// mergeでtreeが1つなら、内容の変わらないentryのstat情報を残して置き換える
+// 2つ(H, M)ならindexをHからMに進める。indexでHから変えたpathがMでも変わっていればError
+// 3つ(base, ours, theirs)なら3-way mergeし、conflictしたpathをstage 1から3に置く
+// 書き換えるentryがworking directoryで変更されていればError
+pub fn read_tree(repo: &Repository, trees: &[Hash], merge: bool) -> io::Result<()> {
+	let mut index = Index::load(repo)?;
+	if merge && !index.unmerged().is_empty() {
+		return Err(io::Error::other(
+			"you need to resolve your current index first",
+		));
+	}
+
+	let trees = trees
+		.iter()
+		.map(|x| diff::tree_entries(repo, &x.0))
+		.collect::<io::Result<Vec<_>>>()?;
+	let mut current = diff::index_entries(&index);
+
+	let results = match (merge, trees.as_slice()) {
+		(false, [tree]) => {
+			index.entries.clear();
+			index.tree = None;
+			current.clear();
+			oneway(&current, tree)
+		}
+		(true, [tree]) => oneway(&current, tree),
+		(true, [head, target]) => twoway(&current, head, target)?,
+		(true, [base, ours, theirs]) => threeway(&current, base, ours, theirs)?,
+		_ => {
+			return Err(io::Error::other(
+				"usage: read-tree (<tree-ish> | -m <tree-ish> [<tree-ish> [<tree-ish>]])",
+			))
+		}
+	};
+
+	// 書き換えるentryはworking directoryでも変更されていてはいけない(消えているのはよい)
+	// bare repositoryにはworking directoryが無いので確かめない
+	if merge && !repo.bare {
+		let worktree = diff::worktree_entries(repo, &index)?;
+		for (path, resolution) in results.iter() {
+			let changed = match resolution {
+				Resolution::Clean(side) => !merge::same(side.as_ref(), current.get(path)),
+				Resolution::Conflict { .. } => true,
+			};
+			let dirty = worktree
+				.get(path)
+				.is_some_and(|x| !merge::same(Some(x), current.get(path)));
+			if changed && current.contains_key(path) && dirty {
+				return Err(io::Error::other(format!(
+					"Entry '{}' not uptodate. Cannot merge.",
+					path
+				)));
+			}
+		}
+	}
+
+	for (path, resolution) in results {
+		match resolution {
+			// 変わらないentryはstat情報をそのまま残す
+			Resolution::Clean(side) if merge::same(side.as_ref(), current.get(&path)) => {}
+			Resolution::Clean(Some(side)) => {
+				index.add(Entry::unmerged(path, side.hash, side.mode, 0));
+			}
+			Resolution::Clean(None) => {
+				index.remove(&path);
+			}
+			Resolution::Conflict { base, ours, theirs } => {
+				index.remove(&path);
+				for (stage, side) in [(1, base), (2, ours), (3, theirs)] {
+					if let Some(side) = side {
+						index.add(Entry::unmerged(path.clone(), side.hash, side.mode, stage));
+					}
+				}
+			}
+		}
+	}
+	index.save(repo)
+}
+
+fn paths<'a>(sets: &[&'a BTreeMap<String, Side>]) -> Vec<&'a String> {
+	let mut paths: Vec<&String> = sets.iter().flat_map(|x| x.keys()).collect();
+	paths.sort();
+	paths.dedup();
+	paths
+}
+
+fn overwritten(path: &str) -> io::Error {
+	io::Error::other(format!(
+		"Entry '{}' would be overwritten by merge. Cannot merge.",
+		path
+	))
+}
+
+fn oneway(
+	current: &BTreeMap<String, Side>,
+	tree: &BTreeMap<String, Side>,
+) -> BTreeMap<String, Resolution> {
+	paths(&[current, tree])
+		.into_iter()
+		.map(|path| (path.clone(), Resolution::Clean(tree.get(path).cloned())))
+		.collect()
+}
+
+// indexがHと同じpathはMにし、そうでなければindexで変えた内容を残す
+fn twoway(
+	current: &BTreeMap<String, Side>,
+	head: &BTreeMap<String, Side>,
+	target: &BTreeMap<String, Side>,
+) -> io::Result<BTreeMap<String, Resolution>> {
+	paths(&[current, head, target])
+		.into_iter()
+		.map(|path| {
+			let (i, h, m) = (current.get(path), head.get(path), target.get(path));
+			let side = if merge::same(i, h) {
+				m
+			} else if merge::same(i, m) || merge::same(h, m) {
+				i
+			} else {
+				return Err(overwritten(path));
+			};
+			Ok((path.clone(), Resolution::Clean(side.cloned())))
+		})
+		.collect()
+}
+
+// indexにあるpathはoursと同じでなければならない(theirsの変更を取り込んだ後と同じならよい)
+// どちらかで消したpathは(gitの--aggressiveなしと同じく)解決せずにstageに残す
+fn threeway(
+	current: &BTreeMap<String, Side>,
+	base: &BTreeMap<String, Side>,
+	ours: &BTreeMap<String, Side>,
+	theirs: &BTreeMap<String, Side>,
+) -> io::Result<BTreeMap<String, Resolution>> {
+	merge::merge_trees(base, ours, theirs)
+		.into_iter()
+		.map(|(path, resolution)| {
+			let index = current.get(&path);
+			let taken = match &resolution {
+				Resolution::Clean(side) => {
+					merge::same(side.as_ref(), index) && merge::same(index, theirs.get(&path))
+				}
+				Resolution::Conflict { .. } => false,
+			};
+			if index.is_some() && !merge::same(index, ours.get(&path)) && !taken {
+				return Err(overwritten(&path));
+			}
+
+			let resolution = match resolution {
+				Resolution::Clean(None) if base.contains_key(&path) => Resolution::Conflict {
+					base: base.get(&path).cloned(),
+					ours: ours.get(&path).cloned(),
+					theirs: theirs.get(&path).cloned(),
+				},
+				x => x,
+			};
+			Ok((path, resolution))
+		})
+		.collect()
+}
@@ -0,0 +1,95 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+
+use chrono::{DateTime, FixedOffset};
+
+use crate::hash::Hash;
+use crate::merge;
+use crate::refs::{self, ReflogEntry};
+use crate::repository::Repository;
+use crate::revparse;
+use crate::tempfile::TempFile;
+
+// refのreflogを新しい順に "<hash> <name>@{<n>}: <message>" の形で出す(reflog show)
+// "HEAD@{n}" で指定できるのと同じ番号になる
+pub fn reflog_show(repo: &Repository, name: &str) -> io::Result<()> {
+	let full = revparse::dwim(repo, name)?.ok_or_else(|| {
+		io::Error::other(format!(
+			"ambiguous argument '{}': unknown revision or path not in the working tree.",
+			name
+		))
+	})?;
+	let entries = refs::read_reflog(repo, &full)?;
+	let mut out = io::stdout().lock();
+	for (i, entry) in entries.iter().rev().enumerate() {
+		writeln!(
+			out,
+			"{} {}@{{{}}}: {}",
+			&entry.new.to_hex()[..7],
+			name,
+			i,
+			entry.message
+		)?;
+	}
+	Ok(())
+}
+
+// refのreflog(.git/logs/<ref>)から古いentryを消す
+// expireより前のentryは全て消し、expire_unreachableより前のentryは
+// 新しい値か古い値のcommitがrefの先から辿れなくなっていれば消す(gitと同じく両方を見る)
+// Noneならその条件では消さない
+pub fn reflog_expire(
+	repo: &Repository,
+	name: &str,
+	expire: Option<DateTime<FixedOffset>>,
+	expire_unreachable: Option<DateTime<FixedOffset>>,
+) -> io::Result<()> {
+	let name = revparse::dwim(repo, name)?.unwrap_or_else(|| String::from(name));
+	let path = repo.reflog_path(&name);
+	let content = match fs::read_to_string(&path) {
+		Ok(content) => content,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+		Err(e) => return Err(e),
+	};
+
+	let reachable = match (expire_unreachable, refs::resolve(repo, &name)?) {
+		(Some(_), Some(tip)) => {
+			let tip = Hash::from_hex(&tip).ok_or_else(|| {
+				io::Error::new(
+					io::ErrorKind::InvalidData,
+					format!("{}: bad object name {}", name, tip),
+				)
+			})?;
+			merge::ancestors(repo, &tip)?
+		}
+		_ => HashSet::new(),
+	};
+
+	let mut kept = String::new();
+	for line in content.lines() {
+		let entry = ReflogEntry::from(line)
+			.map_err(|e| e.context(format!("{}: bad reflog entry {:?}", name, line)))?;
+
+		let mut keep = expire.is_none_or(|x| entry.date >= x);
+		if keep && expire_unreachable.is_some_and(|x| entry.date < x) {
+			// 0のhashはrefを作った、または消したことを表すので辿れるものとして扱う
+			keep = [&entry.old, &entry.new]
+				.iter()
+				.all(|x| x.0.iter().all(|&b| b == 0) || reachable.contains(x));
+		}
+
+		if keep {
+			kept.push_str(line);
+			kept.push('\n');
+		}
+	}
+
+	if kept != content {
+		let dir = path.parent().unwrap_or(&repo.gitdir);
+		let mut file = TempFile::new(dir, "tmp_reflog")?;
+		file.write_all(kept.as_bytes())?;
+		file.persist(&path)?;
+	}
+	Ok(())
+}
@@ -0,0 +1,44 @@
+use std::io;
+
+use crate::refs;
+use crate::repository::Repository;
+use crate::revparse;
+use crate::store;
+
+// originalを読んだときにreplacementが返るようにrefs/replace/<original>を作る
+// forceでなければ既にある置き換えは上書きしない
+pub fn replace(
+	repo: &Repository,
+	original: &str,
+	replacement: &str,
+	force: bool,
+) -> io::Result<()> {
+	let object = revparse::resolve(repo, original)?;
+	let target = revparse::resolve(repo, replacement)?;
+
+	let name = format!("refs/replace/{}", object);
+	if !force && refs::read_ref(repo, &name)?.is_some() {
+		return Err(io::Error::other(format!(
+			"replace ref '{}' already exists",
+			name
+		)));
+	}
+	if object.to_hex() == target.to_hex() {
+		return Err(io::Error::other(format!(
+			"new object is the same as the old one: '{}'",
+			object
+		)));
+	}
+
+	// 置き換える前のobjectの種類と比べる
+	let kind = store::read_original(repo, &object.0)?.0;
+	let new_kind = store::read_original(repo, &target.0)?.0;
+	if kind != new_kind {
+		return Err(io::Error::other(format!(
+			"Objects must be of the same type.\n'{}' points to a replaced object of type '{}'\nwhile '{}' points to a replacement object of type '{}'.",
+			original, kind, replacement, new_kind
+		)));
+	}
+
+	refs::update_ref(repo, &name, &target.to_hex())
+}
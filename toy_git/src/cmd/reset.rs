@@ -0,0 +1,141 @@
+use std::io::{self, Write};
+
+use crate::diff;
+use crate::filter::Filters;
+use crate::index::{Entry, Index};
+use crate::merge;
+use crate::object::ObjectKind;
+use crate::pretty;
+use crate::refs;
+use crate::repository::Repository;
+use crate::revparse;
+use crate::store;
+use crate::worktree::Worktree;
+
+use super::checkout::{checkout_entry, switch_tree};
+
+pub enum Mode {
+	Soft,  // HEADのbranchだけを動かす
+	Mixed, // indexもそのtreeに合わせる。working directoryは変えない
+	Hard,  // indexとworking directoryをそのtreeに合わせる
+}
+
+// HEADのbranchをrevに動かし、modeに応じてindexとworking directoryもそのtreeに合わせる
+// 動かす前のHEADはORIG_HEADに残すので、"reset ORIG_HEAD" で戻せる
+// soft以外ではmergeの途中の状態を捨て、hardではcommitされていない変更も捨てる
+pub fn reset(repo: &Repository, rev: &str, mode: &Mode) -> io::Result<()> {
+	match mode {
+		Mode::Soft if merge::merge_head(repo)?.is_some() => {
+			return Err(io::Error::other(
+				"Cannot do a soft reset in the middle of a merge.",
+			))
+		}
+		Mode::Soft => {}
+		Mode::Mixed if repo.bare => {
+			return Err(io::Error::other(
+				"mixed reset is not allowed in a bare repository",
+			))
+		}
+		Mode::Mixed => {}
+		Mode::Hard => repo.require_worktree()?,
+	}
+	let commit = revparse::peel(
+		repo,
+		revparse::resolve(repo, rev)?,
+		Some(ObjectKind::Commit),
+	)?;
+	let tree = revparse::peel(repo, commit.clone(), Some(ObjectKind::Tree))?;
+
+	let mut index = Index::load(repo)?;
+	match mode {
+		Mode::Soft => {}
+		Mode::Mixed => reset_index(repo, &mut index, &tree.0)?,
+		Mode::Hard => reset_tree(repo, &mut index, &tree.0)?,
+	}
+	if !matches!(mode, Mode::Soft) {
+		index.save(repo)?;
+	}
+
+	if let Some(head) = refs::resolve(repo, "HEAD")? {
+		refs::update_ref(repo, "ORIG_HEAD", &head)?;
+	}
+	refs::update_ref_logged(
+		repo,
+		"HEAD",
+		&commit.to_hex(),
+		&format!("reset: moving to {}", rev),
+	)?;
+
+	let mut out = io::stdout().lock();
+	match mode {
+		Mode::Soft => {}
+		Mode::Mixed => {
+			merge::clear_state(repo)?;
+			let changes = diff::compare(
+				&diff::index_entries(&index),
+				&diff::worktree_entries(repo, &index)?,
+			);
+			if !changes.is_empty() {
+				writeln!(out, "Unstaged changes after reset:")?;
+			}
+			for change in changes {
+				writeln!(out, "{}\t{}", change.status(), change.path)?;
+			}
+		}
+		Mode::Hard => {
+			merge::clear_state(repo)?;
+			let message = store::read_commit(repo, &commit.0)?.message;
+			writeln!(
+				out,
+				"HEAD is now at {} {}",
+				&commit.to_hex()[..7],
+				pretty::subject(&message)
+			)?;
+		}
+	}
+	Ok(())
+}
+
+// indexをtreeの内容にする。working directoryは触らない
+// 内容の変わらないentryはstat情報を残し、conflictしているpathはtreeの内容で置き換える
+fn reset_index(repo: &Repository, index: &mut Index, tree: &[u8]) -> io::Result<()> {
+	let entries = diff::tree_entries(repo, tree)?;
+	let current = diff::index_entries(index);
+	let unmerged: Vec<String> = index.unmerged().into_iter().map(String::from).collect();
+	let stale: Vec<String> = index
+		.entries
+		.iter()
+		.map(|x| x.path.clone())
+		.filter(|x| !entries.contains_key(x))
+		.collect();
+	for path in stale {
+		index.remove(&path);
+	}
+	for (path, side) in entries {
+		if merge::same(Some(&side), current.get(&path)) && !unmerged.contains(&path) {
+			continue;
+		}
+		index.add(Entry::unmerged(path, side.hash, side.mode, 0));
+	}
+	Ok(())
+}
+
+// indexとworking directoryをtreeの内容に合わせる
+// switch_treeと違い、indexは変わらずworking directoryだけで変更されたファイルも戻す
+pub(super) fn reset_tree(repo: &Repository, index: &mut Index, tree: &[u8]) -> io::Result<()> {
+	switch_tree(repo, index, tree)?;
+
+	let worktree = Worktree::new(repo);
+	let filters = Filters::load(repo)?;
+	let entries = diff::tree_entries(repo, tree)?;
+	let changed = diff::compare(
+		&diff::index_entries(index),
+		&diff::worktree_entries(repo, index)?,
+	);
+	for change in changed {
+		if let Some(side) = entries.get(&change.path) {
+			checkout_entry(&worktree, &filters, index, &change.path, side.clone())?;
+		}
+	}
+	Ok(())
+}
@@ -0,0 +1,331 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::io::{self, Write};
+
+use crate::commit_graph;
+use crate::hash::Hash;
+use crate::merge;
+use crate::object::ObjectKind;
+use crate::reachable;
+use crate::refs;
+use crate::repository::Repository;
+use crate::revparse;
+use crate::store;
+use crate::walker::CommitWalker;
+use crate::wildmatch::wildmatch;
+
+pub struct Options {
+	pub objects: bool, // commitの後に、辿れるtreeとblobも "<hash> <path>" の形で出す
+	pub order: Order,
+	pub count: bool,      // commitの代わりにその数を出す
+	pub left_right: bool, // "A...B" の左から辿れるcommitに "<"、他に ">" を付ける。countなら左右の数
+}
+
+// commitを出す順
+#[derive(Clone, Copy, PartialEq)]
+pub enum Order {
+	Walk, // committerの日付の新しいものから辿った順。日付が狂っているとparentが子より先に出ることがある
+	Date, // 子を全て出してからparentを出し、その中では日付の新しい順(--date-order)
+	Topo, // 子を全て出してからparentを出し、1本の履歴をなるべく続けて出す(--topo-order)
+}
+
+// revsから辿れるcommitをcommitterの日付の新しい順に出力する。"^" で始まるrevから辿れるものは除く
+// "A..B" は "^A B"、"A...B" はAかBの片方からだけ辿れるもの("A B" からmerge baseを除いたもの)
+// --allなどで複数のrefから辿るときも、共通のcommitは1度だけ出す
+// orderがWalk以外なら、全て辿ってから並べ替える
+// objectsなら、その後に指定したcommit以外のobject(tagなど)、各commitのtreeから辿れるobjectの順に
+// gitと同じくtreeを先に、中身をtreeの順に出す。同じobjectは1度だけ出す
+pub fn rev_list(repo: &Repository, revs: &[String], opts: &Options) -> io::Result<()> {
+	let Revisions {
+		tips,
+		excluded,
+		left,
+		pending,
+	} = revisions(repo, revs, opts.objects)?;
+	let Walked { commits, trees } = walk(repo, &tips, &excluded)?;
+
+	let mut lefts = HashSet::new();
+	for hash in left.iter() {
+		lefts.extend(merge::ancestors(repo, hash)?);
+	}
+	let is_left = |hash: &str| Hash::from_hex(hash).is_some_and(|x| lefts.contains(&x));
+
+	let order = sort(&commits, opts.order);
+	let mut out = io::BufWriter::new(io::stdout().lock());
+	if opts.count {
+		let n = commits.iter().filter(|(hash, _, _)| is_left(hash)).count();
+		match opts.left_right {
+			true => writeln!(out, "{}\t{}", n, commits.len() - n)?,
+			false => writeln!(out, "{}", commits.len())?,
+		}
+		return out.flush();
+	}
+	for i in order.iter() {
+		let hash = &commits[*i].0;
+		match (opts.left_right, is_left(hash)) {
+			(true, true) => writeln!(out, "<{}", hash)?,
+			(true, false) => writeln!(out, ">{}", hash)?,
+			(false, _) => writeln!(out, "{}", hash)?,
+		}
+	}
+	if !opts.objects {
+		return out.flush();
+	}
+
+	// 除くrevから辿れるobjectは出さない
+	let mut shown: HashSet<Hash> = reachable::objects(repo, &excluded, &[])?
+		.into_iter()
+		.collect();
+	for (hash, name) in pending {
+		show(repo, &mut out, &mut shown, hash, &name)?;
+	}
+	for i in order {
+		let tree = Hash::from_hex(&trees[i])
+			.ok_or_else(|| io::Error::other(format!("bad tree {}", trees[i])))?;
+		show(repo, &mut out, &mut shown, tree, "")?;
+	}
+	out.flush()
+}
+
+pub(super) struct Revisions {
+	pub tips: Vec<Hash>,              // 辿り始めるcommit
+	pub excluded: Vec<Hash>,          // ここから辿れるcommitは除く
+	pub left: Vec<Hash>,              // "A...B" のA
+	pub pending: Vec<(Hash, String)>, // objectsのとき、commit以外のobjectと、その名前
+}
+
+// revsを辿り始めるcommitと除くobjectなどに分ける
+pub(super) fn revisions(
+	repo: &Repository,
+	revs: &[String],
+	objects: bool,
+) -> io::Result<Revisions> {
+	let mut tips = Vec::new();
+	let mut pending = Vec::new(); // commit以外のobjectと、その名前
+	let mut excluded = Vec::new();
+	let mut named = Vec::new(); // (rev, hash, --allなどで選んだrefか)
+	let mut left = Vec::new(); // "A...B" のA
+	let side = |x: &str| String::from(if x.is_empty() { "HEAD" } else { x });
+	for rev in revs {
+		if let Some(rev) = rev.strip_prefix('^') {
+			excluded.push(revparse::resolve(repo, rev)?);
+		} else if let Some((a, b)) = rev.split_once("...") {
+			let (a, b) = (side(a), side(b));
+			let commit = |x: &str| {
+				let hash = revparse::resolve(repo, x)?;
+				revparse::peel(repo, hash, Some(ObjectKind::Commit))
+			};
+			let (ours, theirs) = (commit(&a)?, commit(&b)?);
+			excluded.extend(merge::merge_base_all(repo, &ours, &theirs)?);
+			left.push(ours.clone());
+			named.push((a, ours, false));
+			named.push((b, theirs, false));
+		} else if let Some((a, b)) = rev.split_once("..") {
+			excluded.push(revparse::resolve(repo, &side(a))?);
+			let b = side(b);
+			named.push((b.clone(), revparse::resolve(repo, &b)?, false));
+		} else if let Some(refs) = ref_tips(repo, rev)? {
+			named.extend(refs.into_iter().map(|(name, hash)| (name, hash, true)));
+		} else {
+			named.push((rev.clone(), revparse::resolve(repo, rev)?, false));
+		}
+	}
+
+	for (rev, hash, from_refs) in named {
+		// gitと同じく、tagはrevを、treeとblobは "<rev>:<path>" のpathを名前にする
+		let path = rev
+			.split_once(':')
+			.map(|(_, path)| path)
+			.unwrap_or_default();
+		let mut kind = store::read_object_header(repo, &hash.0)?.0;
+		let mut target = hash;
+		if kind == ObjectKind::Tag {
+			if objects {
+				pending.push((target.clone(), rev.clone()));
+			}
+			target = revparse::peel(repo, target, None)?;
+			kind = store::read_object_header(repo, &target.0)?.0;
+		}
+		match kind {
+			ObjectKind::Commit => tips.push(target),
+			_ if objects => pending.push((target, String::from(path))),
+			// --allなどで選んだrefがcommitを指していなければ無視する
+			_ if from_refs => {}
+			kind => {
+				return Err(io::Error::other(format!(
+					"object {} is a {}, not a commit",
+					target, kind
+				)))
+			}
+		}
+	}
+
+	Ok(Revisions {
+		tips,
+		excluded,
+		left,
+		pending,
+	})
+}
+
+pub(super) struct Walked {
+	pub commits: Vec<(String, Vec<String>, i64)>, // 辿った順の(hash, parents, 日付)
+	pub trees: Vec<String>,                       // それぞれのcommitのtree
+}
+
+// excludedから辿れないcommitをtipsからcommitterの日付の新しい順に辿る
+pub(super) fn walk(repo: &Repository, tips: &[Hash], excluded: &[Hash]) -> io::Result<Walked> {
+	let mut hidden = HashSet::new();
+	for hash in excluded.iter() {
+		if let Ok(commit) = revparse::peel(repo, hash.clone(), Some(ObjectKind::Commit)) {
+			hidden.extend(merge::ancestors(repo, &commit)?);
+		}
+	}
+
+	let mut walker = CommitWalker::new(repo);
+	walker.hide(hidden);
+	for tip in tips {
+		walker.push(tip.clone())?;
+	}
+	let mut commits = Vec::new(); // (hash, parents, 日付)
+	let mut trees = Vec::new();
+	while let Some((hash, date)) = walker.pop() {
+		// commit-graphにあればcommitを読まずに済ませる
+		let (tree, parents) = match commit_graph::find(repo, &hash)? {
+			Some(commit) => (
+				hex::encode(&commit.tree),
+				commit.parents.iter().map(hex::encode).collect(),
+			),
+			None => {
+				let commit = store::read_commit(repo, &hash.0)?;
+				(commit.tree, commit.parents)
+			}
+		};
+		trees.push(tree);
+		for parent in parents.iter() {
+			let parent = Hash::from_hex(parent)
+				.ok_or_else(|| io::Error::other(format!("bad parent in commit {}", hash)))?;
+			walker.push(parent)?;
+		}
+		commits.push((hash.to_hex(), parents, date));
+	}
+	Ok(Walked { commits, trees })
+}
+
+// 辿った順に並べた(hash, parents, 日付)を、orderの順に並べ替えたときの位置
+// gitと同じく、Topoでは最後に出せるようになったものを、Dateでは日付が同じなら先に出せるようになったものを先にする
+pub(super) fn sort(commits: &[(String, Vec<String>, i64)], order: Order) -> Vec<usize> {
+	if order == Order::Walk {
+		return (0..commits.len()).collect();
+	}
+	let position: HashMap<&str, usize> = commits
+		.iter()
+		.enumerate()
+		.map(|(i, (hash, _, _))| (hash.as_str(), i))
+		.collect();
+	// まだ出していない子の数
+	let mut children = vec![0; commits.len()];
+	for (_, parents, _) in commits.iter() {
+		for parent in parents.iter().filter_map(|x| position.get(x.as_str())) {
+			children[*parent] += 1;
+		}
+	}
+
+	let mut ready = BinaryHeap::new();
+	let mut count = 0;
+	let mut push = |ready: &mut BinaryHeap<_>, i: usize| {
+		let key = match order {
+			Order::Topo => count as i64,
+			_ => commits[i].2,
+		};
+		ready.push((key, Reverse(count), i));
+		count += 1;
+	};
+	// 最初に出せるものは辿った順に出す
+	let tips: Vec<usize> = (0..commits.len()).filter(|i| children[*i] == 0).collect();
+	match order {
+		Order::Topo => tips.into_iter().rev().for_each(|i| push(&mut ready, i)),
+		_ => tips.into_iter().for_each(|i| push(&mut ready, i)),
+	}
+
+	let mut sorted = Vec::new();
+	while let Some((_, _, i)) = ready.pop() {
+		sorted.push(i);
+		for parent in commits[i].1.iter() {
+			if let Some(&j) = position.get(parent.as_str()) {
+				children[j] -= 1;
+				if children[j] == 0 {
+					push(&mut ready, j);
+				}
+			}
+		}
+	}
+	sorted
+}
+
+// --all、--branches[=<pattern>]、--tags[=<pattern>] で選んだrefの短い名前とhash
+// gitと同じく--allはHEADも含み、patternにglobの文字が無ければ "<pattern>/*" とみなす
+// これらのoptionでなければNone
+pub(super) fn ref_tips(repo: &Repository, option: &str) -> io::Result<Option<Vec<(String, Hash)>>> {
+	let (prefix, pattern) = match option.split_once('=') {
+		Some(("--branches", x)) => ("refs/heads/", Some(x)),
+		Some(("--tags", x)) => ("refs/tags/", Some(x)),
+		None if option == "--branches" => ("refs/heads/", None),
+		None if option == "--tags" => ("refs/tags/", None),
+		None if option == "--all" => ("refs/", None),
+		_ => return Ok(None),
+	};
+	let pattern = pattern.map(|x| match x.contains(['*', '?', '[']) {
+		true => format!("{}{}", prefix, x),
+		false => format!("{}{}/*", prefix, x),
+	});
+
+	let mut tips = Vec::new();
+	for (name, hash) in refs::list(repo, prefix)? {
+		if pattern
+			.as_ref()
+			.is_some_and(|x| !wildmatch(x, &name, false))
+		{
+			continue;
+		}
+		if let Some(hash) = Hash::from_hex(&hash) {
+			tips.push((String::from(refs::shorten(&name)), hash));
+		}
+	}
+	if option == "--all" {
+		if let Some(hash) = refs::resolve(repo, "HEAD")?.and_then(|x| Hash::from_hex(&x)) {
+			tips.push((String::from("HEAD"), hash));
+		}
+	}
+	Ok(Some(tips))
+}
+
+// objectを "<hash> <path>" の形で出し、treeなら中身も出す
+fn show<W: Write>(
+	repo: &Repository,
+	out: &mut W,
+	shown: &mut HashSet<Hash>,
+	hash: Hash,
+	path: &str,
+) -> io::Result<()> {
+	if !shown.insert(hash.clone()) {
+		return Ok(());
+	}
+	writeln!(out, "{} {}", hash, path)?;
+	if store::read_object_header(repo, &hash.0)?.0 != ObjectKind::Tree {
+		return Ok(());
+	}
+
+	for file in store::read_tree(repo, &hash.0)?.contents {
+		// submoduleのcommitはこのrepositoryには無い
+		if file.mode == 160000 {
+			continue;
+		}
+		let child = match path {
+			"" => file.name.clone(),
+			path => format!("{}/{}", path, file.name),
+		};
+		show(repo, out, shown, Hash(file.hash), &child)?;
+	}
+	Ok(())
+}
@@ -0,0 +1,64 @@
+use std::env;
+use std::io::{self, Write};
+
+use crate::repository::Repository;
+use crate::revparse;
+
+// 各revisionを解決してhashを1行ずつ出力する
+// --show-toplevelなどrepositoryの場所を尋ねるものは、その答えを順番の通りに出力する
+// verifyの場合は1つのobjectに解決できるrevisionがちょうど1つだけ与えられなければならない
+pub fn rev_parse(repo: &Repository, specs: &[String], verify: bool) -> io::Result<()> {
+	let mut out = io::stdout().lock();
+	if verify {
+		let hash = match specs {
+			[spec] => revparse::resolve(repo, spec).ok(),
+			_ => None,
+		}
+		.ok_or_else(|| io::Error::other("Needed a single revision"))?;
+
+		writeln!(out, "{}", hash)?;
+		return Ok(());
+	}
+
+	for spec in specs {
+		match spec.as_str() {
+			"--show-toplevel" | "--git-dir" | "--is-inside-work-tree" | "--show-prefix" => {
+				writeln!(out, "{}", location(repo, spec)?)?
+			}
+			_ => writeln!(out, "{}", revparse::resolve(repo, spec)?)?,
+		}
+	}
+	Ok(())
+}
+
+// cwdから見たrepositoryの場所
+// gitと同じく、gitdirの中はworking treeの外として扱う
+fn location(repo: &Repository, query: &str) -> io::Result<String> {
+	let cwd = env::current_dir()?.canonicalize()?;
+	let in_worktree = !repo.bare && !cwd.starts_with(&repo.gitdir);
+
+	match query {
+		"--show-toplevel" if in_worktree => Ok(repo.workdir.display().to_string()),
+		"--show-toplevel" => Err(io::Error::other(
+			"this operation must be run in a work tree",
+		)),
+		// cwdがgitdirかtoplevelなら相対path、それ以外は絶対path
+		"--git-dir" if cwd == repo.gitdir => Ok(String::from(".")),
+		"--git-dir" => match repo.gitdir.strip_prefix(&cwd) {
+			Ok(x) if cwd == repo.workdir => Ok(x.display().to_string()),
+			_ => Ok(repo.gitdir.display().to_string()),
+		},
+		"--is-inside-work-tree" => Ok(in_worktree.to_string()),
+		// toplevelからcwdへのpath。末尾に "/" を付け、toplevelやworking treeの外では空
+		_ => match cwd.strip_prefix(&repo.workdir) {
+			Ok(x) if in_worktree && x.components().next().is_some() => Ok(format!(
+				"{}/",
+				x.components()
+					.map(|x| x.as_os_str().to_string_lossy())
+					.collect::<Vec<_>>()
+					.join("/")
+			)),
+			_ => Ok(String::new()),
+		},
+	}
+}
@@ -0,0 +1,152 @@
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::diff;
+use crate::index::Index;
+use crate::object::ObjectKind;
+use crate::refs;
+use crate::repository::Repository;
+use crate::revparse;
+use crate::worktree::Worktree;
+
+pub struct Options {
+	pub recursive: bool, // -r: ディレクトリの中のファイルも消す
+	pub cached: bool,    // indexからだけ消し、working directoryのファイルは残す
+	pub force: bool,     // 変更のあるファイルも消す
+	pub dry_run: bool,   // 消すpathを出力するだけで何も消さない
+}
+
+// pathsに一致するindexのファイルを消し、消したpathを "rm '<path>'" と出力する
+// ディレクトリは-rのときだけ、その中のファイルを全て消す
+// forceでなければgitと同じく、消すと失われる変更のあるファイルがあればError
+pub fn rm(repo: &Repository, paths: &[String], opts: &Options) -> io::Result<()> {
+	repo.require_worktree()?;
+	let mut index = Index::load(repo)?;
+
+	let mut removed = BTreeSet::new();
+	for path in paths {
+		let path = repo.relative_path(Path::new(path))?;
+		let prefix = format!("{}/", path);
+		let matched: Vec<&str> = index
+			.entries
+			.iter()
+			.map(|x| x.path.as_str())
+			.filter(|x| path.is_empty() || *x == path || x.starts_with(&prefix))
+			.collect();
+		if matched.is_empty() {
+			return Err(io::Error::other(format!(
+				"pathspec '{}' did not match any files",
+				path
+			)));
+		}
+		if !opts.recursive && !matched.contains(&path.as_str()) {
+			return Err(io::Error::other(format!(
+				"not removing '{}' recursively without -r",
+				path
+			)));
+		}
+		removed.extend(matched.into_iter().map(String::from));
+	}
+
+	if !opts.force {
+		check_changes(repo, &index, &removed, opts.cached)?;
+	}
+
+	let worktree = Worktree::new(repo);
+	let mut out = io::stdout().lock();
+	for path in removed.iter() {
+		writeln!(out, "rm '{}'", path)?;
+		if opts.dry_run {
+			continue;
+		}
+		index.remove(path);
+		if !opts.cached {
+			worktree.remove_file(path)?;
+		}
+	}
+	match opts.dry_run {
+		true => Ok(()),
+		false => index.save(repo),
+	}
+}
+
+// gitのcheck_local_modと同じく、HEADともworking directoryとも違う内容がindexにあるファイルは消さない
+// cachedでなければ、HEADと違う内容がindexにあるものや、working directoryで変更されたものも消さない
+fn check_changes(
+	repo: &Repository,
+	index: &Index,
+	paths: &BTreeSet<String>,
+	cached: bool,
+) -> io::Result<()> {
+	let head = match refs::resolve(repo, "HEAD")? {
+		Some(_) => {
+			let tree = revparse::resolve(repo, "HEAD")?;
+			let tree = revparse::peel(repo, tree, Some(ObjectKind::Tree))?;
+			diff::tree_entries(repo, &tree.0)?
+		}
+		None => Default::default(),
+	};
+	let staged = diff::index_entries(index);
+	let worktree = diff::worktree_entries(repo, index)?;
+
+	let mut both = Vec::new();
+	let mut in_index = Vec::new();
+	let mut local = Vec::new();
+	for path in paths {
+		// working directoryに無いファイルは消しても何も失われない
+		let (Some(entry), Some(file)) = (staged.get(path), worktree.get(path)) else {
+			continue;
+		};
+		let changed_in_worktree = (file.mode, &file.hash) != (entry.mode, &entry.hash);
+		let changed_from_head = head
+			.get(path)
+			.is_none_or(|x| (x.mode, &x.hash) != (entry.mode, &entry.hash));
+
+		if changed_in_worktree && changed_from_head {
+			both.push(path.as_str());
+		} else if !cached && changed_from_head {
+			in_index.push(path.as_str());
+		} else if !cached && changed_in_worktree {
+			local.push(path.as_str());
+		}
+	}
+
+	let mut message = String::new();
+	let mut report = |paths: &[&str], what: &str, hint: &str| {
+		if paths.is_empty() {
+			return;
+		}
+		let (subject, verb) = match paths.len() {
+			1 => ("file", "has"),
+			_ => ("files", "have"),
+		};
+		if !message.is_empty() {
+			message.push('\n');
+		}
+		message.push_str(&format!("the following {} {} {}:\n", subject, verb, what));
+		for path in paths {
+			message.push_str(&format!("    {}\n", path));
+		}
+		message.push_str(hint);
+	};
+	report(
+		&both,
+		"staged content different from both the\nfile and the HEAD",
+		"(use -f to force removal)",
+	);
+	report(
+		&in_index,
+		"changes staged in the index",
+		"(use --cached to keep the file, or -f to force removal)",
+	);
+	report(
+		&local,
+		"local modifications",
+		"(use --cached to keep the file, or -f to force removal)",
+	);
+	match message.is_empty() {
+		true => Ok(()),
+		false => Err(io::Error::other(message)),
+	}
+}
@@ -0,0 +1,59 @@
+use std::cmp::Reverse;
+use std::io::{self, Write};
+
+use crate::hash::Hash;
+use crate::pretty;
+use crate::repository::Repository;
+use crate::store;
+
+use super::rev_list::{revisions, walk, Revisions, Walked};
+
+pub struct Options {
+	pub numbered: bool, // commitの数の多い順に並べる
+	pub summary: bool,  // subjectを出さず数だけ出す
+	pub email: bool,    // 名前の後に "<email>" を付ける
+}
+
+// revsから辿れるcommitをauthorごとにまとめ、"name (数):" の後に字下げしたsubjectを古い順に出す
+// authorは名前の順に、numberedなら数の多い順(同じなら名前の順)に並べる。revsが空ならHEADから
+pub fn shortlog(repo: &Repository, revs: &[String], opts: &Options) -> io::Result<()> {
+	let revs = match revs {
+		[] => vec![String::from("HEAD")],
+		revs => revs.to_vec(),
+	};
+	let Revisions { tips, excluded, .. } = revisions(repo, &revs, false)?;
+	let Walked { commits, .. } = walk(repo, &tips, &excluded)?;
+
+	let mut authors: Vec<(String, Vec<String>)> = Vec::new();
+	for (hash, _, _) in commits {
+		let bad = || io::Error::other(format!("bad commit {}", hash));
+		let commit = store::read_commit(repo, &Hash::from_hex(&hash).ok_or_else(bad)?.0)?;
+		let name = match opts.email {
+			true => format!("{} <{}>", commit.author.name, commit.author.email),
+			false => commit.author.name.clone(),
+		};
+		let subject = pretty::subject(&commit.message);
+		match authors.iter_mut().find(|(x, _)| *x == name) {
+			Some((_, subjects)) => subjects.push(subject),
+			None => authors.push((name, vec![subject])),
+		}
+	}
+	authors.sort_by(|a, b| a.0.cmp(&b.0));
+	if opts.numbered {
+		authors.sort_by_key(|x| Reverse(x.1.len()));
+	}
+
+	let mut out = io::BufWriter::new(io::stdout().lock());
+	for (name, subjects) in authors {
+		if opts.summary {
+			writeln!(out, "{:>6}\t{}", subjects.len(), name)?;
+			continue;
+		}
+		writeln!(out, "{} ({}):", name, subjects.len())?;
+		for subject in subjects.iter().rev() {
+			writeln!(out, "      {}", subject)?;
+		}
+		writeln!(out)?;
+	}
+	out.flush()
+}
@@ -0,0 +1,338 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::object::{Commit, ObjectKind};
+use crate::pretty;
+use crate::refs;
+use crate::repository::Repository;
+use crate::revparse;
+use crate::store;
+
+// commitのflag。下位2bitは辿るときに使い、それより上の1bitずつがどのbranchから辿れるかを表す
+const SEEN: u32 = 1;
+const UNINTERESTING: u32 = 1 << 1;
+const REV_SHIFT: usize = 2;
+const MAX_REVS: usize = 32 - REV_SHIFT - 1;
+
+// branchesの先頭と、そこから辿れるcommitをgitのshow-branchと同じ形で出力する
+// 各commitの行の先頭には、branchごとに辿れるか("*" はHEAD、"+" はそれ以外、"-" はmerge)を並べる
+// 全てのbranchから辿れる最初のcommit(merge base)まで出す
+// branchesが空ならrefs/heads以下の全てのbranch
+pub fn show_branch(repo: &Repository, branches: &[String]) -> io::Result<()> {
+	let names: Vec<String> = match branches {
+		[] => refs::list(repo, "refs/heads")?
+			.into_iter()
+			.map(|(name, _)| String::from(&name["refs/heads/".len()..]))
+			.collect(),
+		branches => branches.to_vec(),
+	};
+	if names.len() > MAX_REVS {
+		return Err(io::Error::other(format!(
+			"cannot handle more than {} revs.",
+			MAX_REVS
+		)));
+	}
+
+	let mut walk = Walk {
+		repo,
+		commits: HashMap::new(),
+		seen: Vec::new(),
+	};
+	let mut tips = Vec::new();
+	let mut list = Vec::new();
+	for (i, name) in names.iter().enumerate() {
+		let hash = revparse::resolve(repo, name)?;
+		let hash = revparse::peel(repo, hash, Some(ObjectKind::Commit))?.to_hex();
+		walk.load(&hash)?;
+		walk.mark_seen(&hash);
+		walk.node(&hash).flags |= 1 << (i + REV_SHIFT);
+		if !list.contains(&hash) {
+			walk.insert_by_date(&mut list, hash.clone());
+		}
+		tips.push(hash);
+	}
+
+	let all_revs = ((1u32 << names.len()) - 1) << REV_SHIFT;
+	walk.join(list, all_revs)?;
+
+	// HEADのbranchを指定していれば、その列に "*" を付ける
+	let head = refs::resolve(repo, "HEAD")?;
+	let head_target = refs::head_target(repo)?;
+	let mut head_at = None;
+	for (i, name) in names.iter().enumerate() {
+		let full = revparse::dwim(repo, name)?;
+		if full.as_deref() == Some(head_target.as_str()) && head.as_deref() == Some(&tips[i]) {
+			head_at = Some(i);
+		}
+	}
+
+	let mut seen = std::mem::take(&mut walk.seen);
+	seen.sort_by_key(|x| std::cmp::Reverse(walk.date(x)));
+
+	let mut out = io::stdout().lock();
+	if names.len() > 1 {
+		for (i, name) in names.iter().enumerate() {
+			let mark = if head_at == Some(i) { '*' } else { '!' };
+			let subject = pretty::subject(&walk.commits[&tips[i]].commit.message);
+			writeln!(out, "{}{} [{}] {}", " ".repeat(i), mark, name, subject)?;
+		}
+		writeln!(out, "{}", "-".repeat(names.len()))?;
+	}
+
+	let seen = walk.topo_order(seen);
+	let labels = walk.name_commits(&seen, &tips, &names);
+
+	for hash in seen {
+		let node = &walk.commits[&hash];
+		let is_merge_point = node.flags & all_revs == all_revs;
+		let is_merge = node.commit.parents.len() > 1;
+
+		if names.len() > 1 {
+			// 1つのbranchからしか辿れないmergeは省く
+			let count = (0..names.len())
+				.filter(|i| node.flags & (1 << (i + REV_SHIFT)) != 0)
+				.count();
+			if is_merge && count == 1 && !tips.contains(&hash) {
+				continue;
+			}
+
+			let marks: String = (0..names.len())
+				.map(|i| match node.flags & (1 << (i + REV_SHIFT)) != 0 {
+					false => ' ',
+					true if is_merge => '-',
+					true if head_at == Some(i) => '*',
+					true => '+',
+				})
+				.collect();
+			write!(out, "{} ", marks)?;
+		}
+
+		let label = match labels.get(&hash) {
+			Some((name, 0)) => name.clone(),
+			Some((name, 1)) => format!("{}^", name),
+			Some((name, generation)) => format!("{}~{}", name, generation),
+			None => String::from(&hash[..7]),
+		};
+		writeln!(out, "[{}] {}", label, pretty::subject(&node.commit.message))?;
+
+		if is_merge_point {
+			break;
+		}
+	}
+	Ok(())
+}
+
+struct Node {
+	commit: Commit,
+	flags: u32,
+}
+
+struct Walk<'a> {
+	repo: &'a Repository,
+	commits: HashMap<String, Node>,
+	seen: Vec<String>, // 見つけたcommit。新しく見つけたものほど前
+}
+
+impl Walk<'_> {
+	fn load(&mut self, hash: &str) -> io::Result<()> {
+		if !self.commits.contains_key(hash) {
+			let commit = store::read_commit(self.repo, &hex::decode(hash).unwrap_or_default())?;
+			self.commits
+				.insert(String::from(hash), Node { commit, flags: 0 });
+		}
+		Ok(())
+	}
+
+	fn node(&mut self, hash: &str) -> &mut Node {
+		self.commits.get_mut(hash).expect("commit is loaded")
+	}
+
+	fn date(&self, hash: &str) -> i64 {
+		self.commits[hash].commit.committer.ts.timestamp()
+	}
+
+	fn parents(&self, hash: &str) -> Vec<String> {
+		self.commits
+			.get(hash)
+			.map(|x| x.commit.parents.clone())
+			.unwrap_or_default()
+	}
+
+	fn mark_seen(&mut self, hash: &str) -> bool {
+		let node = self.node(hash);
+		if node.flags & SEEN != 0 {
+			return false;
+		}
+		node.flags |= SEEN;
+		self.seen.insert(0, String::from(hash));
+		true
+	}
+
+	// 日付が同じなら後から入れたものを後ろにする
+	fn insert_by_date(&self, list: &mut Vec<String>, hash: String) {
+		let date = self.date(&hash);
+		let at = list
+			.iter()
+			.position(|x| self.date(x) < date)
+			.unwrap_or(list.len());
+		list.insert(at, hash);
+	}
+
+	// 新しい順に辿ってparentにflagを伝える
+	// 全てのbranchから辿れるcommitより先はUNINTERESTINGにし、それだけになったら止める
+	fn join(&mut self, mut list: Vec<String>, all_revs: u32) -> io::Result<()> {
+		let all_mask = all_revs | SEEN | UNINTERESTING;
+		while !list.is_empty() {
+			if list
+				.iter()
+				.all(|x| self.commits[x].flags & UNINTERESTING != 0)
+			{
+				break;
+			}
+			let hash = list.remove(0);
+			let mut flags = self.commits[&hash].flags & all_mask;
+			self.mark_seen(&hash);
+			if flags & all_revs == all_revs {
+				flags |= UNINTERESTING;
+			}
+
+			for parent in self.parents(&hash) {
+				self.load(&parent)?;
+				if self.commits[&parent].flags & flags == flags {
+					continue;
+				}
+				self.mark_seen(&parent);
+				self.node(&parent).flags |= flags;
+				self.insert_by_date(&mut list, parent);
+			}
+		}
+
+		// merge baseやUNINTERESTINGなcommitのparentで、既に見つけたものもUNINTERESTINGにする
+		loop {
+			let mut changed = false;
+			for hash in self.seen.clone() {
+				let flags = self.commits[&hash].flags;
+				if flags & all_revs != all_revs && flags & UNINTERESTING == 0 {
+					continue;
+				}
+				for parent in self.parents(&hash) {
+					if let Some(node) = self.commits.get_mut(&parent) {
+						if node.flags & UNINTERESTING == 0 {
+							node.flags |= UNINTERESTING;
+							changed = true;
+						}
+					}
+				}
+			}
+			if !changed {
+				return Ok(());
+			}
+		}
+	}
+
+	// 子を全て出してからparentを出す。同時に出せるものは最後に出せるようになったものを先にする
+	fn topo_order(&self, commits: Vec<String>) -> Vec<String> {
+		let mut indegree: HashMap<&str, usize> = commits.iter().map(|x| (x.as_str(), 1)).collect();
+		for hash in commits.iter() {
+			for parent in self.commits[hash].commit.parents.iter() {
+				if let Some(count) = indegree.get_mut(parent.as_str()) {
+					*count += 1;
+				}
+			}
+		}
+
+		let mut stack: Vec<String> = commits
+			.iter()
+			.filter(|x| indegree[x.as_str()] == 1)
+			.rev()
+			.cloned()
+			.collect();
+		let mut sorted = Vec::new();
+		while let Some(hash) = stack.pop() {
+			for parent in self.commits[&hash].commit.parents.iter() {
+				if let Some(count) = indegree.get_mut(parent.as_str()) {
+					*count -= 1;
+					if *count == 1 {
+						stack.push(parent.clone());
+					}
+				}
+			}
+			sorted.push(hash);
+		}
+		sorted
+	}
+
+	// 各commitに "<branch>~<n>" や "<branch>^2" のような名前を付ける
+	// 先頭、最初のparentを辿った先、それ以外のparentの順に、gitのshow-branchと同じく付ける
+	fn name_commits(
+		&self,
+		seen: &[String],
+		tips: &[String],
+		names: &[String],
+	) -> HashMap<String, (String, usize)> {
+		let mut labels: HashMap<String, (String, usize)> = HashMap::new();
+		for hash in seen {
+			if let Some(i) = tips.iter().position(|x| x == hash) {
+				labels.entry(hash.clone()).or_insert((names[i].clone(), 0));
+			}
+		}
+
+		while seen
+			.iter()
+			.map(|x| self.name_first_parents(&mut labels, x))
+			.sum::<usize>()
+			> 0
+		{}
+
+		loop {
+			let mut named = 0;
+			for hash in seen {
+				let Some((name, generation)) = labels.get(hash).cloned() else {
+					continue;
+				};
+				for (nth, parent) in self.parents(hash).iter().enumerate() {
+					if labels.contains_key(parent) {
+						continue;
+					}
+					let base = match generation {
+						0 => name.clone(),
+						1 => format!("{}^", name),
+						n => format!("{}~{}", name, n),
+					};
+					let label = match nth {
+						0 => format!("{}^", base),
+						n => format!("{}^{}", base, n + 1),
+					};
+					labels.insert(parent.clone(), (label, 0));
+					named += 1;
+					self.name_first_parents(&mut labels, parent);
+				}
+			}
+			if named == 0 {
+				return labels;
+			}
+		}
+	}
+
+	// 名前の付いたcommitから、名前の無い最初のparentを辿って名前を付ける。付けた数を返す
+	fn name_first_parents(
+		&self,
+		labels: &mut HashMap<String, (String, usize)>,
+		hash: &str,
+	) -> usize {
+		let mut count = 0;
+		let mut hash = String::from(hash);
+		while let Some((name, generation)) = labels.get(&hash).cloned() {
+			let Some(parent) = self.parents(&hash).into_iter().next() else {
+				break;
+			};
+			if labels.contains_key(&parent) {
+				break;
+			}
+			labels.insert(parent.clone(), (name, generation + 1));
+			count += 1;
+			hash = parent;
+		}
+		count
+	}
+}
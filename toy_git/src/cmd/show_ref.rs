@@ -0,0 +1,51 @@
+use std::io::{self, Write};
+
+use crate::hash::Hash;
+use crate::object::ObjectKind;
+use crate::refs;
+use crate::repository::Repository;
+use crate::revparse;
+use crate::store;
+
+pub struct Options {
+	pub heads: bool,       // refs/heads 以下だけ
+	pub tags: bool,        // refs/tags 以下だけ(headsと両方指定すれば両方)
+	pub dereference: bool, // tagの場合はpeelした先も "<hash> <refname>^{}" として出す
+}
+
+// "<hash> <refname>" を名前順に出力する
+// patternsがあれば、refnameの末尾の "/" 区切りの部分が一致するものだけ
+pub fn show_ref(repo: &Repository, opts: &Options, patterns: &[String]) -> io::Result<()> {
+	for (name, hash) in refs::list(repo, "refs")? {
+		let kind = match (opts.heads, opts.tags) {
+			(false, false) => true,
+			(heads, tags) => {
+				(heads && refs::list_match(&name, "refs/heads"))
+					|| (tags && refs::list_match(&name, "refs/tags"))
+			}
+		};
+		let hit = patterns.is_empty()
+			|| patterns
+				.iter()
+				.any(|x| name == *x || name.ends_with(&format!("/{}", x)));
+		if !kind || !hit {
+			continue;
+		}
+
+		let mut out = io::stdout().lock();
+		writeln!(out, "{} {}", hash, name)?;
+
+		if opts.dereference {
+			let object = Hash::from_hex(&hash).ok_or_else(|| {
+				io::Error::new(
+					io::ErrorKind::InvalidData,
+					format!("{}: bad object name {}", name, hash),
+				)
+			})?;
+			if store::read_object(repo, &object.0)?.0 == ObjectKind::Tag {
+				writeln!(out, "{} {}^{{}}", revparse::peel(repo, object, None)?, name)?;
+			}
+		}
+	}
+	Ok(())
+}
@@ -0,0 +1,299 @@
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+
+use crate::diff::{self, Side};
+use crate::filter::Filters;
+use crate::hash::Hash;
+use crate::index::{Entry, Index};
+use crate::merge::{self, Resolution};
+use crate::object::{Commit, ObjectKind};
+use crate::pretty;
+use crate::refs;
+use crate::repository::Repository;
+use crate::store;
+use crate::tempfile::TempFile;
+use crate::worktree::Worktree;
+
+use super::checkout::write_content;
+use super::reset::reset_tree;
+use super::write_tree::write_entries;
+use super::{commit_tree, write_tree};
+
+const STASH: &str = "refs/stash";
+
+// indexとworking directoryの変更をcommitにしてrefs/stashに積み、HEADの状態に戻す
+// gitと同じく、indexのtreeを "index on ..." のcommitに、working directoryのtreeを
+// HEADとそのcommitを親にした "WIP on ..." のcommitにする
+pub fn stash_push(repo: &Repository, message: Option<&str>) -> io::Result<()> {
+	repo.require_worktree()?;
+	let head = refs::resolve(repo, "HEAD")?
+		.and_then(|x| Hash::from_hex(&x))
+		.ok_or_else(|| io::Error::other("You do not have the initial commit yet"))?;
+	let head_commit = store::read_commit(repo, &head.0)?;
+	let head_tree = hex::decode(&head_commit.tree).unwrap_or_default();
+
+	let index = Index::load(repo)?;
+	let staged = diff::index_entries(&index);
+	let worktree = diff::worktree_entries(repo, &index)?;
+	let mut out = io::stdout().lock();
+	if diff::compare(&diff::tree_entries(repo, &head_tree)?, &staged).is_empty()
+		&& diff::compare(&staged, &worktree).is_empty()
+	{
+		writeln!(out, "No local changes to save")?;
+		return Ok(());
+	}
+
+	let target = refs::head_target(repo)?;
+	let branch = match target.as_str() {
+		"HEAD" => "(no branch)",
+		target => refs::shorten(target),
+	};
+	let base = format!(
+		"{}: {} {}",
+		branch,
+		&head.to_hex()[..7],
+		pretty::subject(&head_commit.message)
+	);
+	let index_tree = hex::encode(write_tree(repo, None)?);
+	let index_commit = commit_tree(
+		repo,
+		&index_tree,
+		vec![head.to_hex()],
+		&format!("index on {}", base),
+		None,
+	)?;
+
+	// working directoryで変わったファイルの内容もobjectにする
+	let mut files = Vec::new();
+	for (path, side) in worktree.iter() {
+		let hash = match staged.get(path) {
+			Some(x) if x.hash == side.hash => side.hash.clone(),
+			_ => store::write_object(
+				repo,
+				&ObjectKind::Blob.encode(&diff::content(repo, path, side)?),
+			)?,
+		};
+		files.push((path.as_str(), side.mode, hash));
+	}
+	let entries: Vec<(&str, u32, &[u8])> = files
+		.iter()
+		.map(|(path, mode, hash)| (*path, *mode, hash.as_slice()))
+		.collect();
+	let worktree_tree = hex::encode(write_entries(repo, &entries)?);
+
+	let title = match message {
+		Some(message) => format!("On {}: {}", branch, message),
+		None => format!("WIP on {}", base),
+	};
+	let stash = hex::encode(commit_tree(
+		repo,
+		&worktree_tree,
+		vec![head.to_hex(), hex::encode(index_commit)],
+		&title,
+		None,
+	)?);
+
+	// gitと同じく、refs/stashのreflogは設定によらず作る
+	let path = repo.reflog_path(STASH);
+	if let Some(dir) = path.parent() {
+		fs::create_dir_all(dir)?;
+	}
+	OpenOptions::new().create(true).append(true).open(&path)?;
+	let old = refs::resolve(repo, STASH)?.unwrap_or_else(|| repo.hash_algo.null_hex());
+	refs::update_ref(repo, STASH, &stash)?;
+	refs::append_reflog(repo, STASH, &old, &stash, &title)?;
+
+	let mut index = index;
+	reset_tree(repo, &mut index, &head_tree)?;
+	index.save(repo)?;
+	writeln!(out, "Saved working directory and index state {}", title)?;
+	Ok(())
+}
+
+// "stash@{0}: WIP on main: ..." を新しい順に出力する
+pub fn stash_list(repo: &Repository) -> io::Result<()> {
+	let mut out = io::stdout().lock();
+	for (i, (_, message)) in entries(repo)?.iter().enumerate() {
+		writeln!(out, "stash@{{{}}}: {}", i, message)?;
+	}
+	Ok(())
+}
+
+// stashした変更を、stashしたときのHEADからの差分としてworking directoryに3-way mergeする
+// gitと同じく、indexには新しく作られたファイルだけを登録し、stashは消さない
+// conflictしたファイルにはmarkerを書き、indexにconflictとして登録する
+pub fn stash_apply(repo: &Repository, stash: Option<&str>) -> io::Result<()> {
+	apply(repo, stash).map(|_| ())
+}
+
+// stashを1つ消す。最新のものを消したらrefs/stashを次のものにする
+pub fn stash_drop(repo: &Repository, stash: Option<&str>) -> io::Result<()> {
+	let (n, hash) = find(repo, stash)?;
+	let path = repo.reflog_path(STASH);
+	let content = fs::read_to_string(&path)?;
+	let mut lines: Vec<&str> = content.lines().collect();
+	let at = lines.len() - 1 - n;
+	lines.remove(at);
+
+	match lines.last().and_then(|x| x.split(' ').nth(1)) {
+		Some(newest) => {
+			let kept: String = lines.iter().map(|x| format!("{}\n", x)).collect();
+			let dir = path.parent().unwrap_or(&repo.gitdir);
+			let mut file = TempFile::new(dir, "tmp_reflog")?;
+			file.write_all(kept.as_bytes())?;
+			file.persist(&path)?;
+			refs::update_ref(repo, STASH, newest)?;
+		}
+		None => {
+			refs::delete_ref(repo, STASH)?;
+			fs::remove_file(&path)?;
+		}
+	}
+	writeln!(
+		io::stdout().lock(),
+		"Dropped refs/stash@{{{}}} ({})",
+		n,
+		hash
+	)?;
+	Ok(())
+}
+
+// applyしてconflictしなければdropする
+pub fn stash_pop(repo: &Repository, stash: Option<&str>) -> io::Result<()> {
+	match apply(repo, stash)? {
+		true => stash_drop(repo, stash),
+		false => {
+			writeln!(
+				io::stdout().lock(),
+				"The stash entry is kept in case you need it again."
+			)?;
+			Ok(())
+		}
+	}
+}
+
+// refs/stashのreflogの (commit, message)。新しい順
+fn entries(repo: &Repository) -> io::Result<Vec<(String, String)>> {
+	let content = match fs::read_to_string(repo.reflog_path(STASH)) {
+		Ok(content) => content,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(e) => return Err(e),
+	};
+	let mut entries: Vec<(String, String)> = content
+		.lines()
+		.filter_map(|line| {
+			let (header, message) = line.split_once('\t').unwrap_or((line, ""));
+			let hash = header.split(' ').nth(1)?;
+			Some((String::from(hash), String::from(message)))
+		})
+		.collect();
+	entries.reverse();
+	Ok(entries)
+}
+
+// "stash@{<n>}" か "<n>" で指定されたstashの番号とcommit。Noneなら最新のもの
+fn find(repo: &Repository, stash: Option<&str>) -> io::Result<(usize, String)> {
+	let entries = entries(repo)?;
+	if entries.is_empty() {
+		return Err(io::Error::other("No stash entries found."));
+	}
+	let name = stash.unwrap_or("stash@{0}");
+	let n = name
+		.strip_prefix("stash@{")
+		.and_then(|x| x.strip_suffix('}'))
+		.unwrap_or(name)
+		.parse::<usize>()
+		.ok()
+		.filter(|&n| n < entries.len())
+		.ok_or_else(|| io::Error::other(format!("{} is not a valid reference", name)))?;
+	Ok((n, entries[n].0.clone()))
+}
+
+// conflictせずに適用できればtrue
+fn apply(repo: &Repository, stash: Option<&str>) -> io::Result<bool> {
+	repo.require_worktree()?;
+	let (_, hash) = find(repo, stash)?;
+	let commit = read_commit(repo, &hash)?;
+	let parent = commit
+		.parents
+		.first()
+		.ok_or_else(|| io::Error::other(format!("{} is not a stash-like commit", hash)))?;
+	let tree = |hex: &str| diff::tree_entries(repo, &hex::decode(hex).unwrap_or_default());
+	let base = tree(&read_commit(repo, parent)?.tree)?;
+	let theirs = tree(&commit.tree)?;
+
+	let mut index = Index::load(repo)?;
+	if !index.unmerged().is_empty() {
+		return Err(io::Error::other(
+			"Cannot apply a stash in the middle of a merge",
+		));
+	}
+	let ours = diff::index_entries(&index);
+	let results: BTreeMap<String, Resolution> = merge::merge_trees(&base, &ours, &theirs)
+		.into_iter()
+		.filter(|(path, x)| match x {
+			Resolution::Clean(side) => !merge::same(side.as_ref(), ours.get(path)),
+			Resolution::Conflict { .. } => true,
+		})
+		.collect();
+
+	// 書き換えるpathにstageされていない変更があれば止める
+	let dirty: Vec<String> = diff::compare(&ours, &diff::worktree_entries(repo, &index)?)
+		.into_iter()
+		.map(|x| x.path)
+		.filter(|x| results.contains_key(x))
+		.collect();
+	if !dirty.is_empty() {
+		let list: String = dirty.iter().map(|x| format!("\t{}\n", x)).collect();
+		return Err(io::Error::other(format!(
+			"Your local changes to the following files would be overwritten by merge:\n{}Please commit your changes or stash them before you merge.\nAborting",
+			list
+		)));
+	}
+
+	let worktree = Worktree::new(repo);
+	let filters = Filters::load(repo)?;
+	let read = |side: &Side| store::read_object(repo, &side.hash).map(|x| x.1);
+	let mut clean = true;
+	let mut out = io::stdout().lock();
+	for (path, resolution) in results {
+		match resolution {
+			Resolution::Clean(Some(side)) => {
+				write_content(&worktree, &filters, &path, read(&side)?, side.mode)?;
+				if !ours.contains_key(&path) {
+					let meta = worktree.stat(&path)?;
+					index.add(Entry::new(path, side.hash, &meta));
+				}
+			}
+			Resolution::Clean(None) => worktree.remove_file(&path)?,
+			Resolution::Conflict { base, ours, theirs } => {
+				clean = false;
+				let (Some(ours), Some(theirs)) = (ours, theirs) else {
+					writeln!(out, "CONFLICT (modify/delete): {}", path)?;
+					continue;
+				};
+				let (a, b) = (read(&ours)?, read(&theirs)?);
+				let content =
+					merge::conflict_markers(&a, &b, "Updated upstream", "Stashed changes");
+				write_content(&worktree, &filters, &path, content, ours.mode)?;
+
+				index.remove(&path);
+				for (stage, side) in [(1, base), (2, Some(ours)), (3, Some(theirs))] {
+					if let Some(side) = side {
+						index.add(Entry::unmerged(path.clone(), side.hash, side.mode, stage));
+					}
+				}
+				writeln!(out, "CONFLICT (content): Merge conflict in {}", path)?;
+			}
+		}
+	}
+	index.save(repo)?;
+	Ok(clean)
+}
+
+fn read_commit(repo: &Repository, hex: &str) -> io::Result<Commit> {
+	let hash =
+		Hash::from_hex(hex).ok_or_else(|| io::Error::other(format!("bad object name {}", hex)))?;
+	store::read_commit(repo, &hash.0)
+}
@@ -0,0 +1,371 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::diff::{self, Change, Renames};
+use crate::hash::Hash;
+use crate::ignore::Ignore;
+use crate::index::Index;
+use crate::refs;
+use crate::repository::Repository;
+use crate::store;
+use crate::upstream::{self, Upstream};
+
+pub struct Options {
+	pub short: bool,  // -s, --porcelain 変更のあるpathごとに "XY path" の1行だけを出す
+	pub branch: bool, // -b 短い形式でも1行目にbranchとupstreamとの差を出す
+}
+
+// gitと同じ形で、今のbranchとupstreamとの差、stageした変更、stageしていない変更、
+// indexに無いファイル(untracked)を出力する
+pub fn status(repo: &Repository, opts: &Options) -> io::Result<()> {
+	repo.require_worktree()?;
+	let index = Index::load(repo)?;
+	let head = refs::resolve(repo, "HEAD")?.and_then(|x| Hash::from_hex(&x));
+
+	let target = refs::head_target(repo)?;
+	let mut out = io::stdout().lock();
+	if opts.short {
+		if opts.branch {
+			writeln!(out, "## {}", branch_line(repo, &target, &head)?)?;
+		}
+		return short(repo, &index, &head, &mut out);
+	}
+	match (target.strip_prefix("refs/heads/"), &head) {
+		(Some(branch), _) => {
+			writeln!(out, "On branch {}", branch)?;
+			if let Some(info) = upstream::tracking_info(repo, branch)? {
+				writeln!(out, "{}\n", info)?;
+			}
+		}
+		(None, Some(head)) => writeln!(out, "HEAD detached at {}", &head.to_hex()[..7])?,
+		(None, None) => writeln!(out, "Not currently on any branch.")?,
+	}
+	if head.is_none() {
+		writeln!(out, "\nNo commits yet\n")?;
+	}
+
+	let unmerged = index.unmerged();
+	let merging = repo.gitdir.join("MERGE_HEAD").exists();
+	if merging && unmerged.is_empty() {
+		writeln!(out, "All conflicts fixed but you are still merging.")?;
+		writeln!(out, "  (use \"git commit\" to conclude merge)\n")?;
+	} else if merging {
+		writeln!(out, "You have unmerged paths.")?;
+		writeln!(out, "  (fix conflicts and run \"git commit\")")?;
+		writeln!(out, "  (use \"git merge --abort\" to abort the merge)\n")?;
+	}
+
+	// conflictしているpathは "Unmerged paths" にだけ出す
+	let (staged, unstaged) = changes(repo, &index, &head)?;
+	let untracked = untracked(repo, &index, Some(&Ignore::load(repo)?), false)?;
+
+	if !staged.is_empty() {
+		writeln!(out, "Changes to be committed:")?;
+		match (merging, &head) {
+			(true, _) => {}
+			(false, Some(_)) => {
+				writeln!(out, "  (use \"git restore --staged <file>...\" to unstage)")?
+			}
+			(false, None) => writeln!(out, "  (use \"git rm --cached <file>...\" to unstage)")?,
+		}
+		for change in staged.iter() {
+			writeln!(out, "\t{}", describe(change))?;
+		}
+		writeln!(out)?;
+	}
+
+	if !unmerged.is_empty() {
+		let states: Vec<(&str, &str)> = unmerged
+			.iter()
+			.map(|path| (conflict(&index, path).1, *path))
+			.collect();
+		writeln!(out, "Unmerged paths:")?;
+		match states.iter().any(|(state, _)| state.contains("deleted")) {
+			true => writeln!(
+				out,
+				"  (use \"git add/rm <file>...\" as appropriate to mark resolution)"
+			)?,
+			false => writeln!(out, "  (use \"git add <file>...\" to mark resolution)")?,
+		}
+		for (state, path) in states {
+			writeln!(out, "\t{:<17}{}", format!("{}:", state), path)?;
+		}
+		writeln!(out)?;
+	}
+
+	if !unstaged.is_empty() {
+		writeln!(out, "Changes not staged for commit:")?;
+		match unstaged.iter().any(|x| x.new.is_none()) {
+			true => writeln!(
+				out,
+				"  (use \"git add/rm <file>...\" to update what will be committed)"
+			)?,
+			false => writeln!(
+				out,
+				"  (use \"git add <file>...\" to update what will be committed)"
+			)?,
+		}
+		writeln!(
+			out,
+			"  (use \"git restore <file>...\" to discard changes in working directory)"
+		)?;
+		for change in unstaged.iter() {
+			writeln!(out, "\t{}", describe(change))?;
+		}
+		writeln!(out)?;
+	}
+
+	if !untracked.is_empty() {
+		writeln!(out, "Untracked files:")?;
+		writeln!(
+			out,
+			"  (use \"git add <file>...\" to include in what will be committed)"
+		)?;
+		for path in untracked.iter() {
+			writeln!(out, "\t{}", path)?;
+		}
+		writeln!(out)?;
+	}
+
+	if !staged.is_empty() {
+		return Ok(());
+	}
+	if !unstaged.is_empty() || !unmerged.is_empty() {
+		writeln!(
+			out,
+			"no changes added to commit (use \"git add\" and/or \"git commit -a\")"
+		)?;
+	} else if !untracked.is_empty() {
+		writeln!(
+			out,
+			"nothing added to commit but untracked files present (use \"git add\" to track)"
+		)?;
+	} else if head.is_none() {
+		writeln!(
+			out,
+			"nothing to commit (create/copy files and use \"git add\" to track)"
+		)?;
+	} else {
+		writeln!(out, "nothing to commit, working tree clean")?;
+	}
+	Ok(())
+}
+
+// (stageした変更, stageしていない変更)。どちらもconflictしているpathは含めない
+fn changes(
+	repo: &Repository,
+	index: &Index,
+	head: &Option<Hash>,
+) -> io::Result<(Vec<Change>, Vec<Change>)> {
+	let unmerged = index.unmerged();
+	let head_entries = match head {
+		Some(head) => {
+			let commit = store::read_commit(repo, &head.0)?;
+			diff::tree_entries(repo, &hex::decode(&commit.tree).unwrap_or_default())?
+		}
+		None => Default::default(),
+	};
+	let staged_entries = diff::index_entries(index);
+	let staged: Vec<Change> = diff::detect_renames(
+		repo,
+		diff::compare(&head_entries, &staged_entries),
+		&Renames::default(),
+	)?
+	.into_iter()
+	.filter(|x| !unmerged.contains(&x.path.as_str()))
+	.collect();
+	let unstaged: Vec<Change> =
+		diff::compare(&staged_entries, &diff::worktree_entries(repo, index)?)
+			.into_iter()
+			.filter(|x| !unmerged.contains(&x.path.as_str()))
+			.collect();
+	Ok((staged, unstaged))
+}
+
+// gitの "status --short" と同じく、pathの順に "XY path" を出し、最後にuntrackedを "?? path" で出す
+// Xはstageした変更、Yはstageしていない変更の種類。renameとcopyは "R  old -> new"
+fn short(
+	repo: &Repository,
+	index: &Index,
+	head: &Option<Hash>,
+	out: &mut impl Write,
+) -> io::Result<()> {
+	let (staged, unstaged) = changes(repo, index, head)?;
+	// path -> (X, Y, 元のpath)
+	let mut lines: BTreeMap<&str, (char, char, Option<&str>)> = BTreeMap::new();
+	for change in &staged {
+		let line = lines.entry(&change.path).or_insert((' ', ' ', None));
+		line.0 = kind(change);
+		if change.old_path != change.path {
+			line.2 = Some(&change.old_path);
+		}
+	}
+	for change in &unstaged {
+		lines.entry(&change.path).or_insert((' ', ' ', None)).1 = kind(change);
+	}
+	for path in index.unmerged() {
+		let mut code = conflict(index, path).0.chars();
+		let (x, y) = (code.next().unwrap_or('U'), code.next().unwrap_or('U'));
+		lines.insert(path, (x, y, None));
+	}
+
+	for (path, (x, y, old)) in lines {
+		match old {
+			Some(old) => writeln!(out, "{}{} {} -> {}", x, y, old, path)?,
+			None => writeln!(out, "{}{} {}", x, y, path)?,
+		}
+	}
+	for path in untracked(repo, index, Some(&Ignore::load(repo)?), false)? {
+		writeln!(out, "?? {}", path)?;
+	}
+	Ok(())
+}
+
+// "-b" で出す "main...origin/main [ahead 1, behind 2]" のような1行
+fn branch_line(repo: &Repository, target: &str, head: &Option<Hash>) -> io::Result<String> {
+	let (Some(branch), Some(ours)) = (target.strip_prefix("refs/heads/"), head) else {
+		return Ok(match target.strip_prefix("refs/heads/") {
+			Some(branch) => format!("No commits yet on {}", branch),
+			None => String::from("HEAD (no branch)"),
+		});
+	};
+	let Some(upstream) = Upstream::load(repo, branch)? else {
+		return Ok(String::from(branch));
+	};
+	let name = upstream.tracking_ref();
+	let line = format!("{}...{}", branch, refs::shorten(&name));
+	let Some(theirs) = refs::resolve(repo, &name)?.and_then(|x| Hash::from_hex(&x)) else {
+		return Ok(format!("{} [gone]", line));
+	};
+	Ok(match upstream::ahead_behind(repo, ours, &theirs)? {
+		(0, 0) => line,
+		(ahead, 0) => format!("{} [ahead {}]", line, ahead),
+		(0, behind) => format!("{} [behind {}]", line, behind),
+		(ahead, behind) => format!("{} [ahead {}, behind {}]", line, ahead, behind),
+	})
+}
+
+// 変更の種類。Change::statusに加えて、ファイルとsymlinkのように種類が変わったものは 'T'
+fn kind(change: &Change) -> char {
+	match (change.status(), &change.old, &change.new) {
+		('M', Some(old), Some(new)) if old.mode & 0o170000 != new.mode & 0o170000 => 'T',
+		(status, _, _) => status,
+	}
+}
+
+// "modified:   path" のような1行。種類の欄は12文字に揃える
+fn describe(change: &Change) -> String {
+	let kind = match kind(change) {
+		'A' => "new file:",
+		'D' => "deleted:",
+		'R' => "renamed:",
+		'C' => "copied:",
+		'T' => "typechange:",
+		_ => "modified:",
+	};
+	match change.old_path != change.path {
+		true => format!("{:<12}{} -> {}", kind, change.old_path, change.path),
+		false => format!("{:<12}{}", kind, change.path),
+	}
+}
+
+// conflictしているpathのstage(1: base, 2: ours, 3: theirs)の有無からの、短い形式での表記と説明
+fn conflict(index: &Index, path: &str) -> (&'static str, &'static str) {
+	let has = |stage: u16| {
+		index
+			.entries
+			.iter()
+			.any(|x| x.path == path && x.stage() == stage)
+	};
+	match (has(1), has(2), has(3)) {
+		(true, true, true) => ("UU", "both modified"),
+		(false, true, true) => ("AA", "both added"),
+		(true, false, true) => ("DU", "deleted by us"),
+		(true, true, false) => ("UD", "deleted by them"),
+		(false, true, false) => ("AU", "added by us"),
+		(false, false, true) => ("UA", "added by them"),
+		_ => ("DD", "both deleted"),
+	}
+}
+
+// indexに無いファイルをpath順に返す。indexのファイルを1つも含まないディレクトリは "dir/" とまとめる
+// ignoreがあれば、ignoreされたファイルとディレクトリは含めない
+// emptyなら、中にファイルの無いディレクトリも含め、ignoreされたものを含むディレクトリはまとめずに中を返す
+pub(super) fn untracked(
+	repo: &Repository,
+	index: &Index,
+	ignore: Option<&Ignore>,
+	empty: bool,
+) -> io::Result<Vec<String>> {
+	let tracked: HashSet<&str> = index.entries.iter().map(|x| x.path.as_str()).collect();
+	let mut dirs = HashSet::new();
+	for path in tracked.iter() {
+		for (i, _) in path.match_indices('/') {
+			dirs.insert(&path[..=i]);
+		}
+	}
+
+	let mut found = Vec::new();
+	let mut stack = vec![String::new()];
+	while let Some(dir) = stack.pop() {
+		for entry in fs::read_dir(repo.workdir.join(&dir))? {
+			let entry = entry?;
+			let name = entry.file_name().to_string_lossy().into_owned();
+			if name == ".git" {
+				continue;
+			}
+			let path = format!("{}{}", dir, name);
+			let is_dir = entry.file_type()?.is_dir();
+			if !is_dir {
+				if !tracked.contains(path.as_str()) && !is_ignored(ignore, &path, false) {
+					found.push(path);
+				}
+				continue;
+			}
+			if dirs.contains(format!("{}/", path).as_str()) {
+				stack.push(format!("{}/", path));
+				continue;
+			}
+			if is_ignored(ignore, &path, true) {
+				continue;
+			}
+			let (files, ignored) = contents(&entry.path(), &path, ignore)?;
+			let path = format!("{}/", path);
+			if empty && ignored {
+				stack.push(path);
+			} else if empty || files {
+				found.push(path);
+			}
+		}
+	}
+	found.sort();
+	Ok(found)
+}
+
+// ディレクトリの中に(空のディレクトリ以外の)ignoreされていないファイルがあるかと、ignoreされたものがあるか
+fn contents(dir: &Path, path: &str, ignore: Option<&Ignore>) -> io::Result<(bool, bool)> {
+	let (mut files, mut has_ignored) = (false, false);
+	for entry in fs::read_dir(dir)? {
+		let entry = entry?;
+		let child = format!("{}/{}", path, entry.file_name().to_string_lossy());
+		let is_dir = entry.file_type()?.is_dir();
+		if is_ignored(ignore, &child, is_dir) {
+			has_ignored = true;
+			continue;
+		}
+		if !is_dir {
+			files = true;
+			continue;
+		}
+		let (x, y) = contents(&entry.path(), &child, ignore)?;
+		files |= x;
+		has_ignored |= y;
+	}
+	Ok((files, has_ignored))
+}
+
+fn is_ignored(ignore: Option<&Ignore>, path: &str, is_dir: bool) -> bool {
+	ignore.is_some_and(|x| x.is_ignored(path, is_dir))
+}
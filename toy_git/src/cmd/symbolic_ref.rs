@@ -0,0 +1,59 @@
+use std::io::{self, Write};
+
+use crate::refs;
+use crate::repository::Repository;
+
+pub enum Action {
+	Read { short: bool, recurse: bool }, // 指しているrefの名前を出力する
+	Set(String),                         // targetを指すようにする
+	Delete,                              // symbolic refを消す
+}
+
+// nameのsymbolic refを読み書きする
+// Readでnameがsymbolic refでなければError、quietなら何も出さずにfalse
+pub fn symbolic_ref(
+	repo: &Repository,
+	name: &str,
+	action: &Action,
+	quiet: bool,
+) -> io::Result<bool> {
+	let mut out = io::stdout().lock();
+	match action {
+		Action::Read { short, recurse } => match refs::symbolic_target(repo, name, *recurse)? {
+			Some(target) if *short => writeln!(out, "{}", refs::shorten(&target))?,
+			Some(target) => writeln!(out, "{}", target)?,
+			None if quiet => return Ok(false),
+			None => {
+				return Err(io::Error::other(format!(
+					"ref {} is not a symbolic ref",
+					name
+				)))
+			}
+		},
+		Action::Set(target) => {
+			if name == "HEAD" && !target.starts_with("refs/") {
+				return Err(io::Error::other("Refusing to point HEAD outside of refs/"));
+			}
+			if refs::validate_ref_name(target).is_err() {
+				return Err(io::Error::other(format!(
+					"Refusing to set '{}' to invalid ref '{}'",
+					name, target
+				)));
+			}
+			refs::update_ref(repo, name, &format!("ref: {}", target))?;
+		}
+		Action::Delete => {
+			if name == "HEAD" {
+				return Err(io::Error::other("deleting 'HEAD' is not allowed"));
+			}
+			if refs::symbolic_target(repo, name, false)?.is_none() {
+				return Err(io::Error::other(format!(
+					"Cannot delete {}, not a symbolic ref",
+					name
+				)));
+			}
+			refs::delete_ref(repo, name)?;
+		}
+	}
+	Ok(true)
+}
@@ -0,0 +1,216 @@
+use std::cmp::Ordering;
+use std::io::{self, Write};
+
+use crate::hash::Hash;
+use crate::ident;
+use crate::object::{Commit, ObjectKind, Tag};
+use crate::refs;
+use crate::repository::Repository;
+use crate::revparse;
+use crate::store;
+use crate::wildmatch::wildmatch;
+
+use super::verify::{split_tag, verify_tag};
+
+// tagを並べる順(--sort=<key>)
+pub enum Sort {
+	Refname, // 名前のbyte順
+	Version, // version:refname 名前の中の数字を数として比べる("v1.9" < "v1.10")
+}
+
+pub struct Options {
+	pub patterns: Vec<String>, // 空でなければ、どれかのglobに一致するtagだけ
+	pub sort: Sort,
+	pub reverse: bool, // --sort=-<key>
+	pub lines: usize,  // -n<num> 名前の後にmessageの最初のnum行を出す
+}
+
+// refs/tags/のtagの名前を並べる
+// linesが1以上なら、annotated tagはそのmessageを、それ以外は指しているcommitのmessageを付ける
+pub fn tag_list(repo: &Repository, opts: &Options) -> io::Result<()> {
+	let mut tags: Vec<(String, String)> = refs::list(repo, "refs/tags")?
+		.into_iter()
+		.filter_map(|(name, hash)| Some((String::from(name.strip_prefix("refs/tags/")?), hash)))
+		.filter(|(name, _)| {
+			opts.patterns.is_empty() || opts.patterns.iter().any(|x| wildmatch(x, name, false))
+		})
+		.collect();
+	tags.sort_by(|(a, _), (b, _)| match opts.sort {
+		Sort::Refname => a.cmp(b),
+		Sort::Version => version_cmp(a, b),
+	});
+	if opts.reverse {
+		tags.reverse();
+	}
+
+	let mut out = io::stdout().lock();
+	for (name, hash) in tags {
+		if opts.lines == 0 {
+			writeln!(out, "{}", name)?;
+			continue;
+		}
+		let message = match Hash::from_hex(&hash) {
+			Some(hash) => message(repo, &hash)?,
+			None => String::new(),
+		};
+		let lines: Vec<&str> = message.lines().take(opts.lines).collect();
+		writeln!(out, "{:<15} {}", name, lines.join("\n    "))?;
+	}
+	Ok(())
+}
+
+pub struct CreateOptions {
+	pub message: Option<String>, // -m。Someならそのmessageのannotated tagを作る
+	pub force: bool,             // -f 既にあるtagを置き換える
+}
+
+// refs/tags/<name>をtarget(無ければHEAD)に向ける
+// messageがあればtag objectを作ってそれを指し(annotated tag)、無ければtargetを直接指す(lightweight tag)
+pub fn tag_create(
+	repo: &Repository,
+	name: &str,
+	target: Option<&str>,
+	opts: &CreateOptions,
+) -> io::Result<()> {
+	let refname = format!("refs/tags/{}", name);
+	if refs::validate_ref_name(&refname).is_err() {
+		return Err(io::Error::other(format!(
+			"'{}' is not a valid tag name.",
+			name
+		)));
+	}
+	let old = refs::read_ref(repo, &refname)?;
+	if old.is_some() && !opts.force {
+		return Err(io::Error::other(format!("tag '{}' already exists", name)));
+	}
+
+	let object = revparse::resolve(repo, target.unwrap_or("HEAD"))?;
+	let hash = match &opts.message {
+		Some(message) => {
+			let tag = Tag {
+				object: object.to_hex(),
+				kind: store::read_object_header(repo, &object.0)?.0,
+				tag: String::from(name),
+				tagger: Some(ident::committer(repo)?),
+				message: cleanup_message(message),
+			};
+			hex::encode(store::write_object(repo, &tag.as_bytes())?)
+		}
+		None => object.to_hex(),
+	};
+	refs::update_ref(repo, &refname, &hash)?;
+	match old {
+		Some(old) if old != hash => writeln!(
+			io::stdout().lock(),
+			"Updated tag '{}' (was {})",
+			name,
+			&old[..7]
+		)?,
+		_ => {}
+	}
+	Ok(())
+}
+
+// refs/tags/<name>を消す(tag -d)。packed-refsにあるものも消す
+// 無いtagがあればそれを飛ばして続け、最後にfalseを返す
+pub fn tag_delete(repo: &Repository, names: &[String]) -> io::Result<bool> {
+	let mut ok = true;
+	let mut out = io::stdout().lock();
+	for name in names {
+		let refname = format!("refs/tags/{}", name);
+		let Some(hash) = refs::read_ref(repo, &refname)? else {
+			eprintln!("error: tag '{}' not found.", name);
+			ok = false;
+			continue;
+		};
+		refs::delete_ref(repo, &refname)?;
+		writeln!(
+			out,
+			"Deleted tag '{}' (was {})",
+			name,
+			&hash[..hash.len().min(7)]
+		)?;
+	}
+	Ok(ok)
+}
+
+// tagの内容を出してから署名を確かめる(tag -v)
+pub fn tag_verify(repo: &Repository, name: &str) -> io::Result<()> {
+	let full = format!("refs/tags/{}", name);
+	let hash = refs::resolve(repo, &full)?
+		.and_then(|x| Hash::from_hex(&x))
+		.ok_or_else(|| io::Error::other(format!("tag '{}' not found.", name)))?;
+	let (kind, body) = store::read_object(repo, &hash.0)?;
+	if kind == ObjectKind::Tag {
+		write!(
+			io::stdout().lock(),
+			"{}",
+			split_tag(&String::from_utf8_lossy(&body)).0
+		)?;
+	}
+	// revparseでもtagの名前はbranchより先に探される
+	verify_tag(repo, name)
+}
+
+// tagならそのmessage(署名を除く)、commitならそのmessage
+fn message(repo: &Repository, hash: &Hash) -> io::Result<String> {
+	let (kind, body) = store::read_object(repo, &hash.0)?;
+	let message = match kind {
+		ObjectKind::Tag => Tag::from(&body)
+			.map(|x| String::from(split_tag(&x.message).0))
+			.ok(),
+		ObjectKind::Commit => Commit::from(&body).map(|x| x.message).ok(),
+		_ => None,
+	};
+	Ok(message.unwrap_or_default())
+}
+
+// gitのstripspaceと同じく、行末の空白、"#" で始まる行、続く空行と前後の空行を取り除く
+fn cleanup_message(message: &str) -> String {
+	let mut lines: Vec<&str> = Vec::new();
+	for line in message.lines().map(str::trim_end) {
+		if line.starts_with('#') || (line.is_empty() && lines.last().is_none_or(|x| x.is_empty())) {
+			continue;
+		}
+		lines.push(line);
+	}
+	while lines.last() == Some(&"") {
+		lines.pop();
+	}
+	lines.iter().map(|x| format!("{}\n", x)).collect()
+}
+
+// 数字の並びは数として、それ以外は文字として前から比べる
+fn version_cmp(a: &str, b: &str) -> Ordering {
+	let (mut a, mut b) = (a, b);
+	loop {
+		let (x, rest_a) = chunk(a);
+		let (y, rest_b) = chunk(b);
+		if x.is_empty() || y.is_empty() {
+			return x.len().cmp(&y.len());
+		}
+		let order = match (
+			x.as_bytes()[0].is_ascii_digit(),
+			y.as_bytes()[0].is_ascii_digit(),
+		) {
+			(true, true) => {
+				let (x, y) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+				x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+			}
+			_ => x.cmp(y),
+		};
+		if order != Ordering::Equal {
+			return order;
+		}
+		(a, b) = (rest_a, rest_b);
+	}
+}
+
+// 先頭の数字だけか数字以外だけの並びと、残り
+fn chunk(text: &str) -> (&str, &str) {
+	let digit = text.starts_with(|x: char| x.is_ascii_digit());
+	let end = text
+		.find(|x: char| x.is_ascii_digit() != digit)
+		.unwrap_or(text.len());
+	text.split_at(end)
+}
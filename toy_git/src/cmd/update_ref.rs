@@ -0,0 +1,83 @@
+use std::io::{self, BufRead};
+
+use crate::refs::{self, RefChange, RefUpdate};
+use crate::repository::Repository;
+use crate::revparse;
+
+// inputの各行の "update", "create", "delete", "verify" を1つのtransactionとして適用する
+//   update <ref> <new> [<old>]
+//   create <ref> <new>
+//   delete <ref> [<old>]
+//   verify <ref> [<old>]
+// oldが0のhashならrefが存在しないこと、省略すればverify以外では確かめないことを表す
+pub fn update_ref_stdin<R: BufRead>(repo: &Repository, input: R) -> io::Result<()> {
+	let mut updates = Vec::new();
+	for line in input.lines() {
+		updates.push(parse(repo, &line?)?);
+	}
+	refs::transaction(repo, &updates, "")
+}
+
+fn parse(repo: &Repository, line: &str) -> io::Result<RefUpdate> {
+	let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+	let mut args = rest.split(' ');
+	let name = args
+		.next()
+		.filter(|x| !x.is_empty())
+		.ok_or_else(|| match command {
+			"" => io::Error::other("empty command in input"),
+			_ => io::Error::other(format!("{}: missing <ref>", command)),
+		})?;
+
+	if refs::validate_ref_name(name).is_err() {
+		return Err(io::Error::other(format!("invalid ref format: {}", name)));
+	}
+
+	let error = |message: String| io::Error::other(format!("{} {}: {}", command, name, message));
+	let value = |arg: Option<&str>, label: &str| -> io::Result<Option<Option<String>>> {
+		match arg {
+			None | Some("") => Ok(None),
+			Some(x) if x.len() == repo.hash_algo.hex_len() && x.bytes().all(|b| b == b'0') => {
+				Ok(Some(None))
+			}
+			Some(x) => match revparse::resolve(repo, x) {
+				Ok(hash) => Ok(Some(Some(hash.to_hex()))),
+				Err(_) => Err(error(format!("invalid {}: {}", label, x))),
+			},
+		}
+	};
+
+	let (change, old) = match command {
+		"update" => {
+			let change = match value(args.next(), "<newvalue>")? {
+				Some(Some(hash)) => RefChange::Update(hash),
+				Some(None) => RefChange::Delete,
+				None => return Err(error(String::from("missing <newvalue>"))),
+			};
+			(change, value(args.next(), "<oldvalue>")?)
+		}
+		"create" => match value(args.next(), "<newvalue>")? {
+			Some(Some(hash)) => (RefChange::Update(hash), Some(None)),
+			Some(None) => return Err(error(String::from("zero <newvalue>"))),
+			None => return Err(error(String::from("missing <newvalue>"))),
+		},
+		"delete" => match value(args.next(), "<oldvalue>")? {
+			Some(None) => return Err(error(String::from("zero <oldvalue>"))),
+			old => (RefChange::Delete, old),
+		},
+		"verify" => {
+			let old = value(args.next(), "<oldvalue>")?;
+			(RefChange::Verify, Some(old.unwrap_or(None)))
+		}
+		_ => return Err(io::Error::other(format!("unknown command: {}", line))),
+	};
+
+	if let Some(extra) = args.next() {
+		return Err(error(format!("extra input: {}", extra)));
+	}
+	Ok(RefUpdate {
+		name: String::from(name),
+		change,
+		old,
+	})
+}
@@ -0,0 +1,95 @@
+use std::io;
+
+use crate::gpg::{self, Status};
+use crate::object::ObjectKind;
+use crate::repository::Repository;
+use crate::revparse;
+use crate::store;
+
+// tagの署名はmessageの後ろに付いている
+const SIGNATURE_BEGIN: &str = "-----BEGIN PGP SIGNATURE-----";
+
+// commitのgpgsigをgpgで確かめ、gpgの表示をstderrに出す
+// 署名が無い、正しくない、確かめられない場合はError
+pub fn verify_commit(repo: &Repository, rev: &str) -> io::Result<()> {
+	let hash = revparse::resolve(repo, rev)?;
+	let hash = revparse::peel(repo, hash, Some(ObjectKind::Commit))?;
+	let body = store::read_object(repo, &hash.0)?.1;
+
+	let (payload, signature) = split_commit(&body)
+		.ok_or_else(|| io::Error::other(format!("{}: no signature found", rev)))?;
+	verify(repo, rev, &payload, &signature)
+}
+
+// annotated tagの署名をgpgで確かめる
+pub fn verify_tag(repo: &Repository, rev: &str) -> io::Result<()> {
+	let hash = revparse::resolve(repo, rev)?;
+	let (kind, body) = store::read_object(repo, &hash.0)?;
+	if kind != ObjectKind::Tag {
+		return Err(io::Error::other(format!(
+			"{}: cannot verify a non-tag object of type {}.",
+			rev, kind
+		)));
+	}
+
+	let content = String::from_utf8_lossy(&body);
+	let (payload, signature) = split_tag(&content);
+	let signature =
+		signature.ok_or_else(|| io::Error::other(format!("{}: no signature found", rev)))?;
+	verify(repo, rev, payload.as_bytes(), signature)
+}
+
+// tagの内容を、署名の前までと署名に分ける
+pub(super) fn split_tag(content: &str) -> (&str, Option<&str>) {
+	let start = content
+		.match_indices(SIGNATURE_BEGIN)
+		.map(|(i, _)| i)
+		.find(|&i| i == 0 || content[..i].ends_with('\n'));
+	match start {
+		Some(start) => (&content[..start], Some(&content[start..])),
+		None => (content, None),
+	}
+}
+
+fn verify(repo: &Repository, rev: &str, payload: &[u8], signature: &str) -> io::Result<()> {
+	let (status, output) = gpg::verify(repo, payload, signature)?;
+	eprint!("{}", output);
+
+	match status {
+		Status::Good => Ok(()),
+		Status::Bad => Err(io::Error::other(format!("{}: bad signature", rev))),
+		Status::Unknown => Err(io::Error::other(format!(
+			"{}: cannot verify the signature",
+			rev
+		))),
+	}
+}
+
+// gpgsig header(2行目以降は空白で始まる)を取り除いた内容と、その署名
+// 知らないheaderもそのまま残すため、Commitを作り直さずにobjectの内容から取り除く
+fn split_commit(body: &[u8]) -> Option<(Vec<u8>, String)> {
+	let content = std::str::from_utf8(body).ok()?;
+	let (header, message) = content.split_once("\n\n")?;
+
+	let mut payload = String::new();
+	let mut signature: Option<String> = None;
+	let mut in_signature = false;
+	for line in header.lines() {
+		if let (true, Some(rest), Some(signature)) =
+			(in_signature, line.strip_prefix(' '), signature.as_mut())
+		{
+			signature.push('\n');
+			signature.push_str(rest);
+			continue;
+		}
+
+		in_signature = line.starts_with("gpgsig ");
+		match line.strip_prefix("gpgsig ") {
+			Some(rest) => signature = Some(String::from(rest)),
+			None => payload.push_str(&format!("{}\n", line)),
+		}
+	}
+	payload.push_str(&format!("\n{}", message));
+
+	Some((payload.into_bytes(), format!("{}\n", signature?)))
+}
@@ -0,0 +1,133 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use super::checkout::switch_tree;
+use crate::config::Config;
+use crate::index::Index;
+use crate::object::ObjectKind;
+use crate::refs;
+use crate::repository::Repository;
+use crate::revparse;
+use crate::store;
+
+// pathに新しいlinked worktreeを作り、branch(branchでなければそのcommitをdetached HEADで)をcheckoutする
+// HEADとindexは<commondir>/worktrees/<pathの最後の名前>に置き、objectとrefは本体と共有する
+// branchが既に他のworktreeでcheckoutされていればError
+pub fn worktree_add(repo: &Repository, path: &Path, branch: &str) -> io::Result<()> {
+	let refname = format!("refs/heads/{}", branch);
+	let is_branch = refs::read_ref(repo, &refname)?.is_some();
+	let commit = revparse::resolve(repo, if is_branch { &refname } else { branch })
+		.map_err(|_| io::Error::other(format!("invalid reference: {}", branch)))?;
+	let commit = revparse::peel(repo, commit, Some(ObjectKind::Commit))?;
+
+	let mut out = io::stdout().lock();
+	if is_branch {
+		eprintln!("Preparing worktree (checking out '{}')", branch);
+	} else {
+		eprintln!(
+			"Preparing worktree (detached HEAD {})",
+			&commit.to_hex()[..7]
+		);
+	}
+	if path.exists() && fs::read_dir(path).map_or(true, |mut x| x.next().is_some()) {
+		return Err(io::Error::other(format!(
+			"'{}' already exists",
+			path.display()
+		)));
+	}
+	if is_branch {
+		if let Some(at) = checked_out(repo, &refname)? {
+			return Err(io::Error::other(format!(
+				"'{}' is already checked out at '{}'",
+				branch,
+				at.display()
+			)));
+		}
+	}
+
+	fs::create_dir_all(path)?;
+	let path = path.canonicalize()?;
+	let name = path
+		.file_name()
+		.map(|x| x.to_string_lossy().into_owned())
+		.unwrap_or_default();
+	// 同じ名前のworktreeがあれば番号を付けて区別する
+	let worktrees = repo.commondir.join("worktrees");
+	let mut admin = worktrees.join(&name);
+	let mut i = 1;
+	while admin.exists() {
+		admin = worktrees.join(format!("{}{}", name, i));
+		i += 1;
+	}
+
+	fs::create_dir_all(&admin)?;
+	fs::write(admin.join("commondir"), "../..\n")?;
+	fs::write(
+		admin.join("gitdir"),
+		format!("{}\n", path.join(".git").display()),
+	)?;
+	let head = match is_branch {
+		true => format!("ref: {}\n", refname),
+		false => format!("{}\n", commit),
+	};
+	fs::write(admin.join("HEAD"), head)?;
+	fs::write(path.join(".git"), format!("gitdir: {}\n", admin.display()))?;
+
+	let linked = Repository::discover(&path)?;
+	let tree = revparse::peel(&linked, commit.clone(), Some(ObjectKind::Tree))?;
+	let mut index = Index::load(&linked)?;
+	switch_tree(&linked, &mut index, &tree.0)?;
+	index.save(&linked)?;
+	refs::append_reflog(
+		&linked,
+		"HEAD",
+		&linked.hash_algo.null_hex(),
+		&commit.to_hex(),
+		"",
+	)?;
+
+	let message = store::read_commit(repo, &commit.0)?.message;
+	writeln!(
+		out,
+		"HEAD is now at {} {}",
+		&commit.to_hex()[..7],
+		message.lines().next().unwrap_or_default()
+	)?;
+	Ok(())
+}
+
+// refnameをHEADにしているworktreeのpath。bare repositoryのHEADはcheckoutしていないものとして扱う
+fn checked_out(repo: &Repository, refname: &str) -> io::Result<Option<PathBuf>> {
+	let head = format!("ref: {}", refname);
+	let read_head = |dir: &Path| -> io::Result<bool> {
+		match fs::read_to_string(dir.join("HEAD")) {
+			Ok(content) => Ok(content.trim_end() == head),
+			Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+			Err(e) => Err(e),
+		}
+	};
+
+	let bare = Config::load(repo)?.get_bool("core.bare") == Some(true);
+	if !bare && read_head(&repo.commondir)? {
+		let workdir = repo.commondir.parent().unwrap_or(&repo.commondir);
+		return Ok(Some(workdir.to_path_buf()));
+	}
+
+	let entries = match fs::read_dir(repo.commondir.join("worktrees")) {
+		Ok(entries) => entries,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+		Err(e) => return Err(e),
+	};
+	for entry in entries {
+		let dir = entry?.path();
+		if !read_head(&dir)? {
+			continue;
+		}
+		// "gitdir" にはworktreeの.gitファイルのpathが書いてある
+		let gitfile = fs::read_to_string(dir.join("gitdir"))?;
+		let gitfile = Path::new(gitfile.trim_end());
+		return Ok(Some(gitfile.parent().unwrap_or(gitfile).to_path_buf()));
+	}
+	Ok(None)
+}
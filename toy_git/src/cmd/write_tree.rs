@@ -0,0 +1,105 @@
+use std::io;
+
+use crate::index::{CacheTree, Index};
+use crate::object::{tree_mode, Tree};
+use crate::repository::Repository;
+use crate::store;
+
+// indexの内容からtreeを(subtreeも含めて)書き込み、root treeのhashを返す
+// indexのTREE extensionで有効なディレクトリはtreeを作り直さずそのhashを使い、
+// 作ったtreeのhashはTREE extensionに記録してindexを保存する
+// prefixがあればそのディレクトリのtreeのhashを返す
+pub fn write_tree(repo: &Repository, prefix: Option<&str>) -> io::Result<Vec<u8>> {
+	let mut index = Index::load(repo)?;
+
+	// conflictが解決されていなければtreeにできない
+	if let Some(path) = index.unmerged().first() {
+		return Err(io::Error::other(format!("{}: unmerged, cannot write tree", path)));
+	}
+
+	let entries: Vec<(&str, u32, &[u8])> = index
+		.entries
+		.iter()
+		.map(|x| (x.path.as_str(), x.mode, x.hash.as_slice()))
+		.collect();
+
+	let tree = build(repo, "", &entries, index.tree.as_ref())?;
+	let subtree = match prefix.map(|x| x.trim_matches('/')).filter(|x| !x.is_empty()) {
+		Some(prefix) => prefix
+			.split('/')
+			.try_fold(&tree, |tree, name| tree.child(name))
+			.ok_or_else(|| io::Error::other(format!("git-write-tree: prefix {} not found", prefix)))?,
+		None => &tree,
+	};
+	let hash = subtree.hash.clone().unwrap_or_default();
+	index.tree = Some(tree);
+	index.save(repo)?;
+
+	Ok(hash)
+}
+
+// path順に並んだ(path, mode, hash)の組からtreeを書き込み、root treeのhashを返す
+pub fn write_entries(repo: &Repository, entries: &[(&str, u32, &[u8])]) -> io::Result<Vec<u8>> {
+	Ok(build(repo, "", entries, None)?.hash.unwrap_or_default())
+}
+
+// entriesのpathはこのtreeからの相対path
+// cacheはこのディレクトリに対応するTREE extensionの内容
+fn build(
+	repo: &Repository,
+	name: &str,
+	entries: &[(&str, u32, &[u8])],
+	cache: Option<&CacheTree>,
+) -> io::Result<CacheTree> {
+	if let Some(cache) = cache.filter(|x| x.is_valid() && x.entry_count as usize == entries.len()) {
+		// 途中で取り消された操作のtreeは保存されていないので、objectがあるときだけ使う
+		if store::has_object(repo, cache.hash.as_deref().unwrap_or_default())? {
+			return Ok(cache.clone());
+		}
+	}
+
+	let mut tree = Tree::default();
+	let mut children = Vec::new();
+
+	let mut i = 0;
+	while i < entries.len() {
+		let (path, mode, hash) = entries[i];
+		match path.split_once('/') {
+			None => {
+				tree.insert(tree_mode(mode), String::from(path), hash);
+				i += 1;
+			}
+			Some((dir, _)) => {
+				// indexはpath順に並んでいるので同じディレクトリのentryは連続している
+				let files: Vec<_> = entries[i..]
+					.iter()
+					.map_while(|&(x, mode, hash)| {
+						x.strip_prefix(dir)
+							.and_then(|x| x.strip_prefix('/'))
+							.map(|x| (x, mode, hash))
+					})
+					.collect();
+				i += files.len();
+
+				let child = build(repo, dir, &files, cache.and_then(|x| x.child(dir)))?;
+				tree.insert(
+					40000,
+					String::from(dir),
+					child.hash.as_deref().unwrap_or_default(),
+				);
+				children.push(child);
+			}
+		}
+	}
+
+	// gitは名前の長さ、名前の順に並んでいるものとしてsubtreeを探す
+	children.sort_by(|a, b| (a.name.len(), &a.name).cmp(&(b.name.len(), &b.name)));
+
+	let hash = store::write_object(repo, &tree.as_bytes())?;
+	Ok(CacheTree {
+		name: String::from(name),
+		entry_count: entries.len() as i32,
+		hash: Some(hash),
+		children,
+	})
+}
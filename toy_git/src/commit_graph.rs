@@ -0,0 +1,210 @@
+use std::fs;
+use std::io;
+
+use sha1::{Digest, Sha1};
+
+use crate::bytes::{Bytes, Chunks};
+use crate::config::Config;
+use crate::error::GitError;
+use crate::hash::{Hash, HashAlgorithm};
+use crate::refs;
+use crate::repository::Repository;
+
+// objects/info/commit-graph (version 1) の内容
+// "CGPH", version, hashのversion, chunkの数, base graphの数, chunkの表, chunk, checksum
+// chunkはOIDF(fanout)、OIDL(hash)、CDAT(tree、parentの位置、generationと日時)、EDGE(3つ目以降のparent)
+pub struct CommitGraph {
+	pub commits: Vec<GraphCommit>, // hash順
+}
+
+pub struct GraphCommit {
+	pub hash: Vec<u8>,
+	pub tree: Vec<u8>,
+	pub parents: Vec<Vec<u8>>,
+	pub generation: u32, // parentの無いcommitが1で、parentの中で最大のものより1大きい
+	pub time: i64,       // committerの日時
+}
+
+const MAGIC: &[u8] = b"CGPH";
+const FANOUT: &[u8] = b"OIDF";
+const OIDS: &[u8] = b"OIDL";
+const DATA: &[u8] = b"CDAT";
+const EDGES: &[u8] = b"EDGE";
+
+// CDATのparentの欄の特別な値
+const NO_PARENT: u32 = 0x7000_0000;
+const EXTRA_EDGES: u32 = 0x8000_0000; // 下位bitはEDGEの位置。EDGEでは最後のparentの印
+
+impl CommitGraph {
+	pub fn from(bytes: &[u8]) -> Result<Self, GitError> {
+		let bytes = Bytes::new(bytes);
+		if bytes.get(0, 4)? != MAGIC {
+			return Err(bytes.error(0, "bad commit-graph signature"));
+		}
+		if bytes.u8(4)? != 1 {
+			return Err(bytes.error(4, "unsupported commit-graph version"));
+		}
+		if bytes.u8(5)? != 1 {
+			return Err(bytes.error(5, "unsupported commit-graph hash version"));
+		}
+		if bytes.len() < 8 + 20 {
+			return Err(bytes.error(bytes.len(), "commit-graph is too short"));
+		}
+		let body = bytes.slice(0, bytes.len() - 20)?;
+		if Sha1::digest(body.data).to_vec() != bytes.data[body.len()..] {
+			return Err(bytes.error(body.len(), "commit-graph checksum mismatch"));
+		}
+
+		let chunks = Chunks::read(body, 8, bytes.u8(6)? as usize)?;
+		let n = chunks.require(FANOUT)?.u32(255 * 4)? as usize;
+		let oids = chunks.require(OIDS)?;
+		let data = chunks.require(DATA)?;
+		let edges = chunks.find(EDGES)?.unwrap_or(Bytes::new(&[]));
+		let oid = |i: u32| oids.get(i as usize * 20, 20).map(|x| x.to_vec());
+
+		let mut commits = Vec::with_capacity(n);
+		for i in 0..n {
+			let pos = i * 36;
+			let mut parents = Vec::new();
+			match data.u32(pos + 20)? {
+				NO_PARENT => {}
+				x => parents.push(oid(x)?),
+			}
+			match data.u32(pos + 24)? {
+				NO_PARENT => {}
+				x if x & EXTRA_EDGES != 0 => {
+					let mut j = (x & !EXTRA_EDGES) as usize;
+					loop {
+						let edge = edges.u32(j * 4)?;
+						parents.push(oid(edge & !EXTRA_EDGES)?);
+						if edge & EXTRA_EDGES != 0 {
+							break;
+						}
+						j += 1;
+					}
+				}
+				x => parents.push(oid(x)?),
+			}
+			let value = data.u64(pos + 28)?;
+			commits.push(GraphCommit {
+				hash: oid(i as u32)?,
+				tree: data.get(pos, 20)?.to_vec(),
+				parents,
+				generation: (value >> 34) as u32,
+				time: (value & ((1 << 34) - 1)) as i64,
+			});
+		}
+		Ok(Self { commits })
+	}
+
+	pub fn as_bytes(&self) -> Vec<u8> {
+		let mut commits: Vec<&GraphCommit> = self.commits.iter().collect();
+		commits.sort_by(|a, b| a.hash.cmp(&b.hash));
+		let position = |hash: &[u8]| {
+			commits
+				.binary_search_by(|x| x.hash.as_slice().cmp(hash))
+				.map(|x| x as u32)
+				.unwrap_or(NO_PARENT)
+		};
+
+		let mut fanout = Vec::new();
+		let mut total = 0u32;
+		for i in 0..=255u8 {
+			total += commits.iter().filter(|x| x.hash[0] == i).count() as u32;
+			fanout.extend_from_slice(&total.to_be_bytes());
+		}
+
+		let oids: Vec<u8> = commits.iter().flat_map(|x| x.hash.clone()).collect();
+		let mut data = Vec::new();
+		let mut edges: Vec<u8> = Vec::new();
+		for commit in commits.iter() {
+			data.extend_from_slice(&commit.tree);
+			let first = commit
+				.parents
+				.first()
+				.map(|x| position(x))
+				.unwrap_or(NO_PARENT);
+			let second = match commit.parents.as_slice() {
+				[] | [_] => NO_PARENT,
+				[_, x] => position(x),
+				// 2つ目以降のparentはEDGEに並べ、最後のものに印を付ける
+				[_, rest @ ..] => {
+					let start = EXTRA_EDGES | (edges.len() / 4) as u32;
+					for (i, parent) in rest.iter().enumerate() {
+						let mark = if i + 1 == rest.len() { EXTRA_EDGES } else { 0 };
+						edges.extend_from_slice(&(mark | position(parent)).to_be_bytes());
+					}
+					start
+				}
+			};
+			data.extend_from_slice(&first.to_be_bytes());
+			data.extend_from_slice(&second.to_be_bytes());
+			let value = ((commit.generation as u64) << 34) | (commit.time as u64 & ((1 << 34) - 1));
+			data.extend_from_slice(&value.to_be_bytes());
+		}
+
+		let mut chunks = vec![(FANOUT, fanout), (OIDS, oids), (DATA, data)];
+		if !edges.is_empty() {
+			chunks.push((EDGES, edges));
+		}
+
+		let mut out = Vec::from(MAGIC);
+		out.extend_from_slice(&[1, 1, chunks.len() as u8, 0]);
+		let mut offset = (out.len() + (chunks.len() + 1) * 12) as u64;
+		for (id, chunk) in chunks.iter() {
+			out.extend_from_slice(id);
+			out.extend_from_slice(&offset.to_be_bytes());
+			offset += chunk.len() as u64;
+		}
+		out.extend_from_slice(&[0; 4]);
+		out.extend_from_slice(&offset.to_be_bytes());
+		for (_, chunk) in chunks {
+			out.extend_from_slice(&chunk);
+		}
+
+		let checksum = Sha1::digest(&out).to_vec();
+		out.extend_from_slice(&checksum);
+		out
+	}
+
+	// commitsはhash順に並んでいるので二分探索する
+	pub fn find(&self, hash: &[u8]) -> Option<&GraphCommit> {
+		let i = self
+			.commits
+			.binary_search_by(|x| x.hash.as_slice().cmp(hash))
+			.ok()?;
+		Some(&self.commits[i])
+	}
+}
+
+// commit-graphにあるcommitの情報。最初に使うときにobjects/info/commit-graphを読む
+// ファイルが無いか壊れている、core.commitGraphがfalse、refs/replace/で置き換えたobjectがあるときは使わない
+// commit-graphを書いた後に作ったcommitは含まれないので、Noneならobjectを読む
+pub fn find<'a>(repo: &'a Repository, hash: &Hash) -> io::Result<Option<&'a GraphCommit>> {
+	let graph = match repo.commit_graph.get() {
+		Some(graph) => graph,
+		None => {
+			let graph = load(repo)?;
+			repo.commit_graph.get_or_init(|| graph)
+		}
+	};
+	Ok(graph.as_ref().and_then(|x| x.find(&hash.0)))
+}
+
+// commit-graphはSHA-1のものしか読めない
+fn load(repo: &Repository) -> io::Result<Option<CommitGraph>> {
+	if repo.hash_algo != HashAlgorithm::Sha1 {
+		return Ok(None);
+	}
+	if Config::load(repo)?.get_bool("core.commitgraph") == Some(false) {
+		return Ok(None);
+	}
+	if repo.replace_objects && !refs::list(repo, "refs/replace")?.is_empty() {
+		return Ok(None);
+	}
+	match fs::read(repo.objects_dir().join("info").join("commit-graph")) {
+		Ok(bytes) => Ok(CommitGraph::from(&bytes).ok()),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+		Err(e) => Err(e),
+	}
+}
@@ -0,0 +1,462 @@
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::GitError;
+use crate::repository::Repository;
+use crate::tempfile::TempFile;
+
+// .git/configのINI形式を読む
+// keyは "section.subsection.name" の形で保持する(section, nameは大文字小文字を区別しない)
+pub struct Config {
+	pub entries: Vec<(String, Option<String>)>, // 値の無いkey("[core]\n\tbare" のような行)はNone
+}
+
+impl Config {
+	// gitと同じく、値は "" で囲んだ部分の他は前後の空白を除いて中の空白を1つずつの空白にし、
+	// \t \n \b \" \\ をその文字に、行末の \ を次の行へ続けるものとして読む
+	pub fn from(content: &str) -> Result<Self, GitError> {
+		let mut entries = Vec::new();
+		let mut section = String::new();
+
+		let mut lines = content.lines().enumerate();
+		while let Some((i, line)) = lines.next() {
+			let bad = || GitError::BadConfigLine { line: i + 1 };
+			let mut rest = line.trim_start();
+			// 見出しの後に続けてkeyを書いてもよい
+			if let Some(header) = rest.strip_prefix('[') {
+				let (name, after) = parse_header(header).ok_or_else(bad)?;
+				section = name;
+				rest = after.trim_start();
+			}
+			if rest.is_empty() || rest.starts_with(['#', ';']) {
+				continue;
+			}
+
+			let end = rest
+				.find(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+				.unwrap_or(rest.len());
+			let (name, after) = rest.split_at(end);
+			if section.is_empty() || !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+				return Err(bad());
+			}
+			let after = after.trim_start();
+			let value = match after.strip_prefix('=') {
+				Some(value) => Some(parse_value(value, &mut lines).ok_or_else(bad)?),
+				None if after.is_empty() || after.starts_with(['#', ';']) => None,
+				None => return Err(bad()),
+			};
+			entries.push((format!("{}.{}", section, name.to_lowercase()), value));
+		}
+
+		Ok(Self { entries })
+	}
+
+	// gitと同じくsystem、global、repositoryの順に読み、後に読んだものほど優先する
+	pub fn load(repo: &Repository) -> io::Result<Self> {
+		let mut config = Self::load_outside()?;
+		config.entries.extend(Self::load_repo(repo)?.entries);
+		Ok(config)
+	}
+
+	// repositoryの.git/configだけ
+	pub fn load_repo(repo: &Repository) -> io::Result<Self> {
+		Self::load_file(&repo.commondir.join("config"))
+	}
+
+	// repositoryの外で使う設定(init.templateDirなど)のための、systemとglobalの設定
+	pub fn load_outside() -> io::Result<Self> {
+		let mut entries = Vec::new();
+		for path in [system_path(), global_path()].into_iter().flatten() {
+			entries.extend(Self::load_file(&path)?.entries);
+		}
+		Ok(Self { entries })
+	}
+
+	pub fn load_file(path: &Path) -> io::Result<Self> {
+		let content = match fs::read_to_string(path) {
+			Ok(content) => content,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+			Err(e) => return Err(e),
+		};
+
+		// gitと同じく "bad config line <n> in file <path>"
+		Self::from(&content).map_err(|e| {
+			io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!("{} in file {}", e, path.display()),
+			)
+		})
+	}
+
+	// 同じkeyが複数ある場合は後に書かれたものが優先される。値の無いkeyは空文字列
+	pub fn get(&self, key: &str) -> Option<&str> {
+		self.value(key).map(|x| x.unwrap_or(""))
+	}
+
+	// 値の無いkeyはSome(None)
+	pub fn value(&self, key: &str) -> Option<Option<&str>> {
+		let key = normalize_key(key);
+		self.entries
+			.iter()
+			.rev()
+			.find(|(k, _)| *k == key)
+			.map(|(_, v)| v.as_deref())
+	}
+
+	// 値の無いkeyはtrue、空文字列はfalse
+	pub fn get_bool(&self, key: &str) -> Option<bool> {
+		match self.value(key)? {
+			None => Some(true),
+			Some(x) => match x.to_lowercase().as_str() {
+				"true" | "yes" | "on" | "1" => Some(true),
+				"false" | "no" | "off" | "0" | "" => Some(false),
+				_ => None,
+			},
+		}
+	}
+
+	// gitと同じく "k", "m", "g" の単位を付けられる
+	pub fn get_int(&self, key: &str) -> io::Result<Option<i64>> {
+		let Some(value) = self.get(key) else {
+			return Ok(None);
+		};
+		let (number, unit) = match value.to_lowercase().chars().last() {
+			Some('k') => (&value[..value.len() - 1], 1 << 10),
+			Some('m') => (&value[..value.len() - 1], 1 << 20),
+			Some('g') => (&value[..value.len() - 1], 1 << 30),
+			_ => (value, 1),
+		};
+		number
+			.parse::<i64>()
+			.ok()
+			.and_then(|x| x.checked_mul(unit))
+			.map(Some)
+			.ok_or_else(|| {
+				io::Error::new(
+					io::ErrorKind::InvalidData,
+					format!("bad numeric config value '{}' for '{}'", value, key),
+				)
+			})
+	}
+}
+
+// GIT_CONFIG_SYSTEMか/etc/gitconfig。GIT_CONFIG_NOSYSTEMが指定されていればNone
+pub fn system_path() -> Option<PathBuf> {
+	let nosystem = env::var("GIT_CONFIG_NOSYSTEM").is_ok_and(|x| {
+		!matches!(x.to_lowercase().as_str(), "" | "false" | "no" | "off" | "0")
+	});
+	match (nosystem, env::var_os("GIT_CONFIG_SYSTEM")) {
+		(true, _) => None,
+		(false, Some(path)) => Some(PathBuf::from(path)),
+		(false, None) => Some(PathBuf::from("/etc/gitconfig")),
+	}
+}
+
+// GIT_CONFIG_GLOBALか~/.gitconfig。HOMEも無ければNone
+pub fn global_path() -> Option<PathBuf> {
+	match (env::var_os("GIT_CONFIG_GLOBAL"), env::var_os("HOME")) {
+		(Some(path), _) => Some(PathBuf::from(path)),
+		(None, Some(home)) => Some(Path::new(&home).join(".gitconfig")),
+		(None, None) => None,
+	}
+}
+
+// repositoryの.git/configでkeyの値をvalueにする
+pub fn set(repo: &Repository, key: &str, value: &str) -> io::Result<()> {
+	set_file(&repo.commondir.join("config"), key, value)
+}
+
+// pathの設定ファイルでkeyの値をvalueにする。他の行はそのまま残す
+// keyが既にあれば最後のものを書き換え、無ければそのsectionの最後に足す。sectionも無ければファイルの最後に作る
+pub fn set_file(path: &Path, key: &str, value: &str) -> io::Result<()> {
+	let (section, name) = split_key(key)?;
+	let mut lines = read_lines(path)?;
+	let sections = sections(&lines);
+
+	let in_section = |i: &usize| sections[*i] == section;
+	let last_in_section = (0..lines.len()).rfind(in_section); // sectionの最後の行
+	let existing = (0..lines.len()).rfind(|i| in_section(i) && is_key(&lines[*i], &name)); // 最後に書かれたkeyの行
+
+	let entry = format!("\t{} = {}", name, quote(value));
+	match (existing, last_in_section) {
+		(Some(i), _) => {
+			lines.splice(i..entry_end(&lines, i), [entry]);
+		}
+		(None, Some(i)) => lines.insert(i + 1, entry),
+		(None, None) => {
+			let header = match section.split_once('.') {
+				Some((name, sub)) => format!("[{} \"{}\"]", name, sub),
+				None => format!("[{}]", section),
+			};
+			lines.push(header);
+			lines.push(entry);
+		}
+	}
+	write_lines(path, &lines)
+}
+
+// pathの設定ファイルからkeyの行を消す。keyが無ければfalse
+// 同じkeyが複数あればError
+pub fn unset_file(path: &Path, key: &str) -> io::Result<bool> {
+	let (section, name) = split_key(key)?;
+	let mut lines = read_lines(path)?;
+	let sections = sections(&lines);
+
+	let found: Vec<usize> = (0..lines.len())
+		.filter(|i| sections[*i] == section && is_key(&lines[*i], &name))
+		.collect();
+	let i = match found.as_slice() {
+		[] => return Ok(false),
+		[i] => *i,
+		_ => return Err(io::Error::other(format!("{} has multiple values", key))),
+	};
+
+	// gitと同じく、sectionに他のkeyもcommentも無ければ、見出しと前後の空行もまとめて消す
+	let kind = |j: usize| {
+		let line = lines[j].trim();
+		match line.chars().next() {
+			None => Line::Blank,
+			Some('#' | ';') => Line::Comment,
+			Some('[') if sections[j] == section => Line::Header,
+			Some('[') => Line::OtherHeader,
+			_ => Line::Entry,
+		}
+	};
+	let mut start = Some(i);
+	let mut header = false;
+	for j in (0..i).rev() {
+		match kind(j) {
+			Line::Blank => {}
+			Line::Header => header = true,
+			Line::Entry if header => break,
+			Line::OtherHeader => break,
+			Line::Comment | Line::Entry => {
+				start = None;
+				break;
+			}
+		}
+		start = Some(j);
+	}
+	let last = entry_end(&lines, i);
+	let mut end = Some(lines.len());
+	for j in last..lines.len() {
+		match kind(j) {
+			Line::Blank | Line::Header => {}
+			Line::OtherHeader => {
+				end = Some(j);
+				break;
+			}
+			Line::Comment | Line::Entry => {
+				end = None;
+				break;
+			}
+		}
+	}
+
+	match (start, end) {
+		(Some(start), Some(end)) if header => {
+			lines.drain(start..end);
+		}
+		_ => {
+			lines.drain(i..last);
+		}
+	}
+	write_lines(path, &lines)?;
+	Ok(true)
+}
+
+enum Line {
+	Blank,
+	Comment,
+	Header,      // 消すkeyのsectionの見出し
+	OtherHeader, // 他のsectionの見出し
+	Entry,
+}
+
+// "section.name" を(section, name)に分ける
+fn split_key(key: &str) -> io::Result<(String, String)> {
+	normalize_key(key)
+		.rsplit_once('.')
+		.filter(|(section, name)| !section.is_empty() && !name.is_empty())
+		.map(|(section, name)| (String::from(section), String::from(name)))
+		.ok_or_else(|| io::Error::other(format!("key does not contain a section: {}", key)))
+}
+
+// 各行がどのsectionにあるか。見出しの行はそのsection、最初の見出しより前は空
+fn sections(lines: &[String]) -> Vec<String> {
+	let mut current = String::new();
+	let mut sections = Vec::with_capacity(lines.len());
+	for line in lines {
+		if let Some(header) = line.trim().strip_prefix('[') {
+			current = parse_header(header).map(|x| x.0).unwrap_or_default();
+		}
+		sections.push(current.clone());
+	}
+	sections
+}
+
+// nameのkeyを書いた行か
+fn is_key(line: &str, name: &str) -> bool {
+	let line = strip_comment(line).trim();
+	!line.is_empty()
+		&& !line.starts_with('[')
+		&& line
+			.split('=')
+			.next()
+			.is_some_and(|x| x.trim().eq_ignore_ascii_case(name))
+}
+
+fn read_lines(path: &Path) -> io::Result<Vec<String>> {
+	match fs::read_to_string(path) {
+		Ok(content) => Ok(content.lines().map(String::from).collect()),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+		Err(e) => Err(e),
+	}
+}
+
+fn write_lines(path: &Path, lines: &[String]) -> io::Result<()> {
+	let content: String = lines.iter().map(|x| format!("{}\n", x)).collect();
+	let mut file = TempFile::new(path.parent().unwrap_or(Path::new(".")), "tmp_config")?;
+	file.write_all(content.as_bytes())?;
+	file.persist(path)
+}
+
+// "[" の後の "core]" や "remote \"origin\"]" をkeyの前半部分にし、"]" より後と共に返す
+// subsectionの中では \ の次の文字をそのまま使う
+fn parse_header(header: &str) -> Option<(String, &str)> {
+	let end = header.find([']', ' ', '\t', '"'])?;
+	let name = &header[..end];
+	let valid = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '.';
+	if name.is_empty() || !name.chars().all(valid) {
+		return None;
+	}
+	let rest = header[end..].trim_start();
+	if let Some(rest) = rest.strip_prefix(']') {
+		return Some((name.to_lowercase(), rest));
+	}
+
+	let mut sub = String::new();
+	let mut chars = rest.strip_prefix('"')?.char_indices();
+	while let Some((i, c)) = chars.next() {
+		match c {
+			'\\' => sub.push(chars.next()?.1),
+			'"' => {
+				let rest = rest[i + 2..].strip_prefix(']')?;
+				return Some((format!("{}.{}", name.to_lowercase(), sub), rest));
+			}
+			c => sub.push(c),
+		}
+	}
+	None
+}
+
+// "=" より後の値。行末が \ なら次の行に続く。閉じていない "" や知らない \ の組み合わせはNone
+fn parse_value<'a>(
+	first: &'a str,
+	lines: &mut impl Iterator<Item = (usize, &'a str)>,
+) -> Option<String> {
+	let mut value = String::new();
+	let mut quoted = false;
+	let mut space = 0; // まだ加えていない空白。値の最後なら加えない
+	let mut line = first;
+	loop {
+		let mut continued = false;
+		let mut chars = line.chars();
+		while let Some(c) = chars.next() {
+			if c.is_whitespace() && !quoted {
+				if !value.is_empty() {
+					space += 1;
+				}
+				continue;
+			}
+			if !quoted && (c == '#' || c == ';') {
+				return Some(value);
+			}
+			value.extend(std::iter::repeat_n(' ', space));
+			space = 0;
+			match c {
+				'\\' => match chars.next() {
+					None => continued = true,
+					Some('t') => value.push('\t'),
+					Some('n') => value.push('\n'),
+					Some('b') => value.push('\u{8}'),
+					Some(c @ ('"' | '\\')) => value.push(c),
+					Some(_) => return None,
+				},
+				'"' => quoted = !quoted,
+				c => value.push(c),
+			}
+		}
+		if !continued {
+			return (!quoted).then_some(value);
+		}
+		line = lines.next()?.1;
+	}
+}
+
+// subsectionだけは大文字小文字を区別する
+fn normalize_key(key: &str) -> String {
+	let first = key.find('.').unwrap_or(key.len());
+	let last = key.rfind('.').unwrap_or(0);
+	if first >= last {
+		return key.to_lowercase();
+	}
+	format!(
+		"{}{}{}",
+		key[..first].to_lowercase(),
+		&key[first..last],
+		key[last..].to_lowercase()
+	)
+}
+
+fn strip_comment(line: &str) -> &str {
+	let mut quoted = false;
+	let mut escaped = false;
+	for (i, c) in line.char_indices() {
+		match c {
+			_ if escaped => escaped = false,
+			'\\' => escaped = true,
+			'"' => quoted = !quoted,
+			'#' | ';' if !quoted => return &line[..i],
+			_ => {}
+		}
+	}
+	line
+}
+
+// 行末の \ で次の行に続いているか
+fn continued(line: &str) -> bool {
+	let line = strip_comment(line);
+	let backslashes = line.len() - line.trim_end_matches('\\').len();
+	backslashes % 2 == 1
+}
+
+// i行目から始まるkeyの行と、それに続く行の終わり
+fn entry_end(lines: &[String], i: usize) -> usize {
+	let mut end = i + 1;
+	while end <= lines.len() && continued(&lines[end - 1]) {
+		end += 1;
+	}
+	end.min(lines.len())
+}
+
+// gitと同じく、前後の空白やcommentの文字が読むときに消えないように "" で囲み、
+// 改行、tab、"、\ は \ を付けて書く
+fn quote(value: &str) -> String {
+	let mut escaped = String::with_capacity(value.len());
+	for c in value.chars() {
+		match c {
+			'\n' => escaped.push_str("\\n"),
+			'\t' => escaped.push_str("\\t"),
+			'"' => escaped.push_str("\\\""),
+			'\\' => escaped.push_str("\\\\"),
+			c => escaped.push(c),
+		}
+	}
+	match value.starts_with(' ') || value.ends_with(' ') || value.contains(['#', ';']) {
+		true => format!("\"{}\"", escaped),
+		false => escaped,
+	}
+}
@@ -0,0 +1,122 @@
+use chrono::{DateTime, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeZone};
+
+// gitが受け付ける日付の書き方のうち、よく使うもの
+//   "<unix timestamp> <+hhmm>" と "@<unix timestamp>"
+//   "2024-01-02", "2024-01-02 03:04:05", "2024-01-02T03:04:05+0900" のようなISO 8601
+//   "Tue, 2 Jan 2024 03:04:05 +0900" のようなRFC 2822
+//   "now", "yesterday", "3 days ago" のような相対的なもの
+// timezoneが無ければlocal timeとして扱う。時刻が無ければその日の0時
+pub fn parse(date: &str) -> Option<DateTime<FixedOffset>> {
+	let date = date.trim();
+
+	if let Some(x) = raw(date) {
+		return Some(x);
+	}
+	if let Ok(x) = DateTime::parse_from_rfc2822(date) {
+		return Some(x);
+	}
+	if let Some(x) = iso(date) {
+		return Some(x);
+	}
+	relative(date, Local::now().fixed_offset())
+}
+
+// reflog expireやgc --pruneの期限。"never" と "false" は期限無し(None)、"all" と "now" は今
+pub fn parse_expiry(date: &str) -> Option<Option<DateTime<FixedOffset>>> {
+	match date {
+		"never" | "false" => Some(None),
+		"all" | "now" => Some(Some(Local::now().fixed_offset())),
+		_ => parse(date).map(Some),
+	}
+}
+
+fn raw(date: &str) -> Option<DateTime<FixedOffset>> {
+	let mut iter = date.trim_start_matches('@').split_whitespace();
+
+	let ts = iter.next()?.parse::<i64>().ok()?;
+	let offset = match iter.next() {
+		Some(x) => offset(x)?,
+		None => FixedOffset::east_opt(0)?,
+	};
+	if iter.next().is_some() {
+		return None;
+	}
+
+	offset.timestamp_opt(ts, 0).single()
+}
+
+// "+hhmm"
+fn offset(x: &str) -> Option<FixedOffset> {
+	let sign = match x.chars().next()? {
+		'+' => 1,
+		'-' => -1,
+		_ => return None,
+	};
+	let digits = &x[1..];
+	if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+		return None;
+	}
+	let hours: i32 = digits[..2].parse().ok()?;
+	let minutes: i32 = digits[2..].parse().ok()?;
+	FixedOffset::east_opt(sign * (hours * 60 * 60 + minutes * 60))
+}
+
+fn iso(date: &str) -> Option<DateTime<FixedOffset>> {
+	if let Ok(x) = DateTime::parse_from_rfc3339(date) {
+		return Some(x);
+	}
+	let date = date.replacen('T', " ", 1);
+
+	let zoned = [
+		"%Y-%m-%d %H:%M:%S %z",
+		"%Y-%m-%d %H:%M:%S%z",
+		"%Y-%m-%d %H:%M %z",
+	]
+	.iter()
+	.find_map(|x| DateTime::parse_from_str(&date, x).ok());
+	if zoned.is_some() {
+		return zoned;
+	}
+
+	let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"]
+		.iter()
+		.find_map(|x| NaiveDateTime::parse_from_str(&date, x).ok())
+		.or_else(|| {
+			NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+				.ok()
+				.and_then(|x| x.and_hms_opt(0, 0, 0))
+		})?;
+	Local
+		.from_local_datetime(&naive)
+		.single()
+		.map(|x| x.fixed_offset())
+}
+
+fn relative(date: &str, now: DateTime<FixedOffset>) -> Option<DateTime<FixedOffset>> {
+	match date {
+		"now" => return Some(now),
+		"yesterday" => return Some(now - Duration::days(1)),
+		_ => {}
+	}
+
+	// "3.days.ago" のように "." で区切ってもよい
+	let words: Vec<&str> = date
+		.split(|c: char| c.is_whitespace() || c == '.')
+		.filter(|x| !x.is_empty())
+		.collect();
+	let [count, unit, "ago"] = words.as_slice() else {
+		return None;
+	};
+	let count: i64 = count.parse().ok()?;
+	let seconds = match unit.trim_end_matches('s') {
+		"second" => 1,
+		"minute" => 60,
+		"hour" => 60 * 60,
+		"day" => 24 * 60 * 60,
+		"week" => 7 * 24 * 60 * 60,
+		"month" => 30 * 24 * 60 * 60,
+		"year" => 365 * 24 * 60 * 60,
+		_ => return None,
+	};
+	Some(now - Duration::seconds(count * seconds))
+}
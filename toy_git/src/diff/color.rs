@@ -0,0 +1,228 @@
+use std::env;
+use std::io::{self, IsTerminal};
+
+use super::myers::{self, Edit};
+
+// gitのdiffの既定の色
+const META: &str = "\x1b[1m"; // "diff --git" からhunkの前まで
+const FRAG: &str = "\x1b[36m"; // "@@ ... @@"
+const OLD: &str = "\x1b[31m";
+const NEW: &str = "\x1b[32m";
+const RESET: &str = "\x1b[m";
+
+// --color=autoのとき。stdoutが端末で、NO_COLORが設定されていなければ色を付ける
+pub fn auto() -> bool {
+	io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none()
+}
+
+// patchの各行に色を付ける
+// contextの行はそのまま、"-" の行は赤、"+" の行は緑、hunkの先頭は水色、それ以外のheaderは太字
+pub fn lines(patch: &str) -> String {
+	let mut out = String::new();
+	let mut in_hunk = false;
+	for line in patch.lines() {
+		if line.starts_with("diff --git ") {
+			in_hunk = false;
+		} else if line.starts_with("@@") {
+			in_hunk = true;
+		}
+
+		match line.as_bytes().first() {
+			_ if !in_hunk && line.starts_with("Binary files ") => out.push_str(line),
+			_ if !in_hunk => out.push_str(&format!("{}{}{}", META, line, RESET)),
+			Some(b'@') => out.push_str(&format!("{}{}{}", FRAG, line, RESET)),
+			Some(b'-') => out.push_str(&format!("{}{}{}", OLD, line, RESET)),
+			// gitと同じく "+" と内容を分けて色を付ける
+			Some(b'+') if line.len() == 1 => out.push_str(&format!("{}+{}", NEW, RESET)),
+			Some(b'+') => out.push_str(&format!("{}+{}{}{}{}", NEW, RESET, NEW, &line[1..], RESET)),
+			_ => out.push_str(&format!("{}{}", line, RESET)),
+		}
+		out.push('\n');
+	}
+	out
+}
+
+// 単語ごとの差分の出し方
+#[derive(Clone, Copy)]
+enum Style {
+	Color,     // 消えた単語を赤、加わった単語を緑にする
+	Porcelain, // " ", "-", "+" で始まる行にし、改行を "~" の行にする
+}
+
+// 単語の差分のどちら側か
+#[derive(Clone, Copy)]
+enum Part {
+	Common,
+	Old,
+	New,
+}
+
+// hunkの中の連続した "-" と "+" の行を単語(空白以外の並び)ごとに比べ、
+// 新しい内容の中で消えた単語を赤、加わった単語を緑にして表示する(diff --color-words)
+pub fn words(patch: &str) -> String {
+	word_diff(patch, Style::Color)
+}
+
+// wordsと同じ比べ方で、機械で読むための形にする(diff --word-diff=porcelain)
+pub fn porcelain(patch: &str) -> String {
+	word_diff(patch, Style::Porcelain)
+}
+
+fn word_diff(patch: &str, style: Style) -> String {
+	let mut out = String::new();
+	let mut in_hunk = false;
+	let mut minus = String::new();
+	let mut plus = String::new();
+
+	for line in patch.lines() {
+		if line.starts_with("diff --git ") {
+			in_hunk = false;
+		} else if line.starts_with("@@") {
+			in_hunk = true;
+		}
+
+		match line.as_bytes().first() {
+			Some(b'-') if in_hunk => {
+				minus.push_str(&line[1..]);
+				minus.push('\n');
+				continue;
+			}
+			Some(b'+') if in_hunk => {
+				plus.push_str(&line[1..]);
+				plus.push('\n');
+				continue;
+			}
+			// 改行の有無は単語の比較では区別しない
+			Some(b'\\') if in_hunk => continue,
+			_ => {}
+		}
+
+		flush(&mut out, style, &mut minus, &mut plus);
+		match (style, line.as_bytes().first()) {
+			(Style::Porcelain, _) if !in_hunk || line.starts_with("@@") => out.push_str(line),
+			// contextの行も共通の部分として出す
+			(Style::Porcelain, _) => {
+				write(&mut out, style, Part::Common, &format!("{}\n", &line[1..]));
+				continue;
+			}
+			_ if !in_hunk && line.starts_with("Binary files ") => out.push_str(line),
+			_ if !in_hunk => out.push_str(&format!("{}{}{}", META, line, RESET)),
+			(_, Some(b'@')) => out.push_str(&format!("{}{}{}", FRAG, line, RESET)),
+			_ if line.len() > 1 => out.push_str(&format!("{}{}", &line[1..], RESET)),
+			_ => {}
+		}
+		out.push('\n');
+	}
+	flush(&mut out, style, &mut minus, &mut plus);
+	out
+}
+
+// たまった "-" と "+" の内容を単語ごとに比べて出力する
+fn flush(out: &mut String, style: Style, minus: &mut String, plus: &mut String) {
+	if minus.is_empty() && plus.is_empty() {
+		return;
+	}
+	if plus.is_empty() {
+		write(out, style, Part::Old, minus);
+		minus.clear();
+		return;
+	}
+
+	let old = tokens(minus);
+	let new = tokens(plus);
+	let edits = myers::diff(
+		&old.iter().map(|&(s, e)| &minus[s..e]).collect::<Vec<_>>(),
+		&new.iter().map(|&(s, e)| &plus[s..e]).collect::<Vec<_>>(),
+	);
+
+	// 変更の続く範囲ごとに、その直前までの新しい内容をそのまま出してから消えた単語と加わった単語を出す
+	let mut current = 0; // plusのうち出力し終えた位置
+	let mut i = 0;
+	while i < edits.len() {
+		if matches!(edits[i], Edit::Equal(..)) {
+			i += 1;
+			continue;
+		}
+		let start = i;
+		while i < edits.len() && !matches!(edits[i], Edit::Equal(..)) {
+			i += 1;
+		}
+		let deleted: Vec<usize> = edits[start..i]
+			.iter()
+			.filter_map(|x| match x {
+				Edit::Delete(k) => Some(*k),
+				_ => None,
+			})
+			.collect();
+		let inserted: Vec<usize> = edits[start..i]
+			.iter()
+			.filter_map(|x| match x {
+				Edit::Insert(k) => Some(*k),
+				_ => None,
+			})
+			.collect();
+
+		// 加わった単語が無ければ、直前の共通の単語の後ろに消えた単語を置く
+		let begin = match (inserted.first(), start.checked_sub(1).map(|x| edits[x])) {
+			(Some(&k), _) => new[k].0,
+			(None, Some(Edit::Equal(_, k))) => new[k].1,
+			(None, _) => 0,
+		};
+		write(out, style, Part::Common, &plus[current..begin]);
+		if let (Some(&first), Some(&last)) = (deleted.first(), deleted.last()) {
+			write(out, style, Part::Old, &minus[old[first].0..old[last].1]);
+		}
+		current = begin;
+		if let (Some(&first), Some(&last)) = (inserted.first(), inserted.last()) {
+			write(out, style, Part::New, &plus[new[first].0..new[last].1]);
+			current = new[last].1;
+		}
+	}
+	write(out, style, Part::Common, &plus[current..]);
+
+	minus.clear();
+	plus.clear();
+}
+
+// 空白以外の並びの(開始, 終了)の位置
+fn tokens(text: &str) -> Vec<(usize, usize)> {
+	let mut tokens = Vec::new();
+	let mut start = None;
+	for (i, c) in text.char_indices() {
+		match (c.is_whitespace(), start) {
+			(true, Some(s)) => {
+				tokens.push((s, i));
+				start = None;
+			}
+			(false, None) => start = Some(i),
+			_ => {}
+		}
+	}
+	if let Some(s) = start {
+		tokens.push((s, text.len()));
+	}
+	tokens
+}
+
+// 行ごとに色を付けて書く。改行だけの行には色を付けない
+// Porcelainでは1行ずつ印を付け、改行を "~" の行にする
+fn write(out: &mut String, style: Style, part: Part, text: &str) {
+	let (color, sign) = match part {
+		Part::Common => ("", ' '),
+		Part::Old => (OLD, '-'),
+		Part::New => (NEW, '+'),
+	};
+	for (i, line) in text.split('\n').enumerate() {
+		match (style, i > 0) {
+			(Style::Color, true) => out.push('\n'),
+			(Style::Porcelain, true) => out.push_str("~\n"),
+			_ => {}
+		}
+		match (style, line.is_empty(), color.is_empty()) {
+			(_, true, _) => {}
+			(Style::Color, false, true) => out.push_str(line),
+			(Style::Color, false, false) => out.push_str(&format!("{}{}{}", color, line, RESET)),
+			(Style::Porcelain, false, _) => out.push_str(&format!("{}{}\n", sign, line)),
+		}
+	}
+}
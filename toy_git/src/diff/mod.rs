@@ -0,0 +1,345 @@
+mod color;
+mod myers;
+mod patch;
+mod rename;
+mod stat;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+
+use crate::config::Config;
+use crate::filter::Filters;
+use crate::hash::Hash;
+use crate::index::{self, Index};
+use crate::object::{is_binary, Commit, ObjectKind};
+use crate::parallel;
+use crate::repository::Repository;
+use crate::store;
+use crate::worktree::Worktree;
+
+pub use color::{auto as color_auto, lines as color_lines, porcelain as word_diff_porcelain, words as color_words};
+pub use myers::{diff as myers_diff, Edit};
+pub use patch::{lines, unified};
+pub use rename::{detect_renames, parse_score as parse_rename_score, Renames, MAX_SCORE};
+pub use stat::{stat, summary};
+
+// hunkの前後に付けるcontextの行数の既定値(-U<n>)
+pub const CONTEXT: usize = 3;
+
+// 比較する片側のファイル
+#[derive(Clone)]
+pub struct Side {
+	pub mode: u32, // 0o100644のような数値
+	pub hash: Vec<u8>,
+	pub worktree: bool, // 内容がobject storeではなくworking directoryにある
+}
+
+pub struct Change {
+	pub old_path: String,
+	pub path: String,
+	pub old: Option<Side>,
+	pub new: Option<Side>,
+	pub score: u32, // renameかcopyなら、old_pathの内容とどれだけ似ているか(MAX_SCOREが同じ内容)
+	pub copied: bool, // old_pathが残っているcopy
+}
+
+impl Change {
+	pub fn status(&self) -> char {
+		match (&self.old, &self.new) {
+			(None, _) => 'A',
+			(_, None) => 'D',
+			_ if self.copied => 'C',
+			_ if self.old_path != self.path => 'R',
+			_ => 'M',
+		}
+	}
+
+	// "R086" のようにgitが表示する似ている度合い(%)
+	pub fn similarity(&self) -> u32 {
+		self.score * 100 / MAX_SCORE
+	}
+}
+
+// treeを再帰的に辿ってpathとファイルの組にする
+pub fn tree_entries(repo: &Repository, tree: &[u8]) -> io::Result<BTreeMap<String, Side>> {
+	let mut entries = BTreeMap::new();
+	store::walk_tree(repo, tree, true, &mut |path, file| {
+		let side = Side {
+			mode: file.mode_bits(),
+			hash: file.hash,
+			worktree: false,
+		};
+		entries.insert(path, side);
+		Ok(())
+	})?;
+	Ok(entries)
+}
+
+pub fn index_entries(index: &Index) -> BTreeMap<String, Side> {
+	index
+		.entries
+		.iter()
+		.filter(|x| x.stage() == 0)
+		.map(|x| {
+			let side = Side {
+				mode: x.mode,
+				hash: x.hash.clone(),
+				worktree: false,
+			};
+			(x.path.clone(), side)
+		})
+		.collect()
+}
+
+// indexに登録されているファイルのworking directoryでの状態(消えているものは含まない)
+// skip-worktreeのファイルはindexの内容をそのまま使う
+// filterを通さないファイルはthreadに分けて読んでhashする
+pub fn worktree_entries(repo: &Repository, index: &Index) -> io::Result<BTreeMap<String, Side>> {
+	let worktree = Worktree::new(repo);
+	let filters = Filters::load(repo)?;
+	let mut entries = BTreeMap::new();
+	// (path, working directoryでのpath, stat情報)
+	let mut unfiltered = Vec::new();
+
+	for entry in index.entries.iter().filter(|x| x.stage() == 0) {
+		// skip-worktreeのファイルはworking directoryに無くてもindexと同じとみなす
+		if entry.skip_worktree() {
+			let side = Side {
+				mode: entry.mode,
+				hash: entry.hash.clone(),
+				worktree: false,
+			};
+			entries.insert(entry.path.clone(), side);
+			continue;
+		}
+		let meta = match worktree.stat(&entry.path) {
+			Ok(meta) if !meta.is_dir() => meta,
+			Ok(_) => continue,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+			Err(e) => return Err(e),
+		};
+
+		// stat情報が変わっていなければ読み直さずにindexのhashを使う
+		if entry.matches_stat(&meta) && !index.is_racy(entry) {
+			let side = Side {
+				mode: entry.mode,
+				hash: entry.hash.clone(),
+				worktree: true,
+			};
+			entries.insert(entry.path.clone(), side);
+			continue;
+		}
+
+		if meta.file_type().is_file() && filters.passes_through(&entry.path) {
+			unfiltered.push((entry.path.clone(), worktree.path(&entry.path), meta));
+			continue;
+		}
+
+		// objectとして保存されるのはclean filterを通した内容
+		let mut content = worktree.read_file(&entry.path)?;
+		if !meta.file_type().is_symlink() {
+			content = filters.clean(&entry.path, content)?;
+		}
+		let hash = store::hash_bytes(repo, &ObjectKind::Blob.encode(&content));
+
+		entries.insert(
+			entry.path.clone(),
+			Side {
+				mode: index::mode_from(&meta),
+				hash,
+				worktree: true,
+			},
+		);
+	}
+
+	let threads = parallel::threads(&Config::load(repo)?);
+	let algo = repo.hash_algo;
+	let hashes = parallel::map(threads, &unfiltered, |(_, file, meta)| {
+		let mut file = fs::File::open(file)?;
+		store::hash_stream(algo, ObjectKind::Blob, meta.len() as usize, &mut file)
+	})?;
+	for ((path, _, meta), hash) in unfiltered.into_iter().zip(hashes) {
+		let side = Side {
+			mode: index::mode_from(&meta),
+			hash,
+			worktree: true,
+		};
+		entries.insert(path, side);
+	}
+	Ok(entries)
+}
+
+// 2つのファイルの集合を比べて変更のあったpathをpath順に返す
+pub fn compare(old: &BTreeMap<String, Side>, new: &BTreeMap<String, Side>) -> Vec<Change> {
+	let mut paths: Vec<&String> = old.keys().chain(new.keys()).collect();
+	paths.sort();
+	paths.dedup();
+
+	paths
+		.into_iter()
+		.filter_map(|path| {
+			let (a, b) = (old.get(path), new.get(path));
+			match (a, b) {
+				(Some(a), Some(b)) if a.hash == b.hash && a.mode == b.mode => None,
+				_ => Some(Change {
+					old_path: path.clone(),
+					path: path.clone(),
+					old: a.cloned(),
+					new: b.cloned(),
+					score: 0,
+					copied: false,
+				}),
+			}
+		})
+		.collect()
+}
+
+// 片側の内容を読む
+pub fn content(repo: &Repository, path: &str, side: &Side) -> io::Result<Vec<u8>> {
+	if side.worktree {
+		let content = Worktree::new(repo).read_file(path)?;
+		return match side.mode {
+			0o120000 => Ok(content),
+			_ => Filters::load(repo)?.clean(path, content),
+		};
+	}
+	Ok(store::read_object(repo, &side.hash)?.1)
+}
+
+// "diff --git" から始まる1ファイル分のpatch
+// prefixは普段は("a", "b")で、-Rで左右を入れ替えたときは("b", "a")になる
+// hunkの前後にはcontext行ずつ変わっていない行を付ける
+pub fn patch(
+	repo: &Repository,
+	change: &Change,
+	prefix: (&str, &str),
+	context: usize,
+) -> io::Result<String> {
+	let (a, b) = prefix;
+	let mut out = format!("diff --git {}/{} {}/{}\n", a, change.old_path, b, change.path);
+	let short = |side: &Option<Side>| match side {
+		Some(x) => hex::encode(&x.hash)[..7].to_string(),
+		None => String::from("0000000"),
+	};
+
+	match (&change.old, &change.new) {
+		(None, Some(new)) => out.push_str(&format!("new file mode {:o}\n", new.mode)),
+		(Some(old), None) => out.push_str(&format!("deleted file mode {:o}\n", old.mode)),
+		(Some(old), Some(new)) => {
+			if change.old_path != change.path {
+				let kind = if change.copied { "copy" } else { "rename" };
+				out.push_str(&format!(
+					"similarity index {}%\n{} from {}\n{} to {}\n",
+					change.similarity(),
+					kind,
+					change.old_path,
+					kind,
+					change.path
+				));
+			}
+			if old.mode != new.mode {
+				out.push_str(&format!("old mode {:o}\nnew mode {:o}\n", old.mode, new.mode));
+			}
+			// 内容が同じならindex行と本体は出さない
+			if old.hash == new.hash {
+				return Ok(out);
+			}
+		}
+		(None, None) => return Ok(out),
+	}
+
+	let mode = match (&change.old, &change.new) {
+		(Some(old), Some(new)) if old.mode == new.mode => format!(" {:o}", old.mode),
+		_ => String::new(),
+	};
+	out.push_str(&format!("index {}..{}{}\n", short(&change.old), short(&change.new), mode));
+
+	let old = match &change.old {
+		Some(side) => content(repo, &change.old_path, side)?,
+		None => Vec::new(),
+	};
+	let new = match &change.new {
+		Some(side) => content(repo, &change.path, side)?,
+		None => Vec::new(),
+	};
+
+	let old_name = match change.old {
+		Some(_) => format!("{}/{}", a, change.old_path),
+		None => String::from("/dev/null"),
+	};
+	let new_name = match change.new {
+		Some(_) => format!("{}/{}", b, change.path),
+		None => String::from("/dev/null"),
+	};
+
+	if is_binary(&old) || is_binary(&new) {
+		out.push_str(&format!("Binary files {} and {} differ\n", old_name, new_name));
+		return Ok(out);
+	}
+
+	out.push_str(&format!("--- {}\n+++ {}\n", old_name, new_name));
+	out.push_str(&unified(&old, &new, context));
+	Ok(out)
+}
+
+// gitと同じpatch-id。最初のparentからのdiff(renameは探さない)を、空白とhunkの行番号を除いてhashする
+// 同じ変更をcherry-pickしたcommitは同じpatch-idになる
+// hashはrepositoryのもの(SHA-256のrepositoryならSHA-256)を使う
+pub fn patch_id(repo: &Repository, commit: &Commit) -> io::Result<Hash> {
+	let decode = |hex: &str| {
+		Hash::from_hex(hex)
+			.filter(|x| x.0.len() == repo.hash_algo.hash_len())
+			.ok_or_else(|| io::Error::other(format!("bad object {}", hex)))
+	};
+	let tree = |hex: &str| tree_entries(repo, &decode(hex)?.0);
+	let old = match commit.parents.first() {
+		None => BTreeMap::new(),
+		Some(parent) => tree(&store::read_commit(repo, &decode(parent)?.0)?.tree)?,
+	};
+	let new = tree(&commit.tree)?;
+
+	let squeeze = |x: &str| -> String { x.chars().filter(|c| !c.is_ascii_whitespace()).collect() };
+	let mut hasher = repo.hash_algo.hasher();
+	for change in compare(&old, &new) {
+		let (a, b) = (squeeze(&change.old_path), squeeze(&change.path));
+		hasher.update(format!("diff--gita/{}b/{}", a, b).as_bytes());
+		match (&change.old, &change.new) {
+			(None, Some(new)) => hasher.update(format!("newfilemode{:06o}", new.mode).as_bytes()),
+			(Some(old), None) => {
+				hasher.update(format!("deletedfilemode{:06o}", old.mode).as_bytes())
+			}
+			(Some(old), Some(new)) if old.mode != new.mode => {
+				hasher.update(format!("oldmode{:06o}newmode{:06o}", old.mode, new.mode).as_bytes())
+			}
+			_ => {}
+		}
+
+		let read = |path: &str, side: &Option<Side>| match side {
+			Some(side) => content(repo, path, side),
+			None => Ok(Vec::new()),
+		};
+		let old = read(&change.old_path, &change.old)?;
+		let new = read(&change.path, &change.new)?;
+		// binaryなら両側のhashを使う。無い側は0のhash
+		if is_binary(&old) || is_binary(&new) {
+			let hash = |side: &Option<Side>| match side {
+				Some(side) => hex::encode(&side.hash),
+				None => repo.hash_algo.null_hex(),
+			};
+			hasher.update((hash(&change.old) + &hash(&change.new)).as_bytes());
+			continue;
+		}
+		match (&change.old, &change.new) {
+			(None, _) => hasher.update(format!("---/dev/null+++b/{}", b).as_bytes()),
+			(_, None) => hasher.update(format!("---a/{}+++/dev/null", a).as_bytes()),
+			_ => hasher.update(format!("---a/{}+++b/{}", a, b).as_bytes()),
+		}
+		for line in unified(&old, &new, CONTEXT).lines() {
+			if !line.starts_with("@@ -") {
+				hasher.update(squeeze(line).as_bytes());
+			}
+		}
+	}
+	Ok(Hash(hasher.finalize()))
+}
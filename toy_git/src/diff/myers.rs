@@ -0,0 +1,84 @@
+// Myersのアルゴリズムによる行単位の差分
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edit {
+	Equal(usize, usize), // (aの行番号, bの行番号)
+	Delete(usize),       // aの行番号
+	Insert(usize),       // bの行番号
+}
+
+// aをbにするための最短の編集列を返す
+pub fn diff<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Edit> {
+	let n = a.len() as isize;
+	let m = b.len() as isize;
+	let max = (n + m) as usize;
+	let offset = max as isize + 1;
+
+	// v[k]は対角線kで到達できる最も遠いaの位置。各stepのvを後で辿るために保存する
+	let mut v = vec![0isize; 2 * max + 3];
+	let mut trace = Vec::new();
+
+	'outer: for d in 0..=max as isize {
+		trace.push(v.clone());
+		for k in (-d..=d).step_by(2) {
+			let i = (k + offset) as usize;
+			let mut x = if k == -d || (k != d && v[i - 1] < v[i + 1]) {
+				v[i + 1] // 上から来る(挿入)
+			} else {
+				v[i - 1] + 1 // 左から来る(削除)
+			};
+			let mut y = x - k;
+
+			// 同じ行が続く限り斜めに進む
+			while x < n && y < m && a[x as usize] == b[y as usize] {
+				x += 1;
+				y += 1;
+			}
+			v[i] = x;
+
+			if x >= n && y >= m {
+				break 'outer;
+			}
+		}
+	}
+
+	backtrack(&trace, n, m, offset)
+}
+
+fn backtrack(trace: &[Vec<isize>], n: isize, m: isize, offset: isize) -> Vec<Edit> {
+	let mut edits = Vec::new();
+	let (mut x, mut y) = (n, m);
+
+	for (d, v) in trace.iter().enumerate().rev() {
+		let d = d as isize;
+		let k = x - y;
+		let i = (k + offset) as usize;
+
+		let prev_k = if k == -d || (k != d && v[i - 1] < v[i + 1]) {
+			k + 1
+		} else {
+			k - 1
+		};
+		let prev_x = v[(prev_k + offset) as usize];
+		let prev_y = prev_x - prev_k;
+
+		while x > prev_x && y > prev_y {
+			x -= 1;
+			y -= 1;
+			edits.push(Edit::Equal(x as usize, y as usize));
+		}
+
+		if d > 0 {
+			if x == prev_x {
+				edits.push(Edit::Insert(prev_y as usize));
+			} else {
+				edits.push(Edit::Delete(prev_x as usize));
+			}
+		}
+		x = prev_x;
+		y = prev_y;
+	}
+
+	edits.reverse();
+	edits
+}
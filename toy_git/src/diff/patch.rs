@@ -0,0 +1,87 @@
+use super::myers::{self, Edit};
+
+// 改行を含めて1行ずつに分ける
+pub fn lines(content: &[u8]) -> Vec<&[u8]> {
+	content.split_inclusive(|&b| b == b'\n').collect()
+}
+
+// "@@ -l,s +l,s @@" から始まるhunkを並べたunified diffの本体を作る
+pub fn unified(a: &[u8], b: &[u8], context: usize) -> String {
+	let a = lines(a);
+	let b = lines(b);
+	let edits = myers::diff(&a, &b);
+
+	// edits[k]より前に読んだa, bの行数
+	let mut pos = Vec::with_capacity(edits.len() + 1);
+	let (mut x, mut y) = (0, 0);
+	for edit in edits.iter() {
+		pos.push((x, y));
+		match edit {
+			Edit::Equal(..) => {
+				x += 1;
+				y += 1;
+			}
+			Edit::Delete(_) => x += 1,
+			Edit::Insert(_) => y += 1,
+		}
+	}
+	pos.push((x, y));
+
+	let mut out = String::new();
+	for (start, end) in hunks(&edits, context) {
+		let (old_start, new_start) = pos[start];
+		let old_count = pos[end].0 - old_start;
+		let new_count = pos[end].1 - new_start;
+
+		out.push_str(&format!(
+			"@@ -{} +{} @@\n",
+			range(old_start, old_count),
+			range(new_start, new_count)
+		));
+
+		for edit in edits[start..end].iter() {
+			let (mark, line) = match *edit {
+				Edit::Equal(i, _) => (' ', a[i]),
+				Edit::Delete(i) => ('-', a[i]),
+				Edit::Insert(j) => ('+', b[j]),
+			};
+			out.push(mark);
+			out.push_str(&String::from_utf8_lossy(line));
+			if !line.ends_with(b"\n") {
+				out.push_str("\n\\ No newline at end of file\n");
+			}
+		}
+	}
+	out
+}
+
+// 変更のあるeditをcontext行ずつ広げ、重なるものをまとめた範囲の一覧
+fn hunks(edits: &[Edit], context: usize) -> Vec<(usize, usize)> {
+	let changes: Vec<usize> = edits
+		.iter()
+		.enumerate()
+		.filter(|(_, x)| !matches!(x, Edit::Equal(..)))
+		.map(|(i, _)| i)
+		.collect();
+
+	let mut hunks: Vec<(usize, usize)> = Vec::new();
+	for i in changes {
+		let start = i.saturating_sub(context);
+		let end = (i + context + 1).min(edits.len());
+
+		match hunks.last_mut() {
+			Some(last) if start <= last.1 => last.1 = end,
+			_ => hunks.push((start, end)),
+		}
+	}
+	hunks
+}
+
+// 行数が0の場合は直前の行番号、1の場合は行数を省略する
+fn range(start: usize, count: usize) -> String {
+	match count {
+		0 => format!("{},0", start),
+		1 => format!("{}", start + 1),
+		_ => format!("{},{}", start + 1, count),
+	}
+}
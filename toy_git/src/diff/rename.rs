@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::io;
+
+use super::{content, Change};
+use crate::object::is_binary;
+use crate::repository::Repository;
+
+// gitと同じく、似ている度合いを0からMAX_SCOREまでで表す
+pub const MAX_SCORE: u32 = 60000;
+
+// renameとcopyの見つけ方
+pub struct Renames {
+	pub score: u32,   // これ以上似ていれば同じファイルとみなす(-M)。既定は50%
+	pub copies: bool, // 変更されたファイルからのcopyと、1つのファイルからの複数のcopyも探す(-C)
+}
+
+impl Default for Renames {
+	fn default() -> Self {
+		Self {
+			score: MAX_SCORE / 2,
+			copies: false,
+		}
+	}
+}
+
+// "-M90%" や "-M9"(0.9) のような値をscoreにする。gitと同じく "%" が無ければ小数点以下の数字として読む
+pub fn parse_score(value: &str) -> Option<u32> {
+	if value.is_empty() {
+		return Some(Renames::default().score);
+	}
+	let (digits, percent) = match value.strip_suffix('%') {
+		Some(digits) => (digits, true),
+		None => (value, false),
+	};
+	if digits.is_empty() || !digits.bytes().all(|x| x.is_ascii_digit()) {
+		return None;
+	}
+	let number: f64 = digits.parse().ok()?;
+	let fraction = match percent {
+		true => number / 100.0,
+		false => number / 10f64.powi(digits.len() as i32),
+	};
+	Some((fraction.min(1.0) * MAX_SCORE as f64) as u32)
+}
+
+// 削除されたファイル(copiesなら変更されたファイルも)から、追加されたファイルに似ているものを探してrenameかcopyにする
+// 内容が同じものを優先し、次にscoreが高く、ファイル名が同じものを選ぶ
+// 1つのファイルが複数に移ったときは、gitと同じくpath順で最後のものだけをrenameにし、残りはcopyにする
+pub fn detect_renames(
+	repo: &Repository,
+	changes: Vec<Change>,
+	opts: &Renames,
+) -> io::Result<Vec<Change>> {
+	let sources: Vec<usize> = (0..changes.len())
+		.filter(|&i| match (&changes[i].old, &changes[i].new) {
+			(Some(_), None) => true,
+			(Some(_), Some(_)) => opts.copies,
+			_ => false,
+		})
+		.collect();
+	let targets: Vec<usize> = (0..changes.len())
+		.filter(|&i| changes[i].old.is_none())
+		.collect();
+
+	// 内容が同じ組を先に見つけ、残りだけ中身を読んで比べる
+	let mut candidates = Vec::new();
+	for &dst in targets.iter() {
+		let mut exact = false;
+		for &src in sources.iter() {
+			if changes[src].old.as_ref().map(|x| &x.hash)
+				== changes[dst].new.as_ref().map(|x| &x.hash)
+			{
+				candidates.push((MAX_SCORE, same_name(&changes[src], &changes[dst]), dst, src));
+				exact = true;
+			}
+		}
+		if exact {
+			continue;
+		}
+		for &src in sources.iter() {
+			let score = similarity(repo, &changes[src], &changes[dst], opts.score)?;
+			if score >= opts.score {
+				candidates.push((score, same_name(&changes[src], &changes[dst]), dst, src));
+			}
+		}
+	}
+	candidates.sort_by(|a, b| {
+		(b.0, b.1)
+			.cmp(&(a.0, a.1))
+			.then((a.2, a.3).cmp(&(b.2, b.3)))
+	});
+
+	// 削除されたファイルは、copiesでなければ1度しか使えない
+	let mut matched: HashMap<usize, (usize, u32)> = HashMap::new();
+	let mut used: HashMap<usize, usize> = HashMap::new();
+	for (score, _, dst, src) in candidates {
+		if matched.contains_key(&dst) {
+			continue;
+		}
+		let deleted = changes[src].new.is_none();
+		if deleted && !opts.copies && used.contains_key(&src) {
+			continue;
+		}
+		matched.insert(dst, (src, score));
+		*used.entry(src).or_default() += 1;
+	}
+
+	let mut changes: Vec<Option<Change>> = changes.into_iter().map(Some).collect();
+	let mut renamed = Vec::new();
+	for dst in targets {
+		let Some(&(src, score)) = matched.get(&dst) else {
+			continue;
+		};
+		let (old_path, old, deleted) = match &changes[src] {
+			Some(x) => (x.old_path.clone(), x.old.clone(), x.new.is_none()),
+			None => continue,
+		};
+		let remaining = used.get_mut(&src).map(|x| {
+			*x -= 1;
+			*x
+		});
+		if let Some(change) = changes[dst].as_mut() {
+			change.old_path = old_path;
+			change.old = old;
+			change.score = score;
+			change.copied = !deleted || remaining != Some(0);
+		}
+		if deleted && remaining == Some(0) {
+			renamed.push(src);
+		}
+	}
+	for src in renamed {
+		changes[src] = None;
+	}
+
+	let mut changes: Vec<Change> = changes.into_iter().flatten().collect();
+	changes.sort_by(|a, b| a.path.cmp(&b.path));
+	Ok(changes)
+}
+
+fn same_name(src: &Change, dst: &Change) -> bool {
+	let name = |path: &str| path.rsplit('/').next().unwrap_or_default().to_string();
+	name(&src.old_path) == name(&dst.path)
+}
+
+// gitのestimate_similarityと同じく、srcの内容のうちdstにも現れる部分の大きさを大きい方のファイルの大きさで割る
+// 通常のファイル同士でなければ比べず、大きさが違いすぎてminimumに届かないものは中身を読まない
+fn similarity(repo: &Repository, src: &Change, dst: &Change, minimum: u32) -> io::Result<u32> {
+	let (Some(old), Some(new)) = (&src.old, &dst.new) else {
+		return Ok(0);
+	};
+	let regular = |mode: u32| mode & 0o170000 == 0o100000;
+	if !regular(old.mode) || !regular(new.mode) {
+		return Ok(0);
+	}
+
+	let a = content(repo, &src.old_path, old)?;
+	let b = content(repo, &dst.path, new)?;
+	let max_size = a.len().max(b.len()) as u64;
+	let delta_size = max_size - a.len().min(b.len()) as u64;
+	if b.is_empty() || max_size * ((MAX_SCORE - minimum) as u64) < delta_size * MAX_SCORE as u64 {
+		return Ok(0);
+	}
+
+	let src_chunks = chunks(&a);
+	let dst_chunks = chunks(&b);
+	let copied: u64 = src_chunks
+		.iter()
+		.filter_map(|(chunk, &count)| dst_chunks.get(chunk).map(|&x| count.min(x)))
+		.sum();
+	Ok((copied * MAX_SCORE as u64 / max_size) as u32)
+}
+
+// 内容を改行か64byteごとに区切り、同じ区切りの合計のbyte数を数える
+// textではgitと同じく改行の前の "\r" を数えない
+fn chunks(content: &[u8]) -> HashMap<Vec<u8>, u64> {
+	let text = !is_binary(content);
+	let mut chunks = HashMap::new();
+	let mut chunk = Vec::new();
+	for (i, &b) in content.iter().enumerate() {
+		if text && b == b'\r' && content.get(i + 1) == Some(&b'\n') {
+			continue;
+		}
+		chunk.push(b);
+		if chunk.len() < 64 && b != b'\n' {
+			continue;
+		}
+		let count = chunk.len() as u64;
+		*chunks.entry(std::mem::take(&mut chunk)).or_default() += count;
+	}
+	if !chunk.is_empty() {
+		let count = chunk.len() as u64;
+		*chunks.entry(chunk).or_default() += count;
+	}
+	chunks
+}
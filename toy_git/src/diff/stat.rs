@@ -0,0 +1,256 @@
+use std::io;
+
+use super::myers::{self, Edit};
+use super::{content, lines, Change};
+use crate::object::is_binary;
+use crate::repository::Repository;
+
+// 1ファイル分の変更の大きさ。binaryなら前後のbyte数
+struct FileStat {
+	name: String,
+	added: usize,
+	deleted: usize,
+	binary: bool,
+}
+
+// " <path> | <行数> ++--" を並べ、最後に " N files changed, ..." を付ける(diff --stat)
+// gitのshow_statsと同じく、全体がwidthに収まるようにpathとgraphを縮める
+pub fn stat(repo: &Repository, changes: &[Change], width: usize) -> io::Result<String> {
+	let mut files = Vec::new();
+	for change in changes {
+		let old = match &change.old {
+			Some(side) => content(repo, &change.old_path, side)?,
+			None => Vec::new(),
+		};
+		let new = match &change.new {
+			Some(side) => content(repo, &change.path, side)?,
+			None => Vec::new(),
+		};
+		let name = match change.old_path == change.path {
+			true => change.path.clone(),
+			false => rename_name(&change.old_path, &change.path),
+		};
+
+		if is_binary(&old) || is_binary(&new) {
+			files.push(FileStat {
+				name,
+				added: new.len(),
+				deleted: old.len(),
+				binary: true,
+			});
+			continue;
+		}
+		let (a, b) = (lines(&old), lines(&new));
+		let edits = myers::diff(&a, &b);
+		let count = |f: fn(&Edit) -> bool| edits.iter().filter(|x| f(x)).count();
+		files.push(FileStat {
+			name,
+			added: count(|x| matches!(x, Edit::Insert(_))),
+			deleted: count(|x| matches!(x, Edit::Delete(_))),
+			binary: false,
+		});
+	}
+	if files.is_empty() {
+		return Ok(String::new());
+	}
+
+	let digits = |n: usize| n.to_string().len();
+	let text = files.iter().filter(|x| !x.binary);
+	let max_change = text.clone().map(|x| x.added + x.deleted).max().unwrap_or(0);
+	let max_len = files
+		.iter()
+		.map(|x| x.name.chars().count())
+		.max()
+		.unwrap_or(0);
+	// "Bin XXX -> YYY bytes"
+	let bin_width = files
+		.iter()
+		.filter(|x| x.binary)
+		.map(|x| 14 + digits(x.added) + digits(x.deleted))
+		.max()
+		.unwrap_or(0);
+	let number_width = match bin_width {
+		0 => digits(max_change),
+		_ => digits(max_change).max(3),
+	};
+
+	let width = width.max(16 + 6 + number_width);
+	let mut graph_width = match max_change + 4 > bin_width {
+		true => max_change,
+		false => bin_width - 4,
+	};
+	let mut name_width = max_len;
+	if name_width + number_width + 6 + graph_width > width {
+		if graph_width + number_width + 6 > width * 3 / 8 {
+			graph_width = (width * 3 / 8).saturating_sub(number_width + 6).max(6);
+		}
+		if name_width > width.saturating_sub(number_width + 6 + graph_width) {
+			name_width = width.saturating_sub(number_width + 6 + graph_width);
+		} else {
+			graph_width = width - number_width - 6 - name_width;
+		}
+	}
+
+	let mut out = String::new();
+	for file in files.iter() {
+		let name = shorten(&file.name, name_width);
+		let pad = name_width.saturating_sub(name.chars().count());
+		out.push_str(&format!(" {}{} |", name, " ".repeat(pad)));
+		if file.binary {
+			out.push_str(&format!(
+				" {:>w$} {} -> {} bytes\n",
+				"Bin",
+				file.deleted,
+				file.added,
+				w = number_width
+			));
+			continue;
+		}
+
+		let (mut added, mut deleted) = (file.added, file.deleted);
+		if graph_width <= max_change {
+			let mut total = scale(added + deleted, graph_width, max_change);
+			if total < 2 && added > 0 && deleted > 0 {
+				total = 2;
+			}
+			if added < deleted {
+				added = scale(added, graph_width, max_change);
+				deleted = total - added;
+			} else {
+				deleted = scale(deleted, graph_width, max_change);
+				added = total - deleted;
+			}
+		}
+		let changed = file.added + file.deleted;
+		out.push_str(&format!(
+			" {:>w$}{}{}{}\n",
+			changed,
+			if changed > 0 { " " } else { "" },
+			"+".repeat(added),
+			"-".repeat(deleted),
+			w = number_width
+		));
+	}
+
+	let inserted: usize = text.clone().map(|x| x.added).sum();
+	let deleted: usize = text.map(|x| x.deleted).sum();
+	let plural = |n: usize| if n == 1 { "" } else { "s" };
+	out.push_str(&format!(
+		" {} file{} changed",
+		files.len(),
+		plural(files.len())
+	));
+	if inserted > 0 || deleted == 0 {
+		out.push_str(&format!(", {} insertion{}(+)", inserted, plural(inserted)));
+	}
+	if deleted > 0 || inserted == 0 {
+		out.push_str(&format!(", {} deletion{}(-)", deleted, plural(deleted)));
+	}
+	out.push('\n');
+	Ok(out)
+}
+
+// " create mode 100644 <path>" のような、内容以外の変更の一覧(diff --summary)
+pub fn summary(changes: &[Change]) -> String {
+	let mut out = String::new();
+	for change in changes {
+		match (&change.old, &change.new) {
+			(None, Some(new)) => {
+				out.push_str(&format!(" create mode {:o} {}\n", new.mode, change.path))
+			}
+			(Some(old), None) => out.push_str(&format!(
+				" delete mode {:o} {}\n",
+				old.mode, change.old_path
+			)),
+			(Some(old), Some(new)) => {
+				let mode = match old.mode != new.mode {
+					true => format!(" mode change {:o} => {:o}", old.mode, new.mode),
+					false => String::new(),
+				};
+				if change.old_path != change.path {
+					out.push_str(&format!(
+						" {} {} ({}%)\n",
+						if change.copied { "copy" } else { "rename" },
+						rename_name(&change.old_path, &change.path),
+						change.similarity()
+					));
+					if !mode.is_empty() {
+						out.push_str(&format!("{}\n", mode));
+					}
+				} else if !mode.is_empty() {
+					out.push_str(&format!("{} {}\n", mode, change.path));
+				}
+			}
+			(None, None) => {}
+		}
+	}
+	out
+}
+
+// gitのscale_linearと同じく、0でなければ少なくとも1にする
+fn scale(n: usize, width: usize, max_change: usize) -> usize {
+	match n {
+		0 => 0,
+		n => 1 + n * (width - 1) / max_change,
+	}
+}
+
+// 長すぎるpathは先頭を "..." にし、できればディレクトリの区切りから残す
+fn shorten(name: &str, width: usize) -> String {
+	let chars: Vec<char> = name.chars().collect();
+	if chars.len() <= width {
+		return String::from(name);
+	}
+	let keep = width.saturating_sub(3);
+	let rest: String = chars[chars.len() - keep..].iter().collect();
+	match rest.find('/') {
+		Some(i) => format!("...{}", &rest[i..]),
+		None => format!("...{}", rest),
+	}
+}
+
+// "dir/{old => new}/file" のように、共通の前後のディレクトリをまとめる(gitのpprint_rename)
+fn rename_name(a: &str, b: &str) -> String {
+	let (a, b) = (a.as_bytes(), b.as_bytes());
+	let mut prefix = 0;
+	for (i, (x, y)) in a.iter().zip(b.iter()).enumerate() {
+		if x != y {
+			break;
+		}
+		if *x == b'/' {
+			prefix = i + 1;
+		}
+	}
+
+	// 末尾から比べる。前に共通部分があれば、その最後の "/" まで見る
+	let mut suffix = 0;
+	let at = |s: &[u8], i: usize| s.get(i).copied();
+	let stop = prefix.saturating_sub(usize::from(prefix > 0));
+	let (mut i, mut j) = (a.len(), b.len());
+	while i >= stop && j >= stop && at(a, i) == at(b, j) {
+		if at(a, i) == Some(b'/') {
+			suffix = a.len() - i;
+		}
+		if i == 0 || j == 0 {
+			break;
+		}
+		i -= 1;
+		j -= 1;
+	}
+
+	let a_mid = a.len().saturating_sub(prefix + suffix);
+	let b_mid = b.len().saturating_sub(prefix + suffix);
+	let part = |s: &[u8], from: usize, len: usize| {
+		String::from_utf8_lossy(&s[from..from + len]).into_owned()
+	};
+	let middle = format!("{} => {}", part(a, prefix, a_mid), part(b, prefix, b_mid));
+	match prefix + suffix {
+		0 => middle,
+		_ => format!(
+			"{}{{{}}}{}",
+			part(a, 0, prefix),
+			middle,
+			part(a, a.len() - suffix, suffix)
+		),
+	}
+}
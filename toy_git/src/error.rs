@@ -0,0 +1,132 @@
+use std::fmt;
+use std::io;
+
+// objectを読めなかった理由。offsetは読んでいたbyte列の先頭からの位置
+#[derive(Debug)]
+pub enum GitError {
+	InvalidHeader {
+		offset: usize,
+		message: &'static str,
+	}, // "<type> <size>\0" やcommit、tagのheaderがおかしい
+	UnknownType {
+		offset: usize,
+		name: String,
+	},
+	TruncatedEntry {
+		offset: usize,
+	}, // treeのentryが途中で終わっている
+	BadMode {
+		offset: usize,
+	},
+	BadIdent {
+		offset: usize,
+	}, // "<name> <<email>>" の形でない
+	BadTimestamp {
+		offset: usize,
+	},
+	Utf8 {
+		field: &'static str,
+		offset: usize,
+	},
+	Corrupt {
+		offset: usize,
+		message: &'static str,
+	}, // indexや.idxなど、object以外のbinaryのファイルがおかしい
+	BadConfigLine {
+		line: usize,
+	}, // 1から数える
+	Io(io::Error),
+}
+
+impl GitError {
+	// "<what>: <理由>" のInvalidDataにする
+	pub fn context(self, what: impl fmt::Display) -> io::Error {
+		match self {
+			Self::Io(e) => e,
+			e => io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", what, e)),
+		}
+	}
+
+	// 一部分を読んだときのoffsetを、それを含むbyte列の先頭からの位置にする
+	pub fn offset_by(self, base: usize) -> Self {
+		match self {
+			Self::InvalidHeader { offset, message } => Self::InvalidHeader {
+				offset: offset + base,
+				message,
+			},
+			Self::UnknownType { offset, name } => Self::UnknownType {
+				offset: offset + base,
+				name,
+			},
+			Self::TruncatedEntry { offset } => Self::TruncatedEntry {
+				offset: offset + base,
+			},
+			Self::BadMode { offset } => Self::BadMode {
+				offset: offset + base,
+			},
+			Self::BadIdent { offset } => Self::BadIdent {
+				offset: offset + base,
+			},
+			Self::BadTimestamp { offset } => Self::BadTimestamp {
+				offset: offset + base,
+			},
+			Self::Utf8 { field, offset } => Self::Utf8 {
+				field,
+				offset: offset + base,
+			},
+			Self::Corrupt { offset, message } => Self::Corrupt {
+				offset: offset + base,
+				message,
+			},
+			e => e,
+		}
+	}
+}
+
+impl fmt::Display for GitError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::InvalidHeader { offset, message } => {
+				write!(f, "invalid header at byte {}: {}", offset, message)
+			}
+			Self::UnknownType { offset, name } => {
+				write!(f, "unknown object type '{}' at byte {}", name, offset)
+			}
+			Self::TruncatedEntry { offset } => write!(f, "truncated tree entry at byte {}", offset),
+			Self::BadMode { offset } => write!(f, "bad file mode at byte {}", offset),
+			Self::BadIdent { offset } => write!(f, "bad ident at byte {}", offset),
+			Self::BadTimestamp { offset } => write!(f, "bad timestamp at byte {}", offset),
+			Self::Utf8 { field, offset } => {
+				write!(f, "invalid UTF-8 in {} at byte {}", field, offset)
+			}
+			Self::Corrupt { offset, message } => write!(f, "{} at byte {}", message, offset),
+			Self::BadConfigLine { line } => write!(f, "bad config line {}", line),
+			Self::Io(e) => write!(f, "{}", e),
+		}
+	}
+}
+
+impl std::error::Error for GitError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Self::Io(e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+impl From<io::Error> for GitError {
+	fn from(e: io::Error) -> Self {
+		Self::Io(e)
+	}
+}
+
+// 壊れたobjectはInvalidDataとして扱う
+impl From<GitError> for io::Error {
+	fn from(e: GitError) -> Self {
+		match e {
+			GitError::Io(e) => e,
+			e => io::Error::new(io::ErrorKind::InvalidData, e),
+		}
+	}
+}
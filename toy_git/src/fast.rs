@@ -0,0 +1,166 @@
+use std::io::{self, BufRead, Write};
+
+// git fast-import形式のstreamの読み書き
+// streamは "blob", "commit", "reset", "tag" などのcommandの行と、"data <n>" で始まる内容からなる
+
+// "data <n>\n<内容>" を書く
+pub fn write_data<W: Write>(out: &mut W, data: &[u8]) -> io::Result<()> {
+	writeln!(out, "data {}", data.len())?;
+	out.write_all(data)
+}
+
+// '"' で始まるか、'"', '\', 制御文字を含むpathはCの文字列の形で書く
+pub fn quote(path: &str) -> String {
+	let needs = path.starts_with('"')
+		|| path
+			.chars()
+			.any(|c| c == '"' || c == '\\' || c.is_control());
+	if !needs {
+		return String::from(path);
+	}
+
+	let mut out = String::from("\"");
+	for c in path.chars() {
+		match c {
+			'"' => out.push_str("\\\""),
+			'\\' => out.push_str("\\\\"),
+			'\n' => out.push_str("\\n"),
+			'\t' => out.push_str("\\t"),
+			c if c.is_control() => out.push_str(&format!("\\{:03o}", c as u32)),
+			c => out.push(c),
+		}
+	}
+	out.push('"');
+	out
+}
+
+// Cの文字列の形のpathを読み、pathと残りを返す。'"' で始まらなければNone
+pub fn unquote(s: &str) -> Option<(String, &str)> {
+	let mut bytes = Vec::new();
+	let mut iter = s.strip_prefix('"')?.char_indices();
+	while let Some((i, c)) = iter.next() {
+		match c {
+			'"' => {
+				let rest = &s[i + 2..];
+				return Some((String::from_utf8(bytes).ok()?, rest));
+			}
+			'\\' => {
+				let (_, c) = iter.next()?;
+				let byte = match c {
+					'n' => b'\n',
+					't' => b'\t',
+					'r' => b'\r',
+					'a' => 0x07,
+					'b' => 0x08,
+					'f' => 0x0c,
+					'v' => 0x0b,
+					'"' => b'"',
+					'\\' => b'\\',
+					'0'..='7' => {
+						let mut n = c.to_digit(8)?;
+						for _ in 0..2 {
+							n = n * 8 + iter.next()?.1.to_digit(8)?;
+						}
+						u8::try_from(n).ok()?
+					}
+					_ => return None,
+				};
+				bytes.push(byte);
+			}
+			c => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+		}
+	}
+	None
+}
+
+// streamを1行ずつ読む。"#" で始まる行は飛ばす
+pub struct Reader<R: BufRead> {
+	input: R,
+	peeked: Option<String>,
+}
+
+impl<R: BufRead> Reader<R> {
+	pub fn new(input: R) -> Self {
+		Self {
+			input,
+			peeked: None,
+		}
+	}
+
+	// 次の行を読まずに見る
+	pub fn peek(&mut self) -> io::Result<Option<&str>> {
+		if self.peeked.is_none() {
+			self.peeked = self.read_line()?;
+		}
+		Ok(self.peeked.as_deref())
+	}
+
+	pub fn next_line(&mut self) -> io::Result<Option<String>> {
+		match self.peeked.take() {
+			Some(line) => Ok(Some(line)),
+			None => self.read_line(),
+		}
+	}
+
+	// 次の行が "<prefix>" で始まっていれば、その残りを返して読み進める
+	pub fn next_if(&mut self, prefix: &str) -> io::Result<Option<String>> {
+		match self.peek()? {
+			Some(line) if line.starts_with(prefix) => {
+				let line = self.next_line()?.unwrap_or_default();
+				Ok(Some(String::from(&line[prefix.len()..])))
+			}
+			_ => Ok(None),
+		}
+	}
+
+	// "data <n>" の後のn byteか、"data <<<delim>" の後のdelimだけの行までを読む
+	// 内容の後の改行は省略できる
+	pub fn data(&mut self) -> io::Result<Vec<u8>> {
+		let spec = self
+			.next_if("data ")?
+			.ok_or_else(|| io::Error::other("expected 'data n' command"))?;
+
+		if let Some(delim) = spec.strip_prefix("<<") {
+			let mut data = Vec::new();
+			loop {
+				let mut line = Vec::new();
+				if self.input.read_until(b'\n', &mut line)? == 0 {
+					return Err(io::Error::other(format!(
+						"EOF in data (terminator '{}' not found)",
+						delim
+					)));
+				}
+				if line.strip_suffix(b"\n").unwrap_or(&line) == delim.as_bytes() {
+					return Ok(data);
+				}
+				data.extend_from_slice(&line);
+			}
+		}
+
+		let size: usize = spec
+			.parse()
+			.map_err(|_| io::Error::other(format!("invalid data length: {}", spec)))?;
+		let mut data = vec![0; size];
+		self.input.read_exact(&mut data)?;
+		if self.input.fill_buf()?.first() == Some(&b'\n') {
+			self.input.consume(1);
+		}
+		Ok(data)
+	}
+
+	fn read_line(&mut self) -> io::Result<Option<String>> {
+		loop {
+			let mut line = Vec::new();
+			if self.input.read_until(b'\n', &mut line)? == 0 {
+				return Ok(None);
+			}
+			let line = String::from_utf8(line).map_err(|_| {
+				io::Error::new(io::ErrorKind::InvalidData, "invalid UTF-8 in stream")
+			})?;
+			let line = line.strip_suffix('\n').unwrap_or(&line);
+			if !line.starts_with('#') {
+				return Ok(Some(String::from(line)));
+			}
+		}
+	}
+}
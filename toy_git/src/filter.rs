@@ -0,0 +1,199 @@
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+
+use crate::attributes::{Attributes, Value};
+use crate::config::Config;
+use crate::object::is_binary;
+use crate::repository::Repository;
+
+// core.autocrlf
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum AutoCrlf {
+	False,
+	True,
+	Input, // 保存時だけLFにする
+}
+
+// pathの改行を変換するかどうか
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Text {
+	Off,
+	On,
+	Auto, // binaryでなければ変換する
+}
+
+// .gitattributesのfilter=<name>に従って、filter.<name>.clean/smudgeの外部コマンドを通す
+// textとeol、core.autocrlfに従って改行も変換する
+pub struct Filters<'a> {
+	repo: &'a Repository,
+	config: Config,
+	attributes: Attributes,
+	autocrlf: AutoCrlf,
+}
+
+impl<'a> Filters<'a> {
+	pub fn load(repo: &'a Repository) -> io::Result<Self> {
+		let config = Config::load(repo)?;
+		let autocrlf = match config.get("core.autocrlf") {
+			Some(x) if x.eq_ignore_ascii_case("input") => AutoCrlf::Input,
+			_ => match config.get_bool("core.autocrlf") {
+				Some(true) => AutoCrlf::True,
+				_ => AutoCrlf::False,
+			},
+		};
+
+		Ok(Self {
+			repo,
+			config,
+			attributes: Attributes::load(repo)?,
+			autocrlf,
+		})
+	}
+
+	// working directoryの内容をobjectとして保存する前に通す
+	// gitと同じくfilterを通してから改行をLFにする
+	pub fn clean(&self, path: &str, content: Vec<u8>) -> io::Result<Vec<u8>> {
+		let content = self.run(path, "clean", content)?;
+		match self.converts(path, &content) {
+			true => Ok(crlf_to_lf(&content)),
+			false => Ok(content),
+		}
+	}
+
+	// objectの内容をworking directoryに書き出す前に通す
+	// cleanとは逆に改行をCRLFにしてからfilterを通す
+	pub fn smudge(&self, path: &str, content: Vec<u8>) -> io::Result<Vec<u8>> {
+		let content = match self.converts(path, &content) && self.crlf_on_checkout(path) {
+			true => lf_to_crlf(&content),
+			false => content,
+		};
+		self.run(path, "smudge", content)
+	}
+
+	// cleanが内容を変えないことが確かか。filterも改行の変換も無ければ内容を見ずに分かる
+	pub fn passes_through(&self, path: &str) -> bool {
+		self.text(path) == Text::Off && self.command(path, "clean").is_none()
+	}
+
+	fn text(&self, path: &str) -> Text {
+		match self.attributes.get(path, "text") {
+			Value::Set => Text::On,
+			Value::Unset => Text::Off,
+			Value::Text(x) if x == "auto" => Text::Auto,
+			// eolが指定されていればtextとして扱う
+			_ => match self.attributes.get(path, "eol") {
+				Value::Text(_) => Text::On,
+				_ if self.autocrlf != AutoCrlf::False => Text::Auto,
+				_ => Text::Off,
+			},
+		}
+	}
+
+	fn converts(&self, path: &str, content: &[u8]) -> bool {
+		match self.text(path) {
+			Text::On => true,
+			Text::Auto => !is_binary(content),
+			Text::Off => false,
+		}
+	}
+
+	// checkout時にCRLFにするかどうか。eol属性、core.autocrlf、core.eolの順に見る
+	fn crlf_on_checkout(&self, path: &str) -> bool {
+		match self.attributes.get(path, "eol") {
+			Value::Text(x) => x == "crlf",
+			_ => match self.autocrlf {
+				AutoCrlf::True => true,
+				AutoCrlf::Input => false,
+				AutoCrlf::False => self.config.get("core.eol") == Some("crlf"),
+			},
+		}
+	}
+
+	fn run(&self, path: &str, kind: &str, content: Vec<u8>) -> io::Result<Vec<u8>> {
+		let Some((name, command)) = self.command(path, kind) else {
+			return Ok(content);
+		};
+		let required = self
+			.config
+			.get_bool(&format!("filter.{}.required", name))
+			.unwrap_or(false);
+
+		match pipe(self.repo, &command, &content) {
+			Ok(output) => Ok(output),
+			Err(e) if required => Err(io::Error::other(format!(
+				"{}: {} filter {} failed: {}",
+				path, kind, name, e
+			))),
+			// requiredでなければ失敗しても元の内容のまま進める
+			Err(e) => {
+				eprintln!(
+					"warning: {}: {} filter '{}' failed: {}",
+					path, kind, name, e
+				);
+				Ok(content)
+			}
+		}
+	}
+
+	// pathのfilter=<name>と、filter.<name>.<kind>の%fをpathにしたcommand
+	fn command(&self, path: &str, kind: &str) -> Option<(String, String)> {
+		let Value::Text(name) = self.attributes.get(path, "filter") else {
+			return None;
+		};
+		let command = self.config.get(&format!("filter.{}.{}", name, kind))?;
+		let command = command.replace("%f", &format!("'{}'", path.replace('\'', "'\\''")));
+		Some((name, command))
+	}
+}
+
+// shでcommandを実行し、contentを標準入力に渡して標準出力を返す
+fn pipe(repo: &Repository, command: &str, content: &[u8]) -> io::Result<Vec<u8>> {
+	let mut child = Command::new("sh")
+		.args(["-c", command])
+		.current_dir(&repo.workdir)
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.spawn()?;
+
+	// 大きな内容でもpipeが詰まらないように別threadで書き込む
+	let mut stdin = child
+		.stdin
+		.take()
+		.ok_or_else(|| io::Error::other("cannot open stdin"))?;
+	let input = content.to_vec();
+	let writer = thread::spawn(move || stdin.write_all(&input));
+
+	let output = child.wait_with_output()?;
+	writer
+		.join()
+		.map_err(|_| io::Error::other("filter writer panicked"))??;
+
+	if !output.status.success() {
+		return Err(io::Error::other(format!("exit status {}", output.status)));
+	}
+	Ok(output.stdout)
+}
+
+fn crlf_to_lf(content: &[u8]) -> Vec<u8> {
+	let mut out = Vec::with_capacity(content.len());
+	for (i, &b) in content.iter().enumerate() {
+		if b == b'\r' && content.get(i + 1) == Some(&b'\n') {
+			continue;
+		}
+		out.push(b);
+	}
+	out
+}
+
+// 既にCRLFになっている行はそのままにする
+fn lf_to_crlf(content: &[u8]) -> Vec<u8> {
+	let mut out = Vec::with_capacity(content.len());
+	for (i, &b) in content.iter().enumerate() {
+		if b == b'\n' && (i == 0 || content[i - 1] != b'\r') {
+			out.push(b'\r');
+		}
+		out.push(b);
+	}
+	out
+}
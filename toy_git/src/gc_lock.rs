@@ -0,0 +1,109 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, SystemTime};
+
+use crate::repository::Repository;
+
+// gitと同じく、これより古い "gc.pid" は持ち主が死んでいるものとみなす
+const STALE_AFTER: Duration = Duration::from_secs(12 * 60 * 60);
+
+// objectを消す操作(gc、prune-packed)を1つずつしか行わないためのlock
+// "<commondir>/gc.pid" に "<pid> <hostname>" を書いておき、dropされると消す
+pub struct GcLock {
+	path: PathBuf,
+}
+
+impl GcLock {
+	// "gc.pid.lock" をO_EXCLで作ってから、既にある "gc.pid" の持ち主がまだ動いているかを確かめる
+	// 動いていればErrorKind::WouldBlockのError。死んでいるか古ければ、forceでなくても取り直す
+	pub fn acquire(repo: &Repository, force: bool) -> io::Result<Self> {
+		let path = repo.commondir.join("gc.pid");
+		let lock = repo.commondir.join("gc.pid.lock");
+		let mut file = OpenOptions::new()
+			.write(true)
+			.create_new(true)
+			.open(&lock)
+			.map_err(|e| match e.kind() {
+				io::ErrorKind::AlreadyExists => io::Error::other(format!(
+					"Unable to create '{}': File exists.\n\nAnother git process seems to be running in this repository.",
+					lock.display()
+				)),
+				_ => e,
+			})?;
+
+		let host = hostname();
+		if !force {
+			let owner = running(&path, &host);
+			if !matches!(owner, Ok(None)) {
+				fs::remove_file(&lock)?;
+			}
+			if let Some((pid, owner)) = owner? {
+				return Err(io::Error::new(
+					io::ErrorKind::WouldBlock,
+					format!(
+						"gc is already running on machine '{}' pid {} (use --force if not)",
+						owner, pid
+					),
+				));
+			}
+		}
+
+		let written = writeln!(file, "{} {}", process::id(), host).and_then(|_| file.sync_all());
+		if let Err(e) = written.and_then(|_| fs::rename(&lock, &path)) {
+			fs::remove_file(&lock).ok();
+			return Err(e);
+		}
+		Ok(Self { path })
+	}
+}
+
+impl Drop for GcLock {
+	fn drop(&mut self) {
+		fs::remove_file(&self.path).ok();
+	}
+}
+
+// "gc.pid" を書いたprocessがまだ動いていれば、そのpidとhostname
+// 他のmachineのprocessは確かめられないので、古くなるまで動いているものとみなす
+fn running(path: &Path, host: &str) -> io::Result<Option<(u32, String)>> {
+	let content = match fs::read_to_string(path) {
+		Ok(content) => content,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+		Err(e) => return Err(e),
+	};
+	let modified = fs::metadata(path)?.modified()?;
+	if SystemTime::now()
+		.duration_since(modified)
+		.is_ok_and(|x| x > STALE_AFTER)
+	{
+		return Ok(None);
+	}
+
+	let mut fields = content.split_whitespace();
+	let (Some(pid), Some(owner)) = (fields.next().and_then(|x| x.parse().ok()), fields.next())
+	else {
+		return Ok(None);
+	};
+	if owner == host && !alive(pid) {
+		return Ok(None);
+	}
+	Ok(Some((pid, String::from(owner))))
+}
+
+// /procの無いsystemでは確かめられないので動いているものとみなす
+fn alive(pid: u32) -> bool {
+	pid == process::id()
+		|| !Path::new("/proc/self").exists()
+		|| Path::new("/proc").join(pid.to_string()).exists()
+}
+
+fn hostname() -> String {
+	fs::read_to_string("/proc/sys/kernel/hostname")
+		.or_else(|_| fs::read_to_string("/etc/hostname"))
+		.map(|x| String::from(x.trim()))
+		.ok()
+		.filter(|x| !x.is_empty())
+		.unwrap_or_else(|| String::from("unknown"))
+}
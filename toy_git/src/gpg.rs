@@ -0,0 +1,91 @@
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+use crate::config::Config;
+use crate::repository::Repository;
+use crate::tempfile::TempFile;
+
+// 署名を確かめた結果
+#[derive(PartialEq, Eq)]
+pub enum Status {
+	Good,    // 署名が正しい
+	Bad,     // 内容が署名と一致しない
+	Unknown, // 公開鍵が無いなどで確かめられない
+}
+
+// gpg.program(デフォルトはgpg)
+fn program(repo: &Repository) -> io::Result<String> {
+	Ok(Config::load(repo)?
+		.get("gpg.program")
+		.map(String::from)
+		.unwrap_or_else(|| String::from("gpg")))
+}
+
+// gpg.programにpayloadを渡してASCII armorの分離署名を作る
+pub fn sign(repo: &Repository, payload: &[u8], key: &str) -> io::Result<String> {
+	let program = program(repo)?;
+
+	let mut child = Command::new(&program)
+		.args(["--status-fd=2", "-bsau", key])
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.spawn()
+		.map_err(|e| io::Error::other(format!("cannot run {}: {}", program, e)))?;
+
+	if let Some(mut stdin) = child.stdin.take() {
+		stdin.write_all(payload)?;
+	}
+	let output = child.wait_with_output()?;
+
+	let signature = String::from_utf8_lossy(&output.stdout).into_owned();
+	if !output.status.success() || !signature.contains("-----BEGIN") {
+		return Err(io::Error::other(format!(
+			"gpg failed to sign the data\n{}",
+			String::from_utf8_lossy(&output.stderr)
+		)));
+	}
+
+	Ok(signature)
+}
+
+// payloadに対する分離署名を確かめる。gpgが表示した内容も返す
+pub fn verify(repo: &Repository, payload: &[u8], signature: &str) -> io::Result<(Status, String)> {
+	let program = program(repo)?;
+
+	// gpgは署名をファイルから、payloadをstdinから読む
+	let mut file = TempFile::new(&repo.gitdir, "tmp_signature")?;
+	file.write_all(signature.as_bytes())?;
+	file.flush()?;
+
+	let mut child = Command::new(&program)
+		.args(["--keyid-format=long", "--status-fd=1", "--verify"])
+		.arg(file.path())
+		.arg("-")
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.spawn()
+		.map_err(|e| io::Error::other(format!("cannot run {}: {}", program, e)))?;
+
+	if let Some(mut stdin) = child.stdin.take() {
+		stdin.write_all(payload)?;
+	}
+	let output = child.wait_with_output()?;
+
+	let status = String::from_utf8_lossy(&output.stdout);
+	let has = |name: &str| {
+		status
+			.lines()
+			.any(|x| x.strip_prefix("[GNUPG:] ").is_some_and(|x| x.split(' ').next() == Some(name)))
+	};
+	let status = if has("BADSIG") {
+		Status::Bad
+	} else if has("GOODSIG") && output.status.success() {
+		Status::Good
+	} else {
+		Status::Unknown
+	};
+
+	Ok((status, String::from_utf8_lossy(&output.stderr).into_owned()))
+}
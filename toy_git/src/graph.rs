@@ -0,0 +1,134 @@
+// log --graph の左側に描く線
+// 各列はそこを通っている線が次に辿り着くcommitのhashを持つ
+#[derive(Default)]
+pub struct Graph {
+	columns: Vec<String>,
+	width: usize, // 直前のcommitで描いた幅。次のcommitまではこの幅に揃える
+}
+
+impl Graph {
+	pub fn new() -> Self {
+		Self {
+			columns: Vec::new(),
+			width: 0,
+		}
+	}
+
+	// commitの行と、必要なら線が分かれたり合流したりする行を返す
+	// commitはtopological orderで渡す
+	pub fn next(&mut self, hash: &str, parents: &[String]) -> Vec<String> {
+		let index = match self.columns.iter().position(|x| x == hash) {
+			Some(i) => i,
+			None => {
+				self.columns.push(String::from(hash));
+				self.columns.len() - 1
+			}
+		};
+
+		// commitの列をparentで置き換え、同じcommitに向かう線は左側に合流させる
+		// (hash, 元の列)
+		let mut lines: Vec<(&String, usize)> = Vec::new();
+		lines.extend(self.columns[..index].iter().zip(0..));
+		lines.extend(parents.iter().map(|x| (x, index)));
+		lines.extend(self.columns[index + 1..].iter().zip(index + 1..));
+
+		let mut columns: Vec<String> = Vec::new();
+		let mut moves = Vec::new();
+		for (hash, from) in lines {
+			let to = match columns.iter().position(|x| x == hash) {
+				Some(i) => i,
+				None => {
+					columns.push(hash.clone());
+					columns.len() - 1
+				}
+			};
+			moves.push((from, to));
+		}
+
+		let width = 2 * self.columns.len().max(columns.len());
+		let mut rows = Vec::new();
+
+		let row: String = (0..self.columns.len())
+			.map(|i| if i == index { "* " } else { "| " })
+			.collect();
+		rows.push(format!("{:width$}", row, width = width));
+
+		if parents.len() > 1 || moves.iter().any(|(from, to)| from != to) {
+			let mut chars = vec![' '; width];
+			for &(from, to) in moves.iter() {
+				match to {
+					_ if to == from => chars[2 * to] = '|',
+					_ if to > from => chars[2 * from + 1] = '\\',
+					_ if to + 1 == from => chars[2 * from - 1] = '/',
+					// 2列以上左へ移る線は、gitと同じくこの行で間の線を "_" で越え、次の行で "/" で入る
+					_ => {
+						chars[2 * from - 1] = '/';
+						for c in chars[2 * to + 3..2 * from - 1].iter_mut() {
+							if *c == ' ' {
+								*c = '_';
+							}
+						}
+					}
+				}
+			}
+			rows.push(chars.into_iter().collect());
+
+			if moves.iter().any(|(from, to)| to + 1 < *from) {
+				let mut chars = vec![' '; width];
+				for &(from, to) in moves.iter() {
+					match to + 1 < from {
+						true => chars[2 * to + 1] = '/',
+						false => chars[2 * to] = '|',
+					}
+				}
+				rows.push(chars.into_iter().collect());
+			}
+		}
+
+		self.columns = columns;
+		self.width = width;
+		rows
+	}
+
+	// commitの2行目以降の前に付ける、線が真っ直ぐ続くだけの部分
+	pub fn padding(&self) -> String {
+		let row = "| ".repeat(self.columns.len());
+		format!("{:width$}", row, width = self.width)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn rows(graph: &mut Graph, hash: &str, parents: &[&str]) -> Vec<String> {
+		let parents: Vec<String> = parents.iter().map(|x| x.to_string()).collect();
+		graph
+			.next(hash, &parents)
+			.into_iter()
+			.map(|x| x.trim_end().to_string())
+			.collect()
+	}
+
+	#[test]
+	fn merge_and_fork() {
+		let mut graph = Graph::new();
+		assert_eq!(rows(&mut graph, "m", &["a", "b"]), ["*", "|\\"]);
+		assert_eq!(rows(&mut graph, "b", &["a"]), ["| *", "|/"]);
+		assert_eq!(rows(&mut graph, "a", &[]), ["*"]);
+	}
+
+	#[test]
+	fn collapse_over_columns() {
+		let mut graph = Graph::new();
+		rows(&mut graph, "e", &["x"]);
+		rows(&mut graph, "q", &["q1"]);
+		rows(&mut graph, "p", &["p1"]);
+		// 3列目の線が0列目に合流するときはgitと同じく2行で描く
+		assert_eq!(
+			rows(&mut graph, "d", &["x"]),
+			["| | | *", "| |_|/", "|/| |"]
+		);
+		assert_eq!(graph.padding().trim_end(), "| | |");
+	}
+}
@@ -0,0 +1,150 @@
+use std::fmt;
+
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+
+// objectのhash(SHA-1なら20byte、SHA-256なら32byte)
+// 辞書順で比べる。HashSetやBTreeMapのkeyにもできる
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Hash(pub Vec<u8>);
+
+impl Hash {
+	pub fn from_hex(hex: &str) -> Option<Self> {
+		match hex::decode(hex) {
+			Ok(bytes) if bytes.len() == 20 || bytes.len() == 32 => Some(Self(bytes)),
+			_ => None,
+		}
+	}
+
+	pub fn to_hex(&self) -> String {
+		hex::encode(&self.0)
+	}
+}
+
+impl fmt::Display for Hash {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", self.to_hex())
+	}
+}
+
+// objectの名前にするhashの種類。repositoryごとにextensions.objectFormatで決まる
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HashAlgorithm {
+	#[default]
+	Sha1,
+	Sha256,
+}
+
+impl HashAlgorithm {
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name.to_ascii_lowercase().as_str() {
+			"sha1" => Some(Self::Sha1),
+			"sha256" => Some(Self::Sha256),
+			_ => None,
+		}
+	}
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			Self::Sha1 => "sha1",
+			Self::Sha256 => "sha256",
+		}
+	}
+
+	// hashのbyte数
+	pub fn hash_len(&self) -> usize {
+		match self {
+			Self::Sha1 => 20,
+			Self::Sha256 => 32,
+		}
+	}
+
+	pub fn hex_len(&self) -> usize {
+		self.hash_len() * 2
+	}
+
+	// refを作った、または消したことを表す0だけのhash
+	pub fn null_hex(&self) -> String {
+		"0".repeat(self.hex_len())
+	}
+
+	pub fn digest(&self, bytes: &[u8]) -> Vec<u8> {
+		match self {
+			Self::Sha1 => Sha1::digest(bytes).to_vec(),
+			Self::Sha256 => Sha256::digest(bytes).to_vec(),
+		}
+	}
+
+	pub fn hasher(&self) -> Hasher {
+		match self {
+			Self::Sha1 => Hasher::Sha1(Sha1::new()),
+			Self::Sha256 => Hasher::Sha256(Sha256::new()),
+		}
+	}
+}
+
+// 少しずつ渡した内容のhash
+pub enum Hasher {
+	Sha1(Sha1),
+	Sha256(Sha256),
+}
+
+impl Hasher {
+	pub fn update(&mut self, bytes: &[u8]) {
+		match self {
+			Self::Sha1(x) => x.update(bytes),
+			Self::Sha256(x) => x.update(bytes),
+		}
+	}
+
+	pub fn finalize(self) -> Vec<u8> {
+		match self {
+			Self::Sha1(x) => x.finalize().to_vec(),
+			Self::Sha256(x) => x.finalize().to_vec(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::{BTreeSet, HashSet};
+
+	use super::*;
+
+	const FOO: &str = "257cc5642cb1a054f08cc83f2d943e56fd3ebe99";
+
+	#[test]
+	fn equal_from_different_sources() {
+		// hexの大文字小文字に関係無く、digestしたものとも同じ
+		let digest = Hash(HashAlgorithm::Sha1.digest(b"blob 4\0foo\n"));
+		let lower = Hash::from_hex(FOO).unwrap();
+		let upper = Hash::from_hex(&FOO.to_uppercase()).unwrap();
+		assert_eq!(digest, lower);
+		assert_eq!(lower, upper);
+		assert_eq!(upper.to_string(), FOO);
+
+		let mut set = HashSet::new();
+		assert!(set.insert(digest));
+		assert!(!set.insert(upper));
+		assert!(set.contains(&lower));
+		assert_eq!(set.len(), 1);
+	}
+
+	#[test]
+	fn ordered_by_bytes() {
+		let hashes: BTreeSet<Hash> = ["ff", "00", "80", "0f"]
+			.iter()
+			.map(|x| Hash::from_hex(&x.repeat(20)).unwrap())
+			.collect();
+		let hex: Vec<String> = hashes.iter().map(|x| x.to_hex()[..2].to_string()).collect();
+		assert_eq!(hex, ["00", "0f", "80", "ff"]);
+	}
+
+	#[test]
+	fn from_hex_length() {
+		assert!(Hash::from_hex(&"ab".repeat(20)).is_some());
+		assert!(Hash::from_hex(&"ab".repeat(32)).is_some());
+		assert!(Hash::from_hex(&"ab".repeat(19)).is_none());
+		assert!(Hash::from_hex("not hex").is_none());
+	}
+}
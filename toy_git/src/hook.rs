@@ -0,0 +1,50 @@
+use std::fs;
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::config::Config;
+use crate::repository::Repository;
+
+// hookを置くディレクトリ。core.hooksPathがあればそれを使う(相対pathはworking directoryから)
+fn hooks_dir(repo: &Repository) -> io::Result<PathBuf> {
+	Ok(match Config::load(repo)?.get("core.hookspath") {
+		Some(path) => repo.workdir.join(path),
+		None => repo.commondir.join("hooks"),
+	})
+}
+
+// nameのhookがあれば、working directoryでargsを渡して実行し、0以外で終了したらErrorにする
+// 実行権限の無いhookはgitと同じく実行せずにhintを出す
+pub fn run(repo: &Repository, name: &str, args: &[&str]) -> io::Result<()> {
+	let path = hooks_dir(repo)?.join(name);
+	let metadata = match fs::metadata(&path) {
+		Ok(metadata) => metadata,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+		Err(e) => return Err(e),
+	};
+	if metadata.permissions().mode() & 0o111 == 0 {
+		let shown = path.strip_prefix(&repo.workdir).unwrap_or(&path);
+		eprintln!(
+			"hint: The '{}' hook was ignored because it's not set as executable.",
+			shown.display()
+		);
+		return Ok(());
+	}
+
+	let status = Command::new(&path)
+		.args(args)
+		.current_dir(&repo.workdir)
+		.env("GIT_INDEX_FILE", repo.gitdir.join("index"))
+		.status()
+		.map_err(|e| io::Error::other(format!("cannot run {}: {}", path.display(), e)))?;
+	match status.code() {
+		Some(0) => Ok(()),
+		Some(code) => Err(io::Error::other(format!(
+			"{} hook exited with status {}",
+			name, code
+		))),
+		None => Err(io::Error::other(format!("{} hook was killed", name))),
+	}
+}
@@ -0,0 +1,40 @@
+use std::env;
+use std::io;
+
+use chrono::Local;
+
+use crate::config::Config;
+use crate::date;
+use crate::object::User;
+use crate::repository::Repository;
+
+// GIT_AUTHOR_NAMEなどの環境変数を優先し、無ければconfigのuser.name/user.emailを使う
+pub fn author(repo: &Repository) -> io::Result<User> {
+	ident(repo, "AUTHOR")
+}
+
+pub fn committer(repo: &Repository) -> io::Result<User> {
+	ident(repo, "COMMITTER")
+}
+
+fn ident(repo: &Repository, role: &str) -> io::Result<User> {
+	let config = Config::load(repo)?;
+	let lookup = |name: &str, key: &str| {
+		env::var(format!("GIT_{}_{}", role, name))
+			.ok()
+			.or_else(|| config.get(key).map(String::from))
+	};
+
+	let name = lookup("NAME", "user.name")
+		.ok_or_else(|| io::Error::other("unable to auto-detect name (set user.name)"))?;
+	let email = lookup("EMAIL", "user.email")
+		.ok_or_else(|| io::Error::other("unable to auto-detect email address (set user.email)"))?;
+
+	let ts = match env::var(format!("GIT_{}_DATE", role)) {
+		Ok(date) => date::parse(&date)
+			.ok_or_else(|| io::Error::other(format!("invalid date format: {}", date)))?,
+		Err(_) => Local::now().fixed_offset(),
+	};
+
+	Ok(User::new(name, email, ts))
+}
@@ -0,0 +1,158 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::repository::Repository;
+use crate::wildmatch::wildmatch;
+
+// .gitignoreか.git/info/excludeの1行
+struct Pattern {
+	base: String, // 書かれていた.gitignoreのあるディレクトリ。patternはここからのpath
+	pattern: String,
+	negated: bool,  // "!" で始まる行は一致したpathをignoreしない
+	dir_only: bool, // "/" で終わる行はディレクトリだけに一致する
+	anchored: bool, // 先頭か途中に "/" があれば、path全体と比べる
+}
+
+// 優先度の低い順に並べたpattern
+pub struct Ignore {
+	patterns: Vec<Pattern>,
+}
+
+impl Ignore {
+	pub fn from(content: &str) -> Self {
+		let mut ignore = Self {
+			patterns: Vec::new(),
+		};
+		ignore.parse(content, "");
+		ignore
+	}
+
+	// .git/info/exclude(最も優先度が低い)と、working directoryにある.gitignore(深いディレクトリのものほど優先)を読む
+	// gitと同じく、ignoreされたディレクトリの中の.gitignoreは読まない
+	pub fn load(repo: &Repository) -> io::Result<Self> {
+		let mut ignore = Self::from("");
+		if let Some(content) = read(&repo.commondir.join("info").join("exclude"))? {
+			ignore.parse(&content, "");
+		}
+		if !repo.bare {
+			ignore.load_dir(&repo.workdir, "")?;
+		}
+		Ok(ignore)
+	}
+
+	// pathがignoreされるか。親のディレクトリがignoreされていれば、その中は "!" の行があってもignoreされる
+	// indexにあるファイルかどうかは見ないので、呼ぶ側で除く
+	pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+		path.match_indices('/')
+			.any(|(i, _)| self.check(&path[..i], true))
+			|| self.check(path, is_dir)
+	}
+
+	// 後に書かれた行が優先される
+	fn check(&self, path: &str, is_dir: bool) -> bool {
+		self.patterns
+			.iter()
+			.rev()
+			.find(|x| (is_dir || !x.dir_only) && x.matches(path))
+			.is_some_and(|x| !x.negated)
+	}
+
+	fn parse(&mut self, content: &str, base: &str) {
+		for line in content.lines() {
+			let line = trim_spaces(line);
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+			let (negated, line) = match line.strip_prefix('!') {
+				Some(rest) => (true, rest),
+				None => (false, line),
+			};
+			let dir_only = line.ends_with('/');
+			let line = line.trim_end_matches('/');
+			if line.is_empty() {
+				continue;
+			}
+			// "\#" や "\!" で始まる行は、その文字そのものに一致する
+			let pattern = match line.strip_prefix('\\') {
+				Some(rest) if rest.starts_with('#') || rest.starts_with('!') => rest,
+				_ => line,
+			};
+			self.patterns.push(Pattern {
+				base: String::from(base),
+				pattern: String::from(pattern.strip_prefix('/').unwrap_or(pattern)),
+				negated,
+				dir_only,
+				anchored: line.contains('/'),
+			});
+		}
+	}
+
+	// dirの.gitignoreを読んでから、ignoreされていないその下のディレクトリを辿る
+	// 親のpatternが子のpatternより前に並ぶ
+	fn load_dir(&mut self, dir: &Path, base: &str) -> io::Result<()> {
+		if let Some(content) = read(&dir.join(".gitignore"))? {
+			self.parse(&content, base);
+		}
+
+		let mut children = Vec::new();
+		for entry in fs::read_dir(dir)? {
+			let entry = entry?;
+			let name = entry.file_name().to_string_lossy().into_owned();
+			if name != ".git" && entry.file_type()?.is_dir() {
+				children.push(name);
+			}
+		}
+		children.sort();
+
+		for name in children {
+			let child = match base {
+				"" => name.clone(),
+				base => format!("{}/{}", base, name),
+			};
+			if !self.check(&child, true) {
+				self.load_dir(&dir.join(&name), &child)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+impl Pattern {
+	fn matches(&self, path: &str) -> bool {
+		let path = match self.base.as_str() {
+			"" => path,
+			base => match path.strip_prefix(base).and_then(|x| x.strip_prefix('/')) {
+				Some(rest) => rest,
+				None => return false,
+			},
+		};
+		match self.anchored {
+			true => wildmatch(&self.pattern, path, true),
+			false => {
+				let name = path.rsplit('/').next().unwrap_or(path);
+				wildmatch(&self.pattern, name, true)
+			}
+		}
+	}
+}
+
+// 行末の空白を除く。"\ " で終わる場合はその空白を残す
+fn trim_spaces(line: &str) -> &str {
+	let mut line = line.trim_end_matches(['\r', '\n']);
+	while let Some(rest) = line.strip_suffix(' ') {
+		if rest.ends_with('\\') {
+			break;
+		}
+		line = rest;
+	}
+	line
+}
+
+fn read(path: &Path) -> io::Result<Option<String>> {
+	match fs::read_to_string(path) {
+		Ok(content) => Ok(Some(content)),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+		Err(e) => Err(e),
+	}
+}
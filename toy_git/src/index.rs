@@ -0,0 +1,500 @@
+use std::fs;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::os::unix::fs::MetadataExt;
+
+use crate::bytes::Bytes;
+use crate::config::Config;
+use crate::error::GitError;
+use crate::hash::HashAlgorithm;
+use crate::repository::Repository;
+use crate::tempfile::TempFile;
+
+// skip-worktreeのエントリはworking directoryにファイルを置かない(sparse checkout)
+const SKIP_WORKTREE: u16 = 0x4000;
+// flagsにこのbitがあるとextended flagsが続く(version 3)
+const EXTENDED: u16 = 0x4000;
+
+// .git/index (version 2, 3) の1エントリ
+pub struct Entry {
+	pub ctime: u32,
+	pub ctime_nsec: u32,
+	pub mtime: u32,
+	pub mtime_nsec: u32,
+	pub dev: u32,
+	pub ino: u32,
+	pub mode: u32,
+	pub uid: u32,
+	pub gid: u32,
+	pub size: u32,
+	pub hash: Vec<u8>,
+	pub flags: u16,
+	pub extended_flags: u16, // version 3のみ
+	pub path: String,
+}
+
+#[derive(Default)]
+pub struct Index {
+	pub entries: Vec<Entry>,
+	pub tree: Option<CacheTree>,   // TREE extension
+	pub ignore_case: bool,         // core.ignorecase
+	pub mtime: Option<(u32, u32)>, // 読み込んだindexファイルの(秒, ナノ秒)。racyなエントリの判定に使う
+}
+
+// TREE extensionの1つのディレクトリ
+// write-treeで作ったtreeのhashを覚えておき、変更の無いディレクトリはtreeを作り直さない
+#[derive(Clone)]
+pub struct CacheTree {
+	pub name: String,          // 親のディレクトリからの名前。rootは空
+	pub entry_count: i32,      // このディレクトリ以下のエントリの数。-1は無効になったもの
+	pub hash: Option<Vec<u8>>, // 無効なものには無い
+	pub children: Vec<CacheTree>,
+}
+
+// symlinkか、実行権限の有無だけをmodeに反映する
+pub fn mode_from(meta: &fs::Metadata) -> u32 {
+	if meta.file_type().is_symlink() {
+		0o120000
+	} else if meta.mode() & 0o111 != 0 {
+		0o100755
+	} else {
+		0o100644
+	}
+}
+
+impl Entry {
+	pub fn new(path: String, hash: Vec<u8>, meta: &fs::Metadata) -> Self {
+		Self {
+			ctime: meta.ctime() as u32,
+			ctime_nsec: meta.ctime_nsec() as u32,
+			mtime: meta.mtime() as u32,
+			mtime_nsec: meta.mtime_nsec() as u32,
+			dev: meta.dev() as u32,
+			ino: meta.ino() as u32,
+			mode: mode_from(meta),
+			uid: meta.uid(),
+			gid: meta.gid(),
+			size: meta.size() as u32,
+			hash,
+			flags: path.len().min(0xfff) as u16,
+			extended_flags: 0,
+			path,
+		}
+	}
+
+	// 保存したときのstat情報と今のファイルが同じか(同じなら内容も変わっていないとみなす)
+	pub fn matches_stat(&self, meta: &fs::Metadata) -> bool {
+		self.mode == mode_from(meta)
+			&& self.size == meta.size() as u32
+			&& self.ino == meta.ino() as u32
+			&& (self.mtime, self.mtime_nsec) == (meta.mtime() as u32, meta.mtime_nsec() as u32)
+			&& (self.ctime, self.ctime_nsec) == (meta.ctime() as u32, meta.ctime_nsec() as u32)
+	}
+
+	// hashの長さはrepositoryのhashによる。bytesの位置はindexのファイルでのもの
+	pub fn from(bytes: Bytes, hash_len: usize) -> Result<(Self, usize), GitError> {
+		let field = |i: usize| bytes.u32(i * 4);
+
+		let end = 40 + hash_len;
+		let hash = bytes.get(40, hash_len)?.to_vec();
+		let flags = bytes.u16(end)?;
+		let (extended_flags, start) = match flags & EXTENDED {
+			0 => (0, end + 2),
+			_ => (bytes.u16(end + 2)?, end + 4),
+		};
+
+		// pathは\0で終わり、エントリ全体が8byte境界になるまで\0で埋められる
+		let name = bytes.rest(start)?;
+		let len = name
+			.data
+			.iter()
+			.position(|&b| b == b'\0')
+			.ok_or_else(|| name.error(name.len(), "unterminated path"))?;
+		let path = String::from_utf8(name.data[..len].to_vec()).map_err(|e| GitError::Utf8 {
+			field: "path",
+			offset: name.base + e.utf8_error().valid_up_to(),
+		})?;
+		let size = (start + len + 8) / 8 * 8;
+
+		Ok((
+			Self {
+				ctime: field(0)?,
+				ctime_nsec: field(1)?,
+				mtime: field(2)?,
+				mtime_nsec: field(3)?,
+				dev: field(4)?,
+				ino: field(5)?,
+				mode: field(6)?,
+				uid: field(7)?,
+				gid: field(8)?,
+				size: field(9)?,
+				hash,
+				flags: flags & !EXTENDED,
+				extended_flags,
+				path,
+			},
+			size,
+		))
+	}
+
+	pub fn encode(&self) -> Vec<u8> {
+		let fields = [
+			self.ctime,
+			self.ctime_nsec,
+			self.mtime,
+			self.mtime_nsec,
+			self.dev,
+			self.ino,
+			self.mode,
+			self.uid,
+			self.gid,
+			self.size,
+		];
+		let mut bytes: Vec<u8> = fields.iter().flat_map(|x| x.to_be_bytes()).collect();
+		bytes.extend_from_slice(&self.hash);
+		if self.extended_flags == 0 {
+			bytes.extend_from_slice(&self.flags.to_be_bytes());
+		} else {
+			bytes.extend_from_slice(&(self.flags | EXTENDED).to_be_bytes());
+			bytes.extend_from_slice(&self.extended_flags.to_be_bytes());
+		}
+		bytes.extend_from_slice(self.path.as_bytes());
+
+		let size = (bytes.len() + 8) / 8 * 8;
+		bytes.resize(size, b'\0');
+		bytes
+	}
+
+	// mergeでconflictしたときの、working directoryに対応するファイルの無いエントリ
+	// stageは1がbase、2がHEAD、3がmergeする側
+	pub fn unmerged(path: String, hash: Vec<u8>, mode: u32, stage: u16) -> Self {
+		Self {
+			ctime: 0,
+			ctime_nsec: 0,
+			mtime: 0,
+			mtime_nsec: 0,
+			dev: 0,
+			ino: 0,
+			mode,
+			uid: 0,
+			gid: 0,
+			size: 0,
+			hash,
+			flags: (stage << 12) | path.len().min(0xfff) as u16,
+			extended_flags: 0,
+			path,
+		}
+	}
+
+	pub fn stage(&self) -> u16 {
+		(self.flags >> 12) & 0b11
+	}
+
+	pub fn skip_worktree(&self) -> bool {
+		self.extended_flags & SKIP_WORKTREE != 0
+	}
+
+	pub fn set_skip_worktree(&mut self, skip: bool) {
+		if skip {
+			self.extended_flags |= SKIP_WORKTREE;
+		} else {
+			self.extended_flags &= !SKIP_WORKTREE;
+		}
+	}
+}
+
+impl CacheTree {
+	// "<name>\0<entry_count> <subtree_count>\n" の後に、有効ならhashが続き、その後にsubtreeが並ぶ
+	fn from(bytes: Bytes, hash_len: usize) -> Result<(Self, usize), GitError> {
+		let bad = |pos| bytes.error(pos, "bad cache tree");
+		let nul = bytes
+			.data
+			.iter()
+			.position(|&b| b == b'\0')
+			.ok_or_else(|| bad(0))?;
+		let name = String::from_utf8(bytes.data[..nul].to_vec()).map_err(|_| GitError::Utf8 {
+			field: "cache tree",
+			offset: bytes.base,
+		})?;
+		let lf = nul
+			+ bytes.data[nul..]
+				.iter()
+				.position(|&b| b == b'\n')
+				.ok_or_else(|| bad(nul))?;
+		let counts = std::str::from_utf8(&bytes.data[nul + 1..lf]).map_err(|_| bad(nul + 1))?;
+		let (entry_count, subtree_count) = counts.split_once(' ').ok_or_else(|| bad(nul + 1))?;
+		let entry_count: i32 = entry_count.parse().map_err(|_| bad(nul + 1))?;
+		let subtree_count: usize = subtree_count.parse().map_err(|_| bad(nul + 1))?;
+
+		let mut offset = lf + 1;
+		let hash = match entry_count {
+			0.. => {
+				offset += hash_len;
+				Some(bytes.get(offset - hash_len, hash_len)?.to_vec())
+			}
+			_ => None,
+		};
+
+		let mut children = Vec::new();
+		for _ in 0..subtree_count {
+			let (child, size) = Self::from(bytes.rest(offset)?, hash_len)?;
+			children.push(child);
+			offset += size;
+		}
+
+		let tree = Self {
+			name,
+			entry_count,
+			hash,
+			children,
+		};
+		Ok((tree, offset))
+	}
+
+	fn encode(&self) -> Vec<u8> {
+		let header = format!("{}\0{} {}\n", self.name, self.entry_count, self.children.len());
+		let mut bytes = header.into_bytes();
+		if let Some(hash) = &self.hash {
+			bytes.extend_from_slice(hash);
+		}
+		for child in self.children.iter() {
+			bytes.extend(child.encode());
+		}
+		bytes
+	}
+
+	pub fn child(&self, name: &str) -> Option<&CacheTree> {
+		self.children.iter().find(|x| x.name == name)
+	}
+
+	pub fn is_valid(&self) -> bool {
+		self.entry_count >= 0 && self.hash.is_some()
+	}
+
+	// pathを含むディレクトリを全て無効にする
+	fn invalidate(&mut self, path: &str) {
+		self.entry_count = -1;
+		self.hash = None;
+
+		if let Some((dir, rest)) = path.split_once('/') {
+			if let Some(child) = self.children.iter_mut().find(|x| x.name == dir) {
+				child.invalidate(rest);
+			}
+		}
+	}
+}
+
+impl Index {
+	pub fn new() -> Self {
+		Self {
+			entries: Vec::new(),
+			tree: None,
+			ignore_case: false,
+			mtime: None,
+		}
+	}
+
+	// 最後のhash(SHA-1なら20byte)はそれ以前の内容のchecksum。entryのhashも同じ種類
+	pub fn from(bytes: &[u8], algo: HashAlgorithm) -> Result<Self, GitError> {
+		let len = bytes
+			.len()
+			.checked_sub(algo.hash_len())
+			.ok_or_else(|| Bytes::new(bytes).error(0, "index file too short"))?;
+		let (body, checksum) = bytes.split_at(len);
+		let body = Bytes::new(body);
+		if body.get(0, 4)? != b"DIRC" {
+			return Err(body.error(0, "bad signature"));
+		}
+		if algo.digest(body.data) != checksum {
+			return Err(body.error(len, "bad index file sha1 signature"));
+		}
+
+		let version = body.u32(4)?;
+		if version != 2 && version != 3 {
+			return Err(body.error(4, "unsupported index version"));
+		}
+		let count = body.u32(8)?;
+
+		let mut entries = Vec::new();
+		let mut offset = 12;
+		for _ in 0..count {
+			let (entry, size) = Entry::from(body.rest(offset)?, algo.hash_len())?;
+			entries.push(entry);
+			offset += size;
+		}
+
+		// エントリの後には "<signature><size>" で始まるextensionが並ぶ
+		let mut tree = None;
+		while offset < body.len() {
+			let signature = body.get(offset, 4)?;
+			let size = body.u32(offset + 4)? as usize;
+			let data = body.slice(offset + 8, offset + 8 + size)?;
+			match signature {
+				b"TREE" => tree = Some(CacheTree::from(data, algo.hash_len())?.0),
+				// 大文字で始まるものは読まなくてもよいcache。それ以外は理解できないと読めない
+				x if x[0].is_ascii_uppercase() => {}
+				_ => return Err(body.error(offset, "unknown index extension")),
+			}
+			offset += 8 + size;
+		}
+
+		Ok(Self {
+			entries,
+			tree,
+			ignore_case: false,
+			mtime: None,
+		})
+	}
+
+	pub fn as_bytes(&self, algo: HashAlgorithm) -> Vec<u8> {
+		// extended flagsを使うエントリがあるときだけversion 3にする
+		let version: u32 = match self.entries.iter().any(|x| x.extended_flags != 0) {
+			true => 3,
+			false => 2,
+		};
+		let header = [
+			b"DIRC".to_vec(),
+			version.to_be_bytes().to_vec(),
+			(self.entries.len() as u32).to_be_bytes().to_vec(),
+		]
+		.concat();
+		let entries: Vec<u8> = self.entries.iter().flat_map(|x| x.encode()).collect();
+
+		let mut extensions = Vec::new();
+		if let Some(tree) = &self.tree {
+			let data = tree.encode();
+			extensions.extend_from_slice(b"TREE");
+			extensions.extend_from_slice(&(data.len() as u32).to_be_bytes());
+			extensions.extend(data);
+		}
+
+		let body = [header, entries, extensions].concat();
+		let checksum = algo.digest(&body);
+		[body, checksum].concat()
+	}
+
+	pub fn load(repo: &Repository) -> io::Result<Self> {
+		let path = repo.gitdir.join("index");
+		let mut index = match fs::read(&path) {
+			Ok(bytes) => {
+				Self::from(&bytes, repo.hash_algo).map_err(|e| e.context("index file corrupt"))?
+			}
+			Err(e) if e.kind() == io::ErrorKind::NotFound => Self::new(),
+			Err(e) => return Err(e),
+		};
+		if let Ok(meta) = fs::metadata(&path) {
+			index.mtime = Some((meta.mtime() as u32, meta.mtime_nsec() as u32));
+		}
+
+		index.ignore_case = Config::load(repo)?
+			.get_bool("core.ignorecase")
+			.unwrap_or(false);
+		Ok(index)
+	}
+
+	// "index.lock" に書いてからrenameで置き換える。lockが既にあれば書かずに失敗する
+	pub fn save(&self, repo: &Repository) -> io::Result<()> {
+		let path = repo.gitdir.join("index");
+		let mut file = TempFile::lock(&path)?;
+		file.write_all(&self.as_bytes(repo.hash_algo))?;
+		file.persist(&path)
+	}
+
+	// indexを書いたのと同じ時刻以降に変更されたファイルは、stat情報が同じでも
+	// 書いた直後に同じ時刻のうちに変更されたかもしれないので(racy git)、内容を比べなければならない
+	pub fn is_racy(&self, entry: &Entry) -> bool {
+		match self.mtime {
+			Some(mtime) => (entry.mtime, entry.mtime_nsec) >= mtime,
+			None => true,
+		}
+	}
+
+	fn same_path(&self, a: &str, b: &str) -> bool {
+		if self.ignore_case {
+			a.to_lowercase() == b.to_lowercase()
+		} else {
+			a == b
+		}
+	}
+
+	// entriesはpathのbyte列の順に並んでいるので二分探索する
+	// ignore_caseの場合は大文字と小文字が違うpathが離れて並ぶので、先頭から探す
+	pub fn find(&self, path: &str) -> Option<&Entry> {
+		match self.ignore_case {
+			true => self.entries.iter().find(|x| self.same_path(&x.path, path)),
+			false => self.entries[self.range(path)].first(),
+		}
+	}
+
+	// pathと全く同じpathのエントリ(各stage)がある範囲
+	fn range(&self, path: &str) -> Range<usize> {
+		let start = self
+			.entries
+			.partition_point(|x| x.path.as_bytes() < path.as_bytes());
+		let len = self.entries[start..].partition_point(|x| x.path == path);
+		start..start + len
+	}
+
+	// prefix("/"で終わるディレクトリ)の下にエントリがあるか
+	pub fn has_under(&self, prefix: &str) -> bool {
+		let start = self
+			.entries
+			.partition_point(|x| x.path.as_bytes() < prefix.as_bytes());
+		self.entries
+			.get(start)
+			.is_some_and(|x| x.path.starts_with(prefix))
+	}
+
+	// 同じpathのエントリがあれば置き換える
+	// stage 0のエントリはconflictした各stageのエントリも置き換え、
+	// conflictのエントリ(stage 1から3)はstage 0と同じstageのものだけを置き換える
+	// ignore_caseの場合は既に登録されているpathの表記を残す
+	pub fn add(&mut self, mut entry: Entry) {
+		if let Some(existing) = self.find(&entry.path) {
+			entry.path = existing.path.clone();
+			entry.flags = (entry.flags & 0x3000) | existing.flags & 0xfff;
+		}
+		if let Some(tree) = &mut self.tree {
+			tree.invalidate(&entry.path);
+		}
+
+		// pathは既にあるものの表記にしたので、同じpathのエントリは並んでいる
+		let stage = entry.stage();
+		let range = self.range(&entry.path);
+		let start = range.start;
+		let mut kept: Vec<Entry> = self
+			.entries
+			.drain(range)
+			.filter(|x| stage != 0 && x.stage() != 0 && x.stage() != stage)
+			.collect();
+		kept.push(entry);
+		kept.sort_by_key(|x| x.stage());
+		self.entries.splice(start..start, kept);
+	}
+
+	// pathの全てのstageのエントリを消して、最初のものを返す
+	pub fn remove(&mut self, path: &str) -> Option<Entry> {
+		let (removed, rest) = std::mem::take(&mut self.entries)
+			.into_iter()
+			.partition::<Vec<_>, _>(|x| self.same_path(&x.path, path));
+		self.entries = rest;
+
+		let removed = removed.into_iter().next();
+		if let (Some(entry), Some(tree)) = (&removed, &mut self.tree) {
+			tree.invalidate(&entry.path);
+		}
+		removed
+	}
+
+	// conflictしている(stageが0でないエントリがある)path
+	pub fn unmerged(&self) -> Vec<&str> {
+		let mut paths: Vec<&str> = self
+			.entries
+			.iter()
+			.filter(|x| x.stage() != 0)
+			.map(|x| x.path.as_str())
+			.collect();
+		paths.dedup();
+		paths
+	}
+}
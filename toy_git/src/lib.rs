@@ -0,0 +1,54 @@
+// object、ref、indexなどと各コマンド。src/main.rsは引数を読んでcmdを呼ぶだけ
+
+pub mod attributes;
+pub mod blame;
+pub mod bytes;
+pub mod cmd;
+pub mod commit_graph;
+pub mod config;
+pub mod date;
+pub mod diff;
+pub mod error;
+pub mod fast;
+pub mod filter;
+pub mod gc_lock;
+pub mod gpg;
+pub mod graph;
+pub mod hash;
+pub mod hook;
+pub mod ident;
+pub mod ignore;
+pub mod index;
+pub mod mail;
+pub mod merge;
+pub mod notes;
+pub mod object;
+pub mod pack;
+pub mod parallel;
+pub mod pkt_line;
+pub mod pretty;
+pub mod progress;
+pub mod reachable;
+pub mod refs;
+pub mod regex;
+pub mod repository;
+pub mod revparse;
+pub mod sparse;
+pub mod store;
+pub mod tempfile;
+pub mod transaction;
+pub mod transport;
+pub mod upstream;
+pub mod walker;
+pub mod wildmatch;
+pub mod worktree;
+pub mod zlib;
+
+pub use error::GitError;
+pub use hash::{Hash, HashAlgorithm};
+pub use index::Index;
+pub use object::{Blob, Commit, GitObject, ObjectKind, Tag, Tree};
+pub use refs::Refs;
+pub use repository::Repository;
+pub use store::ObjectStore;
+pub use walker::CommitWalker;
@@ -0,0 +1,356 @@
+// format-patchが書き、amが読むmail
+// gitと同じく、headerのASCIIでない文字はRFC 2047の "=?UTF-8?q?...?=" にし、長い行は折り返す
+use chrono::{DateTime, FixedOffset};
+
+use crate::date;
+use crate::error::GitError;
+
+// 折り返す幅。RFC 2047で書いた行は76文字まで
+const WRAP: usize = 78;
+const ENCODED_WRAP: usize = 76;
+
+// RFC 2822でquoteが必要な文字
+const SPECIALS: &str = "()<>@,;:\\\".[]";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+	Subject,
+	Address,
+}
+
+// "From: <name> <<email>>"
+pub fn from_header(name: &str, email: &str) -> String {
+	let mut out = String::from("From: ");
+	if needs_encoding(name) {
+		encode(&mut out, name, Kind::Address);
+	} else if name.chars().any(|x| SPECIALS.contains(x)) {
+		let quoted = name.replace('\\', "\\\\").replace('"', "\\\"");
+		wrap(&mut out, &format!("\"{}\"", quoted));
+	} else {
+		wrap(&mut out, name);
+	}
+	let width = if needs_encoding(name) {
+		ENCODED_WRAP
+	} else {
+		WRAP
+	};
+	if last_line(&out) + email.len() + 3 > width {
+		out.push('\n');
+	}
+	out.push_str(&format!(" <{}>\n", email));
+	out
+}
+
+// "Subject: <prefix><title>"。prefixは "[PATCH 1/2] " のようなもので、encodeしない
+pub fn subject_header(prefix: &str, title: &str) -> String {
+	let mut out = format!("Subject: {}", prefix);
+	match needs_encoding(title) {
+		true => encode(&mut out, title, Kind::Subject),
+		false => wrap(&mut out, title),
+	}
+	out.push('\n');
+	out
+}
+
+// ASCIIでない文字や、encodeしたものと紛らわしい "=?" があるとき
+fn needs_encoding(text: &str) -> bool {
+	!text.is_ascii() || text.contains('\n') || text.contains("=?")
+}
+
+fn last_line(out: &str) -> usize {
+	out.len() - out.rfind('\n').map_or(0, |x| x + 1)
+}
+
+// gitのadd_rfc2047と同じく、特別な文字を "=XX" にし、1つの文字を分けずに76文字で折り返す
+fn encode(out: &mut String, text: &str, kind: Kind) {
+	let mut len = last_line(out) + "=?UTF-8?q?".len();
+	out.push_str("=?UTF-8?q?");
+	for c in text.chars() {
+		let mut bytes = [0; 4];
+		let bytes = c.encode_utf8(&mut bytes).as_bytes();
+		let special = bytes.len() > 1 || is_special(bytes[0], kind);
+		let encoded_len = if special { 3 * bytes.len() } else { 1 };
+		if len + encoded_len + 2 > ENCODED_WRAP {
+			out.push_str("?=\n =?UTF-8?q?");
+			len = " =?UTF-8?q?".len();
+		}
+		for b in bytes {
+			match special {
+				true => out.push_str(&format!("={:02X}", b)),
+				false => out.push(*b as char),
+			}
+		}
+		len += encoded_len;
+	}
+	out.push_str("?=");
+}
+
+// gitと同じく、空白も "_" ではなく "=20" にする
+fn is_special(b: u8, kind: Kind) -> bool {
+	if !b.is_ascii_graphic() || b == b'=' || b == b'?' || b == b'_' {
+		return true;
+	}
+	kind == Kind::Address && !(b.is_ascii_alphanumeric() || b"!*+-/".contains(&b))
+}
+
+// 空白の位置で、行がWRAPを超えないように折り返す。続きの行は空白1つで始める
+fn wrap(out: &mut String, text: &str) {
+	let mut len = last_line(out);
+	for (i, word) in text.split(' ').enumerate() {
+		if i > 0 {
+			if len + 1 + word.len() > WRAP {
+				out.push_str("\n ");
+				len = 1;
+			} else {
+				out.push(' ');
+				len += 1;
+			}
+		}
+		out.push_str(word);
+		len += word.len();
+	}
+}
+
+// mboxから読んだ1通のpatch
+pub struct Mail {
+	pub name: String,
+	pub email: String,
+	pub date: Option<DateTime<FixedOffset>>,
+	pub subject: String, // "[PATCH 1/2]" などを除いたもの
+	pub body: String,    // subjectの後から "---" の前まで
+	pub patch: Vec<u8>,  // "---" 以降。無ければ空
+}
+
+impl Mail {
+	// headerと本文を分け、本文は最初の "---" の行か "diff -" で始まる行からをpatchとする
+	pub fn from(bytes: &[u8]) -> Result<Self, GitError> {
+		let text = String::from_utf8_lossy(bytes);
+		let mut lines = text.split_inclusive('\n');
+		let mut headers: Vec<(String, String)> = Vec::new();
+		let mut offset = 0;
+		for line in lines.by_ref() {
+			let start = offset;
+			offset += line.len();
+			let line = line.trim_end_matches(['\r', '\n']);
+			if line.is_empty() {
+				break;
+			}
+			let bad = |message| GitError::InvalidHeader {
+				offset: start,
+				message,
+			};
+			// 空白で始まる行は前のheaderの続き
+			if line.starts_with([' ', '\t']) {
+				let last = headers
+					.last_mut()
+					.ok_or_else(|| bad("continuation line without header"))?;
+				last.1.push_str(line);
+				continue;
+			}
+			let (name, value) = line.split_once(':').ok_or_else(|| bad("bad header line"))?;
+			headers.push((name.to_ascii_lowercase(), String::from(value.trim())));
+		}
+		let header = |name: &str| {
+			headers
+				.iter()
+				.find(|(x, _)| x == name)
+				.map(|(_, x)| decode(x))
+		};
+
+		let from = header("from").ok_or(GitError::InvalidHeader {
+			offset: 0,
+			message: "missing From header",
+		})?;
+		let (name, email) = address(&from);
+		let mut body = String::new();
+		let mut patch = String::new();
+		for line in lines {
+			let content = line.trim_end_matches(['\r', '\n']);
+			if patch.is_empty() && content.trim_end() != "---" && !content.starts_with("diff -") {
+				body.push_str(content);
+				body.push('\n');
+			} else {
+				patch.push_str(line);
+			}
+		}
+		Ok(Self {
+			name,
+			email,
+			date: header("date").and_then(|x| date::parse(&x)),
+			subject: clean_subject(&header("subject").unwrap_or_default()),
+			body: String::from(body.trim()),
+			patch: patch.into_bytes(),
+		})
+	}
+
+	// commit message。本文が無ければsubjectだけ
+	pub fn message(&self) -> String {
+		match self.body.is_empty() {
+			true => format!("{}\n", self.subject),
+			false => format!("{}\n\n{}\n", self.subject, self.body),
+		}
+	}
+}
+
+// mboxを "From " で始まる区切りの行ごとに分ける。区切りが無ければ全体を1通とする
+pub fn split(mbox: &[u8]) -> Vec<&[u8]> {
+	// 本文中の "From " と区別するため、区切りの行には時刻の ":" があるものとする
+	let is_from = |line: &[u8]| line.starts_with(b"From ") && line.contains(&b':');
+	let mut starts = Vec::new();
+	let mut pos = 0;
+	for line in mbox.split_inclusive(|x| *x == b'\n') {
+		if is_from(line) {
+			starts.push(pos);
+		}
+		pos += line.len();
+	}
+	if starts.is_empty() {
+		return match mbox.iter().all(u8::is_ascii_whitespace) {
+			true => Vec::new(),
+			false => vec![mbox],
+		};
+	}
+
+	starts.push(mbox.len());
+	starts
+		.windows(2)
+		.map(|x| {
+			let mail = &mbox[x[0]..x[1]];
+			// 区切りの行は除く
+			let body = mail
+				.iter()
+				.position(|x| *x == b'\n')
+				.map_or(mail.len(), |x| x + 1);
+			&mail[body..]
+		})
+		.collect()
+}
+
+// "Name <email>" か "email (Name)" か "email"。quoteされた名前は戻す
+fn address(value: &str) -> (String, String) {
+	let unquote = |name: &str| {
+		let name = name.trim();
+		match name.strip_prefix('"').and_then(|x| x.strip_suffix('"')) {
+			Some(quoted) => quoted.replace("\\\"", "\"").replace("\\\\", "\\"),
+			None => String::from(name),
+		}
+	};
+	if let Some((name, rest)) = value.split_once('<') {
+		let email = rest.split('>').next().unwrap_or(rest).trim();
+		let name = unquote(name);
+		let name = if name.is_empty() {
+			String::from(email)
+		} else {
+			name
+		};
+		return (name, String::from(email));
+	}
+	if let Some((email, name)) = value.split_once('(') {
+		return (
+			unquote(name.trim_end_matches(')')),
+			String::from(email.trim()),
+		);
+	}
+	(String::from(value.trim()), String::from(value.trim()))
+}
+
+// gitのcleanup_subjectと同じく、先頭の "[PATCH]" や "Re:" を除く
+fn clean_subject(subject: &str) -> String {
+	let mut subject = subject.trim();
+	loop {
+		if subject.starts_with('[') {
+			match subject.find(']') {
+				Some(end) => subject = subject[end + 1..].trim_start(),
+				None => break,
+			}
+		} else if subject.len() >= 3 && subject[..3].eq_ignore_ascii_case("re:") {
+			subject = subject[3..].trim_start();
+		} else {
+			break;
+		}
+	}
+	String::from(subject)
+}
+
+// RFC 2047の "=?charset?q?...?=" と "=?charset?b?...?=" を戻す
+// 続けて並んだencoded-wordの間の空白は除く
+fn decode(value: &str) -> String {
+	let mut out = String::new();
+	let mut rest = value;
+	let mut after_word = false;
+	while let Some(start) = rest.find("=?") {
+		let Some(word) = encoded_word(&rest[start..]) else {
+			out.push_str(&rest[..start + 2]);
+			rest = &rest[start + 2..];
+			after_word = false;
+			continue;
+		};
+		let between = &rest[..start];
+		if !(after_word && between.trim().is_empty()) {
+			out.push_str(between);
+		}
+		out.push_str(&word.0);
+		rest = &rest[start + word.1..];
+		after_word = true;
+	}
+	out.push_str(rest);
+	out
+}
+
+// 戻した文字列と、encoded-wordのbyte数
+fn encoded_word(text: &str) -> Option<(String, usize)> {
+	let mut parts = text[2..].splitn(3, '?');
+	let charset = parts.next()?;
+	let encoding = parts.next()?;
+	let rest = parts.next()?;
+	let end = rest.find("?=")?;
+	let encoded = &rest[..end];
+	let len = 2 + charset.len() + 1 + encoding.len() + 1 + end + 2;
+
+	let bytes = match encoding {
+		"q" | "Q" => {
+			let mut bytes = Vec::new();
+			let mut i = 0;
+			let raw = encoded.as_bytes();
+			while i < raw.len() {
+				match raw[i] {
+					b'_' => bytes.push(b' '),
+					b'=' => {
+						let hex = encoded.get(i + 1..i + 3)?;
+						bytes.push(u8::from_str_radix(hex, 16).ok()?);
+						i += 2;
+					}
+					b => bytes.push(b),
+				}
+				i += 1;
+			}
+			bytes
+		}
+		"b" | "B" => base64(encoded)?,
+		_ => return None,
+	};
+	// UTF-8以外はlatin-1として読む
+	let text = match charset.to_ascii_lowercase().as_str() {
+		"iso-8859-1" | "latin1" => bytes.iter().map(|&x| x as char).collect(),
+		_ => String::from_utf8_lossy(&bytes).into_owned(),
+	};
+	Some((text, len))
+}
+
+fn base64(text: &str) -> Option<Vec<u8>> {
+	const TABLE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+	let mut out = Vec::new();
+	let mut bits = 0u32;
+	let mut count = 0;
+	for c in text.bytes().filter(|x| *x != b'=') {
+		let value = TABLE.iter().position(|x| *x == c)? as u32;
+		bits = bits << 6 | value;
+		count += 6;
+		if count >= 8 {
+			count -= 8;
+			out.push((bits >> count) as u8);
+			bits &= (1 << count) - 1;
+		}
+	}
+	Some(out)
+}
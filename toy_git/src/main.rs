@@ -1,232 +1,1564 @@
-pub enum GitObject {
-	Blob(Blob),
-	Tree(Tree),
-	Commit(Commit),
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use toy_git::object::ObjectKind;
+use toy_git::repository::Repository;
+use toy_git::{cmd, date, diff, hash, pretty, progress, refs, revparse};
+
+fn main() {
+	let mut args: Vec<String> = env::args().skip(1).collect();
+
+	// subcommandより前に書くoption。gitと同じく環境変数で子processにも伝える
+	if args.first().is_some_and(|x| x == "--no-replace-objects") {
+		args.remove(0);
+		env::set_var("GIT_NO_REPLACE_OBJECTS", "1");
+	}
+
+	match run(&args) {
+		Ok(()) => {}
+		// "| head" などで読む側が先に終わったときは、gitと同じく何も言わずに終わる(SIGPIPEで終わったときの終了コード)
+		Err(e) if e.kind() == io::ErrorKind::BrokenPipe => process::exit(141),
+		Err(e) => {
+			eprintln!("fatal: {}", e);
+			process::exit(128);
+		}
+	}
 }
 
-pub struct Blob {
-	pub size: usize,
-	pub content: String,
+fn run(args: &[String]) -> io::Result<()> {
+	match args.first().map(String::as_str) {
+		Some("add") => add(&args[1..]),
+		Some("am") => am(&args[1..]),
+		Some("apply") => apply(&args[1..]),
+		Some("bisect") => bisect(&args[1..]),
+		Some("blame") => blame(&args[1..]),
+		Some("branch") => branch(&args[1..]),
+		Some("bundle") => bundle(&args[1..]),
+		Some("cat-file") => cat_file(&args[1..]),
+		Some("check-attr") => check_attr(&args[1..]),
+		Some("checkout") => checkout(&args[1..]),
+		Some("cherry") => cherry(&args[1..]),
+		Some("clean") => clean(&args[1..]),
+		Some("clone") => clone(&args[1..]),
+		Some("commit") => commit(&args[1..]),
+		Some("commit-graph") => match &args[1..] {
+			[x] if x == "write" => cmd::commit_graph_write(&open_repo()?),
+			[x, y] if x == "write" && y == "--reachable" => cmd::commit_graph_write(&open_repo()?),
+			_ => Err(io::Error::other("usage: commit-graph write [--reachable]")),
+		},
+		Some("commit-tree") => commit_tree(&args[1..]),
+		Some("config") => config(&args[1..]),
+		Some("diff") => diff(&args[1..]),
+		Some("diff-index") => diff_index(&args[1..]),
+		Some("diff-tree") => diff_tree(&args[1..]),
+		Some("fast-export") => match &args[1..] {
+			[] => cmd::fast_export(&open_repo()?, io::BufWriter::new(io::stdout().lock())),
+			[x] if x == "--all" => cmd::fast_export(&open_repo()?, io::BufWriter::new(io::stdout().lock())),
+			_ => Err(io::Error::other("usage: fast-export [--all]")),
+		},
+		Some("fast-import") => cmd::fast_import(&open_repo()?, io::stdin().lock()),
+		Some("fetch") => fetch(&args[1..]),
+		Some("for-each-ref") => for_each_ref(&args[1..]),
+		Some("format-patch") => format_patch(&args[1..]),
+		Some("fsck") => fsck(&args[1..]),
+		Some("gc") => gc(&args[1..]),
+		Some("grep") => grep(&args[1..]),
+		Some("hash-object") => hash_object(&args[1..]),
+		Some("init") => init(&args[1..]),
+		Some("interpret-trailers") => interpret_trailers(&args[1..]),
+		Some("log") => log(&args[1..]),
+		Some("ls-files") => match &args[1..] {
+			[] => cmd::ls_files(&open_repo()?, false),
+			[stage] if stage == "-s" || stage == "--stage" => cmd::ls_files(&open_repo()?, true),
+			_ => Err(io::Error::other("usage: ls-files [-s | --stage]")),
+		},
+		Some("ls-remote") => match &args[1..] {
+			[remote] => cmd::ls_remote(open_repo().ok().as_ref(), remote),
+			_ => Err(io::Error::other("usage: ls-remote <repository>")),
+		},
+		Some("ls-tree") => match &args[1..] {
+			[rev] if !rev.starts_with('-') => cmd::ls_tree(&open_repo()?, rev, false),
+			[recursive, rev] if recursive == "-r" => cmd::ls_tree(&open_repo()?, rev, true),
+			_ => Err(io::Error::other("usage: ls-tree [-r] <tree-ish>")),
+		},
+		Some("merge") => merge(&args[1..]),
+		Some("merge-file") => merge_file(&args[1..]),
+		Some("mktag") => cmd::mktag(&open_repo()?, io::stdin().lock()),
+		Some("multi-pack-index") => match &args[1..] {
+			[write] if write == "write" => cmd::multi_pack_index_write(&open_repo()?),
+			_ => Err(io::Error::other("usage: multi-pack-index write")),
+		},
+		Some("notes") => notes(&args[1..]),
+		Some("pack-objects") => match &args[1..] {
+			[base] => cmd::pack_objects(
+				&open_repo()?,
+				io::stdin().lock(),
+				Path::new(base),
+				progress::stderr().as_mut(),
+			),
+			_ => Err(io::Error::other("usage: pack-objects <base-name>")),
+		},
+		Some("prune-packed") => match &args[1..] {
+			[] => cmd::prune_packed(&open_repo()?, false),
+			[x] if x == "-n" || x == "--dry-run" => cmd::prune_packed(&open_repo()?, true),
+			_ => Err(io::Error::other("usage: prune-packed [-n | --dry-run]")),
+		},
+		Some("push") => push(&args[1..]),
+		Some("read-tree") => read_tree(&args[1..]),
+		Some("reflog") => reflog(&args[1..]),
+		Some("replace") => match &args[1..] {
+			[f, object, replacement] if f == "-f" || f == "--force" => {
+				cmd::replace(&open_repo()?, object, replacement, true)
+			}
+			[object, replacement] => cmd::replace(&open_repo()?, object, replacement, false),
+			_ => Err(io::Error::other("usage: replace [-f] <object> <replacement>")),
+		},
+		Some("reset") => reset(&args[1..]),
+		Some("rev-list") => rev_list(&args[1..]),
+		Some("rev-parse") => {
+			let verify = args[1..].iter().any(|x| x == "--verify");
+			let specs: Vec<String> = args[1..].iter().filter(|x| *x != "--verify").cloned().collect();
+			cmd::rev_parse(&open_repo()?, &specs, verify)
+		}
+		Some("rm") => rm(&args[1..]),
+		Some("shortlog") => shortlog(&args[1..]),
+		Some("show-branch") => cmd::show_branch(&open_repo()?, &args[1..]),
+		Some("show-ref") => show_ref(&args[1..]),
+		Some("stash") => stash(&args[1..]),
+		Some("status") => status(&args[1..]),
+		Some("switch") => switch(&args[1..]),
+		Some("symbolic-ref") => symbolic_ref(&args[1..]),
+		Some("tag") => tag(&args[1..]),
+		Some("update-ref") => match &args[1..] {
+			[x] if x == "--stdin" => cmd::update_ref_stdin(&open_repo()?, io::stdin().lock()),
+			_ => Err(io::Error::other("usage: update-ref --stdin")),
+		},
+		Some("verify-commit") => match &args[1..] {
+			[] => Err(io::Error::other("usage: verify-commit <commit>...")),
+			revs => {
+				let repo = open_repo()?;
+				revs.iter().try_for_each(|x| cmd::verify_commit(&repo, x))
+			}
+		},
+		Some("verify-tag") => match &args[1..] {
+			[] => Err(io::Error::other("usage: verify-tag <tag>...")),
+			revs => {
+				let repo = open_repo()?;
+				revs.iter().try_for_each(|x| cmd::verify_tag(&repo, x))
+			}
+		},
+		Some("worktree") => match &args[1..] {
+			[sub, path, branch] if sub == "add" => cmd::worktree_add(&open_repo()?, Path::new(path), branch),
+			_ => Err(io::Error::other("usage: worktree add <path> <branch>")),
+		},
+		Some("write-tree") => {
+			let prefix = match &args[1..] {
+				[] => None,
+				[x] => match x.strip_prefix("--prefix=") {
+					Some(prefix) => Some(prefix),
+					None => return Err(io::Error::other("usage: write-tree [--prefix=<prefix>]")),
+				},
+				_ => return Err(io::Error::other("usage: write-tree [--prefix=<prefix>]")),
+			};
+			writeln!(io::stdout().lock(), "{}", hex::encode(cmd::write_tree(&open_repo()?, prefix)?))?;
+			Ok(())
+		}
+		Some(x) => Err(io::Error::other(format!("'{}' is not a toy_git command", x))),
+		None => Err(io::Error::other("usage: toy_git <command> [<args>]")),
+	}
 }
 
+fn open_repo() -> io::Result<Repository> {
+	Repository::discover(&env::current_dir()?)
+}
 
-impl Blob {
-	pub fn new(content: String) -> Self {
-		Self {
-			size: content.len(),
-			content,
+// add [-n | --dry-run] [-f | --force] <path>...
+fn add(args: &[String]) -> io::Result<()> {
+	let mut opts = cmd::AddOptions {
+		dry_run: false,
+		force: false,
+	};
+	let mut paths = Vec::new();
+	for arg in args {
+		match arg.as_str() {
+			"-n" | "--dry-run" => opts.dry_run = true,
+			"-f" | "--force" => opts.force = true,
+			x if x.starts_with('-') => return Err(io::Error::other(format!("unknown option: {}", x))),
+			_ => paths.push(arg.clone()),
 		}
 	}
-	
-	pub fn from(bytes: &[u8]) -> Option<Self> {
-		let content = String::from_utf8(bytes.to_vec());
-	
-		match content {
-			Ok(content) => Some(Self {
-				size: content.len(),
-				content,
-			}),
-			_ => None,
+	// ignoreされたpathを指定していれば1で終わる
+	match cmd::add(&open_repo()?, &paths, &opts)? {
+		true => Ok(()),
+		false => process::exit(1),
+	}
+}
+
+// am [<mbox>...] / am (--continue | --abort)
+fn am(args: &[String]) -> io::Result<()> {
+	match args.first().map(String::as_str) {
+		Some("--continue") => return cmd::am_continue(&open_repo()?),
+		Some("--abort") => return cmd::am_abort(&open_repo()?),
+		Some(x) if x.starts_with('-') && x != "-" => {
+			return Err(io::Error::other(format!("unknown option: {}", x)))
 		}
+		_ => {}
 	}
-	
-	pub fn as_bytes(&self) -> Vec<u8> {
-		// headerとbodyが\0で区切られる
-		let header = format!("blob {}\0", self.size);
-		let store = format!("{}{}", header, self.to_string());
-	
-		Vec::from(store.as_bytes())
+
+	// ファイルが無いか "-" ならstdinから読む
+	let mut mbox = Vec::new();
+	if args.is_empty() {
+		io::stdin().lock().read_to_end(&mut mbox)?;
 	}
-	
-	pub fn calc_hash(&self) -> Vec<u8> {
-		Vec::from(Sha1::digest(&self.as_bytes()).as_slice())
+	for file in args {
+		match file.as_str() {
+			"-" => {
+				io::stdin().lock().read_to_end(&mut mbox)?;
+			}
+			file => mbox.extend(
+				fs::read(file)
+					.map_err(|e| io::Error::new(e.kind(), format!("could not open '{}': {}", file, e)))?,
+			),
+		}
 	}
+	cmd::am(&open_repo()?, &mbox)
 }
 
-pub struct Tree {
-	pub contents: Vec<File>,
+// apply [--check] [-R | --reverse] [--cached | --index] [<patch>...]
+fn apply(args: &[String]) -> io::Result<()> {
+	let mut opts = cmd::ApplyOptions {
+		check: false,
+		reverse: false,
+		cached: false,
+		index: false,
+	};
+	let mut files = Vec::new();
+
+	for arg in args {
+		match arg.as_str() {
+			"--check" => opts.check = true,
+			"-R" | "--reverse" => opts.reverse = true,
+			"--cached" => opts.cached = true,
+			"--index" => opts.index = true,
+			x if x.starts_with('-') && x != "-" => {
+				return Err(io::Error::other(format!("unknown option: {}", x)))
+			}
+			_ => files.push(arg.as_str()),
+		}
+	}
+
+	// ファイルが無いか "-" ならstdinから読む
+	let mut patch = Vec::new();
+	if files.is_empty() {
+		files.push("-");
+	}
+	for file in files {
+		match file {
+			"-" => {
+				io::stdin().lock().read_to_end(&mut patch)?;
+			}
+			file => patch.extend(
+				fs::read(file)
+					.map_err(|e| io::Error::new(e.kind(), format!("can't open patch '{}': {}", file, e)))?,
+			),
+		}
+	}
+	cmd::apply(&open_repo()?, &patch, &opts)
 }
 
-pub struct File {
-	pub mode: usize,
-	pub name: String,
-	pub hash: Vec<u8>,
+// bisect (start [<bad> [<good>...]] | good [<rev>...] | bad [<rev>] | reset)
+fn bisect(args: &[String]) -> io::Result<()> {
+	let repo = open_repo()?;
+
+	match args.split_first() {
+		Some((sub, revs)) if sub == "start" => cmd::bisect_start(&repo, revs),
+		Some((sub, revs)) if sub == "good" => cmd::bisect_good(&repo, revs),
+		Some((sub, [])) if sub == "bad" => cmd::bisect_bad(&repo, None),
+		Some((sub, [rev])) if sub == "bad" => cmd::bisect_bad(&repo, Some(rev)),
+		Some((sub, [])) if sub == "reset" => cmd::bisect_reset(&repo),
+		_ => Err(io::Error::other("usage: bisect (start [<bad> [<good>...]] | good [<rev>...] | bad [<rev>] | reset)")),
+	}
+}
+
+// blame [<rev>] [--] <file>
+fn blame(args: &[String]) -> io::Result<()> {
+	let (rev, path) = match args {
+		[path] if !path.starts_with('-') => (None, path),
+		[dash, path] if dash == "--" => (None, path),
+		[rev, path] if !rev.starts_with('-') => (Some(rev.as_str()), path),
+		[rev, dash, path] if !rev.starts_with('-') && dash == "--" => (Some(rev.as_str()), path),
+		_ => return Err(io::Error::other("usage: blame [<rev>] [--] <file>")),
+	};
+	cmd::blame(&open_repo()?, rev, path)
+}
+
+// branch [--list]
+// branch [-f | --force] <name> [<start>]
+// branch (-d | -D) <name>...
+// branch (--set-upstream-to=<upstream> | -u <upstream>) [<branch>]
+fn branch(args: &[String]) -> io::Result<()> {
+	let usage = || io::Error::other("usage: branch [--list]\n   or: branch [-f | --force] <name> [<start>]\n   or: branch (-d | -D) <name>...\n   or: branch (--set-upstream-to=<upstream> | -u <upstream>) [<branch>]");
+	match args {
+		[] => return cmd::branch_list(&open_repo()?),
+		[x] if x == "--list" || x == "-l" => return cmd::branch_list(&open_repo()?),
+		[x, names @ ..] if ["-d", "-D", "--delete"].contains(&x.as_str()) && !names.is_empty() => {
+			return match cmd::branch_delete(&open_repo()?, names, x == "-D")? {
+				true => Ok(()),
+				false => process::exit(1),
+			}
+		}
+		_ => {}
+	}
+	let (upstream, rest) = match args {
+		[flag, upstream, rest @ ..] if flag == "-u" => (upstream.as_str(), rest),
+		[flag, rest @ ..] if flag.starts_with("--set-upstream-to=") => (&flag["--set-upstream-to=".len()..], rest),
+		_ => {
+			let (force, rest) = match args {
+				[flag, rest @ ..] if flag == "-f" || flag == "--force" => (true, rest),
+				rest => (false, rest),
+			};
+			return match rest {
+				[name] if !name.starts_with('-') => cmd::branch_create(&open_repo()?, name, None, force),
+				[name, start] if !name.starts_with('-') => cmd::branch_create(&open_repo()?, name, Some(start), force),
+				_ => Err(usage()),
+			};
+		}
+	};
+	match rest {
+		[] => cmd::branch_set_upstream(&open_repo()?, None, upstream),
+		[branch] => cmd::branch_set_upstream(&open_repo()?, Some(branch), upstream),
+		_ => Err(usage()),
+	}
 }
 
-impl File {
-	pub fn from(header: &[u8], hash: &[u8]) -> Option<Self> {
-		let split_header = String::from_utf8(header.to_vec()).ok?;
+// bundle create <file> <rev>... / bundle unbundle <file>
+fn bundle(args: &[String]) -> io::Result<()> {
+	let repo = open_repo()?;
 
-		let mut iter = split_header.split_whitespace();
+	match args {
+		[sub, file, revs @ ..] if sub == "create" && !revs.is_empty() => {
+			cmd::bundle_create(&repo, Path::new(file), revs, progress::stderr().as_mut())
+		}
+		[sub, file] if sub == "unbundle" => {
+			cmd::bundle_unbundle(&repo, Path::new(file), progress::stderr().as_mut())
+		}
+		_ => Err(io::Error::other("usage: bundle (create <file> <rev>... | unbundle <file>)")),
+	}
+}
 
-		let mode = iter.next().and_then(|x| x.parse::<usize>().ok())?;
-		let name = iter.next()?;
+// cat-file [--allow-unknown-type] (-t | -s | -p) <object>
+// cat-file -e <object>
+// cat-file (--batch | --batch-check) [--batch-all-objects]
+// cat-file --filters --path=<path> <object>
+fn cat_file(args: &[String]) -> io::Result<()> {
+	let repo = open_repo()?;
+	let usage = || io::Error::other("usage: cat-file [--allow-unknown-type] (-t | -s | -p) <object> / cat-file -e <object> / cat-file --filters --path=<path> <object>");
 
-		Some(Self::new(mode, String::from(name), hash))
+	match args {
+		[x, all] | [all, x] if all == "--batch-all-objects" && (x == "--batch" || x == "--batch-check") => {
+			return cmd::cat_file_batch_all(&repo, x == "--batch")
+		}
+		_ => {}
+	}
+	match args.first().map(String::as_str) {
+		Some("--batch") => return cmd::cat_file_batch(&repo, io::stdin().lock(), true),
+		Some("--batch-check") => return cmd::cat_file_batch(&repo, io::stdin().lock(), false),
+		_ => {}
+	}
+	// gitと同じく、objectが無ければ何も出さずにexit code 1で終わる
+	if let [e, rev] = args {
+		if e == "-e" {
+			match cmd::cat_file_exists(&repo, rev)? {
+				true => return Ok(()),
+				false => process::exit(1),
+			}
+		}
 	}
 
-	pub fn encode(&self) -> Vec<u8> {
-		let header = format!("{} {}\0", self.mode, self.name);
-		[header.as_bytes(), &self.hash].concat()
+	let allow_unknown_type = args.iter().any(|x| x == "--allow-unknown-type");
+	let path = args.iter().find_map(|x| x.strip_prefix("--path=")).map(String::from);
+	let args: Vec<&String> = args
+		.iter()
+		.filter(|x| *x != "--allow-unknown-type" && !x.starts_with("--path="))
+		.collect();
+	let (query, rev) = match args.as_slice() {
+		[flag, rev] => match flag.as_str() {
+			"-t" => (cmd::CatFileQuery::Type, rev),
+			"-s" => (cmd::CatFileQuery::Size, rev),
+			"-p" => (cmd::CatFileQuery::Pretty, rev),
+			"--filters" => match &path {
+				Some(path) => (cmd::CatFileQuery::Filters(path.clone()), rev),
+				None => return Err(io::Error::other("--filters requires --path=<path>")),
+			},
+			_ => return Err(usage()),
+		},
+		_ => return Err(usage()),
+	};
+	if path.is_some() && !matches!(query, cmd::CatFileQuery::Filters(_)) {
+		return Err(io::Error::other("'--path=<path>' needs '--filters'"));
 	}
+	cmd::cat_file(&repo, rev, query, allow_unknown_type)
 }
 
-impl Tree {
-	pub fn from(bytes: &[u8]) -> Option<Self> {
-		let contents: Vec<File> = Vec::new();
-		let mut iter = bytes.split(|&b| b == b'\0'); // entry is splited by '\0'
+// checkout [-f | --force] [--dry-run] <branch | commit>
+// checkout [-f | --force] [--dry-run] (-b | -B) <new-branch> [<start>]
+fn checkout(args: &[String]) -> io::Result<()> {
+	let usage = || io::Error::other("usage: checkout [-f | --force] [--dry-run] <branch | commit>\n   or: checkout [-f | --force] [--dry-run] (-b | -B) <new-branch> [<start>]");
+	let mut opts = cmd::CheckoutOptions { dry_run: false, new_branch: None, reset: false, branch_only: false, force: false };
+	let mut target = None;
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		match arg.as_str() {
+			"--dry-run" => opts.dry_run = true,
+			"-f" | "--force" => opts.force = true,
+			"-b" | "-B" => {
+				opts.reset = arg == "-B";
+				opts.new_branch = Some(args.next().ok_or_else(usage)?.clone());
+			}
+			x if x.starts_with('-') => return Err(io::Error::other(format!("unknown option: {}", x))),
+			x if target.is_none() => target = Some(x),
+			_ => return Err(usage()),
+		}
+	}
+	match (target, &opts.new_branch) {
+		(Some(target), _) => cmd::checkout(&open_repo()?, target, &opts),
+		(None, Some(_)) => cmd::checkout(&open_repo()?, "HEAD", &opts),
+		(None, None) => Err(usage()),
+	}
+}
 
-		let mut header = iter.next()?;
-		let contents = iter.try_fold(contents, |mut acc, x| {
-			let (hash, next_header) = x.split_at(20); // hash value is 20bytes so split 20
-			let file = File::from(header, hash)?;
+// check-attr <attr>... -- <path>...
+// check-attr <attr> <path>...
+fn check_attr(args: &[String]) -> io::Result<()> {
+	let (attrs, paths) = match args.iter().position(|x| x == "--") {
+		Some(i) => (&args[..i], &args[i + 1..]),
+		None if !args.is_empty() => (&args[..1], &args[1..]),
+		None => (args, args),
+	};
+	if attrs.is_empty() || paths.is_empty() {
+		return Err(io::Error::other("usage: check-attr <attr>... [--] <path>..."));
+	}
+	cmd::check_attr(&open_repo()?, attrs, paths)
+}
 
-			acc.push(file);
-			header = next_header;
-			Some(acc)
-		})?;
-		Some(Self { contents })
+// cherry [-v | --verbose] [<upstream> [<head> [<limit>]]]
+fn cherry(args: &[String]) -> io::Result<()> {
+	let verbose = args.iter().any(|x| x == "-v" || x == "--verbose");
+	let revs: Vec<&str> = args.iter().filter(|x| *x != "-v" && *x != "--verbose").map(String::as_str).collect();
+	if revs.len() > 3 {
+		return Err(io::Error::other("usage: cherry [-v] [<upstream> [<head> [<limit>]]]"));
 	}
+	cmd::cherry(&open_repo()?, revs.first().copied(), revs.get(1).copied().unwrap_or("HEAD"), revs.get(2).copied(), verbose)
+}
+
+// -m <msg>を複数回指定すると空行で区切ってつなげる
+fn push_message(message: &mut Option<String>, value: Option<&String>) -> io::Result<()> {
+	let value = value.ok_or_else(|| io::Error::other("switch `m' requires a value"))?;
+	*message = Some(match message.take() {
+		Some(x) => format!("{}\n\n{}", x, value),
+		None => value.clone(),
+	});
+	Ok(())
+}
 
-	pub fn as_bytes(&self) -> Vec<u8> {
-		let content: Vec<u8> = self.contents.iter().flat_map(|x| x.encode()).collect(); // flat_mapにわたる値がiterator(この場合にmapは使えない)
-		let header = format!("tree {}\0", content.len());
+// -S[<keyid>] / --gpg-sign[=<keyid>]
+fn parse_sign(arg: &str) -> Option<String> {
+	arg.strip_prefix("--gpg-sign")
+		.map(|x| x.trim_start_matches('='))
+		.or_else(|| arg.strip_prefix("-S"))
+		.map(String::from)
+}
+
+// clean [-d] [-f | --force] [-n | --dry-run] [-x]
+fn clean(args: &[String]) -> io::Result<()> {
+	let mut opts = cmd::CleanOptions {
+		dirs: false,
+		force: false,
+		dry_run: false,
+		ignored: false,
+	};
 
-		[header.as_bytes(), content.as_slice()].concat()
+	for arg in args {
+		let flags = match arg.as_str() {
+			"--force" => "f",
+			"--dry-run" => "n",
+			x if x.starts_with('-') && !x.starts_with("--") => &x[1..],
+			x => return Err(io::Error::other(format!("unknown option: {}", x))),
+		};
+		// "-fd" のようにまとめて書ける
+		for flag in flags.chars() {
+			match flag {
+				'd' => opts.dirs = true,
+				'f' => opts.force = true,
+				'n' => opts.dry_run = true,
+				'x' => opts.ignored = true,
+				_ => return Err(io::Error::other(format!("unknown switch `{}'", flag))),
+			}
+		}
 	}
+	cmd::clean(&open_repo()?, &opts)
 }
 
-pub struct User {
-	pub name: String,
-	pub email: String,
-	pub ts: DateTime<FixedOffset>,
+// clone [--bare] [-b <branch>] <repository> [<directory>]
+fn clone(args: &[String]) -> io::Result<()> {
+	let usage = || io::Error::other("usage: clone [--bare] [-b <branch>] <repository> [<directory>]");
+	let mut branch = None;
+	let mut bare = false;
+	let mut rest = Vec::new();
+	let mut iter = args.iter();
+	while let Some(arg) = iter.next() {
+		match arg.as_str() {
+			"--bare" => bare = true,
+			"-b" | "--branch" => branch = Some(iter.next().ok_or_else(|| io::Error::other(format!("option '{}' requires a value", arg)))?.as_str()),
+			x if x.starts_with("--branch=") => branch = Some(&x["--branch=".len()..]),
+			x if x.starts_with('-') => return Err(io::Error::other(format!("unknown option: {}", x))),
+			_ => rest.push(arg.as_str()),
+		}
+	}
+	match rest.as_slice() {
+		[url] => cmd::clone(url, None, branch, bare, progress::stderr().as_mut()),
+		[url, dir] => cmd::clone(url, Some(Path::new(dir)), branch, bare, progress::stderr().as_mut()),
+		_ => Err(usage()),
+	}
 }
 
-pub struct Commit {
-	pub tree: String,
-	pub parent: Option<String>, // 最初のコミットにはparentが存在しないからOption
-	pub author: User,
-	pub committer: User,
-	pub message: String,
+// commit [-m <msg>] [--amend] [--allow-empty] [-n | --no-verify] [--dry-run] [-s | --signoff] [-S[<keyid>]]
+fn commit(args: &[String]) -> io::Result<()> {
+	let mut message = None;
+	let mut sign = None;
+	let mut opts = cmd::CommitOptions {
+		amend: false,
+		allow_empty: false,
+		no_verify: false,
+		dry_run: false,
+		signoff: false,
+	};
+
+	let mut iter = args.iter();
+	while let Some(arg) = iter.next() {
+		match arg.as_str() {
+			"-m" => push_message(&mut message, iter.next())?,
+			"-s" | "--signoff" => opts.signoff = true,
+			"--amend" => opts.amend = true,
+			"--allow-empty" => opts.allow_empty = true,
+			"-n" | "--no-verify" => opts.no_verify = true,
+			"--dry-run" => opts.dry_run = true,
+			x => match parse_sign(x) {
+				Some(key) => sign = Some(key),
+				None => return Err(io::Error::other(format!("unknown option: {}", x))),
+			},
+		}
+	}
+
+	cmd::commit(&open_repo()?, message.as_deref(), sign.as_deref(), &opts)
 }
 
-impl Commit {
-	pub fn from(bytes: &[u8]) -> Option<Self> {
-		// commitメッセージとの間に空行があるからfilterにかける
-		let mut iter = bytes.split(|&x| x == b'\n').filter(|x| x != b"");
+// commit-tree <tree> [-p <parent>]... -m <msg> [-S[<keyid>]]
+fn commit_tree(args: &[String]) -> io::Result<()> {
+	let mut tree = None;
+	let mut parents = Vec::new();
+	let mut message = None;
+	let mut sign = None;
 
-		let tree = iter
-			.next()
-			.map(|x| {
-				x.splitn(2, |&x| x == b' ')
-					.skip(1) // 最初の要素はtreeで決まっているからスキップする
-					.flatten()
-					.map(|&x| x)
-					.collect::<Vec<_>>()
-			})
-			.and_then(|x| String::from_utf8(x).ok())?;
+	let mut iter = args.iter();
+	while let Some(arg) = iter.next() {
+		match arg.as_str() {
+			"-m" => push_message(&mut message, iter.next())?,
+			"-p" => parents.push(
+				iter.next()
+					.cloned()
+					.ok_or_else(|| io::Error::other("option '-p' requires a value"))?,
+			),
+			x => match parse_sign(x) {
+				Some(key) => sign = Some(key),
+				None => tree = Some(x),
+			},
+		}
+	}
+
+	let tree = tree.ok_or_else(|| io::Error::other("must give exactly one tree"))?;
+	let message = message.ok_or_else(|| io::Error::other("no commit message given (use -m)"))?;
+	let hash = cmd::commit_tree(&open_repo()?, tree, parents, &message, sign.as_deref())?;
+	writeln!(io::stdout().lock(), "{}", hex::encode(hash))?;
+	Ok(())
+}
+
+// hash-object [-w] [-t <type>] [--literally] [--stdin] [<file>...]
+fn hash_object(args: &[String]) -> io::Result<()> {
+	let mut write = false;
+	let mut stdin = false;
+	let mut literally = false;
+	let mut kind = "blob";
+	let mut paths = Vec::new();
+
+	let mut iter = args.iter();
+	while let Some(arg) = iter.next() {
+		match arg.as_str() {
+			"-w" => write = true,
+			"--stdin" => stdin = true,
+			"--literally" => literally = true,
+			"-t" => kind = iter.next().ok_or_else(|| io::Error::other("option '-t' requires a value"))?,
+			_ => paths.push(arg.clone()),
+		}
+	}
+
+	// -wでなければrepositoryの外でもよい(その場合はSHA-1)
+	let repo = if write { Some(open_repo()?) } else { open_repo().ok() };
+	cmd::hash_object(repo.as_ref(), write, kind, literally, stdin, &paths)
+}
+
+// init [-q | --quiet] [--bare] [--template=<dir>] [-b <branch> | --initial-branch=<branch>] [--object-format=<format>] [<directory>]
+fn init(args: &[String]) -> io::Result<()> {
+	let mut opts = cmd::InitOptions {
+		bare: false,
+		template: None,
+		initial_branch: None,
+		quiet: false,
+		object_format: None,
+	};
+	let algo = |x: &str| hash::HashAlgorithm::from_name(x).ok_or_else(|| io::Error::other(format!("unknown hash algorithm '{}'", x)));
+	let mut dir = None;
+
+	let mut iter = args.iter();
+	while let Some(arg) = iter.next() {
+		match arg.as_str() {
+			"-q" | "--quiet" => opts.quiet = true,
+			"--bare" => opts.bare = true,
+			"--template" => {
+				let template = iter.next().ok_or_else(|| io::Error::other("option '--template' requires a value"))?;
+				opts.template = Some(template.clone());
+			}
+			x if x.starts_with("--template=") => opts.template = Some(String::from(&x["--template=".len()..])),
+			"-b" | "--initial-branch" => {
+				let branch = iter.next().ok_or_else(|| io::Error::other(format!("option '{}' requires a value", arg)))?;
+				opts.initial_branch = Some(branch.clone());
+			}
+			x if x.starts_with("--initial-branch=") => {
+				opts.initial_branch = Some(String::from(&x["--initial-branch=".len()..]))
+			}
+			"--object-format" => {
+				let format = iter.next().ok_or_else(|| io::Error::other("option '--object-format' requires a value"))?;
+				opts.object_format = Some(algo(format)?);
+			}
+			x if x.starts_with("--object-format=") => opts.object_format = Some(algo(&x["--object-format=".len()..])?),
+			x if x.starts_with('-') => return Err(io::Error::other(format!("unknown option: {}", x))),
+			_ if dir.is_some() => return Err(io::Error::other("usage: init [--bare] [--template=<dir>] [-b <branch> | --initial-branch=<branch>] [<directory>]")),
+			_ => dir = Some(arg.as_str()),
+		}
+	}
+
+	cmd::init(Path::new(dir.unwrap_or(".")), &opts)
+}
 
-		let parent = &iter
-			.next()
-			.map(|x| {
-				x.splitn(2, |&x| x == b' ')
-					.map(Vec::from)
-					.map(|x| String::from_utf8(x).ok().unwrap_or_default())
-					.collect::<Vec<_>>()
-			})
-			.ok_or(Vec::new())
-			.and_then(|x| match x[0].as_str() {
-				"parent" => Ok(x[1].clone()), // 最初の文字列がparentなら
-				_ => Err(|[x[0]].as_bytes(), b" ", x[1].asbytes()].concat()), // そうでなければ元の形に戻してErrに包む
-			});
+// interpret-trailers [--trailer <token>[(=|:)<value>]]... [<file>...]
+// fileが無ければ標準入力のmessageを読む
+fn interpret_trailers(args: &[String]) -> io::Result<()> {
+	let mut trailers = Vec::new();
+	let mut paths = Vec::new();
 
-		let author = match parent {
-			Ok(_) => iter.next().map(|x| Vec::from(x)), // parentがOkならiteratorからとる
-			Err(v) => Some(v.clone()), // Errならその値を使う
+	let mut iter = args.iter();
+	while let Some(arg) = iter.next() {
+		let trailer = match arg.as_str() {
+			"--trailer" => iter.next().ok_or_else(|| io::Error::other("option '--trailer' requires a value"))?,
+			x if x.starts_with("--trailer=") => &x["--trailer=".len()..],
+			x if x.starts_with('-') => return Err(io::Error::other(format!("unknown option: {}", x))),
+			x => {
+				paths.push(x);
+				continue;
+			}
+		};
+		let (token, value) = trailer.split_once(['=', ':']).unwrap_or((trailer, ""));
+		if token.trim().is_empty() {
+			return Err(io::Error::other(format!("empty trailer token in trailer '{}'", trailer)));
 		}
-		.map(|x| {
-			x.splitn(2, |&x| x == b' ')
-				.skip(1)
-				.flatten()
-				.map(|&x| x)
-				.collect::<Vec<_>>()
+		trailers.push((String::from(token.trim()), String::from(value.trim())));
+	}
+
+	let mut out = io::stdout().lock();
+	if paths.is_empty() {
+		write!(out, "{}", cmd::interpret_trailers(&io::read_to_string(io::stdin())?, &trailers))?;
+	}
+	for path in paths {
+		write!(out, "{}", cmd::interpret_trailers(&fs::read_to_string(path)?, &trailers))?;
+	}
+	Ok(())
+}
+
+// config [--global | --system | --local] [--get] <name>
+// config [--global | --system | --local] <name> <value>
+// config [--global | --system | --local] --unset <name>
+fn config(args: &[String]) -> io::Result<()> {
+	let usage = || io::Error::other("usage: config [--global | --system | --local] [--get | --unset] <name> [<value>]");
+	// scopeは他のoptionの前後どちらにも書ける
+	let is_scope = |x: &String| matches!(x.as_str(), "--global" | "--system" | "--local");
+	let scope = args.iter().find(|x| is_scope(x)).map(|x| x.as_str());
+	let args: Vec<String> = args.iter().filter(|x| !is_scope(x)).cloned().collect();
+	let (key, action) = match args.as_slice() {
+		[flag, key] if flag == "--get" => (key, cmd::ConfigAction::Get),
+		[flag, key] if flag == "--unset" => (key, cmd::ConfigAction::Unset),
+		[key] if !key.starts_with('-') => (key, cmd::ConfigAction::Get),
+		[key, value] if !key.starts_with('-') => (key, cmd::ConfigAction::Set(value.clone())),
+		_ => return Err(usage()),
+	};
+	let repo;
+	let scope = match scope {
+		Some("--global") => cmd::ConfigScope::Global,
+		Some("--system") => cmd::ConfigScope::System,
+		Some(_) => {
+			repo = open_repo()?;
+			cmd::ConfigScope::Local(&repo)
+		}
+		None => {
+			repo = open_repo()?;
+			cmd::ConfigScope::Default(&repo)
+		}
+	};
+	// gitと同じく、keyが無ければGetは1、Unsetは5で終わる
+	match (cmd::config(&scope, key, &action)?, action) {
+		(true, _) => Ok(()),
+		(false, cmd::ConfigAction::Unset) => process::exit(5),
+		(false, _) => process::exit(1),
+	}
+}
+
+// diff [-R] [--cached] [--name-only | --name-status] [--color[=<when>] | --color-words | --word-diff=porcelain]
+//     [-U<n>] [-M[<n>] | -C[<n>] | --no-renames] [<commit> [<commit>]]
+// diff [-R] [--color[=<when>] | --color-words | --word-diff=porcelain] [-U<n>] --no-index <path> <path>
+fn diff(args: &[String]) -> io::Result<()> {
+	let mut cached = false;
+	let mut no_index = false;
+	let mut opts = cmd::DiffOptions {
+		format: cmd::DiffFormat::Patch,
+		reverse: false,
+		color: cmd::DiffColor::Never,
+		context: diff::CONTEXT,
+		renames: Some(diff::Renames::default()),
+	};
+	let mut revs = Vec::new();
+
+	for arg in args {
+		let score = arg
+			.strip_prefix("--find-renames")
+			.or_else(|| arg.strip_prefix("--find-copies"))
+			.map(|x| x.strip_prefix('=').unwrap_or(x))
+			.or_else(|| arg.strip_prefix("-M"))
+			.or_else(|| arg.strip_prefix("-C"));
+		if let Some(score) = score {
+			let score = diff::parse_rename_score(score).ok_or_else(|| io::Error::other(format!("invalid argument to {}", arg)))?;
+			let copies = arg.starts_with("-C") || arg.starts_with("--find-copies");
+			// -Cを指定したら、後の-Mでcopyの検出はやめない
+			let copies = copies || opts.renames.as_ref().is_some_and(|x| x.copies);
+			opts.renames = Some(diff::Renames { score, copies });
+			continue;
+		}
+		if let Some(n) = arg.strip_prefix("--unified=").or_else(|| arg.strip_prefix("-U")) {
+			opts.context = n.parse().map_err(|_| io::Error::other(format!("invalid argument to {}", arg)))?;
+			continue;
+		}
+		match arg.as_str() {
+			"--cached" | "--staged" => cached = true,
+			"--no-renames" => opts.renames = None,
+			"-R" => opts.reverse = true,
+			"--no-index" => no_index = true,
+			"--name-only" => opts.format = cmd::DiffFormat::NameOnly,
+			"--name-status" => opts.format = cmd::DiffFormat::NameStatus,
+			"--color" | "--color=always" => opts.color = cmd::DiffColor::Lines,
+			"--color=never" | "--no-color" => opts.color = cmd::DiffColor::Never,
+			"--color=auto" if diff::color_auto() => opts.color = cmd::DiffColor::Lines,
+			"--color=auto" => opts.color = cmd::DiffColor::Never,
+			"--color-words" => opts.color = cmd::DiffColor::Words,
+			"--word-diff=porcelain" => opts.color = cmd::DiffColor::Porcelain,
+			x if x.starts_with("--color=") => return Err(io::Error::other("option `color' expects \"always\", \"auto\", or \"never\"")),
+			x => revs.push(x),
+		}
+	}
+
+	if no_index {
+		return match revs.as_slice() {
+			[a, b] => cmd::diff_no_index(Path::new(a), Path::new(b), &opts),
+			_ => Err(io::Error::other("usage: diff --no-index <path> <path>")),
+		};
+	}
+
+	let repo = open_repo()?;
+	let tree = |rev: &str| -> io::Result<cmd::DiffTarget> {
+		let hash = revparse::resolve(&repo, rev)?;
+		Ok(cmd::DiffTarget::Tree(revparse::peel(&repo, hash, Some(ObjectKind::Tree))?.0))
+	};
+
+	let (old, new) = match (cached, revs.as_slice()) {
+		(false, []) => (cmd::DiffTarget::Index, cmd::DiffTarget::Worktree),
+		(true, []) => match refs::resolve(&repo, "HEAD")? {
+			Some(_) => (tree("HEAD")?, cmd::DiffTarget::Index),
+			None => (cmd::DiffTarget::Empty, cmd::DiffTarget::Index),
+		},
+		(true, [rev]) => (tree(rev)?, cmd::DiffTarget::Index),
+		(false, [rev]) => (tree(rev)?, cmd::DiffTarget::Worktree),
+		(false, [a, b]) => (tree(a)?, tree(b)?),
+		_ => return Err(io::Error::other("usage: diff [--cached] [<commit> [<commit>]]")),
+	};
+
+	cmd::diff(&repo, old, new, &opts)
+}
+
+// diff-index [--cached] <tree-ish>
+fn diff_index(args: &[String]) -> io::Result<()> {
+	let cached = args.iter().any(|x| x == "--cached");
+	let trees: Vec<&String> = args.iter().filter(|x| *x != "--cached").collect();
+
+	match trees.as_slice() {
+		[tree] => {
+			let repo = open_repo()?;
+			let hash = revparse::resolve(&repo, tree)?;
+			cmd::diff_index(&repo, &revparse::peel(&repo, hash, Some(ObjectKind::Tree))?, cached)
+		}
+		_ => Err(io::Error::other("usage: diff-index [--cached] <tree-ish>")),
+	}
+}
+
+// diff-tree [-r] <tree-ish> <tree-ish>
+fn diff_tree(args: &[String]) -> io::Result<()> {
+	let recursive = args.iter().any(|x| x == "-r");
+	let trees: Vec<&String> = args.iter().filter(|x| *x != "-r").collect();
+
+	let repo = open_repo()?;
+	let tree = |rev: &str| -> io::Result<hash::Hash> {
+		let hash = revparse::resolve(&repo, rev)?;
+		revparse::peel(&repo, hash, Some(ObjectKind::Tree))
+	};
+
+	match trees.as_slice() {
+		[a, b] => cmd::diff_tree(&repo, &tree(a)?, &tree(b)?, recursive),
+		_ => Err(io::Error::other("usage: diff-tree [-r] <tree-ish> <tree-ish>")),
+	}
+}
+
+// fetch [--prune] [<remote>]
+fn fetch(args: &[String]) -> io::Result<()> {
+	let prune = args.iter().any(|x| x == "--prune" || x == "-p");
+	let remotes: Vec<&String> = args.iter().filter(|x| !x.starts_with('-')).collect();
+
+	match remotes.as_slice() {
+		[] => cmd::fetch(&open_repo()?, "origin", prune, progress::stderr().as_mut()),
+		[remote] => cmd::fetch(&open_repo()?, remote, prune, progress::stderr().as_mut()),
+		_ => Err(io::Error::other("usage: fetch [--prune] [<remote>]")),
+	}
+}
+
+// for-each-ref [--format=<format>] [<pattern>]
+fn for_each_ref(args: &[String]) -> io::Result<()> {
+	let mut format = "%(objectname) %(objecttype)\t%(refname)";
+	let mut pattern = None;
+
+	for arg in args {
+		match arg.strip_prefix("--format=") {
+			Some(x) => format = x,
+			None => pattern = Some(arg.as_str()),
+		}
+	}
+
+	cmd::for_each_ref(&open_repo()?, pattern, format)
+}
+
+// format-patch [-o <dir>] [--stdout] (<since> | <since>..<until>)
+fn format_patch(args: &[String]) -> io::Result<()> {
+	let usage = || io::Error::other("usage: format-patch [-o <dir>] [--stdout] (<since> | <since>..<until>)");
+	let mut opts = cmd::FormatPatchOptions {
+		output_dir: None,
+		stdout: false,
+	};
+	let mut ranges = Vec::new();
+	let mut iter = args.iter();
+	while let Some(arg) = iter.next() {
+		match arg.as_str() {
+			"-o" | "--output-directory" => opts.output_dir = Some(PathBuf::from(iter.next().ok_or_else(usage)?)),
+			"--stdout" => opts.stdout = true,
+			x if x.starts_with('-') => return Err(io::Error::other(format!("unknown option: {}", x))),
+			_ => ranges.push(arg),
+		}
+	}
+
+	match ranges.as_slice() {
+		[range] => cmd::format_patch(&open_repo()?, range, &opts),
+		_ => Err(usage()),
+	}
+}
+
+// fsck [--unreachable] [--[no-]dangling]
+fn fsck(args: &[String]) -> io::Result<()> {
+	let mut opts = cmd::FsckOptions { unreachable: false, dangling: true };
+	for arg in args {
+		match arg.as_str() {
+			"--unreachable" => opts.unreachable = true,
+			"--dangling" => opts.dangling = true,
+			"--no-dangling" => opts.dangling = false,
+			x => return Err(io::Error::other(format!("unknown option: {}", x))),
+		}
+	}
+	match cmd::fsck(&open_repo()?, &opts, progress::stderr().as_mut())? {
+		0 => Ok(()),
+		status => process::exit(status),
+	}
+}
+
+// gc [--auto] [--force] [--prune=<date> | --no-prune]
+fn gc(args: &[String]) -> io::Result<()> {
+	let mut opts = cmd::GcOptions {
+		auto: false,
+		force: false,
+		prune: None,
+	};
+	for arg in args {
+		match arg.as_str() {
+			"--auto" => opts.auto = true,
+			"--force" => opts.force = true,
+			"--prune" => opts.prune = None,
+			"--no-prune" => opts.prune = Some(String::from("never")),
+			x => match x.strip_prefix("--prune=") {
+				Some(date) => opts.prune = Some(String::from(date)),
+				None => return Err(io::Error::other("usage: gc [--auto] [--force] [--prune=<date> | --no-prune]")),
+			},
+		}
+	}
+	cmd::gc(&open_repo()?, &opts, progress::stderr().as_mut())
+}
+
+// grep [-i] [-l] [-E] [-n] [--cached] <pattern> [<commit>]
+fn grep(args: &[String]) -> io::Result<()> {
+	let mut opts = cmd::GrepOptions {
+		ignore_case: false,
+		files_with_matches: false,
+		extended: false,
+		cached: false,
+	};
+	let mut rest = Vec::new();
+
+	for arg in args {
+		match arg.as_str() {
+			"-i" | "--ignore-case" => opts.ignore_case = true,
+			"-l" | "--files-with-matches" => opts.files_with_matches = true,
+			"-E" | "--extended-regexp" => opts.extended = true,
+			"-G" | "--basic-regexp" => opts.extended = false,
+			"-n" | "--line-number" => {} // 行番号は常に出す
+			"--cached" => opts.cached = true,
+			_ => rest.push(arg.as_str()),
+		}
+	}
+
+	match rest.as_slice() {
+		[pattern] => cmd::grep(&open_repo()?, pattern, None, &opts),
+		[pattern, rev] => cmd::grep(&open_repo()?, pattern, Some(rev), &opts),
+		_ => Err(io::Error::other("usage: grep [-i] [-l] [-E] [--cached] <pattern> [<commit>]")),
+	}
+}
+
+// log [--oneline | --pretty=<format> | --format=<format>] [--graph] [-p] [--follow] [--[no-]notes]
+//     [--date-order | --topo-order] [-<n> | -n <n> | --max-count=<n>] [--author=<pattern>] [--since=<date>] [--until=<date>]
+//     [<commit>...] [[--] <path>...]
+fn log(args: &[String]) -> io::Result<()> {
+	let mut opts = cmd::LogOptions {
+		format: pretty::Format::Medium,
+		graph: false,
+		author: None,
+		since: None,
+		until: None,
+		patch: false,
+		paths: Vec::new(),
+		follow: false,
+		notes: false,
+		order: cmd::RevListOrder::Walk,
+		max_count: None,
+	};
+	let repo = open_repo()?;
+	let mut revs = Vec::new();
+	let mut paths = Vec::new();
+	let mut notes = None;
+	let mut count_next = false; // "-n <n>" の<n>を待っている
+
+	let parse_date = |x: &str| {
+		date::parse(x).ok_or_else(|| io::Error::other(format!("invalid date format: {}", x)))
+	};
+	let parse_count = |x: &str| {
+		x.parse::<usize>().map_err(|_| io::Error::other(format!("switch `n' expects a numerical value: {}", x)))
+	};
+	for (i, arg) in args.iter().enumerate() {
+		if count_next {
+			opts.max_count = Some(parse_count(arg)?);
+			count_next = false;
+			continue;
+		}
+		match arg.split_once('=') {
+			_ if arg == "--" => {
+				paths.extend(args[i + 1..].iter().cloned());
+				break;
+			}
+			Some(("--pretty" | "--format", name)) => {
+				opts.format = pretty::Format::from(name).ok_or_else(|| {
+					io::Error::other(format!("invalid --pretty format: {}", name))
+				})?
+			}
+			Some(("--author", x)) => opts.author = Some(String::from(x)),
+			Some(("--max-count", x)) => opts.max_count = Some(parse_count(x)?),
+			Some(("--since" | "--after", x)) => opts.since = Some(parse_date(x)?),
+			Some(("--until" | "--before", x)) => opts.until = Some(parse_date(x)?),
+			_ => match arg.as_str() {
+				"--graph" => opts.graph = true,
+				"-p" | "-u" | "--patch" => opts.patch = true,
+				"--follow" => opts.follow = true,
+				"--notes" => notes = Some(true),
+				"--date-order" => opts.order = cmd::RevListOrder::Date,
+				"--topo-order" => opts.order = cmd::RevListOrder::Topo,
+				"--no-notes" => notes = Some(false),
+				"-n" => count_next = true,
+				x if x.starts_with("-n") => opts.max_count = Some(parse_count(&x[2..])?),
+				x if x.len() > 1 && x.starts_with('-') && x[1..].bytes().all(|b| b.is_ascii_digit()) => opts.max_count = Some(parse_count(&x[1..])?),
+				// --onelineは短いhashを使う
+				"--oneline" => {
+					opts.format = pretty::Format::Custom {
+						template: String::from("%h %s"),
+						terminator: true,
+					}
+				}
+				// revisionとして読めず、ファイルとしてあればpathとして扱う
+				x if !x.starts_with('-') && revparse::resolve(&repo, x).is_err() && Path::new(x).exists() => paths.push(arg.clone()),
+				_ => revs.push(arg.clone()),
+			},
+		}
+	}
+	if count_next {
+		return Err(io::Error::other("switch `n' requires a value"));
+	}
+	opts.paths = paths
+		.iter()
+		.map(|x| repo.relative_path(Path::new(x)))
+		.collect::<io::Result<_>>()?;
+	// gitと同じく、形式を指定しなければnoteも出す
+	opts.notes = notes.unwrap_or(matches!(opts.format, pretty::Format::Medium));
+
+	cmd::log(&repo, &revs, &opts)
+}
+
+// merge [--no-ff] [--dry-run] [-s <strategy>] <commit> / merge --abort
+fn merge(args: &[String]) -> io::Result<()> {
+	let usage = || io::Error::other("usage: merge [--no-ff] [--dry-run] [-s <strategy>] <commit> / merge --abort");
+	if let [x] = args {
+		if x == "--abort" {
+			return cmd::merge_abort(&open_repo()?);
+		}
+	}
+
+	let mut opts = cmd::MergeOptions {
+		no_ff: false,
+		strategy: cmd::MergeStrategy::Ort,
+		dry_run: false,
+	};
+	let mut revs = Vec::new();
+	let mut iter = args.iter();
+	while let Some(arg) = iter.next() {
+		let strategy = match arg.as_str() {
+			"--no-ff" => {
+				opts.no_ff = true;
+				continue;
+			}
+			"--dry-run" => {
+				opts.dry_run = true;
+				continue;
+			}
+			"-s" | "--strategy" => iter.next().ok_or_else(usage)?.as_str(),
+			x => match x.strip_prefix("--strategy=") {
+				Some(strategy) => strategy,
+				None if x.starts_with('-') => return Err(io::Error::other(format!("unknown option: {}", x))),
+				None => {
+					revs.push(arg);
+					continue;
+				}
+			},
+		};
+		opts.strategy = match strategy {
+			"ort" => cmd::MergeStrategy::Ort,
+			"ours" => cmd::MergeStrategy::Ours,
+			x => {
+				return Err(io::Error::other(format!(
+					"Could not find merge strategy '{}'.\nAvailable strategies are: ort ours.",
+					x
+				)))
+			}
+		};
+	}
+
+	match revs.as_slice() {
+		[rev] => match cmd::merge(&open_repo()?, rev, &opts)? {
+			true => Ok(()),
+			false => process::exit(1),
+		},
+		_ => Err(usage()),
+	}
+}
+
+// merge-file [-L <label> [-L <label> [-L <label>]]] [-p | --stdout] <current> <base> <other>
+// gitと同じく、conflictの数(127まで)をexit codeにする
+fn merge_file(args: &[String]) -> io::Result<()> {
+	let usage = || io::Error::other("usage: merge-file [-L <label>]... [-p | --stdout] <current> <base> <other>");
+	let mut opts = cmd::MergeFileOptions {
+		labels: Vec::new(),
+		stdout: false,
+	};
+	let mut paths = Vec::new();
+
+	let mut iter = args.iter();
+	while let Some(arg) = iter.next() {
+		match arg.as_str() {
+			"-L" if opts.labels.len() < 3 => opts.labels.push(iter.next().ok_or_else(usage)?.clone()),
+			"-L" => return Err(io::Error::other("too many labels on the command line")),
+			"-p" | "--stdout" => opts.stdout = true,
+			x if x.starts_with('-') => return Err(io::Error::other(format!("unknown option: {}", x))),
+			x => paths.push(Path::new(x)),
+		}
+	}
+
+	match paths.as_slice() {
+		[ours, base, theirs] => match cmd::merge_file(ours, base, theirs, &opts)? {
+			0 => Ok(()),
+			n => process::exit(n.min(127) as i32),
+		},
+		_ => Err(usage()),
+	}
+}
+
+// notes add [-f] -m <msg> [<object>] / notes show [<object>]
+fn notes(args: &[String]) -> io::Result<()> {
+	let usage = || io::Error::other("usage: notes (add [-f] -m <msg> [<object>] | show [<object>])");
+	let Some((sub, args)) = args.split_first() else {
+		return Err(usage());
+	};
+
+	let mut message = None;
+	let mut force = false;
+	let mut objects = Vec::new();
+	let mut iter = args.iter();
+	while let Some(arg) = iter.next() {
+		match arg.as_str() {
+			"-m" if sub == "add" => push_message(&mut message, iter.next())?,
+			"-f" | "--force" if sub == "add" => force = true,
+			x if x.starts_with('-') => return Err(io::Error::other(format!("unknown option: {}", x))),
+			_ => objects.push(arg.as_str()),
+		}
+	}
+	let object = match objects.as_slice() {
+		[] => "HEAD",
+		[x] => x,
+		_ => return Err(io::Error::other("too many arguments")),
+	};
+
+	let repo = open_repo()?;
+	match (sub.as_str(), message) {
+		("add", Some(message)) => cmd::notes_add(&repo, object, &message, force),
+		("show", _) => cmd::notes_show(&repo, object),
+		_ => Err(usage()),
+	}
+}
+
+// read-tree [-m] <tree-ish>...
+fn read_tree(args: &[String]) -> io::Result<()> {
+	let merge = args.iter().any(|x| x == "-m");
+	let repo = open_repo()?;
+
+	let trees = args
+		.iter()
+		.filter(|x| *x != "-m")
+		.map(|rev| {
+			let hash = revparse::resolve(&repo, rev)?;
+			revparse::peel(&repo, hash, Some(ObjectKind::Tree))
 		})
-		.and_then(|x| User::from(x.as_slice()))?;
-
-		let commiter = iter
-			.next()
-			.map(|x| {
-				x.splitn(2, |&x| x == b' ')
-					.skip(1)
-					.flatten()
-					.map(|&x| x)
-					.collect::<Vec<_>>()
-			})
-			.and_then(|x| User::from(x.as_slice()))?;
-
-		let message = iter
-			.next()
-			.map(Vec::from)
-			.and_then(|x| String::from_utf8(x).ok())?;
-
-		Some(Self::new(
-			tree,
-			parent.clone().ok(),
-			author,
-			committer,
-			message,
-		))
-	}
-}
-
-impl User{
-	pub fn from(bytes: &[u8]) -> Option<Self> {
-		let name = String::from_utf8(
-			bytes
-				.into_iter()
-				.take_while(|&&x| x != b'<')
-				.map(|&x| x)
-				.collect(),
-		}
-		.map(|x| String::from(x.trim())) // 最後の空白をtrimする
-		.ok()?;
-
-		let info = String::from_utf8(
-			bytes
-				.into_iter()
-				.skip_while(|&&x| x != b'<') // 関数がtrueの間要素を捨てる
-				.map(|&x| x)
-				.collect(),
-		)
-		.ok()?;
-
-		let mut info_iter = info.splitn(3, " ");
-
-		let email = info_iter
-			.next()
-			.map(|x| String::from(x.trim_matches(|x| x == '<' || x == '>')))?;
-
-		// and_then return None if option is None, otherwise calls f
-		let ts = Utc.timestamp(infow_iter.next().and_then(|x| x.parse::<i64>().ok())?, 0);
-		let offset = info_iter
-			.next()
-			.and_then(|x| x.parse::<i32>().ok())
-			.map(|x| {
-				if x < 0 {
-					FixedOffset::west(x / 100 * 60 * 60)
-				} else {
-					FixedOffset::east(x / 100 * 60 * 60)
+		.collect::<io::Result<Vec<_>>>()?;
+	cmd::read_tree(&repo, &trees, merge)
+}
+
+// reflog [show] [<ref>]
+// reflog expire [--expire=<time>] [--expire-unreachable=<time>] <ref>...
+// gitと同じく、expireは指定が無ければ90日前と30日前より古いものを消す
+fn reflog(args: &[String]) -> io::Result<()> {
+	let usage = || io::Error::other("usage: reflog [show] [<ref>] / reflog expire [<options>] <ref>...");
+	let args = match args {
+		[] => return cmd::reflog_show(&open_repo()?, "HEAD"),
+		[x, rest @ ..] if x == "expire" => rest,
+		[x] if x == "show" => return cmd::reflog_show(&open_repo()?, "HEAD"),
+		[x, name] if x == "show" => return cmd::reflog_show(&open_repo()?, name),
+		[name] if !name.starts_with('-') => return cmd::reflog_show(&open_repo()?, name),
+		_ => return Err(usage()),
+	};
+
+	let time = |x: &str| {
+		date::parse_expiry(x).ok_or_else(|| io::Error::other(format!("invalid date format: {}", x)))
+	};
+	let mut expire = time("90.days.ago")?;
+	let mut expire_unreachable = time("30.days.ago")?;
+	let mut refs = Vec::new();
+	for arg in args {
+		if let Some(x) = arg.strip_prefix("--expire=") {
+			expire = time(x)?;
+		} else if let Some(x) = arg.strip_prefix("--expire-unreachable=") {
+			expire_unreachable = time(x)?;
+		} else {
+			refs.push(arg);
+		}
+	}
+	if refs.is_empty() {
+		return Err(usage());
+	}
+
+	let repo = open_repo()?;
+	for name in refs {
+		cmd::reflog_expire(&repo, name, expire, expire_unreachable)?;
+	}
+	Ok(())
+}
+
+// push [-f | --force] [<remote> [[+]<src>[:<dst>]...]]
+// push (-d | --delete) <remote> <ref>...
+fn push(args: &[String]) -> io::Result<()> {
+	let mut force = false;
+	let mut delete = false;
+	let mut rest = Vec::new();
+	for arg in args {
+		match arg.as_str() {
+			"-f" | "--force" => force = true,
+			"-d" | "--delete" => delete = true,
+			x if x.starts_with('-') => return Err(io::Error::other(format!("unknown option: {}", x))),
+			_ => rest.push(arg.clone()),
+		}
+	}
+	let ok = match rest.as_slice() {
+		[remote, names @ ..] if delete && !names.is_empty() => cmd::push_delete(&open_repo()?, remote, names, progress::stderr().as_mut())?,
+		_ if delete => return Err(io::Error::other("--delete doesn't make sense without any refs")),
+		[] => cmd::push(&open_repo()?, None, &[], force, progress::stderr().as_mut())?,
+		[remote, refspecs @ ..] => cmd::push(&open_repo()?, Some(remote), refspecs, force, progress::stderr().as_mut())?,
+	};
+	match ok {
+		true => Ok(()),
+		false => process::exit(1),
+	}
+}
+
+// reset [--soft | --mixed | --hard] [<commit>]
+fn reset(args: &[String]) -> io::Result<()> {
+	let usage = || io::Error::other("usage: reset [--soft | --mixed | --hard] [<commit>]");
+	let mut mode = cmd::ResetMode::Mixed;
+	let mut rev = None;
+	for arg in args {
+		match arg.as_str() {
+			"--soft" => mode = cmd::ResetMode::Soft,
+			"--mixed" => mode = cmd::ResetMode::Mixed,
+			"--hard" => mode = cmd::ResetMode::Hard,
+			x if x.starts_with('-') => return Err(io::Error::other(format!("unknown option: {}", x))),
+			x if rev.is_none() => rev = Some(x),
+			_ => return Err(usage()),
+		}
+	}
+	cmd::reset(&open_repo()?, rev.unwrap_or("HEAD"), &mode)
+}
+
+// rev-list [--objects] [--date-order | --topo-order] [--count] [--left-right]
+//     (<commit> | <commit>..<commit> | <commit>...<commit> | --all | --branches[=<pattern>] | --tags[=<pattern>])... [^<commit>...]
+fn rev_list(args: &[String]) -> io::Result<()> {
+	let mut opts = cmd::RevListOptions { objects: false, order: cmd::RevListOrder::Walk, count: false, left_right: false };
+	let mut revs = Vec::new();
+
+	for arg in args {
+		match arg.as_str() {
+			"--objects" => opts.objects = true,
+			"--date-order" => opts.order = cmd::RevListOrder::Date,
+			"--topo-order" => opts.order = cmd::RevListOrder::Topo,
+			"--count" => opts.count = true,
+			"--left-right" => opts.left_right = true,
+			_ => revs.push(arg.clone()),
+		}
+	}
+
+	if revs.is_empty() {
+		return Err(io::Error::other("usage: rev-list [--objects] [--date-order | --topo-order] [--count] [--left-right] (<commit> | <range> | --all | --branches | --tags)... [^<commit>...]"));
+	}
+	cmd::rev_list(&open_repo()?, &revs, &opts)
+}
+
+// rm [-r] [--cached] [-f | --force] [-n | --dry-run] <path>...
+fn rm(args: &[String]) -> io::Result<()> {
+	let mut opts = cmd::RmOptions {
+		recursive: false,
+		cached: false,
+		force: false,
+		dry_run: false,
+	};
+	let mut paths = Vec::new();
+
+	for arg in args {
+		match arg.as_str() {
+			"-r" => opts.recursive = true,
+			"--cached" => opts.cached = true,
+			"-f" | "--force" => opts.force = true,
+			"-n" | "--dry-run" => opts.dry_run = true,
+			x if x.starts_with('-') => return Err(io::Error::other(format!("unknown option: {}", x))),
+			_ => paths.push(arg.clone()),
+		}
+	}
+
+	if paths.is_empty() {
+		return Err(io::Error::other("usage: rm [-r] [--cached] [-f | --force] [-n | --dry-run] <path>..."));
+	}
+	cmd::rm(&open_repo()?, &paths, &opts)
+}
+
+// shortlog [-n | --numbered] [-s | --summary] [-e | --email] [<revision-range>...]
+fn shortlog(args: &[String]) -> io::Result<()> {
+	let mut opts = cmd::ShortlogOptions { numbered: false, summary: false, email: false };
+	let mut revs = Vec::new();
+
+	for arg in args {
+		let flags = match arg.as_str() {
+			"--numbered" => "n",
+			"--summary" => "s",
+			"--email" => "e",
+			x if x.starts_with('-') && !x.starts_with("--") && x.len() > 1 => &x[1..],
+			_ => {
+				revs.push(arg.clone());
+				continue;
+			}
+		};
+		// "-sn" のようにまとめて書ける
+		for flag in flags.chars() {
+			match flag {
+				'n' => opts.numbered = true,
+				's' => opts.summary = true,
+				'e' => opts.email = true,
+				_ => return Err(io::Error::other(format!("unknown switch `{}'", flag))),
+			}
+		}
+	}
+	cmd::shortlog(&open_repo()?, &revs, &opts)
+}
+
+// show-ref [--heads] [--tags] [-d | --dereference] [<pattern>...]
+fn show_ref(args: &[String]) -> io::Result<()> {
+	let mut opts = cmd::ShowRefOptions {
+		heads: false,
+		tags: false,
+		dereference: false,
+	};
+	let mut patterns = Vec::new();
+
+	for arg in args {
+		match arg.as_str() {
+			"--heads" => opts.heads = true,
+			"--tags" => opts.tags = true,
+			"-d" | "--dereference" => opts.dereference = true,
+			_ => patterns.push(arg.clone()),
+		}
+	}
+
+	cmd::show_ref(&open_repo()?, &opts, &patterns)
+}
+
+// stash [push [-m <message>] | save [<message>] | list | (apply | drop | pop) [<stash>]]
+fn stash(args: &[String]) -> io::Result<()> {
+	let repo = open_repo()?;
+	let usage = || io::Error::other("usage: stash [push [-m <message>] | list | (apply | drop | pop) [<stash>]]");
+	let stash = args.get(1).map(String::as_str);
+	if args.len() > 2 && !matches!(args[0].as_str(), "push" | "save") {
+		return Err(usage());
+	}
+
+	match args.first().map(String::as_str) {
+		None => cmd::stash_push(&repo, None),
+		Some("push") => match &args[1..] {
+			[] => cmd::stash_push(&repo, None),
+			[m, message] if m == "-m" || m == "--message" => cmd::stash_push(&repo, Some(message)),
+			_ => Err(usage()),
+		},
+		Some("save") => match args[1..].join(" ") {
+			message if message.is_empty() => cmd::stash_push(&repo, None),
+			message => cmd::stash_push(&repo, Some(&message)),
+		},
+		Some("list") => cmd::stash_list(&repo),
+		Some("apply") => cmd::stash_apply(&repo, stash),
+		Some("drop") => cmd::stash_drop(&repo, stash),
+		Some("pop") => cmd::stash_pop(&repo, stash),
+		Some(_) => Err(usage()),
+	}
+}
+
+// status [-s | --short | --porcelain] [-b | --branch] [--long]
+fn status(args: &[String]) -> io::Result<()> {
+	let usage = || io::Error::other("usage: status [-s | --short | --porcelain] [-b | --branch] [--long]");
+	let mut opts = cmd::StatusOptions { short: false, branch: false };
+	for arg in args {
+		let flags = match arg.as_str() {
+			"--short" | "--porcelain" => "s",
+			"--branch" => "b",
+			"--long" => {
+				opts.short = false;
+				continue;
+			}
+			x if x.starts_with('-') && !x.starts_with("--") && x.len() > 1 => &x[1..],
+			_ => return Err(usage()),
+		};
+		// "-sb" のようにまとめて書ける
+		for flag in flags.chars() {
+			match flag {
+				's' => opts.short = true,
+				'b' => opts.branch = true,
+				_ => return Err(usage()),
+			}
+		}
+	}
+	cmd::status(&open_repo()?, &opts)
+}
+
+// switch <branch>
+// switch (-c | -C) <new-branch> [<start>]
+fn switch(args: &[String]) -> io::Result<()> {
+	let usage = || io::Error::other("usage: switch <branch>\n   or: switch (-c | -C) <new-branch> [<start>]");
+	let mut opts = cmd::CheckoutOptions { dry_run: false, new_branch: None, reset: false, branch_only: true, force: false };
+	let target = match args {
+		[flag, name, rest @ ..] if flag == "-c" || flag == "-C" => {
+			opts.reset = flag == "-C";
+			opts.new_branch = Some(name.clone());
+			match rest {
+				[] => "HEAD",
+				[start] => {
+					// 新しいbranchのstartはcommitでもよい
+					opts.branch_only = false;
+					start
 				}
-			})?;
+				_ => return Err(usage()),
+			}
+		}
+		[target] if !target.starts_with('-') => target,
+		_ => return Err(usage()),
+	};
+	cmd::checkout(&open_repo()?, target, &opts)
+}
 
-		Some(Self::new(
-			name,
-			email,
-			offset.from_utc_datetime(&ts.naive_utc()), // UTC時間のタイムスタンプにoffsetをつける
-		))
+// symbolic-ref [-q | --quiet] [--short] [--no-recurse] <name>
+// symbolic-ref <name> <ref>
+// symbolic-ref (-d | --delete) [-q | --quiet] <name>
+fn symbolic_ref(args: &[String]) -> io::Result<()> {
+	let usage = || io::Error::other("usage: symbolic-ref [-q] [--short] [--no-recurse] <name> | <name> <ref> | --delete [-q] <name>");
+	let (mut quiet, mut delete, mut short, mut recurse) = (false, false, false, true);
+	let mut names = Vec::new();
+	for arg in args {
+		match arg.as_str() {
+			"-q" | "--quiet" => quiet = true,
+			"-d" | "--delete" => delete = true,
+			"--short" => short = true,
+			"--recurse" => recurse = true,
+			"--no-recurse" => recurse = false,
+			x if x.starts_with('-') => return Err(usage()),
+			_ => names.push(arg.clone()),
+		}
+	}
+	let (name, action) = match names.as_slice() {
+		[name] if delete => (name, cmd::SymbolicRefAction::Delete),
+		[name] => (name, cmd::SymbolicRefAction::Read { short, recurse }),
+		[name, target] if !delete => (name, cmd::SymbolicRefAction::Set(target.clone())),
+		_ => return Err(usage()),
+	};
+	match cmd::symbolic_ref(&open_repo()?, name, &action, quiet)? {
+		true => Ok(()),
+		false => process::exit(1),
 	}
 }
 
-fn main() {
-    println!("Hello, world!");
+// tag [-l | --list] [-n[<num>]] [--sort=[-](refname | version:refname)] [<pattern>...]
+// tag [-f] [-a] [-m <msg>] <tagname> [<commit> | <object>]
+// tag -v <tag>...
+// tag -d <tag>...
+fn tag(args: &[String]) -> io::Result<()> {
+	let usage = || io::Error::other("usage: tag [-l] [-n[<num>]] [--sort=<key>] [<pattern>...] / tag [-f] [-a] [-m <msg>] <tagname> [<commit>] / tag -v <tag>... / tag -d <tag>...");
+	if let Some((flag, names)) = args.split_first() {
+		if flag == "-d" || flag == "--delete" {
+			return match names {
+				[] => Err(usage()),
+				names => match cmd::tag_delete(&open_repo()?, names)? {
+					true => Ok(()),
+					false => process::exit(1),
+				},
+			};
+		}
+		if flag == "-v" || flag == "--verify" {
+			let repo = open_repo()?;
+			return match names {
+				[] => Err(usage()),
+				names => names.iter().try_for_each(|x| cmd::tag_verify(&repo, x)),
+			};
+		}
+	}
+
+	let mut opts = cmd::TagOptions {
+		patterns: Vec::new(),
+		sort: cmd::TagSort::Refname,
+		reverse: false,
+		lines: 0,
+	};
+	let mut create = cmd::TagCreateOptions {
+		message: None,
+		force: false,
+	};
+	let mut annotate = false;
+	let mut list = false;
+	let mut iter = args.iter();
+	while let Some(arg) = iter.next() {
+		if let Some(key) = arg.strip_prefix("--sort=") {
+			let (reverse, key) = match key.strip_prefix('-') {
+				Some(key) => (true, key),
+				None => (false, key),
+			};
+			opts.reverse = reverse;
+			opts.sort = match key {
+				"refname" => cmd::TagSort::Refname,
+				"version:refname" | "v:refname" => cmd::TagSort::Version,
+				_ => return Err(io::Error::other(format!("unsupported sort specification '{}'", key))),
+			};
+			continue;
+		}
+		// -nはlistを指定したことにもなる
+		if let Some(n) = arg.strip_prefix("-n") {
+			opts.lines = match n {
+				"" => 1,
+				n => n.parse().map_err(|_| usage())?,
+			};
+			list = true;
+			continue;
+		}
+		match arg.as_str() {
+			"-l" | "--list" => list = true,
+			"-a" | "--annotate" => annotate = true,
+			"-f" | "--force" => create.force = true,
+			// -mを何度も指定すると、それぞれを段落としてつなげる
+			"-m" | "--message" => {
+				let message = iter.next().ok_or_else(usage)?;
+				create.message = Some(match create.message.take() {
+					Some(x) => format!("{}\n\n{}", x, message),
+					None => message.clone(),
+				});
+			}
+			x if x.starts_with('-') => return Err(io::Error::other(format!("unknown option: {}", x))),
+			x => opts.patterns.push(String::from(x)),
+		}
+	}
+
+	if list || opts.patterns.is_empty() {
+		return cmd::tag_list(&open_repo()?, &opts);
+	}
+	// messageはeditorで書けないので-mで渡す
+	if annotate && create.message.is_none() {
+		return Err(io::Error::other("no tag message?"));
+	}
+	match opts.patterns.as_slice() {
+		[name] => cmd::tag_create(&open_repo()?, name, None, &create),
+		[name, target] => cmd::tag_create(&open_repo()?, name, Some(target), &create),
+		_ => Err(io::Error::other("too many arguments")),
+	}
 }
@@ -0,0 +1,416 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+
+use crate::commit_graph;
+use crate::diff::{self, Side};
+use crate::hash::Hash;
+use crate::object::{is_binary, ObjectKind};
+use crate::repository::Repository;
+use crate::store;
+
+// mergeの途中であることを表すファイル
+const STATE_FILES: [&str; 3] = ["MERGE_HEAD", "MERGE_MSG", "MERGE_MODE"];
+
+// aとbの最良の共通祖先のうち最初のもの(merge_base_allを参照)
+pub fn merge_base(repo: &Repository, a: &Hash, b: &Hash) -> io::Result<Option<Hash>> {
+	Ok(merge_base_all(repo, a, b)?.into_iter().next())
+}
+
+// aとbの両方から辿れるcommitのうち、他のそのようなcommitの祖先でないもの(最良の共通祖先)
+// criss-crossなmergeがあると複数になる。新しい順、同じ日時ならhash順に並べる
+pub fn merge_base_all(repo: &Repository, a: &Hash, b: &Hash) -> io::Result<Vec<Hash>> {
+	best_common(repo, std::slice::from_ref(a), b)
+}
+
+// tipsのどれかとotherの両方から辿れるcommitのうち最良のもの
+fn best_common(repo: &Repository, tips: &[Hash], other: &Hash) -> io::Result<Vec<Hash>> {
+	let mut ours = HashSet::new();
+	for tip in tips {
+		ours.extend(ancestors(repo, tip)?);
+	}
+	let common: HashSet<Hash> = ancestors(repo, other)?
+		.into_iter()
+		.filter(|x| ours.contains(x))
+		.collect();
+
+	// 共通祖先のparentから辿れるものは、より良い共通祖先があるので除く
+	let mut redundant = HashSet::new();
+	let mut stack = Vec::new();
+	for hash in common.iter() {
+		stack.extend(parents(repo, hash)?);
+	}
+	while let Some(hash) = stack.pop() {
+		if redundant.insert(hash.clone()) {
+			stack.extend(parents(repo, &hash)?);
+		}
+	}
+
+	let mut best = Vec::new();
+	for hash in common.into_iter().filter(|x| !redundant.contains(x)) {
+		let date = match commit_graph::find(repo, &hash)? {
+			Some(commit) => commit.time,
+			None => store::read_commit(repo, &hash.0)?.committer.ts.timestamp(),
+		};
+		best.push((std::cmp::Reverse(date), hash.to_hex(), hash));
+	}
+	best.sort();
+	Ok(best.into_iter().map(|(_, _, hash)| hash).collect())
+}
+
+// 複数の共通祖先を1つずつ再帰的にmergeした仮想的なbaseのファイル
+// その中でconflictしたファイルは、両側にtextの内容があればconflict markerを入れたもの、そうでなければ元のbaseのものにする
+pub fn virtual_base(repo: &Repository, bases: &[Hash]) -> io::Result<BTreeMap<String, Side>> {
+	let Some((first, rest)) = bases.split_first() else {
+		return Ok(BTreeMap::new());
+	};
+	let mut merged = vec![first.clone()];
+	let mut entries = commit_entries(repo, first)?;
+	for next in rest {
+		let base = match best_common(repo, &merged, next)?.as_slice() {
+			[base] => commit_entries(repo, base)?,
+			bases => virtual_base(repo, bases)?,
+		};
+		let theirs = commit_entries(repo, next)?;
+
+		let mut result = BTreeMap::new();
+		for (path, resolution) in merge_trees(&base, &entries, &theirs) {
+			let side = match resolution {
+				Resolution::Clean(side) => side,
+				Resolution::Conflict {
+					base,
+					ours: Some(ours),
+					theirs: Some(theirs),
+				} => {
+					let (_, a) = store::read_object(repo, &ours.hash)?;
+					let (_, b) = store::read_object(repo, &theirs.hash)?;
+					// binaryにはmarkerを入れられない
+					if is_binary(&a) || is_binary(&b) {
+						base
+					} else {
+						let content = conflict_markers(
+							&a,
+							&b,
+							"Temporary merge branch 1",
+							"Temporary merge branch 2",
+						);
+						let hash = store::write_object(repo, &ObjectKind::Blob.encode(&content))?;
+						Some(Side { hash, ..ours })
+					}
+				}
+				Resolution::Conflict { base, .. } => base,
+			};
+			if let Some(side) = side {
+				result.insert(path, side);
+			}
+		}
+		entries = result;
+		merged.push(next.clone());
+	}
+	Ok(entries)
+}
+
+fn commit_entries(repo: &Repository, hash: &Hash) -> io::Result<BTreeMap<String, Side>> {
+	let tree = store::read_commit(repo, &hash.0)?.tree;
+	diff::tree_entries(repo, &hex::decode(&tree).unwrap_or_default())
+}
+
+// hash自身を含む全ての祖先
+pub fn ancestors(repo: &Repository, hash: &Hash) -> io::Result<HashSet<Hash>> {
+	let mut seen = HashSet::new();
+	let mut stack = vec![hash.clone()];
+	while let Some(hash) = stack.pop() {
+		if seen.insert(hash.clone()) {
+			stack.extend(parents(repo, &hash)?);
+		}
+	}
+	Ok(seen)
+}
+
+// commit-graphにあればcommitを読まずに済ませる
+fn parents(repo: &Repository, hash: &Hash) -> io::Result<Vec<Hash>> {
+	if let Some(commit) = commit_graph::find(repo, hash)? {
+		return Ok(commit.parents.iter().map(|x| Hash(x.clone())).collect());
+	}
+	store::read_commit(repo, &hash.0)?
+		.parents
+		.iter()
+		.map(|x| Hash::from_hex(x).ok_or_else(|| bad_hash(x)))
+		.collect()
+}
+
+fn bad_hash(hash: &str) -> io::Error {
+	io::Error::new(
+		io::ErrorKind::InvalidData,
+		format!("bad object name {}", hash),
+	)
+}
+
+// 1つのpathのmerge結果
+pub enum Resolution {
+	Clean(Option<Side>), // Noneは削除
+	Conflict {
+		base: Option<Side>,
+		ours: Option<Side>,
+		theirs: Option<Side>,
+	},
+}
+
+// modeと内容が同じか(どちらも無い場合も同じとする)
+pub fn same(a: Option<&Side>, b: Option<&Side>) -> bool {
+	match (a, b) {
+		(Some(a), Some(b)) => a.mode == b.mode && a.hash == b.hash,
+		(None, None) => true,
+		_ => false,
+	}
+}
+
+// baseからの変更が片側だけならその変更を採り、両側で違う変更をしていればconflictにする
+pub fn merge_trees(
+	base: &BTreeMap<String, Side>,
+	ours: &BTreeMap<String, Side>,
+	theirs: &BTreeMap<String, Side>,
+) -> BTreeMap<String, Resolution> {
+	let mut paths: Vec<&String> = base
+		.keys()
+		.chain(ours.keys())
+		.chain(theirs.keys())
+		.collect();
+	paths.sort();
+	paths.dedup();
+
+	paths
+		.into_iter()
+		.map(|path| {
+			let (b, o, t) = (base.get(path), ours.get(path), theirs.get(path));
+			let resolution = if same(o, t) || same(b, t) {
+				Resolution::Clean(o.cloned())
+			} else if same(b, o) {
+				Resolution::Clean(t.cloned())
+			} else {
+				Resolution::Conflict {
+					base: b.cloned(),
+					ours: o.cloned(),
+					theirs: t.cloned(),
+				}
+			};
+			(path.clone(), resolution)
+		})
+		.collect()
+}
+
+// 両側の内容全体をconflict markerで囲む
+pub fn conflict_markers(
+	ours: &[u8],
+	theirs: &[u8],
+	ours_label: &str,
+	theirs_label: &str,
+) -> Vec<u8> {
+	let mut out = format!("<<<<<<< {}\n", ours_label).into_bytes();
+	for (content, marker) in [
+		(ours, String::from("=======\n")),
+		(theirs, format!(">>>>>>> {}\n", theirs_label)),
+	] {
+		out.extend_from_slice(content);
+		if !content.is_empty() && !content.ends_with(b"\n") {
+			out.push(b'\n');
+		}
+		out.extend_from_slice(marker.as_bytes());
+	}
+	out
+}
+
+// 行ごとのmerge結果の1部分
+enum Region<'a> {
+	Clean(Vec<&'a [u8]>),
+	Conflict(Vec<&'a [u8]>, Vec<&'a [u8]>), // (ours, theirs)
+}
+
+// baseからoursとtheirsへの変更を行ごとにmergeし、(結果, conflictの数)を返す
+// 重なるか接している変更は、両側で同じでなければconflict markerで囲む
+// gitと同じく、conflictの両側で共通の行はconflictの外に出し、間が3行以下のconflictは1つにまとめる
+pub fn merge_content(
+	base: &[u8],
+	ours: &[u8],
+	theirs: &[u8],
+	ours_label: &str,
+	theirs_label: &str,
+) -> (Vec<u8>, usize) {
+	let (b, o, t) = (diff::lines(base), diff::lines(ours), diff::lines(theirs));
+	let (ho, ht) = (
+		hunks(&diff::myers_diff(&b, &o)),
+		hunks(&diff::myers_diff(&b, &t)),
+	);
+
+	let mut regions = Vec::new();
+	let (mut i, mut j) = (0, 0);
+	let mut pos = 0;
+	// oursとtheirsの行番号からbaseの行番号を引いたもの
+	let (mut delta_o, mut delta_t) = (0isize, 0isize);
+	while i < ho.len() || j < ht.len() {
+		let start = match (ho.get(i), ht.get(j)) {
+			(Some(x), Some(y)) => x.0.min(y.0),
+			(Some(x), None) | (None, Some(x)) => x.0,
+			(None, None) => break,
+		};
+		regions.push(Region::Clean(b[pos..start].to_vec()));
+
+		// 重なるか接している変更をまとめる
+		let (i0, j0, mut end) = (i, j, start);
+		let (os, ts) = (
+			start.wrapping_add_signed(delta_o),
+			start.wrapping_add_signed(delta_t),
+		);
+		loop {
+			if let Some(&(bs, be, ss, se)) = ho.get(i).filter(|x| x.0 <= end) {
+				end = end.max(be);
+				delta_o += (se - ss) as isize - (be - bs) as isize;
+				i += 1;
+			} else if let Some(&(bs, be, ss, se)) = ht.get(j).filter(|x| x.0 <= end) {
+				end = end.max(be);
+				delta_t += (se - ss) as isize - (be - bs) as isize;
+				j += 1;
+			} else {
+				break;
+			}
+		}
+		let ours = &o[os..end.wrapping_add_signed(delta_o)];
+		let theirs = &t[ts..end.wrapping_add_signed(delta_t)];
+		regions.push(match (i > i0, j > j0) {
+			(true, true) if ours != theirs => Region::Conflict(ours.to_vec(), theirs.to_vec()),
+			(true, _) => Region::Clean(ours.to_vec()),
+			(false, _) => Region::Clean(theirs.to_vec()),
+		});
+		pos = end;
+	}
+	regions.push(Region::Clean(b[pos..].to_vec()));
+
+	let regions = simplify(regions.into_iter().flat_map(refine).collect());
+	let mut out = Vec::new();
+	let mut conflicts = 0;
+	for region in regions {
+		match region {
+			Region::Clean(lines) => out.extend(lines.concat()),
+			Region::Conflict(ours, theirs) => {
+				conflicts += 1;
+				out.extend(conflict_markers(
+					&ours.concat(),
+					&theirs.concat(),
+					ours_label,
+					theirs_label,
+				));
+			}
+		}
+	}
+	(out, conflicts)
+}
+
+// 変更された範囲を(baseの開始, baseの終わり, 変更後の開始, 変更後の終わり)として並べる
+fn hunks(edits: &[diff::Edit]) -> Vec<(usize, usize, usize, usize)> {
+	let mut hunks = Vec::new();
+	let (mut x, mut y) = (0, 0);
+	let mut start = None;
+	for edit in edits {
+		match edit {
+			diff::Edit::Equal(..) => {
+				if let Some((sx, sy)) = start.take() {
+					hunks.push((sx, x, sy, y));
+				}
+				x += 1;
+				y += 1;
+			}
+			diff::Edit::Delete(_) => {
+				start.get_or_insert((x, y));
+				x += 1;
+			}
+			diff::Edit::Insert(_) => {
+				start.get_or_insert((x, y));
+				y += 1;
+			}
+		}
+	}
+	if let Some((sx, sy)) = start {
+		hunks.push((sx, x, sy, y));
+	}
+	hunks
+}
+
+// conflictの両側を比べ、同じ行をconflictの外に出していくつかのconflictに分ける
+fn refine(region: Region) -> Vec<Region> {
+	let (ours, theirs) = match region {
+		Region::Conflict(ours, theirs) if !ours.is_empty() && !theirs.is_empty() => (ours, theirs),
+		region => return vec![region],
+	};
+	let mut regions = Vec::new();
+	let mut x = 0;
+	for (bs, be, ss, se) in hunks(&diff::myers_diff(&ours, &theirs)) {
+		regions.push(Region::Clean(ours[x..bs].to_vec()));
+		regions.push(Region::Conflict(
+			ours[bs..be].to_vec(),
+			theirs[ss..se].to_vec(),
+		));
+		x = be;
+	}
+	regions.push(Region::Clean(ours[x..].to_vec()));
+	regions
+}
+
+// 間が3行以下のconflictを、間の行を両側に含めて1つにまとめる
+fn simplify(regions: Vec<Region>) -> Vec<Region> {
+	let mut out: Vec<Region> = Vec::new();
+	for region in regions {
+		match (region, out.as_mut_slice()) {
+			(Region::Clean(lines), _) if lines.is_empty() => {}
+			(Region::Conflict(ours, theirs), [.., Region::Conflict(a, b), Region::Clean(gap)])
+				if gap.len() <= 3 =>
+			{
+				let gap = std::mem::take(gap);
+				a.extend(gap.iter().chain(ours.iter()));
+				b.extend(gap.iter().chain(theirs.iter()));
+				out.pop();
+			}
+			(Region::Conflict(ours, theirs), [.., Region::Conflict(a, b)]) => {
+				a.extend(ours);
+				b.extend(theirs);
+			}
+			(Region::Clean(lines), [.., Region::Clean(prev)]) => prev.extend(lines),
+			(region, _) => out.push(region),
+		}
+	}
+	out
+}
+
+// mergeの途中ならMERGE_HEADに書かれたcommit
+pub fn merge_head(repo: &Repository) -> io::Result<Option<String>> {
+	match fs::read_to_string(repo.gitdir.join("MERGE_HEAD")) {
+		Ok(content) => Ok(content.lines().next().map(String::from)),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+		Err(e) => Err(e),
+	}
+}
+
+pub fn merge_msg(repo: &Repository) -> io::Result<Option<String>> {
+	match fs::read_to_string(repo.gitdir.join("MERGE_MSG")) {
+		Ok(content) => Ok(Some(content)),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+		Err(e) => Err(e),
+	}
+}
+
+// conflictしたmergeを後でcommitかabortできるように記録する
+pub fn write_state(repo: &Repository, theirs: &Hash, message: &str) -> io::Result<()> {
+	fs::write(repo.gitdir.join("MERGE_HEAD"), format!("{}\n", theirs))?;
+	fs::write(repo.gitdir.join("MERGE_MSG"), message)?;
+	fs::write(repo.gitdir.join("MERGE_MODE"), "")
+}
+
+pub fn clear_state(repo: &Repository) -> io::Result<()> {
+	for name in STATE_FILES {
+		match fs::remove_file(repo.gitdir.join(name)) {
+			Ok(()) => {}
+			Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+			Err(e) => return Err(e),
+		}
+	}
+	Ok(())
+}
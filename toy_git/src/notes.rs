@@ -0,0 +1,58 @@
+use std::collections::BTreeMap;
+use std::io;
+
+use crate::hash::Hash;
+use crate::refs;
+use crate::repository::Repository;
+use crate::store;
+
+// git notesが既定で使うref。commitのtreeに、noteを付けたobjectのhashを名前にしたblobを置く
+pub const NOTES_REF: &str = "refs/notes/commits";
+
+// 全てのnoteを、noteを付けたobjectのhashからnoteのblobのhashへのmapにして返す
+// gitはnoteが多いと "ab/cdef..." のようにディレクトリに分けて置くので、それも読む
+pub fn list(repo: &Repository) -> io::Result<BTreeMap<String, Vec<u8>>> {
+	let mut notes = BTreeMap::new();
+	let Some(commit) = refs::resolve(repo, NOTES_REF)? else {
+		return Ok(notes);
+	};
+	let commit = Hash::from_hex(&commit)
+		.ok_or_else(|| io::Error::other(format!("bad object name {}", commit)))?;
+	let tree = store::read_commit(repo, &commit.0)?.tree;
+	collect(
+		repo,
+		&hex::decode(&tree).unwrap_or_default(),
+		"",
+		&mut notes,
+	)?;
+	Ok(notes)
+}
+
+// objectに付けたnoteの内容
+pub fn read(repo: &Repository, object: &str) -> io::Result<Option<Vec<u8>>> {
+	match list(repo)?.get(object) {
+		Some(blob) => Ok(Some(store::read_object(repo, blob)?.1)),
+		None => Ok(None),
+	}
+}
+
+// prefixはここまでのディレクトリ名をつなげたhashの前半
+fn collect(
+	repo: &Repository,
+	tree: &[u8],
+	prefix: &str,
+	notes: &mut BTreeMap<String, Vec<u8>>,
+) -> io::Result<()> {
+	for file in store::read_tree(repo, tree)?.contents {
+		let name = format!("{}{}", prefix, file.name);
+		if !name.bytes().all(|x| x.is_ascii_hexdigit()) {
+			continue;
+		}
+		if file.is_tree() && name.len() < repo.hash_algo.hex_len() {
+			collect(repo, &file.hash, &name, notes)?;
+		} else if !file.is_tree() && name.len() == repo.hash_algo.hex_len() {
+			notes.insert(name, file.hash);
+		}
+	}
+	Ok(())
+}
@@ -0,0 +1,101 @@
+use std::fmt;
+
+use crate::error::GitError;
+use crate::hash::HashAlgorithm;
+
+// gitと同じく先頭からこの長さの中にNULがあればbinaryとする
+const BINARY_CHECK_SIZE: usize = 8000;
+
+// 内容はUTF-8とは限らない(画像や実行ファイルなど)のでbyte列のまま持つ
+pub struct Blob {
+	pub size: usize,
+	pub content: Vec<u8>,
+}
+
+impl Blob {
+	pub fn new(content: Vec<u8>) -> Self {
+		Self {
+			size: content.len(),
+			content,
+		}
+	}
+
+	// 他のobjectと形を揃えてResultを返すが、どんなbyte列もblobにできる
+	pub fn from(bytes: &[u8]) -> Result<Self, GitError> {
+		Ok(Self::new(bytes.to_vec()))
+	}
+
+	pub fn as_bytes(&self) -> Vec<u8> {
+		// headerとbodyが\0で区切られる
+		let header = format!("blob {}\0", self.size);
+		[header.as_bytes(), &self.content].concat()
+	}
+
+	pub fn calc_hash(&self, algo: HashAlgorithm) -> Vec<u8> {
+		algo.digest(&self.as_bytes())
+	}
+
+	pub fn is_binary(&self) -> bool {
+		is_binary(&self.content)
+	}
+}
+
+// diff, grep, merge, 改行の変換などで内容をtextとして扱ってよいかの判定
+// Blobを作らずに判定することも多いのでbyte列で受け取る
+pub fn is_binary(content: &[u8]) -> bool {
+	content.iter().take(BINARY_CHECK_SIZE).any(|&b| b == b'\0')
+}
+
+impl fmt::Display for Blob {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", String::from_utf8_lossy(&self.content))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn text() {
+		assert!(!Blob::new(b"hello\nworld\n".to_vec()).is_binary());
+		// UTF-8でなくてもNULが無ければtext
+		assert!(!Blob::new(vec![0xff, 0xfe, b'\n']).is_binary());
+	}
+
+	#[test]
+	fn early_nul() {
+		assert!(Blob::new(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec()).is_binary());
+		let mut content = vec![b'a'; BINARY_CHECK_SIZE - 1];
+		content.push(b'\0');
+		assert!(Blob::new(content).is_binary());
+	}
+
+	#[test]
+	fn late_nul() {
+		// gitと同じく先頭の8000byteしか見ない
+		let mut content = vec![b'a'; BINARY_CHECK_SIZE];
+		content.push(b'\0');
+		assert!(!Blob::new(content.clone()).is_binary());
+		assert!(!is_binary(&content));
+	}
+
+	#[test]
+	fn empty() {
+		assert!(!Blob::new(Vec::new()).is_binary());
+	}
+
+	// UTF-8として読めない内容もそのまま持ち、hashはgit hash-objectと同じ
+	#[test]
+	fn non_utf8() {
+		let content = b"\xff\xfe\0\x01\x89PNG\r\n\x1a\n\xc3\x28";
+		let blob = Blob::from(content).unwrap();
+		assert_eq!(blob.content, content);
+		assert_eq!(blob.size, content.len());
+		assert_eq!(blob.as_bytes(), [&b"blob 14\0"[..], content].concat());
+		assert_eq!(
+			hex::encode(blob.calc_hash(HashAlgorithm::Sha1)),
+			"fb6f1e3856598500eb1e76853fa41bb1246ce6ce"
+		);
+	}
+}
@@ -0,0 +1,314 @@
+use std::fmt;
+
+use chrono::{DateTime, FixedOffset, TimeZone};
+
+use super::{split_headers, utf8};
+use crate::error::GitError;
+use crate::hash::HashAlgorithm;
+
+pub struct User {
+	pub name: String,
+	pub email: String,
+	pub ts: DateTime<FixedOffset>,
+}
+
+pub struct Commit {
+	pub tree: String,
+	pub parents: Vec<String>, // 最初のコミットには無く、mergeのcommitには複数ある
+	pub author: User,
+	pub committer: User,
+	pub message: String,
+	pub signature: Option<String>, // gpgsig headerに入っているASCII armorの署名
+}
+
+// authorとcommitterを取り違えないよう、名前を付けて1つずつ設定する
+// tree, author, committerは必須。messageは省略すれば空になる
+#[derive(Default)]
+pub struct CommitBuilder {
+	tree: Option<String>,
+	parents: Vec<String>,
+	author: Option<User>,
+	committer: Option<User>,
+	message: String,
+	signature: Option<String>,
+}
+
+impl CommitBuilder {
+	pub fn tree(mut self, tree: String) -> Self {
+		self.tree = Some(tree);
+		self
+	}
+
+	pub fn parent(mut self, parent: String) -> Self {
+		self.parents.push(parent);
+		self
+	}
+
+	pub fn parents(mut self, parents: Vec<String>) -> Self {
+		self.parents.extend(parents);
+		self
+	}
+
+	pub fn author(mut self, author: User) -> Self {
+		self.author = Some(author);
+		self
+	}
+
+	pub fn committer(mut self, committer: User) -> Self {
+		self.committer = Some(committer);
+		self
+	}
+
+	pub fn message(mut self, message: String) -> Self {
+		self.message = message;
+		self
+	}
+
+	pub fn signature(mut self, signature: Option<String>) -> Self {
+		self.signature = signature;
+		self
+	}
+
+	pub fn build(self) -> Result<Commit, &'static str> {
+		Ok(Commit {
+			tree: self.tree.ok_or("commit has no tree")?,
+			parents: self.parents,
+			author: self.author.ok_or("commit has no author")?,
+			committer: self.committer.ok_or("commit has no committer")?,
+			message: self.message,
+			signature: self.signature,
+		})
+	}
+}
+
+impl Commit {
+	pub fn builder() -> CommitBuilder {
+		CommitBuilder::default()
+	}
+
+	pub fn from(bytes: &[u8]) -> Result<Self, GitError> {
+		let content = utf8(bytes, "commit")?;
+
+		// headerとcommitメッセージの間は空行で区切られる
+		let (header, message) = content.split_once("\n\n").ok_or(GitError::InvalidHeader {
+			offset: content.len(),
+			message: "missing blank line before message",
+		})?;
+		let mut iter = split_headers(header).into_iter();
+
+		let tree = iter
+			.next()
+			.filter(|(_, key, _)| *key == "tree") // 最初の行はtreeで決まっている
+			.map(|(_, _, value)| value)
+			.ok_or(GitError::InvalidHeader {
+				offset: 0,
+				message: "missing tree",
+			})?;
+
+		let mut parents = Vec::new();
+		let mut author = None;
+		let mut committer = None;
+		let mut signature = None;
+		for (offset, key, value) in iter {
+			let user =
+				|| User::from(value.as_bytes()).map_err(|e| e.offset_by(offset + key.len() + 1));
+			match key {
+				"parent" => parents.push(value),
+				"author" => author = Some(user()?),
+				"committer" => committer = Some(user()?),
+				"gpgsig" => signature = Some(value),
+				_ => {} // 知らないheaderは読み飛ばす
+			}
+		}
+
+		let mut builder = Self::builder()
+			.tree(tree)
+			.parents(parents)
+			.message(String::from(message))
+			.signature(signature);
+		if let Some(author) = author {
+			builder = builder.author(author);
+		}
+		if let Some(committer) = committer {
+			builder = builder.committer(committer);
+		}
+		builder.build().map_err(|message| GitError::InvalidHeader {
+			offset: header.len(),
+			message,
+		})
+	}
+
+	// 署名の対象になる内容(gpgsig headerを除いたcommitのbody)
+	pub fn payload(&self) -> String {
+		self.encode(None)
+	}
+
+	fn encode(&self, signature: Option<&str>) -> String {
+		let mut content = format!("tree {}\n", self.tree);
+		for parent in self.parents.iter() {
+			content.push_str(&format!("parent {}\n", parent));
+		}
+		content.push_str(&format!("author {}\n", self.author));
+		content.push_str(&format!("committer {}\n", self.committer));
+		if let Some(signature) = signature {
+			// 複数行のheaderは2行目以降を空白で始める
+			content.push_str(&format!("gpgsig {}\n", signature.trim_end().replace('\n', "\n ")));
+		}
+		content.push_str(&format!("\n{}", self.message));
+		content
+	}
+
+	pub fn as_bytes(&self) -> Vec<u8> {
+		let content = format!("{}", self);
+		let header = format!("commit {}\0", content.len());
+
+		[header.as_bytes(), content.as_bytes()].concat()
+	}
+
+	pub fn calc_hash(&self, algo: HashAlgorithm) -> Vec<u8> {
+		algo.digest(&self.as_bytes())
+	}
+}
+
+impl fmt::Display for Commit {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", self.encode(self.signature.as_deref()))
+	}
+}
+
+impl User {
+	pub fn new(name: String, email: String, ts: DateTime<FixedOffset>) -> Self {
+		Self { name, email, ts }
+	}
+
+	// "<name> <<email>> <timestamp> <+hhmm>"
+	pub fn from(bytes: &[u8]) -> Result<Self, GitError> {
+		let lt = bytes
+			.iter()
+			.position(|&x| x == b'<')
+			.ok_or(GitError::BadIdent { offset: 0 })?;
+		let gt = bytes[lt..]
+			.iter()
+			.position(|&x| x == b'>')
+			.map(|x| lt + x)
+			.ok_or(GitError::BadIdent { offset: lt })?;
+
+		let name = String::from_utf8(bytes[..lt].to_vec())
+			.map(|x| String::from(x.trim())) // 最後の空白をtrimする
+			.map_err(|e| GitError::Utf8 {
+				field: "ident name",
+				offset: e.utf8_error().valid_up_to(),
+			})?;
+		let email = String::from_utf8(bytes[lt + 1..gt].to_vec()).map_err(|e| GitError::Utf8 {
+			field: "ident email",
+			offset: lt + 1 + e.utf8_error().valid_up_to(),
+		})?;
+
+		let bad_timestamp = GitError::BadTimestamp { offset: gt + 1 };
+		let info = std::str::from_utf8(&bytes[gt + 1..]).map_err(|_| bad_timestamp)?;
+		let ts_at = gt + 1 + info.len() - info.trim_start().len();
+		let (ts, zone) = info
+			.trim_start()
+			.split_once(char::is_whitespace)
+			.unwrap_or((info.trim_start(), ""));
+		let zone_at = ts_at + ts.len() + 1 + zone.len() - zone.trim_start().len();
+
+		let ts = ts
+			.parse::<i64>()
+			.map_err(|_| GitError::BadTimestamp { offset: ts_at })?;
+		let offset = zone
+			.split_whitespace()
+			.next()
+			.and_then(|x| x.parse::<i32>().ok())
+			.and_then(|x| FixedOffset::east_opt(x / 100 * 60 * 60 + x % 100 * 60)) // +hhmm形式なので秒に直す
+			.ok_or(GitError::BadTimestamp { offset: zone_at })?;
+
+		Ok(Self::new(
+			name,
+			email,
+			offset // UTC時間のタイムスタンプにoffsetをつける
+				.timestamp_opt(ts, 0)
+				.single()
+				.ok_or(GitError::BadTimestamp { offset: ts_at })?,
+		))
+	}
+}
+
+impl fmt::Display for User {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"{} <{}> {} {}",
+			self.name,
+			self.email,
+			self.ts.timestamp(),
+			self.ts.format("%z")
+		)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+	fn user(name: &str) -> User {
+		let ts = FixedOffset::east_opt(0)
+			.unwrap()
+			.timestamp_opt(1700000000, 0)
+			.unwrap();
+		User::new(name.to_string(), format!("{}@example.com", name), ts)
+	}
+
+	#[test]
+	fn build() {
+		let commit = Commit::builder()
+			.tree(TREE.to_string())
+			.parent("a".repeat(40))
+			.parents(vec!["b".repeat(40)])
+			.author(user("author"))
+			.committer(user("committer"))
+			.message("message\n".to_string())
+			.build()
+			.unwrap();
+		assert_eq!(commit.parents, ["a".repeat(40), "b".repeat(40)]);
+		assert_eq!(commit.author.name, "author");
+		assert_eq!(commit.committer.name, "committer");
+		assert!(commit.signature.is_none());
+
+		// 書いたものを読み直しても同じ
+		let read = Commit::from(commit.to_string().as_bytes()).unwrap();
+		assert_eq!(read.to_string(), commit.to_string());
+		assert_eq!(read.author.email, "author@example.com");
+	}
+
+	#[test]
+	fn missing_fields() {
+		let full = || {
+			Commit::builder()
+				.tree(TREE.to_string())
+				.author(user("a"))
+				.committer(user("c"))
+		};
+		assert!(full().build().is_ok());
+		let no_tree = CommitBuilder {
+			tree: None,
+			..full()
+		};
+		assert_eq!(no_tree.build().err(), Some("commit has no tree"));
+		let no_author = CommitBuilder {
+			author: None,
+			..full()
+		};
+		assert_eq!(no_author.build().err(), Some("commit has no author"));
+		let no_committer = CommitBuilder {
+			committer: None,
+			..full()
+		};
+		assert_eq!(no_committer.build().err(), Some("commit has no committer"));
+
+		// messageは省略すれば空
+		assert_eq!(full().build().unwrap().message, "");
+	}
+}
@@ -0,0 +1,153 @@
+mod blob;
+mod commit;
+mod tag;
+mod tree;
+
+use std::fmt;
+
+use crate::error::GitError;
+use crate::hash::HashAlgorithm;
+
+pub use blob::{is_binary, Blob};
+pub use commit::{Commit, User};
+pub use tag::Tag;
+pub use tree::{tree_mode, File as TreeEntry, Tree};
+
+pub enum GitObject {
+	Blob(Blob),
+	Tree(Tree),
+	Commit(Commit),
+	Tag(Tag),
+}
+
+impl GitObject {
+	// "<type> <size>\0<body>" を読み、headerのtypeのobjectとしてbodyをparseする
+	pub fn parse(bytes: &[u8], algo: HashAlgorithm) -> Result<Self, GitError> {
+		let (kind, body) = split_header(bytes)?;
+		// bodyの中の位置はheaderの後から
+		Self::from(kind, body, algo).map_err(|e| e.offset_by(bytes.len() - body.len()))
+	}
+
+	// headerを除いたbodyをkindのobjectとしてparseする
+	pub fn from(kind: ObjectKind, body: &[u8], algo: HashAlgorithm) -> Result<Self, GitError> {
+		Ok(match kind {
+			ObjectKind::Blob => Self::Blob(Blob::from(body)?),
+			ObjectKind::Tree => Self::Tree(Tree::from(body, algo)?),
+			ObjectKind::Commit => Self::Commit(Commit::from(body)?),
+			ObjectKind::Tag => Self::Tag(Tag::from(body)?),
+		})
+	}
+
+	pub fn kind(&self) -> ObjectKind {
+		match self {
+			Self::Blob(_) => ObjectKind::Blob,
+			Self::Tree(_) => ObjectKind::Tree,
+			Self::Commit(_) => ObjectKind::Commit,
+			Self::Tag(_) => ObjectKind::Tag,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ObjectKind {
+	Blob,
+	Tree,
+	Commit,
+	Tag,
+}
+
+impl ObjectKind {
+	pub fn from(s: &str) -> Option<Self> {
+		match s {
+			"blob" => Some(Self::Blob),
+			"tree" => Some(Self::Tree),
+			"commit" => Some(Self::Commit),
+			"tag" => Some(Self::Tag),
+			_ => None,
+		}
+	}
+
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Self::Blob => "blob",
+			Self::Tree => "tree",
+			Self::Commit => "commit",
+			Self::Tag => "tag",
+		}
+	}
+
+	// "<type> <size>\0<body>" の形にしてobjectとして保存できるbyte列を作る
+	pub fn encode(&self, body: &[u8]) -> Vec<u8> {
+		[self.header(body.len()).as_bytes(), body].concat()
+	}
+
+	// bodyより前の "<type> <size>\0"
+	pub fn header(&self, size: usize) -> String {
+		format!("{} {}\0", self, size)
+	}
+}
+
+impl fmt::Display for ObjectKind {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", self.as_str())
+	}
+}
+
+// "<type> <size>\0<body>" を分解する
+pub fn split_header(bytes: &[u8]) -> Result<(ObjectKind, &[u8]), GitError> {
+	let invalid = |offset, message| GitError::InvalidHeader { offset, message };
+	let nul = bytes
+		.iter()
+		.position(|&b| b == b'\0')
+		.ok_or(invalid(bytes.len(), "missing NUL after header"))?;
+	let header = std::str::from_utf8(&bytes[..nul]).map_err(|e| GitError::Utf8 {
+		field: "object header",
+		offset: e.valid_up_to(),
+	})?;
+	let (kind, size) = header
+		.split_once(' ')
+		.ok_or(invalid(0, "missing space after type"))?;
+
+	let kind = ObjectKind::from(kind).ok_or_else(|| GitError::UnknownType {
+		offset: 0,
+		name: String::from(kind),
+	})?;
+	let body = &bytes[nul + 1..];
+	if size.parse::<usize>().ok() != Some(body.len()) {
+		return Err(invalid(kind.as_str().len() + 1, "size does not match"));
+	}
+
+	Ok((kind, body))
+}
+
+// commitやtagのheader部分を (行の位置, key, value) に分ける。valueの位置はkeyと空白の分だけ後ろになる
+// 空白で始まる行は前のheaderの続き(gpgsigのような複数行の値)
+fn split_headers(header: &str) -> Vec<(usize, &str, String)> {
+	let mut headers: Vec<(usize, &str, String)> = Vec::new();
+
+	let mut offset = 0;
+	for line in header.split_inclusive('\n') {
+		let start = offset;
+		offset += line.len();
+		let line = line.strip_suffix('\n').unwrap_or(line);
+		match (line.strip_prefix(' '), headers.last_mut()) {
+			(Some(rest), Some((_, _, value))) => {
+				value.push('\n');
+				value.push_str(rest);
+			}
+			_ => {
+				let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+				headers.push((start, key, String::from(value)));
+			}
+		}
+	}
+	headers
+}
+
+// byte列全体をUTF-8として読む
+fn utf8(bytes: &[u8], field: &'static str) -> Result<String, GitError> {
+	String::from_utf8(bytes.to_vec()).map_err(|e| GitError::Utf8 {
+		field,
+		offset: e.utf8_error().valid_up_to(),
+	})
+}
@@ -0,0 +1,64 @@
+use std::fmt;
+
+use super::commit::User;
+use super::{split_headers, utf8, ObjectKind};
+use crate::error::GitError;
+
+pub struct Tag {
+	pub object: String,
+	pub kind: ObjectKind,
+	pub tag: String,
+	pub tagger: Option<User>, // 古いtagにはtaggerが無いことがある
+	pub message: String,
+}
+
+impl Tag {
+	pub fn from(bytes: &[u8]) -> Result<Self, GitError> {
+		let content = utf8(bytes, "tag")?;
+
+		// messageが無い場合は空行も無い
+		let (header, message) = content.split_once("\n\n").unwrap_or((&content, ""));
+		let mut iter = split_headers(header).into_iter();
+
+		// headerはこの順に並ぶ。(valueの位置, value)
+		let mut next = |name: &str, message: &'static str| match iter.next() {
+			Some((offset, key, value)) if key == name => Ok((offset + key.len() + 1, value)),
+			Some((offset, _, _)) => Err(GitError::InvalidHeader { offset, message }),
+			None => Err(GitError::InvalidHeader {
+				offset: header.len(),
+				message,
+			}),
+		};
+
+		let (_, object) = next("object", "missing object")?;
+		let (offset, kind) = next("type", "missing type")?;
+		let kind = ObjectKind::from(&kind).ok_or(GitError::UnknownType { offset, name: kind })?;
+		let (_, tag) = next("tag", "missing tag")?;
+		let tagger = match next("tagger", "missing tagger") {
+			Ok((offset, x)) => Some(User::from(x.as_bytes()).map_err(|e| e.offset_by(offset))?),
+			Err(_) => None,
+		};
+
+		Ok(Self {
+			object,
+			kind,
+			tag,
+			tagger,
+			message: String::from(message),
+		})
+	}
+
+	pub fn as_bytes(&self) -> Vec<u8> {
+		ObjectKind::Tag.encode(self.to_string().as_bytes())
+	}
+}
+
+impl fmt::Display for Tag {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		writeln!(f, "object {}\ntype {}\ntag {}", self.object, self.kind, self.tag)?;
+		if let Some(tagger) = &self.tagger {
+			writeln!(f, "tagger {}", tagger)?;
+		}
+		write!(f, "\n{}", self.message)
+	}
+}
@@ -0,0 +1,191 @@
+use super::ObjectKind;
+use crate::error::GitError;
+use crate::hash::HashAlgorithm;
+
+#[derive(Default)]
+pub struct Tree {
+	pub contents: Vec<File>,
+}
+
+pub struct File {
+	pub mode: usize,
+	pub name: String,
+	pub hash: Vec<u8>,
+}
+
+impl File {
+	pub fn new(mode: usize, name: String, hash: &[u8]) -> Self {
+		Self {
+			mode,
+			name,
+			hash: hash.to_vec(),
+		}
+	}
+
+	// headerは "<mode> <name>"。Errのoffsetはheaderの先頭からの位置
+	pub fn from(header: &[u8], hash: &[u8]) -> Result<Self, GitError> {
+		// nameには空白が含まれることがあるので最初の空白だけで分ける
+		let space = header
+			.iter()
+			.position(|&b| b == b' ')
+			.ok_or(GitError::BadMode { offset: 0 })?;
+		let mode = std::str::from_utf8(&header[..space])
+			.ok()
+			.filter(|x| !x.is_empty() && x.bytes().all(|b| b.is_ascii_digit()))
+			.and_then(|x| x.parse::<usize>().ok())
+			.ok_or(GitError::BadMode { offset: 0 })?;
+		let name = String::from_utf8(header[space + 1..].to_vec()).map_err(|e| GitError::Utf8 {
+			field: "tree entry name",
+			offset: space + 1 + e.utf8_error().valid_up_to(),
+		})?;
+
+		Ok(Self::new(mode, name, hash))
+	}
+
+	pub fn encode(&self) -> Vec<u8> {
+		let header = format!("{} {}\0", self.mode, self.name);
+		[header.as_bytes(), &self.hash].concat()
+	}
+
+	// treeのmodeの表記(100644)を数値としてのmode(0o100644)に直す
+	pub fn mode_bits(&self) -> u32 {
+		u32::from_str_radix(&self.mode.to_string(), 8).unwrap_or(0o100644)
+	}
+
+	pub fn is_tree(&self) -> bool {
+		self.mode == 40000
+	}
+
+	// entryが指すobjectの種類。submoduleはそのcommit
+	pub fn kind(&self) -> ObjectKind {
+		match self.mode {
+			40000 => ObjectKind::Tree,
+			160000 => ObjectKind::Commit,
+			_ => ObjectKind::Blob,
+		}
+	}
+
+	// treeの中ではディレクトリは名前の後ろに"/"があるものとして並べる
+	fn sort_key(&self) -> String {
+		match self.is_tree() {
+			true => format!("{}/", self.name),
+			false => self.name.clone(),
+		}
+	}
+}
+
+// 数値としてのmode(0o100644)をtreeでの表記(100644)に直す
+pub fn tree_mode(bits: u32) -> usize {
+	format!("{:o}", bits).parse().unwrap_or(100644)
+}
+
+impl Tree {
+	// entryのhashの長さはrepositoryのhashによる
+	pub fn from(bytes: &[u8], algo: HashAlgorithm) -> Result<Self, GitError> {
+		let len = algo.hash_len();
+		let mut contents: Vec<File> = Vec::new();
+		let mut rest = bytes;
+
+		// entryは "<mode> <name>\0<hash>" の繰り返し
+		// hashの中にも\0が現れうるので、splitではなく先頭から順に読む
+		while !rest.is_empty() {
+			let offset = bytes.len() - rest.len();
+			let truncated = GitError::TruncatedEntry { offset };
+			let nul = rest.iter().position(|&b| b == b'\0').ok_or(truncated)?;
+			let (header, body) = rest.split_at(nul);
+			if body.len() < len + 1 {
+				return Err(GitError::TruncatedEntry { offset });
+			}
+			let (hash, next) = body[1..].split_at(len);
+
+			contents.push(File::from(header, hash).map_err(|e| e.offset_by(offset))?);
+			rest = next;
+		}
+		Ok(Self { contents })
+	}
+
+	// 同じ名前のentryがあれば置き換え、gitの順に並ぶ位置に入れる
+	pub fn insert(&mut self, mode: usize, name: String, hash: &[u8]) {
+		self.remove(&name);
+		let file = File::new(mode, name, hash);
+		let key = file.sort_key();
+		let at = self.contents.partition_point(|x| x.sort_key() < key);
+		self.contents.insert(at, file);
+	}
+
+	pub fn remove(&mut self, name: &str) -> Option<File> {
+		let at = self.contents.iter().position(|x| x.name == name)?;
+		Some(self.contents.remove(at))
+	}
+
+	pub fn as_bytes(&self) -> Vec<u8> {
+		let content: Vec<u8> = self.contents.iter().flat_map(|x| x.encode()).collect(); // flat_mapにわたる値がiterator(この場合にmapは使えない)
+		let header = format!("tree {}\0", content.len());
+
+		[header.as_bytes(), content.as_slice()].concat()
+	}
+
+	pub fn calc_hash(&self, algo: HashAlgorithm) -> Vec<u8> {
+		algo.digest(&self.as_bytes())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::hash::Hash;
+
+	const EMPTY_BLOB: &str = "e69de29bb2d1d6434b8b29ae775ad8c2e48c5391";
+	const EMPTY_TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+	fn hash(hex: &str) -> Vec<u8> {
+		Hash::from_hex(hex).unwrap().0
+	}
+
+	fn names(tree: &Tree) -> Vec<&str> {
+		tree.contents.iter().map(|x| x.name.as_str()).collect()
+	}
+
+	#[test]
+	fn insert_sorts() {
+		let mut tree = Tree::default();
+		tree.insert(100644, String::from("a.txt"), &hash(EMPTY_BLOB));
+		tree.insert(40000, String::from("a"), &hash(EMPTY_TREE));
+		tree.insert(100644, String::from("a0"), &hash(EMPTY_BLOB));
+		tree.insert(100755, String::from("a-b"), &hash(EMPTY_BLOB));
+		// ディレクトリのaは "a/" として "a.txt" と "a0" の間に並ぶ
+		assert_eq!(names(&tree), ["a-b", "a.txt", "a", "a0"]);
+		// git mktreeで同じものを作ったhash
+		assert_eq!(
+			Hash(tree.calc_hash(HashAlgorithm::Sha1)).to_hex(),
+			"0fa0a591b27c329c2a93dd0443fab63cac5e806f"
+		);
+		let bytes = tree.as_bytes();
+		let nul = bytes.iter().position(|&b| b == b'\0').unwrap();
+		let parsed = Tree::from(&bytes[nul + 1..], HashAlgorithm::Sha1).unwrap();
+		assert_eq!(names(&parsed), names(&tree));
+	}
+
+	#[test]
+	fn insert_replaces() {
+		let mut tree = Tree::default();
+		tree.insert(100644, String::from("a"), &hash(EMPTY_BLOB));
+		tree.insert(100644, String::from("b"), &hash(EMPTY_BLOB));
+		// 同じ名前ならmodeが変わっても1つのまま
+		tree.insert(40000, String::from("a"), &hash(EMPTY_TREE));
+		assert_eq!(names(&tree), ["a", "b"]);
+		assert!(tree.contents[0].is_tree());
+		assert_eq!(tree.contents[0].hash, hash(EMPTY_TREE));
+	}
+
+	#[test]
+	fn remove() {
+		let mut tree = Tree::default();
+		tree.insert(100644, String::from("a"), &hash(EMPTY_BLOB));
+		tree.insert(100644, String::from("b"), &hash(EMPTY_BLOB));
+		let removed = tree.remove("a").unwrap();
+		assert_eq!(removed.name, "a");
+		assert_eq!(names(&tree), ["b"]);
+		assert!(tree.remove("a").is_none());
+	}
+}
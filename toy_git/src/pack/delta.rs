@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+// packのdeltaは "<baseのsize><結果のsize>" の後にcopyとinsertの命令が並ぶ
+
+// baseをこの長さのblockに区切って、targetの中で同じblockを探す
+const BLOCK: usize = 16;
+const MAX_COPY: usize = 0xffffff; // copy命令のsizeは3byteまで
+const MAX_INSERT: usize = 0x7f;
+
+// 7bitずつ下位から並んだ可変長の整数
+pub fn read_size(delta: &[u8], pos: &mut usize) -> Option<usize> {
+	let mut size = 0;
+	let mut shift = 0;
+	loop {
+		let byte = *delta.get(*pos)?;
+		*pos += 1;
+		size |= ((byte & 0x7f) as usize) << shift;
+		shift += 7;
+		if byte & 0x80 == 0 {
+			return Some(size);
+		}
+	}
+}
+
+// baseにdeltaを適用する。deltaが壊れていればNone
+pub fn apply(base: &[u8], delta: &[u8]) -> Option<Vec<u8>> {
+	let mut pos = 0;
+	if read_size(delta, &mut pos)? != base.len() {
+		return None;
+	}
+	let size = read_size(delta, &mut pos)?;
+	let mut out = Vec::with_capacity(size);
+
+	while pos < delta.len() {
+		let cmd = delta[pos];
+		pos += 1;
+
+		if cmd & 0x80 != 0 {
+			// 下位4bitがoffset、次の3bitがsizeのどのbyteが続くかを表す
+			let mut offset = 0;
+			let mut len = 0;
+			for i in 0..4 {
+				if cmd & (1 << i) != 0 {
+					offset |= (*delta.get(pos)? as usize) << (8 * i);
+					pos += 1;
+				}
+			}
+			for i in 0..3 {
+				if cmd & (0x10 << i) != 0 {
+					len |= (*delta.get(pos)? as usize) << (8 * i);
+					pos += 1;
+				}
+			}
+			if len == 0 {
+				len = 0x10000;
+			}
+			out.extend_from_slice(base.get(offset..offset.checked_add(len)?)?);
+		} else if cmd != 0 {
+			// cmd byteの内容をそのまま挿入する
+			let len = cmd as usize;
+			out.extend_from_slice(delta.get(pos..pos + len)?);
+			pos += len;
+		} else {
+			return None;
+		}
+	}
+
+	match out.len() == size {
+		true => Some(out),
+		false => None,
+	}
+}
+
+fn write_size(out: &mut Vec<u8>, mut size: usize) {
+	while size >= 0x80 {
+		out.push((size & 0x7f) as u8 | 0x80);
+		size >>= 7;
+	}
+	out.push(size as u8);
+}
+
+// baseからtargetを作るdeltaを求める
+// targetの各位置でbaseのblockと一致するところを探し、一致すればcopy、しなければinsertにする
+pub fn create(base: &[u8], target: &[u8]) -> Vec<u8> {
+	let mut out = Vec::new();
+	write_size(&mut out, base.len());
+	write_size(&mut out, target.len());
+
+	let mut blocks: HashMap<&[u8], usize> = HashMap::new();
+	for offset in (0..base.len().saturating_sub(BLOCK - 1)).step_by(BLOCK) {
+		blocks
+			.entry(&base[offset..offset + BLOCK])
+			.or_insert(offset);
+	}
+
+	let mut insert: Vec<u8> = Vec::new();
+	let mut pos = 0;
+	while pos < target.len() {
+		let found = target
+			.get(pos..pos + BLOCK)
+			.and_then(|x| blocks.get(x))
+			.copied();
+		let Some(offset) = found else {
+			insert.push(target[pos]);
+			pos += 1;
+			continue;
+		};
+
+		// 一致を前後に伸ばす。前はまだ書いていないinsertの分だけ戻れる
+		let mut start = offset;
+		let mut from = pos;
+		while start > 0 && !insert.is_empty() && base[start - 1] == target[from - 1] {
+			insert.pop();
+			start -= 1;
+			from -= 1;
+		}
+		let mut end = offset + BLOCK;
+		pos += BLOCK;
+		while end < base.len() && pos < target.len() && base[end] == target[pos] {
+			end += 1;
+			pos += 1;
+		}
+
+		flush_insert(&mut out, &mut insert);
+		while start < end {
+			let len = (end - start).min(MAX_COPY);
+			write_copy(&mut out, start, len);
+			start += len;
+		}
+	}
+	flush_insert(&mut out, &mut insert);
+	out
+}
+
+// insert命令は1つで127byteまで
+fn flush_insert(out: &mut Vec<u8>, insert: &mut Vec<u8>) {
+	for chunk in insert.chunks(MAX_INSERT) {
+		out.push(chunk.len() as u8);
+		out.extend_from_slice(chunk);
+	}
+	insert.clear();
+}
+
+// 0でないbyteだけを書き、どのbyteを書いたかをcmd byteで表す
+fn write_copy(out: &mut Vec<u8>, offset: usize, len: usize) {
+	let mut cmd = 0x80;
+	let mut bytes = Vec::new();
+	for i in 0..4 {
+		let byte = (offset >> (8 * i)) as u8;
+		if byte != 0 {
+			cmd |= 1 << i;
+			bytes.push(byte);
+		}
+	}
+	for i in 0..3 {
+		let byte = (len >> (8 * i)) as u8;
+		if byte != 0 {
+			cmd |= 0x10 << i;
+			bytes.push(byte);
+		}
+	}
+	out.push(cmd);
+	out.extend_from_slice(&bytes);
+}
@@ -0,0 +1,118 @@
+use crate::bytes::Bytes;
+use crate::error::GitError;
+use crate::hash::HashAlgorithm;
+
+// .idx (version 2) の内容
+// "\377tOc", version, fanout, hash, crc32, offset, 64bit offset, packのchecksum, idxのchecksum
+// hashとchecksumの長さはrepositoryのhashの種類で決まる
+pub struct PackIndex {
+	pub entries: Vec<IndexEntry>, // hash順
+	pub pack_checksum: Vec<u8>,
+}
+
+pub struct IndexEntry {
+	pub hash: Vec<u8>,
+	pub crc: u32,
+	pub offset: u64,
+}
+
+const MAGIC: &[u8] = b"\xfftOc";
+
+impl PackIndex {
+	pub fn from(bytes: &[u8], algo: HashAlgorithm) -> Result<Self, GitError> {
+		let len = algo.hash_len();
+		let bytes = Bytes::new(bytes);
+		if bytes.get(0, 4)? != MAGIC {
+			return Err(bytes.error(0, "bad pack index signature"));
+		}
+		if bytes.u32(4)? != 2 {
+			return Err(bytes.error(4, "unsupported pack index version"));
+		}
+		if bytes.len() < 8 + 256 * 4 + len * 2 {
+			return Err(bytes.error(bytes.len(), "pack index is too short"));
+		}
+
+		let (body, checksum) = bytes.data.split_at(bytes.len() - len);
+		if algo.digest(body) != checksum {
+			return Err(bytes.error(body.len(), "pack index checksum mismatch"));
+		}
+
+		let n = bytes.u32(8 + 255 * 4)? as usize;
+		let hashes = 8 + 256 * 4;
+		let crcs = hashes + n * len;
+		let offsets = crcs + n * 4;
+		let large = offsets + n * 4;
+
+		let mut entries = Vec::with_capacity(n);
+		for i in 0..n {
+			let hash = bytes.get(hashes + i * len, len)?.to_vec();
+			let crc = bytes.u32(crcs + i * 4)?;
+			let offset = match bytes.u32(offsets + i * 4)? {
+				// 最上位bitが立っていれば64bit offsetの表の位置
+				x if x & 0x8000_0000 != 0 => bytes.u64(large + (x & 0x7fff_ffff) as usize * 8)?,
+				x => x as u64,
+			};
+			entries.push(IndexEntry { hash, crc, offset });
+		}
+
+		let pack_checksum = bytes.get(bytes.len() - len * 2, len)?.to_vec();
+		Ok(Self {
+			entries,
+			pack_checksum,
+		})
+	}
+
+	pub fn as_bytes(&self, algo: HashAlgorithm) -> Vec<u8> {
+		let mut entries: Vec<&IndexEntry> = self.entries.iter().collect();
+		entries.sort_by(|a, b| a.hash.cmp(&b.hash));
+
+		let mut out = Vec::from(MAGIC);
+		out.extend_from_slice(&2u32.to_be_bytes());
+
+		// fanout[i]は先頭byteがi以下のobjectの数
+		let mut fanout = [0u32; 256];
+		for entry in entries.iter() {
+			fanout[entry.hash[0] as usize] += 1;
+		}
+		let mut total = 0;
+		for count in fanout.iter() {
+			total += count;
+			out.extend_from_slice(&total.to_be_bytes());
+		}
+
+		for entry in entries.iter() {
+			out.extend_from_slice(&entry.hash);
+		}
+		for entry in entries.iter() {
+			out.extend_from_slice(&entry.crc.to_be_bytes());
+		}
+
+		let mut large = Vec::new();
+		for entry in entries.iter() {
+			let offset = match u32::try_from(entry.offset) {
+				Ok(x) if x & 0x8000_0000 == 0 => x,
+				_ => {
+					large.push(entry.offset);
+					0x8000_0000 | (large.len() - 1) as u32
+				}
+			};
+			out.extend_from_slice(&offset.to_be_bytes());
+		}
+		for offset in large {
+			out.extend_from_slice(&offset.to_be_bytes());
+		}
+
+		out.extend_from_slice(&self.pack_checksum);
+		let checksum = algo.digest(&out);
+		out.extend_from_slice(&checksum);
+		out
+	}
+
+	// entriesはhash順に並んでいるので二分探索する
+	pub fn find(&self, hash: &[u8]) -> Option<u64> {
+		self.entries
+			.binary_search_by(|x| x.hash.as_slice().cmp(hash))
+			.ok()
+			.map(|i| self.entries[i].offset)
+	}
+}
@@ -0,0 +1,155 @@
+use sha1::{Digest, Sha1};
+
+use crate::bytes::{Bytes, Chunks};
+use crate::error::GitError;
+
+// objects/pack/multi-pack-index (version 1) の内容
+// "MIDX", version, hashのversion, chunkの数, base midxの数, packの数, chunkの表, chunk, checksum
+// chunkはPNAM(.idxの名前)、OIDF(fanout)、OIDL(hash)、OOFF(packの番号とoffset)、LOFF(64bit offset)
+pub struct MultiPackIndex {
+	pub packs: Vec<String>,      // "pack-<checksum>.idx" の名前順
+	pub entries: Vec<MidxEntry>, // hash順
+}
+
+pub struct MidxEntry {
+	pub hash: Vec<u8>,
+	pub pack: u32, // packsでの位置
+	pub offset: u64,
+}
+
+const MAGIC: &[u8] = b"MIDX";
+const PACK_NAMES: &[u8] = b"PNAM";
+const FANOUT: &[u8] = b"OIDF";
+const OIDS: &[u8] = b"OIDL";
+const OFFSETS: &[u8] = b"OOFF";
+const LARGE_OFFSETS: &[u8] = b"LOFF";
+
+impl MultiPackIndex {
+	pub fn from(bytes: &[u8]) -> Result<Self, GitError> {
+		let bytes = Bytes::new(bytes);
+		if bytes.get(0, 4)? != MAGIC {
+			return Err(bytes.error(0, "bad multi-pack-index signature"));
+		}
+		if bytes.u8(4)? != 1 {
+			return Err(bytes.error(4, "unsupported multi-pack-index version"));
+		}
+		if bytes.u8(5)? != 1 {
+			return Err(bytes.error(5, "unsupported multi-pack-index hash version"));
+		}
+		if bytes.len() < 12 + 20 {
+			return Err(bytes.error(bytes.len(), "multi-pack-index is too short"));
+		}
+		let body = bytes.slice(0, bytes.len() - 20)?;
+		if Sha1::digest(body.data).to_vec() != bytes.data[body.len()..] {
+			return Err(bytes.error(body.len(), "multi-pack-index checksum mismatch"));
+		}
+
+		let chunks = Chunks::read(body, 12, bytes.u8(6)? as usize)?;
+		let count = bytes.u32(8)? as usize;
+		let names = chunks.require(PACK_NAMES)?;
+		let packs: Vec<String> = names
+			.data
+			.split(|x| *x == 0)
+			.filter(|x| !x.is_empty())
+			.take(count)
+			.map(|x| String::from_utf8_lossy(x).into_owned())
+			.collect();
+		if packs.len() != count {
+			return Err(names.error(0, "too few pack names"));
+		}
+
+		let n = chunks.require(FANOUT)?.u32(255 * 4)? as usize;
+		let oids = chunks.require(OIDS)?;
+		let offsets = chunks.require(OFFSETS)?;
+		let large = chunks.find(LARGE_OFFSETS)?.unwrap_or(Bytes::new(&[]));
+		let mut entries = Vec::with_capacity(n);
+		for i in 0..n {
+			let hash = oids.get(i * 20, 20)?.to_vec();
+			let pack = offsets.u32(i * 8)?;
+			let offset = match offsets.u32(i * 8 + 4)? {
+				// 最上位bitが立っていればLOFFの位置
+				x if x & 0x8000_0000 != 0 => large.u64((x & 0x7fff_ffff) as usize * 8)?,
+				x => x as u64,
+			};
+			if pack as usize >= count {
+				return Err(offsets.error(i * 8, "bad pack position"));
+			}
+			entries.push(MidxEntry { hash, pack, offset });
+		}
+		Ok(Self { packs, entries })
+	}
+
+	pub fn as_bytes(&self) -> Vec<u8> {
+		let mut entries: Vec<&MidxEntry> = self.entries.iter().collect();
+		entries.sort_by(|a, b| a.hash.cmp(&b.hash));
+
+		// packの名前は "\0" で区切り、4byteの倍数になるまで埋める
+		let mut names = Vec::new();
+		for name in self.packs.iter() {
+			names.extend_from_slice(name.as_bytes());
+			names.push(0);
+		}
+		names.resize(names.len().div_ceil(4) * 4, 0);
+
+		let mut fanout = Vec::new();
+		let mut total = 0u32;
+		for i in 0..=255u8 {
+			total += entries.iter().filter(|x| x.hash[0] == i).count() as u32;
+			fanout.extend_from_slice(&total.to_be_bytes());
+		}
+
+		let oids: Vec<u8> = entries.iter().flat_map(|x| x.hash.clone()).collect();
+		let mut offsets = Vec::new();
+		let mut large = Vec::new();
+		for entry in entries.iter() {
+			offsets.extend_from_slice(&entry.pack.to_be_bytes());
+			let offset = match u32::try_from(entry.offset) {
+				Ok(x) if x & 0x8000_0000 == 0 => x,
+				_ => {
+					large.extend_from_slice(&entry.offset.to_be_bytes());
+					0x8000_0000 | (large.len() / 8 - 1) as u32
+				}
+			};
+			offsets.extend_from_slice(&offset.to_be_bytes());
+		}
+
+		let mut chunks = vec![
+			(PACK_NAMES, names),
+			(FANOUT, fanout),
+			(OIDS, oids),
+			(OFFSETS, offsets),
+		];
+		if !large.is_empty() {
+			chunks.push((LARGE_OFFSETS, large));
+		}
+
+		let mut out = Vec::from(MAGIC);
+		out.extend_from_slice(&[1, 1, chunks.len() as u8, 0]);
+		out.extend_from_slice(&(self.packs.len() as u32).to_be_bytes());
+		let mut offset = (out.len() + (chunks.len() + 1) * 12) as u64;
+		for (id, chunk) in chunks.iter() {
+			out.extend_from_slice(id);
+			out.extend_from_slice(&offset.to_be_bytes());
+			offset += chunk.len() as u64;
+		}
+		out.extend_from_slice(&[0; 4]);
+		out.extend_from_slice(&offset.to_be_bytes());
+		for (_, chunk) in chunks {
+			out.extend_from_slice(&chunk);
+		}
+
+		let checksum = Sha1::digest(&out).to_vec();
+		out.extend_from_slice(&checksum);
+		out
+	}
+
+	// entriesはhash順に並んでいるので二分探索する
+	pub fn find(&self, hash: &[u8]) -> Option<(&str, u64)> {
+		let i = self
+			.entries
+			.binary_search_by(|x| x.hash.as_slice().cmp(hash))
+			.ok()?;
+		let entry = &self.entries[i];
+		Some((&self.packs[entry.pack as usize], entry.offset))
+	}
+}
@@ -0,0 +1,327 @@
+mod delta;
+mod idx;
+mod midx;
+mod write;
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use libflate::zlib::Decoder;
+
+use crate::hash::HashAlgorithm;
+use crate::object::ObjectKind;
+use crate::repository::Repository;
+use crate::store;
+
+pub use idx::PackIndex;
+pub use midx::{MidxEntry, MultiPackIndex};
+pub use write::{index_pack, index_pack_to, write, write_thin};
+
+// packの中でのobjectのtype番号。1から4は通常のobject
+const OFS_DELTA: u8 = 6;
+const REF_DELTA: u8 = 7;
+
+fn kind_from(n: u8) -> Option<ObjectKind> {
+	match n {
+		1 => Some(ObjectKind::Commit),
+		2 => Some(ObjectKind::Tree),
+		3 => Some(ObjectKind::Blob),
+		4 => Some(ObjectKind::Tag),
+		_ => None,
+	}
+}
+
+fn kind_number(kind: ObjectKind) -> u8 {
+	match kind {
+		ObjectKind::Commit => 1,
+		ObjectKind::Tree => 2,
+		ObjectKind::Blob => 3,
+		ObjectKind::Tag => 4,
+	}
+}
+
+// deltaのbase
+enum Base {
+	None,
+	Offset(u64),   // OFS_DELTA: pack内のoffset
+	Hash(Vec<u8>), // REF_DELTA: objectのhash
+}
+
+// 各objectの先頭にあるtypeと展開後のsize
+struct Header {
+	kind: u8,
+	size: usize,
+	base: Base,
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> io::Result<u8> {
+	let mut buf = [0; 1];
+	reader.read_exact(&mut buf)?;
+	Ok(buf[0])
+}
+
+// 1byte目は "継続bit, type(3bit), sizeの下位4bit"、以降は7bitずつsizeが続く
+// REF_DELTAならその後にhash_len byteのbaseのhashが続く
+fn read_header<R: Read>(reader: &mut R, offset: u64, hash_len: usize) -> io::Result<Header> {
+	let mut byte = read_u8(reader)?;
+	let kind = (byte >> 4) & 0x7;
+	let mut size = (byte & 0xf) as usize;
+	let mut shift = 4;
+	while byte & 0x80 != 0 {
+		byte = read_u8(reader)?;
+		size |= ((byte & 0x7f) as usize) << shift;
+		shift += 7;
+	}
+
+	let base = match kind {
+		// baseまでの距離。続くbyteごとに1を足してからshiftする
+		OFS_DELTA => {
+			let mut byte = read_u8(reader)?;
+			let mut distance = (byte & 0x7f) as u64;
+			while byte & 0x80 != 0 {
+				byte = read_u8(reader)?;
+				distance = ((distance + 1) << 7) | (byte & 0x7f) as u64;
+			}
+			let base = offset
+				.checked_sub(distance)
+				.ok_or_else(|| corrupt(format!("bad delta base offset at {}", offset)))?;
+			Base::Offset(base)
+		}
+		REF_DELTA => {
+			let mut hash = vec![0; hash_len];
+			reader.read_exact(&mut hash)?;
+			Base::Hash(hash)
+		}
+		_ => Base::None,
+	};
+
+	Ok(Header { kind, size, base })
+}
+
+fn inflate<R: Read>(reader: R, size: usize) -> io::Result<Vec<u8>> {
+	let mut data = Vec::with_capacity(size);
+	Decoder::new(reader)?.read_to_end(&mut data)?;
+	match data.len() == size {
+		true => Ok(data),
+		false => Err(corrupt(String::from("packed object size mismatch"))),
+	}
+}
+
+// deltaの先頭の "<baseのsize><結果のsize>" だけを展開して結果のsizeを読む
+// 可変長の整数は1つ10byteまでなので20byteあれば足りる
+fn delta_size<R: Read>(reader: R) -> io::Result<usize> {
+	let mut data = Vec::new();
+	Decoder::new(reader)?.take(20).read_to_end(&mut data)?;
+
+	let mut pos = 0;
+	delta::read_size(&data, &mut pos)
+		.and_then(|_| delta::read_size(&data, &mut pos))
+		.ok_or_else(|| corrupt(String::from("bad delta header")))
+}
+
+fn corrupt(message: String) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// offsetにあるobjectを読む。deltaの場合はbaseを読んでから適用する
+// REF_DELTAのbaseはlookupで探す
+fn read_at<R, F>(
+	reader: &mut R,
+	offset: u64,
+	hash_len: usize,
+	lookup: &F,
+) -> io::Result<(ObjectKind, Vec<u8>)>
+where
+	R: Read + Seek,
+	F: Fn(&[u8]) -> io::Result<(ObjectKind, Vec<u8>)>,
+{
+	reader.seek(SeekFrom::Start(offset))?;
+	let header = read_header(reader, offset, hash_len)?;
+	let data = inflate(&mut *reader, header.size)?;
+
+	let (kind, base) = match header.base {
+		Base::None => {
+			let kind = kind_from(header.kind).ok_or_else(|| {
+				corrupt(format!("unknown object type {} at {}", header.kind, offset))
+			})?;
+			return Ok((kind, data));
+		}
+		Base::Offset(base) => read_at(reader, base, hash_len, lookup)?,
+		Base::Hash(hash) => lookup(&hash)?,
+	};
+
+	let body =
+		delta::apply(&base, &data).ok_or_else(|| corrupt(format!("bad delta at {}", offset)))?;
+	Ok((kind, body))
+}
+
+// .packと対応する.idx
+pub struct Pack {
+	pub path: PathBuf, // .packのpath
+	pub index: PackIndex,
+}
+
+impl Pack {
+	pub fn open(idx: &Path, algo: HashAlgorithm) -> io::Result<Self> {
+		let index = PackIndex::from(&fs::read(idx)?, algo)
+			.map_err(|e| e.context(format!("{}: bad pack index", idx.display())))?;
+
+		Ok(Self {
+			path: idx.with_extension("pack"),
+			index,
+		})
+	}
+
+	pub fn read(&self, repo: &Repository, offset: u64) -> io::Result<(ObjectKind, Vec<u8>)> {
+		let mut reader = BufReader::new(File::open(&self.path)?);
+
+		// REF_DELTAのbaseはまず同じpackから探す
+		read_at(
+			&mut reader,
+			offset,
+			repo.hash_algo.hash_len(),
+			&|hash: &[u8]| match self.index.find(hash) {
+				Some(offset) => self.read(repo, offset),
+				None => store::read_original(repo, hash),
+			},
+		)
+	}
+}
+
+impl Pack {
+	// 内容を展開せずにtypeとsizeを読む
+	// deltaならsizeはdeltaの先頭から、typeはbaseを辿って読む
+	pub fn read_header(&self, repo: &Repository, offset: u64) -> io::Result<(ObjectKind, usize)> {
+		let mut reader = BufReader::new(File::open(&self.path)?);
+		self.header_at(repo, &mut reader, offset)
+	}
+
+	fn header_at<R: Read + Seek>(
+		&self,
+		repo: &Repository,
+		reader: &mut R,
+		offset: u64,
+	) -> io::Result<(ObjectKind, usize)> {
+		reader.seek(SeekFrom::Start(offset))?;
+		let header = read_header(reader, offset, repo.hash_algo.hash_len())?;
+		let size = match header.base {
+			Base::None => header.size,
+			_ => delta_size(&mut *reader)?,
+		};
+
+		let kind = match header.base {
+			Base::None => kind_from(header.kind).ok_or_else(|| {
+				corrupt(format!("unknown object type {} at {}", header.kind, offset))
+			})?,
+			Base::Offset(base) => self.header_at(repo, reader, base)?.0,
+			Base::Hash(hash) => match self.index.find(&hash) {
+				Some(base) => self.header_at(repo, reader, base)?.0,
+				None => store::read_original_header(repo, &hash)?.0,
+			},
+		};
+		Ok((kind, size))
+	}
+}
+
+// objects/pack にある全てのpack。gitと同じく新しいpackほど先に探す
+// (repackした後は新しいpackに全てのobjectが入っていることが多い)
+pub fn packs(repo: &Repository) -> io::Result<Vec<Pack>> {
+	pack_indexes(repo)?
+		.iter()
+		.map(|x| Pack::open(x, repo.hash_algo))
+		.collect()
+}
+
+// packsと同じ順の.idxのpath
+fn pack_indexes(repo: &Repository) -> io::Result<Vec<PathBuf>> {
+	let dir = repo.objects_dir().join("pack");
+	let mut paths = match fs::read_dir(&dir) {
+		Ok(entries) => entries
+			.map(|x| x.map(|x| x.path()))
+			.collect::<io::Result<Vec<_>>>()?,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(e) => return Err(e),
+	};
+	paths.retain(|x| x.extension().is_some_and(|x| x == "idx"));
+
+	// .packの更新時刻の新しい順。同じならpathの順
+	let mut paths = paths
+		.into_iter()
+		.map(|x| {
+			let mtime = fs::metadata(x.with_extension("pack")).and_then(|x| x.modified())?;
+			Ok((mtime, x))
+		})
+		.collect::<io::Result<Vec<_>>>()?;
+	paths.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+	Ok(paths.into_iter().map(|(_, x)| x).collect())
+}
+
+// objects/pack/multi-pack-index。無いか壊れているか、書いた後に消されたpackがあればNone
+// SHA-1でないrepositoryのものは読めないのでNoneにし、各packの.idxで探す
+pub fn multi_pack_index(repo: &Repository) -> io::Result<Option<MultiPackIndex>> {
+	if repo.hash_algo != HashAlgorithm::Sha1 {
+		return Ok(None);
+	}
+	let dir = repo.objects_dir().join("pack");
+	let bytes = match fs::read(dir.join("multi-pack-index")) {
+		Ok(bytes) => bytes,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+		Err(e) => return Err(e),
+	};
+	Ok(MultiPackIndex::from(&bytes).ok().filter(|midx| {
+		midx.packs.iter().all(|x| {
+			let idx = dir.join(x);
+			idx.exists() && idx.with_extension("pack").exists()
+		})
+	}))
+}
+
+// hashのobjectが入っているpackとそのoffset
+// multi-pack-indexがあればそれで探し、そこに含まれないpackだけ.idxを読んで探す
+fn locate(repo: &Repository, hash: &[u8]) -> io::Result<Option<(Pack, u64)>> {
+	let dir = repo.objects_dir().join("pack");
+	let midx = multi_pack_index(repo)?;
+	if let Some((name, offset)) = midx.as_ref().and_then(|x| x.find(hash)) {
+		return Ok(Some((Pack::open(&dir.join(name), repo.hash_algo)?, offset)));
+	}
+
+	for path in pack_indexes(repo)? {
+		let covered = midx.as_ref().is_some_and(|midx| {
+			path.file_name()
+				.is_some_and(|name| midx.packs.iter().any(|x| *x == name.to_string_lossy()))
+		});
+		if covered {
+			continue;
+		}
+		let pack = Pack::open(&path, repo.hash_algo)?;
+		if let Some(offset) = pack.index.find(hash) {
+			return Ok(Some((pack, offset)));
+		}
+	}
+	Ok(None)
+}
+
+// packに入っているobjectか。中身は読まない
+pub fn contains(repo: &Repository, hash: &[u8]) -> io::Result<bool> {
+	Ok(locate(repo, hash)?.is_some())
+}
+
+// packに入っているobjectを探す。どのpackにも無ければNone
+pub fn read_object(repo: &Repository, hash: &[u8]) -> io::Result<Option<(ObjectKind, Vec<u8>)>> {
+	match locate(repo, hash)? {
+		Some((pack, offset)) => pack.read(repo, offset).map(Some),
+		None => Ok(None),
+	}
+}
+
+// packに入っているobjectのtypeとsize。どのpackにも無ければNone
+pub fn read_object_header(
+	repo: &Repository,
+	hash: &[u8],
+) -> io::Result<Option<(ObjectKind, usize)>> {
+	match locate(repo, hash)? {
+		Some((pack, offset)) => pack.read_header(repo, offset).map(Some),
+		None => Ok(None),
+	}
+}
@@ -0,0 +1,397 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Cursor, Write};
+use std::path::Path;
+
+use sha1::{Digest, Sha1};
+
+use super::idx::{IndexEntry, PackIndex};
+use super::{
+	corrupt, delta, inflate, kind_from, kind_number, read_header, Base, OFS_DELTA, REF_DELTA,
+};
+use crate::object::ObjectKind;
+use crate::progress::Progress;
+use crate::repository::Repository;
+use crate::store;
+use crate::tempfile::TempFile;
+use crate::zlib;
+
+// "<継続bit><type><sizeの下位4bit>" に続けて残りのsizeを7bitずつ書く
+fn write_header(out: &mut Vec<u8>, kind: u8, size: usize) {
+	let mut byte = (kind << 4) | (size & 0xf) as u8;
+	let mut size = size >> 4;
+	while size > 0 {
+		out.push(byte | 0x80);
+		byte = (size & 0x7f) as u8;
+		size >>= 7;
+	}
+	out.push(byte);
+}
+
+fn write_entry(out: &mut Vec<u8>, kind: ObjectKind, body: &[u8], level: u32) -> io::Result<()> {
+	write_header(out, kind_number(kind), body.len());
+	out.extend_from_slice(&zlib::compress(body, level)?);
+	Ok(())
+}
+
+// OFS_DELTAのbaseまでの距離。read_headerと逆に、上位の7bitから順に1を引きながら書く
+fn write_offset(out: &mut Vec<u8>, distance: u64) {
+	let mut bytes = vec![(distance & 0x7f) as u8];
+	let mut distance = distance >> 7;
+	while distance > 0 {
+		distance -= 1;
+		bytes.push(0x80 | (distance & 0x7f) as u8);
+		distance >>= 7;
+	}
+	bytes.reverse();
+	out.extend_from_slice(&bytes);
+}
+
+// 何個前までのobjectをbaseの候補にするか
+const WINDOW: usize = 10;
+// deltaのbaseをさらにdeltaにしてよい深さ
+const MAX_DEPTH: usize = 50;
+
+// packに書くobjectの並び。deltaならbaseはそれより前に書かれる
+struct Planned {
+	index: usize,                    // objectsの中の位置。objectsより後ろはbasesの中
+	delta: Option<(usize, Vec<u8>)>, // (baseの位置, delta)
+	depth: usize,
+}
+
+// 同じtypeのobjectを大きい順に並べ、直前のWINDOW個の中で最も小さくなるdeltaを選ぶ
+// 大きいものをbaseにすると、小さいものは主にcopyで表せる
+// basesはpackに書かないが、deltaのbaseの候補にはする
+fn plan(
+	objects: &[(ObjectKind, Vec<u8>)],
+	bases: &[(ObjectKind, Vec<u8>)],
+	progress: &mut dyn Progress,
+) -> Vec<Planned> {
+	let get = |i: usize| match i.checked_sub(objects.len()) {
+		Some(i) => &bases[i],
+		None => &objects[i],
+	};
+	// 同じ大きさならbasesを先にして、候補にできるようにする
+	let mut order: Vec<usize> = (0..objects.len() + bases.len()).collect();
+	order.sort_by_key(|&i| {
+		(
+			kind_number(get(i).0),
+			std::cmp::Reverse(get(i).1.len()),
+			i < objects.len(),
+		)
+	});
+
+	let mut planned: Vec<Planned> = Vec::with_capacity(order.len());
+	progress.start("Compressing objects", objects.len());
+	for (pos, &index) in order.iter().enumerate() {
+		let (kind, body) = get(index);
+		if index >= objects.len() {
+			planned.push(Planned {
+				index,
+				delta: None,
+				depth: 0,
+			});
+			continue;
+		}
+		progress.inc(1);
+
+		// deltaが元の半分より小さくならなければそのまま書く
+		let mut best: Option<(usize, Vec<u8>)> = None;
+		for base in (pos.saturating_sub(WINDOW)..pos).rev() {
+			let candidate = &planned[base];
+			let (base_kind, base_body) = get(candidate.index);
+			if base_kind != kind || candidate.depth >= MAX_DEPTH {
+				continue;
+			}
+			let delta = delta::create(base_body, body);
+			let limit = best.as_ref().map_or(body.len() / 2, |(_, x)| x.len());
+			if delta.len() < limit {
+				best = Some((base, delta));
+			}
+		}
+
+		let depth = best
+			.as_ref()
+			.map_or(0, |&(base, _)| planned[base].depth + 1);
+		planned.push(Planned {
+			index,
+			delta: best,
+			depth,
+		});
+	}
+	progress.finish();
+	planned
+}
+
+// "PACK", version 2, objectの数, 各object, 全体のSHA-1
+// 似たobjectは前に書いたobjectに対するOFS_DELTAにする。圧縮levelはrepoのpack.compression
+pub fn write(
+	repo: &Repository,
+	objects: &[(ObjectKind, Vec<u8>)],
+	progress: &mut dyn Progress,
+) -> io::Result<Vec<u8>> {
+	write_thin(repo, objects, &[], progress)
+}
+
+// 受け取る側が既に持っているbasesに対するdeltaも使うpack(thin pack)
+// basesはpackに書かず、それに対するdeltaはbaseのhashを書いたREF_DELTAにする
+pub fn write_thin(
+	repo: &Repository,
+	objects: &[(ObjectKind, Vec<u8>)],
+	bases: &[(ObjectKind, Vec<u8>)],
+	progress: &mut dyn Progress,
+) -> io::Result<Vec<u8>> {
+	repo.require_sha1("pack files")?;
+	let mut out = Vec::from(&b"PACK"[..]);
+	out.extend_from_slice(&2u32.to_be_bytes());
+	out.extend_from_slice(&(objects.len() as u32).to_be_bytes());
+
+	let level = zlib::level(repo, "pack.compression", zlib::PACK_DEFAULT)?;
+	let planned = plan(objects, bases, progress);
+	// plannedの位置ごとの、packの中のoffset。basesのものは書かないのでNone
+	let mut offsets = Vec::with_capacity(planned.len());
+	progress.start("Writing objects", objects.len());
+	for entry in &planned {
+		if entry.index >= objects.len() {
+			offsets.push(None);
+			continue;
+		}
+		let offset = out.len() as u64;
+		offsets.push(Some(offset));
+		progress.inc(1);
+
+		match &entry.delta {
+			Some((base, delta)) => match offsets[*base] {
+				Some(base) => {
+					write_header(&mut out, OFS_DELTA, delta.len());
+					write_offset(&mut out, offset - base);
+					out.extend_from_slice(&zlib::compress(delta, level)?);
+				}
+				None => {
+					let (kind, body) = &bases[planned[*base].index - objects.len()];
+					write_header(&mut out, REF_DELTA, delta.len());
+					out.extend_from_slice(&store::hash_bytes(repo, &kind.encode(body)));
+					out.extend_from_slice(&zlib::compress(delta, level)?);
+				}
+			},
+			None => {
+				let (kind, body) = &objects[entry.index];
+				write_entry(&mut out, *kind, body, level)?;
+			}
+		}
+	}
+	progress.finish();
+
+	let checksum = Sha1::digest(&out).to_vec();
+	out.extend_from_slice(&checksum);
+	Ok(out)
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+	let mut crc = !0u32;
+	for &b in bytes {
+		crc ^= b as u32;
+		for _ in 0..8 {
+			crc = match crc & 1 {
+				1 => (crc >> 1) ^ 0xedb8_8320,
+				_ => crc >> 1,
+			};
+		}
+	}
+	!crc
+}
+
+// 展開しただけでまだbaseを適用していないobject
+struct Raw {
+	offset: u64,
+	crc: u32,
+	kind: u8,
+	base: Base,
+	data: Vec<u8>,
+}
+
+// deltaを適用し終えたobject
+struct Resolved {
+	objects: HashMap<u64, (ObjectKind, Vec<u8>)>, // offsetごとの内容
+	offsets: HashMap<Vec<u8>, u64>,
+	entries: Vec<IndexEntry>,
+}
+
+impl Resolved {
+	fn add(&mut self, offset: u64, crc: u32, kind: ObjectKind, body: Vec<u8>) {
+		let hash = Sha1::digest(&kind.encode(&body)).to_vec();
+		self.offsets.insert(hash.clone(), offset);
+		self.entries.push(IndexEntry { hash, crc, offset });
+		self.objects.insert(offset, (kind, body));
+	}
+
+	fn base(&self, base: &Base) -> Option<&(ObjectKind, Vec<u8>)> {
+		match base {
+			Base::Offset(offset) => self.objects.get(offset),
+			Base::Hash(hash) => self.offsets.get(hash).and_then(|x| self.objects.get(x)),
+			Base::None => None,
+		}
+	}
+}
+
+// packを先頭から読んで各objectの位置とcrc32を求める
+// REF_DELTAのbaseはhash_len byteのhash
+fn scan(body: &[u8], hash_len: usize, progress: &mut dyn Progress) -> io::Result<Vec<Raw>> {
+	if body.len() < 12 || &body[..4] != b"PACK" {
+		return Err(corrupt(String::from("not a pack file")));
+	}
+	let version = u32::from_be_bytes([body[4], body[5], body[6], body[7]]);
+	if version != 2 && version != 3 {
+		return Err(corrupt(format!("pack version {} unsupported", version)));
+	}
+	let count = u32::from_be_bytes([body[8], body[9], body[10], body[11]]);
+
+	let mut reader = Cursor::new(body);
+	reader.set_position(12);
+
+	let mut raws = Vec::with_capacity(count as usize);
+	progress.start("Indexing objects", count as usize);
+	for _ in 0..count {
+		progress.inc(1);
+		let offset = reader.position();
+		let header = read_header(&mut reader, offset, hash_len)?;
+		let data = inflate(&mut reader, header.size)?;
+		let crc = crc32(&body[offset as usize..reader.position() as usize]);
+
+		raws.push(Raw {
+			offset,
+			crc,
+			kind: header.kind,
+			base: header.base,
+			data,
+		});
+	}
+	progress.finish();
+
+	if reader.position() as usize != body.len() {
+		return Err(corrupt(String::from("pack has junk at the end")));
+	}
+	Ok(raws)
+}
+
+// packを読んで.idxを作り、objects/pack/pack-<checksum>.{pack,idx}として保存する
+pub fn index_pack(
+	repo: &Repository,
+	data: &[u8],
+	progress: &mut dyn Progress,
+) -> io::Result<String> {
+	let prefix = repo.objects_dir().join("pack").join("pack");
+	index_pack_to(repo, data, &prefix, progress)
+}
+
+// packを読んで.idxを作り、<prefix>-<checksum>.{pack,idx}として保存してchecksumを返す
+// pack内に無いobjectに対するREF_DELTAがあれば(thin pack)、そのbaseをpackの末尾に加える
+pub fn index_pack_to(
+	repo: &Repository,
+	data: &[u8],
+	prefix: &Path,
+	progress: &mut dyn Progress,
+) -> io::Result<String> {
+	repo.require_sha1("pack files")?;
+	if data.len() < 32 {
+		return Err(corrupt(String::from("pack is too short")));
+	}
+	let (body, checksum) = data.split_at(data.len() - 20);
+	if Sha1::digest(body).to_vec() != checksum {
+		return Err(corrupt(String::from("pack checksum mismatch")));
+	}
+
+	let mut pending = scan(body, repo.hash_algo.hash_len(), progress)?;
+	let mut body = body.to_vec();
+
+	let mut resolved = Resolved {
+		objects: HashMap::new(),
+		offsets: HashMap::new(),
+		entries: Vec::new(),
+	};
+
+	// baseが先に求まったものから順にdeltaを適用する
+	let deltas = pending
+		.iter()
+		.filter(|x| !matches!(x.base, Base::None))
+		.count();
+	progress.start("Resolving deltas", deltas);
+	while !pending.is_empty() {
+		let count = pending.len();
+		let mut rest = Vec::new();
+
+		for raw in pending {
+			if let Base::None = raw.base {
+				let kind = kind_from(raw.kind).ok_or_else(|| {
+					corrupt(format!(
+						"unknown object type {} at {}",
+						raw.kind, raw.offset
+					))
+				})?;
+				resolved.add(raw.offset, raw.crc, kind, raw.data);
+				continue;
+			}
+
+			match resolved.base(&raw.base) {
+				Some((kind, base)) => {
+					let body = delta::apply(base, &raw.data)
+						.ok_or_else(|| corrupt(format!("bad delta at {}", raw.offset)))?;
+					resolved.add(raw.offset, raw.crc, *kind, body);
+					progress.inc(1);
+				}
+				None => rest.push(raw),
+			}
+		}
+
+		// 1つも進まなければ残りはpackの外にbaseがあるので、そのbaseをpackの末尾に加える
+		if !rest.is_empty() && rest.len() == count {
+			let missing = rest.iter().find_map(|x| match &x.base {
+				Base::Hash(hash) => Some(hash.clone()),
+				_ => None,
+			});
+			let missing = missing
+				.ok_or_else(|| corrupt(String::from("delta base offset is out of the pack")))?;
+			let (kind, base) = store::read_original(repo, &missing)?;
+
+			let offset = body.len() as u64;
+			let level = zlib::level(repo, "pack.compression", zlib::PACK_DEFAULT)?;
+			write_entry(&mut body, kind, &base, level)?;
+			let crc = crc32(&body[offset as usize..]);
+			resolved.add(offset, crc, kind, base);
+		}
+		pending = rest;
+	}
+	progress.finish();
+	let mut entries = resolved.entries;
+
+	// baseを加えた場合はobjectの数とchecksumを書き直す
+	let count = entries.len() as u32;
+	body[8..12].copy_from_slice(&count.to_be_bytes());
+	let checksum = Sha1::digest(&body).to_vec();
+	body.extend_from_slice(&checksum);
+
+	entries.sort_by(|a, b| a.hash.cmp(&b.hash));
+	let index = PackIndex {
+		entries,
+		pack_checksum: checksum.clone(),
+	};
+
+	let dir = match prefix.parent() {
+		Some(dir) if !dir.as_os_str().is_empty() => dir,
+		_ => Path::new("."),
+	};
+	fs::create_dir_all(dir)?;
+	let name = hex::encode(&checksum);
+	let base = prefix.file_name().unwrap_or_default().to_string_lossy();
+
+	// .idxがあればpackとして読まれるので、.packを先に置く
+	let mut pack = TempFile::new(dir, "tmp_pack")?;
+	pack.write_all(&body)?;
+	pack.persist(&dir.join(format!("{}-{}.pack", base, name)))?;
+
+	let mut idx = TempFile::new(dir, "tmp_idx")?;
+	idx.write_all(&index.as_bytes(repo.hash_algo))?;
+	idx.persist(&dir.join(format!("{}-{}.idx", base, name)))?;
+
+	Ok(name)
+}
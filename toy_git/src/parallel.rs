@@ -0,0 +1,68 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+
+use crate::config::Config;
+
+// gitのpreload-indexと同じく、threadは多くても20
+const MAX_THREADS: usize = 20;
+
+// ファイルを読んでhashするときに使うthreadの数
+// core.preloadIndexがfalseなら1、そうでなければCPUの数
+pub fn threads(config: &Config) -> usize {
+	if config.get_bool("core.preloadIndex") == Some(false) {
+		return 1;
+	}
+	thread::available_parallelism()
+		.map(|x| x.get())
+		.unwrap_or(1)
+		.min(MAX_THREADS)
+}
+
+// itemsのそれぞれにfを呼んだ結果をitemsと同じ順に返す
+// 各threadが次のitemを1つずつ取っていき、どれかがErrorになれば残りはやめてそのErrorを返す
+pub fn map<T, R, F>(threads: usize, items: &[T], f: F) -> io::Result<Vec<R>>
+where
+	T: Sync,
+	R: Send,
+	F: Fn(&T) -> io::Result<R> + Sync,
+{
+	let threads = threads.min(items.len());
+	if threads <= 1 {
+		return items.iter().map(f).collect();
+	}
+
+	let next = AtomicUsize::new(0);
+	let failed = AtomicBool::new(false);
+	let work = || -> io::Result<Vec<(usize, R)>> {
+		let mut done = Vec::new();
+		while !failed.load(Ordering::Relaxed) {
+			let i = next.fetch_add(1, Ordering::Relaxed);
+			let Some(item) = items.get(i) else {
+				break;
+			};
+			match f(item) {
+				Ok(x) => done.push((i, x)),
+				Err(e) => {
+					failed.store(true, Ordering::Relaxed);
+					return Err(e);
+				}
+			}
+		}
+		Ok(done)
+	};
+
+	let results = thread::scope(|scope| {
+		let workers: Vec<_> = (0..threads).map(|_| scope.spawn(work)).collect();
+		workers
+			.into_iter()
+			.map(|x| x.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+			.collect::<Vec<_>>()
+	});
+	let mut done = Vec::with_capacity(items.len());
+	for result in results {
+		done.extend(result?);
+	}
+	done.sort_by_key(|(i, _)| *i);
+	Ok(done.into_iter().map(|(_, x)| x).collect())
+}
@@ -0,0 +1,306 @@
+use std::io;
+
+// 長さの4桁を含めた1行の最大の長さ
+pub const MAX_LEN: usize = 65520;
+
+// pkt-lineの1行
+#[derive(Debug, PartialEq)]
+pub enum Packet {
+	Flush,         // "0000"。一続きのものの終わり
+	Delim,         // "0001"。protocol v2でsectionを区切る
+	ResponseEnd,   // "0002"。protocol v2で返事の終わり
+	Data(Vec<u8>), // "<4桁の16進数の長さ><data>"。長さは4桁の分を含む
+}
+
+// side-band-64kの番号
+pub const BAND_DATA: u8 = 1;
+pub const BAND_PROGRESS: u8 = 2;
+pub const BAND_ERROR: u8 = 3;
+
+// bytesの先頭からpkt-lineを読む
+pub struct Reader<'a> {
+	rest: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+	pub fn new(bytes: &'a [u8]) -> Self {
+		Self { rest: bytes }
+	}
+
+	// 次の行。残りが無ければNone
+	pub fn read(&mut self) -> io::Result<Option<Packet>> {
+		if self.rest.is_empty() {
+			return Ok(None);
+		}
+		let len = self
+			.rest
+			.get(..4)
+			.and_then(|x| std::str::from_utf8(x).ok())
+			.filter(|x| x.bytes().all(|b| b.is_ascii_hexdigit()))
+			.and_then(|x| usize::from_str_radix(x, 16).ok())
+			.ok_or_else(|| invalid("invalid pkt-line length"))?;
+		let packet = match len {
+			0 => Packet::Flush,
+			1 => Packet::Delim,
+			2 => Packet::ResponseEnd,
+			3 => return Err(invalid("invalid pkt-line length")),
+			_ => Packet::Data(
+				self.rest
+					.get(4..len)
+					.ok_or_else(|| invalid("pkt-line is truncated"))?
+					.to_vec(),
+			),
+		};
+		self.rest = &self.rest[len.max(4)..];
+		Ok(Some(packet))
+	}
+
+	// 次の行を読まずに見る
+	pub fn peek(&self) -> io::Result<Option<Packet>> {
+		Reader { rest: self.rest }.read()
+	}
+
+	// flushかその他の区切りまでのdataの行
+	pub fn read_section(&mut self) -> io::Result<Vec<Vec<u8>>> {
+		let mut lines = Vec::new();
+		while let Some(packet) = self.read()? {
+			match packet {
+				Packet::Data(x) => lines.push(x),
+				_ => break,
+			}
+		}
+		Ok(lines)
+	}
+
+	// まだ読んでいない部分。pkt-lineの後に続くpackなど
+	pub fn rest(&self) -> &'a [u8] {
+		self.rest
+	}
+
+	// side-bandの行をflushか終わりまで読み、1番のdataをつなげて返す
+	// 2番はprogressに渡し、3番はError
+	pub fn demux(&mut self, progress: &mut dyn FnMut(&[u8])) -> io::Result<Vec<u8>> {
+		let mut data = Vec::new();
+		while let Some(packet) = self.read()? {
+			let Packet::Data(line) = packet else {
+				break;
+			};
+			match line.split_first() {
+				Some((&BAND_DATA, x)) => data.extend_from_slice(x),
+				Some((&BAND_PROGRESS, x)) => progress(x),
+				Some((&BAND_ERROR, x)) => {
+					return Err(io::Error::other(format!(
+						"remote error: {}",
+						String::from_utf8_lossy(x).trim_end()
+					)))
+				}
+				_ => return Err(invalid("invalid side-band")),
+			}
+		}
+		Ok(data)
+	}
+}
+
+// pkt-lineを書き足していく
+#[derive(Default)]
+pub struct Writer {
+	pub bytes: Vec<u8>,
+}
+
+impl Writer {
+	pub fn new() -> Self {
+		Self { bytes: Vec::new() }
+	}
+
+	pub fn data(&mut self, data: &[u8]) -> io::Result<()> {
+		if data.len() + 4 > MAX_LEN {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"pkt-line is too long",
+			));
+		}
+		self.bytes
+			.extend_from_slice(format!("{:04x}", data.len() + 4).as_bytes());
+		self.bytes.extend_from_slice(data);
+		Ok(())
+	}
+
+	pub fn flush(&mut self) {
+		self.bytes.extend_from_slice(b"0000");
+	}
+
+	pub fn delim(&mut self) {
+		self.bytes.extend_from_slice(b"0001");
+	}
+
+	// dataを1行に入るように分けてbandの行にする
+	pub fn band(&mut self, band: u8, data: &[u8]) -> io::Result<()> {
+		for chunk in data.chunks(MAX_LEN - 5) {
+			self.data(&[&[band], chunk].concat())?;
+		}
+		Ok(())
+	}
+}
+
+fn invalid(message: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::hash::HashAlgorithm;
+
+	// git 2.39のls-remoteがprotocol v2で送ったls-refsのrequest。最後のflushは接続を終えるもの
+	const LS_REFS: &[u8] = b"0014command=ls-refs\n0014agent=git/2.39.50016object-format=sha1\
+		00010009peel\n000csymrefs\n000bunborn\n00000000";
+
+	// git 2.39の "git upload-pack --stateless-rpc" が1つのcommitのfetchに返したもの
+	const FETCHED: &[u8] = b"0008NAK\n0023\x02Enumerating objects: 2, done.\n\
+		0022\x02Counting objects:  50% (1/2)\r0022\x02Counting objects: 100% (2/2)\r\
+		0029\x02Counting objects: 100% (2/2), done.\n\
+		0078\x01PACK\x00\x00\x00\x02\x00\x00\x00\x02\x94\x07x\x9c}\xca1\n\x800\x0c\x00\xc0\xbd\xaf\
+		\xc8\xee\x12kl\x13\x10\xf1+MM\xd1A\n\xa5\x82\xcf\xd7\x17x\xf3\xf5f\x06\xa4\xec\xe7=\x07\xf2Y\
+		\x83\xa9$\x0ch3i\xe1=\x88g.jF\x82\xe4\xd2\xdd\x8f\xda \xc1\x926]a\x8c2JD\xcf\x13\x0c\xf8q\xb9^\
+		\xd7\xd9\xbb\xfd\x14\xf7\xb8\x17\x7f\xf5\x1e\xc2 x\x9c\x03\x00\x00\x00\x00\
+		0019\x01\x01}w\xcc\x9c\xc6{\xc3ml;|~/u\x86J{\xf3\xa50006\x01\xf4\
+		0039\x02Total 2 (delta 0), reused 0 (delta 0), pack-reused 00006\x02\n0000";
+
+	// git 2.39の "git receive-pack --stateless-rpc" がrefを1つ消したときに返したもの
+	const REPORT: &[u8] = b"002f\x01000eunpack ok\n0018ok refs/heads/topic\n00000000";
+
+	fn data(line: &[u8]) -> Option<Packet> {
+		Some(Packet::Data(line.to_vec()))
+	}
+
+	#[test]
+	fn read_request() {
+		let mut reader = Reader::new(LS_REFS);
+		assert_eq!(reader.read().unwrap(), data(b"command=ls-refs\n"));
+		// 行の終わりの改行は無くてもよい
+		assert_eq!(reader.peek().unwrap(), data(b"agent=git/2.39.5"));
+		assert_eq!(
+			reader.read_section().unwrap(),
+			[&b"agent=git/2.39.5"[..], b"object-format=sha1"]
+		);
+		assert_eq!(
+			reader.read_section().unwrap(),
+			[&b"peel\n"[..], b"symrefs\n", b"unborn\n"]
+		);
+		assert_eq!(reader.read().unwrap(), Some(Packet::Flush));
+		assert_eq!(reader.read().unwrap(), None);
+		assert!(reader.rest().is_empty());
+	}
+
+	#[test]
+	fn write_request() {
+		let mut writer = Writer::new();
+		writer.data(b"command=ls-refs\n").unwrap();
+		writer.data(b"agent=git/2.39.5").unwrap();
+		writer.data(b"object-format=sha1").unwrap();
+		writer.delim();
+		for line in ["peel\n", "symrefs\n", "unborn\n"] {
+			writer.data(line.as_bytes()).unwrap();
+		}
+		writer.flush();
+		writer.flush();
+		assert_eq!(writer.bytes, LS_REFS);
+	}
+
+	#[test]
+	fn demux_fetch() {
+		let mut reader = Reader::new(FETCHED);
+		assert_eq!(reader.read().unwrap(), data(b"NAK\n"));
+		let mut progress = Vec::new();
+		let pack = reader
+			.demux(&mut |x| progress.extend_from_slice(x))
+			.unwrap();
+		assert_eq!(
+			String::from_utf8(progress).unwrap(),
+			"Enumerating objects: 2, done.\nCounting objects:  50% (1/2)\r\
+			Counting objects: 100% (2/2)\rCounting objects: 100% (2/2), done.\n\
+			Total 2 (delta 0), reused 0 (delta 0), pack-reused 0\n"
+		);
+		// 3つの行に分かれていたpackがつながる
+		assert_eq!(pack.len(), 136);
+		assert_eq!(&pack[..12], b"PACK\0\0\0\x02\0\0\0\x02");
+		let (body, checksum) = pack.split_at(pack.len() - 20);
+		assert_eq!(HashAlgorithm::Sha1.digest(body), checksum);
+		assert_eq!(reader.read().unwrap(), None);
+	}
+
+	// report-statusはside-bandの中にpkt-lineで入っている
+	#[test]
+	fn demux_report() {
+		let mut reader = Reader::new(REPORT);
+		let report = reader.demux(&mut |_| panic!("no progress")).unwrap();
+		assert_eq!(report, &REPORT[5..47]);
+		assert!(reader.rest().is_empty());
+
+		let mut reader = Reader::new(&report);
+		assert_eq!(reader.read().unwrap(), data(b"unpack ok\n"));
+		assert_eq!(reader.read().unwrap(), data(b"ok refs/heads/topic\n"));
+		assert_eq!(reader.read().unwrap(), Some(Packet::Flush));
+		assert_eq!(reader.read().unwrap(), None);
+	}
+
+	#[test]
+	fn demux_error() {
+		let mut writer = Writer::new();
+		writer.band(BAND_PROGRESS, b"working\n").unwrap();
+		writer.band(BAND_ERROR, b"access denied\n").unwrap();
+		writer.band(BAND_DATA, b"never read").unwrap();
+		let mut progress = Vec::new();
+		let err = Reader::new(&writer.bytes)
+			.demux(&mut |x| progress.extend_from_slice(x))
+			.unwrap_err();
+		assert_eq!(err.to_string(), "remote error: access denied");
+		assert_eq!(progress, b"working\n");
+
+		let err = Reader::new(b"0006\x04x").demux(&mut |_| {}).unwrap_err();
+		assert_eq!(err.to_string(), "invalid side-band");
+	}
+
+	// 1行に入らないものは分けて書き、demuxでつながる
+	#[test]
+	fn long_band() {
+		let content: Vec<u8> = (0..MAX_LEN * 2).map(|i| i as u8).collect();
+		let mut writer = Writer::new();
+		writer.band(BAND_DATA, &content).unwrap();
+		writer.flush();
+		let mut reader = Reader::new(&writer.bytes);
+		assert_eq!(&writer.bytes[..4], b"fff0");
+		assert_eq!(reader.demux(&mut |_| {}).unwrap(), content);
+
+		let mut writer = Writer::new();
+		assert!(writer.data(&vec![b'x'; MAX_LEN - 4]).is_ok());
+		let err = writer.data(&vec![b'x'; MAX_LEN - 3]).unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+	}
+
+	#[test]
+	fn special_packets() {
+		let mut reader = Reader::new(b"000000010002");
+		assert_eq!(reader.read().unwrap(), Some(Packet::Flush));
+		assert_eq!(reader.read().unwrap(), Some(Packet::Delim));
+		assert_eq!(reader.read().unwrap(), Some(Packet::ResponseEnd));
+		assert_eq!(reader.read().unwrap(), None);
+		// 空のdataの行
+		assert_eq!(Reader::new(b"0004").read().unwrap(), data(b""));
+	}
+
+	#[test]
+	fn invalid() {
+		for (bytes, message) in [
+			(&b"0003"[..], "invalid pkt-line length"),
+			(b"00", "invalid pkt-line length"),
+			(b"zzzz", "invalid pkt-line length"),
+			(b"+01a", "invalid pkt-line length"),
+			(b"000ashort", "pkt-line is truncated"),
+		] {
+			let err = Reader::new(bytes).read().unwrap_err();
+			assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+			assert_eq!(err.to_string(), message);
+		}
+	}
+}
@@ -0,0 +1,184 @@
+use crate::object::{Commit, User};
+
+// log --pretty で選べる出力形式
+// format:は各commitの間に改行を入れ、tformat:は各commitの後ろに改行を付ける
+pub enum Format {
+	Oneline,
+	Short,
+	Medium,
+	Full,
+	Raw,
+	Custom { template: String, terminator: bool },
+}
+
+impl Format {
+	pub fn from(name: &str) -> Option<Self> {
+		let format = match name {
+			"oneline" => Self::Oneline,
+			"short" => Self::Short,
+			"medium" => Self::Medium,
+			"full" => Self::Full,
+			"raw" => Self::Raw,
+			x => {
+				let (template, terminator) = match x.split_once(':') {
+					Some(("format", x)) => (x, false),
+					Some(("tformat", x)) => (x, true),
+					_ if x.contains('%') => (x, true),
+					_ => return None,
+				};
+				Self::Custom {
+					template: String::from(template),
+					terminator,
+				}
+			}
+		};
+		Some(format)
+	}
+
+	// 2つ目以降のcommitの前に出すもの
+	pub fn separator(&self) -> &str {
+		match self {
+			Self::Oneline => "",
+			Self::Custom { terminator, .. } if *terminator => "",
+			_ => "\n",
+		}
+	}
+}
+
+// commitをformatに従って整形する
+// headerはrawでそのまま出すcommit objectのheader部分(messageの前の空行より前)
+pub fn format(format: &Format, hash: &str, commit: &Commit, header: &str) -> String {
+	// 定義済みの形式もplaceholderで組み立てる。messageだけは字下げして後ろに付ける
+	let merge = if commit.parents.len() > 1 {
+		"Merge: %p\n"
+	} else {
+		""
+	};
+	let message = Some(commit.message.clone());
+	let (template, message) = match format {
+		Format::Oneline => ("%H %s\n".to_string(), None),
+		// shortではmessageの最初の段落だけ
+		Format::Short => (
+			format!("commit %H\n{}Author: %an <%ae>\n\n", merge),
+			Some(format!("{}\n", subject(&commit.message))),
+		),
+		Format::Medium => (
+			format!("commit %H\n{}Author: %an <%ae>\nDate:   %ad\n\n", merge),
+			message,
+		),
+		Format::Full => (
+			format!(
+				"commit %H\n{}Author: %an <%ae>\nCommit: %cn <%ce>\n\n",
+				merge
+			),
+			message,
+		),
+		Format::Raw => {
+			let header = header.replace('%', "%%");
+			(format!("commit %H\n{}\n\n", header), message)
+		}
+		Format::Custom {
+			template,
+			terminator,
+		} => {
+			let newline = if *terminator { "\n" } else { "" };
+			(format!("{}{}", template, newline), None)
+		}
+	};
+
+	let mut out = expand(&template, hash, commit);
+	if let Some(message) = message {
+		out.push_str(&indent(&message));
+	}
+	out
+}
+
+fn expand(template: &str, hash: &str, commit: &Commit) -> String {
+	let mut out = String::new();
+	let mut rest = template;
+
+	while let Some(i) = rest.find('%') {
+		out.push_str(&rest[..i]);
+		rest = &rest[i + 1..];
+
+		match placeholder(rest, hash, commit) {
+			Some((value, len)) => {
+				out.push_str(&value);
+				rest = &rest[len..];
+			}
+			// 知らないplaceholderはそのまま出す
+			None => out.push('%'),
+		}
+	}
+	out.push_str(rest);
+	out
+}
+
+// "%"の後ろを読んで、展開した値と読んだ長さを返す
+fn placeholder(rest: &str, hash: &str, commit: &Commit) -> Option<(String, usize)> {
+	let short = |x: &str| String::from(&x[..7.min(x.len())]);
+	let user = |user: &User, c: char| -> Option<String> {
+		let value = match c {
+			'n' => user.name.clone(),
+			'e' => user.email.clone(),
+			'd' => date(user),
+			't' => user.ts.timestamp().to_string(),
+			_ => return None,
+		};
+		Some(value)
+	};
+
+	let value = match rest.chars().next()? {
+		'%' => String::from("%"),
+		'n' => String::from("\n"),
+		'H' => String::from(hash),
+		'h' => short(hash),
+		'T' => commit.tree.clone(),
+		't' => short(&commit.tree),
+		'P' => commit.parents.join(" "),
+		'p' => commit
+			.parents
+			.iter()
+			.map(|x| short(x))
+			.collect::<Vec<_>>()
+			.join(" "),
+		's' => subject(&commit.message),
+		'b' => body(&commit.message),
+		'B' => String::from(&commit.message),
+		'a' => return Some((user(&commit.author, rest[1..].chars().next()?)?, 2)),
+		'c' => return Some((user(&commit.committer, rest[1..].chars().next()?)?, 2)),
+		_ => return None,
+	};
+	Some((value, 1))
+}
+
+// gitの既定の日付形式 "Wed Oct 14 04:45:26 2026 +0000"
+pub fn date(user: &User) -> String {
+	user.ts.format("%a %b %-d %H:%M:%S %Y %z").to_string()
+}
+
+// 最初の段落を1行につなげたもの
+pub fn subject(message: &str) -> String {
+	message
+		.lines()
+		.skip_while(|x| x.trim().is_empty())
+		.take_while(|x| !x.trim().is_empty())
+		.map(|x| x.trim())
+		.collect::<Vec<_>>()
+		.join(" ")
+}
+
+// 最初の段落より後ろ
+fn body(message: &str) -> String {
+	message
+		.lines()
+		.skip_while(|x| x.trim().is_empty())
+		.skip_while(|x| !x.trim().is_empty())
+		.skip_while(|x| x.trim().is_empty())
+		.map(|x| format!("{}\n", x))
+		.collect()
+}
+
+fn indent(message: &str) -> String {
+	message.lines().map(|x| format!("    {}\n", x)).collect()
+}
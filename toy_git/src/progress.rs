@@ -0,0 +1,74 @@
+use std::io::{self, IsTerminal, Write};
+
+// 時間のかかる処理(objectの数え上げ、packの書き込みや読み込みなど)の進み具合を受け取る
+// 1つの処理の中でstartからfinishまでを段階(phase)ごとに繰り返す
+pub trait Progress {
+	fn start(&mut self, phase: &str, total: usize);
+	fn inc(&mut self, n: usize);
+	fn finish(&mut self);
+}
+
+// 何も表示しない
+pub struct Silent;
+
+impl Progress for Silent {
+	fn start(&mut self, _phase: &str, _total: usize) {}
+	fn inc(&mut self, _n: usize) {}
+	fn finish(&mut self) {}
+}
+
+// stderrが端末のときだけ表示する
+pub fn stderr() -> Box<dyn Progress> {
+	match io::stderr().is_terminal() {
+		true => Box::new(Stderr::default()),
+		false => Box::new(Silent),
+	}
+}
+
+// gitと同じく "<phase>: 100% (x/y), done." の形でstderrの同じ行を書き換えていく
+#[derive(Default)]
+pub struct Stderr {
+	phase: String,
+	total: usize,
+	count: usize,
+	percent: Option<usize>, // 最後に表示した割合。変わったときだけ書き直す
+}
+
+impl Stderr {
+	fn show(&mut self, done: bool) {
+		let percent = match self.total {
+			0 => 100,
+			total => self.count * 100 / total,
+		};
+		if !done && self.percent == Some(percent) {
+			return;
+		}
+		self.percent = Some(percent);
+
+		let end = if done { ", done.\n" } else { "" };
+		eprint!(
+			"\r{}: {:3}% ({}/{}){}",
+			self.phase, percent, self.count, self.total, end
+		);
+		io::stderr().flush().ok();
+	}
+}
+
+impl Progress for Stderr {
+	fn start(&mut self, phase: &str, total: usize) {
+		self.phase = String::from(phase);
+		self.total = total;
+		self.count = 0;
+		self.percent = None;
+		self.show(false);
+	}
+
+	fn inc(&mut self, n: usize) {
+		self.count += n;
+		self.show(false);
+	}
+
+	fn finish(&mut self) {
+		self.show(true);
+	}
+}
@@ -0,0 +1,69 @@
+use std::collections::HashSet;
+use std::io;
+
+use crate::hash::Hash;
+use crate::object::{Commit, ObjectKind, Tag, Tree};
+use crate::repository::Repository;
+use crate::store;
+
+// tipsから辿れる全てのobjectのhashを返す。excludeから辿れるものは含まない
+pub fn objects(repo: &Repository, tips: &[Hash], exclude: &[Hash]) -> io::Result<Vec<Hash>> {
+	let mut seen = HashSet::new();
+	walk(repo, exclude, &mut seen)?;
+	walk(repo, tips, &mut seen)
+}
+
+// seenに無いobjectを辿ってseenに加え、見つけた順に返す
+fn walk(repo: &Repository, tips: &[Hash], seen: &mut HashSet<Hash>) -> io::Result<Vec<Hash>> {
+	let mut stack: Vec<Hash> = tips.iter().rev().cloned().collect();
+	let mut found = Vec::new();
+
+	// 深い履歴でも再帰しないようにstackで辿る
+	while let Some(hash) = stack.pop() {
+		if !seen.insert(hash.clone()) {
+			continue;
+		}
+
+		let (kind, body) = store::read_original(repo, &hash.0)?;
+		let bad = || {
+			io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!("bad {} object {}", kind, hash),
+			)
+		};
+
+		match kind {
+			ObjectKind::Commit => {
+				let commit = Commit::from(&body).map_err(|e| e.context(bad()))?;
+				for parent in commit.parents.iter().rev() {
+					stack.push(Hash::from_hex(parent).ok_or_else(bad)?);
+				}
+				stack.push(Hash::from_hex(&commit.tree).ok_or_else(bad)?);
+			}
+			ObjectKind::Tree => {
+				let tree = Tree::from(&body, repo.hash_algo).map_err(|e| e.context(bad()))?;
+				for file in tree.contents.into_iter().rev() {
+					match file.mode {
+						160000 => {} // submoduleのcommitはこのrepositoryには無い
+						40000 => stack.push(Hash(file.hash)),
+						// blobは中身を読まなくてよい
+						_ => {
+							let hash = Hash(file.hash);
+							if seen.insert(hash.clone()) {
+								found.push(hash);
+							}
+						}
+					}
+				}
+			}
+			ObjectKind::Tag => {
+				let tag = Tag::from(&body).map_err(|e| e.context(bad()))?;
+				stack.push(Hash::from_hex(&tag.object).ok_or_else(bad)?);
+			}
+			ObjectKind::Blob => {}
+		}
+		found.push(hash);
+	}
+
+	Ok(found)
+}
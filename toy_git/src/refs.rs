@@ -0,0 +1,647 @@
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, FixedOffset};
+
+use crate::config::Config;
+use crate::date;
+use crate::error::GitError;
+use crate::hash::Hash;
+use crate::ident;
+use crate::repository::Repository;
+
+// libraryとして使うときのrefの読み書き。Repository::refsで作る
+// 下の関数をHashで呼べるようにしたもの
+pub struct Refs<'a> {
+	repo: &'a Repository,
+}
+
+impl<'a> Refs<'a> {
+	pub fn new(repo: &'a Repository) -> Self {
+		Self { repo }
+	}
+
+	// symbolic refを辿ったhash。refが無いかunbornなbranchならNone
+	pub fn resolve(&self, name: &str) -> io::Result<Option<Hash>> {
+		resolve(self.repo, name)?
+			.map(|x| parse_hash(name, &x))
+			.transpose()
+	}
+
+	// HEADが指しているrefの名前。detached HEADなら "HEAD"
+	pub fn head(&self) -> io::Result<String> {
+		head_target(self.repo)
+	}
+
+	// nameをhashにしてreflogにmessageを記録する
+	pub fn update(&self, name: &str, hash: &Hash, message: &str) -> io::Result<()> {
+		update_ref_logged(self.repo, name, &hash.to_string(), message)
+	}
+
+	pub fn delete(&self, name: &str) -> io::Result<()> {
+		delete_ref(self.repo, name)
+	}
+
+	// prefixの下の全てのrefを名前の順に
+	pub fn list(&self, prefix: &str) -> io::Result<Vec<(String, Hash)>> {
+		list(self.repo, prefix)?
+			.into_iter()
+			.map(|(name, hex)| {
+				let hash = parse_hash(&name, &hex)?;
+				Ok((name, hash))
+			})
+			.collect()
+	}
+}
+
+fn parse_hash(name: &str, hex: &str) -> io::Result<Hash> {
+	Hash::from_hex(hex).ok_or_else(|| {
+		io::Error::new(
+			io::ErrorKind::InvalidData,
+			format!("{}: bad ref value {}", name, hex),
+		)
+	})
+}
+
+// refの中身をそのまま返す("ref: refs/heads/main" のようなsymbolic refも含む)
+// looseなrefが無ければpacked-refsから探す
+pub fn read_ref(repo: &Repository, name: &str) -> io::Result<Option<String>> {
+	match fs::read_to_string(repo.ref_path(name)) {
+		Ok(content) => return Ok(Some(String::from(content.trim()))),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+		Err(e) if e.kind() == io::ErrorKind::IsADirectory => return Ok(None),
+		Err(e) => return Err(e),
+	}
+
+	Ok(packed_refs(repo)?
+		.into_iter()
+		.find(|(x, _)| x == name)
+		.map(|(_, hash)| hash))
+}
+
+// refにhashを書き込む(looseなrefとして保存する)
+pub fn update_ref(repo: &Repository, name: &str, hash: &str) -> io::Result<()> {
+	let path = repo.ref_path(name);
+	if let Some(dir) = path.parent() {
+		fs::create_dir_all(dir)?;
+	}
+	fs::write(path, format!("{}\n", hash))
+}
+
+// refをhashに書き換え、reflogにmessageを記録する。symbolic refはその指す先を書き換える
+// HEADが指しているbranchならHEADのreflogにも記録する
+// transactionと違い、gitのcommitやresetと同じく値が変わらなくても記録する
+pub fn update_ref_logged(
+	repo: &Repository,
+	name: &str,
+	hash: &str,
+	message: &str,
+) -> io::Result<()> {
+	let target = symref_target(repo, name)?;
+	let old = resolve(repo, &target)?.unwrap_or_else(|| repo.hash_algo.null_hex());
+	update_ref(repo, &target, hash)?;
+	append_reflog(repo, &target, &old, hash, message)?;
+	if target != "HEAD" && head_target(repo)? == target {
+		append_reflog(repo, "HEAD", &old, hash, message)?;
+	}
+	Ok(())
+}
+
+// looseなrefとpacked-refsの両方から消す。空になった親ディレクトリも消す
+// gitと同じく、refs/heads/のようなrefs/の直下のディレクトリは残す
+pub fn delete_ref(repo: &Repository, name: &str) -> io::Result<()> {
+	match fs::remove_file(repo.ref_path(name)) {
+		Ok(()) => {}
+		Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+		Err(e) => return Err(e),
+	}
+	let mut dir = name;
+	while let Some((parent, _)) = dir.rsplit_once('/') {
+		if parent.matches('/').count() < 2 || fs::remove_dir(repo.ref_path(parent)).is_err() {
+			break;
+		}
+		dir = parent;
+	}
+
+	let path = repo.commondir.join("packed-refs");
+	let content = match fs::read_to_string(&path) {
+		Ok(content) => content,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+		Err(e) => return Err(e),
+	};
+
+	// refの行と、その直後にある "^<hash>" のpeel行を除く
+	let mut lines = Vec::new();
+	let mut removed = false;
+	for line in content.lines() {
+		if line.starts_with('^') && removed {
+			continue;
+		}
+		removed = line.split_once(' ').is_some_and(|(_, x)| x == name);
+		if !removed {
+			lines.push(line);
+		}
+	}
+	if lines.len() != content.lines().count() {
+		fs::write(path, lines.iter().map(|x| format!("{}\n", x)).collect::<String>())?;
+	}
+	Ok(())
+}
+
+// HEADが指しているrefの名前を返す。detached HEADの場合は "HEAD" になる
+pub fn head_target(repo: &Repository) -> io::Result<String> {
+	let mut name = String::from("HEAD");
+	for _ in 0..5 {
+		match read_ref(repo, &name)?.as_deref().and_then(|x| x.strip_prefix("ref: ")) {
+			Some(target) => name = String::from(target),
+			None => return Ok(name),
+		}
+	}
+	Err(io::Error::other("HEAD: symbolic ref is too deep"))
+}
+
+// symbolic refが指すrefの名前。recurseなら指す先もsymbolic refである限り辿る
+// 指す先のrefは無くてもよい(unbornなbranch)。nameがsymbolic refでなければNone
+pub fn symbolic_target(repo: &Repository, name: &str, recurse: bool) -> io::Result<Option<String>> {
+	let mut target = None;
+	for _ in 0..5 {
+		let name = target.as_deref().unwrap_or(name);
+		match read_ref(repo, name)?.as_deref().and_then(|x| x.strip_prefix("ref: ")) {
+			Some(x) if recurse => target = Some(String::from(x)),
+			Some(x) => return Ok(Some(String::from(x))),
+			None => return Ok(target),
+		}
+	}
+	Err(io::Error::other(format!("{}: symbolic ref is too deep", name)))
+}
+
+// symbolic refを辿ってhashを返す。unbornなbranchの場合はNone
+pub fn resolve(repo: &Repository, name: &str) -> io::Result<Option<String>> {
+	let mut name = String::from(name);
+
+	// symbolic refが循環していても止まるように深さを制限する
+	for _ in 0..5 {
+		match read_ref(repo, &name)? {
+			Some(content) => match content.strip_prefix("ref: ") {
+				Some(target) => name = String::from(target),
+				None => return Ok(Some(content)),
+			},
+			None => return Ok(None),
+		}
+	}
+
+	Err(io::Error::other(format!("{}: symbolic ref is too deep", name)))
+}
+
+// packed-refsの "<hash> <refname>" 行を読む。"^<hash>" のpeel行は読み飛ばす
+pub fn packed_refs(repo: &Repository) -> io::Result<Vec<(String, String)>> {
+	let content = match fs::read_to_string(repo.commondir.join("packed-refs")) {
+		Ok(content) => content,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(e) => return Err(e),
+	};
+
+	Ok(content
+		.lines()
+		.filter(|x| !x.starts_with('#') && !x.starts_with('^'))
+		.filter_map(|x| x.split_once(' '))
+		.map(|(hash, name)| (String::from(name), String::from(hash)))
+		.collect())
+}
+
+// prefix(例えば "refs/tags")以下の全てのrefを (refname, hash) の組で名前順に返す
+pub fn list(repo: &Repository, prefix: &str) -> io::Result<Vec<(String, String)>> {
+	let mut names = Vec::new();
+	walk_loose(&repo.commondir.join("refs"), "refs", &mut names)?;
+	// linked worktreeではrefs/bisect/だけは自分のものを使う
+	if repo.gitdir != repo.commondir {
+		names.retain(|x| !list_match(x, "refs/bisect"));
+		let dir = repo.gitdir.join("refs").join("bisect");
+		walk_loose(&dir, "refs/bisect", &mut names)?;
+	}
+
+	let mut refs = Vec::new();
+	for name in names {
+		if let Some(hash) = resolve(repo, &name)? {
+			refs.push((name, hash));
+		}
+	}
+
+	// looseなrefの方がpacked-refsより新しい
+	for (name, hash) in packed_refs(repo)? {
+		if !refs.iter().any(|(x, _)| *x == name) {
+			refs.push((name, hash));
+		}
+	}
+
+	let mut refs: Vec<_> = refs
+		.into_iter()
+		.filter(|(name, _)| list_match(name, prefix))
+		.collect();
+	refs.sort();
+	Ok(refs)
+}
+
+// prefixがpathの区切りで一致するか("refs/head" は "refs/heads/main" に一致しない)
+pub fn list_match(name: &str, prefix: &str) -> bool {
+	let prefix = prefix.trim_end_matches('/');
+	prefix.is_empty()
+		|| name == prefix
+		|| name
+			.strip_prefix(prefix)
+			.is_some_and(|x| x.starts_with('/'))
+}
+
+fn walk_loose(dir: &Path, name: &str, names: &mut Vec<String>) -> io::Result<()> {
+	let entries = match fs::read_dir(dir) {
+		Ok(entries) => entries,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+		Err(e) => return Err(e),
+	};
+
+	for entry in entries {
+		let entry = entry?;
+		let child = format!("{}/{}", name, entry.file_name().to_string_lossy());
+		if entry.file_type()?.is_dir() {
+			walk_loose(&entry.path(), &child, names)?;
+		} else if !child.ends_with(".lock") {
+			names.push(child);
+		}
+	}
+	Ok(())
+}
+
+// 表示用にrefs/heads/などを取り除いた名前
+pub fn shorten(name: &str) -> &str {
+	["refs/heads/", "refs/tags/", "refs/remotes/", "refs/"]
+		.iter()
+		.find_map(|x| name.strip_prefix(x))
+		.unwrap_or(name)
+}
+
+// ref名として使えない理由
+#[derive(Debug)]
+pub enum RefNameError {
+	Empty,
+	Char(char),             // 空白や制御文字、"@" だけの名前、"~^:?*[\"
+	Sequence(&'static str), // "..", "@{", "//"
+	Slash,                  // "/" で始まるか終わる
+	TrailingDot,
+	Component(String), // "." で始まるか ".lock" で終わる "/" の間の部分
+}
+
+impl fmt::Display for RefNameError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Empty => write!(f, "empty ref name"),
+			Self::Char(x) => write!(f, "ref name cannot contain {:?}", x),
+			Self::Sequence(x) => write!(f, "ref name cannot contain '{}'", x),
+			Self::Slash => write!(f, "ref name cannot begin or end with '/'"),
+			Self::TrailingDot => write!(f, "ref name cannot end with '.'"),
+			Self::Component(x) => write!(f, "bad ref name component '{}'", x),
+		}
+	}
+}
+
+impl std::error::Error for RefNameError {}
+
+// gitのcheck-ref-formatと同じ規則でref名として使えるか確かめる
+pub fn validate_ref_name(name: &str) -> Result<(), RefNameError> {
+	if name.is_empty() {
+		return Err(RefNameError::Empty);
+	}
+	if name == "@" {
+		return Err(RefNameError::Char('@'));
+	}
+	if let Some(x) = name
+		.chars()
+		.find(|x| x.is_ascii_control() || " ~^:?*[\\".contains(*x))
+	{
+		return Err(RefNameError::Char(x));
+	}
+	if let Some(x) = ["..", "@{", "//"].into_iter().find(|x| name.contains(x)) {
+		return Err(RefNameError::Sequence(x));
+	}
+	if name.starts_with('/') || name.ends_with('/') {
+		return Err(RefNameError::Slash);
+	}
+	if name.ends_with('.') {
+		return Err(RefNameError::TrailingDot);
+	}
+	match name
+		.split('/')
+		.find(|x| x.starts_with('.') || x.ends_with(".lock"))
+	{
+		Some(x) => Err(RefNameError::Component(String::from(x))),
+		None => Ok(()),
+	}
+}
+
+// update-ref --stdinなどで1つのrefに加える変更
+pub enum RefChange {
+	Update(String), // 新しいhash
+	Delete,
+	Verify, // 値を確かめるだけで変えない
+}
+
+// oldがSomeなら変更前にその値でなければならない(Some(None)は存在しないこと)
+pub struct RefUpdate {
+	pub name: String,
+	pub change: RefChange,
+	pub old: Option<Option<String>>,
+}
+
+// 全てのrefを "<ref>.lock" でlockしてから古い値を確かめ、全て合っていたときだけ書き換える
+// 1つでもlockできないか値が違えば、どのrefも変えずにError
+// 書き換えたrefはreflogにも記録する
+pub fn transaction(repo: &Repository, updates: &[RefUpdate], message: &str) -> io::Result<()> {
+	let mut locks = Vec::with_capacity(updates.len());
+	let mut names: Vec<String> = Vec::with_capacity(updates.len());
+	for update in updates {
+		let name = symref_target(repo, &update.name)?;
+		if names.contains(&name) {
+			return Err(io::Error::other(format!(
+				"multiple updates for ref '{}' not allowed",
+				update.name
+			)));
+		}
+		locks.push(Lock::acquire(&repo.ref_path(&name), &name)?);
+		names.push(name);
+	}
+
+	let mut olds = Vec::with_capacity(updates.len());
+	for (update, name) in updates.iter().zip(names.iter()) {
+		let current = resolve(repo, name)?;
+		let error = |reason: String| {
+			io::Error::other(format!("cannot lock ref '{}': {}", update.name, reason))
+		};
+		match (&update.old, &current) {
+			(None, _) => {}
+			(Some(None), Some(_)) => return Err(error(String::from("reference already exists"))),
+			(Some(Some(_)), None) => {
+				return Err(error(format!("unable to resolve reference '{}'", name)))
+			}
+			(Some(Some(old)), Some(current)) if old != current => {
+				return Err(error(format!("is at {} but expected {}", current, old)))
+			}
+			_ => {}
+		}
+		olds.push(current);
+	}
+
+	// ここから先はrefを書き換える
+	let head = head_target(repo)?;
+	let zero = repo.hash_algo.null_hex();
+	for ((update, name), (lock, old)) in updates
+		.iter()
+		.zip(names.iter())
+		.zip(locks.into_iter().zip(olds))
+	{
+		match &update.change {
+			// 値の変わらない更新はreflogにも残さない
+			RefChange::Update(hash) if old.as_ref() == Some(hash) => drop(lock),
+			RefChange::Update(hash) => {
+				lock.commit(&format!("{}\n", hash))?;
+				let old = old.unwrap_or_else(|| zero.clone());
+				append_reflog(repo, name, &old, hash, message)?;
+				if *name == head && name != "HEAD" {
+					append_reflog(repo, "HEAD", &old, hash, message)?;
+				}
+			}
+			RefChange::Delete => {
+				// lockが残っていると空になったディレクトリを消せない
+				drop(lock);
+				delete_ref(repo, name)?;
+				match fs::remove_file(repo.reflog_path(name)) {
+					Ok(()) => {}
+					Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+					Err(e) => return Err(e),
+				}
+			}
+			RefChange::Verify => drop(lock),
+		}
+	}
+	Ok(())
+}
+
+// symbolic refはその指す先を書き換える
+fn symref_target(repo: &Repository, name: &str) -> io::Result<String> {
+	let mut name = String::from(name);
+	for _ in 0..5 {
+		match read_ref(repo, &name)?.as_deref().and_then(|x| x.strip_prefix("ref: ")) {
+			Some(target) => name = String::from(target),
+			None => return Ok(name),
+		}
+	}
+	Err(io::Error::other(format!("{}: symbolic ref is too deep", name)))
+}
+
+// "<old> <new> <committer>\t<message>" を.git/logs/<ref>の末尾に加える
+// core.logAllRefUpdatesに従い、branchなど以外はreflogが既にあるときだけ書く
+pub fn append_reflog(
+	repo: &Repository,
+	name: &str,
+	old: &str,
+	new: &str,
+	message: &str,
+) -> io::Result<()> {
+	let path = repo.reflog_path(name);
+	let config = Config::load(repo)?;
+	let create = match config.get("core.logallrefupdates") {
+		Some(x) if x.eq_ignore_ascii_case("always") => true,
+		_ => {
+			config.get_bool("core.logallrefupdates") != Some(false)
+				&& (name == "HEAD"
+					|| ["refs/heads", "refs/remotes", "refs/notes"]
+						.iter()
+						.any(|x| list_match(name, x)))
+		}
+	};
+	if !create && !path.exists() {
+		return Ok(());
+	}
+
+	let mut line = format!("{} {} {}", old, new, ident::committer(repo)?);
+	if !message.is_empty() {
+		line.push('\t');
+		line.push_str(message);
+	}
+	if let Some(dir) = path.parent() {
+		fs::create_dir_all(dir)?;
+	}
+	let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+	writeln!(file, "{}", line)
+}
+
+// reflogの1行 "<old> <new> <name> <<email>> <timestamp> <timezone>\t<message>"
+pub struct ReflogEntry {
+	pub old: Hash,
+	pub new: Hash,
+	pub date: DateTime<FixedOffset>,
+	pub message: String,
+}
+
+impl ReflogEntry {
+	pub fn from(line: &str) -> Result<Self, GitError> {
+		let (header, message) = line.split_once('\t').unwrap_or((line, ""));
+		let hash = |offset: usize| {
+			let hex = header.get(offset..).unwrap_or_default();
+			let hex = hex.split(' ').next().unwrap_or_default();
+			Hash::from_hex(hex)
+				.map(|x| (x, offset + hex.len() + 1))
+				.ok_or(GitError::InvalidHeader {
+					offset,
+					message: "bad hash",
+				})
+		};
+		let (old, offset) = hash(0)?;
+		let (new, offset) = hash(offset)?;
+
+		let ident = header.get(offset..).unwrap_or_default();
+		let (_, date) = ident
+			.rsplit_once("> ")
+			.ok_or(GitError::BadIdent { offset })?;
+		let date = date::parse(date).ok_or(GitError::BadTimestamp {
+			offset: header.len() - date.len(),
+		})?;
+		Ok(Self {
+			old,
+			new,
+			date,
+			message: String::from(message),
+		})
+	}
+}
+
+// refのreflogを古い順に読む。reflogが無ければ空
+pub fn read_reflog(repo: &Repository, name: &str) -> io::Result<Vec<ReflogEntry>> {
+	let content = match fs::read_to_string(repo.reflog_path(name)) {
+		Ok(content) => content,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(e) => return Err(e),
+	};
+	content
+		.lines()
+		.map(|line| {
+			ReflogEntry::from(line)
+				.map_err(|e| e.context(format!("{}: bad reflog entry {:?}", name, line)))
+		})
+		.collect()
+}
+
+// "<path>.lock" を新しく作れたらlockできたことになる。commitされずにdropされると消す
+struct Lock {
+	dest: PathBuf,
+	path: PathBuf,
+	file: Option<fs::File>,
+}
+
+impl Lock {
+	fn acquire(dest: &Path, name: &str) -> io::Result<Self> {
+		let mut path = dest.as_os_str().to_owned();
+		path.push(".lock");
+		let path = PathBuf::from(path);
+
+		if let Some(dir) = path.parent() {
+			fs::create_dir_all(dir)?;
+		}
+		let file = OpenOptions::new()
+			.write(true)
+			.create_new(true)
+			.open(&path)
+			.map_err(|e| {
+				let reason = match e.kind() {
+					io::ErrorKind::AlreadyExists => String::from("File exists."),
+					_ => e.to_string(),
+				};
+				io::Error::other(format!(
+					"cannot lock ref '{}': Unable to create '{}': {}",
+					name,
+					path.display(),
+					reason
+				))
+			})?;
+		Ok(Self {
+			dest: dest.to_path_buf(),
+			path,
+			file: Some(file),
+		})
+	}
+
+	// 内容を書いてから元のpathにrenameする
+	fn commit(mut self, content: &str) -> io::Result<()> {
+		if let Some(file) = self.file.as_mut() {
+			file.write_all(content.as_bytes())?;
+			file.sync_all()?;
+		}
+		fs::rename(&self.path, &self.dest)?;
+		self.file = None;
+		Ok(())
+	}
+}
+
+impl Drop for Lock {
+	fn drop(&mut self) {
+		if self.file.take().is_some() {
+			fs::remove_file(&self.path).ok();
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// 結果はgit check-ref-formatと同じ
+	#[test]
+	fn accepted() {
+		for name in [
+			"refs/heads/feature/foo-1",
+			"refs/heads/a@b",
+			"refs/heads/a.b/c.lockx",
+			"refs/heads/ünï",
+		] {
+			assert!(validate_ref_name(name).is_ok(), "{}", name);
+		}
+	}
+
+	#[test]
+	fn rejected() {
+		for name in [
+			"",
+			"@",
+			"refs/heads/a b",
+			"refs/heads/a\x7f",
+			"refs/heads/a~1",
+			"refs/heads/a^",
+			"refs/heads/a:b",
+			"refs/heads/a?",
+			"refs/heads/a*",
+			"refs/heads/a[",
+			"refs/heads/a\\b",
+			"refs/heads/a..b",
+			"refs/heads/a@{b",
+			"refs/heads/a//b",
+			"/refs/heads/a",
+			"refs/heads/a/",
+			"refs/heads/a.",
+			"refs/heads/.hidden",
+			"refs/heads/x.lock",
+		] {
+			assert!(validate_ref_name(name).is_err(), "{:?}", name);
+		}
+	}
+
+	#[test]
+	fn reason() {
+		let reason = |name: &str| validate_ref_name(name).unwrap_err().to_string();
+		assert_eq!(reason("refs/heads/a b"), "ref name cannot contain ' '");
+		assert_eq!(reason("refs/heads/a..b"), "ref name cannot contain '..'");
+		assert_eq!(
+			reason("refs/heads/x.lock"),
+			"bad ref name component 'x.lock'"
+		);
+	}
+}
@@ -0,0 +1,248 @@
+// grepで使う正規表現
+// basic(BRE)では "+", "?", "|", "()" はただの文字で、extended(ERE)ではそれぞれ特別な意味を持つ
+pub struct Regex {
+	alternatives: Vec<Vec<Node>>,
+	ignore_case: bool,
+}
+
+enum Node {
+	Char(char),
+	Any,
+	Class {
+		ranges: Vec<(char, char)>,
+		negated: bool,
+	},
+	Start,
+	End,
+	Group(Vec<Vec<Node>>),
+	Repeat {
+		node: Box<Node>,
+		min: usize,
+		max: Option<usize>,
+	},
+}
+
+impl Regex {
+	pub fn new(pattern: &str, extended: bool, ignore_case: bool) -> Option<Self> {
+		let pattern: Vec<char> = pattern.chars().collect();
+
+		let mut parser = Parser {
+			p: &pattern,
+			i: 0,
+			extended,
+		};
+		let alternatives = parser.alternatives()?;
+		if parser.i != pattern.len() {
+			return None; // 対応しない ")"
+		}
+		Some(Self {
+			alternatives,
+			ignore_case,
+		})
+	}
+
+	// textのどこかに一致するか
+	pub fn is_match(&self, text: &str) -> bool {
+		let text: Vec<char> = text.chars().collect();
+
+		(0..=text.len())
+			.any(|start| !ends(&self.alternatives, &text, &[start], self.ignore_case).is_empty())
+	}
+}
+
+struct Parser<'a> {
+	p: &'a [char],
+	i: usize,
+	extended: bool,
+}
+
+impl Parser<'_> {
+	// "|" で区切られた並び
+	fn alternatives(&mut self) -> Option<Vec<Vec<Node>>> {
+		let mut alternatives = vec![self.sequence()?];
+		while self.extended && self.p.get(self.i) == Some(&'|') {
+			self.i += 1;
+			alternatives.push(self.sequence()?);
+		}
+		Some(alternatives)
+	}
+
+	fn sequence(&mut self) -> Option<Vec<Node>> {
+		let mut nodes: Vec<Node> = Vec::new();
+		while let Some(&c) = self.p.get(self.i) {
+			if self.extended && (c == '|' || c == ')') {
+				break;
+			}
+			self.i += 1;
+
+			let (min, max) = match c {
+				'*' => (0, None),
+				'+' if self.extended => (1, None),
+				'?' if self.extended => (0, Some(1)),
+				_ => {
+					nodes.push(self.atom(c, nodes.is_empty())?);
+					continue;
+				}
+			};
+			// 先頭の "*" はただの文字
+			match nodes.pop() {
+				Some(node) => nodes.push(Node::Repeat {
+					node: Box::new(node),
+					min,
+					max,
+				}),
+				None => nodes.push(Node::Char(c)),
+			}
+		}
+		Some(nodes)
+	}
+
+	fn atom(&mut self, c: char, first: bool) -> Option<Node> {
+		let node = match c {
+			'.' => Node::Any,
+			'^' if first || self.extended => Node::Start,
+			'$' if self.extended || self.i == self.p.len() => Node::End,
+			'[' => self.class()?,
+			'(' if self.extended => {
+				let group = self.alternatives()?;
+				if self.p.get(self.i) != Some(&')') {
+					return None;
+				}
+				self.i += 1;
+				Node::Group(group)
+			}
+			'\\' => {
+				let c = *self.p.get(self.i)?;
+				self.i += 1;
+				Node::Char(c)
+			}
+			c => Node::Char(c),
+		};
+		Some(node)
+	}
+
+	// "[" の後ろから "]" まで読む
+	fn class(&mut self) -> Option<Node> {
+		let negated = self.p.get(self.i) == Some(&'^');
+		if negated {
+			self.i += 1;
+		}
+
+		let mut ranges = Vec::new();
+		let start = self.i;
+		loop {
+			let lo = *self.p.get(self.i)?;
+			// 先頭の "]" は文字として扱う
+			if lo == ']' && self.i > start {
+				self.i += 1;
+				return Some(Node::Class { ranges, negated });
+			}
+			if lo == '[' && self.p.get(self.i + 1) == Some(&':') {
+				let rest: String = self.p[self.i + 2..].iter().collect();
+				let name = &rest[..rest.find(":]")?];
+				ranges.extend(named_class(name)?);
+				self.i += name.chars().count() + 4;
+				continue;
+			}
+			match (self.p.get(self.i + 1), self.p.get(self.i + 2)) {
+				(Some('-'), Some(&hi)) if hi != ']' => {
+					ranges.push((lo, hi));
+					self.i += 3;
+				}
+				_ => {
+					ranges.push((lo, lo));
+					self.i += 1;
+				}
+			}
+		}
+	}
+}
+
+// "[:digit:]" のような名前の付いた文字の集合
+fn named_class(name: &str) -> Option<Vec<(char, char)>> {
+	let ranges = match name {
+		"alpha" => vec![('a', 'z'), ('A', 'Z')],
+		"digit" => vec![('0', '9')],
+		"alnum" => vec![('a', 'z'), ('A', 'Z'), ('0', '9')],
+		"upper" => vec![('A', 'Z')],
+		"lower" => vec![('a', 'z')],
+		"space" => vec![(' ', ' '), ('\t', '\r')],
+		"blank" => vec![(' ', ' '), ('\t', '\t')],
+		"punct" => vec![('!', '/'), (':', '@'), ('[', '`'), ('{', '~')],
+		"xdigit" => vec![('0', '9'), ('a', 'f'), ('A', 'F')],
+		_ => return None,
+	};
+	Some(ranges)
+}
+
+// startsのそれぞれから並びに一致させたときに、一致が終わる位置の全て
+fn ends(alternatives: &[Vec<Node>], text: &[char], starts: &[usize], icase: bool) -> Vec<usize> {
+	let mut result = Vec::new();
+	for nodes in alternatives {
+		let mut positions = starts.to_vec();
+		for node in nodes {
+			positions = step(node, text, &positions, icase);
+			if positions.is_empty() {
+				break;
+			}
+		}
+		result.extend(positions);
+	}
+	result.sort();
+	result.dedup();
+	result
+}
+
+fn step(node: &Node, text: &[char], positions: &[usize], icase: bool) -> Vec<usize> {
+	// icaseなら大文字と小文字のどちらかが一致すればよい
+	let single = |hit: &dyn Fn(char) -> bool| -> Vec<usize> {
+		let hit =
+			|c: char| hit(c) || (icase && (c.to_lowercase().any(hit) || c.to_uppercase().any(hit)));
+		positions
+			.iter()
+			.filter(|&&i| text.get(i).is_some_and(|&c| hit(c)))
+			.map(|i| i + 1)
+			.collect()
+	};
+
+	match node {
+		Node::Char(x) => single(&|c| c == *x),
+		Node::Any => single(&|_| true),
+		Node::Class { ranges, negated } => {
+			single(&|c| ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) != *negated)
+		}
+		Node::Start => positions.iter().copied().filter(|&i| i == 0).collect(),
+		Node::End => positions
+			.iter()
+			.copied()
+			.filter(|&i| i == text.len())
+			.collect(),
+		Node::Group(alternatives) => ends(alternatives, text, positions, icase),
+		Node::Repeat { node, min, max } => {
+			let mut result = Vec::new();
+			let mut current = positions.to_vec();
+			let mut count = 0;
+			loop {
+				if count >= *min {
+					result.extend(current.iter().copied());
+				}
+				if max.is_some_and(|max| count >= max) || current.is_empty() {
+					break;
+				}
+				let mut next = step(node, text, &current, icase);
+				// 空文字列に一致し続ける場合に止まるように、新しい位置だけを残す
+				next.retain(|x| !result.contains(x) || count < *min);
+				next.sort();
+				next.dedup();
+				if next.is_empty() && count >= *min {
+					break;
+				}
+				current = next;
+				count += 1;
+			}
+			result.sort();
+			result.dedup();
+			result
+		}
+	}
+}
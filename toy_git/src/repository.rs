@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::cmd;
+use crate::commit_graph::CommitGraph;
+use crate::config::Config;
+use crate::hash::{Hash, HashAlgorithm};
+use crate::refs::Refs;
+use crate::store::ObjectStore;
+
+pub struct Repository {
+	pub workdir: PathBuf, // bareならgitdirと同じ
+	pub gitdir: PathBuf,
+	// linked worktreeでは本体の.git。objectやref、configはここにあり、それ以外ならgitdirと同じ
+	pub commondir: PathBuf,
+	pub bare: bool,                                     // working directoryが無い
+	pub replace_objects: bool,                          // falseならrefs/replace/を無視する
+	pub replace_map: OnceLock<HashMap<String, String>>, // 最初に使うときにrefs/replace/から読む
+	pub quarantine: Mutex<Option<PathBuf>>, // ObjectTransactionの間にobjectを書くディレクトリ
+	pub commit_graph: OnceLock<Option<CommitGraph>>, // 最初に使うときにobjects/info/commit-graphから読む
+	pub hash_algo: HashAlgorithm, // extensions.objectFormat。objectやindexのhash
+}
+
+impl Repository {
+	// pathから親ディレクトリを遡って.gitを探す
+	// core.bareがtrueのrepositoryそのもの(.gitの中身が直下にあるもの)が先に見つかればbareとして開く
+	pub fn discover(path: &Path) -> io::Result<Self> {
+		let path = path.canonicalize()?;
+
+		for dir in path.ancestors() {
+			if dir.join(".git").is_dir() {
+				let mut repo = Self::new(dir.to_path_buf(), dir.join(".git"), false);
+				repo.check_format()?;
+				return Ok(repo);
+			}
+			// linked worktreeの.gitは本体の.git/worktrees/<name>を指すファイル
+			if dir.join(".git").is_file() {
+				let gitdir = read_gitfile(&dir.join(".git"))?;
+				let mut repo = Self::new(dir.to_path_buf(), gitdir, false);
+				repo.check_format()?;
+				return Ok(repo);
+			}
+			if is_gitdir(dir) {
+				let mut repo = Self::new(dir.to_path_buf(), dir.to_path_buf(), true);
+				if Config::load(&repo)?.get_bool("core.bare") == Some(true) {
+					repo.check_format()?;
+					return Ok(repo);
+				}
+			}
+		}
+		Err(io::Error::other(
+			"not a git repository (or any of the parent directories): .git",
+		))
+	}
+
+	// pathにrepositoryを作って開く。bareでなければpath/.gitに作る
+	// git initと同じく既にあれば壊さず、HEADはinit.defaultBranchかmainを指す
+	pub fn init(path: &Path, bare: bool) -> io::Result<Self> {
+		let opts = cmd::InitOptions {
+			bare,
+			template: None,
+			initial_branch: None,
+			quiet: true,
+			object_format: None,
+		};
+		cmd::init(path, &opts)?;
+		match bare {
+			true => Self::open_bare(path),
+			false => Self::discover(path),
+		}
+	}
+
+	// pathをbare repositoryとして開く
+	pub fn open_bare(path: &Path) -> io::Result<Self> {
+		let path = path.canonicalize()?;
+		if !is_gitdir(&path) {
+			return Err(io::Error::other(format!(
+				"not a git repository: '{}'",
+				path.display()
+			)));
+		}
+
+		let mut repo = Self::new(path.clone(), path, true);
+		repo.check_format()?;
+		Ok(repo)
+	}
+
+	// gitdirに "commondir" があれば、そこに書かれたディレクトリを共有する
+	fn new(workdir: PathBuf, gitdir: PathBuf, bare: bool) -> Self {
+		let commondir = match fs::read_to_string(gitdir.join("commondir")) {
+			Ok(x) => {
+				let path = gitdir.join(x.trim_end_matches('\n'));
+				path.canonicalize().unwrap_or(path)
+			}
+			Err(_) => gitdir.clone(),
+		};
+		Self {
+			workdir,
+			gitdir,
+			commondir,
+			bare,
+			replace_objects: env::var_os("GIT_NO_REPLACE_OBJECTS").is_none(),
+			replace_map: OnceLock::new(),
+			quarantine: Mutex::new(None),
+			commit_graph: OnceLock::new(),
+			hash_algo: HashAlgorithm::Sha1,
+		}
+	}
+
+	// working directoryを読み書きするcommandは、bare repositoryではError
+	pub fn require_worktree(&self) -> io::Result<()> {
+		match self.bare {
+			true => Err(io::Error::other(
+				"this operation must be run in a work tree",
+			)),
+			false => Ok(()),
+		}
+	}
+
+	// 理解できない形式のrepositoryを書き換えて壊さないように、gitと同じく開く前に確かめる
+	// version 0ではextensions.*を無視し(v1でしか使えないものはError)、1では知らないものがあればError
+	// 読める形式なら、extensions.objectFormatのhashを使うようにする
+	fn check_format(&mut self) -> io::Result<()> {
+		let config = Config::load_repo(self)?;
+		let version = match config.get("core.repositoryformatversion") {
+			Some(x) => x.parse::<u32>().map_err(|_| {
+				io::Error::new(
+					io::ErrorKind::InvalidData,
+					format!(
+						"bad numeric config value '{}' for 'core.repositoryformatversion'",
+						x
+					),
+				)
+			})?,
+			None => 0,
+		};
+		if version > 1 {
+			return Err(io::Error::other(format!(
+				"Expected git repo version <= 1, found {}",
+				version
+			)));
+		}
+
+		let mut unknown = Vec::new();
+		let mut v1_only = Vec::new();
+		for (key, _) in config.entries.iter() {
+			let name = match key.strip_prefix("extensions.") {
+				Some(x) => x,
+				None => continue,
+			};
+			let list = match name {
+				"noop" | "preciousobjects" | "partialclone" | "worktreeconfig" => continue,
+				"noop-v1" | "objectformat" => &mut v1_only,
+				_ => &mut unknown,
+			};
+			if !list.contains(&name) {
+				list.push(name);
+			}
+		}
+
+		let found = |message: &str, names: &[&str]| {
+			let plural = if names.len() > 1 { "s" } else { "" };
+			let names: String = names.iter().map(|x| format!("\n\t{}", x)).collect();
+			Err(io::Error::other(format!(
+				"{}{} found:{}",
+				message, plural, names
+			)))
+		};
+		match version {
+			0 if !v1_only.is_empty() => {
+				return found("repo version is 0, but v1-only extension", &v1_only)
+			}
+			1 if !unknown.is_empty() => return found("unknown repository extension", &unknown),
+			_ => {}
+		}
+
+		// version 0ならextensions.objectFormatは既にErrorになっている
+		if let Some(x) = config.get("extensions.objectformat") {
+			self.hash_algo = HashAlgorithm::from_name(x).ok_or_else(|| {
+				io::Error::other(format!(
+					"unsupported object format '{}' for 'extensions.objectformat'",
+					x
+				))
+			})?;
+		}
+		Ok(())
+	}
+
+	// packやcommit-graphなど、SHA-1のrepositoryでしか読み書きできないものを使う前に確かめる
+	pub fn require_sha1(&self, what: &str) -> io::Result<()> {
+		match self.hash_algo {
+			HashAlgorithm::Sha1 => Ok(()),
+			algo => Err(io::Error::other(format!(
+				"{} are not supported in {} repositories",
+				what,
+				algo.name()
+			))),
+		}
+	}
+
+	pub fn objects(&self) -> ObjectStore<'_> {
+		ObjectStore::new(self)
+	}
+
+	pub fn refs(&self) -> Refs<'_> {
+		Refs::new(self)
+	}
+
+	// indexの内容でHEADのbranchにcommitし、そのhashを返す
+	// commit -m と同じく、authorとcommitterはconfigか環境変数から取り、hookも実行する
+	pub fn commit(&self, message: &str) -> io::Result<Hash> {
+		let opts = cmd::CommitOptions {
+			amend: false,
+			allow_empty: false,
+			no_verify: false,
+			dry_run: false,
+			signoff: false,
+		};
+		cmd::create_commit(self, Some(message), None, &opts)?
+			.ok_or_else(|| io::Error::other("nothing committed"))
+	}
+
+	// 今のObjectTransactionのディレクトリ
+	pub fn quarantine_dir(&self) -> Option<PathBuf> {
+		self.quarantine
+			.lock()
+			.unwrap_or_else(|e| e.into_inner())
+			.clone()
+	}
+
+	pub fn end_quarantine(&self) {
+		*self.quarantine.lock().unwrap_or_else(|e| e.into_inner()) = None;
+	}
+
+	pub fn objects_dir(&self) -> PathBuf {
+		self.commondir.join("objects")
+	}
+
+	// refのファイルのpath。HEADなどrefs/の外のものとrefs/bisect/はworktreeごとに持ち、他は共有する
+	pub fn ref_path(&self, name: &str) -> PathBuf {
+		self.ref_base(name).join(name)
+	}
+
+	// reflogもrefと同じ側に置く
+	pub fn reflog_path(&self, name: &str) -> PathBuf {
+		self.ref_base(name).join("logs").join(name)
+	}
+
+	fn ref_base(&self, name: &str) -> &Path {
+		match name.starts_with("refs/") && !name.starts_with("refs/bisect/") {
+			true => &self.commondir,
+			false => &self.gitdir,
+		}
+	}
+
+	// cwdからのpathをworkdirからの "/" 区切りのpathに直す
+	// 存在しないpathも扱えるようにcanonicalizeせずに"."と".."を解決する
+	pub fn relative_path(&self, path: &Path) -> io::Result<String> {
+		let absolute = env::current_dir()?.join(path);
+
+		let mut normalized = PathBuf::new();
+		for component in absolute.components() {
+			match component {
+				Component::CurDir => {}
+				Component::ParentDir => {
+					normalized.pop();
+				}
+				x => normalized.push(x),
+			}
+		}
+
+		let relative = normalized
+			.strip_prefix(&self.workdir)
+			.map_err(|_| io::Error::other(format!("{}: outside repository", path.display())))?;
+
+		Ok(relative
+			.components()
+			.map(|x| x.as_os_str().to_string_lossy())
+			.collect::<Vec<_>>()
+			.join("/"))
+	}
+}
+
+// "gitdir: <path>" の形の.gitファイルが指すディレクトリ。相対pathは.gitファイルのある場所から
+fn read_gitfile(path: &Path) -> io::Result<PathBuf> {
+	let content = fs::read_to_string(path)?;
+	let gitdir = content
+		.strip_prefix("gitdir: ")
+		.map(|x| x.trim_end_matches('\n'))
+		.ok_or_else(|| io::Error::other(format!("invalid gitfile format: {}", path.display())))?;
+	let gitdir = path.parent().unwrap_or(Path::new(".")).join(gitdir);
+	if !gitdir.is_dir() {
+		return Err(io::Error::other(format!(
+			"not a git repository: {}",
+			gitdir.display()
+		)));
+	}
+	gitdir.canonicalize()
+}
+
+// HEAD、objects/、refs/があればrepositoryのディレクトリとみなす
+fn is_gitdir(path: &Path) -> bool {
+	path.join("HEAD").is_file() && path.join("objects").is_dir() && path.join("refs").is_dir()
+}
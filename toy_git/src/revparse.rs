@@ -0,0 +1,293 @@
+use std::cmp::Ordering;
+use std::io;
+
+use crate::date;
+use crate::hash::Hash;
+use crate::object::{Commit, ObjectKind, Tag, Tree};
+use crate::refs;
+use crate::repository::Repository;
+use crate::store;
+use crate::upstream::Upstream;
+
+// "HEAD~2" や "v1.0^{tree}" のようなrevisionをobjectのhashに解決する
+// "HEAD:src/main.rs" はそのcommitのtreeの中のpathにあるobjectになる
+pub fn resolve(repo: &Repository, spec: &str) -> io::Result<Hash> {
+	// "HEAD@{10:00}" のような "@{...}" の中の ":" では分けない
+	let mut depth = 0;
+	let colon = spec.find(|x| {
+		match x {
+			'{' => depth += 1,
+			'}' => depth -= 1,
+			_ => {}
+		}
+		x == ':' && depth == 0
+	});
+	if let Some((rev, path)) = colon.map(|i| (&spec[..i], &spec[i + 1..])) {
+		if rev.is_empty() {
+			return Err(unknown(spec));
+		}
+		let tree = peel(repo, resolve(repo, rev)?, Some(ObjectKind::Tree))?;
+		return tree_path(repo, tree, path)?.ok_or_else(|| {
+			io::Error::new(
+				io::ErrorKind::NotFound,
+				format!("path '{}' does not exist in '{}'", path, rev),
+			)
+		});
+	}
+
+	// ref名には "~" と "^" は使えないので、最初に現れたところからがsuffix
+	let split = spec.find(['~', '^']).unwrap_or(spec.len());
+	let (base, mut suffix) = spec.split_at(split);
+
+	let mut hash = resolve_name(repo, base)?.ok_or_else(|| unknown(spec))?;
+
+	while !suffix.is_empty() {
+		let op = suffix.as_bytes()[0];
+		suffix = &suffix[1..];
+
+		if op == b'^' && suffix.starts_with('{') {
+			let end = suffix.find('}').ok_or_else(|| unknown(spec))?;
+			let kind = &suffix[1..end];
+			suffix = &suffix[end + 1..];
+
+			hash = match kind {
+				// "^{}" はtagを辿りきる
+				"" => peel(repo, hash, None)?,
+				x => {
+					let kind = ObjectKind::from(x).ok_or_else(|| unknown(spec))?;
+					peel(repo, hash, Some(kind))?
+				}
+			};
+			continue;
+		}
+
+		let digits = suffix.len() - suffix.trim_start_matches(|x: char| x.is_ascii_digit()).len();
+		let n = match digits {
+			0 => 1, // 数字が無い場合は1として扱う
+			_ => suffix[..digits].parse::<usize>().map_err(|_| unknown(spec))?,
+		};
+		suffix = &suffix[digits..];
+
+		hash = match op {
+			// "~n" は最初のparentをn回辿る
+			b'~' => (0..n).try_fold(hash, |x, _| parent(repo, x, 1)?.ok_or_else(|| unknown(spec)))?,
+			// "^n" はn番目のparent("^0" はcommit自身)
+			_ => match n {
+				0 => peel(repo, hash, Some(ObjectKind::Commit))?,
+				n => parent(repo, hash, n)?.ok_or_else(|| unknown(spec))?,
+			},
+		};
+	}
+
+	Ok(hash)
+}
+
+// 全ての桁のhash、HEAD、branchやtagの名前、"main@{u}" のようなupstream、
+// "HEAD@{2}" や "main@{yesterday}" のようなreflogでの値を解決する
+fn resolve_name(repo: &Repository, name: &str) -> io::Result<Option<Hash>> {
+	if let Some(hash) = Hash::from_hex(name).filter(|x| x.0.len() == repo.hash_algo.hash_len()) {
+		return Ok(Some(hash));
+	}
+	if let Some((branch, at)) = name.split_once("@{") {
+		let Some(at) = at.strip_suffix('}') else {
+			return Ok(None);
+		};
+		if matches!(at.to_lowercase().as_str(), "u" | "upstream") {
+			let name = upstream(repo, branch)?;
+			return Ok(refs::resolve(repo, &name)?.and_then(|x| Hash::from_hex(&x)));
+		}
+		return reflog(repo, branch, at);
+	}
+
+	if let Some(name) = dwim(repo, name)? {
+		return Ok(refs::resolve(repo, &name)?.and_then(|x| Hash::from_hex(&x)));
+	}
+	short_hash(repo, name)
+}
+
+// 4桁以上の短いhash。gitと同じく、同じ名前のrefがあればそちらが先
+// 複数のobjectに当てはまるときは候補を出してNone
+fn short_hash(repo: &Repository, name: &str) -> io::Result<Option<Hash>> {
+	if name.len() < 4 || !name.bytes().all(|x| x.is_ascii_hexdigit()) {
+		return Ok(None);
+	}
+	let mut hashes = store::find_prefix(repo, name)?;
+	if hashes.len() <= 1 {
+		return Ok(hashes.pop().map(Hash));
+	}
+
+	// gitと同じくtag、commit、tree、blobの順に並べる
+	let mut candidates = Vec::with_capacity(hashes.len());
+	for hash in hashes {
+		let kind = store::read_object_header(repo, &hash)?.0;
+		let order = [ObjectKind::Tag, ObjectKind::Commit, ObjectKind::Tree, ObjectKind::Blob]
+			.iter()
+			.position(|x| *x == kind);
+		candidates.push((order, Hash(hash), kind));
+	}
+	candidates.sort_by_key(|x| x.0);
+	eprintln!("error: short object ID {} is ambiguous", name);
+	eprintln!("hint: The candidates are:");
+	for (_, hash, kind) in candidates {
+		eprintln!("hint:   {} {}", &hash.to_hex()[..7], kind);
+	}
+	Ok(None)
+}
+
+// "@{n}" はreflogでn回前の値、"@{<date>}" はその時点での値
+// nameが空なら今のbranch(detached HEADならHEAD)のreflogを使う
+fn reflog(repo: &Repository, name: &str, at: &str) -> io::Result<Option<Hash>> {
+	let name = match name {
+		"" => match refs::head_target(repo)? {
+			target if target.starts_with("refs/heads/") => target,
+			_ => String::from("HEAD"),
+		},
+		name => match dwim(repo, name)? {
+			Some(name) => name,
+			None => return Ok(None),
+		},
+	};
+	let mut entries = refs::read_reflog(repo, &name)?;
+	let short = refs::shorten(&name);
+	let null = |x: &Hash| x.0.iter().all(|&b| b == 0);
+
+	if !at.is_empty() && at.chars().all(|x| x.is_ascii_digit()) {
+		let n = at.parse::<usize>().map_err(|_| unknown(at))?;
+		return match n.cmp(&entries.len()) {
+			Ordering::Less => Ok(Some(entries.swap_remove(entries.len() - 1 - n).new)),
+			// 一番古いentryの前の値も、0のhashでなければ使える
+			Ordering::Equal if entries.first().is_some_and(|x| !null(&x.old)) => Ok(Some(entries.swap_remove(0).old)),
+			_ => Err(io::Error::other(format!(
+				"log for '{}' only has {} entries",
+				short,
+				entries.len()
+			))),
+		};
+	}
+
+	// "1.day.ago" のように "." で区切ってもよい
+	let Some(date) = date::parse(at).or_else(|| date::parse(&at.replace('.', " "))) else {
+		return Ok(None);
+	};
+	match entries.iter().rposition(|x| x.date <= date) {
+		Some(i) => Ok(Some(entries.swap_remove(i).new)),
+		None if entries.is_empty() => Ok(None),
+		None => {
+			let first = entries.swap_remove(0);
+			eprintln!(
+				"warning: log for '{}' only goes back to {}",
+				short,
+				first.date.format("%a, %-d %b %Y %H:%M:%S %z")
+			);
+			Ok(Some(if null(&first.old) { first.new } else { first.old }))
+		}
+	}
+}
+
+// branchのupstreamのref。branchが空かHEADなら今のbranchのもの
+pub fn upstream(repo: &Repository, branch: &str) -> io::Result<String> {
+	let branch = match branch {
+		"" | "HEAD" | "@" => match refs::head_target(repo)?.strip_prefix("refs/heads/") {
+			Some(branch) => String::from(branch),
+			None => return Err(io::Error::other("HEAD does not point to a branch")),
+		},
+		branch if refs::read_ref(repo, &format!("refs/heads/{}", branch))?.is_some() => String::from(branch),
+		branch => return Err(io::Error::other(format!("no such branch: '{}'", branch))),
+	};
+	match Upstream::load(repo, &branch)? {
+		Some(upstream) => Ok(upstream.tracking_ref()),
+		None => Err(io::Error::other(format!("no upstream configured for branch '{}'", branch))),
+	}
+}
+
+// "main" のような短い名前を、存在するrefの完全な名前にする
+pub fn dwim(repo: &Repository, name: &str) -> io::Result<Option<String>> {
+	// "@" はHEADの別名
+	let name = if name == "@" { "HEAD" } else { name };
+
+	// gitと同じ順でref名を補って探す
+	let candidates = [
+		String::from(name),
+		format!("refs/{}", name),
+		format!("refs/tags/{}", name),
+		format!("refs/heads/{}", name),
+		format!("refs/remotes/{}", name),
+		format!("refs/remotes/{}/HEAD", name),
+	];
+	for candidate in candidates {
+		if refs::resolve(repo, &candidate)?.is_some() {
+			return Ok(Some(candidate));
+		}
+	}
+
+	Ok(None)
+}
+
+// tagを辿ってkindのobjectにする。kindがNoneならtag以外になるまで辿る
+// commitからtreeを求めたときはcommitのtreeになる
+pub fn peel(repo: &Repository, hash: Hash, kind: Option<ObjectKind>) -> io::Result<Hash> {
+	let mut hash = hash;
+
+	loop {
+		let (current, body) = store::read_object(repo, &hash.0)?;
+		if Some(current) == kind || (kind.is_none() && current != ObjectKind::Tag) {
+			return Ok(hash);
+		}
+
+		hash = match current {
+			ObjectKind::Tag => Tag::from(&body).map(|x| x.object),
+			ObjectKind::Commit if kind == Some(ObjectKind::Tree) => Commit::from(&body).map(|x| x.tree),
+			_ => {
+				return Err(io::Error::other(format!(
+					"{} is a {}, cannot be peeled to {}",
+					hash,
+					current,
+					kind.map(|x| x.as_str()).unwrap_or("non-tag")
+				)))
+			}
+		}
+		.map_err(|e| e.context(format!("bad {} object {}", current, hash)))
+		.and_then(|x| Hash::from_hex(&x).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("bad {} object {}", current, hash))))?;
+	}
+}
+
+// treeからpathを "/" で区切った名前ずつ辿ったobject。空のpathならtree自身
+// 途中の名前が無いかtreeでなければNone
+pub fn tree_path(repo: &Repository, tree: Hash, path: &str) -> io::Result<Option<Hash>> {
+	let mut hash = tree;
+	for name in path.split('/').filter(|x| !x.is_empty()) {
+		let (kind, body) = store::read_object(repo, &hash.0)?;
+		if kind != ObjectKind::Tree {
+			return Ok(None);
+		}
+		let tree = Tree::from(&body, repo.hash_algo)
+			.map_err(|e| e.context(format!("bad tree object {}", hash)))?;
+		match tree.contents.into_iter().find(|x| x.name == name) {
+			Some(file) => hash = Hash(file.hash),
+			None => return Ok(None),
+		}
+	}
+	Ok(Some(hash))
+}
+
+// n番目(1から数える)のparent
+fn parent(repo: &Repository, hash: Hash, n: usize) -> io::Result<Option<Hash>> {
+	let commit = store::read_commit(repo, &peel(repo, hash, Some(ObjectKind::Commit))?.0)?;
+
+	match n.checked_sub(1).and_then(|i| commit.parents.get(i)) {
+		Some(parent) => Ok(Some(object_hash(parent)?)),
+		None => Ok(None),
+	}
+}
+
+fn object_hash(hex: &str) -> io::Result<Hash> {
+	Hash::from_hex(hex)
+		.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("bad object name {}", hex)))
+}
+
+fn unknown(spec: &str) -> io::Error {
+	io::Error::new(
+		io::ErrorKind::NotFound,
+		format!("ambiguous argument '{}': unknown revision", spec),
+	)
+}
@@ -0,0 +1,95 @@
+use std::fs;
+use std::io;
+
+use crate::config::Config;
+use crate::repository::Repository;
+use crate::wildmatch::wildmatch;
+
+// .git/info/sparse-checkout の1行
+// gitignoreと同じ書き方で、一致したpathだけがworking directoryに置かれる
+struct Pattern {
+	pattern: String,
+	negated: bool,  // "!" で始まる行は一致したpathを除外する
+	dir_only: bool, // "/" で終わる行はディレクトリだけに一致する
+	anchored: bool, // 先頭か途中に "/" があれば、path全体と比べる
+}
+
+pub struct Sparse {
+	patterns: Vec<Pattern>,
+}
+
+impl Sparse {
+	pub fn from(content: &str) -> Self {
+		let patterns = content
+			.lines()
+			.map(str::trim_end)
+			.filter(|x| !x.is_empty() && !x.starts_with('#'))
+			.map(|line| {
+				let (negated, line) = match line.strip_prefix('!') {
+					Some(rest) => (true, rest),
+					None => (false, line),
+				};
+				let dir_only = line.ends_with('/');
+				let line = line.trim_end_matches('/');
+				Pattern {
+					pattern: String::from(line.trim_start_matches('/')),
+					negated,
+					dir_only,
+					anchored: line.contains('/'),
+				}
+			})
+			.collect();
+
+		Self { patterns }
+	}
+
+	// core.sparseCheckoutが有効なときだけ読む
+	pub fn load(repo: &Repository) -> io::Result<Option<Self>> {
+		if Config::load(repo)?.get_bool("core.sparseCheckout") != Some(true) {
+			return Ok(None);
+		}
+		match fs::read_to_string(repo.gitdir.join("info").join("sparse-checkout")) {
+			Ok(content) => Ok(Some(Self::from(&content))),
+			Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Some(Self::from(""))),
+			Err(e) => Err(e),
+		}
+	}
+
+	// pathをworking directoryに置くか
+	// ファイル自身、近い親ディレクトリの順に調べ、最初に何かの行に一致したものが結果になる
+	pub fn includes(&self, path: &str) -> bool {
+		let mut current = path;
+		let mut is_dir = false;
+		loop {
+			if let Some(included) = self.check(current, is_dir) {
+				return included;
+			}
+			match current.rsplit_once('/') {
+				Some((parent, _)) => current = parent,
+				None => return false,
+			}
+			is_dir = true;
+		}
+	}
+
+	// 後に書かれた行が優先される
+	fn check(&self, path: &str, is_dir: bool) -> Option<bool> {
+		self.patterns
+			.iter()
+			.rev()
+			.find(|x| (is_dir || !x.dir_only) && x.matches(path))
+			.map(|x| !x.negated)
+	}
+}
+
+impl Pattern {
+	fn matches(&self, path: &str) -> bool {
+		match self.anchored {
+			true => wildmatch(&self.pattern, path, true),
+			false => {
+				let name = path.rsplit('/').next().unwrap_or(path);
+				wildmatch(&self.pattern, name, true)
+			}
+		}
+	}
+}
@@ -0,0 +1,558 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use libflate::zlib::Decoder;
+
+use crate::error::GitError;
+use crate::hash::{Hash, HashAlgorithm};
+use crate::object::{self, Commit, GitObject, ObjectKind, Tag, Tree, TreeEntry};
+use crate::pack;
+use crate::refs;
+use crate::repository::Repository;
+use crate::tempfile::TempFile;
+use crate::zlib;
+
+// libraryとして使うときのobjectの読み書き。Repository::objectsで作る
+// 下の関数をHashで呼べるようにしたもの
+pub struct ObjectStore<'a> {
+	repo: &'a Repository,
+}
+
+impl<'a> ObjectStore<'a> {
+	pub fn new(repo: &'a Repository) -> Self {
+		Self { repo }
+	}
+
+	// headerのtypeのobjectとして読む
+	pub fn read(&self, hash: &Hash) -> io::Result<GitObject> {
+		read_parsed(self.repo, &hash.0)
+	}
+
+	// typeとheaderを除いた内容
+	pub fn read_raw(&self, hash: &Hash) -> io::Result<(ObjectKind, Vec<u8>)> {
+		read_object(self.repo, &hash.0)
+	}
+
+	pub fn contains(&self, hash: &Hash) -> io::Result<bool> {
+		has_object(self.repo, &hash.0)
+	}
+
+	// bodyをkindのobjectとして保存する
+	pub fn write(&self, kind: ObjectKind, body: &[u8]) -> io::Result<Hash> {
+		write_object(self.repo, &kind.encode(body)).map(Hash)
+	}
+
+	// 16進数のprefixで始まる全てのobject
+	pub fn find_prefix(&self, prefix: &str) -> io::Result<Vec<Hash>> {
+		Ok(find_prefix(self.repo, prefix)?
+			.into_iter()
+			.map(Hash)
+			.collect())
+	}
+}
+
+// objectは hashの先頭2文字をディレクトリ名、残りをファイル名として保存される
+pub fn object_path(repo: &Repository, hash: &[u8]) -> PathBuf {
+	loose_path(&repo.objects_dir(), hash)
+}
+
+fn loose_path(dir: &Path, hash: &[u8]) -> PathBuf {
+	let hex = hex::encode(hash);
+	dir.join(&hex[..2]).join(&hex[2..])
+}
+
+// looseなobjectを読むファイル。ObjectTransactionの間はそのディレクトリにあればそちら
+fn loose_file(repo: &Repository, hash: &[u8]) -> PathBuf {
+	if let Some(dir) = repo.quarantine_dir() {
+		let path = loose_path(&dir, hash);
+		if path.exists() {
+			return path;
+		}
+	}
+	object_path(repo, hash)
+}
+
+// 既にあるlooseなobjectを書く代わりに、mtimeを今にしてtrueを返す。無ければfalse
+// gcは辿れないobjectでもpruneの期限より新しければ消さないので、同時に動いているgcに消されない
+pub fn freshen(path: &Path) -> io::Result<bool> {
+	match fs::File::open(path) {
+		Ok(file) => file.set_modified(SystemTime::now()).map(|_| true),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+		Err(e) => Err(e),
+	}
+}
+
+// 新しいlooseなobjectを書くディレクトリ
+fn write_dir(repo: &Repository) -> PathBuf {
+	repo.quarantine_dir().unwrap_or_else(|| repo.objects_dir())
+}
+
+// repositoryのhash(extensions.objectFormat)でのhash
+pub fn hash_bytes(repo: &Repository, bytes: &[u8]) -> Vec<u8> {
+	repo.hash_algo.digest(bytes)
+}
+
+// "<type> <size>\0<body>" の形のbyte列をzlibで圧縮して保存し、そのhashを返す
+// ObjectTransactionの間はそのディレクトリに置いておき、commitしたときにobjectsに移す
+pub fn write_object(repo: &Repository, bytes: &[u8]) -> io::Result<Vec<u8>> {
+	let hash = hash_bytes(repo, bytes);
+	write_loose(repo, &hash, bytes)?;
+	Ok(hash)
+}
+
+// looseなobjectとして保存する
+pub fn write_loose(repo: &Repository, hash: &[u8], bytes: &[u8]) -> io::Result<()> {
+	// 同じ内容のobjectは既に存在するので書き直さない
+	if freshen(&loose_file(repo, hash))? {
+		return Ok(());
+	}
+
+	let level = zlib::level(repo, "core.loosecompression", zlib::LOOSE_DEFAULT)?;
+	let compressed = zlib::compress(bytes, level)?;
+
+	// 途中で失敗しても壊れたobjectが残らないように、一時ファイルに書いてからrenameする
+	let dir = write_dir(repo);
+	let path = loose_path(&dir, hash);
+	let mut temp = TempFile::new(&dir, "tmp_obj")?;
+	temp.write_all(&compressed)?;
+
+	if let Some(dir) = path.parent() {
+		fs::create_dir_all(dir)?;
+	}
+	temp.persist(&path)
+}
+
+// 大きなファイルを丸ごとmemoryに載せないように、readerのsize byteをbodyとして
+// 少しずつhashしながら圧縮して一時ファイルに書き、looseなobjectとして保存する
+pub fn write_stream(
+	repo: &Repository,
+	kind: ObjectKind,
+	size: usize,
+	reader: &mut dyn Read,
+) -> io::Result<Vec<u8>> {
+	LooseWriter::new(repo)?.write_stream(kind, size, reader)
+}
+
+// 多くのobjectを書くときにconfigを1度だけ読むように、looseなobjectを書くのに要るものを持つ
+pub struct LooseWriter {
+	dir: PathBuf,     // 書くディレクトリ。ObjectTransactionの間はそのディレクトリ
+	objects: PathBuf, // objectsのディレクトリ
+	algo: HashAlgorithm,
+	level: u32, // core.looseCompression
+}
+
+impl LooseWriter {
+	pub fn new(repo: &Repository) -> io::Result<Self> {
+		Ok(Self {
+			dir: write_dir(repo),
+			objects: repo.objects_dir(),
+			algo: repo.hash_algo,
+			level: zlib::level(repo, "core.loosecompression", zlib::LOOSE_DEFAULT)?,
+		})
+	}
+
+	pub fn write_stream(
+		&self,
+		kind: ObjectKind,
+		size: usize,
+		reader: &mut dyn Read,
+	) -> io::Result<Vec<u8>> {
+		let temp = TempFile::new(&self.dir, "tmp_obj")?;
+		let mut encoder = zlib::encoder(io::BufWriter::new(temp), self.level)?;
+		let hash = copy_hashed(self.algo, kind, size, reader, &mut encoder)?;
+		let temp = encoder
+			.finish()
+			.into_result()?
+			.into_inner()
+			.map_err(|e| e.into_error())?;
+
+		let path = loose_path(&self.dir, &hash);
+		if freshen(&path)? || freshen(&loose_path(&self.objects, &hash))? {
+			return Ok(hash);
+		}
+		if let Some(dir) = path.parent() {
+			fs::create_dir_all(dir)?;
+		}
+		temp.persist(&path)?;
+		Ok(hash)
+	}
+}
+
+// write_streamと同じく少しずつ読んでhashだけを求める
+pub fn hash_stream(
+	algo: HashAlgorithm,
+	kind: ObjectKind,
+	size: usize,
+	reader: &mut dyn Read,
+) -> io::Result<Vec<u8>> {
+	copy_hashed(algo, kind, size, reader, &mut io::sink())
+}
+
+// headerとreaderの内容をoutに書きながらhashする。読めた長さがsizeと違えばError
+fn copy_hashed(
+	algo: HashAlgorithm,
+	kind: ObjectKind,
+	size: usize,
+	reader: &mut dyn Read,
+	out: &mut dyn Write,
+) -> io::Result<Vec<u8>> {
+	let header = kind.header(size);
+	let mut hasher = algo.hasher();
+	hasher.update(header.as_bytes());
+	out.write_all(header.as_bytes())?;
+
+	let mut buf = vec![0; 64 * 1024];
+	let mut total = 0;
+	loop {
+		let n = match reader.read(&mut buf) {
+			Ok(0) => break,
+			Ok(n) => n,
+			Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+			Err(e) => return Err(e),
+		};
+		hasher.update(&buf[..n]);
+		out.write_all(&buf[..n])?;
+		total += n;
+	}
+	check_size(size, total)?;
+	Ok(hasher.finalize())
+}
+
+// 読んでいる間にファイルが変わっていれば、headerのsizeと内容が合わなくなる
+fn check_size(expected: usize, actual: usize) -> io::Result<()> {
+	match expected == actual {
+		true => Ok(()),
+		false => Err(io::Error::other(format!(
+			"read {} bytes, expected {} (file changed while reading?)",
+			actual, expected
+		))),
+	}
+}
+
+// objectがrepositoryにあるか。中身は読まない
+pub fn has_object(repo: &Repository, hash: &[u8]) -> io::Result<bool> {
+	if loose_file(repo, hash).exists() {
+		return Ok(true);
+	}
+	pack::contains(repo, hash)
+}
+
+// objects/<xx>/ にあるlooseなobjectのhash
+pub fn loose_objects(repo: &Repository) -> io::Result<Vec<Vec<u8>>> {
+	let mut hashes = Vec::new();
+	for i in 0..=255u8 {
+		let entries = match fs::read_dir(repo.objects_dir().join(format!("{:02x}", i))) {
+			Ok(entries) => entries,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+			Err(e) => return Err(e),
+		};
+		for entry in entries {
+			let name = entry?.file_name();
+			let Some(name) = name.to_str() else {
+				continue;
+			};
+			// 書き込み途中の一時ファイルなどは数えない
+			match hex::decode(format!("{:02x}{}", i, name)) {
+				Ok(hash) if hash.len() == repo.hash_algo.hash_len() => hashes.push(hash),
+				_ => {}
+			}
+		}
+	}
+	Ok(hashes)
+}
+
+// looseなobjectと全てのpackのobjectのhash。looseとpackの両方にあるものは2度出る
+// packは.idxだけを読み、objectの中身は読まない
+pub fn objects_iter(repo: &Repository) -> io::Result<impl Iterator<Item = Vec<u8>>> {
+	let loose = loose_objects(repo)?;
+	let packs = pack::packs(repo)?;
+	Ok(loose.into_iter().chain(
+		packs
+			.into_iter()
+			.flat_map(|x| x.index.entries.into_iter().map(|x| x.hash)),
+	))
+}
+
+// hashの16進数がprefixで始まるobject。同じものは1度だけ、hashの順に返す
+pub fn find_prefix(repo: &Repository, prefix: &str) -> io::Result<Vec<Vec<u8>>> {
+	let prefix = prefix.to_lowercase();
+	let mut hashes: Vec<Vec<u8>> = objects_iter(repo)?
+		.filter(|x| hex::encode(x).starts_with(&prefix))
+		.collect();
+	hashes.sort();
+	hashes.dedup();
+	Ok(hashes)
+}
+
+// objectを展開してheaderのtypeとbodyを返す
+// refs/replace/で置き換えられていれば置き換え先のobjectを読む
+pub fn read_object(repo: &Repository, hash: &[u8]) -> io::Result<(ObjectKind, Vec<u8>)> {
+	let mut hash = hash.to_vec();
+
+	// 置き換え先がさらに置き換えられていても辿る。循環していれば止める
+	for _ in 0..5 {
+		match replacement(repo, &hash)? {
+			Some(replaced) => hash = replaced,
+			None => return read_original(repo, &hash),
+		}
+	}
+	Err(io::Error::other(format!(
+		"replace depth too high for object {}",
+		hex::encode(hash)
+	)))
+}
+
+fn replacement(repo: &Repository, hash: &[u8]) -> io::Result<Option<Vec<u8>>> {
+	if !repo.replace_objects {
+		return Ok(None);
+	}
+
+	let map = match repo.replace_map.get() {
+		Some(map) => map,
+		None => {
+			let map = refs::list(repo, "refs/replace")?
+				.into_iter()
+				.map(|(name, hash)| (String::from(&name["refs/replace/".len()..]), hash))
+				.collect();
+			repo.replace_map.get_or_init(|| map)
+		}
+	};
+
+	Ok(map
+		.get(&hex::encode(hash))
+		.and_then(|x| hex::decode(x).ok()))
+}
+
+// 置き換えを無視してhashそのもののobjectを読む
+// looseなobjectが無ければpackから探す
+pub fn read_original(repo: &Repository, hash: &[u8]) -> io::Result<(ObjectKind, Vec<u8>)> {
+	let compressed = match fs::read(loose_file(repo, hash)) {
+		Ok(compressed) => compressed,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => {
+			return pack::read_object(repo, hash)?.ok_or_else(|| {
+				io::Error::new(
+					io::ErrorKind::NotFound,
+					format!("object {} not found", hex::encode(hash)),
+				)
+			})
+		}
+		Err(e) => return Err(e),
+	};
+
+	let mut bytes = Vec::new();
+	Decoder::new(compressed.as_slice())?.read_to_end(&mut bytes)?;
+
+	split_header(&bytes).map_err(|e| e.context(format!("object {} is corrupt", hex::encode(hash))))
+}
+
+fn split_header(bytes: &[u8]) -> Result<(ObjectKind, Vec<u8>), GitError> {
+	object::split_header(bytes).map(|(kind, body)| (kind, body.to_vec()))
+}
+
+// bodyを丸ごと展開せずに少しずつ読めるようにして、typeとsizeと共に返す。置き換えはread_objectと同じく辿る
+// looseなobjectだけをそのまま展開しながら読み、packやObjectTransactionの間のものは読んでから渡す
+pub fn open_object(
+	repo: &Repository,
+	hash: &[u8],
+) -> io::Result<(ObjectKind, usize, Box<dyn Read>)> {
+	let mut hash = hash.to_vec();
+	for _ in 0..5 {
+		match replacement(repo, &hash)? {
+			Some(replaced) => hash = replaced,
+			None => return open_original(repo, &hash),
+		}
+	}
+	Err(io::Error::other(format!(
+		"replace depth too high for object {}",
+		hex::encode(hash)
+	)))
+}
+
+fn open_original(repo: &Repository, hash: &[u8]) -> io::Result<(ObjectKind, usize, Box<dyn Read>)> {
+	let file = match fs::File::open(loose_file(repo, hash)) {
+		Ok(file) => Some(file),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+		Err(e) => return Err(e),
+	};
+	let Some(file) = file else {
+		let (kind, body) = read_original(repo, hash)?;
+		return Ok((kind, body.len(), Box::new(io::Cursor::new(body))));
+	};
+
+	// headerはNULまでを1byteずつ展開して読む
+	let mut decoder = Decoder::new(io::BufReader::new(file))?;
+	let mut header = Vec::new();
+	let mut byte = [0];
+	while header.len() < 1024 {
+		decoder.read_exact(&mut byte)?;
+		if byte[0] == b'\0' {
+			break;
+		}
+		header.push(byte[0]);
+	}
+	let corrupt = || {
+		io::Error::new(
+			io::ErrorKind::InvalidData,
+			format!("object {} is corrupt", hex::encode(hash)),
+		)
+	};
+	let (kind, size) = std::str::from_utf8(&header)
+		.ok()
+		.and_then(|x| x.split_once(' '))
+		.and_then(|(kind, size)| Some((ObjectKind::from(kind)?, size.parse().ok()?)))
+		.ok_or_else(corrupt)?;
+	Ok((kind, size, Box::new(decoder.take(size as u64))))
+}
+
+// 内容を読まずにtypeとsizeだけを返す。置き換えはread_objectと同じく辿る
+pub fn read_object_header(repo: &Repository, hash: &[u8]) -> io::Result<(ObjectKind, usize)> {
+	let mut hash = hash.to_vec();
+	for _ in 0..5 {
+		match replacement(repo, &hash)? {
+			Some(replaced) => hash = replaced,
+			None => return read_original_header(repo, &hash),
+		}
+	}
+	Err(io::Error::other(format!(
+		"replace depth too high for object {}",
+		hex::encode(hash)
+	)))
+}
+
+// 置き換えを無視してhashそのもののobjectのtypeとsize
+pub fn read_original_header(repo: &Repository, hash: &[u8]) -> io::Result<(ObjectKind, usize)> {
+	let (kind, size) = read_raw_header(repo, hash)?;
+	let kind = ObjectKind::from(&kind).ok_or_else(|| {
+		io::Error::new(
+			io::ErrorKind::InvalidData,
+			format!("object {} is corrupt", hex::encode(hash)),
+		)
+	})?;
+	Ok((kind, size))
+}
+
+// typeを確かめずにheaderの "<type> <size>" を返す
+// looseなobjectはheaderの終わりまでだけを展開する
+pub fn read_raw_header(repo: &Repository, hash: &[u8]) -> io::Result<(String, usize)> {
+	let header = match fs::File::open(loose_file(repo, hash)) {
+		Ok(file) => {
+			let mut decoder = Decoder::new(io::BufReader::new(file))?;
+			let mut header = Vec::new();
+			let mut buf = [0; 64];
+			while !header.contains(&b'\0') && header.len() < 1024 {
+				let n = decoder.read(&mut buf)?;
+				if n == 0 {
+					break;
+				}
+				header.extend_from_slice(&buf[..n]);
+			}
+			header
+		}
+		Err(e) if e.kind() == io::ErrorKind::NotFound => {
+			let (kind, size) = pack::read_object_header(repo, hash)?.ok_or_else(|| {
+				io::Error::new(
+					io::ErrorKind::NotFound,
+					format!("object {} not found", hex::encode(hash)),
+				)
+			})?;
+			return Ok((kind.to_string(), size));
+		}
+		Err(e) => return Err(e),
+	};
+
+	let header = header.split(|&b| b == b'\0').next().unwrap_or_default();
+	String::from_utf8_lossy(header)
+		.split_once(' ')
+		.and_then(|(kind, size)| Some((String::from(kind), size.parse().ok()?)))
+		.ok_or_else(|| {
+			io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!("object {} has no header", hex::encode(hash)),
+			)
+		})
+}
+
+// typeを確かめずに "<type> <size>\0<body>" をそのまま返す(壊れたobjectを調べるため)
+// packには知っているtypeのobjectしか入らないので、looseなobjectでなければ読んでから組み立て直す
+pub fn read_raw(repo: &Repository, hash: &[u8]) -> io::Result<Vec<u8>> {
+	match fs::read(loose_file(repo, hash)) {
+		Ok(compressed) => {
+			let mut bytes = Vec::new();
+			Decoder::new(compressed.as_slice())?.read_to_end(&mut bytes)?;
+			Ok(bytes)
+		}
+		Err(e) if e.kind() == io::ErrorKind::NotFound => {
+			let (kind, body) = read_original(repo, hash)?;
+			Ok(kind.encode(&body))
+		}
+		Err(e) => Err(e),
+	}
+}
+
+// read_objectで読んだものをheaderのtypeのobjectにする
+pub fn read_parsed(repo: &Repository, hash: &[u8]) -> io::Result<GitObject> {
+	let (kind, body) = read_object(repo, hash)?;
+	GitObject::from(kind, &body, repo.hash_algo).map_err(|e| corrupt(hash, kind.as_str(), e))
+}
+
+pub fn read_commit(repo: &Repository, hash: &[u8]) -> io::Result<Commit> {
+	match read_parsed(repo, hash)? {
+		GitObject::Commit(commit) => Ok(commit),
+		object => Err(mismatch(hash, object.kind(), "commit")),
+	}
+}
+
+pub fn read_tree(repo: &Repository, hash: &[u8]) -> io::Result<Tree> {
+	match read_parsed(repo, hash)? {
+		GitObject::Tree(tree) => Ok(tree),
+		object => Err(mismatch(hash, object.kind(), "tree")),
+	}
+}
+
+// treeのentryを名前の順に、treeの中でのpathと共にfに渡す
+// recursiveならsubtreeの中も辿り、subtreeそのものは渡さない
+pub fn walk_tree(
+	repo: &Repository,
+	tree: &[u8],
+	recursive: bool,
+	f: &mut dyn FnMut(String, TreeEntry) -> io::Result<()>,
+) -> io::Result<()> {
+	walk_tree_in(repo, tree, "", recursive, f)
+}
+
+fn walk_tree_in(
+	repo: &Repository,
+	tree: &[u8],
+	prefix: &str,
+	recursive: bool,
+	f: &mut dyn FnMut(String, TreeEntry) -> io::Result<()>,
+) -> io::Result<()> {
+	for file in read_tree(repo, tree)?.contents {
+		let path = format!("{}{}", prefix, file.name);
+		match recursive && file.is_tree() {
+			true => walk_tree_in(repo, &file.hash, &format!("{}/", path), recursive, f)?,
+			false => f(path, file)?,
+		}
+	}
+	Ok(())
+}
+
+pub fn read_tag(repo: &Repository, hash: &[u8]) -> io::Result<Tag> {
+	match read_parsed(repo, hash)? {
+		GitObject::Tag(tag) => Ok(tag),
+		object => Err(mismatch(hash, object.kind(), "tag")),
+	}
+}
+
+fn corrupt(hash: &[u8], kind: &str, e: GitError) -> io::Error {
+	e.context(format!("bad {} object {}", kind, hex::encode(hash)))
+}
+
+fn mismatch(hash: &[u8], kind: ObjectKind, expected: &str) -> io::Error {
+	io::Error::new(
+		io::ErrorKind::InvalidData,
+		format!("object {} is a {}, not a {}", hex::encode(hash), kind, expected),
+	)
+}
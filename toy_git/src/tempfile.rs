@@ -0,0 +1,140 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// 並行して書き込む他のprocessと名前が衝突しないようにpidと時刻を含める
+fn unique_name(prefix: &str) -> String {
+	let nanos = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|x| x.subsec_nanos())
+		.unwrap_or_default();
+	format!(
+		"{}_{}_{:08x}{}",
+		prefix,
+		process::id(),
+		nanos,
+		COUNTER.fetch_add(1, Ordering::Relaxed)
+	)
+}
+
+// dirの中に他と重ならない名前の空のディレクトリを作る
+pub fn create_dir(dir: &Path, prefix: &str) -> io::Result<PathBuf> {
+	fs::create_dir_all(dir)?;
+	loop {
+		let path = dir.join(unique_name(prefix));
+		match fs::create_dir(&path) {
+			Ok(()) => return Ok(path),
+			Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+			Err(e) => return Err(e),
+		}
+	}
+}
+
+// 書き終わってからrenameで置き換えるための一時ファイル
+// persistされずにdropされた場合は削除される
+pub struct TempFile {
+	path: PathBuf,
+	file: Option<fs::File>,
+}
+
+impl TempFile {
+	pub fn new(dir: &Path, prefix: &str) -> io::Result<Self> {
+		fs::create_dir_all(dir)?;
+
+		loop {
+			let path = dir.join(unique_name(prefix));
+			match fs::OpenOptions::new()
+				.write(true)
+				.create_new(true)
+				.open(&path)
+			{
+				Ok(file) => {
+					return Ok(Self {
+						path,
+						file: Some(file),
+					})
+				}
+				Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+				Err(e) => return Err(e),
+			}
+		}
+	}
+
+	// "<dest>.lock" を新しく作ってlockする。既にあれば他のprocessが書いているので失敗する
+	// persistでdestを置き換えるまで、他のprocessは同じものをlockできない
+	pub fn lock(dest: &Path) -> io::Result<Self> {
+		let mut path = dest.as_os_str().to_owned();
+		path.push(".lock");
+		let path = PathBuf::from(path);
+		match fs::OpenOptions::new()
+			.write(true)
+			.create_new(true)
+			.open(&path)
+		{
+			Ok(file) => Ok(Self {
+				path,
+				file: Some(file),
+			}),
+			// gitと同じメッセージ
+			Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Err(io::Error::other(format!(
+				"Unable to create '{}': File exists.\n\n\
+				Another git process seems to be running in this repository, e.g.\n\
+				an editor opened by 'git commit'. Please make sure all processes\n\
+				are terminated then try again. If it still fails, a git process\n\
+				may have crashed in this repository earlier:\n\
+				remove the file manually to continue.",
+				path.display()
+			))),
+			Err(e) => Err(io::Error::other(format!(
+				"Unable to create '{}': {}",
+				path.display(),
+				e
+			))),
+		}
+	}
+
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	// 内容をdiskに書き出してからdestにrenameする
+	pub fn persist(mut self, dest: &Path) -> io::Result<()> {
+		if let Some(file) = self.file.take() {
+			file.sync_all()?;
+		}
+		fs::rename(&self.path, dest)?;
+		// lockの場合、renameした後に他のprocessが作った同じ名前のファイルを消さない
+		self.path = PathBuf::new();
+		Ok(())
+	}
+}
+
+impl Write for TempFile {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		match self.file.as_mut() {
+			Some(file) => file.write(buf),
+			None => Err(io::Error::other("temporary file is already closed")),
+		}
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		match self.file.as_mut() {
+			Some(file) => file.flush(),
+			None => Ok(()),
+		}
+	}
+}
+
+impl Drop for TempFile {
+	fn drop(&mut self) {
+		// persistに成功した後はpathは空になっている
+		if self.file.take().is_some() || self.path.exists() {
+			let _ = fs::remove_file(&self.path);
+		}
+	}
+}
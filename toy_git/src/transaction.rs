@@ -0,0 +1,74 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::repository::Repository;
+use crate::store;
+use crate::tempfile;
+
+// この間にstoreで書いたobjectはobjectsの中の一時ディレクトリ(gitのquarantineと同じもの)に置かれ、
+// commitしたときにobjectsへrenameされる
+// commitせずにdropすると一時ディレクトリごと消すので、途中で失敗した操作のobjectが残らない
+// 置いてある間もstore::read_objectで読める
+pub struct ObjectTransaction<'a> {
+	repo: &'a Repository,
+	dir: Option<PathBuf>, // 外側のtransactionの中で始めた場合はNoneで、外側に任せる
+}
+
+impl<'a> ObjectTransaction<'a> {
+	pub fn begin(repo: &'a Repository) -> io::Result<Self> {
+		let mut quarantine = repo.quarantine.lock().unwrap_or_else(|e| e.into_inner());
+		if quarantine.is_some() {
+			return Ok(Self { repo, dir: None });
+		}
+		let dir = tempfile::create_dir(&repo.objects_dir(), "incoming")?;
+		*quarantine = Some(dir.clone());
+		Ok(Self {
+			repo,
+			dir: Some(dir),
+		})
+	}
+
+	pub fn commit(mut self) -> io::Result<()> {
+		let Some(dir) = self.dir.take() else {
+			return Ok(());
+		};
+		self.repo.end_quarantine();
+
+		let result = migrate(&dir, &self.repo.objects_dir());
+		let _ = fs::remove_dir_all(&dir);
+		result
+	}
+
+	pub fn rollback(self) {}
+}
+
+impl Drop for ObjectTransaction<'_> {
+	fn drop(&mut self) {
+		if let Some(dir) = self.dir.take() {
+			self.repo.end_quarantine();
+			let _ = fs::remove_dir_all(dir);
+		}
+	}
+}
+
+// dirの "<xx>/<残り>" のobjectをobjectsの同じ場所に移す。既にあるものは移さずにmtimeだけ新しくする
+fn migrate(dir: &Path, objects: &Path) -> io::Result<()> {
+	for entry in fs::read_dir(dir)? {
+		let entry = entry?;
+		if !entry.file_type()?.is_dir() {
+			continue;
+		}
+		let dest = objects.join(entry.file_name());
+		for object in fs::read_dir(entry.path())? {
+			let object = object?;
+			let path = dest.join(object.file_name());
+			if store::freshen(&path)? {
+				continue;
+			}
+			fs::create_dir_all(&dest)?;
+			fs::rename(object.path(), path)?;
+		}
+	}
+	Ok(())
+}
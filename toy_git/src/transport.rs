@@ -0,0 +1,586 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::hash::Hash;
+use crate::object::{Commit, ObjectKind, Tree, TreeEntry};
+use crate::pack;
+use crate::pkt_line::{self, Packet};
+use crate::progress::Progress;
+use crate::reachable;
+use crate::refs::{self, RefChange, RefUpdate};
+use crate::repository::Repository;
+use crate::revparse;
+use crate::store;
+
+// remoteが公開しているrefを (hash, refname) の組で返す
+// annotated tagはpeelした先も "<refname>^{}" として並ぶ
+// "http://" はsmart HTTPのinfo/refsを読み、それ以外はlocalのrepositoryとして開く
+pub fn list_refs(url: &str) -> io::Result<Vec<(String, String)>> {
+	if url.starts_with("http://") {
+		return Ok(http_refs(url, "git-upload-pack")?.refs);
+	}
+	if url.starts_with("https://") {
+		return Err(io::Error::other("https is not supported"));
+	}
+
+	local_refs(&open_local(url)?)
+}
+
+// remoteのHEADが指すbranchの名前("refs/heads/main" の形)。detached HEADならNone
+// smart HTTPではcapabilitiesの "symref=HEAD:<ref>" から知る
+pub fn head_symref(url: &str) -> io::Result<Option<String>> {
+	if url.starts_with("http://") {
+		return Ok(http_refs(url, "git-upload-pack")?
+			.capabilities
+			.iter()
+			.find_map(|x| x.strip_prefix("symref=HEAD:"))
+			.map(String::from));
+	}
+	if url.starts_with("https://") {
+		return Err(io::Error::other("https is not supported"));
+	}
+
+	refs::symbolic_target(&open_local(url)?, "HEAD", true)
+}
+
+// remoteのobjectのうちwantsから辿れてhavesから辿れないものをpackにしてrepoに加える
+// "http://" はsmart HTTPのgit-upload-packから受け取り、それ以外はlocalのrepositoryから読む
+pub fn fetch_objects(
+	repo: &Repository,
+	url: &str,
+	wants: &[Hash],
+	haves: &[Hash],
+	progress: &mut dyn Progress,
+) -> io::Result<()> {
+	repo.require_sha1("remote transports")?;
+	if url.starts_with("http://") {
+		return http_fetch(repo, url, wants, haves, progress);
+	}
+	if url.starts_with("https://") {
+		return Err(io::Error::other("https is not supported"));
+	}
+	let remote = open_local(url)?;
+
+	// remoteが持っていないhaveは辿れないので除く
+	let haves: Vec<Hash> = haves
+		.iter()
+		.filter(|x| store::read_original(&remote, &x.0).is_ok())
+		.cloned()
+		.collect();
+
+	let hashes = reachable::objects(&remote, wants, &haves)?;
+	let mut objects = Vec::with_capacity(hashes.len());
+	progress.start("Counting objects", hashes.len());
+	for hash in hashes {
+		objects.push(store::read_original(&remote, &hash.0)?);
+		progress.inc(1);
+	}
+	progress.finish();
+
+	if !objects.is_empty() {
+		let data = pack::write(&remote, &objects, progress)?;
+		pack::index_pack(repo, &data, progress)?;
+	}
+	Ok(())
+}
+
+// pushでremoteのrefに加える変更。oldが無ければ作り、newが無ければ消す
+#[derive(Clone)]
+pub struct RefCommand {
+	pub name: String,
+	pub old: Option<Hash>,
+	pub new: Option<Hash>,
+}
+
+// newから辿れてhavesから辿れないobjectをpackにして送り、remoteのrefを書き換える
+// commandsと同じ順に、書き換えられたならNone、拒まれたならその理由を返す
+// "http://" はsmart HTTPのgit-receive-packに送り、それ以外はlocalのrepositoryに書く
+pub fn push(
+	repo: &Repository,
+	url: &str,
+	commands: &[RefCommand],
+	haves: &[Hash],
+	progress: &mut dyn Progress,
+) -> io::Result<Vec<Option<String>>> {
+	repo.require_sha1("remote transports")?;
+	if url.starts_with("http://") {
+		return http_push(repo, url, commands, haves, progress);
+	}
+	if url.starts_with("https://") {
+		return Err(io::Error::other("https is not supported"));
+	}
+	let remote = open_local(url)?;
+
+	let objects = push_objects(repo, commands, haves, progress)?;
+	if !objects.is_empty() {
+		let data = pack::write(repo, &objects, progress)?;
+		pack::index_pack(&remote, &data, progress)?;
+	}
+	let mut results = Vec::with_capacity(commands.len());
+	for command in commands {
+		results.push(receive(&remote, command)?);
+	}
+	Ok(results)
+}
+
+// commandsのnewから辿れてhavesから辿れないobject
+fn push_objects(
+	repo: &Repository,
+	commands: &[RefCommand],
+	haves: &[Hash],
+	progress: &mut dyn Progress,
+) -> io::Result<Vec<(ObjectKind, Vec<u8>)>> {
+	let wants: Vec<Hash> = commands.iter().filter_map(|x| x.new.clone()).collect();
+	if wants.is_empty() {
+		return Ok(Vec::new());
+	}
+	let hashes = reachable::objects(repo, &wants, haves)?;
+	let mut objects = Vec::with_capacity(hashes.len());
+	progress.start("Counting objects", hashes.len());
+	for hash in hashes {
+		objects.push(store::read_original(repo, &hash.0)?);
+		progress.inc(1);
+	}
+	progress.finish();
+	Ok(objects)
+}
+
+// thin packでdeltaのbaseにする、remoteにあるtreeとblob
+// 送るcommitのtreeを、remoteにあるcommit(commandの古い値。新しいrefならhavesの全て)のtreeと
+// 同じpathどうしで比べ、内容が変わったもののremote側を選ぶ
+fn thin_bases(
+	repo: &Repository,
+	commands: &[RefCommand],
+	haves: &[Hash],
+) -> io::Result<Vec<(ObjectKind, Vec<u8>)>> {
+	// commitでなければ(tagなど)None
+	let tree = |hash: &Hash| -> io::Result<Option<Hash>> {
+		let (kind, body) = store::read_original(repo, &hash.0)?;
+		if kind != ObjectKind::Commit {
+			return Ok(None);
+		}
+		Ok(Hash::from_hex(&Commit::from(&body)?.tree))
+	};
+	// (送るtree, remoteにあるtree)
+	let mut stack = Vec::new();
+	for command in commands {
+		let Some(new) = command.new.as_ref().map(tree).transpose()?.flatten() else {
+			continue;
+		};
+		let olds: Vec<&Hash> = match &command.old {
+			Some(old) if haves.contains(old) => vec![old],
+			_ => haves.iter().collect(),
+		};
+		for old in olds {
+			if let Some(old) = tree(old)? {
+				stack.push((new.clone(), old));
+			}
+		}
+	}
+
+	let mut seen = HashSet::new();
+	let mut bases = Vec::new();
+	while let Some((new, old)) = stack.pop() {
+		if new == old || !seen.insert(old.clone()) {
+			continue;
+		}
+		let (_, old_body) = store::read_original(repo, &old.0)?;
+		let (_, new_body) = store::read_original(repo, &new.0)?;
+		let old_tree = Tree::from(&old_body, repo.hash_algo)?;
+		let old_entries: HashMap<&str, &TreeEntry> = old_tree
+			.contents
+			.iter()
+			.map(|x| (x.name.as_str(), x))
+			.collect();
+		for entry in Tree::from(&new_body, repo.hash_algo)?.contents {
+			let Some(base) = old_entries
+				.get(entry.name.as_str())
+				.filter(|x| x.kind() == entry.kind() && x.hash != entry.hash)
+			else {
+				continue;
+			};
+			let (kind, base) = (entry.kind(), Hash(base.hash.clone()));
+			match kind {
+				ObjectKind::Tree => stack.push((Hash(entry.hash), base)),
+				ObjectKind::Blob if seen.insert(base.clone()) => {
+					bases.push(store::read_original(repo, &base.0)?)
+				}
+				_ => {}
+			}
+		}
+		bases.push((ObjectKind::Tree, old_body));
+	}
+	Ok(bases)
+}
+
+// localのremoteでreceive-packと同じようにrefを書き換える
+// receive.denyCurrentBranchで許していなければbareでないremoteのHEADが指すbranchは書き換えず、
+// receive.denyDeleteCurrentで許していなければ消さない
+fn receive(remote: &Repository, command: &RefCommand) -> io::Result<Option<String>> {
+	// "warn" と "ignore"、falseなら許す
+	let config = Config::load(remote)?;
+	let deny = |key: &str| match config.get(key) {
+		Some(x) => x == "refuse" || config.get_bool(key) == Some(true),
+		None => true,
+	};
+	let current = refs::head_target(remote)? == command.name;
+	if current && !remote.bare && deny("receive.denyCurrentBranch") {
+		eprintln!(
+			"remote: error: refusing to update checked out branch: {}",
+			command.name
+		);
+		return Ok(Some(String::from("branch is currently checked out")));
+	}
+	let change = match &command.new {
+		Some(new) => RefChange::Update(new.to_hex()),
+		None if current && deny("receive.denyDeleteCurrent") => {
+			return Ok(Some(String::from(
+				"deletion of the current branch prohibited",
+			)));
+		}
+		None => {
+			if refs::read_ref(remote, &command.name)?.is_none() {
+				eprintln!("remote: warning: deleting a non-existent ref");
+			}
+			RefChange::Delete
+		}
+	};
+	let update = RefUpdate {
+		name: command.name.clone(),
+		change,
+		// 消すときは古い値を確かめない
+		old: command
+			.new
+			.as_ref()
+			.map(|_| command.old.as_ref().map(Hash::to_hex)),
+	};
+	match refs::transaction(remote, &[update], "push") {
+		Ok(()) => Ok(None),
+		Err(_) => Ok(Some(String::from("failed to update ref"))),
+	}
+}
+
+// localのpathか "file://" のURLのrepositoryを開く
+fn open_local(url: &str) -> io::Result<Repository> {
+	let path = url.strip_prefix("file://").unwrap_or(url);
+	Repository::discover(Path::new(path)).map_err(|_| {
+		io::Error::other(format!("'{}' does not appear to be a git repository", url))
+	})
+}
+
+// upload-packと同じく、HEAD、名前順のref、tagのpeelした先の順に並べる
+fn local_refs(repo: &Repository) -> io::Result<Vec<(String, String)>> {
+	let mut refs = Vec::new();
+	if let Some(hash) = refs::resolve(repo, "HEAD")? {
+		refs.push((hash, String::from("HEAD")));
+	}
+
+	for (name, hash) in refs::list(repo, "refs")? {
+		let object = Hash::from_hex(&hash).ok_or_else(|| {
+			io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!("{}: bad object name {}", name, hash),
+			)
+		})?;
+		let tag = store::read_original(repo, &object.0)?.0 == ObjectKind::Tag;
+
+		refs.push((hash, name.clone()));
+		if tag {
+			let peeled = revparse::peel(repo, object, None)?;
+			refs.push((peeled.to_hex(), format!("{}^{{}}", name)));
+		}
+	}
+	Ok(refs)
+}
+
+// info/refsが返すrefと、最初の行に付いているcapabilities
+struct Advertisement {
+	refs: Vec<(String, String)>,
+	capabilities: Vec<String>,
+}
+
+fn http_refs(url: &str, service: &str) -> io::Result<Advertisement> {
+	let url = url.trim_end_matches('/');
+	let body = http_request(&format!("{}/info/refs?service={}", url, service), None)?;
+	let bad = || {
+		io::Error::new(
+			io::ErrorKind::InvalidData,
+			format!("{}: invalid ref advertisement", url),
+		)
+	};
+
+	let mut reader = pkt_line::Reader::new(&body);
+
+	// smart HTTPでは最初に "# service=git-upload-pack" とflushが来る
+	match reader.read().map_err(|_| bad())? {
+		Some(Packet::Data(x)) if x.starts_with(b"# service=") => {
+			if reader.read().map_err(|_| bad())? != Some(Packet::Flush) {
+				return Err(bad());
+			}
+		}
+		_ => {
+			return Err(io::Error::other(format!(
+				"{}: not a smart HTTP git server",
+				url
+			)))
+		}
+	}
+
+	let mut refs = Vec::new();
+	let mut capabilities = Vec::new();
+	for line in reader.read_section().map_err(|_| bad())? {
+		// 最初の行だけはrefの後ろに "\0<capabilities>" が付く
+		let mut parts = line.splitn(2, |&b| b == b'\0');
+		let line = parts.next().unwrap_or_default();
+		if let Some(x) = parts.next() {
+			let x = String::from_utf8_lossy(x);
+			capabilities = x.split_whitespace().map(String::from).collect();
+		}
+		let line = String::from_utf8(line.to_vec()).map_err(|_| bad())?;
+		let (hash, name) = line.trim_end().split_once(' ').ok_or_else(bad)?;
+
+		// 空のrepositoryは "capabilities^{}" という仮のrefを送ってくる
+		if name != "capabilities^{}" {
+			refs.push((String::from(hash), String::from(name)));
+		}
+	}
+	Ok(Advertisement { refs, capabilities })
+}
+
+// git-upload-packに "want" と "have" を1度に送り、返ってきたpackをrepoに加える
+// HTTPではやり取りの状態が残らないので、全てのhaveを送って "done" で終える
+fn http_fetch(
+	repo: &Repository,
+	url: &str,
+	wants: &[Hash],
+	haves: &[Hash],
+	progress: &mut dyn Progress,
+) -> io::Result<()> {
+	let mut wanted = Vec::new();
+	for want in wants {
+		if !wanted.contains(want) && !store::has_object(repo, &want.0)? {
+			wanted.push(want.clone());
+		}
+	}
+	if wanted.is_empty() {
+		return Ok(());
+	}
+
+	let advertised = http_refs(url, "git-upload-pack")?.capabilities;
+	let capabilities: Vec<&str> = ["side-band-64k", "ofs-delta"]
+		.into_iter()
+		.filter(|x| advertised.iter().any(|y| y == x))
+		.chain(["agent=git/toy_git"])
+		.collect();
+
+	let mut request = pkt_line::Writer::new();
+	for (i, want) in wanted.iter().enumerate() {
+		let line = match i {
+			0 => format!("want {} {}\n", want, capabilities.join(" ")),
+			_ => format!("want {}\n", want),
+		};
+		request.data(line.as_bytes())?;
+	}
+	request.flush();
+	for have in haves {
+		request.data(format!("have {}\n", have).as_bytes())?;
+	}
+	request.data(b"done\n")?;
+
+	let url = url.trim_end_matches('/');
+	let body = http_request(
+		&format!("{}/git-upload-pack", url),
+		Some(("application/x-git-upload-pack-request", &request.bytes)),
+	)?;
+	let sideband = capabilities.contains(&"side-band-64k");
+	let data = read_response(&body, sideband)?;
+	pack::index_pack(repo, &data, progress)?;
+	Ok(())
+}
+
+// git-receive-packに "<old> <new> <ref>" の行とpackを送り、report-statusの返事を読む
+fn http_push(
+	repo: &Repository,
+	url: &str,
+	commands: &[RefCommand],
+	haves: &[Hash],
+	progress: &mut dyn Progress,
+) -> io::Result<Vec<Option<String>>> {
+	let advertised = http_refs(url, "git-receive-pack")?.capabilities;
+	let capabilities: Vec<&str> = ["report-status", "side-band-64k"]
+		.into_iter()
+		.filter(|x| advertised.iter().any(|y| y == x))
+		.chain(["agent=git/toy_git"])
+		.collect();
+	if !capabilities.contains(&"report-status") {
+		return Err(io::Error::other(format!(
+			"{}: the receiving end does not support report-status",
+			url
+		)));
+	}
+	if commands.iter().any(|x| x.new.is_none()) && !advertised.iter().any(|x| x == "delete-refs") {
+		return Err(io::Error::other(format!(
+			"{}: the receiving end does not support deleting refs",
+			url
+		)));
+	}
+
+	let zero = "0".repeat(40);
+	let hex = |x: &Option<Hash>| x.as_ref().map_or(zero.clone(), Hash::to_hex);
+	let mut request = pkt_line::Writer::new();
+	for (i, command) in commands.iter().enumerate() {
+		let mut line = format!(
+			"{} {} {}",
+			hex(&command.old),
+			hex(&command.new),
+			command.name
+		);
+		if i == 0 {
+			line = format!("{}\0{}", line, capabilities.join(" "));
+		}
+		request.data(format!("{}\n", line).as_bytes())?;
+	}
+	request.flush();
+	// 消すだけでなければ、送るobjectが無くてもpackを送る
+	let mut request = request.bytes;
+	if commands.iter().any(|x| x.new.is_some()) {
+		let objects = push_objects(repo, commands, haves, progress)?;
+		// "no-thin" でなければ、remoteにあるobjectに対するdeltaも使う
+		let bases = match advertised.iter().any(|x| x == "no-thin") {
+			true => Vec::new(),
+			false => thin_bases(repo, commands, haves)?,
+		};
+		request.extend(pack::write_thin(repo, &objects, &bases, progress)?);
+	}
+
+	let url = url.trim_end_matches('/');
+	let body = http_request(
+		&format!("{}/git-receive-pack", url),
+		Some(("application/x-git-receive-pack-request", &request)),
+	)?;
+	let report = read_response(&body, capabilities.contains(&"side-band-64k"))?;
+	let bad = || {
+		io::Error::new(
+			io::ErrorKind::InvalidData,
+			format!("{}: invalid report-status", url),
+		)
+	};
+
+	// "unpack ok" の後に、refごとに "ok <ref>" か "ng <ref> <reason>" が並ぶ
+	let lines = pkt_line::Reader::new(&report)
+		.read_section()
+		.map_err(|_| bad())?;
+	let mut lines = lines
+		.into_iter()
+		.map(|x| String::from_utf8_lossy(&x).trim_end().to_string());
+	let unpack = lines.next().ok_or_else(bad)?;
+	let unpacked = unpack == "unpack ok";
+	let mut results: Vec<Option<String>> = vec![None; commands.len()];
+	for line in lines {
+		let (status, rest) = line.split_once(' ').ok_or_else(bad)?;
+		let (name, reason) = rest.split_once(' ').unwrap_or((rest, ""));
+		let Some(i) = commands.iter().position(|x| x.name == name) else {
+			continue;
+		};
+		results[i] = match (status, unpacked) {
+			("ok", true) => None,
+			("ok", false) => Some(String::from("unpacker error")),
+			_ => Some(String::from(reason)),
+		};
+	}
+	Ok(results)
+}
+
+// upload-packやreceive-packの返事を読む。先に来る "ACK" や "NAK" の行は読み飛ばす
+// side-bandでは1番がデータ、2番がremoteの進み具合、3番がError
+// side-bandでなければ残りをそのまま返す
+fn read_response(body: &[u8], sideband: bool) -> io::Result<Vec<u8>> {
+	let mut reader = pkt_line::Reader::new(body);
+	loop {
+		// packはpkt-lineでなく、そのまま続く
+		if !sideband && reader.rest().starts_with(b"PACK") {
+			return Ok(reader.rest().to_vec());
+		}
+		match reader.peek()? {
+			Some(Packet::Data(x)) if x.starts_with(b"ACK ") || x.starts_with(b"NAK") => {}
+			Some(Packet::Data(x)) if x.starts_with(b"ERR ") => {
+				return Err(io::Error::other(format!(
+					"remote error: {}",
+					String::from_utf8_lossy(&x[4..]).trim_end()
+				)))
+			}
+			_ => break,
+		}
+		reader.read()?;
+	}
+	if !sideband {
+		return Ok(reader.rest().to_vec());
+	}
+
+	let mut line_start = true;
+	reader.demux(&mut |x| {
+		// 行の途中で区切られて届くことがある
+		for message in x.split_inclusive(|&b| b == b'\r' || b == b'\n') {
+			if line_start {
+				eprint!("remote: ");
+			}
+			eprint!("{}", String::from_utf8_lossy(message));
+			line_start = message.ends_with(b"\r") || message.ends_with(b"\n");
+		}
+	})
+}
+
+// HTTP/1.0でGETし、postがあればその (Content-Type, body) をPOSTしてbodyを返す
+// 1.0ならchunkedで返ってこない
+fn http_request(url: &str, post: Option<(&str, &[u8])>) -> io::Result<Vec<u8>> {
+	let rest = url.strip_prefix("http://").unwrap_or(url);
+	let (host, path) = match rest.find('/') {
+		Some(i) => (&rest[..i], &rest[i..]),
+		None => (rest, "/"),
+	};
+	let address = if host.contains(':') {
+		String::from(host)
+	} else {
+		format!("{}:80", host)
+	};
+
+	let mut stream = TcpStream::connect(&address)?;
+	match post {
+		Some((content_type, body)) => {
+			write!(
+				stream,
+				"POST {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: git/toy_git\r\nAccept: */*\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n",
+				path,
+				host,
+				content_type,
+				body.len()
+			)?;
+			stream.write_all(body)?;
+		}
+		None => write!(
+			stream,
+			"GET {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: git/toy_git\r\nAccept: */*\r\n\r\n",
+			path, host
+		)?,
+	}
+	let mut response = Vec::new();
+	stream.read_to_end(&mut response)?;
+
+	let end = response
+		.windows(4)
+		.position(|x| x == b"\r\n\r\n")
+		.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid HTTP response"))?;
+	let header = String::from_utf8_lossy(&response[..end]);
+	let status = header.lines().next().unwrap_or_default();
+
+	match status.split_whitespace().nth(1) {
+		Some("200") => Ok(response[end + 4..].to_vec()),
+		_ => Err(io::Error::other(format!(
+			"unable to access '{}': {}",
+			url, status
+		))),
+	}
+}
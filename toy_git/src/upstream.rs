@@ -0,0 +1,125 @@
+use std::io;
+
+use crate::config::{self, Config};
+use crate::hash::Hash;
+use crate::merge;
+use crate::refs;
+use crate::repository::Repository;
+
+// branchのupstreamの設定(branch.<name>.remoteとbranch.<name>.merge)
+// remoteが "." ならmergeはlocal branch、それ以外ならremoteのbranch
+pub struct Upstream {
+	pub remote: String,
+	pub merge: String, // "refs/heads/main" の形
+}
+
+impl Upstream {
+	pub fn load(repo: &Repository, branch: &str) -> io::Result<Option<Self>> {
+		let config = Config::load(repo)?;
+		let get = |name: &str| config.get(&format!("branch.{}.{}", branch, name));
+		match (get("remote"), get("merge")) {
+			(Some(remote), Some(merge)) => Ok(Some(Self {
+				remote: String::from(remote),
+				merge: String::from(merge),
+			})),
+			_ => Ok(None),
+		}
+	}
+
+	// upstreamにするref("refs/remotes/origin/main" や "refs/heads/main")から設定を作る
+	// remoteの名前には "/" が含まれることがあるので、設定にあるremoteの名前で分ける
+	pub fn from_ref(repo: &Repository, name: &str) -> io::Result<Option<Self>> {
+		if name.starts_with("refs/heads/") {
+			return Ok(Some(Self {
+				remote: String::from("."),
+				merge: String::from(name),
+			}));
+		}
+		let Some(rest) = name.strip_prefix("refs/remotes/") else {
+			return Ok(None);
+		};
+
+		let config = Config::load(repo)?;
+		let remote = config
+			.entries
+			.iter()
+			.filter_map(|(key, _)| key.strip_prefix("remote.")?.strip_suffix(".url"))
+			.filter(|remote| {
+				rest.strip_prefix(*remote)
+					.is_some_and(|x| x.starts_with('/'))
+			})
+			.max_by_key(|remote| remote.len())
+			.or_else(|| rest.split_once('/').map(|(remote, _)| remote));
+		Ok(remote.map(|remote| Self {
+			remote: String::from(remote),
+			merge: format!("refs/heads/{}", &rest[remote.len() + 1..]),
+		}))
+	}
+
+	// branch.<name>.remoteとbranch.<name>.mergeに書く
+	pub fn save(&self, repo: &Repository, branch: &str) -> io::Result<()> {
+		config::set(repo, &format!("branch.{}.remote", branch), &self.remote)?;
+		config::set(repo, &format!("branch.{}.merge", branch), &self.merge)
+	}
+
+	// 比べる相手のref。fetchと同じく、remoteのbranchは refs/remotes/<remote>/<branch> に記録されている
+	pub fn tracking_ref(&self) -> String {
+		match self.remote.as_str() {
+			"." => self.merge.clone(),
+			remote => format!("refs/remotes/{}/{}", remote, refs::shorten(&self.merge)),
+		}
+	}
+}
+
+// (oursにだけあるcommitの数, theirsにだけあるcommitの数)
+pub fn ahead_behind(repo: &Repository, ours: &Hash, theirs: &Hash) -> io::Result<(usize, usize)> {
+	let ours = merge::ancestors(repo, ours)?;
+	let theirs = merge::ancestors(repo, theirs)?;
+	Ok((
+		ours.difference(&theirs).count(),
+		theirs.difference(&ours).count(),
+	))
+}
+
+// gitのcheckoutやstatusと同じ "Your branch is ahead of 'origin/main' by 1 commit." のような説明
+// upstreamが設定されていなければNone
+pub fn tracking_info(repo: &Repository, branch: &str) -> io::Result<Option<String>> {
+	let Some(upstream) = Upstream::load(repo, branch)? else {
+		return Ok(None);
+	};
+	let name = upstream.tracking_ref();
+	let short = refs::shorten(&name);
+	let resolve = |name: &str| -> io::Result<Option<Hash>> {
+		Ok(refs::resolve(repo, name)?.and_then(|x| Hash::from_hex(&x)))
+	};
+	let (Some(ours), Some(theirs)) = (resolve(&format!("refs/heads/{}", branch))?, resolve(&name)?)
+	else {
+		return Ok(Some(format!(
+			"Your branch is based on '{}', but the upstream is gone.\n  (use \"git branch --unset-upstream\" to fixup)",
+			short
+		)));
+	};
+
+	let commits = |n: usize| match n {
+		1 => String::from("1 commit"),
+		n => format!("{} commits", n),
+	};
+	let info = match ahead_behind(repo, &ours, &theirs)? {
+		(0, 0) => format!("Your branch is up to date with '{}'.", short),
+		(ahead, 0) => format!(
+			"Your branch is ahead of '{}' by {}.\n  (use \"git push\" to publish your local commits)",
+			short,
+			commits(ahead)
+		),
+		(0, behind) => format!(
+			"Your branch is behind '{}' by {}, and can be fast-forwarded.\n  (use \"git pull\" to update your local branch)",
+			short,
+			commits(behind)
+		),
+		(ahead, behind) => format!(
+			"Your branch and '{}' have diverged,\nand have {} and {} different commits each, respectively.\n  (use \"git pull\" to merge the remote branch into yours)",
+			short, ahead, behind
+		),
+	};
+	Ok(Some(info))
+}
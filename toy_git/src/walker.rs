@@ -0,0 +1,89 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+use std::io;
+
+use crate::commit_graph;
+use crate::hash::Hash;
+use crate::object::Commit;
+use crate::repository::Repository;
+use crate::store;
+
+// pushしたcommitからcommitterの日付の新しい順に辿る。日付が同じなら先にpushしたもの
+// 同じcommitは1度だけ辿るので、mergeで合流したり循環していたりしても止まる
+// Iteratorとして使うと取り出したcommitの全てのparentを辿る
+// 辿るparentを選ぶときはpopで取り出し、辿るものだけをpushする
+pub struct CommitWalker<'a> {
+	repo: &'a Repository,
+	queue: BinaryHeap<(i64, Reverse<usize>, Hash)>,
+	seen: HashSet<Hash>,   // 1度pushしたもの
+	hidden: HashSet<Hash>, // 辿らないもの
+	count: usize,
+}
+
+impl<'a> CommitWalker<'a> {
+	pub fn new(repo: &'a Repository) -> Self {
+		Self {
+			repo,
+			queue: BinaryHeap::new(),
+			seen: HashSet::new(),
+			hidden: HashSet::new(),
+			count: 0,
+		}
+	}
+
+	// これらのcommitはpushしても辿らない("^A" から辿れるものなど)
+	pub fn hide(&mut self, hashes: impl IntoIterator<Item = Hash>) {
+		self.hidden.extend(hashes);
+	}
+
+	pub fn push(&mut self, hash: Hash) -> io::Result<()> {
+		if self.hidden.contains(&hash) || !self.seen.insert(hash.clone()) {
+			return Ok(());
+		}
+		self.push_again(hash)
+	}
+
+	// 既に辿ったcommitでももう1度取り出す
+	// blameで日付の狂った子から後で行が渡されたときのため
+	pub fn push_again(&mut self, hash: Hash) -> io::Result<()> {
+		// commit-graphにあればcommitを読まずに済ませる
+		let date = match commit_graph::find(self.repo, &hash)? {
+			Some(commit) => commit.time,
+			None => store::read_commit(self.repo, &hash.0)?
+				.committer
+				.ts
+				.timestamp(),
+		};
+		self.queue.push((date, Reverse(self.count), hash));
+		self.count += 1;
+		Ok(())
+	}
+
+	// 次のcommitのhashとcommitterの日付。parentは辿らない
+	pub fn pop(&mut self) -> Option<(Hash, i64)> {
+		self.queue.pop().map(|(date, _, hash)| (hash, date))
+	}
+
+	fn read(&mut self, hash: &Hash) -> io::Result<Commit> {
+		let commit = store::read_commit(self.repo, &hash.0)?;
+		for parent in commit.parents.iter() {
+			let parent = Hash::from_hex(parent).ok_or_else(|| {
+				io::Error::new(
+					io::ErrorKind::InvalidData,
+					format!("bad parent {} in commit {}", parent, hash),
+				)
+			})?;
+			self.push(parent)?;
+		}
+		Ok(commit)
+	}
+}
+
+impl Iterator for CommitWalker<'_> {
+	type Item = io::Result<Commit>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let (hash, _) = self.pop()?;
+		Some(self.read(&hash))
+	}
+}
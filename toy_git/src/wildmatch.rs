@@ -0,0 +1,66 @@
+// gitのwildmatchに相当するglobの一致判定
+// pathnameがtrueの場合、"*" や "?" は "/" に一致せず、"**" だけがディレクトリをまたぐ
+pub fn wildmatch(pattern: &str, text: &str, pathname: bool) -> bool {
+	let pattern: Vec<char> = pattern.chars().collect();
+	let text: Vec<char> = text.chars().collect();
+
+	matches(&pattern, &text, pathname)
+}
+
+fn matches(p: &[char], t: &[char], pathname: bool) -> bool {
+	match p.first() {
+		None => t.is_empty(),
+		Some('*') if pathname && p.get(1) == Some(&'*') => {
+			let rest = &p[2..];
+			// "**/" は0個以上のディレクトリに一致する
+			if rest.first() == Some(&'/') && matches(&rest[1..], t, pathname) {
+				return true;
+			}
+			(0..=t.len()).any(|i| matches(rest, &t[i..], pathname))
+		}
+		Some('*') => (0..=t.len())
+			.take_while(|&i| i == 0 || !(pathname && t[i - 1] == '/'))
+			.any(|i| matches(&p[1..], &t[i..], pathname)),
+		Some('?') => match t.first() {
+			Some(&c) if !(pathname && c == '/') => matches(&p[1..], &t[1..], pathname),
+			_ => false,
+		},
+		Some('[') => match (class(&p[1..], t.first().copied()), t.first()) {
+			(Some((hit, len)), Some(&c)) => {
+				hit && !(pathname && c == '/') && matches(&p[len + 1..], &t[1..], pathname)
+			}
+			(Some(_), None) => false,
+			// 閉じていない "[" はただの文字として扱う
+			(None, _) => t.first() == Some(&'[') && matches(&p[1..], &t[1..], pathname),
+		},
+		Some('\\') if p.len() > 1 => t.first() == Some(&p[1]) && matches(&p[2..], &t[1..], pathname),
+		Some(&c) => t.first() == Some(&c) && matches(&p[1..], &t[1..], pathname),
+	}
+}
+
+// "[...]" の中身を読み、cが一致するかと "]" までの長さを返す
+fn class(p: &[char], c: Option<char>) -> Option<(bool, usize)> {
+	let negated = matches!(p.first(), Some('!') | Some('^'));
+	let mut i = if negated { 1 } else { 0 };
+	let mut hit = false;
+
+	// 先頭の "]" は文字として扱う
+	let start = i;
+	while i < p.len() {
+		let (lo, hi) = (p[i], p.get(i + 2).copied());
+		if lo == ']' && i > start {
+			return Some((hit != negated, i + 1));
+		}
+		match hi {
+			Some(hi) if p[i + 1] == '-' && hi != ']' => {
+				hit |= c.is_some_and(|c| lo <= c && c <= hi);
+				i += 3;
+			}
+			_ => {
+				hit |= c == Some(lo);
+				i += 1;
+			}
+		}
+	}
+	None
+}
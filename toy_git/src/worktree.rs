@@ -0,0 +1,83 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{symlink, PermissionsExt};
+use std::path::PathBuf;
+
+use crate::object::Blob;
+use crate::repository::Repository;
+
+// working directoryのファイル操作をまとめたもの
+// pathは全てworkdirからの "/" 区切りの相対path
+pub struct Worktree<'a> {
+	pub repo: &'a Repository,
+}
+
+impl<'a> Worktree<'a> {
+	pub fn new(repo: &'a Repository) -> Self {
+		Self { repo }
+	}
+
+	pub fn path(&self, path: &str) -> PathBuf {
+		self.repo.workdir.join(path)
+	}
+
+	// symlinkはリンク先を辿らない
+	pub fn stat(&self, path: &str) -> io::Result<fs::Metadata> {
+		fs::symlink_metadata(self.path(path))
+	}
+
+	// symlinkの場合はリンク先のpathを内容として返す(gitはそれをblobとして保存する)
+	pub fn read_file(&self, path: &str) -> io::Result<Vec<u8>> {
+		let full = self.path(path);
+
+		if fs::symlink_metadata(&full)?.file_type().is_symlink() {
+			let target = fs::read_link(&full)?;
+			return Ok(target.as_os_str().as_bytes().to_vec());
+		}
+		fs::read(full)
+	}
+
+	// modeはindexと同じ表記(0o100644, 0o100755, 0o120000)
+	pub fn write_file(&self, path: &str, blob: &Blob, mode: u32) -> io::Result<()> {
+		let full = self.path(path);
+		if let Some(dir) = full.parent() {
+			fs::create_dir_all(dir)?;
+		}
+
+		// 既存のファイルやsymlinkは置き換える
+		match fs::symlink_metadata(&full) {
+			Ok(meta) if meta.is_dir() => fs::remove_dir_all(&full)?,
+			Ok(_) => fs::remove_file(&full)?,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+			Err(e) => return Err(e),
+		}
+
+		if mode == 0o120000 {
+			return symlink(OsStr::from_bytes(&blob.content), &full);
+		}
+
+		fs::write(&full, &blob.content)?;
+		let permissions = if mode == 0o100755 { 0o755 } else { 0o644 };
+		fs::set_permissions(&full, fs::Permissions::from_mode(permissions))
+	}
+
+	// ファイルを消し、空になった親ディレクトリも消す
+	pub fn remove_file(&self, path: &str) -> io::Result<()> {
+		match fs::remove_file(self.path(path)) {
+			Ok(()) => {}
+			Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+			Err(e) => return Err(e),
+		}
+
+		let mut dir = path;
+		while let Some((parent, _)) = dir.rsplit_once('/') {
+			if fs::remove_dir(self.path(parent)).is_err() {
+				break;
+			}
+			dir = parent;
+		}
+		Ok(())
+	}
+}
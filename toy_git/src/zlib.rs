@@ -0,0 +1,50 @@
+use std::io::{self, Write};
+
+use libflate::lz77::DefaultLz77Encoder;
+use libflate::zlib::{EncodeOptions, Encoder};
+
+use crate::config::Config;
+use crate::repository::Repository;
+
+// gitと同じく、looseなobjectは速さを優先し、packはzlibの既定(-1)にする
+pub const LOOSE_DEFAULT: i64 = 1;
+pub const PACK_DEFAULT: i64 = -1;
+
+// zlibの-1(既定)が表すlevel
+const ZLIB_DEFAULT: u32 = 6;
+
+// key(core.looseCompressionやpack.compression)の圧縮level。無ければcore.compression、それも無ければdefault
+pub fn level(repo: &Repository, key: &str, default: i64) -> io::Result<u32> {
+	let config = Config::load(repo)?;
+	let level = match config.get_int(key)? {
+		Some(level) => level,
+		None => config.get_int("core.compression")?.unwrap_or(default),
+	};
+	match level {
+		-1 => Ok(ZLIB_DEFAULT),
+		0..=9 => Ok(level as u32),
+		_ => Err(io::Error::other(format!(
+			"bad zlib compression level {}",
+			level
+		))),
+	}
+}
+
+// levelが0なら圧縮せずにstored blockとして書き、それ以外はlevelが大きいほどLZ77の窓を広げる
+pub fn compress(bytes: &[u8], level: u32) -> io::Result<Vec<u8>> {
+	let mut encoder = encoder(Vec::new(), level)?;
+	encoder.write_all(bytes)?;
+	encoder.finish().into_result()
+}
+
+// 書いたものを少しずつ圧縮してwに書くencoder。levelの扱いはcompressと同じ
+pub fn encoder<W: Write>(w: W, level: u32) -> io::Result<Encoder<W>> {
+	let options = match level {
+		0 => EncodeOptions::new().no_compression(),
+		level => {
+			let window = 1 << (level + 6).min(15);
+			EncodeOptions::with_lz77(DefaultLz77Encoder::with_window_size(window))
+		}
+	};
+	Encoder::with_options(w, options)
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use common::TempRepo;
+
+// Aliceがbaseの上に作った2つのcommitのrepositoryと、そのformat-patch --stdout
+fn patches() -> (TempRepo, String) {
+	let repo = TempRepo::empty();
+	repo.git(&["init", "-q"]);
+	repo.append(
+		".git/config",
+		"[user]\n\tname = Alice\n\temail = alice@example.com\n",
+	);
+	repo.git_at(
+		"1600000000 +0900",
+		&["commit", "--allow-empty", "-m", "base"],
+	);
+	repo.write("a", "a\nb\n");
+	repo.git(&["add", "a"]);
+	repo.git_at(
+		"1600000100 +0900",
+		&["commit", "-m", "Add a", "-m", "Body line."],
+	);
+	repo.write("c", "c\n");
+	repo.git(&["add", "c"]);
+	repo.git_at("1600000200 +0900", &["commit", "-m", "Add c"]);
+	let mbox = repo.git(&["format-patch", "--stdout", "HEAD~2"]);
+	(repo, mbox)
+}
+
+// Aliceのbaseと同じtreeのcommit
+fn base() -> TempRepo {
+	let repo = TempRepo::new();
+	repo.git(&["commit", "--allow-empty", "-m", "base"]);
+	repo
+}
+
+#[test]
+fn applies_mailbox() {
+	let (origin, mbox) = patches();
+	let repo = base();
+	repo.write("mbox", &mbox);
+	let output = repo.git(&["am", "mbox"]);
+	assert_eq!(output, "Applying: Add a\nApplying: Add c\n");
+
+	assert_eq!(
+		repo.git(&["rev-parse", "HEAD^{tree}"]),
+		origin.git(&["rev-parse", "HEAD^{tree}"])
+	);
+	// authorとその日付、messageは元のまま。committerはこのrepository
+	let format = ["log", "-n", "2", "--format=%an <%ae> %ad|%cn|%s|%b"];
+	assert_eq!(
+		repo.git(&format),
+		"Alice <alice@example.com> Sun Sep 13 21:30:00 2020 +0900|Test|Add c|\n\
+		 Alice <alice@example.com> Sun Sep 13 21:28:20 2020 +0900|Test|Add a|Body line.\n\n"
+	);
+	assert!(!repo.path(".git/rebase-apply").exists());
+}
+
+#[test]
+fn failed_patch_pauses() {
+	let (_, mbox) = patches();
+	let repo = base();
+	let before = repo.commit_file("a", "x\n", "other");
+	repo.write("mbox", &mbox);
+	let err = repo.fail(&["am", "mbox"]);
+	assert!(err.contains("Patch failed at 0001 Add a"), "{}", err);
+	assert!(err.contains("git am --continue"), "{}", err);
+	assert!(repo.path(".git/rebase-apply").is_dir());
+	assert_eq!(repo.read("a"), b"x\n");
+	// 途中のものがあるうちは新しく始めない
+	repo.fail(&["am", "mbox"]);
+
+	repo.git(&["am", "--abort"]);
+	assert!(!repo.path(".git/rebase-apply").exists());
+	assert_eq!(repo.git(&["rev-parse", "HEAD"]).trim(), before);
+	let err = repo.fail(&["am", "--continue"]);
+	assert!(err.contains("Resolve operation not in progress"), "{}", err);
+}
+
+#[test]
+fn continue_after_resolving() {
+	let (_, mbox) = patches();
+	let repo = base();
+	repo.commit_file("a", "x\n", "other");
+	repo.write("mbox", &mbox);
+	repo.fail(&["am", "mbox"]);
+
+	// 手で直したものを使い、残りのpatchを続ける
+	repo.write("a", "x\nb\n");
+	repo.git(&["add", "a"]);
+	assert_eq!(repo.git(&["am", "--continue"]), "Applying: Add c\n");
+	assert!(!repo.path(".git/rebase-apply").exists());
+	assert_eq!(
+		repo.git(&["log", "--format=%an %s"]),
+		"Alice Add c\nAlice Add a\nTest other\nTest base\n"
+	);
+	assert_eq!(repo.git(&["cat-file", "-p", "HEAD~1:a"]), "x\nb\n");
+	assert_eq!(repo.read("c"), b"c\n");
+}
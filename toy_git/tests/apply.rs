@@ -0,0 +1,84 @@
+mod common;
+
+use common::TempRepo;
+
+const LINES: &str = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+
+// fの5行目を変えるdiffを作り、working directoryを元に戻す
+fn patch() -> (TempRepo, String) {
+	let repo = TempRepo::new();
+	repo.commit_file("f", LINES, "first");
+	repo.write("f", LINES.replace("5\n", "five\n"));
+	let patch = repo.git(&["diff"]);
+	repo.git(&["reset", "--hard"]);
+	repo.write("patch.diff", &patch);
+	(repo, patch)
+}
+
+fn read(repo: &TempRepo) -> String {
+	String::from_utf8(repo.read("f")).unwrap()
+}
+
+#[test]
+fn apply_and_reverse() {
+	let (repo, patch) = patch();
+	repo.git(&["apply", "patch.diff"]);
+	assert_eq!(read(&repo), LINES.replace("5\n", "five\n"));
+
+	repo.git_with(&["apply", "-R"], patch.as_bytes());
+	assert_eq!(read(&repo), LINES);
+}
+
+#[test]
+fn apply_with_offset() {
+	let (repo, _) = patch();
+	// 前に行が増えていても、contextが合う場所に当てる
+	repo.write("f", format!("0\n{}", LINES));
+	repo.git(&["apply", "patch.diff"]);
+	assert_eq!(
+		read(&repo),
+		format!("0\n{}", LINES.replace("5\n", "five\n"))
+	);
+}
+
+#[test]
+fn context_mismatch() {
+	let (repo, _) = patch();
+	let changed = LINES.replace("4\n", "four\n");
+	repo.write("f", &changed);
+	let err = repo.fail(&["apply", "patch.diff"]);
+	assert!(err.contains("patch failed: f:2"), "{}", err);
+	assert!(err.contains("f: patch does not apply"), "{}", err);
+	assert_eq!(read(&repo), changed);
+
+	// --checkは当てられるかを確かめるだけ
+	repo.fail(&["apply", "--check", "patch.diff"]);
+	repo.write("f", LINES);
+	repo.git(&["apply", "--check", "patch.diff"]);
+	assert_eq!(read(&repo), LINES);
+}
+
+#[test]
+fn cached() {
+	let (repo, _) = patch();
+	repo.git(&["apply", "--cached", "patch.diff"]);
+	assert_eq!(read(&repo), LINES);
+	assert_eq!(repo.git(&["diff", "--cached", "--name-only"]), "f\n");
+	assert!(repo.git(&["diff"]).contains("\n-five\n+5\n"));
+}
+
+#[test]
+fn new_and_deleted_files() {
+	let repo = TempRepo::new();
+	repo.commit_file("old", "old\n", "first");
+	repo.git(&["rm", "old"]);
+	repo.write("new", "new\n");
+	repo.git(&["add", "new"]);
+	let patch = repo.git(&["diff", "--cached"]);
+	repo.git(&["reset", "--hard"]);
+	assert!(!repo.path("new").exists());
+
+	repo.git_with(&["apply"], patch.as_bytes());
+	assert!(!repo.path("old").exists());
+	assert_eq!(repo.read("new"), b"new\n");
+}
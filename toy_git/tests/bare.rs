@@ -0,0 +1,90 @@
+mod common;
+
+use std::fs;
+
+use common::TempRepo;
+use toy_git::{store, Repository};
+
+#[test]
+fn init_bare() {
+	let repo = TempRepo::empty();
+	repo.git(&["init", "-q", "--bare"]);
+	assert!(repo.path("HEAD").is_file());
+	assert!(!repo.path(".git").exists());
+	assert!(repo.read("config").windows(11).any(|x| x == b"bare = true"));
+
+	let opened = Repository::open_bare(&repo.dir).unwrap();
+	assert!(opened.bare);
+	assert_eq!(opened.gitdir, repo.dir);
+
+	// objectは書けるが、working directoryを使うcommandはError
+	let hash = repo.git_with(&["hash-object", "-w", "--stdin"], b"foo\n");
+	assert_eq!(repo.git(&["cat-file", "-p", hash.trim()]), "foo\n");
+	assert!(store::read_object(&opened, &hex::decode(hash.trim()).unwrap()).is_ok());
+	repo.write("a", "a\n");
+	for args in [&["checkout", "main"][..], &["status"], &["add", "a"]] {
+		let err = repo.fail(args);
+		assert!(
+			err.contains("this operation must be run in a work tree"),
+			"{:?}: {}",
+			args,
+			err
+		);
+	}
+}
+
+// HOMEのgitconfigにreflogに書く名前を置いたディレクトリ
+fn home() -> TempRepo {
+	let home = TempRepo::empty();
+	home.write(
+		".gitconfig",
+		"[user]\n\tname = Test\n\temail = test@example.com\n",
+	);
+	home
+}
+
+#[test]
+fn clone_bare() {
+	let remote = TempRepo::new();
+	let first = remote.commit_file("a", "a\n", "first");
+	remote.git(&["branch", "other"]);
+	let second = remote.commit_file("a", "b\n", "second");
+
+	let repo = home();
+	let url = remote.dir.to_str().unwrap();
+	repo.git(&["clone", "--bare", url, "copy.git"]);
+	let copy = repo.path("copy.git");
+	let git = |args: &[&str]| {
+		let output = repo.run_in(&copy, args, None);
+		assert!(output.status.success(), "{:?}", args);
+		String::from_utf8(output.stdout).unwrap()
+	};
+
+	// remoteのbranchはそのままlocalのbranchになり、checkoutしない
+	assert_eq!(
+		fs::read_to_string(copy.join("HEAD")).unwrap(),
+		"ref: refs/heads/main\n"
+	);
+	assert_eq!(git(&["rev-parse", "refs/heads/main"]).trim(), second);
+	assert_eq!(git(&["rev-parse", "refs/heads/other"]).trim(), first);
+	assert!(!copy.join("refs/remotes").join("origin").exists());
+	assert!(!copy.join("a").exists());
+	assert!(!copy.join("index").exists());
+	assert_eq!(git(&["config", "remote.origin.url"]).trim(), url);
+	assert!(!repo
+		.run_in(&copy, &["config", "remote.origin.fetch"], None)
+		.status
+		.success());
+	assert!(Repository::open_bare(&copy).unwrap().bare);
+}
+
+#[test]
+fn clone_bare_default_dir() {
+	let remote = TempRepo::new();
+	remote.commit_file("a", "a\n", "first");
+	let repo = home();
+	let url = remote.dir.to_str().unwrap();
+	repo.git(&["clone", "--bare", url]);
+	let name = format!("{}.git", remote.dir.file_name().unwrap().to_str().unwrap());
+	assert!(repo.path(&name).join("HEAD").is_file());
+}
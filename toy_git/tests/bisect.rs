@@ -0,0 +1,71 @@
+mod common;
+
+use std::fs;
+
+use common::TempRepo;
+
+// fの中身が1..=7の直線の7つのcommit。古い順のhash
+fn history() -> (TempRepo, Vec<String>) {
+	let repo = TempRepo::new();
+	let commits = (1..=7)
+		.map(|i| repo.commit_file("f", &format!("{}\n", i), &format!("c{}", i)))
+		.collect();
+	(repo, commits)
+}
+
+// fの中身がbad以上ならbadと答え続けて、見つかったfirst bad commitと答えた回数
+fn converge(repo: &TempRepo, commits: &[String], bad: usize) -> (String, usize) {
+	let mut output = repo.git(&["bisect", "start", &commits[6], &commits[0]]);
+	let mut steps = 0;
+	loop {
+		if let Some((hash, _)) = output.split_once(" is the first bad commit") {
+			return (hash.to_string(), steps);
+		}
+		assert!(output.starts_with("Bisecting: "), "{}", output);
+		let current: usize = String::from_utf8(repo.read("f"))
+			.unwrap()
+			.trim()
+			.parse()
+			.unwrap();
+		let term = if current >= bad { "bad" } else { "good" };
+		output = repo.git(&["bisect", term]);
+		steps += 1;
+	}
+}
+
+#[test]
+fn converges() {
+	let (repo, commits) = history();
+	for bad in 2..=7 {
+		let (found, steps) = converge(&repo, &commits, bad);
+		assert_eq!(found, commits[bad - 1], "first bad c{}", bad);
+		// 試すのは間のc2..c6の5つなので3回以内
+		assert!(steps <= 3, "first bad c{}: {} steps", bad, steps);
+		repo.git(&["bisect", "reset"]);
+	}
+}
+
+#[test]
+fn reset_restores_head() {
+	let (repo, commits) = history();
+	repo.git(&["bisect", "start", &commits[6], &commits[0]]);
+	assert_eq!(repo.git(&["rev-parse", "HEAD"]).trim(), commits[3]);
+	assert!(repo.path(".git/BISECT_START").exists());
+
+	repo.git(&["bisect", "reset"]);
+	assert_eq!(repo.git(&["symbolic-ref", "HEAD"]), "refs/heads/main\n");
+	assert_eq!(repo.read("f"), b"7\n");
+	assert!(!repo.path(".git/BISECT_START").exists());
+	assert!(!repo.path(".git/BISECT_LOG").exists());
+	assert!(!fs::read_dir(repo.path(".git/refs/bisect"))
+		.map(|mut x| x.next().is_some())
+		.unwrap_or(false));
+	assert_eq!(repo.git(&["bisect", "reset"]), "We are not bisecting.\n");
+}
+
+#[test]
+fn not_started() {
+	let (repo, _) = history();
+	let err = repo.fail(&["bisect", "good"]);
+	assert!(err.contains("You need to start by"), "{}", err);
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use common::TempRepo;
+
+// 2つのcommitのあるmain
+fn two_commits() -> (TempRepo, String, String) {
+	let repo = TempRepo::new();
+	let first = repo.commit_file("f", "1\n", "first");
+	let second = repo.commit_file("f", "2\n", "second");
+	(repo, first, second)
+}
+
+#[test]
+fn create_and_unbundle() {
+	let (repo, first, second) = two_commits();
+	let file = repo.path("all.bundle");
+	let file = file.to_str().unwrap();
+	repo.git(&["bundle", "create", file, "main"]);
+	let content = repo.read("all.bundle");
+	let header = format!("# v2 git bundle\n{} refs/heads/main\n\nPACK", second);
+	assert!(content.starts_with(header.as_bytes()));
+
+	let other = TempRepo::new();
+	assert_eq!(
+		other.git(&["bundle", "unbundle", file]),
+		format!("{} refs/heads/main\n", second)
+	);
+	assert_eq!(other.git(&["rev-parse", "main"]), format!("{}\n", second));
+	assert_eq!(other.git(&["rev-parse", "main~1"]), format!("{}\n", first));
+	assert_eq!(other.git(&["cat-file", "-p", "main:f"]), "2\n");
+	assert_eq!(other.git(&["cat-file", "-p", "main~1:f"]), "1\n");
+}
+
+#[test]
+fn prerequisites() {
+	let (repo, first, second) = two_commits();
+	let file = repo.path("part.bundle");
+	let file = file.to_str().unwrap();
+	repo.git(&["bundle", "create", file, "main~1..main"]);
+	let content = repo.read("part.bundle");
+	let header = format!(
+		"# v2 git bundle\n-{} first\n{} refs/heads/main\n\n",
+		first, second
+	);
+	assert!(content.starts_with(header.as_bytes()));
+
+	// 1つ目のcommitが無ければ読めない
+	let other = TempRepo::new();
+	let err = other.fail(&["bundle", "unbundle", file]);
+	assert!(
+		err.contains("Repository lacks these prerequisite commits"),
+		"{}",
+		err
+	);
+	other.fail(&["rev-parse", "--verify", "main"]);
+
+	let all = repo.path("first.bundle");
+	repo.git(&["branch", "old", "main~1"]);
+	repo.git(&["bundle", "create", all.to_str().unwrap(), "old"]);
+	other.git(&["bundle", "unbundle", all.to_str().unwrap()]);
+	other.git(&["bundle", "unbundle", file]);
+	assert_eq!(other.git(&["rev-parse", "main"]), format!("{}\n", second));
+	assert_eq!(other.git(&["cat-file", "-p", "main:f"]), "2\n");
+}
@@ -0,0 +1,136 @@
+mod common;
+
+use common::TempRepo;
+
+const FOO: &str = "257cc5642cb1a054f08cc83f2d943e56fd3ebe99";
+
+fn batch_check(repo: &TempRepo) -> (String, String) {
+	let commit = repo.commit_file("a", "foo\n", "first");
+	let size = repo.git(&["cat-file", "-p", &commit]).len();
+	let input = format!(
+		"{}\n{}\n0000000000000000000000000000000000000001\n",
+		FOO, commit
+	);
+	let expected = format!(
+		"{} blob 4\n{} commit {}\n0000000000000000000000000000000000000001 missing\n",
+		FOO, commit, size
+	);
+	(
+		repo.git_with(&["cat-file", "--batch-check"], input.as_bytes()),
+		expected,
+	)
+}
+
+#[test]
+fn batch_check_loose() {
+	let repo = TempRepo::new();
+	let (output, expected) = batch_check(&repo);
+	assert_eq!(output, expected);
+}
+
+#[test]
+fn batch_check_packed() {
+	let repo = TempRepo::new();
+	let (_, expected) = batch_check(&repo);
+	repo.git(&["gc"]);
+	assert!(!repo.path(".git/objects/25").exists());
+
+	let commit = repo.git(&["rev-parse", "HEAD"]);
+	let input = format!(
+		"{}\n{}0000000000000000000000000000000000000001\n",
+		FOO, commit
+	);
+	assert_eq!(
+		repo.git_with(&["cat-file", "--batch-check"], input.as_bytes()),
+		expected
+	);
+}
+
+#[test]
+fn batch_check_revisions() {
+	let repo = TempRepo::new();
+	repo.commit_file("a", "foo\n", "first");
+	let output = repo.git_with(&["cat-file", "--batch-check"], b"HEAD:a\nnope\n");
+	assert_eq!(output, format!("{} blob 4\nnope missing\n", FOO));
+}
+
+#[test]
+fn allow_unknown_type() {
+	let repo = TempRepo::new();
+	let hash = repo.git_with(
+		&["hash-object", "-t", "bogus", "--literally", "-w", "--stdin"],
+		b"hi\n",
+	);
+	let hash = hash.trim();
+	for flag in ["-t", "-s", "-p"] {
+		let err = repo.fail(&["cat-file", flag, hash]);
+		assert!(
+			err.contains(&format!("object {} is corrupt", hash)),
+			"{}",
+			err
+		);
+	}
+
+	let allow = |flag: &str| repo.git(&["cat-file", flag, "--allow-unknown-type", hash]);
+	assert_eq!(allow("-t"), "bogus\n");
+	assert_eq!(allow("-s"), "3\n");
+	assert_eq!(allow("-p"), "hi\n");
+}
+
+// packとlooseの両方にあるobjectも1回だけ、hashの順に出る
+#[test]
+fn batch_all_objects() {
+	let repo = TempRepo::new();
+	repo.commit_file("a", "foo\n", "first");
+	repo.commit_file("b", "bar\n", "second");
+	let copy = repo.read(".git/objects/25/7cc5642cb1a054f08cc83f2d943e56fd3ebe99");
+	repo.git(&["gc"]);
+	repo.write(
+		".git/objects/25/7cc5642cb1a054f08cc83f2d943e56fd3ebe99",
+		copy,
+	);
+	let loose = repo.git_with(&["hash-object", "-w", "--stdin"], b"loose\n");
+
+	let mut expected: Vec<String> = repo
+		.git(&["rev-list", "--objects", "--all"])
+		.lines()
+		.map(|x| x[..40].to_string())
+		.collect();
+	expected.push(loose.trim().to_string());
+	expected.sort();
+
+	let output = repo.git(&["cat-file", "--batch-all-objects", "--batch-check"]);
+	let hashes: Vec<String> = output.lines().map(|x| x[..40].to_string()).collect();
+	assert_eq!(hashes, expected);
+	assert!(output.contains(&format!("{} blob 4\n", FOO)));
+	assert!(output.contains(&format!("{} blob 6\n", loose.trim())));
+
+	// treeはUTF-8でない
+	let output = repo.run(&["cat-file", "--batch-all-objects", "--batch"]);
+	let needle = format!("{} blob 4\nfoo\n\n", FOO);
+	assert!(output
+		.stdout
+		.windows(needle.len())
+		.any(|x| x == needle.as_bytes()));
+}
+
+#[test]
+fn exists() {
+	let repo = TempRepo::new();
+	repo.commit_file("a", "foo\n", "first");
+	let exists = |rev: &str| {
+		let output = repo.run(&["cat-file", "-e", rev]);
+		assert!(output.stdout.is_empty() && output.stderr.is_empty());
+		output.status.code()
+	};
+	assert_eq!(exists(FOO), Some(0));
+	assert_eq!(exists("HEAD:a"), Some(0));
+	// 見つからなければ何も出さずに1
+	assert_eq!(exists("0000000000000000000000000000000000000001"), Some(1));
+	repo.git(&["gc"]);
+	assert_eq!(exists(FOO), Some(0));
+
+	// objectの名前にならないものはError
+	let err = repo.fail(&["cat-file", "-e", "nope"]);
+	assert!(err.contains("unknown revision"), "{}", err);
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use common::TempRepo;
+
+#[test]
+fn set_and_unspecified() {
+	let repo = TempRepo::new();
+	repo.write(".gitattributes", "*.rs text\n");
+	assert_eq!(
+		repo.git(&["check-attr", "text", "--", "foo.rs", "foo.bin"]),
+		"foo.rs: text: set\nfoo.bin: text: unspecified\n"
+	);
+}
+
+#[test]
+fn attribute_stack() {
+	let repo = TempRepo::new();
+	repo.write(".gitattributes", "*.rs text\n*.bin -text diff=bin\n");
+	// 深いディレクトリのものと.git/info/attributesが優先される
+	repo.write("sub/.gitattributes", "*.rs eol=lf\n");
+	repo.write(".git/info/attributes", "foo.rs -text\n");
+	assert_eq!(
+		repo.git(&[
+			"check-attr",
+			"text",
+			"eol",
+			"diff",
+			"--",
+			"foo.rs",
+			"foo.bin",
+			"sub/a.rs"
+		]),
+		"foo.rs: text: unset\n\
+		foo.rs: eol: unspecified\n\
+		foo.rs: diff: unspecified\n\
+		foo.bin: text: unset\n\
+		foo.bin: eol: unspecified\n\
+		foo.bin: diff: bin\n\
+		sub/a.rs: text: set\n\
+		sub/a.rs: eol: lf\n\
+		sub/a.rs: diff: unspecified\n"
+	);
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use common::TempRepo;
+
+// aだけのfirstと、bを足したsecond
+fn two_commits() -> (TempRepo, String, String) {
+	let repo = TempRepo::new();
+	let first = repo.commit_file("a", "a\n", "first");
+	let second = repo.commit_file("b", "b\n", "second");
+	(repo, first, second)
+}
+
+fn rev(repo: &TempRepo, rev: &str) -> String {
+	repo.git(&["rev-parse", rev]).trim().to_string()
+}
+
+#[test]
+fn new_branch() {
+	let (repo, first, second) = two_commits();
+	let output = repo.run(&["checkout", "-b", "topic"]);
+	assert!(output.status.success());
+	assert_eq!(
+		String::from_utf8(output.stdout).unwrap(),
+		"Switched to a new branch 'topic'\n"
+	);
+	assert_eq!(repo.git(&["symbolic-ref", "HEAD"]), "refs/heads/topic\n");
+	assert_eq!(rev(&repo, "topic"), second);
+
+	// 起点を渡せばそこに作り、working directoryも合わせる
+	repo.git(&["checkout", "-b", "old", &first]);
+	assert_eq!(repo.git(&["symbolic-ref", "HEAD"]), "refs/heads/old\n");
+	assert_eq!(rev(&repo, "old"), first);
+	assert!(repo.path("a").exists());
+	assert!(!repo.path("b").exists());
+	assert_eq!(rev(&repo, "main"), second);
+}
+
+#[test]
+fn existing_branch() {
+	let (repo, first, second) = two_commits();
+	repo.git(&["branch", "topic", &first]);
+	let err = repo.fail(&["checkout", "-b", "topic"]);
+	assert!(
+		err.contains("a branch named 'topic' already exists"),
+		"{}",
+		err
+	);
+	assert_eq!(repo.git(&["symbolic-ref", "HEAD"]), "refs/heads/main\n");
+	assert_eq!(rev(&repo, "topic"), first);
+	assert!(repo.path("b").exists());
+
+	let err = repo.fail(&["checkout", "-b", "other", "nosuch"]);
+	assert!(err.contains("unknown revision"), "{}", err);
+	assert!(!repo.path(".git/refs/heads/other").exists());
+	assert_eq!(rev(&repo, "main"), second);
+}
+
+#[test]
+fn reset_branch() {
+	let (repo, first, second) = two_commits();
+	repo.git(&["branch", "topic", &first]);
+	let output = repo.run(&["checkout", "-B", "topic"]);
+	assert!(output.status.success());
+	assert_eq!(
+		String::from_utf8(output.stdout).unwrap(),
+		"Switched to and reset branch 'topic'\n"
+	);
+	assert_eq!(repo.git(&["symbolic-ref", "HEAD"]), "refs/heads/topic\n");
+	assert_eq!(rev(&repo, "topic"), second);
+
+	// 無ければ-bと同じ
+	repo.git(&["checkout", "-B", "fresh", &first]);
+	assert_eq!(repo.git(&["symbolic-ref", "HEAD"]), "refs/heads/fresh\n");
+	assert_eq!(rev(&repo, "fresh"), first);
+	assert!(!repo.path("b").exists());
+}
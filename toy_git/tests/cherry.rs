@@ -0,0 +1,95 @@
+mod common;
+
+use common::TempRepo;
+use toy_git::{diff, store, Hash};
+
+// topicにpickedとunique、mainにotherとpickedと同じ変更のpicked again
+fn picked() -> TempRepo {
+	let repo = TempRepo::new();
+	repo.commit_file("a", "a\n", "base");
+	repo.git(&["checkout", "-b", "topic"]);
+	repo.commit_file("x", "1\n2\n", "picked");
+	repo.commit_file("y", "unique\n", "unique");
+	repo.git(&["checkout", "main"]);
+	repo.commit_file("o", "other\n", "other");
+	repo.commit_file("x", "1\n2\n", "picked again");
+	repo.git(&["checkout", "topic"]);
+	repo
+}
+
+fn rev(repo: &TempRepo, rev: &str) -> String {
+	repo.git(&["rev-parse", rev]).trim().to_string()
+}
+
+// 出力はgit cherryと同じ
+#[test]
+fn marks_picked_commits() {
+	let repo = picked();
+	let (picked, unique) = (rev(&repo, "topic~1"), rev(&repo, "topic"));
+	let expected = format!("- {}\n+ {}\n", picked, unique);
+	assert_eq!(repo.git(&["cherry", "main"]), expected);
+	assert_eq!(repo.git(&["cherry", "main", "topic"]), expected);
+	assert_eq!(
+		repo.git(&["cherry", "-v", "main"]),
+		format!("- {} picked\n+ {} unique\n", picked, unique)
+	);
+	// limitから辿れるものは出さない
+	assert_eq!(
+		repo.git(&["cherry", "main", "topic", "topic~1"]),
+		format!("+ {}\n", unique)
+	);
+	assert_eq!(repo.git(&["cherry", "topic", "main~2"]), "");
+}
+
+// git patch-id --stableと同じ値
+#[test]
+fn patch_id() {
+	let repo = picked();
+	let opened = repo.open();
+	let id = |spec: &str| {
+		let hash = Hash::from_hex(&rev(&repo, spec)).unwrap();
+		diff::patch_id(&opened, &store::read_commit(&opened, &hash.0).unwrap()).unwrap()
+	};
+	assert_eq!(id("topic~1"), id("main"));
+	assert_eq!(
+		id("main").to_hex(),
+		"866ee7c7825d64c1ebbb5fb0dba24ebae70f4bf8"
+	);
+	assert_ne!(id("topic"), id("main"));
+}
+
+// SHA-256のrepositoryではpatch-idもSHA-256。値はgit patch-id --stableと同じ
+#[test]
+fn patch_id_sha256() {
+	let repo = TempRepo::empty();
+	repo.git(&["init", "-q", "--object-format=sha256"]);
+	repo.append(
+		".git/config",
+		"[user]\n\tname = Test\n\temail = test@example.com\n",
+	);
+	repo.commit_file("o", "o\n", "base");
+	let head = repo.commit_file("x", "1\n2\n", "picked");
+	let opened = repo.open();
+	let commit = store::read_commit(&opened, &Hash::from_hex(&head).unwrap().0).unwrap();
+	assert_eq!(
+		diff::patch_id(&opened, &commit).unwrap().to_hex(),
+		"a79ab9f6858b5a12cdac51b526682136c89c975f422f1381398818d2406361ac"
+	);
+}
+
+// hexとして読めないobjectの名前は空のtreeとして扱わずにErr
+#[test]
+fn patch_id_bad_object() {
+	let repo = picked();
+	let opened = repo.open();
+	let hash = Hash::from_hex(&rev(&repo, "main")).unwrap();
+	let mut commit = store::read_commit(&opened, &hash.0).unwrap();
+	commit.tree = String::from("not a hash");
+	let err = diff::patch_id(&opened, &commit).unwrap_err();
+	assert_eq!(err.to_string(), "bad object not a hash");
+
+	let mut commit = store::read_commit(&opened, &hash.0).unwrap();
+	commit.parents = vec![String::from("1234")];
+	let err = diff::patch_id(&opened, &commit).unwrap_err();
+	assert_eq!(err.to_string(), "bad object 1234");
+}
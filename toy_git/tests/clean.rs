@@ -0,0 +1,73 @@
+mod common;
+
+use common::TempRepo;
+
+// trackedとt/aがcommitされ、untracked、t/b、d/f、無視されるx.logがあるrepository
+fn untracked() -> TempRepo {
+	let repo = TempRepo::new();
+	repo.write("tracked", "t\n");
+	repo.write(".gitignore", "*.log\n");
+	repo.write("t/a", "a\n");
+	repo.git(&["add", "tracked", ".gitignore", "t/a"]);
+	repo.git(&["commit", "-m", "first"]);
+	repo.write("untracked", "u\n");
+	repo.write("t/b", "b\n");
+	repo.write("d/f", "f\n");
+	repo.write("x.log", "l\n");
+	repo
+}
+
+fn exists(repo: &TempRepo) -> Vec<&'static str> {
+	["tracked", "t/a", "untracked", "t/b", "d/f", "x.log"]
+		.into_iter()
+		.filter(|x| repo.path(x).exists())
+		.collect()
+}
+
+#[test]
+fn dry_run() {
+	let repo = untracked();
+	// 出力はgitと同じ
+	assert_eq!(
+		repo.git(&["clean", "-n"]),
+		"Would remove t/b\nWould remove untracked\n"
+	);
+	assert_eq!(
+		repo.git(&["clean", "-n", "-d"]),
+		"Would remove d/\nWould remove t/b\nWould remove untracked\n"
+	);
+	assert_eq!(
+		repo.git(&["clean", "-n", "-d", "-x"]),
+		"Would remove d/\nWould remove t/b\nWould remove untracked\nWould remove x.log\n"
+	);
+	assert_eq!(exists(&repo).len(), 6);
+}
+
+#[test]
+fn requires_force() {
+	let repo = untracked();
+	let err = repo.fail(&["clean"]);
+	assert!(err.contains("refusing to clean"), "{}", err);
+	assert_eq!(exists(&repo).len(), 6);
+
+	repo.git(&["config", "clean.requireForce", "false"]);
+	repo.git(&["clean"]);
+	assert_eq!(exists(&repo), ["tracked", "t/a", "d/f", "x.log"]);
+}
+
+#[test]
+fn force() {
+	let repo = untracked();
+	assert_eq!(
+		repo.git(&["clean", "-f"]),
+		"Removing t/b\nRemoving untracked\n"
+	);
+	// -dが無ければ全てuntrackedなディレクトリは残し、無視されるものも残す
+	assert_eq!(exists(&repo), ["tracked", "t/a", "d/f", "x.log"]);
+
+	repo.git(&["clean", "-f", "-d", "-x"]);
+	assert_eq!(exists(&repo), ["tracked", "t/a"]);
+	assert!(!repo.path("d").exists());
+	assert!(repo.path(".gitignore").exists());
+	assert!(repo.git(&["status"]).contains("nothing to commit"));
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use common::TempRepo;
+use toy_git::{store, Commit, Hash};
+
+fn read(repo: &TempRepo, rev: &str) -> Commit {
+	let hash = Hash::from_hex(repo.git(&["rev-parse", rev]).trim()).unwrap();
+	store::read_commit(&repo.open(), &hash.0).unwrap()
+}
+
+#[test]
+fn amend() {
+	let repo = TempRepo::new();
+	let first = repo.commit_file("a", "a\n", "first");
+	let second = repo.commit_file("a", "b\n", "second");
+	let old = read(&repo, "HEAD");
+
+	// committerだけが新しい名前になる
+	repo.git(&["config", "user.name", "Other"]);
+	repo.write("b", "new\n");
+	repo.git(&["add", "b"]);
+	repo.git(&["commit", "--amend", "-m", "amended"]);
+
+	let amended = read(&repo, "HEAD");
+	assert_ne!(repo.git(&["rev-parse", "HEAD"]).trim(), second);
+	assert_eq!(amended.parents, vec![first]);
+	assert_eq!(amended.message, "amended\n");
+	assert_ne!(amended.tree, old.tree);
+	assert_eq!(repo.git(&["cat-file", "-p", "HEAD:b"]), "new\n");
+	assert_eq!(amended.author.name, "Test");
+	assert_eq!(amended.committer.name, "Other");
+	assert_eq!(
+		repo.git(&["rev-parse", "main"]),
+		repo.git(&["rev-parse", "HEAD"])
+	);
+}
+
+#[test]
+fn amend_keeps_message() {
+	let repo = TempRepo::new();
+	repo.commit_file("a", "a\n", "first");
+	repo.write("a", "b\n");
+	repo.git(&["add", "a"]);
+	// -mが無ければ元のmessageのまま
+	repo.git(&["commit", "--amend"]);
+
+	let amended = read(&repo, "HEAD");
+	assert_eq!(amended.message, "first\n");
+	assert!(amended.parents.is_empty());
+	assert_eq!(repo.git(&["cat-file", "-p", "HEAD:a"]), "b\n");
+}
+
+#[test]
+fn amend_unborn() {
+	let repo = TempRepo::new();
+	repo.write("a", "a\n");
+	repo.git(&["add", "a"]);
+	let err = repo.fail(&["commit", "--amend", "-m", "x"]);
+	assert!(err.contains("You have nothing to amend"), "{}", err);
+}
+
+#[test]
+fn amend_to_empty() {
+	let repo = TempRepo::new();
+	repo.commit_file("a", "a\n", "first");
+	repo.commit_file("a", "b\n", "second");
+	// 1つ前のcommitと同じtreeになる
+	repo.write("a", "a\n");
+	repo.git(&["add", "a"]);
+	let err = repo.fail(&["commit", "--amend", "-m", "x"]);
+	assert!(err.contains("would make it empty"), "{}", err);
+}
+
+#[test]
+fn empty_commit_is_refused() {
+	let repo = TempRepo::new();
+	let first = repo.commit_file("a", "a\n", "first");
+	let err = repo.fail(&["commit", "-m", "second"]);
+	assert!(err.contains("nothing to commit"), "{}", err);
+	assert_eq!(repo.git(&["rev-parse", "HEAD"]).trim(), first);
+}
+
+#[test]
+fn allow_empty() {
+	let repo = TempRepo::new();
+	let first = repo.commit_file("a", "a\n", "first");
+	repo.git(&["commit", "--allow-empty", "-m", "second"]);
+
+	let commit = read(&repo, "HEAD");
+	assert_eq!(commit.parents, vec![first.clone()]);
+	assert_eq!(commit.tree, read(&repo, &first).tree);
+	assert_eq!(commit.message, "second\n");
+}
@@ -0,0 +1,112 @@
+mod common;
+
+use std::fs;
+
+use common::TempRepo;
+use toy_git::commit_graph::{self, CommitGraph};
+use toy_git::{merge, Hash};
+
+fn hash(repo: &TempRepo, rev: &str) -> Hash {
+	Hash::from_hex(repo.git(&["rev-parse", rev]).trim()).unwrap()
+}
+
+// baseからmainでa1、otherでb1を作り、お互いをmergeしたもの
+fn criss_cross() -> TempRepo {
+	let repo = TempRepo::new();
+	repo.commit_file("base", "base\n", "base");
+	repo.git(&["branch", "other"]);
+	repo.commit_file("a", "a\n", "a1");
+	repo.git(&["branch", "a1"]);
+	repo.git(&["checkout", "other"]);
+	repo.commit_file("b", "b\n", "b1");
+	repo.git(&["merge", "a1"]);
+	repo.git(&["checkout", "main"]);
+	repo.git(&["merge", "other~1"]);
+	repo
+}
+
+fn bases(repo: &TempRepo) -> Vec<Hash> {
+	let (main, other) = (hash(repo, "main"), hash(repo, "other"));
+	let mut bases = merge::merge_base_all(&repo.open(), &main, &other).unwrap();
+	bases.sort();
+	bases
+}
+
+fn read_graph(repo: &TempRepo) -> CommitGraph {
+	CommitGraph::from(&repo.read(".git/objects/info/commit-graph")).unwrap()
+}
+
+#[test]
+fn contents() {
+	let repo = criss_cross();
+	repo.git(&["commit-graph", "write", "--reachable"]);
+	let graph = read_graph(&repo);
+	assert_eq!(graph.commits.len(), 5);
+
+	for rev in ["main", "main~1", "other", "other~1", "main~2"] {
+		let commit = graph.find(&hash(&repo, rev).0).unwrap();
+		let parents: Vec<String> = commit.parents.iter().map(hex::encode).collect();
+		let expected = repo.git(&["log", "-n", "1", "--format=%P", rev]);
+		assert_eq!(parents.join(" "), expected.trim(), "{}", rev);
+		assert_eq!(
+			Hash(commit.tree.clone()),
+			hash(&repo, &format!("{}^{{tree}}", rev))
+		);
+	}
+	let generation = |rev: &str| graph.find(&hash(&repo, rev).0).unwrap().generation;
+	assert_eq!(generation("main~2"), 1);
+	assert_eq!(generation("main~1"), 2);
+	assert_eq!(generation("other~1"), 2);
+	assert_eq!(generation("main"), 3);
+	assert_eq!(generation("other"), 3);
+
+	// もう一度書けば同じもの
+	let bytes = repo.read(".git/objects/info/commit-graph");
+	assert_eq!(graph.as_bytes(), bytes);
+	repo.git(&["commit-graph", "write", "--reachable"]);
+	assert_eq!(repo.read(".git/objects/info/commit-graph"), bytes);
+}
+
+// commit-graphがあればcommitのobjectを読まずに辿る
+#[test]
+fn merge_base_without_objects() {
+	let repo = criss_cross();
+	let expected = bases(&repo);
+	repo.git(&["commit-graph", "write"]);
+	let graph = read_graph(&repo);
+
+	for commit in graph.commits.iter() {
+		let hex = hex::encode(&commit.hash);
+		fs::remove_file(repo.path(&format!(".git/objects/{}/{}", &hex[..2], &hex[2..]))).unwrap();
+	}
+	assert_eq!(bases(&repo), expected);
+
+	// 使わなければobjectが無いのでError
+	repo.git(&["config", "core.commitGraph", "false"]);
+	let opened = repo.open();
+	assert!(commit_graph::find(&opened, &expected[0]).unwrap().is_none());
+	let (main, other) = (hash(&repo, "main"), hash(&repo, "other"));
+	assert!(merge::merge_base_all(&opened, &main, &other).is_err());
+}
+
+// 書いた後のcommitはobjectを読む
+#[test]
+fn stale_graph() {
+	let repo = criss_cross();
+	repo.git(&["commit-graph", "write"]);
+	repo.commit_file("c", "c\n", "after");
+	let opened = repo.open();
+	assert!(commit_graph::find(&opened, &hash(&repo, "main"))
+		.unwrap()
+		.is_none());
+	assert!(commit_graph::find(&opened, &hash(&repo, "main~1"))
+		.unwrap()
+		.is_some());
+
+	let (main, a1) = (hash(&repo, "main"), hash(&repo, "a1"));
+	assert_eq!(merge::merge_base_all(&opened, &main, &a1).unwrap(), [a1]);
+	assert_eq!(
+		repo.git(&["log", "--format=%s", "-n", "2"]),
+		"after\nMerge commit 'other~1'\n"
+	);
+}
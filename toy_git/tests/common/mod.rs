@@ -0,0 +1,204 @@
+// 各testで使う一時ディレクトリのrepositoryと、toy_gitのcommandを子processで動かすもの
+// testはthreadで並んで動くので、cwdや環境変数は変えずに子processにだけ渡す
+#![allow(dead_code)]
+
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread::{self, JoinHandle};
+
+use toy_git::Repository;
+
+static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+pub struct TempRepo {
+	pub dir: PathBuf,
+}
+
+impl TempRepo {
+	// user.nameなどを設定したworking directoryのあるrepository
+	pub fn new() -> Self {
+		let repo = Self::empty();
+		repo.git(&["init", "-q"]);
+		repo.append(
+			".git/config",
+			"[user]\n\tname = Test\n\temail = test@example.com\n",
+		);
+		repo
+	}
+
+	// まだ何も無いディレクトリ
+	pub fn empty() -> Self {
+		let dir = env::temp_dir().join(format!(
+			"toy_git_test_{}_{}",
+			std::process::id(),
+			COUNT.fetch_add(1, Ordering::Relaxed)
+		));
+		let _ = fs::remove_dir_all(&dir);
+		fs::create_dir_all(&dir).unwrap();
+		Self {
+			dir: dir.canonicalize().unwrap(),
+		}
+	}
+
+	pub fn path(&self, path: &str) -> PathBuf {
+		self.dir.join(path)
+	}
+
+	pub fn open(&self) -> Repository {
+		Repository::discover(&self.dir).unwrap()
+	}
+
+	pub fn write(&self, path: &str, content: impl AsRef<[u8]>) {
+		let path = self.path(path);
+		fs::create_dir_all(path.parent().unwrap()).unwrap();
+		fs::write(path, content).unwrap();
+	}
+
+	pub fn read(&self, path: &str) -> Vec<u8> {
+		fs::read(self.path(path)).unwrap()
+	}
+
+	pub fn append(&self, path: &str, content: &str) {
+		let mut old = fs::read_to_string(self.path(path)).unwrap_or_default();
+		old.push_str(content);
+		self.write(path, old);
+	}
+
+	// dirでtoy_gitを動かすcommand。日付と名前は固定し、~/.gitconfigなどは読まない
+	fn command(&self, dir: &Path, args: &[&str]) -> Command {
+		let mut command = Command::new(env!("CARGO_BIN_EXE_toy_git"));
+		command
+			.args(args)
+			.current_dir(dir)
+			.env("HOME", &self.dir)
+			.env("GIT_CONFIG_NOSYSTEM", "1")
+			.env_remove("GIT_CONFIG_GLOBAL")
+			.env_remove("GIT_DEFAULT_HASH")
+			.env_remove("GIT_NO_REPLACE_OBJECTS")
+			.env_remove("GIT_TEMPLATE_DIR")
+			.env_remove("GIT_AUTHOR_NAME")
+			.env_remove("GIT_AUTHOR_EMAIL")
+			.env_remove("GIT_COMMITTER_NAME")
+			.env_remove("GIT_COMMITTER_EMAIL")
+			.env("NO_COLOR", "1")
+			.env("GIT_AUTHOR_DATE", "1700000000 +0000")
+			.env("GIT_COMMITTER_DATE", "1700000000 +0000");
+		command
+	}
+
+	pub fn run_in(&self, dir: &Path, args: &[&str], stdin: Option<&[u8]>) -> Output {
+		let mut command = self.command(dir, args);
+		match stdin {
+			None => command.stdin(std::process::Stdio::null()).output().unwrap(),
+			Some(input) => {
+				let mut child = command
+					.stdin(std::process::Stdio::piped())
+					.stdout(std::process::Stdio::piped())
+					.stderr(std::process::Stdio::piped())
+					.spawn()
+					.unwrap();
+				child.stdin.take().unwrap().write_all(input).unwrap();
+				child.wait_with_output().unwrap()
+			}
+		}
+	}
+
+	pub fn run(&self, args: &[&str]) -> Output {
+		self.run_in(&self.dir, args, None)
+	}
+
+	// 成功すると決まっているcommandの標準出力
+	pub fn git(&self, args: &[&str]) -> String {
+		check(args, self.run(args))
+	}
+
+	// authorとcommitterの日付をdateにするgit
+	pub fn git_at(&self, date: &str, args: &[&str]) -> String {
+		let mut command = self.command(&self.dir, args);
+		command
+			.env("GIT_AUTHOR_DATE", date)
+			.env("GIT_COMMITTER_DATE", date)
+			.stdin(std::process::Stdio::null());
+		check(args, command.output().unwrap())
+	}
+
+	// stdinを渡すgit
+	pub fn git_with(&self, args: &[&str], stdin: &[u8]) -> String {
+		check(args, self.run_in(&self.dir, args, Some(stdin)))
+	}
+
+	// 失敗すると決まっているcommandの標準エラー出力
+	pub fn fail(&self, args: &[&str]) -> String {
+		let output = self.run(args);
+		assert!(!output.status.success(), "toy_git {:?} succeeded", args);
+		String::from_utf8(output.stderr).unwrap()
+	}
+
+	// ファイルを書いてaddし、commitしてそのhash
+	pub fn commit_file(&self, path: &str, content: &str, message: &str) -> String {
+		self.write(path, content);
+		self.git(&["add", path]);
+		self.git(&["commit", "-m", message]);
+		self.git(&["rev-parse", "HEAD"]).trim().to_string()
+	}
+}
+
+fn check(args: &[&str], output: Output) -> String {
+	assert!(
+		output.status.success(),
+		"toy_git {:?} failed: {}",
+		args,
+		String::from_utf8_lossy(&output.stderr)
+	);
+	String::from_utf8(output.stdout).unwrap()
+}
+
+impl Drop for TempRepo {
+	fn drop(&mut self) {
+		let _ = fs::remove_dir_all(&self.dir);
+	}
+}
+
+// serveが受け取ったrequestの1行目とbody
+pub type Requests = Vec<(String, Vec<u8>)>;
+
+// responsesを1つずつ返すHTTP server。"http://127.0.0.1:<port>/repo.git" と、
+// 終わったときに受け取ったrequestを返すthread
+pub fn serve(responses: Vec<Vec<u8>>) -> (String, JoinHandle<Requests>) {
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let url = format!("http://{}/repo.git", listener.local_addr().unwrap());
+	let handle = thread::spawn(move || {
+		let mut requests = Vec::new();
+		for response in responses {
+			let (stream, _) = listener.accept().unwrap();
+			let mut reader = BufReader::new(stream);
+			let mut first = String::new();
+			reader.read_line(&mut first).unwrap();
+			let mut length = 0;
+			loop {
+				let mut line = String::new();
+				reader.read_line(&mut line).unwrap();
+				if line == "\r\n" || line.is_empty() {
+					break;
+				}
+				if let Some(x) = line.to_lowercase().strip_prefix("content-length:") {
+					length = x.trim().parse().unwrap();
+				}
+			}
+			let mut body = vec![0; length];
+			reader.read_exact(&mut body).unwrap();
+			requests.push((first.trim_end().to_string(), body));
+
+			let mut stream = reader.into_inner();
+			stream.write_all(b"HTTP/1.0 200 OK\r\n\r\n").unwrap();
+			stream.write_all(&response).unwrap();
+		}
+		requests
+	});
+	(url, handle)
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use common::TempRepo;
+
+const EXISTING: &str = "# note\n[alias]\n\tco = checkout # x\n";
+
+fn config_file(repo: &TempRepo) -> String {
+	String::from_utf8(repo.read(".git/config")).unwrap()
+}
+
+#[test]
+fn set_and_get() {
+	let repo = TempRepo::new();
+	repo.git(&["config", "user.name", "Alice"]);
+	assert_eq!(repo.git(&["config", "--get", "user.name"]), "Alice\n");
+	// 書き換えても行は1つ
+	repo.git(&["config", "user.name", "Bob"]);
+	assert_eq!(repo.git(&["config", "user.name"]), "Bob\n");
+	assert_eq!(config_file(&repo).matches("name = ").count(), 1);
+
+	// 無ければexit code 1
+	let output = repo.run(&["config", "--get", "no.such"]);
+	assert_eq!(output.status.code(), Some(1));
+	assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn set_keeps_other_entries() {
+	let repo = TempRepo::new();
+	let before = config_file(&repo);
+	repo.append(".git/config", EXISTING);
+	repo.git(&["config", "color.ui", "never"]);
+	assert_eq!(
+		config_file(&repo),
+		format!("{}{}[color]\n\tui = never\n", before, EXISTING)
+	);
+	assert_eq!(repo.git(&["config", "alias.co"]), "checkout\n");
+
+	// 既にあるsectionにはその中に足す
+	repo.git(&["config", "alias.st", "status"]);
+	assert_eq!(
+		config_file(&repo),
+		format!(
+			"{}# note\n[alias]\n\tco = checkout # x\n\tst = status\n[color]\n\tui = never\n",
+			before
+		)
+	);
+}
+
+#[test]
+fn unset() {
+	let repo = TempRepo::new();
+	repo.append(".git/config", EXISTING);
+	let before = config_file(&repo);
+	repo.git(&["config", "alias.st", "status"]);
+	repo.git(&["config", "--unset", "alias.st"]);
+	assert_eq!(config_file(&repo), before);
+	assert_eq!(repo.git(&["config", "alias.co"]), "checkout\n");
+
+	// 無いkeyはexit code 5
+	let output = repo.run(&["config", "--unset", "alias.st"]);
+	assert_eq!(output.status.code(), Some(5));
+	assert_eq!(config_file(&repo), before);
+}
+
+// HOMEはこのrepositoryのディレクトリ
+#[test]
+fn global() {
+	let repo = TempRepo::new();
+	let before = config_file(&repo);
+	repo.git(&["config", "--global", "core.editor", "ed"]);
+	assert_eq!(
+		String::from_utf8(repo.read(".gitconfig")).unwrap(),
+		"[core]\n\teditor = ed\n"
+	);
+	assert_eq!(config_file(&repo), before);
+	assert_eq!(repo.git(&["config", "core.editor"]), "ed\n");
+	let output = repo.run(&["config", "--local", "core.editor"]);
+	assert_eq!(output.status.code(), Some(1));
+
+	// repositoryのほうが優先
+	repo.git(&["config", "core.editor", "vi"]);
+	assert_eq!(repo.git(&["config", "core.editor"]), "vi\n");
+	assert_eq!(repo.git(&["config", "--global", "core.editor"]), "ed\n");
+	repo.git(&["config", "--global", "--unset", "core.editor"]);
+	let output = repo.run(&["config", "--global", "core.editor"]);
+	assert_eq!(output.status.code(), Some(1));
+	assert_eq!(repo.git(&["config", "core.editor"]), "vi\n");
+}
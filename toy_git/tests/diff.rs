@@ -0,0 +1,203 @@
+mod common;
+
+use common::TempRepo;
+use toy_git::{diff, store, Hash};
+
+fn patch_id(repo: &TempRepo, rev: &str) -> Hash {
+	let opened = repo.open();
+	let hash = Hash::from_hex(rev).unwrap();
+	let commit = store::read_commit(&opened, &hash.0).unwrap();
+	diff::patch_id(&opened, &commit).unwrap()
+}
+
+const BASE: &str = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+const CHANGED: &str = "1\n2\n3\n4\n5\n6\n7\neight\n9\n10\n";
+
+#[test]
+fn patch_id_ignores_base() {
+	let repo = TempRepo::new();
+	repo.commit_file("f", BASE, "base");
+	repo.git(&["branch", "other"]);
+	let first = repo.commit_file("f", CHANGED, "change");
+
+	// 別のbaseでは行番号がずれ、関係無いファイルもある
+	repo.git(&["checkout", "other"]);
+	repo.commit_file("g", "x\n", "unrelated");
+	let shifted = format!("0\n{}", BASE);
+	let unrelated = repo.commit_file("f", &shifted, "shift");
+	let second = repo.commit_file("f", &format!("0\n{}", CHANGED), "same change");
+
+	assert_eq!(patch_id(&repo, &first), patch_id(&repo, &second));
+	assert_ne!(patch_id(&repo, &first), patch_id(&repo, &unrelated));
+}
+
+// a、b、cのcommitから、aを変えてbを消し、dを足してcをeにrenameする
+fn changes() -> TempRepo {
+	let repo = TempRepo::new();
+	repo.write("a", "a\n");
+	repo.write("b", "b\n");
+	repo.write("c", "c\n");
+	repo.git(&["add", "."]);
+	repo.git(&["commit", "-m", "first"]);
+	repo.write("a", "a2\n");
+	repo.write("d", "d\n");
+	repo.write("e", "c\n");
+	repo.git(&["rm", "b", "c"]);
+	repo.git(&["add", "."]);
+	repo.git(&["commit", "-m", "second"]);
+	repo
+}
+
+#[test]
+fn name_status() {
+	let repo = changes();
+	assert_eq!(
+		repo.git(&["diff", "--name-status", "HEAD~1", "HEAD"]),
+		"M\ta\nD\tb\nA\td\nR100\tc\te\n"
+	);
+	// renameは新しい名前だけ
+	assert_eq!(
+		repo.git(&["diff", "--name-only", "HEAD~1", "HEAD"]),
+		"a\nb\nd\ne\n"
+	);
+}
+
+#[test]
+fn name_status_worktree_and_index() {
+	let repo = changes();
+	repo.write("a", "a3\n");
+	repo.write("f", "f\n");
+	repo.git(&["add", "f"]);
+	assert_eq!(repo.git(&["diff", "--name-status"]), "M\ta\n");
+	assert_eq!(repo.git(&["diff", "--cached", "--name-status"]), "A\tf\n");
+	assert_eq!(repo.git(&["diff", "--cached", "--name-only"]), "f\n");
+}
+
+#[test]
+fn reverse() {
+	let repo = TempRepo::new();
+	let first = repo.commit_file("f", BASE, "base");
+	let second = repo.commit_file("f", CHANGED, "change");
+
+	// gitと同じく、prefixも入れ替わる
+	let reversed = repo.git(&["diff", "-R", &first, &second]);
+	let swapped = repo.git(&["diff", &second, &first]);
+	assert!(reversed.starts_with("diff --git b/f a/f\n"), "{}", reversed);
+	assert!(reversed.contains("\n--- b/f\n+++ a/f\n"), "{}", reversed);
+	let hunks = |x: &str| x[x.find("@@").unwrap()..].to_string();
+	assert_eq!(hunks(&reversed), hunks(&swapped));
+	assert!(reversed.contains("\n-eight\n+8\n"), "{}", reversed);
+}
+
+#[test]
+fn no_index() {
+	// repositoryの外でも動く
+	let dir = TempRepo::empty();
+	dir.write("x", BASE);
+	dir.write("y", CHANGED);
+	let output = dir.run(&["diff", "--no-index", "x", "y"]);
+	let patch = String::from_utf8(output.stdout).unwrap();
+	assert!(patch.starts_with("diff --git a/x b/y\n"), "{}", patch);
+	assert!(patch.contains("\n--- a/x\n+++ b/y\n"), "{}", patch);
+	assert!(patch.contains("\n-8\n+eight\n"), "{}", patch);
+	assert!(!dir.path(".git").exists());
+
+	let output = dir.run(&["diff", "--no-index", "x", "x"]);
+	assert!(output.stdout.is_empty());
+}
+
+// aを1行変えてbにrenameし、cに1行足してdにcopyしたindex。数字はgitで確かめたもの
+fn similar() -> TempRepo {
+	let repo = TempRepo::new();
+	repo.write("a", BASE);
+	repo.write("c", CHANGED);
+	repo.git(&["add", "a", "c"]);
+	repo.git(&["commit", "-m", "first"]);
+	repo.git(&["rm", "--cached", "a"]);
+	repo.write("b", BASE.replace("5\n", "five\n"));
+	repo.write("d", CHANGED);
+	repo.append("c", "11\n");
+	repo.git(&["add", "b", "c", "d"]);
+	repo
+}
+
+#[test]
+fn similar_rename() {
+	let repo = similar();
+	let diff = |args: &[&str]| repo.git(&[&["diff", "--cached"], args].concat());
+	let renamed = "R079\ta\tb\nM\tc\nA\td\n";
+	assert_eq!(diff(&["--name-status"]), renamed);
+	assert_eq!(diff(&["-M", "--name-status"]), renamed);
+	assert_eq!(diff(&["-M79", "--name-status"]), renamed);
+	// thresholdより似ていなければ消したのと足したもの
+	assert_eq!(
+		diff(&["-M80%", "--name-status"]),
+		"D\ta\nA\tb\nM\tc\nA\td\n"
+	);
+	// -Cは変更したファイルからのcopyも探す
+	assert_eq!(
+		diff(&["-C", "--name-status"]),
+		"R079\ta\tb\nM\tc\nC100\tc\td\n"
+	);
+
+	let patch = diff(&["-M"]);
+	assert!(
+		patch.starts_with("diff --git a/a b/b\nsimilarity index 79%\nrename from a\nrename to b\n"),
+		"{}",
+		patch
+	);
+	assert!(patch.contains("\n-5\n+five\n"), "{}", patch);
+}
+
+// 1から13の行の5と12を変えたもの。hunkの行はgitの出力と同じ
+fn two_changes() -> TempRepo {
+	let repo = TempRepo::new();
+	let lines: String = (1..=13).map(|i| format!("{}\n", i)).collect();
+	repo.commit_file("f", &lines, "first");
+	repo.write(
+		"f",
+		lines.replace("\n5\n", "\n55\n").replace("12\n", "1212\n"),
+	);
+	repo
+}
+
+fn hunks(patch: &str) -> &str {
+	&patch[patch.find("@@").unwrap()..]
+}
+
+#[test]
+fn context_lines() {
+	let repo = two_changes();
+	assert_eq!(
+		hunks(&repo.git(&["diff", "-U0"])),
+		"@@ -5 +5 @@\n-5\n+55\n@@ -12 +12 @@\n-12\n+1212\n"
+	);
+	assert_eq!(
+		hunks(&repo.git(&["diff", "-U1"])),
+		"@@ -4,3 +4,3 @@\n 4\n-5\n+55\n 6\n@@ -11,3 +11,3 @@\n 11\n-12\n+1212\n 13\n"
+	);
+	assert_eq!(
+		repo.git(&["diff", "--unified=1"]),
+		repo.git(&["diff", "-U1"])
+	);
+	// 間の行がcontextに収まればhunkは1つ
+	let wide = repo.git(&["diff", "-U4"]);
+	assert!(
+		hunks(&wide).starts_with("@@ -1,13 +1,13 @@\n 1\n"),
+		"{}",
+		wide
+	);
+	assert_eq!(wide.matches("@@ -").count(), 1);
+	assert_eq!(repo.git(&["diff", "-U3"]), repo.git(&["diff"]));
+}
+
+#[test]
+fn word_diff_porcelain() {
+	let repo = TempRepo::new();
+	repo.commit_file("w", "hello world foo\nbar\n", "first");
+	repo.write("w", "hello there foo\nbar baz\n");
+	assert_eq!(
+		hunks(&repo.git(&["diff", "--word-diff=porcelain"])),
+		"@@ -1,2 +1,2 @@\n hello \n-world\n+there\n  foo\n~\n bar \n+baz\n~\n"
+	);
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use common::TempRepo;
+
+// 太字にしたheaderの行
+fn header(line: &str) -> String {
+	format!("\x1b[1m{}\x1b[m\n", line)
+}
+
+fn changed(repo: &TempRepo, old: &str, new: &str) {
+	repo.commit_file("f", old, "first");
+	repo.write("f", new);
+}
+
+// gitと同じく、足した行は "+" と内容に別々に色を付ける
+#[test]
+fn color_always() {
+	let repo = TempRepo::new();
+	changed(&repo, "one\ntwo\n", "one\n2\n");
+	let expected = format!(
+		"{}{}{}{}\x1b[36m@@ -1,2 +1,2 @@\x1b[m\n \
+		one\x1b[m\n\
+		\x1b[31m-two\x1b[m\n\
+		\x1b[32m+\x1b[m\x1b[32m2\x1b[m\n",
+		header("diff --git a/f b/f"),
+		header("index 814f4a4..99b356d 100644"),
+		header("--- a/f"),
+		header("+++ b/f"),
+	);
+	assert_eq!(repo.git(&["diff", "--color=always"]), expected);
+	assert_eq!(repo.git(&["diff", "--color"]), expected);
+}
+
+#[test]
+fn color_never() {
+	let repo = TempRepo::new();
+	changed(&repo, "one\ntwo\n", "one\n2\n");
+	let plain = repo.git(&["diff"]);
+	assert!(!plain.contains('\x1b'));
+	assert_eq!(repo.git(&["diff", "--color=never"]), plain);
+	// 端末でなければautoでも付けない
+	assert_eq!(repo.git(&["diff", "--color=auto"]), plain);
+	assert_eq!(repo.git(&["diff", "--color=always", "--no-color"]), plain);
+
+	let err = repo.fail(&["diff", "--color=sometimes"]);
+	assert!(err.contains("option `color' expects"), "{}", err);
+}
+
+#[test]
+fn color_words() {
+	let repo = TempRepo::new();
+	changed(&repo, "one\nthe red fox\n", "one\nthe blue fox\n");
+	let words = repo.git(&["diff", "--color-words"]);
+	// 変わった単語だけに色が付く
+	assert!(
+		words.ends_with(
+			"\x1b[36m@@ -1,2 +1,2 @@\x1b[m\none\x1b[m\nthe \x1b[31mred\x1b[m\x1b[32mblue\x1b[m fox\n"
+		),
+		"{:?}",
+		words
+	);
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use std::fs;
+
+use common::TempRepo;
+
+const NULL: &str = "0000000000000000000000000000000000000000";
+
+fn blob(repo: &TempRepo, rev: &str) -> String {
+	repo.git(&["rev-parse", rev]).trim().to_string()
+}
+
+// addしたaのblob。working directoryのaも同じ内容
+fn staged_a(repo: &TempRepo) -> String {
+	repo.git(&["hash-object", "a"]).trim().to_string()
+}
+
+// a、b、cをcommitした後、aの変更だけをaddし、bを変えてcを消したもの
+fn staged() -> TempRepo {
+	let repo = TempRepo::new();
+	repo.write("a", "a\n");
+	repo.write("b", "b\n");
+	repo.write("c", "c\n");
+	repo.git(&["add", "a", "b", "c"]);
+	repo.git(&["commit", "-m", "first"]);
+	repo.write("a", "a2\n");
+	repo.git(&["add", "a"]);
+	repo.write("b", "b2\n");
+	fs::remove_file(repo.path("c")).unwrap();
+	repo
+}
+
+#[test]
+fn cached() {
+	let repo = staged();
+	let expected = format!(
+		":100644 100644 {} {} M\ta\n",
+		blob(&repo, "HEAD:a"),
+		staged_a(&repo)
+	);
+	assert_eq!(repo.git(&["diff-index", "--cached", "HEAD"]), expected);
+	// treeを直接渡しても同じ
+	assert_eq!(
+		repo.git(&["diff-index", "--cached", "HEAD^{tree}"]),
+		expected
+	);
+}
+
+// 出力はgitと同じ。working directoryのhashは計算せずに0にする
+#[test]
+fn worktree() {
+	let repo = staged();
+	assert_eq!(
+		repo.git(&["diff-index", "HEAD"]),
+		format!(
+			":100644 100644 {} {} M\ta\n:100644 100644 {} {} M\tb\n:100644 000000 {} {} D\tc\n",
+			blob(&repo, "HEAD:a"),
+			staged_a(&repo),
+			blob(&repo, "HEAD:b"),
+			NULL,
+			blob(&repo, "HEAD:c"),
+			NULL
+		)
+	);
+
+	repo.git(&["reset", "--hard"]);
+	assert_eq!(repo.git(&["diff-index", "HEAD"]), "");
+	assert_eq!(repo.git(&["diff-index", "--cached", "HEAD"]), "");
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+
+use common::TempRepo;
+
+const ZERO: &str = "0000000000000000000000000000000000000000";
+
+// aとd/cとgoneから、aとd/cを変え、goneを消し、newと実行可能なd/eを足す
+fn two_trees() -> TempRepo {
+	let repo = TempRepo::new();
+	repo.write("a", "a\n");
+	repo.write("d/c", "c\n");
+	repo.write("gone", "g\n");
+	repo.git(&["add", "."]);
+	repo.git(&["commit", "-m", "first"]);
+	repo.write("a", "a2\n");
+	repo.write("d/c", "c2\n");
+	repo.write("d/e", "e\n");
+	fs::set_permissions(repo.path("d/e"), fs::Permissions::from_mode(0o755)).unwrap();
+	repo.write("new", "n\n");
+	repo.git(&["rm", "gone"]);
+	repo.git(&["add", "."]);
+	repo.git(&["commit", "-m", "second"]);
+	repo
+}
+
+fn blob(repo: &TempRepo, path: &str) -> String {
+	repo.git(&["rev-parse", path]).trim().to_string()
+}
+
+#[test]
+fn top_level() {
+	let repo = two_trees();
+	let expected = format!(
+		":100644 100644 {} {} M\ta\n\
+		:040000 040000 {} {} M\td\n\
+		:100644 000000 {} {} D\tgone\n\
+		:000000 100644 {} {} A\tnew\n",
+		blob(&repo, "HEAD~1:a"),
+		blob(&repo, "HEAD:a"),
+		blob(&repo, "HEAD~1:d"),
+		blob(&repo, "HEAD:d"),
+		blob(&repo, "HEAD~1:gone"),
+		ZERO,
+		ZERO,
+		blob(&repo, "HEAD:new"),
+	);
+	assert_eq!(repo.git(&["diff-tree", "HEAD~1", "HEAD"]), expected);
+	// treeのhashを直接渡しても同じ
+	let a = blob(&repo, "HEAD~1^{tree}");
+	let b = blob(&repo, "HEAD^{tree}");
+	assert_eq!(repo.git(&["diff-tree", &a, &b]), expected);
+}
+
+#[test]
+fn recursive() {
+	let repo = two_trees();
+	assert_eq!(
+		repo.git(&["diff-tree", "-r", "HEAD~1", "HEAD"]),
+		format!(
+			":100644 100644 78981922613b2afb6025042ff6bd878ac1994e85 \
+			c1827f07e114c20547dc6a7296588870a4b5b62c M\ta\n\
+			:100644 100644 f2ad6c76f0115a6ba5b00456a849810e7ec0af20 \
+			16f9ec009e5568c435f473ba3a1df732d49ce8c3 M\td/c\n\
+			:000000 100755 {0} d905d9da82c97264ab6f4920e20242e088850ce9 A\td/e\n\
+			:100644 000000 01058d844a98d293a3b03a8615a34700e4ed2be3 {0} D\tgone\n\
+			:000000 100644 {0} 8ba3a16384aacc37d01564b28401755ce8053f51 A\tnew\n",
+			ZERO
+		)
+	);
+	assert_eq!(repo.git(&["diff-tree", "-r", "HEAD", "HEAD"]), "");
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use common::TempRepo;
+
+// dirの下(.gitも含む)の全てのファイルとその中身
+fn snapshot(dir: &Path) -> Vec<(PathBuf, Vec<u8>)> {
+	let mut found = Vec::new();
+	for entry in fs::read_dir(dir).unwrap() {
+		let path = entry.unwrap().path();
+		match path.is_dir() {
+			true => found.extend(snapshot(&path)),
+			false => found.push((path.clone(), fs::read(&path).unwrap())),
+		}
+	}
+	found.sort();
+	found
+}
+
+// 何も書き換えずに、そのcommandの出力を返す
+fn dry_run(repo: &TempRepo, args: &[&str]) -> String {
+	let before = snapshot(&repo.dir);
+	let output = repo.git(args);
+	assert!(before == snapshot(&repo.dir), "{:?} changed files", args);
+	output
+}
+
+// otherがmainと別のcommitを持ち、mainにはaの変更とbの追加がある
+fn changed() -> TempRepo {
+	let repo = TempRepo::new();
+	repo.commit_file("a", "a\n", "base");
+	repo.git(&["checkout", "-b", "other"]);
+	repo.commit_file("o", "o\n", "other");
+	repo.git(&["checkout", "main"]);
+	repo.write("a", "a2\n");
+	repo.write("b", "b\n");
+	repo
+}
+
+#[test]
+fn add() {
+	let repo = changed();
+	assert_eq!(
+		dry_run(&repo, &["add", "--dry-run", "a", "b"]),
+		"add 'a'\nadd 'b'\n"
+	);
+	assert_eq!(dry_run(&repo, &["add", "-n", "."]), "add 'a'\nadd 'b'\n");
+	assert_eq!(repo.git(&["ls-files"]), "a\n");
+}
+
+#[test]
+fn commit() {
+	let repo = changed();
+	repo.git(&["add", "a", "b"]);
+	let output = dry_run(&repo, &["commit", "--dry-run", "-m", "x"]);
+	assert!(
+		output.contains("\tmodified:   a\n\tnew file:   b\n"),
+		"{}",
+		output
+	);
+}
+
+#[test]
+fn rm() {
+	let repo = changed();
+	assert_eq!(dry_run(&repo, &["rm", "-n", "--cached", "a"]), "rm 'a'\n");
+}
+
+#[test]
+fn checkout() {
+	let repo = changed();
+	assert_eq!(
+		dry_run(&repo, &["checkout", "--dry-run", "other"]),
+		"Switched to branch 'other'\n"
+	);
+	assert_eq!(
+		dry_run(&repo, &["checkout", "--dry-run", "-b", "new"]),
+		"Switched to a new branch 'new'\n"
+	);
+}
+
+#[test]
+fn merge() {
+	let repo = changed();
+	repo.git(&["add", "a", "b"]);
+	repo.git(&["commit", "-m", "main"]);
+	assert_eq!(
+		dry_run(&repo, &["merge", "--dry-run", "other"]),
+		"Merge made by the 'ort' strategy.\n"
+	);
+	assert_eq!(
+		dry_run(&repo, &["merge", "--dry-run", "-s", "ours", "other"]),
+		"Merge made by the 'ours' strategy.\n"
+	);
+}
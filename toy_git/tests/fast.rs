@@ -0,0 +1,48 @@
+mod common;
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+
+use common::TempRepo;
+
+// branch、merge、annotated tag、subディレクトリ、実行可能なファイル、消したファイルのあるrepository
+fn history() -> TempRepo {
+	let repo = TempRepo::new();
+	repo.write("a", "a\n");
+	repo.write("dir/b", "b\n");
+	repo.git(&["add", "a", "dir/b"]);
+	repo.git(&["commit", "-m", "base"]);
+	repo.git(&["checkout", "-b", "topic"]);
+	repo.write("run.sh", "#!/bin/sh\n");
+	fs::set_permissions(repo.path("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+	repo.git(&["add", "run.sh"]);
+	repo.git(&["commit", "-m", "script"]);
+	repo.git(&["checkout", "main"]);
+	repo.git(&["rm", "a"]);
+	repo.commit_file("dir/b", "b2\n", "change");
+	repo.git(&["merge", "--no-ff", "topic"]);
+	repo.git(&["tag", "-a", "v1", "-m", "release"]);
+	repo
+}
+
+#[test]
+fn round_trip() {
+	let repo = history();
+	let stream = repo.git(&["fast-export", "--all"]);
+	assert!(stream.contains("\nreset refs/heads/topic\nfrom :"), "{}", stream);
+	assert!(stream.contains("\nM 100755 "), "{}", stream);
+	assert!(stream.contains("\nD a\n"), "{}", stream);
+
+	let copy = TempRepo::new();
+	copy.git_with(&["fast-import"], stream.as_bytes());
+	// 同じobjectができるので、refのhashも同じ
+	assert_eq!(copy.git(&["for-each-ref"]), repo.git(&["for-each-ref"]));
+	assert_eq!(
+		copy.git(&["rev-parse", "HEAD^{tree}"]),
+		repo.git(&["rev-parse", "HEAD^{tree}"])
+	);
+	assert_eq!(
+		copy.git(&["cat-file", "-p", "v1"]),
+		repo.git(&["cat-file", "-p", "v1"])
+	);
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use common::TempRepo;
+
+// main、gone、keepのあるremoteと、それをoriginとしてfetchしたrepository
+fn fetched() -> (TempRepo, TempRepo) {
+	let remote = TempRepo::new();
+	remote.commit_file("a", "a\n", "first");
+	remote.git(&["branch", "gone"]);
+	remote.git(&["branch", "keep"]);
+
+	let repo = TempRepo::new();
+	repo.git(&["config", "remote.origin.url", remote.dir.to_str().unwrap()]);
+	repo.git(&["fetch"]);
+	(remote, repo)
+}
+
+fn refs(repo: &TempRepo) -> String {
+	repo.git(&["for-each-ref", "--format=%(refname)"])
+}
+
+#[test]
+fn prune_removes_stale_tracking_refs() {
+	let (remote, repo) = fetched();
+	let commit = repo.git(&["rev-parse", "origin/gone"]);
+	// 同じ名前のlocal branchと、別のremoteのrefは消さない
+	repo.git(&["branch", "gone", "origin/gone"]);
+	repo.write(".git/refs/remotes/other/gone", commit.as_bytes());
+	remote.git(&["branch", "-d", "gone"]);
+
+	// --pruneが無ければ残す
+	repo.git(&["fetch"]);
+	assert!(refs(&repo).contains("refs/remotes/origin/gone\n"));
+
+	let output = repo.git(&["fetch", "--prune"]);
+	assert!(output.contains(" - [deleted]"), "{}", output);
+	assert!(output.contains("-> origin/gone"), "{}", output);
+	assert_eq!(
+		refs(&repo),
+		"refs/heads/gone\nrefs/remotes/origin/keep\nrefs/remotes/origin/main\nrefs/remotes/other/gone\n"
+	);
+}
+
+#[test]
+fn prune_without_stale_refs() {
+	let (_remote, repo) = fetched();
+	let before = refs(&repo);
+	repo.git(&["fetch", "-p", "origin"]);
+	assert_eq!(refs(&repo), before);
+}
@@ -0,0 +1,166 @@
+mod common;
+
+use std::fs;
+
+use common::TempRepo;
+
+// *.txtを大文字にして保存し、小文字にして書き出すfilter
+fn upper() -> TempRepo {
+	let repo = TempRepo::new();
+	repo.append(
+		".git/config",
+		"[filter \"up\"]\n\tclean = tr a-z A-Z\n\tsmudge = tr A-Z a-z\n",
+	);
+	repo.write(".gitattributes", "*.txt filter=up\n");
+	repo
+}
+
+// indexにあるpathのblobの内容
+fn staged(repo: &TempRepo, path: &str) -> String {
+	let entries = repo.git(&["ls-files", "-s"]);
+	let entry = entries
+		.lines()
+		.find(|x| x.ends_with(&format!("\t{}", path)))
+		.unwrap();
+	let hash = entry.split(' ').nth(1).unwrap();
+	repo.git(&["cat-file", "-p", hash])
+}
+
+#[test]
+fn clean_on_add() {
+	let repo = upper();
+	repo.write("a.txt", "hello\n");
+	repo.write("b.md", "hello\n");
+	repo.git(&["add", "."]);
+	assert_eq!(staged(&repo, "a.txt"), "HELLO\n");
+	// 属性の無いpathはそのまま
+	assert_eq!(staged(&repo, "b.md"), "hello\n");
+}
+
+#[test]
+fn smudge_on_checkout() {
+	let repo = upper();
+	repo.commit_file("a.txt", "hello\n", "first");
+	repo.git(&["branch", "old"]);
+	repo.commit_file("a.txt", "World\n", "second");
+	assert_eq!(repo.git(&["cat-file", "-p", "HEAD:a.txt"]), "WORLD\n");
+
+	repo.git(&["checkout", "old"]);
+	assert_eq!(repo.read("a.txt"), b"hello\n");
+	repo.git(&["checkout", "main"]);
+	assert_eq!(repo.read("a.txt"), b"world\n");
+	// 書き出したものをcleanすればobjectと同じなので変更は無い
+	let status = repo.git(&["status"]);
+	assert!(!status.contains("a.txt"), "{}", status);
+}
+
+#[test]
+fn failing_filter() {
+	let repo = TempRepo::new();
+	repo.append(".git/config", "[filter \"bad\"]\n\tclean = false\n");
+	repo.write(".gitattributes", "*.txt filter=bad\n");
+	repo.write("a.txt", "hello\n");
+	// requiredでなければ元の内容のまま保存する
+	repo.git(&["add", "a.txt"]);
+	assert_eq!(staged(&repo, "a.txt"), "hello\n");
+
+	repo.append(".git/config", "\trequired = true\n");
+	repo.write("a.txt", "changed\n");
+	let err = repo.fail(&["add", "a.txt"]);
+	assert!(err.contains("clean filter bad failed"), "{}", err);
+	assert_eq!(staged(&repo, "a.txt"), "hello\n");
+}
+
+#[test]
+fn autocrlf() {
+	let repo = TempRepo::new();
+	repo.git(&["config", "core.autocrlf", "true"]);
+	repo.write("a.txt", "a\r\nb\r\n");
+	repo.write("bin", "x\0\r\n");
+	repo.git(&["add", "."]);
+	assert_eq!(staged(&repo, "a.txt"), "a\nb\n");
+	// binaryは変換しない
+	assert_eq!(staged(&repo, "bin"), "x\0\r\n");
+
+	repo.git(&["commit", "-m", "first"]);
+	fs::remove_file(repo.path("a.txt")).unwrap();
+	fs::remove_file(repo.path("bin")).unwrap();
+	repo.git(&["checkout", "-f", "HEAD"]);
+	assert_eq!(repo.read("a.txt"), b"a\r\nb\r\n");
+	assert_eq!(repo.read("bin"), b"x\0\r\n");
+}
+
+#[test]
+fn text_attribute() {
+	let repo = TempRepo::new();
+	repo.write(".gitattributes", "*.txt text eol=crlf\n*.raw -text\n");
+	repo.write("a.txt", "a\r\n");
+	repo.write("b.raw", "b\r\n");
+	repo.git(&["add", "."]);
+	// autocrlfが無くてもtextなら保存するときにLFにする
+	assert_eq!(staged(&repo, "a.txt"), "a\n");
+	assert_eq!(staged(&repo, "b.raw"), "b\r\n");
+
+	repo.git(&["commit", "-m", "first"]);
+	fs::remove_file(repo.path("a.txt")).unwrap();
+	repo.git(&["checkout", "-f", "HEAD"]);
+	assert_eq!(repo.read("a.txt"), b"a\r\n");
+}
+
+// checkoutとresetで書き出すときも、attributeがcore.autocrlfより優先
+#[test]
+fn eol_attribute_on_write() {
+	let repo = TempRepo::new();
+	repo.write(".gitattributes", "*.crlf eol=crlf\n*.raw -text\n");
+	repo.write("a.crlf", "a\nb\n");
+	repo.write("b.raw", "a\nb\n");
+	repo.write("c.txt", "a\nb\n");
+	repo.git(&["add", "."]);
+	repo.git(&["commit", "-m", "first"]);
+
+	let rewrite = |command: &[&str]| {
+		for path in ["a.crlf", "b.raw", "c.txt"] {
+			fs::remove_file(repo.path(path)).unwrap();
+		}
+		repo.git(command);
+	};
+	for (autocrlf, text) in [
+		("true", &b"a\r\nb\r\n"[..]),
+		("false", b"a\nb\n"),
+		("input", b"a\nb\n"),
+	] {
+		repo.git(&["config", "core.autocrlf", autocrlf]);
+		for command in [&["checkout", "-f", "HEAD"][..], &["reset", "--hard"]] {
+			rewrite(command);
+			assert_eq!(repo.read("a.crlf"), b"a\r\nb\r\n", "{:?}", command);
+			assert_eq!(repo.read("b.raw"), b"a\nb\n", "{:?}", command);
+			assert_eq!(repo.read("c.txt"), text, "{} {:?}", autocrlf, command);
+		}
+	}
+}
+
+// cat-file --filtersはcheckoutで書き出す内容を出す
+#[test]
+fn cat_file_filters() {
+	let repo = upper();
+	repo.git(&["config", "core.autocrlf", "true"]);
+	repo.commit_file("a.md", "a\r\nb\r\n", "first");
+	let blob = repo.git(&["rev-parse", "HEAD:a.md"]);
+	let blob = blob.trim();
+	assert_eq!(repo.git(&["cat-file", "-p", blob]), "a\nb\n");
+	assert_eq!(
+		repo.git(&["cat-file", "--filters", "--path=a.md", blob]),
+		"a\r\nb\r\n"
+	);
+	// filterはpathの属性で決まる
+	let upper = repo.git_with(&["hash-object", "-w", "--stdin"], b"HELLO\n");
+	assert_eq!(
+		repo.git(&["cat-file", "--filters", "--path=x.txt", upper.trim()]),
+		"hello\r\n"
+	);
+
+	let err = repo.fail(&["cat-file", "--filters", blob]);
+	assert!(err.contains("--filters requires --path=<path>"), "{}", err);
+	let err = repo.fail(&["cat-file", "-p", "--path=a.md", blob]);
+	assert!(err.contains("'--path=<path>' needs '--filters'"), "{}", err);
+}
@@ -0,0 +1,46 @@
+mod common;
+
+use common::TempRepo;
+
+#[test]
+fn heads_with_format() {
+	let repo = TempRepo::new();
+	let first = repo.commit_file("a", "a\n", "first");
+	repo.git(&["branch", "topic"]);
+	let second = repo.commit_file("a", "b\n", "second");
+	// packed-refsにしか無いrefも並ぶ
+	repo.append(
+		".git/packed-refs",
+		&format!("{} refs/heads/packed\n", first),
+	);
+	repo.git(&["tag", "v1"]);
+
+	let out = repo.git(&[
+		"for-each-ref",
+		"--format=%(refname) %(objectname)",
+		"refs/heads",
+	]);
+	assert_eq!(
+		out,
+		format!(
+			"refs/heads/main {}\nrefs/heads/packed {}\nrefs/heads/topic {}\n",
+			second, first, first
+		)
+	);
+}
+
+#[test]
+fn peeled_tag() {
+	let repo = TempRepo::new();
+	let commit = repo.commit_file("a", "a\n", "first");
+	repo.git(&["tag", "-a", "-m", "release", "v1"]);
+	let tag = repo.git(&["rev-parse", "v1"]).trim().to_string();
+	assert_ne!(tag, commit);
+
+	let out = repo.git(&[
+		"for-each-ref",
+		"--format=%(objecttype) %(objectname) %(*objectname)",
+		"refs/tags",
+	]);
+	assert_eq!(out, format!("tag {} {}\n", tag, commit));
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use common::TempRepo;
+
+// baseの上に2つのcommit
+fn history() -> TempRepo {
+	let repo = TempRepo::new();
+	repo.commit_file("a", "a\n", "base");
+	repo.write("a", "a\nb\n");
+	repo.git(&["add", "a"]);
+	repo.git(&["commit", "-m", "Second change", "-m", "Body line."]);
+	repo.commit_file("c", "c\n", "third: add c");
+	repo
+}
+
+#[test]
+fn numbered_files() {
+	let repo = history();
+	assert_eq!(
+		repo.git(&["format-patch", "HEAD~2..HEAD"]),
+		"0001-Second-change.patch\n0002-third-add-c.patch\n"
+	);
+	// gitで作ったものと比べると、署名のversionだけが違う
+	let first = repo.git(&["rev-parse", "HEAD~1"]);
+	let expected = format!(
+		"From {} Mon Sep 17 00:00:00 2001
+From: Test <test@example.com>
+Date: Tue, 14 Nov 2023 22:13:20 +0000
+Subject: [PATCH 1/2] Second change
+
+Body line.
+---
+ a | 1 +
+ 1 file changed, 1 insertion(+)
+
+diff --git a/a b/a
+index 7898192..422c2b7 100644
+--- a/a
++++ b/a
+@@ -1 +1,2 @@
+ a
++b
+-- 
+{}
+
+",
+		first.trim(),
+		env!("CARGO_PKG_VERSION")
+	);
+	assert_eq!(
+		String::from_utf8(repo.read("0001-Second-change.patch")).unwrap(),
+		expected
+	);
+
+	let second = String::from_utf8(repo.read("0002-third-add-c.patch")).unwrap();
+	assert!(
+		second.contains("\nSubject: [PATCH 2/2] third: add c\n\n---\n"),
+		"{}",
+		second
+	);
+	assert!(
+		second.contains("\ndiff --git a/c b/c\nnew file mode 100644\n"),
+		"{}",
+		second
+	);
+	assert!(second.contains("\n+c\n-- \n"), "{}", second);
+}
+
+#[test]
+fn output_directory_and_stdout() {
+	let repo = history();
+	// <since>だけならそこからHEADまで
+	assert_eq!(
+		repo.git(&["format-patch", "-o", "out", "HEAD~1"]),
+		"out/0001-third-add-c.patch\n"
+	);
+	// 1つだけなら番号を付けない
+	let patch = String::from_utf8(repo.read("out/0001-third-add-c.patch")).unwrap();
+	assert!(
+		patch.contains("\nSubject: [PATCH] third: add c\n"),
+		"{}",
+		patch
+	);
+
+	let stdout = repo.git(&["format-patch", "--stdout", "HEAD~2"]);
+	assert_eq!(stdout.matches("\nSubject: [PATCH ").count(), 2);
+	assert!(stdout.find("1/2] Second change") < stdout.find("2/2] third: add c"));
+}
@@ -0,0 +1,115 @@
+mod common;
+
+use common::TempRepo;
+use toy_git::Hash;
+
+const EMPTY_BLOB: &str = "e69de29bb2d1d6434b8b29ae775ad8c2e48c5391";
+
+// entryが全て空のblobを指すtreeを--literallyで書き、そのhashを返す
+fn write_tree(repo: &TempRepo, entries: &[(&str, &str)]) -> String {
+	let blob = Hash::from_hex(EMPTY_BLOB).unwrap().0;
+	let mut body = Vec::new();
+	for (mode, name) in entries {
+		body.extend(format!("{} {}\0", mode, name).bytes());
+		body.extend(&blob);
+	}
+	let args = ["hash-object", "-t", "tree", "--literally", "-w", "--stdin"];
+	repo.git_with(&args, &body).trim().to_string()
+}
+
+fn fsck(repo: &TempRepo) -> (Option<i32>, String) {
+	let output = repo.run(&["fsck"]);
+	(
+		output.status.code(),
+		String::from_utf8(output.stderr).unwrap(),
+	)
+}
+
+fn repo() -> TempRepo {
+	let repo = TempRepo::new();
+	repo.git_with(&["hash-object", "-w", "--stdin"], b"");
+	repo
+}
+
+// modeが8進数として読めないtreeは壊れている。hashはgitと同じ
+#[test]
+fn bad_mode() {
+	let repo = repo();
+	let hash = write_tree(&repo, &[("33188", "a")]);
+	assert_eq!(hash, "dee0393578335e81a5b332ba11aec06b81223d70");
+	let (code, err) = fsck(&repo);
+	assert_eq!(code, Some(1));
+	assert!(
+		err.contains(&format!(
+			"error in tree {}: badTree: cannot be parsed as a tree",
+			hash
+		)),
+		"{}",
+		err
+	);
+}
+
+#[test]
+fn not_sorted() {
+	let repo = repo();
+	let hash = write_tree(&repo, &[("100644", "b"), ("100644", "a")]);
+	assert_eq!(hash, "3107656e9e18cdf2ebbb3ea59d954ae1d7d02d41");
+	let (code, err) = fsck(&repo);
+	assert_eq!(code, Some(1));
+	assert!(
+		err.contains(&format!(
+			"error in tree {}: treeNotSorted: not properly sorted",
+			hash
+		)),
+		"{}",
+		err
+	);
+}
+
+#[test]
+fn duplicate_entries() {
+	let repo = repo();
+	let hash = write_tree(&repo, &[("100644", "a"), ("100644", "a")]);
+	let (code, err) = fsck(&repo);
+	assert_eq!(code, Some(1));
+	assert!(
+		err.contains(&format!(
+			"error in tree {}: duplicateEntries: contains duplicate file entries",
+			hash
+		)),
+		"{}",
+		err
+	);
+	assert!(!err.contains("treeNotSorted"), "{}", err);
+}
+
+// gitと同じく、warningだけなら終了コードは0
+#[test]
+fn warnings() {
+	for (entry, message) in [
+		(
+			("0100644", "a"),
+			"zeroPaddedFilemode: contains zero-padded file modes",
+		),
+		(("644", "a"), "badFilemode: contains bad file modes"),
+		(("100644", "."), "hasDot: contains '.'"),
+		(("100644", ".."), "hasDotdot: contains '..'"),
+		(("100644", ""), "emptyName: contains empty pathname"),
+		(("100644", "x/y"), "fullPathname: contains full pathnames"),
+	] {
+		let repo = repo();
+		let hash = write_tree(&repo, &[entry]);
+		let (code, err) = fsck(&repo);
+		assert_eq!(code, Some(0), "{}", err);
+		assert!(
+			err.contains(&format!("warning in tree {}: {}", hash, message)),
+			"{}",
+			err
+		);
+	}
+	// 100664は古いgitが書いていたので受け付ける
+	let repo = repo();
+	write_tree(&repo, &[("100664", "a"), ("120000", "b")]);
+	let (_, err) = fsck(&repo);
+	assert!(!err.contains("in tree"), "{}", err);
+}
@@ -0,0 +1,164 @@
+mod common;
+
+use std::fs;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use common::TempRepo;
+use toy_git::gc_lock::GcLock;
+
+fn packs(repo: &TempRepo) -> usize {
+	fs::read_dir(repo.path(".git/objects/pack"))
+		.unwrap()
+		.filter(|x| x.as_ref().unwrap().path().extension() == Some("pack".as_ref()))
+		.count()
+}
+
+fn loose(repo: &TempRepo) -> usize {
+	fs::read_dir(repo.path(".git/objects"))
+		.unwrap()
+		.map(|x| x.unwrap())
+		.filter(|x| x.file_name().len() == 2)
+		.map(|x| fs::read_dir(x.path()).unwrap().count())
+		.sum()
+}
+
+// 3つのcommit、3つのtree、3つのblob
+fn history() -> TempRepo {
+	let repo = TempRepo::new();
+	repo.commit_file("a", "1\n", "first");
+	repo.commit_file("a", "2\n", "second");
+	repo.commit_file("a", "3\n", "third");
+	assert_eq!(loose(&repo), 9);
+	repo
+}
+
+#[test]
+fn auto_with_few_loose_objects() {
+	let repo = history();
+	// 既定では6700より多いときだけ
+	repo.git(&["gc", "--auto"]);
+	assert_eq!(packs(&repo), 0);
+	repo.git(&["config", "gc.auto", "9"]);
+	repo.git(&["gc", "--auto"]);
+	assert_eq!(packs(&repo), 0);
+	assert_eq!(loose(&repo), 9);
+
+	// 0ならいくつあってもまとめない
+	repo.git(&["config", "gc.auto", "0"]);
+	repo.git(&["gc", "--auto"]);
+	assert_eq!(packs(&repo), 0);
+}
+
+#[test]
+fn auto_with_many_loose_objects() {
+	let repo = history();
+	repo.git(&["config", "gc.auto", "8"]);
+	let output = repo.run(&["gc", "--auto"]);
+	assert!(output.status.success());
+	let err = String::from_utf8(output.stderr).unwrap();
+	assert!(err.contains("Auto packing the repository"), "{}", err);
+	assert_eq!(packs(&repo), 1);
+	assert_eq!(loose(&repo), 0);
+	assert_eq!(repo.git(&["log", "--format=%s"]), "third\nsecond\nfirst\n");
+}
+
+#[test]
+fn auto_with_many_packs() {
+	let repo = history();
+	let base = repo.path(".git/objects/pack/pack");
+	for rev in ["HEAD~2", "HEAD~1"] {
+		let hash = repo.git(&["rev-parse", rev]);
+		repo.git_with(&["pack-objects", base.to_str().unwrap()], hash.as_bytes());
+	}
+	repo.git(&["config", "gc.auto", "0"]);
+	repo.git(&["config", "gc.autoPackLimit", "2"]);
+	repo.git(&["gc", "--auto"]);
+	assert_eq!(packs(&repo), 2);
+
+	repo.git(&["config", "gc.autoPackLimit", "1"]);
+	repo.git(&["gc", "--auto"]);
+	assert_eq!(packs(&repo), 1);
+	assert_eq!(loose(&repo), 0);
+}
+
+#[test]
+fn lock_blocks_gc() {
+	let repo = history();
+	let lock = GcLock::acquire(&repo.open(), false).unwrap();
+	assert!(repo.path(".git/gc.pid").exists());
+	// このprocessは動いているので、他のgcやprune-packedはobjectに触らない
+	let err = repo.fail(&["gc"]);
+	assert!(err.contains("gc is already running on machine"), "{}", err);
+	let err = repo.fail(&["prune-packed"]);
+	assert!(err.contains("gc is already running on machine"), "{}", err);
+	assert_eq!(packs(&repo), 0);
+	assert_eq!(loose(&repo), 9);
+	assert!(!repo.path(".git/gc.pid.lock").exists());
+
+	drop(lock);
+	assert!(!repo.path(".git/gc.pid").exists());
+	repo.git(&["gc"]);
+	assert_eq!(packs(&repo), 1);
+	assert!(!repo.path(".git/gc.pid").exists());
+}
+
+#[test]
+fn stale_lock_is_reclaimed() {
+	let repo = history();
+	let host = fs::read_to_string("/proc/sys/kernel/hostname").unwrap();
+	// 同じmachineの死んだprocess
+	repo.write(".git/gc.pid", format!("4294967295 {}\n", host.trim()));
+	repo.git(&["gc"]);
+	assert_eq!(packs(&repo), 1);
+	assert!(!repo.path(".git/gc.pid").exists());
+
+	// 他のmachineのものは古くなるまで残る
+	repo.write(".git/gc.pid", "1 elsewhere\n");
+	let err = repo.fail(&["gc"]);
+	assert!(err.contains("machine 'elsewhere' pid 1"), "{}", err);
+	let old = SystemTime::now() - Duration::from_secs(13 * 60 * 60);
+	fs::File::options()
+		.write(true)
+		.open(repo.path(".git/gc.pid"))
+		.unwrap()
+		.set_modified(old)
+		.unwrap();
+	repo.git(&["gc"]);
+
+	repo.write(".git/gc.pid", "1 elsewhere\n");
+	repo.git(&["gc", "--force"]);
+	assert!(!repo.path(".git/gc.pid").exists());
+}
+
+#[test]
+fn leftover_lock_file() {
+	let repo = history();
+	repo.write(".git/gc.pid.lock", "");
+	let err = repo.fail(&["gc"]);
+	assert!(err.contains("gc.pid.lock': File exists."), "{}", err);
+	assert_eq!(loose(&repo), 9);
+}
+
+// 同時に動かしたgcはどれか1つだけが行い、残りはlockで止まる
+#[test]
+fn concurrent_gc() {
+	let repo = history();
+	let outputs: Vec<_> = thread::scope(|scope| {
+		let handles: Vec<_> = (0..4).map(|_| scope.spawn(|| repo.run(&["gc"]))).collect();
+		handles.into_iter().map(|x| x.join().unwrap()).collect()
+	});
+	for output in outputs.iter().filter(|x| !x.status.success()) {
+		let err = String::from_utf8_lossy(&output.stderr);
+		assert!(
+			err.contains("gc is already running") || err.contains("File exists."),
+			"{}",
+			err
+		);
+	}
+	assert!(outputs.iter().any(|x| x.status.success()));
+	repo.git(&["fsck"]);
+	assert_eq!(repo.git(&["log", "--format=%s"]), "third\nsecond\nfirst\n");
+	assert_eq!(repo.git(&["cat-file", "-p", "HEAD~2:a"]), "1\n");
+	assert!(!repo.path(".git/gc.pid").exists());
+}
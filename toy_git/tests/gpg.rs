@@ -0,0 +1,184 @@
+mod common;
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+
+use common::TempRepo;
+use toy_git::{store, Hash};
+
+// 署名するときは渡されたpayloadを残し、そのcksumを署名とするgpg.program
+// --verifyではpayloadのcksumが署名と合うかを確かめる
+const FAKE_GPG: &str = r#"#!/bin/sh
+case "$*" in
+*--verify*)
+	sum=$(cksum | cut -d' ' -f1)
+	if grep -q "^$sum\$" "$4"; then
+		echo "[GNUPG:] GOODSIG 0123456789ABCDEF Test"
+		echo 'gpg: Good signature from "Test"' >&2
+	else
+		echo "[GNUPG:] BADSIG 0123456789ABCDEF Test"
+		echo 'gpg: BAD signature from "Test"' >&2
+		exit 1
+	fi;;
+*)
+	sum=$(tee "$(dirname "$0")/payload" | cksum | cut -d' ' -f1)
+	printf -- '-----BEGIN PGP SIGNATURE-----\n\n%s\n-----END PGP SIGNATURE-----\n' "$sum";;
+esac
+"#;
+
+fn fake_gpg(repo: &TempRepo) {
+	let script = repo.path("fake-gpg");
+	fs::write(&script, FAKE_GPG).unwrap();
+	fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+	repo.git(&["config", "gpg.program", script.to_str().unwrap()]);
+}
+
+#[test]
+fn signed_commit() {
+	let repo = TempRepo::new();
+	fake_gpg(&repo);
+	repo.write("a", "a\n");
+	repo.git(&["add", "a"]);
+	repo.git(&["commit", "-S", "-m", "signed"]);
+
+	let hash = Hash::from_hex(repo.git(&["rev-parse", "HEAD"]).trim()).unwrap();
+	let commit = store::read_commit(&repo.open(), &hash.0).unwrap();
+	let signature = commit.signature.clone().unwrap();
+	assert!(
+		signature.starts_with("-----BEGIN PGP SIGNATURE-----\n\n"),
+		"{}",
+		signature
+	);
+	// 署名したのはgpgsig headerを除いたもの
+	assert_eq!(commit.payload().as_bytes(), repo.read("payload"));
+
+	let raw = repo.git(&["cat-file", "-p", "HEAD"]);
+	assert!(
+		raw.contains("\ngpgsig -----BEGIN PGP SIGNATURE-----\n \n "),
+		"{}",
+		raw
+	);
+}
+
+#[test]
+fn signed_commit_tree() {
+	let repo = TempRepo::new();
+	fake_gpg(&repo);
+	repo.write("a", "a\n");
+	repo.git(&["add", "a"]);
+	let tree = repo.git(&["write-tree"]);
+	let hash = repo.git(&["commit-tree", tree.trim(), "-m", "signed", "-S"]);
+
+	let hash = Hash::from_hex(hash.trim()).unwrap();
+	let commit = store::read_commit(&repo.open(), &hash.0).unwrap();
+	assert!(commit.signature.is_some());
+	assert_eq!(commit.payload().as_bytes(), repo.read("payload"));
+}
+
+#[test]
+fn failing_program() {
+	let repo = TempRepo::new();
+	repo.git(&["config", "gpg.program", "false"]);
+	repo.write("a", "a\n");
+	repo.git(&["add", "a"]);
+	let err = repo.fail(&["commit", "-S", "-m", "signed"]);
+	assert!(err.contains("gpg failed to sign the data"), "{}", err);
+	assert!(repo.run(&["rev-parse", "--verify", "HEAD"]).status.code() != Some(0));
+}
+
+#[test]
+fn failed_signing_writes_no_objects() {
+	let repo = TempRepo::new();
+	repo.git(&["config", "gpg.program", "false"]);
+	repo.write("a", "a\n");
+	repo.git(&["add", "a"]);
+	let objects = || {
+		fs::read_dir(repo.path(".git/objects"))
+			.unwrap()
+			.map(|x| x.unwrap().file_name().into_string().unwrap())
+			.filter(|x| x.len() == 2)
+			.count()
+	};
+	let before = objects();
+	repo.fail(&["commit", "-S", "-m", "signed"]);
+	// commitの中で作ったtreeもtransactionごと消える
+	assert_eq!(objects(), before);
+}
+
+// fake-gpgでpayloadに付ける署名
+fn sign(repo: &TempRepo, payload: &str) -> String {
+	repo.write("unsigned", payload);
+	let output = std::process::Command::new(repo.path("fake-gpg"))
+		.stdin(fs::File::open(repo.path("unsigned")).unwrap())
+		.output()
+		.unwrap();
+	String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn verify_commit() {
+	let repo = TempRepo::new();
+	fake_gpg(&repo);
+	repo.write("a", "a\n");
+	repo.git(&["add", "a"]);
+	repo.git(&["commit", "-S", "-m", "signed"]);
+	let output = repo.run(&["verify-commit", "HEAD"]);
+	assert!(output.status.success());
+	let err = String::from_utf8(output.stderr).unwrap();
+	assert!(err.contains("Good signature"), "{}", err);
+
+	// 署名はそのままでmessageを変えたcommit
+	let raw = repo.git(&["cat-file", "-p", "HEAD"]);
+	let tampered = raw.replace("\n\nsigned\n", "\n\ntampered\n");
+	let hash = repo.git_with(
+		&["hash-object", "-w", "-t", "commit", "--stdin"],
+		tampered.as_bytes(),
+	);
+	let err = repo.fail(&["verify-commit", hash.trim()]);
+	assert!(err.contains(": bad signature"), "{}", err);
+}
+
+#[test]
+fn verify_unsigned_commit() {
+	let repo = TempRepo::new();
+	fake_gpg(&repo);
+	repo.commit_file("a", "a\n", "unsigned");
+	let err = repo.fail(&["verify-commit", "HEAD"]);
+	assert!(err.contains("HEAD: no signature found"), "{}", err);
+}
+
+#[test]
+fn verify_tag() {
+	let repo = TempRepo::new();
+	fake_gpg(&repo);
+	let commit = repo.commit_file("a", "a\n", "first");
+	let payload = format!(
+		"object {}\ntype commit\ntag v1\ntagger Test <test@example.com> 1700000000 +0000\n\nrelease\n",
+		commit
+	);
+	let tag = format!("{}{}", payload, sign(&repo, &payload));
+	let hash = repo.git_with(
+		&["hash-object", "-w", "-t", "tag", "--stdin"],
+		tag.as_bytes(),
+	);
+	let update = format!("create refs/tags/v1 {}", hash);
+	repo.git_with(&["update-ref", "--stdin"], update.as_bytes());
+	repo.git(&["verify-tag", "v1"]);
+	// tag -vはmessageも表示する
+	assert!(repo.git(&["tag", "-v", "v1"]).ends_with("\n\nrelease\n"));
+
+	let tampered = tag.replace("\nrelease\n", "\nchanged\n");
+	let hash = repo.git_with(
+		&["hash-object", "-w", "-t", "tag", "--stdin"],
+		tampered.as_bytes(),
+	);
+	let err = repo.fail(&["verify-tag", hash.trim()]);
+	assert!(err.contains(": bad signature"), "{}", err);
+
+	let err = repo.fail(&["verify-tag", &commit]);
+	assert!(
+		err.contains("cannot verify a non-tag object of type commit"),
+		"{}",
+		err
+	);
+}
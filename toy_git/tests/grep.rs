@@ -0,0 +1,52 @@
+mod common;
+
+use common::TempRepo;
+
+// fooを含むaとd/cと含まないb、binaryのbinをcommitしたもの
+fn files() -> TempRepo {
+	let repo = TempRepo::new();
+	repo.write("a", "one\nfoo bar\nthree\nFOO\n");
+	repo.write("b", "none\n");
+	repo.write("d/c", "x\nfoo\n");
+	repo.write("bin", "foo\0bin");
+	repo.git(&["add", "."]);
+	repo.git(&["commit", "-m", "first"]);
+	repo
+}
+
+#[test]
+fn lines() {
+	let repo = files();
+	assert_eq!(repo.git(&["grep", "foo"]), "a:2:foo bar\nd/c:2:foo\n");
+	assert_eq!(
+		repo.git(&["grep", "-i", "foo"]),
+		"a:2:foo bar\na:4:FOO\nd/c:2:foo\n"
+	);
+	assert_eq!(repo.git(&["grep", "-l", "foo"]), "a\nd/c\n");
+	assert_eq!(repo.git(&["grep", "-E", "^(one|x)$"]), "a:1:one\nd/c:1:x\n");
+}
+
+#[test]
+fn worktree_index_and_commit() {
+	let repo = files();
+	repo.write("b", "foo\n");
+	assert_eq!(
+		repo.git(&["grep", "foo"]),
+		"a:2:foo bar\nb:1:foo\nd/c:2:foo\n"
+	);
+	assert_eq!(
+		repo.git(&["grep", "--cached", "foo"]),
+		"a:2:foo bar\nd/c:2:foo\n"
+	);
+
+	repo.git(&["add", "b"]);
+	repo.git(&["commit", "-m", "second"]);
+	assert_eq!(
+		repo.git(&["grep", "foo", "HEAD~1"]),
+		"HEAD~1:a:2:foo bar\nHEAD~1:d/c:2:foo\n"
+	);
+	assert_eq!(
+		repo.git(&["grep", "-l", "foo", "HEAD"]),
+		"HEAD:a\nHEAD:b\nHEAD:d/c\n"
+	);
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use common::TempRepo;
+
+const FOO: &str = "257cc5642cb1a054f08cc83f2d943e56fd3ebe99"; // "foo\n"
+const HELLO: &str = "ce013625030ba8dba906f756967f9e9ca394464a"; // "hello\n"
+const EMPTY: &str = "e69de29bb2d1d6434b8b29ae775ad8c2e48c5391";
+
+#[test]
+fn stdin() {
+	let repo = TempRepo::new();
+	assert_eq!(
+		repo.git_with(&["hash-object", "--stdin"], b"foo\n"),
+		format!("{}\n", FOO)
+	);
+	assert!(!repo.path(".git/objects/25").exists());
+
+	repo.git_with(&["hash-object", "--stdin", "-w"], b"foo\n");
+	assert!(repo
+		.path(".git/objects/25/7cc5642cb1a054f08cc83f2d943e56fd3ebe99")
+		.exists());
+	assert_eq!(repo.git(&["cat-file", "-p", FOO]), "foo\n");
+}
+
+#[test]
+fn several_paths() {
+	let repo = TempRepo::new();
+	repo.write("a", "hello\n");
+	repo.write("b", "");
+	assert_eq!(
+		repo.git_with(&["hash-object", "--stdin", "a", "b"], b"foo\n"),
+		format!("{}\n{}\n{}\n", FOO, HELLO, EMPTY)
+	);
+}
+
+#[test]
+fn commit_type_is_checked() {
+	let repo = TempRepo::new();
+	let output = repo.run_in(
+		&repo.dir,
+		&["hash-object", "-t", "commit", "--stdin"],
+		Some(b"not a commit\n"),
+	);
+	assert!(!output.status.success());
+
+	let commit = "tree 4b825dc642cb6eb9a060e54bf8d69288fbee4904\n\
+		author A <a@example.com> 0 +0000\n\
+		committer A <a@example.com> 0 +0000\n\nmessage\n";
+	let hash = repo.git_with(
+		&["hash-object", "-t", "commit", "--stdin", "-w"],
+		commit.as_bytes(),
+	);
+	assert_eq!(repo.git(&["cat-file", "-t", hash.trim()]), "commit\n");
+
+	// --literallyなら確かめない
+	repo.git_with(
+		&["hash-object", "-t", "commit", "--literally", "--stdin"],
+		b"not a commit\n",
+	);
+}
+
+// --literally -wで壊れたobjectを作れる。hashはgitと同じ
+#[test]
+fn literally_written_object_fails_fsck() {
+	let repo = TempRepo::new();
+	repo.commit_file("a", "a\n", "first");
+	repo.git(&["fsck"]);
+	let hash = repo.git_with(
+		&[
+			"hash-object",
+			"-t",
+			"commit",
+			"--literally",
+			"-w",
+			"--stdin",
+		],
+		b"not a commit\n",
+	);
+	assert_eq!(hash, "fcd4989c0b35a94fc0ab7a3c52a38a4edcf9b41a\n");
+	assert_eq!(repo.git(&["cat-file", "-t", hash.trim()]), "commit\n");
+
+	let err = repo.fail(&["fsck"]);
+	assert!(
+		err.contains(&format!("error in commit {}", hash.trim())),
+		"{}",
+		err
+	);
+}
+
+#[test]
+fn tag_type_is_checked() {
+	let repo = TempRepo::new();
+	// gitと同じく、taggerが無いのはよいがtypeが無いのはError
+	let tag = format!("object {}\ntag v1\n\nno type\n", FOO);
+	let output = repo.run_in(
+		&repo.dir,
+		&["hash-object", "-t", "tag", "--stdin"],
+		Some(tag.as_bytes()),
+	);
+	assert!(!output.status.success());
+	let err = String::from_utf8(output.stderr).unwrap();
+	assert!(err.contains("corrupt tag"), "{}", err);
+
+	let tag = format!(
+		"object {}\ntype blob\ntag v1\ntagger A <a@example.com> 0 +0000\n\nmessage\n",
+		FOO
+	);
+	let hash = repo.git_with(
+		&["hash-object", "-t", "tag", "--stdin", "-w"],
+		tag.as_bytes(),
+	);
+	assert_eq!(repo.git(&["cat-file", "-t", hash.trim()]), "tag\n");
+}
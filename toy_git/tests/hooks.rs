@@ -0,0 +1,124 @@
+mod common;
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+
+use common::TempRepo;
+
+fn hook(repo: &TempRepo, name: &str, script: &str) {
+	let path = repo.path(&format!(".git/hooks/{}", name));
+	fs::write(&path, format!("#!/bin/sh\n{}", script)).unwrap();
+	fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+fn commits(repo: &TempRepo) -> usize {
+	match repo.run(&["rev-list", "HEAD"]) {
+		x if x.status.success() => x.stdout.iter().filter(|&&b| b == b'\n').count(),
+		_ => 0,
+	}
+}
+
+#[test]
+fn default_hooks_are_disabled() {
+	let repo = TempRepo::new();
+	assert!(repo.path(".git/hooks/pre-commit.sample").is_file());
+	assert!(!repo.path(".git/hooks/pre-commit").exists());
+	repo.commit_file("a", "a\n", "first");
+	assert_eq!(commits(&repo), 1);
+}
+
+#[test]
+fn failing_pre_commit_blocks_commit() {
+	let repo = TempRepo::new();
+	hook(&repo, "pre-commit", "echo rejected >&2\nexit 1\n");
+	repo.write("a", "a\n");
+	repo.git(&["add", "a"]);
+	let err = repo.fail(&["commit", "-m", "first"]);
+	assert!(err.contains("rejected"), "{}", err);
+	assert!(
+		err.contains("pre-commit hook exited with status 1"),
+		"{}",
+		err
+	);
+	assert_eq!(commits(&repo), 0);
+
+	// --no-verifyなら実行しない
+	repo.git(&["commit", "--no-verify", "-m", "first"]);
+	assert_eq!(commits(&repo), 1);
+}
+
+#[test]
+fn passing_pre_commit_allows_commit() {
+	let repo = TempRepo::new();
+	// working directoryで実行される
+	hook(&repo, "pre-commit", "touch ran\nexit 0\n");
+	repo.commit_file("a", "a\n", "first");
+	assert_eq!(commits(&repo), 1);
+	assert!(repo.path("ran").exists());
+}
+
+#[test]
+fn not_executable_hook_is_ignored() {
+	let repo = TempRepo::new();
+	hook(&repo, "pre-commit", "exit 1\n");
+	let path = repo.path(".git/hooks/pre-commit");
+	fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+	repo.write("a", "a\n");
+	repo.git(&["add", "a"]);
+	let output = repo.run(&["commit", "-m", "first"]);
+	assert!(output.status.success());
+	let err = String::from_utf8(output.stderr).unwrap();
+	assert!(
+		err.contains("hook was ignored because it's not set as executable"),
+		"{}",
+		err
+	);
+	assert_eq!(commits(&repo), 1);
+}
+
+#[test]
+fn template() {
+	let template = TempRepo::empty();
+	template.write("hooks/pre-commit", "#!/bin/sh\nexit 1\n");
+	let path = template.path("hooks/pre-commit");
+	fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+	template.write("info/exclude", "*.log\n");
+
+	let repo = TempRepo::empty();
+	let arg = format!("--template={}", template.dir.display());
+	repo.git(&["init", "-q", &arg]);
+	// 権限もそのままコピーされ、既定のsampleは置かない
+	let copied = fs::metadata(repo.path(".git/hooks/pre-commit")).unwrap();
+	assert_eq!(copied.permissions().mode() & 0o777, 0o755);
+	assert_eq!(repo.read(".git/info/exclude"), b"*.log\n");
+	assert!(!repo.path(".git/hooks/pre-commit.sample").exists());
+
+	repo.git(&["config", "user.name", "Test"]);
+	repo.git(&["config", "user.email", "test@example.com"]);
+	repo.write("a", "a\n");
+	repo.git(&["add", "a"]);
+	repo.fail(&["commit", "-m", "first"]);
+	assert_eq!(commits(&repo), 0);
+}
+
+#[test]
+fn template_from_config() {
+	let template = TempRepo::empty();
+	template.write("description", "from config\n");
+	let repo = TempRepo::empty();
+	let dir = template.dir.to_str().unwrap();
+	repo.write(".gitconfig", format!("[init]\n\ttemplateDir = {}\n", dir));
+	repo.git(&["init", "-q", "sub"]);
+	assert_eq!(repo.read("sub/.git/description"), b"from config\n");
+
+	// 無いtemplateは警告して使わない
+	let output = repo.run(&["init", "-q", "--template=/nonexistent", "other"]);
+	assert!(output.status.success());
+	let err = String::from_utf8(output.stderr).unwrap();
+	assert!(
+		err.contains("templates not found in /nonexistent"),
+		"{}",
+		err
+	);
+	assert!(repo.path("other/.git/HEAD").is_file());
+}
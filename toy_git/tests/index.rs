@@ -0,0 +1,339 @@
+mod common;
+
+use std::fs;
+use std::time::Duration;
+
+use common::TempRepo;
+use toy_git::index::Entry;
+use toy_git::{diff, Hash, Index};
+
+fn stage_both(ignorecase: bool) -> String {
+	let repo = TempRepo::new();
+	repo.git(&["config", "core.ignorecase", &ignorecase.to_string()]);
+	repo.write("README", "upper\n");
+	repo.git(&["add", "README"]);
+	repo.write("readme", "lower\n");
+	repo.git(&["add", "readme"]);
+	repo.git(&["ls-files", "-s"])
+}
+
+#[test]
+fn ignorecase_true_keeps_one_entry() {
+	// 先に登録したpathの表記のまま、後から加えた内容になる
+	let files = stage_both(true);
+	assert_eq!(
+		files,
+		"100644 3f2d9cedc75ad2766b40a4158022fcc9f5dfe9b1 0\tREADME\n"
+	);
+}
+
+#[test]
+fn ignorecase_false_keeps_both() {
+	let files = stage_both(false);
+	let paths: Vec<&str> = files
+		.lines()
+		.map(|x| x.split('\t').nth(1).unwrap())
+		.collect();
+	assert_eq!(paths, ["README", "readme"]);
+}
+
+fn paths(index: &Index) -> Vec<(&str, u16)> {
+	index
+		.entries
+		.iter()
+		.map(|x| (x.path.as_str(), x.stage()))
+		.collect()
+}
+
+// どの順にaddしてもpathのbyte列とstageの順に並ぶ
+#[test]
+fn add_keeps_order() {
+	let mut index = Index::new();
+	let hash = vec![0xab; 20];
+	for path in ["b", "a/b", "a.txt", "c", "a-b", "a"] {
+		index.add(Entry::unmerged(
+			String::from(path),
+			hash.clone(),
+			0o100644,
+			0,
+		));
+	}
+	for stage in [3, 1, 2] {
+		index.add(Entry::unmerged(
+			String::from("b"),
+			hash.clone(),
+			0o100644,
+			stage,
+		));
+	}
+	assert_eq!(
+		paths(&index),
+		[
+			("a", 0),
+			("a-b", 0),
+			("a.txt", 0),
+			("a/b", 0),
+			("b", 1),
+			("b", 2),
+			("b", 3),
+			("c", 0)
+		]
+	);
+	assert_eq!(index.find("b").unwrap().stage(), 1);
+	assert!(index.find("a/").is_none());
+	assert!(index.has_under("a/") && !index.has_under("b/"));
+
+	// conflictのエントリは同じstageのものだけを、stage 0は全てのstageを置き換える
+	index.add(Entry::unmerged(
+		String::from("b"),
+		vec![0xcd; 20],
+		0o100644,
+		2,
+	));
+	assert_eq!(paths(&index)[4..7], [("b", 1), ("b", 2), ("b", 3)]);
+	assert_eq!(index.entries[5].hash, vec![0xcd; 20]);
+	index.add(Entry::unmerged(
+		String::from("b"),
+		hash.clone(),
+		0o100644,
+		0,
+	));
+	assert_eq!(paths(&index)[4..], [("b", 0), ("c", 0)]);
+}
+
+// 多くのファイルを一度にaddしても、変わっていなければ二度目は何も変えない
+#[test]
+fn add_many_files() {
+	let repo = TempRepo::new();
+	for dir in 0..20 {
+		for file in 0..50 {
+			repo.write(
+				&format!("d{}/f{}", dir, file),
+				format!("{} {}\n", dir, file),
+			);
+		}
+	}
+	repo.git(&["add", "."]);
+	let files = repo.git(&["ls-files"]);
+	let mut sorted: Vec<&str> = files.lines().collect();
+	assert_eq!(sorted.len(), 1000);
+	sorted.sort();
+	assert_eq!(sorted, files.lines().collect::<Vec<_>>());
+
+	let before = repo.read(".git/index");
+	repo.git(&["add", "."]);
+	assert_eq!(repo.read(".git/index"), before);
+	repo.git(&["commit", "-m", "many"]);
+	repo.git(&["fsck"]);
+}
+
+// dとeのディレクトリとfileをaddしてwrite-treeしたもの
+fn written() -> (TempRepo, String) {
+	let repo = TempRepo::new();
+	repo.write("d/a", "a\n");
+	repo.write("e/b", "b\n");
+	repo.write("file", "f\n");
+	repo.git(&["add", "."]);
+	let tree = repo.git(&["write-tree"]).trim().to_string();
+	(repo, tree)
+}
+
+#[test]
+fn tree_cache_is_recorded() {
+	let (repo, tree) = written();
+	let index = Index::load(&repo.open()).unwrap();
+	let root = index.tree.unwrap();
+	assert_eq!(Hash(root.hash.clone().unwrap()).to_string(), tree);
+	assert_eq!(root.entry_count, 3);
+	let d = root.child("d").unwrap();
+	assert_eq!(d.entry_count, 1);
+	assert_eq!(
+		Hash(d.hash.clone().unwrap()).to_string(),
+		repo.git(&["rev-parse", &format!("{}:d", tree)]).trim()
+	);
+}
+
+#[test]
+fn tree_cache_is_reused() {
+	let (repo, tree) = written();
+	// 有効なcacheのhashはそのまま使うので、別のtreeにすり替えるとそれが返る
+	let other = repo
+		.git(&["rev-parse", &format!("{}:d", tree)])
+		.trim()
+		.to_string();
+	let opened = repo.open();
+	let mut index = Index::load(&opened).unwrap();
+	index.tree.as_mut().unwrap().hash = Some(Hash::from_hex(&other).unwrap().0);
+	index.save(&opened).unwrap();
+	assert_eq!(repo.git(&["write-tree"]).trim(), other);
+}
+
+#[test]
+fn tree_cache_is_invalidated() {
+	let (repo, tree) = written();
+	repo.write("d/new", "n\n");
+	repo.git(&["add", "d/new"]);
+	let index = Index::load(&repo.open()).unwrap();
+	let root = index.tree.unwrap();
+	assert_eq!(root.entry_count, -1);
+	assert_eq!(root.child("d").unwrap().entry_count, -1);
+	// 変わっていないディレクトリは有効なまま
+	assert_eq!(root.child("e").unwrap().entry_count, 1);
+
+	let second = repo.git(&["write-tree"]).trim().to_string();
+	assert_ne!(second, tree);
+	assert_eq!(
+		repo.git(&["rev-parse", &format!("{}:e", second)]),
+		repo.git(&["rev-parse", &format!("{}:e", tree)])
+	);
+
+	repo.git(&["rm", "--cached", "e/b"]);
+	let index = Index::load(&repo.open()).unwrap();
+	let root = index.tree.unwrap();
+	assert_eq!(root.entry_count, -1);
+	assert_eq!(root.child("d").unwrap().entry_count, 2);
+}
+
+#[test]
+fn write_tree_prefix() {
+	let repo = TempRepo::new();
+	repo.write("a/b.txt", "b\n");
+	repo.write("a/d/e.txt", "e\n");
+	repo.write("c.txt", "c\n");
+	repo.git(&["add", "."]);
+
+	let tree = repo.git(&["write-tree", "--prefix=a"]);
+	assert_eq!(repo.git(&["write-tree", "--prefix=a/"]), tree);
+	let root = repo.git(&["write-tree"]);
+	assert_eq!(
+		repo.git(&["rev-parse", &format!("{}:a", root.trim())]),
+		tree
+	);
+	let listing = repo.git(&["ls-tree", tree.trim()]);
+	assert!(
+		listing.starts_with(
+			"100644 blob 61780798228d17af2d34fce4cfbdf35556832472\tb.txt\n040000 tree "
+		),
+		"{}",
+		listing
+	);
+	assert!(listing.ends_with("\td\n"), "{}", listing);
+	assert_eq!(listing.lines().count(), 2);
+
+	let nested = repo.git(&["write-tree", "--prefix=a/d"]);
+	assert_eq!(
+		repo.git(&["rev-parse", &format!("{}:a/d", root.trim())]),
+		nested
+	);
+}
+
+#[test]
+fn write_tree_bad_prefix() {
+	let repo = TempRepo::new();
+	repo.write("a/b.txt", "b\n");
+	repo.write("c.txt", "c\n");
+	repo.git(&["add", "."]);
+	for prefix in ["nope", "c.txt", "a/b.txt"] {
+		let err = repo.fail(&["write-tree", &format!("--prefix={}", prefix)]);
+		assert!(
+			err.contains(&format!("prefix {} not found", prefix)),
+			"{}",
+			err
+		);
+	}
+}
+
+// 大きなファイルをaddし、indexのhashだけを別のものにする
+// statの情報が使われればそのhashが、読み直せばファイルのhashが返る
+fn stat_cached() -> (TempRepo, Vec<u8>) {
+	let repo = TempRepo::new();
+	repo.write("big", "line\n".repeat(200_000));
+	repo.git(&["add", "big"]);
+	let opened = repo.open();
+	let mut index = Index::load(&opened).unwrap();
+	let fake = vec![0xab; 20];
+	index.entries[0].hash = fake.clone();
+	index.save(&opened).unwrap();
+	(repo, fake)
+}
+
+// indexファイルのmtimeをファイルのmtimeからsecs秒ずらす
+fn touch_index(repo: &TempRepo, secs: i64) {
+	let file = fs::metadata(repo.path("big")).unwrap().modified().unwrap();
+	let time = match secs >= 0 {
+		true => file + Duration::from_secs(secs as u64),
+		false => file - Duration::from_secs(-secs as u64),
+	};
+	let index = fs::File::options()
+		.write(true)
+		.open(repo.path(".git/index"))
+		.unwrap();
+	index.set_modified(time).unwrap();
+}
+
+fn worktree_hash(repo: &TempRepo) -> Vec<u8> {
+	let opened = repo.open();
+	let index = Index::load(&opened).unwrap();
+	let entries = diff::worktree_entries(&opened, &index).unwrap();
+	entries["big"].hash.clone()
+}
+
+#[test]
+fn unchanged_file_is_not_rehashed() {
+	let (repo, fake) = stat_cached();
+	touch_index(&repo, 10);
+	assert_eq!(worktree_hash(&repo), fake);
+	// 内容を読まないので、diffでも変更されていない
+	assert_eq!(repo.git(&["diff", "--name-only"]), "");
+}
+
+#[test]
+fn racy_file_is_rehashed() {
+	let (repo, fake) = stat_cached();
+	// ファイルがindexと同じ時刻以降に変わっていれば、statが同じでも読み直す
+	touch_index(&repo, 0);
+	let hash = worktree_hash(&repo);
+	assert_ne!(hash, fake);
+	let expected = repo.git(&["hash-object", "big"]);
+	assert_eq!(Hash(hash).to_string(), expected.trim());
+
+	touch_index(&repo, -10);
+	assert_ne!(worktree_hash(&repo), fake);
+}
+
+#[test]
+fn changed_stat_is_rehashed() {
+	let (repo, fake) = stat_cached();
+	repo.append("big", "more\n");
+	touch_index(&repo, 10);
+	assert_ne!(worktree_hash(&repo), fake);
+}
+
+// indexはindex.lockに書いてからrenameする。lockが既にあれば書かない
+#[test]
+fn locked_index() {
+	let repo = TempRepo::new();
+	repo.commit_file("a", "a\n", "first");
+	let before = repo.read(".git/index");
+	repo.write(".git/index.lock", "");
+	repo.write("b", "b\n");
+	let output = repo.run(&["add", "b"]);
+	assert_eq!(output.status.code(), Some(128));
+	let err = String::from_utf8(output.stderr).unwrap();
+	assert!(
+		err.starts_with(&format!(
+			"fatal: Unable to create '{}': File exists.\n\nAnother git process",
+			repo.path(".git/index.lock").display()
+		)),
+		"{}",
+		err
+	);
+	// 他のprocessのlockは消さない
+	assert!(repo.path(".git/index.lock").exists());
+	assert_eq!(repo.read(".git/index"), before);
+
+	fs::remove_file(repo.path(".git/index.lock")).unwrap();
+	repo.git(&["add", "b"]);
+	assert!(!repo.path(".git/index.lock").exists());
+	assert_eq!(repo.git(&["ls-files"]), "a\nb\n");
+}
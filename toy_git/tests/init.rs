@@ -0,0 +1,75 @@
+mod common;
+
+use common::TempRepo;
+
+fn head(dir: &TempRepo, repo: &str) -> String {
+	String::from_utf8(dir.read(&format!("{}/.git/HEAD", repo))).unwrap()
+}
+
+#[test]
+fn default_branch() {
+	let dir = TempRepo::empty();
+	dir.git(&["init", "-q", "plain"]);
+	assert_eq!(head(&dir, "plain"), "ref: refs/heads/main\n");
+	dir.git(&["init", "-q", "--initial-branch=trunk", "long"]);
+	assert_eq!(head(&dir, "long"), "ref: refs/heads/trunk\n");
+	dir.git(&["init", "-q", "-b", "dev", "short"]);
+	assert_eq!(head(&dir, "short"), "ref: refs/heads/dev\n");
+}
+
+#[test]
+fn default_branch_from_config() {
+	// HOMEはこのディレクトリ
+	let dir = TempRepo::empty();
+	dir.write(".gitconfig", "[init]\n\tdefaultBranch = configured\n");
+	dir.git(&["init", "-q", "a"]);
+	assert_eq!(head(&dir, "a"), "ref: refs/heads/configured\n");
+	// optionのほうが優先
+	dir.git(&["init", "-q", "-b", "trunk", "b"]);
+	assert_eq!(head(&dir, "b"), "ref: refs/heads/trunk\n");
+
+	dir.write(".gitconfig", "[init]\n\tdefaultBranch = \"bad name\"\n");
+	let err = dir.fail(&["init", "-q", "c"]);
+	assert!(
+		err.contains("invalid branch name: init.defaultBranch = bad name"),
+		"{}",
+		err
+	);
+}
+
+#[test]
+fn invalid_initial_branch() {
+	let dir = TempRepo::empty();
+	for name in [
+		"has space",
+		"a..b",
+		"x.lock",
+		"a~b",
+		"a:b",
+		"ctl\x01",
+		"/a",
+		"a/",
+	] {
+		let err = dir.fail(&["init", "-q", "-b", name, "repo"]);
+		assert!(
+			err.contains(&format!("invalid initial branch name: '{}'", name)),
+			"{}",
+			err
+		);
+		assert!(!dir.path("repo/.git").exists(), "{}", name);
+	}
+}
+
+#[test]
+fn reinit_keeps_head() {
+	let repo = TempRepo::new();
+	let output = repo.run(&["init", "-q", "-b", "other"]);
+	assert!(output.status.success());
+	let err = String::from_utf8(output.stderr).unwrap();
+	assert!(
+		err.contains("warning: re-init: ignored --initial-branch=other"),
+		"{}",
+		err
+	);
+	assert_eq!(head(&repo, "."), "ref: refs/heads/main\n");
+}
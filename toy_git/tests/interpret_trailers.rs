@@ -0,0 +1,83 @@
+mod common;
+
+use common::TempRepo;
+use toy_git::cmd;
+
+const SIGNOFF: &str = "Signed-off-by: Test <test@example.com>";
+
+fn signoff(message: &str) -> String {
+	let trailers = [(
+		String::from("Signed-off-by"),
+		String::from("Test <test@example.com>"),
+	)];
+	cmd::interpret_trailers(message, &trailers)
+}
+
+// 期待する出力はgit interpret-trailersで確かめたもの
+#[test]
+fn existing_block() {
+	assert_eq!(
+		signoff("Subject\n\nBody.\n\nReviewed-by: B <b@x>\n"),
+		format!("Subject\n\nBody.\n\nReviewed-by: B <b@x>\n{}\n", SIGNOFF)
+	);
+	// 続きの行はtrailerの一部で、後ろのコメントはそのまま残す
+	assert_eq!(
+		signoff("Subject\n\nFixes: x\n  continued\n\n# comment\n"),
+		format!(
+			"Subject\n\nFixes: x\n  continued\n{}\n\n# comment\n",
+			SIGNOFF
+		)
+	);
+}
+
+#[test]
+fn new_block() {
+	assert_eq!(signoff("Subject\n"), format!("Subject\n\n{}\n", SIGNOFF));
+	assert_eq!(
+		signoff("Subject\n\nBody.\n"),
+		format!("Subject\n\nBody.\n\n{}\n", SIGNOFF)
+	);
+	// trailerでない行がある段落はtrailerの段落ではない
+	assert_eq!(
+		signoff("Subject\n\nnot a: trailer block\nplain line\n"),
+		format!(
+			"Subject\n\nnot a: trailer block\nplain line\n\n{}\n",
+			SIGNOFF
+		)
+	);
+	// titleはtrailerの形でもtrailerではない
+	assert_eq!(
+		signoff("Subject: not trailer\n"),
+		format!("Subject: not trailer\n\n{}\n", SIGNOFF)
+	);
+}
+
+#[test]
+fn same_as_last() {
+	let signed = format!("Subject\n\n{}\n", SIGNOFF);
+	assert_eq!(signoff(&signed), signed);
+	// 最後のtrailerでなければもう一度加える
+	let acked = format!("Subject\n\n{}\nAcked-by: C\n", SIGNOFF);
+	assert_eq!(signoff(&acked), format!("{}{}\n", acked, SIGNOFF));
+}
+
+#[test]
+fn command_and_signoff() {
+	let repo = TempRepo::new();
+	assert_eq!(
+		repo.git_with(
+			&["interpret-trailers", "--trailer", "Acked-by=C"],
+			b"Subject\n\nBody.\n"
+		),
+		"Subject\n\nBody.\n\nAcked-by: C\n"
+	);
+
+	// commit -sはcommitterの名前で加える
+	repo.write("a", "a\n");
+	repo.git(&["add", "a"]);
+	repo.git(&["commit", "-s", "-m", "Subject"]);
+	assert_eq!(
+		repo.git(&["log", "-n", "1", "--format=%B"]),
+		format!("Subject\n\n{}\n\n", SIGNOFF)
+	);
+}
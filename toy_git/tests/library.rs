@@ -0,0 +1,112 @@
+mod common;
+
+use std::fs;
+
+use common::TempRepo;
+use toy_git::index::Entry;
+use toy_git::object::tree_mode;
+use toy_git::{
+	Blob, CommitWalker, GitObject, Hash, Index, ObjectKind, ObjectStore, Refs, Repository, Tree,
+};
+
+// objectとindexを書いてcommitし、それをCLIで読む
+#[test]
+fn build_history() {
+	let dir = TempRepo::empty();
+	let lib = dir.path("lib");
+	let repo = Repository::init(&lib, false).unwrap();
+	dir.append(
+		"lib/.git/config",
+		"[user]\n\tname = Lib\n\temail = lib@example.com\n",
+	);
+	let objects = ObjectStore::new(&repo);
+	let refs = Refs::new(&repo);
+	// 名前はinit.defaultBranchによる
+	let branch = refs.head().unwrap();
+	assert!(branch.starts_with("refs/heads/"), "{}", branch);
+	assert_eq!(refs.resolve("HEAD").unwrap(), None);
+
+	let mut commits = Vec::new();
+	for (content, message) in [("hello\n", "first"), ("hello again\n", "second")] {
+		dir.write("lib/hello.txt", content);
+		let blob = Blob::new(content.as_bytes().to_vec());
+		let blob_hash = objects.write(ObjectKind::Blob, &blob.content).unwrap();
+		assert_eq!(blob_hash.0, blob.calc_hash(repo.hash_algo));
+		let mut index = Index::load(&repo).unwrap();
+		let meta = fs::metadata(lib.join("hello.txt")).unwrap();
+		index.add(Entry::new(
+			String::from("hello.txt"),
+			blob_hash.0.clone(),
+			&meta,
+		));
+		index.save(&repo).unwrap();
+
+		let hash = repo.commit(message).unwrap();
+		assert_eq!(refs.resolve(&branch).unwrap(), Some(hash.clone()));
+		// indexから作られたtreeは同じentryで作ったものと同じ
+		let mut tree = Tree::default();
+		tree.insert(tree_mode(0o100644), String::from("hello.txt"), &blob_hash.0);
+		let GitObject::Commit(commit) = objects.read(&hash).unwrap() else {
+			panic!("not a commit");
+		};
+		assert_eq!(commit.tree, Hash(tree.calc_hash(repo.hash_algo)).to_hex());
+		assert_eq!(
+			commit.parents,
+			commits.iter().map(Hash::to_hex).collect::<Vec<_>>()
+		);
+		commits.insert(0, hash);
+	}
+	assert_eq!(refs.resolve("HEAD").unwrap().as_ref(), commits.first());
+
+	let mut walker = CommitWalker::new(&repo);
+	walker.push(commits[0].clone()).unwrap();
+	let subjects: Vec<String> = walker.map(|x| x.unwrap().message).collect();
+	assert_eq!(subjects, ["second\n", "first\n"]);
+
+	let git = |args: &[&str]| {
+		let output = dir.run_in(&lib, args, None);
+		assert!(output.status.success(), "{:?}", args);
+		String::from_utf8(output.stdout).unwrap()
+	};
+	assert_eq!(git(&["log", "--format=%s %an"]), "second Lib\nfirst Lib\n");
+	assert_eq!(git(&["cat-file", "-p", "HEAD:hello.txt"]), "hello again\n");
+	assert_eq!(git(&["cat-file", "-p", "HEAD~:hello.txt"]), "hello\n");
+	assert_eq!(
+		git(&["ls-tree", "HEAD"]),
+		format!(
+			"100644 blob {}\thello.txt\n",
+			git(&["rev-parse", "HEAD:hello.txt"]).trim()
+		)
+	);
+	assert!(git(&["status"]).ends_with("nothing to commit, working tree clean\n"));
+	assert_eq!(git(&["fsck"]), "");
+}
+
+// CLIで作ったものをlibraryから読む
+#[test]
+fn read_repository() {
+	let dir = TempRepo::new();
+	let commit = dir.commit_file("a", "a\n", "first");
+	let repo = Repository::discover(&dir.dir).unwrap();
+
+	let hash = repo.refs().resolve("HEAD").unwrap().unwrap();
+	assert_eq!(hash.to_hex(), commit);
+	let GitObject::Commit(read) = repo.objects().read(&hash).unwrap() else {
+		panic!("not a commit");
+	};
+	assert_eq!(read.message, "first\n");
+	assert_eq!(read.author.name, "Test");
+
+	let index = Index::load(&repo).unwrap();
+	let paths: Vec<&str> = index.entries.iter().map(|x| x.path.as_str()).collect();
+	assert_eq!(paths, ["a"]);
+	let (kind, body) = repo
+		.objects()
+		.read_raw(&Hash(index.entries[0].hash.clone()))
+		.unwrap();
+	assert_eq!((kind, body), (ObjectKind::Blob, b"a\n".to_vec()));
+	assert_eq!(
+		repo.refs().list("refs/heads/").unwrap(),
+		[(String::from("refs/heads/main"), hash)]
+	);
+}
@@ -0,0 +1,323 @@
+mod common;
+
+use common::TempRepo;
+
+#[test]
+fn pretty_oneline() {
+	let repo = TempRepo::new();
+	let first = repo.commit_file("a", "a\n", "first");
+	repo.write("a", "b\n");
+	repo.git(&["add", "a"]);
+	repo.git(&["commit", "-m", "second", "-m", "body"]);
+	let second = repo.git(&["rev-parse", "HEAD"]);
+
+	assert_eq!(
+		repo.git(&["log", "--pretty=oneline"]),
+		format!("{} second\n{} first\n", second.trim(), first)
+	);
+	assert_eq!(
+		repo.git(&["log", "--oneline"]),
+		format!("{} second\n{} first\n", &second[..7], &first[..7])
+	);
+}
+
+#[test]
+fn pretty_raw() {
+	let repo = TempRepo::new();
+	let first = repo.commit_file("a", "a\n", "first");
+	let second = repo.commit_file("a", "b\n", "second");
+	let tree = repo.git(&["rev-parse", "HEAD^{tree}"]);
+
+	assert_eq!(
+		repo.git(&["log", "--pretty=raw", "-1"]),
+		format!(
+			"commit {}\ntree {}parent {}\n\
+			author Test <test@example.com> 1700000000 +0000\n\
+			committer Test <test@example.com> 1700000000 +0000\n\
+			\n    second\n",
+			second, tree, first
+		)
+	);
+}
+
+#[test]
+fn pretty_presets() {
+	let repo = TempRepo::new();
+	repo.git(&["config", "user.name", "Other"]);
+	repo.git(&["config", "user.email", "other@example.com"]);
+	let hash = repo.commit_file("a", "a\n", "first");
+
+	assert_eq!(
+		repo.git(&["log", "--pretty=short"]),
+		format!(
+			"commit {}\nAuthor: Other <other@example.com>\n\n    first\n",
+			hash
+		)
+	);
+	assert_eq!(
+		repo.git(&["log", "--pretty=medium"]),
+		format!(
+			"commit {}\nAuthor: Other <other@example.com>\n\
+			Date:   Tue Nov 14 22:13:20 2023 +0000\n\n    first\n",
+			hash
+		)
+	);
+	assert_eq!(repo.git(&["log"]), repo.git(&["log", "--pretty=medium"]));
+	assert_eq!(
+		repo.git(&["log", "--pretty=full"]),
+		format!(
+			"commit {}\nAuthor: Other <other@example.com>\n\
+			Commit: Other <other@example.com>\n\n    first\n",
+			hash
+		)
+	);
+	let err = repo.fail(&["log", "--pretty=nope"]);
+	assert!(err.contains("invalid --pretty format: nope"), "{}", err);
+}
+
+// Alice、Bob、Aliceの順に1日ずつ後の日付で作った3つのcommit
+fn history(repo: &TempRepo) {
+	for (i, (name, day)) in [("Alice", "01"), ("Bob", "02"), ("Alice", "03")]
+		.iter()
+		.enumerate()
+	{
+		repo.git(&["config", "user.name", name]);
+		repo.git(&[
+			"config",
+			"user.email",
+			&format!("{}@example.com", name.to_lowercase()),
+		]);
+		repo.write("a", format!("{}\n", i));
+		repo.git(&["add", "a"]);
+		let date = format!("2024-01-{}T12:00:00+0000", day);
+		repo.git_at(&date, &["commit", "-m", &format!("c{}", i)]);
+	}
+}
+
+fn subjects(repo: &TempRepo, args: &[&str]) -> String {
+	repo.git(&[&["log", "--format=%s"], args].concat())
+}
+
+#[test]
+fn author_filter() {
+	let repo = TempRepo::new();
+	history(&repo);
+	// Bobのcommitは出さないが、その先のparentも辿る
+	assert_eq!(subjects(&repo, &["--author=Alice"]), "c2\nc0\n");
+	assert_eq!(subjects(&repo, &["--author=bob@example"]), "c1\n");
+	assert_eq!(subjects(&repo, &["--author=Carol"]), "");
+}
+
+#[test]
+fn date_filter() {
+	let repo = TempRepo::new();
+	history(&repo);
+	assert_eq!(
+		subjects(&repo, &["--since=2024-01-02T00:00:00+0000"]),
+		"c2\nc1\n"
+	);
+	assert_eq!(
+		subjects(&repo, &["--until=2024-01-02T13:00:00+0000"]),
+		"c1\nc0\n"
+	);
+	assert_eq!(
+		subjects(
+			&repo,
+			&[
+				"--since=2024-01-02T00:00:00+0000",
+				"--until=2024-01-02T23:00:00+0000"
+			]
+		),
+		"c1\n"
+	);
+	assert_eq!(
+		subjects(&repo, &["--author=Alice", "--after=1704153600 +0000"]),
+		"c2\n"
+	);
+	let err = repo.fail(&["log", "--since=someday"]);
+	assert!(!err.is_empty());
+}
+
+#[test]
+fn merge_base_is_listed_once() {
+	let repo = TempRepo::new();
+	let base = repo.commit_file("a", "a\n", "base");
+	repo.git(&["checkout", "-b", "side"]);
+	let side = repo.commit_file("b", "b\n", "side");
+	repo.git(&["checkout", "main"]);
+	let main = repo.commit_file("c", "c\n", "main");
+	repo.git(&["merge", "side"]);
+	let merge = repo.git(&["rev-parse", "HEAD"]);
+
+	// 2つのparentから辿り着くbaseも1度だけ
+	let listed = repo.git(&["rev-list", "HEAD"]);
+	let mut lines: Vec<&str> = listed.lines().collect();
+	assert_eq!(lines.len(), 4);
+	assert_eq!(lines[0], merge.trim());
+	lines.sort();
+	let mut expected = vec![base.as_str(), side.as_str(), main.as_str(), merge.trim()];
+	expected.sort();
+	assert_eq!(lines, expected);
+}
+
+// nameというファイルを足し、nameというmessageでtimeにcommitする
+fn commit_at(repo: &TempRepo, name: &str, time: u64) {
+	repo.write(name, name);
+	repo.git(&["add", name]);
+	repo.git_at(&format!("{} +0000", time), &["commit", "-m", name]);
+}
+
+// baseからmainとsideに分かれ、日付はm2、s2、m1、s1、baseの順に新しい。tagはs1を指す
+fn divergent() -> TempRepo {
+	let repo = TempRepo::new();
+	let commit = |name: &str, time: u64| commit_at(&repo, name, time);
+	commit("base", 1700000000);
+	repo.git(&["branch", "side"]);
+	commit("m1", 1700003000);
+	repo.git(&["checkout", "side"]);
+	commit("s1", 1700002000);
+	repo.git(&["tag", "t1"]);
+	commit("s2", 1700004000);
+	repo.git(&["checkout", "main"]);
+	commit("m2", 1700005000);
+	repo
+}
+
+#[test]
+fn all_refs() {
+	let repo = divergent();
+	// 共有するbaseも1度だけ、新しい順に
+	assert_eq!(subjects(&repo, &[]), "m2\nm1\nbase\n");
+	assert_eq!(subjects(&repo, &["--all"]), "m2\ns2\nm1\ns1\nbase\n");
+	assert_eq!(repo.git(&["rev-list", "--all"]).lines().count(), 5);
+	assert_eq!(subjects(&repo, &["--branches"]), "m2\ns2\nm1\ns1\nbase\n");
+	assert_eq!(subjects(&repo, &["--branches=s*"]), "s2\ns1\nbase\n");
+	assert_eq!(subjects(&repo, &["--tags"]), "s1\nbase\n");
+	let listed = repo.git(&["rev-list", "--all", "^side"]);
+	assert_eq!(listed, repo.git(&["rev-list", "main", "^side"]));
+	assert_eq!(listed.lines().count(), 2);
+}
+
+// baseの後、sideにs1とs2、mainにm1(とm2)を作ってsideをmergeする。m2はbaseより古い日付
+fn merged(skewed: bool) -> TempRepo {
+	let repo = TempRepo::new();
+	let commit = |name: &str, time: u64| commit_at(&repo, name, time);
+	commit("base", 1700001000);
+	repo.git(&["checkout", "-b", "side"]);
+	commit("s1", 1700002000);
+	commit("s2", 1700004000);
+	repo.git(&["checkout", "main"]);
+	commit("m1", 1700003000);
+	if skewed {
+		commit("m2", 1700000500);
+	}
+	repo.git_at("1700006000 +0000", &["merge", "side"]);
+	repo
+}
+
+// parentを全てのchildより後に出したか
+fn children_first(repo: &TempRepo, args: &[&str]) -> bool {
+	let listed = repo.git(&[&["log", "--format=%H %P"], args].concat());
+	let mut shown = Vec::new();
+	for line in listed.lines() {
+		let mut hashes = line.split(' ');
+		shown.push(hashes.next().unwrap().to_string());
+		if hashes.any(|parent| shown.iter().any(|x| x == parent)) {
+			return false;
+		}
+	}
+	true
+}
+
+// 出力はgit logと同じ
+#[test]
+fn ordering() {
+	let repo = merged(false);
+	let merge = "Merge branch 'side'";
+	assert_eq!(
+		subjects(&repo, &["--date-order"]),
+		format!("{}\ns2\nm1\ns1\nbase\n", merge)
+	);
+	assert_eq!(
+		subjects(&repo, &["--topo-order"]),
+		format!("{}\ns2\ns1\nm1\nbase\n", merge)
+	);
+	let topo = repo.git(&["rev-list", "--topo-order", "HEAD"]);
+	assert_eq!(topo.lines().count(), 5);
+	assert_ne!(topo, repo.git(&["rev-list", "--date-order", "HEAD"]));
+
+	// 日付が前後していると、指定しなければparentのbaseがchildのm2より先になる
+	let repo = merged(true);
+	assert_eq!(
+		subjects(&repo, &[]),
+		format!("{}\ns2\ns1\nbase\nm2\nm1\n", merge)
+	);
+	assert!(!children_first(&repo, &[]));
+	assert!(children_first(&repo, &["--topo-order"]));
+	assert!(children_first(&repo, &["--date-order"]));
+	assert_eq!(
+		subjects(&repo, &["--topo-order"]),
+		format!("{}\ns2\ns1\nm2\nm1\nbase\n", merge)
+	);
+}
+
+#[test]
+fn patch() {
+	let repo = TempRepo::new();
+	let first = repo.commit_file("f", "one\n", "first");
+	let second = repo.commit_file("f", "one\ntwo\n", "second");
+	let header = |hash: &str, message: &str| {
+		format!(
+			"commit {}\nAuthor: Test <test@example.com>\nDate:   Tue Nov 14 22:13:20 2023 +0000\n\n    {}\n\n",
+			hash, message
+		)
+	};
+	// rootのcommitは全てのファイルを足すpatch
+	let expected = format!(
+		"{}diff --git a/f b/f\nindex 5626abf..814f4a4 100644\n--- a/f\n+++ b/f\n\
+		@@ -1 +1,2 @@\n one\n+two\n\n\
+		{}diff --git a/f b/f\nnew file mode 100644\nindex 0000000..5626abf\n--- /dev/null\n+++ b/f\n\
+		@@ -0,0 +1 @@\n+one\n",
+		header(&second, "second"),
+		header(&first, "first")
+	);
+	assert_eq!(repo.git(&["log", "-p"]), expected);
+	assert_eq!(repo.git(&["log", "--patch"]), expected);
+}
+
+const LINES: &str = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+
+// aを2回変えてからbにrenameし、bを変える。renameのときに内容も少し変えることがある
+fn renamed(content: &str) -> TempRepo {
+	let repo = TempRepo::new();
+	repo.commit_file("a", "1\n2\n3\n4\n5\n6\n7\n8\n9\n", "one");
+	repo.commit_file("a", LINES, "two");
+	repo.commit_file("other", "x\n", "unrelated");
+	repo.git(&["rm", "a"]);
+	repo.commit_file("b", content, "rename");
+	repo.commit_file("b", &format!("{}11\n", content), "after");
+	repo
+}
+
+#[test]
+fn follow_rename() {
+	let repo = renamed(LINES);
+	assert_eq!(subjects(&repo, &["--", "b"]), "after\nrename\n");
+	assert_eq!(
+		subjects(&repo, &["--follow", "--", "b"]),
+		"after\nrename\ntwo\none\n"
+	);
+	assert_eq!(
+		subjects(&repo, &["--follow", "b"]),
+		"after\nrename\ntwo\none\n"
+	);
+}
+
+#[test]
+fn follow_similar_rename() {
+	let repo = renamed("1\n2\n3\n4\n5\n6\n7\n8\n9\nten\n");
+	assert_eq!(
+		subjects(&repo, &["--follow", "--", "b"]),
+		"after\nrename\ntwo\none\n"
+	);
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use common::{serve, TempRepo};
+
+// git 2.39の "git upload-pack --advertise-refs --stateless-rpc" が返したもの
+const ADVERTISEMENT: &[u8] = b"001e# service=git-upload-pack\n0000\
+	010b9fb189164baa0d50d96a1739e322ea43f840616f HEAD\0multi_ack thin-pack side-band side-band-64k \
+	ofs-delta shallow deepen-since deepen-not deepen-relative no-progress include-tag \
+	multi_ack_detailed no-done symref=HEAD:refs/heads/main object-format=sha1 agent=git/2.39.5\n\
+	003d9fb189164baa0d50d96a1739e322ea43f840616f refs/heads/main\n\
+	003e9fb189164baa0d50d96a1739e322ea43f840616f refs/heads/topic\n\
+	003ae351ddc332659a0bbd7ec37e63bfd69ec7f3d213 refs/tags/v1\n\
+	003d9fb189164baa0d50d96a1739e322ea43f840616f refs/tags/v1^{}\n\
+	0000";
+
+const REFS: &str = "9fb189164baa0d50d96a1739e322ea43f840616f\tHEAD\n\
+	9fb189164baa0d50d96a1739e322ea43f840616f\trefs/heads/main\n\
+	9fb189164baa0d50d96a1739e322ea43f840616f\trefs/heads/topic\n\
+	e351ddc332659a0bbd7ec37e63bfd69ec7f3d213\trefs/tags/v1\n\
+	9fb189164baa0d50d96a1739e322ea43f840616f\trefs/tags/v1^{}\n";
+
+#[test]
+fn http() {
+	let repo = TempRepo::new();
+	let (url, server) = serve(vec![ADVERTISEMENT.to_vec()]);
+	assert_eq!(repo.git(&["ls-remote", &url]), REFS);
+
+	// info/refsだけを読み、objectは取ってこない
+	let requests = server.join().unwrap();
+	assert_eq!(requests.len(), 1);
+	assert_eq!(
+		requests[0].0,
+		"GET /repo.git/info/refs?service=git-upload-pack HTTP/1.0"
+	);
+}
+
+#[test]
+fn remote_name() {
+	let repo = TempRepo::new();
+	let (url, server) = serve(vec![ADVERTISEMENT.to_vec()]);
+	repo.git(&["config", "remote.origin.url", &url]);
+	assert_eq!(repo.git(&["ls-remote", "origin"]), REFS);
+	server.join().unwrap();
+}
+
+#[test]
+fn local() {
+	let remote = TempRepo::new();
+	let commit = remote.commit_file("a", "a\n", "first");
+	remote.git(&["branch", "topic"]);
+	remote.git(&["tag", "-a", "-m", "release", "v1"]);
+	let tag = remote.git(&["rev-parse", "v1"]);
+
+	let repo = TempRepo::empty();
+	let output = repo.git(&["ls-remote", remote.dir.to_str().unwrap()]);
+	assert_eq!(
+		output,
+		format!(
+			"{0}\tHEAD\n{0}\trefs/heads/main\n{0}\trefs/heads/topic\n\
+			{1}\trefs/tags/v1\n{0}\trefs/tags/v1^{{}}\n",
+			commit,
+			tag.trim()
+		)
+	);
+}
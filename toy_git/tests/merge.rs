@@ -0,0 +1,125 @@
+mod common;
+
+use common::TempRepo;
+
+// mainとotherがfを別々に変えたrepository。mainをcheckoutしている
+fn conflicting() -> TempRepo {
+	let repo = TempRepo::new();
+	repo.write("keep", "k\n");
+	repo.git(&["add", "keep"]);
+	repo.commit_file("f", "one\n", "base");
+	repo.git(&["checkout", "-b", "other"]);
+	repo.commit_file("f", "two\n", "other");
+	repo.git(&["checkout", "main"]);
+	repo.commit_file("f", "three\n", "main");
+	repo
+}
+
+#[test]
+fn abort_restores_worktree() {
+	let repo = conflicting();
+	let head = repo.git(&["rev-parse", "HEAD"]);
+	let index = repo.git(&["ls-files", "-s"]);
+	let other = repo.git(&["rev-parse", "other"]);
+
+	let output = repo.run(&["merge", "other"]);
+	assert_eq!(output.status.code(), Some(1));
+	assert_eq!(repo.read(".git/MERGE_HEAD"), other.as_bytes());
+	assert!(repo.path(".git/MERGE_MSG").exists());
+	assert_eq!(
+		repo.read("f"),
+		b"<<<<<<< HEAD\nthree\n=======\ntwo\n>>>>>>> other\n"
+	);
+	let stages = repo.git(&["ls-files", "-s"]);
+	assert_eq!(stages.lines().filter(|x| x.ends_with("\tf")).count(), 3);
+	// 途中のmergeがあるうちは次のmergeを始めない
+	let err = repo.fail(&["merge", "other"]);
+	assert!(err.contains("MERGE_HEAD exists"), "{}", err);
+
+	repo.git(&["merge", "--abort"]);
+	assert_eq!(repo.read("f"), b"three\n");
+	assert_eq!(repo.git(&["rev-parse", "HEAD"]), head);
+	assert_eq!(repo.git(&["ls-files", "-s"]), index);
+	assert!(!repo.path(".git/MERGE_HEAD").exists());
+	assert!(!repo.path(".git/MERGE_MSG").exists());
+	assert!(!repo.path(".git/MERGE_MODE").exists());
+}
+
+#[test]
+fn abort_keeps_unrelated_changes() {
+	let repo = conflicting();
+	repo.write("keep", "local\n");
+	assert_eq!(repo.run(&["merge", "other"]).status.code(), Some(1));
+	repo.git(&["merge", "--abort"]);
+	assert_eq!(repo.read("f"), b"three\n");
+	assert_eq!(repo.read("keep"), b"local\n");
+}
+
+#[test]
+fn abort_without_merge() {
+	let repo = conflicting();
+	let err = repo.fail(&["merge", "--abort"]);
+	assert!(err.contains("There is no merge to abort"), "{}", err);
+}
+
+// mainから1つ進んだtopic。(repository, base, topic)
+fn topic() -> (TempRepo, String, String) {
+	let repo = TempRepo::new();
+	let base = repo.commit_file("a", "a\n", "base");
+	repo.git(&["branch", "topic"]);
+	repo.git(&["checkout", "topic"]);
+	let tip = repo.commit_file("b", "b\n", "topic");
+	repo.git(&["checkout", "main"]);
+	(repo, base, tip)
+}
+
+#[test]
+fn fast_forward() {
+	let (repo, _, tip) = topic();
+	repo.git(&["merge", "topic"]);
+	assert_eq!(repo.git(&["rev-parse", "HEAD"]).trim(), tip);
+}
+
+#[test]
+fn no_ff() {
+	let (repo, base, tip) = topic();
+	repo.git(&["merge", "--no-ff", "topic"]);
+	let head = repo.git(&["rev-parse", "HEAD"]);
+	assert_ne!(head.trim(), tip);
+	let parents = repo.git(&["log", "-n", "1", "--format=%P"]);
+	assert_eq!(parents, format!("{} {}\n", base, tip));
+	// treeはtopicと同じ
+	assert_eq!(
+		repo.git(&["rev-parse", "HEAD^{tree}"]),
+		repo.git(&["rev-parse", "topic^{tree}"])
+	);
+	assert_eq!(repo.read("b"), b"b\n");
+	let message = repo.git(&["log", "-n", "1", "--format=%s"]);
+	assert_eq!(message, "Merge branch 'topic'\n");
+
+	// もう一度mergeしても何もしない
+	repo.git(&["merge", "--no-ff", "topic"]);
+	assert_eq!(repo.git(&["rev-parse", "HEAD"]), head);
+}
+
+// oursは衝突するmergeでもHEADのtreeのまま、otherを2つ目の親にする
+#[test]
+fn strategy_ours() {
+	let repo = conflicting();
+	let head = repo.git(&["rev-parse", "HEAD"]);
+	let tree = repo.git(&["rev-parse", "HEAD^{tree}"]);
+	let other = repo.git(&["rev-parse", "other"]);
+
+	repo.git(&["merge", "-s", "ours", "other"]);
+	let parents = repo.git(&["log", "-n", "1", "--format=%P"]);
+	assert_eq!(parents, format!("{} {}\n", head.trim(), other.trim()));
+	assert_eq!(repo.git(&["rev-parse", "HEAD^{tree}"]), tree);
+	assert_eq!(repo.read("f"), b"three\n");
+	assert!(repo.git(&["status"]).contains("nothing to commit"));
+	assert!(!repo.path(".git/MERGE_HEAD").exists());
+
+	// otherはもう含まれている
+	let merged = repo.git(&["rev-parse", "HEAD"]);
+	repo.git(&["merge", "-s", "ours", "other"]);
+	assert_eq!(repo.git(&["rev-parse", "HEAD"]), merged);
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use common::TempRepo;
+use toy_git::{merge, Hash};
+
+fn hash(repo: &TempRepo, rev: &str) -> Hash {
+	Hash::from_hex(repo.git(&["rev-parse", rev]).trim()).unwrap()
+}
+
+// baseからmainでa1、otherでb1を作り、mainにb1を、otherにa1をmergeする
+// mainとotherの最良の共通祖先はa1とb1の2つ
+fn criss_cross() -> (TempRepo, Hash, Hash) {
+	let repo = TempRepo::new();
+	repo.commit_file("base", "base\n", "base");
+	repo.git(&["branch", "other"]);
+	repo.commit_file("a", "a\n", "a1");
+	repo.git(&["branch", "a1"]);
+	repo.git(&["checkout", "other"]);
+	repo.commit_file("b", "b\n", "b1");
+	repo.git(&["merge", "a1"]);
+	repo.git(&["checkout", "main"]);
+	repo.git(&["merge", "other~1"]);
+	let (a1, b1) = (hash(&repo, "a1"), hash(&repo, "other~1"));
+	(repo, a1, b1)
+}
+
+#[test]
+fn all_best_bases() {
+	let (repo, a1, b1) = criss_cross();
+	let (main, other) = (hash(&repo, "main"), hash(&repo, "other"));
+	assert_ne!(main, other);
+
+	let mut bases = merge::merge_base_all(&repo.open(), &main, &other).unwrap();
+	bases.sort();
+	let mut expected = vec![a1, b1];
+	expected.sort();
+	assert_eq!(bases, expected);
+	// 逆に与えても同じもの
+	let mut reversed = merge::merge_base_all(&repo.open(), &other, &main).unwrap();
+	reversed.sort();
+	assert_eq!(reversed, expected);
+	// 1つだけのときは同じ結果の最初のもの
+	let first = merge::merge_base(&repo.open(), &main, &other).unwrap();
+	assert!(first.is_some_and(|x| expected.contains(&x)));
+
+	// 祖先どうしなら祖先そのもの
+	let base = hash(&repo, "main~2");
+	assert_eq!(
+		merge::merge_base_all(&repo.open(), &base, &main).unwrap(),
+		[base]
+	);
+}
+
+#[test]
+fn merge_with_two_bases() {
+	let (repo, _, _) = criss_cross();
+	repo.commit_file("p", "p\n", "p");
+	repo.git(&["checkout", "other"]);
+	repo.commit_file("q", "q\n", "q");
+	repo.git(&["checkout", "main"]);
+	repo.git(&["merge", "other"]);
+	for path in ["base", "a", "b", "p", "q"] {
+		assert!(repo.path(path).exists(), "{}", path);
+	}
+	assert!(repo.git(&["status"]).contains("nothing to commit"));
+}
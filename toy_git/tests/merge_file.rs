@@ -0,0 +1,82 @@
+mod common;
+
+use common::TempRepo;
+
+// 1から9の行のうち、changesの番号の行をその内容にしたもの
+fn lines(changes: &[(usize, &str)]) -> String {
+	(1..=9)
+		.map(|i| match changes.iter().find(|x| x.0 == i) {
+			Some((_, line)) => format!("{}\n", line),
+			None => format!("{}\n", i),
+		})
+		.collect()
+}
+
+// repositoryの外でも動く
+#[test]
+fn clean_merge() {
+	let dir = TempRepo::empty();
+	dir.write("base", lines(&[]));
+	dir.write("ours", lines(&[(2, "ours")]));
+	dir.write("theirs", lines(&[(8, "theirs")]));
+	let output = dir.run(&["merge-file", "ours", "base", "theirs"]);
+	assert_eq!(output.status.code(), Some(0));
+	let merged = lines(&[(2, "ours"), (8, "theirs")]);
+	assert_eq!(dir.read("ours"), merged.as_bytes());
+	assert!(!dir.path(".git").exists());
+}
+
+#[test]
+fn stdout() {
+	let dir = TempRepo::empty();
+	dir.write("base", lines(&[]));
+	dir.write("ours", lines(&[(2, "ours")]));
+	dir.write("theirs", lines(&[(8, "theirs")]));
+	let output = dir.run(&["merge-file", "-p", "ours", "base", "theirs"]);
+	assert_eq!(output.status.code(), Some(0));
+	assert_eq!(
+		output.stdout,
+		lines(&[(2, "ours"), (8, "theirs")]).as_bytes()
+	);
+	// -pならファイルは書き換えない
+	assert_eq!(dir.read("ours"), lines(&[(2, "ours")]).as_bytes());
+}
+
+// exit codeは衝突の数。出力はgit merge-fileと同じ
+#[test]
+fn conflicts() {
+	let dir = TempRepo::empty();
+	dir.write("base", lines(&[]));
+	let ours = lines(&[(2, "O2"), (8, "O8")]);
+	dir.write("ours", &ours);
+	dir.write("theirs", lines(&[(2, "T2"), (8, "T8")]));
+	let output = dir.run(&[
+		"merge-file",
+		"-L",
+		"mine",
+		"-L",
+		"orig",
+		"-L",
+		"yours",
+		"ours",
+		"base",
+		"theirs",
+	]);
+	assert_eq!(output.status.code(), Some(2));
+	assert_eq!(
+		String::from_utf8(dir.read("ours")).unwrap(),
+		"1\n<<<<<<< mine\nO2\n=======\nT2\n>>>>>>> yours\n3\n4\n5\n6\n7\n\
+		 <<<<<<< mine\nO8\n=======\nT8\n>>>>>>> yours\n9\n"
+	);
+
+	// -Lが無ければファイルの名前
+	dir.write("ours", &ours);
+	let output = dir.run(&["merge-file", "-p", "ours", "base", "theirs"]);
+	assert_eq!(output.status.code(), Some(2));
+	let merged = String::from_utf8(output.stdout).unwrap();
+	assert!(
+		merged.starts_with("1\n<<<<<<< ours\nO2\n=======\nT2\n>>>>>>> theirs\n"),
+		"{}",
+		merged
+	);
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use common::TempRepo;
+
+fn body(object: &str, kind: &str) -> String {
+	format!(
+		"object {}\ntype {}\ntag v1\ntagger Test <test@example.com> 1700000000 +0000\n\nrelease\n",
+		object, kind
+	)
+}
+
+#[test]
+fn valid_tag() {
+	let repo = TempRepo::new();
+	let commit = repo.commit_file("a", "a\n", "first");
+	let tag = body(&commit, "commit");
+	let hash = repo.git_with(&["mktag"], tag.as_bytes());
+	assert_eq!(
+		hash,
+		repo.git_with(&["hash-object", "-t", "tag", "--stdin"], tag.as_bytes())
+	);
+	assert_eq!(repo.git(&["cat-file", "-t", hash.trim()]), "tag\n");
+	assert_eq!(repo.git(&["cat-file", "-p", hash.trim()]), tag);
+}
+
+#[test]
+fn missing_tagger() {
+	let repo = TempRepo::new();
+	let commit = repo.commit_file("a", "a\n", "first");
+	let tag = format!("object {}\ntype commit\ntag v1\n\nrelease\n", commit);
+	let output = repo.run_in(&repo.dir, &["mktag"], Some(tag.as_bytes()));
+	assert!(!output.status.success());
+	let err = String::from_utf8(output.stderr).unwrap();
+	assert!(
+		err.contains("tag input does not pass fsck: missingTaggerEntry"),
+		"{}",
+		err
+	);
+}
+
+#[test]
+fn wrong_type() {
+	let repo = TempRepo::new();
+	let commit = repo.commit_file("a", "a\n", "first");
+	let tag = body(&commit, "tree");
+	let output = repo.run_in(&repo.dir, &["mktag"], Some(tag.as_bytes()));
+	assert!(!output.status.success());
+	let err = String::from_utf8(output.stderr).unwrap();
+	assert!(
+		err.contains(&format!(
+			"object '{}' tagged as 'tree', but is a 'commit' type",
+			commit
+		)),
+		"{}",
+		err
+	);
+
+	// 存在しないobject
+	let tag = body("0123456789012345678901234567890123456789", "commit");
+	let output = repo.run_in(&repo.dir, &["mktag"], Some(tag.as_bytes()));
+	let err = String::from_utf8(output.stderr).unwrap();
+	assert!(err.contains("could not read tagged object"), "{}", err);
+}
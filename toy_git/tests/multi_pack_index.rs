@@ -0,0 +1,95 @@
+mod common;
+
+use std::fs;
+
+use common::TempRepo;
+use toy_git::pack;
+
+// 1つ目と2つ目のcommitを別々のpackに入れ、looseなものを消したrepository
+fn two_packs() -> TempRepo {
+	let repo = TempRepo::new();
+	repo.commit_file("a", "1\n", "first");
+	repo.commit_file("b", "2\n", "second");
+	let base = repo.path(".git/objects/pack/pack");
+	for range in ["HEAD~1", "HEAD~1..HEAD"] {
+		let objects = repo.git(&["rev-list", "--objects", range]);
+		repo.git_with(
+			&["pack-objects", base.to_str().unwrap()],
+			objects.as_bytes(),
+		);
+	}
+	repo.git(&["prune-packed"]);
+	repo
+}
+
+#[test]
+fn lookup_matches_pack_indexes() {
+	let repo = two_packs();
+	repo.git(&["multi-pack-index", "write"]);
+	let opened = repo.open();
+	let midx = pack::multi_pack_index(&opened).unwrap().unwrap();
+	assert_eq!(midx.packs.len(), 2);
+
+	// 各packの.idxで見つかる場所と同じ
+	let mut count = 0;
+	for pack in pack::packs(&opened).unwrap() {
+		let name = pack.path.with_extension("idx");
+		let name = name.file_name().unwrap().to_str().unwrap();
+		for entry in pack.index.entries.iter() {
+			assert_eq!(midx.find(&entry.hash), Some((name, entry.offset)));
+			count += 1;
+		}
+	}
+	assert_eq!(midx.entries.len(), count);
+	assert!(midx.find(&[0; 20]).is_none());
+
+	assert_eq!(repo.git(&["cat-file", "-p", "HEAD~1:a"]), "1\n");
+	assert_eq!(repo.git(&["cat-file", "-p", "HEAD:b"]), "2\n");
+	repo.git(&["fsck"]);
+}
+
+#[test]
+fn stale_index_falls_back() {
+	let repo = two_packs();
+	repo.git(&["multi-pack-index", "write"]);
+	let opened = repo.open();
+	let midx = pack::multi_pack_index(&opened).unwrap().unwrap();
+
+	// 書いた後に足したpackは.idxで探す
+	repo.commit_file("c", "3\n", "third");
+	let objects = repo.git(&["rev-list", "--objects", "HEAD~1..HEAD"]);
+	let base = repo.path(".git/objects/pack/pack");
+	repo.git_with(
+		&["pack-objects", base.to_str().unwrap()],
+		objects.as_bytes(),
+	);
+	repo.git(&["prune-packed"]);
+	assert_eq!(repo.git(&["cat-file", "-p", "HEAD:c"]), "3\n");
+
+	// 含まれるpackが消されていれば使わない
+	let first = repo.git(&["rev-parse", "HEAD~2"]);
+	let first = hex::decode(first.trim()).unwrap();
+	let (name, _) = midx.find(&first).unwrap();
+	let idx = repo.path(".git/objects/pack").join(name);
+	fs::remove_file(idx.with_extension("pack")).unwrap();
+	fs::remove_file(idx).unwrap();
+	assert!(pack::multi_pack_index(&opened).unwrap().is_none());
+	assert_eq!(repo.git(&["cat-file", "-p", "HEAD~1:b"]), "2\n");
+	assert_eq!(repo.git(&["cat-file", "-p", "HEAD:c"]), "3\n");
+
+	// 壊れていても使わない
+	repo.write(".git/objects/pack/multi-pack-index", "MIDX broken");
+	assert!(pack::multi_pack_index(&opened).unwrap().is_none());
+	assert_eq!(repo.git(&["cat-file", "-p", "HEAD~1:b"]), "2\n");
+}
+
+// gcはmulti-pack-indexを残ったpackで書き直す
+#[test]
+fn rewritten_by_gc() {
+	let repo = two_packs();
+	repo.git(&["multi-pack-index", "write"]);
+	repo.git(&["gc"]);
+	let midx = pack::multi_pack_index(&repo.open()).unwrap().unwrap();
+	assert_eq!(midx.packs.len(), 1);
+	assert_eq!(repo.git(&["cat-file", "-p", "HEAD~1:a"]), "1\n");
+}
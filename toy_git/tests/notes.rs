@@ -0,0 +1,75 @@
+mod common;
+
+use common::TempRepo;
+
+fn notes_ref(repo: &TempRepo) -> Option<String> {
+	let output = repo.run(&["rev-parse", "--verify", "refs/notes/commits"]);
+	output
+		.status
+		.success()
+		.then(|| String::from_utf8(output.stdout).unwrap())
+}
+
+#[test]
+fn add_and_show() {
+	let repo = TempRepo::new();
+	let first = repo.commit_file("a", "a\n", "first");
+	let second = repo.commit_file("a", "b\n", "second");
+	assert_eq!(notes_ref(&repo), None);
+
+	repo.git(&["notes", "add", "-m", "reviewed", &first]);
+	assert_eq!(repo.git(&["notes", "show", &first]), "reviewed\n");
+	let after_add = notes_ref(&repo).unwrap();
+	// noteはcommitのhashをpathにしたblob
+	let tree = repo.git(&["ls-tree", "refs/notes/commits"]);
+	assert!(tree.ends_with(&format!("\t{}\n", first)), "{}", tree);
+
+	// 省略すればHEAD
+	repo.git(&["notes", "add", "-m", "latest"]);
+	assert_eq!(repo.git(&["notes", "show"]), "latest\n");
+	assert_eq!(repo.git(&["notes", "show", &second]), "latest\n");
+	let after_second = notes_ref(&repo).unwrap();
+	assert_ne!(after_second, after_add);
+	// notesのrefはcommitを重ねて進む
+	assert_eq!(repo.git(&["rev-parse", "refs/notes/commits~1"]), after_add);
+}
+
+#[test]
+fn overwrite() {
+	let repo = TempRepo::new();
+	let commit = repo.commit_file("a", "a\n", "first");
+	repo.git(&["notes", "add", "-m", "one"]);
+	let err = repo.fail(&["notes", "add", "-m", "two"]);
+	assert!(err.contains("Found existing notes for object"), "{}", err);
+	assert_eq!(repo.git(&["notes", "show"]), "one\n");
+
+	repo.git(&["notes", "add", "-f", "-m", "two"]);
+	assert_eq!(repo.git(&["notes", "show", &commit]), "two\n");
+}
+
+#[test]
+fn missing_note() {
+	let repo = TempRepo::new();
+	let commit = repo.commit_file("a", "a\n", "first");
+	let err = repo.fail(&["notes", "show"]);
+	assert!(
+		err.contains(&format!("no note found for object {}.", commit)),
+		"{}",
+		err
+	);
+}
+
+#[test]
+fn log_shows_notes() {
+	let repo = TempRepo::new();
+	repo.commit_file("a", "a\n", "first");
+	repo.commit_file("a", "b\n", "second");
+	repo.git(&["notes", "add", "-m", "line one\nline two", "HEAD~1"]);
+	let log = repo.git(&["log"]);
+	assert!(
+		log.ends_with("    first\n\nNotes:\n    line one\n    line two\n"),
+		"{}",
+		log
+	);
+	assert_eq!(log.matches("Notes:").count(), 1);
+}
@@ -0,0 +1,156 @@
+mod common;
+
+use std::fs;
+
+use common::TempRepo;
+
+// zlibでは縮まないbyte列
+fn noise(len: usize, seed: u64) -> Vec<u8> {
+	let mut state = seed;
+	(0..len)
+		.map(|_| {
+			state = state
+				.wrapping_mul(6364136223846793005)
+				.wrapping_add(1442695040888963407);
+			(state >> 56) as u8
+		})
+		.collect()
+}
+
+fn pack_size(repo: &TempRepo) -> u64 {
+	let packs: Vec<_> = fs::read_dir(repo.path(".git/objects/pack"))
+		.unwrap()
+		.map(|x| x.unwrap().path())
+		.filter(|x| x.extension().is_some_and(|x| x == "pack"))
+		.collect();
+	assert_eq!(packs.len(), 1);
+	fs::metadata(&packs[0]).unwrap().len()
+}
+
+#[test]
+fn similar_blobs_are_deltified() {
+	let repo = TempRepo::new();
+	let first = noise(100 * 1024, 1);
+	let mut second = first.clone();
+	second[50 * 1024..50 * 1024 + 16].copy_from_slice(b"a small change!!");
+	second.extend_from_slice(b"and a tail\n");
+	repo.write("a", &first);
+	repo.write("b", &second);
+	repo.git(&["add", "."]);
+	repo.git(&["commit", "-m", "first"]);
+	let a = repo.git(&["rev-parse", "HEAD:a"]);
+	let b = repo.git(&["rev-parse", "HEAD:b"]);
+
+	repo.git(&["gc"]);
+	// 丸ごと書けば200KBを超える
+	let size = pack_size(&repo);
+	assert!(size < 110 * 1024, "pack is {} bytes", size);
+
+	assert!(!repo.path(&format!(".git/objects/{}", &a[..2])).exists());
+	let output = repo.run(&["cat-file", "-p", a.trim()]);
+	assert!(output.status.success());
+	assert!(output.stdout == first);
+	let output = repo.run(&["cat-file", "-p", b.trim()]);
+	assert!(output.status.success());
+	assert!(output.stdout == second);
+}
+
+// deltaになったobjectも、deltaを戻した後の大きさ
+#[test]
+fn size_of_delta() {
+	let repo = TempRepo::new();
+	let first = noise(100 * 1024, 1);
+	let mut second = first.clone();
+	second.truncate(90 * 1024);
+	second.extend_from_slice(b"a different tail\n");
+	repo.write("a", &first);
+	repo.write("b", &second);
+	repo.git(&["add", "."]);
+	repo.git(&["commit", "-m", "first"]);
+	let size = |spec: &str| repo.git(&["cat-file", "-s", spec]);
+	let loose = (size("HEAD:a"), size("HEAD:b"));
+	assert_eq!(loose.1, format!("{}\n", second.len()));
+
+	repo.git(&["gc"]);
+	// どちらかはdeltaになっている
+	assert!(pack_size(&repo) < 110 * 1024);
+	assert_eq!((size("HEAD:a"), size("HEAD:b")), loose);
+	let check = repo.git_with(&["cat-file", "--batch-check"], b"HEAD:a\nHEAD:b\n");
+	let sizes: Vec<&str> = check
+		.lines()
+		.map(|x| x.rsplit(' ').next().unwrap())
+		.collect();
+	assert_eq!(sizes, [loose.0.trim(), loose.1.trim()]);
+}
+
+#[test]
+fn unrelated_blobs_are_not_deltified() {
+	let repo = TempRepo::new();
+	repo.write("a", noise(50 * 1024, 1));
+	repo.write("b", noise(50 * 1024, 2));
+	repo.git(&["add", "."]);
+	repo.git(&["commit", "-m", "first"]);
+	repo.git(&["gc"]);
+	assert!(pack_size(&repo) > 100 * 1024);
+	assert_eq!(
+		repo.run(&["cat-file", "-p", "HEAD:b"]).stdout,
+		noise(50 * 1024, 2)
+	);
+}
+
+#[test]
+fn pack_objects_from_rev_list() {
+	let repo = TempRepo::new();
+	repo.write("a", "a\n");
+	repo.write("dir/b", "b\n");
+	repo.git(&["add", "a", "dir/b"]);
+	repo.git(&["commit", "-m", "first"]);
+	repo.commit_file("dir/b", "b2\n", "second");
+	repo.git(&["tag", "-a", "v1", "-m", "tag"]);
+
+	let mut objects = repo.git(&["rev-list", "--objects", "HEAD"]);
+	objects.push_str(&repo.git(&["rev-parse", "v1"]));
+	let base = repo.path(".git/objects/pack/test");
+	let name = repo.git_with(
+		&["pack-objects", base.to_str().unwrap()],
+		objects.as_bytes(),
+	);
+	let name = name.trim();
+	assert_eq!(name.len(), 40);
+	for ext in ["pack", "idx"] {
+		assert!(repo
+			.path(&format!(".git/objects/pack/test-{}.{}", name, ext))
+			.is_file());
+	}
+	// 名前はpackの最後のchecksum
+	let pack = repo.read(&format!(".git/objects/pack/test-{}.pack", name));
+	assert_eq!(hex::encode(&pack[pack.len() - 20..]), name);
+
+	// looseなobjectを消しても、全てpackから読める
+	repo.git(&["prune-packed"]);
+	for line in objects.lines() {
+		let hash = line.split(' ').next().unwrap();
+		assert!(!repo
+			.path(&format!(".git/objects/{}/{}", &hash[..2], &hash[2..]))
+			.exists());
+		repo.git(&["cat-file", "-e", hash]);
+	}
+	assert_eq!(repo.git(&["cat-file", "-p", "HEAD:dir/b"]), "b2\n");
+	assert_eq!(repo.git(&["cat-file", "-p", "HEAD~1:dir/b"]), "b\n");
+	assert_eq!(repo.git(&["cat-file", "-t", "v1"]), "tag\n");
+	assert_eq!(repo.git(&["log", "--format=%s"]), "second\nfirst\n");
+}
+
+#[test]
+fn pack_objects_bad_input() {
+	let repo = TempRepo::new();
+	let base = repo.path(".git/objects/pack/test");
+	let output = repo.run_in(
+		&repo.dir,
+		&["pack-objects", base.to_str().unwrap()],
+		Some(b"not-a-hash\n"),
+	);
+	assert!(!output.status.success());
+	let err = String::from_utf8(output.stderr).unwrap();
+	assert!(err.contains("expected object ID, got garbage"), "{}", err);
+}
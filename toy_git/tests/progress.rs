@@ -0,0 +1,80 @@
+mod common;
+
+use common::TempRepo;
+use toy_git::cmd::{self, FsckOptions, GcOptions};
+use toy_git::progress::Progress;
+
+// 呼ばれたphaseごとの (名前, total, incの合計, finishしたか)
+#[derive(Default)]
+struct Recorder {
+	phases: Vec<(String, usize, usize, bool)>,
+}
+
+impl Progress for Recorder {
+	fn start(&mut self, phase: &str, total: usize) {
+		self.phases.push((phase.to_string(), total, 0, false));
+	}
+
+	fn inc(&mut self, n: usize) {
+		self.phases.last_mut().unwrap().2 += n;
+	}
+
+	fn finish(&mut self) {
+		self.phases.last_mut().unwrap().3 = true;
+	}
+}
+
+// 3つのcommit、3つのtree、3つのblob
+fn history() -> TempRepo {
+	let repo = TempRepo::new();
+	repo.commit_file("a", "a\n", "first");
+	repo.commit_file("a", "b\n", "second");
+	repo.commit_file("a", "c\n", "third");
+	repo
+}
+
+#[test]
+fn repack() {
+	let repo = history();
+	let mut progress = Recorder::default();
+	let opts = GcOptions {
+		auto: false,
+		force: false,
+		prune: None,
+	};
+	cmd::gc(&repo.open(), &opts, &mut progress).unwrap();
+
+	// Resolving deltasのtotalはdeltaにしたobjectの数
+	let names: Vec<&str> = progress.phases.iter().map(|x| x.0.as_str()).collect();
+	assert_eq!(
+		names,
+		[
+			"Compressing objects",
+			"Writing objects",
+			"Indexing objects",
+			"Resolving deltas"
+		]
+	);
+	for (name, total, count, finished) in progress.phases.iter() {
+		if name != "Resolving deltas" {
+			assert_eq!(*total, 9, "{}", name);
+		}
+		assert_eq!(count, total, "{}", name);
+		assert!(finished, "{}", name);
+	}
+}
+
+#[test]
+fn fsck() {
+	let repo = history();
+	let mut progress = Recorder::default();
+	let opts = FsckOptions {
+		unreachable: false,
+		dangling: false,
+	};
+	assert_eq!(cmd::fsck(&repo.open(), &opts, &mut progress).unwrap(), 0);
+	assert_eq!(
+		progress.phases,
+		[("Checking objects".to_string(), 9, 9, true)]
+	);
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use std::fs;
+
+use common::TempRepo;
+
+fn loose(repo: &TempRepo, hash: &str) -> bool {
+	repo.path(&format!(".git/objects/{}/{}", &hash[..2], &hash[2..]))
+		.exists()
+}
+
+// hashesのobjectだけのpackを作る。looseなobjectはそのまま残る
+fn pack(repo: &TempRepo, hashes: &[&str]) {
+	let input: String = hashes.iter().map(|x| format!("{}\n", x)).collect();
+	let base = repo.path(".git/objects/pack/pack");
+	repo.git_with(&["pack-objects", base.to_str().unwrap()], input.as_bytes());
+}
+
+fn blob(repo: &TempRepo, content: &str) -> String {
+	let hash = repo.git_with(&["hash-object", "-w", "--stdin"], content.as_bytes());
+	hash.trim().to_string()
+}
+
+#[test]
+fn two_packs() {
+	let repo = TempRepo::new();
+	let a = blob(&repo, "a\n");
+	let b = blob(&repo, "b\n");
+	let c = blob(&repo, "c\n");
+	pack(&repo, &[&a]);
+	pack(&repo, &[&b]);
+	let packs = fs::read_dir(repo.path(".git/objects/pack"))
+		.unwrap()
+		.filter(|x| x.as_ref().unwrap().path().extension() == Some("idx".as_ref()))
+		.count();
+	assert_eq!(packs, 2);
+
+	// -nは消すものを出すだけ
+	let dry = repo.git(&["prune-packed", "-n"]);
+	let mut listed: Vec<&str> = dry.lines().collect();
+	listed.sort();
+	let mut expected = [&a, &b].map(|x| {
+		let path = repo.path(&format!(".git/objects/{}/{}", &x[..2], &x[2..]));
+		format!("rm -f {}", path.display())
+	});
+	expected.sort();
+	assert_eq!(listed, expected);
+	assert!(loose(&repo, &a) && loose(&repo, &b));
+
+	repo.git(&["prune-packed"]);
+	assert!(!loose(&repo, &a));
+	assert!(!loose(&repo, &b));
+	// packに無いものは残る
+	assert!(loose(&repo, &c));
+	assert!(!repo.path(&format!(".git/objects/{}", &a[..2])).exists());
+
+	// それぞれのpackから読める
+	assert_eq!(repo.git(&["cat-file", "-p", &a]), "a\n");
+	assert_eq!(repo.git(&["cat-file", "-p", &b]), "b\n");
+	assert_eq!(repo.git(&["cat-file", "-p", &c]), "c\n");
+}
@@ -0,0 +1,199 @@
+mod common;
+
+use common::{serve, TempRepo};
+use toy_git::pack;
+use toy_git::pkt_line::Writer;
+use toy_git::progress::Silent;
+
+// git 2.39の "git upload-pack --advertise-refs --stateless-rpc" が返したもの
+const REFS: &[u8] = b"001e# service=git-upload-pack\n0000\
+	010b43e24ecf2a190db97decc99271e48edb9cf3cd72 HEAD\0multi_ack thin-pack side-band side-band-64k \
+	ofs-delta shallow deepen-since deepen-not deepen-relative no-progress include-tag \
+	multi_ack_detailed no-done symref=HEAD:refs/heads/main object-format=sha1 agent=git/2.39.5\n\
+	003d43e24ecf2a190db97decc99271e48edb9cf3cd72 refs/heads/main\n\
+	003e43e24ecf2a190db97decc99271e48edb9cf3cd72 refs/heads/topic\n\
+	0000";
+
+// 同じrepositoryで "git receive-pack --advertise-refs --stateless-rpc" が返したもの
+const ADVERTISEMENT: &[u8] = b"001f# service=git-receive-pack\n0000\
+	00b143e24ecf2a190db97decc99271e48edb9cf3cd72 refs/heads/main\0report-status report-status-v2 \
+	delete-refs side-band-64k quiet atomic ofs-delta object-format=sha1 agent=git/2.39.5\n\
+	003e43e24ecf2a190db97decc99271e48edb9cf3cd72 refs/heads/topic\n\
+	0000";
+
+// 上のrepositoryのtopicを消すrequestにgit receive-pack --stateless-rpcが返したもの
+const DELETED: &[u8] = b"002f\x01000eunpack ok\n0018ok refs/heads/topic\n00000000";
+
+const TOPIC: &str = "43e24ecf2a190db97decc99271e48edb9cf3cd72";
+
+fn refs(repo: &TempRepo) -> String {
+	repo.git(&["for-each-ref", "--format=%(refname)"])
+}
+
+// main、topic、tag v1のあるremoteと、それをoriginとしてfetchしたrepository
+fn fetched() -> (TempRepo, TempRepo) {
+	let remote = TempRepo::new();
+	remote.commit_file("a", "a\n", "first");
+	remote.git(&["branch", "topic"]);
+	remote.git(&["tag", "v1"]);
+
+	let repo = TempRepo::new();
+	repo.git(&["config", "remote.origin.url", remote.dir.to_str().unwrap()]);
+	repo.git(&["fetch"]);
+	(remote, repo)
+}
+
+#[test]
+fn delete_local() {
+	let (remote, repo) = fetched();
+	let output = repo.run(&["push", "--delete", "origin", "topic", "v1"]);
+	assert!(output.status.success());
+	assert_eq!(
+		String::from_utf8(output.stderr).unwrap(),
+		format!(
+			"To {}\n - [deleted]         topic\n - [deleted]         v1\n",
+			remote.dir.display()
+		)
+	);
+	assert_eq!(refs(&remote), "refs/heads/main\n");
+	// remote-tracking refも消える
+	assert!(!refs(&repo).contains("refs/remotes/origin/topic"));
+	assert!(refs(&repo).contains("refs/remotes/origin/main"));
+
+	// 無いものを消そうとすれば何も送らない
+	let output = repo.run(&["push", "-d", "origin", "main", "topic"]);
+	assert_eq!(output.status.code(), Some(1));
+	let err = String::from_utf8(output.stderr).unwrap();
+	assert!(
+		err.contains("error: unable to delete 'topic': remote ref does not exist"),
+		"{}",
+		err
+	);
+	assert_eq!(refs(&remote), "refs/heads/main\n");
+}
+
+// receive-packには古い値と0を送る
+#[test]
+fn delete_http() {
+	let repo = TempRepo::new();
+	repo.commit_file("a", "a\n", "first");
+	let (url, server) = serve(vec![
+		REFS.to_vec(),
+		ADVERTISEMENT.to_vec(),
+		DELETED.to_vec(),
+	]);
+	repo.git(&["config", "remote.origin.url", &url]);
+	repo.write(".git/refs/remotes/origin/topic", format!("{}\n", TOPIC));
+	repo.git(&["push", "--delete", "origin", "topic"]);
+	assert!(!repo.path(".git/refs/remotes/origin/topic").exists());
+
+	let requests = server.join().unwrap();
+	assert_eq!(
+		requests[0].0,
+		"GET /repo.git/info/refs?service=git-upload-pack HTTP/1.0"
+	);
+	assert_eq!(
+		requests[1].0,
+		"GET /repo.git/info/refs?service=git-receive-pack HTTP/1.0"
+	);
+	assert_eq!(requests[2].0, "POST /repo.git/git-receive-pack HTTP/1.0");
+	let command = format!(
+		"{} {} refs/heads/topic\0report-status side-band-64k agent=git/toy_git\n",
+		TOPIC,
+		"0".repeat(40)
+	);
+	// packは送らない
+	assert_eq!(
+		requests[2].1,
+		format!("{:04x}{}0000", command.len() + 4, command).as_bytes()
+	);
+}
+
+// HTTPのremoteのmainがheadにあるとして、無いobjectをpushしたときに送ったpack
+// capabilitiesはreceive-packが広告するもの
+fn pushed_pack(repo: &TempRepo, head: &str, capabilities: &str) -> Vec<u8> {
+	let mut refs = Writer::new();
+	refs.data(b"# service=git-upload-pack\n").unwrap();
+	refs.flush();
+	refs.data(format!("{} refs/heads/main\0side-band-64k\n", head).as_bytes())
+		.unwrap();
+	refs.flush();
+	let mut advertisement = Writer::new();
+	advertisement.data(b"# service=git-receive-pack\n").unwrap();
+	advertisement.flush();
+	let line = format!("{} refs/heads/main\0{}\n", head, capabilities);
+	advertisement.data(line.as_bytes()).unwrap();
+	advertisement.flush();
+	let mut report = Writer::new();
+	report.data(b"unpack ok\n").unwrap();
+	report.data(b"ok refs/heads/main\n").unwrap();
+	report.flush();
+	let mut response = Writer::new();
+	response.band(1, &report.bytes).unwrap();
+	response.flush();
+
+	let (url, server) = serve(vec![refs.bytes, advertisement.bytes, response.bytes]);
+	repo.git(&["config", "remote.origin.url", &url]);
+	repo.git(&["push", "origin", "main"]);
+	let requests = server.join().unwrap();
+	assert_eq!(requests[2].0, "POST /repo.git/git-receive-pack HTTP/1.0");
+	let body = &requests[2].1;
+	let start = body.windows(4).position(|x| x == b"PACK").unwrap();
+	body[start..].to_vec()
+}
+
+// 大きなファイルのあるremoteをfetchし、その1行を変えてcommitしたrepository
+// (remote, repository, remoteのmain, remoteにある元のblob)
+fn modified() -> (TempRepo, TempRepo, String, Vec<u8>) {
+	let (remote, repo) = fetched();
+	let lines: String = (0..1000).map(|i| format!("line {}\n", i)).collect();
+	remote.commit_file("big", &lines, "big");
+	repo.git(&["fetch"]);
+	repo.git(&["reset", "--hard", "origin/main"]);
+	let head = repo.git(&["rev-parse", "HEAD"]).trim().to_string();
+	let blob = repo.git(&["rev-parse", "HEAD:big"]);
+	repo.commit_file("big", &lines.replace("line 500\n", "changed\n"), "change");
+	(
+		remote,
+		repo,
+		head,
+		toy_git::Hash::from_hex(blob.trim()).unwrap().0,
+	)
+}
+
+// remoteにあるblobに対するREF_DELTAにして、そのblobは送らない(thin pack)
+#[test]
+fn thin_pack_http() {
+	let (remote, repo, head, base) = modified();
+	let data = pushed_pack(&repo, &head, "report-status side-band-64k ofs-delta");
+	// commit、tree、blobの3つ
+	assert_eq!(&data[8..12], &3u32.to_be_bytes());
+	assert!(data.windows(base.len()).any(|x| x == base));
+	assert!(data.len() < 1000, "{}", data.len());
+
+	// baseの無いrepositoryには加えられない
+	let empty = TempRepo::new();
+	assert!(pack::index_pack(&empty.open(), &data, &mut Silent).is_err());
+	// remoteではbaseを補って読める
+	pack::index_pack(&remote.open(), &data, &mut Silent).unwrap();
+	let change = repo.git(&["rev-parse", "HEAD"]);
+	assert_eq!(
+		remote.git(&["cat-file", "-p", &format!("{}:big", change.trim())]),
+		repo.read("big")
+			.iter()
+			.map(|&b| b as char)
+			.collect::<String>()
+	);
+	remote.git(&["fsck"]);
+}
+
+// "no-thin" のremoteには全てのobjectを含むpackを送る
+#[test]
+fn no_thin_http() {
+	let (_, repo, head, base) = modified();
+	let data = pushed_pack(&repo, &head, "report-status side-band-64k no-thin");
+	assert_eq!(&data[8..12], &3u32.to_be_bytes());
+	assert!(!data.windows(base.len()).any(|x| x == base));
+	let empty = TempRepo::new();
+	pack::index_pack(&empty.open(), &data, &mut Silent).unwrap();
+}
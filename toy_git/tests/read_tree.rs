@@ -0,0 +1,137 @@
+mod common;
+
+use common::TempRepo;
+
+// ls-files -sの各行を (path, hash, stage) にしたもの
+fn staged(repo: &TempRepo) -> Vec<(String, String, String)> {
+	repo.git(&["ls-files", "-s"])
+		.lines()
+		.map(|line| {
+			let (info, path) = line.split_once('\t').unwrap();
+			let info: Vec<&str> = info.split(' ').collect();
+			(path.to_string(), info[1].to_string(), info[2].to_string())
+		})
+		.collect()
+}
+
+fn blob(repo: &TempRepo, rev: &str) -> String {
+	repo.git(&["rev-parse", rev]).trim().to_string()
+}
+
+#[test]
+fn single_tree() {
+	let repo = TempRepo::new();
+	repo.write("a", "a\n");
+	repo.write("b", "b\n");
+	repo.git(&["add", "a", "b"]);
+	repo.git(&["commit", "-m", "first"]);
+	let first = blob(&repo, "HEAD");
+	repo.git(&["rm", "b"]);
+	repo.commit_file("c", "c\n", "second");
+
+	// working directoryは変えずにindexだけfirstのtreeにする
+	repo.git(&["read-tree", &first]);
+	let paths: Vec<String> = staged(&repo).into_iter().map(|x| x.0).collect();
+	assert_eq!(paths, ["a", "b"]);
+	assert!(repo.path("c").exists());
+	assert!(!repo.path("b").exists());
+}
+
+#[test]
+fn twoway_fast_forward() {
+	let repo = TempRepo::new();
+	repo.write("a", "a\n");
+	repo.write("b", "b\n");
+	repo.git(&["add", "a", "b"]);
+	let head = repo.commit_file("b", "b\n", "first");
+	repo.write("a", "a2\n");
+	repo.git(&["add", "a"]);
+	repo.git(&["rm", "b"]);
+	let target = repo.commit_file("c", "c\n", "second");
+	repo.git(&["reset", "--hard", &head]);
+
+	repo.git(&["read-tree", "-m", &head, &target]);
+	assert_eq!(
+		staged(&repo),
+		[
+			(
+				"a".to_string(),
+				blob(&repo, &format!("{}:a", target)),
+				"0".to_string()
+			),
+			(
+				"c".to_string(),
+				blob(&repo, &format!("{}:c", target)),
+				"0".to_string()
+			),
+		]
+	);
+}
+
+#[test]
+fn twoway_keeps_index_changes() {
+	let repo = TempRepo::new();
+	repo.write("a", "a\n");
+	repo.write("b", "b\n");
+	repo.git(&["add", "a", "b"]);
+	repo.git(&["commit", "-m", "first"]);
+	let head = blob(&repo, "HEAD");
+	let target = repo.commit_file("a", "a2\n", "second");
+	repo.git(&["reset", "--hard", &head]);
+
+	// targetで変わらないbの変更はindexに残る
+	repo.write("b", "b2\n");
+	repo.git(&["add", "b"]);
+	let b = staged(&repo)[1].1.clone();
+	repo.git(&["read-tree", "-m", &head, &target]);
+	let entries = staged(&repo);
+	assert_eq!(entries[0].1, blob(&repo, &format!("{}:a", target)));
+	assert_eq!(entries[1].1, b);
+
+	// targetで変わるaの変更はError
+	repo.git(&["reset", "--hard", &head]);
+	repo.write("a", "mine\n");
+	repo.git(&["add", "a"]);
+	let err = repo.fail(&["read-tree", "-m", &head, &target]);
+	assert!(
+		err.contains("Entry 'a' would be overwritten by merge"),
+		"{}",
+		err
+	);
+}
+
+#[test]
+fn threeway_conflict() {
+	let repo = TempRepo::new();
+	repo.write("a", "a\n");
+	repo.write("b", "b\n");
+	repo.git(&["add", "a", "b"]);
+	repo.git(&["commit", "-m", "base"]);
+	let base = blob(&repo, "HEAD");
+	repo.git(&["branch", "other"]);
+	repo.write("a", "ours\n");
+	repo.git(&["add", "a"]);
+	let ours = repo.commit_file("b", "b2\n", "ours");
+	repo.git(&["checkout", "other"]);
+	let theirs = repo.commit_file("a", "theirs\n", "theirs");
+	repo.git(&["checkout", "main"]);
+
+	repo.git(&["read-tree", "-m", &base, &ours, &theirs]);
+	let entries = staged(&repo);
+	let a = |rev: &str| blob(&repo, &format!("{}:a", rev));
+	assert_eq!(
+		entries,
+		[
+			("a".to_string(), a(&base), "1".to_string()),
+			("a".to_string(), a(&ours), "2".to_string()),
+			("a".to_string(), a(&theirs), "3".to_string()),
+			(
+				"b".to_string(),
+				blob(&repo, &format!("{}:b", ours)),
+				"0".to_string()
+			),
+		]
+	);
+	let err = repo.fail(&["read-tree", "-m", &ours]);
+	assert!(err.contains("resolve your current index first"), "{}", err);
+}
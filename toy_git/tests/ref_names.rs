@@ -0,0 +1,80 @@
+mod common;
+
+use common::TempRepo;
+
+const BAD: [&str; 6] = ["a..b", "a b", "x.lock", "a~b", ".hidden", "a/"];
+
+fn refs(repo: &TempRepo) -> String {
+	repo.git(&["for-each-ref", "--format=%(refname)"])
+}
+
+#[test]
+fn rejected_by_commands() {
+	let repo = TempRepo::new();
+	let head = repo.commit_file("a", "a\n", "first");
+	let before = refs(&repo);
+	for name in BAD {
+		let err = repo.fail(&["branch", name]);
+		assert!(
+			err.contains(&format!("'{}' is not a valid branch name", name)),
+			"{}",
+			err
+		);
+		let err = repo.fail(&["checkout", "-b", name]);
+		assert!(
+			err.contains(&format!("'{}' is not a valid branch name", name)),
+			"{}",
+			err
+		);
+		let err = repo.fail(&["tag", name]);
+		assert!(
+			err.contains(&format!("'{}' is not a valid tag name.", name)),
+			"{}",
+			err
+		);
+		// update-ref --stdinでは空白は区切りになる
+		if name.contains(' ') {
+			continue;
+		}
+		let input = format!("create refs/heads/{} {}\n", name, head);
+		let output = repo.run_in(
+			&repo.dir,
+			&["update-ref", "--stdin"],
+			Some(input.as_bytes()),
+		);
+		assert!(!output.status.success(), "{}", name);
+		let err = String::from_utf8(output.stderr).unwrap();
+		assert!(
+			err.contains(&format!("invalid ref format: refs/heads/{}", name)),
+			"{}",
+			err
+		);
+	}
+	// branchの名前にHEADは使えない
+	let err = repo.fail(&["branch", "HEAD"]);
+	assert!(err.contains("'HEAD' is not a valid branch name"), "{}", err);
+	// "-" で始まる名前も使えない
+	let err = repo.fail(&["checkout", "-b", "-dash"]);
+	assert!(
+		err.contains("'-dash' is not a valid branch name"),
+		"{}",
+		err
+	);
+	assert_eq!(refs(&repo), before);
+	assert_eq!(repo.git(&["symbolic-ref", "HEAD"]), "refs/heads/main\n");
+}
+
+#[test]
+fn accepted_by_commands() {
+	let repo = TempRepo::new();
+	let head = repo.commit_file("a", "a\n", "first");
+	repo.git(&["branch", "feature/foo-1"]);
+	repo.git(&["checkout", "-b", "topic/a@b"]);
+	repo.git(&["tag", "v1.0-rc.1"]);
+	let input = format!("create refs/heads/ok/x {}\n", head);
+	repo.git_with(&["update-ref", "--stdin"], input.as_bytes());
+	assert_eq!(
+		refs(&repo),
+		"refs/heads/feature/foo-1\nrefs/heads/main\nrefs/heads/ok/x\nrefs/heads/topic/a@b\nrefs/tags/v1.0-rc.1\n"
+	);
+}
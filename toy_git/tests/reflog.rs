@@ -0,0 +1,145 @@
+mod common;
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use common::TempRepo;
+
+// 今の日付
+fn now() -> String {
+	let secs = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.unwrap()
+		.as_secs();
+	format!("{} +0000", secs)
+}
+
+// mainのreflogの各行の新しい値
+fn entries(repo: &TempRepo) -> Vec<String> {
+	String::from_utf8(repo.read(".git/logs/refs/heads/main"))
+		.unwrap()
+		.lines()
+		.map(|x| x.split(' ').nth(1).unwrap().to_string())
+		.collect()
+}
+
+// 2020年のcommitと今のcommit
+fn history(repo: &TempRepo) -> (String, String) {
+	repo.write("a", "a\n");
+	repo.git(&["add", "a"]);
+	repo.git_at("1577836800 +0000", &["commit", "-m", "old"]);
+	let old = repo.git(&["rev-parse", "HEAD"]).trim().to_string();
+	repo.write("a", "b\n");
+	repo.git(&["add", "a"]);
+	repo.git_at(&now(), &["commit", "-m", "new"]);
+	let new = repo.git(&["rev-parse", "HEAD"]).trim().to_string();
+	(old, new)
+}
+
+#[test]
+fn expire_old_entries() {
+	let repo = TempRepo::new();
+	let (old, new) = history(&repo);
+	assert_eq!(entries(&repo), vec![old, new.clone()]);
+
+	repo.git(&["reflog", "expire", "main"]);
+	assert_eq!(entries(&repo), vec![new.clone()]);
+	assert!(!repo.path(".git/logs/refs/heads/main.lock").exists());
+	// HEADのreflogは別に扱う
+	let head = String::from_utf8(repo.read(".git/logs/HEAD")).unwrap();
+	assert_eq!(head.lines().count(), 2);
+
+	repo.git(&["reflog", "expire", "--expire=never", "main"]);
+	assert_eq!(entries(&repo), vec![new]);
+}
+
+#[test]
+fn expire_unreachable() {
+	let repo = TempRepo::new();
+	let (old, new) = history(&repo);
+	repo.write("a", "c\n");
+	repo.git(&["add", "a"]);
+	repo.git_at(&now(), &["commit", "-m", "dropped"]);
+	let dropped = repo.git(&["rev-parse", "HEAD"]).trim().to_string();
+	repo.git_at(&now(), &["reset", "--hard", "HEAD~1"]);
+	assert_eq!(
+		entries(&repo),
+		vec![old.clone(), new.clone(), dropped, new.clone()]
+	);
+
+	// 期限の前でも、古い値か新しい値がtipから辿れない行は--expire-unreachableで消える
+	repo.git(&[
+		"reflog",
+		"expire",
+		"--expire=never",
+		"--expire-unreachable=now",
+		"main",
+	]);
+	assert_eq!(entries(&repo), vec![old, new]);
+}
+
+#[test]
+fn expire_all() {
+	let repo = TempRepo::new();
+	history(&repo);
+	repo.git(&["reflog", "expire", "--expire=all", "main"]);
+	assert_eq!(entries(&repo), Vec::<String>::new());
+	assert_eq!(repo.git(&["rev-parse", "main"]).len(), 41);
+}
+
+// 1700001000、1700002000、1700003000に動いたmain。古い順のhash
+fn moves(repo: &TempRepo) -> Vec<String> {
+	(1..=3)
+		.map(|i| {
+			repo.write("a", format!("{}\n", i));
+			repo.git(&["add", "a"]);
+			let date = format!("170000{}000 +0000", i);
+			repo.git_at(&date, &["commit", "-m", &format!("c{}", i)]);
+			repo.git(&["rev-parse", "HEAD"]).trim().to_string()
+		})
+		.collect()
+}
+
+fn rev(repo: &TempRepo, spec: &str) -> String {
+	repo.git(&["rev-parse", spec]).trim().to_string()
+}
+
+#[test]
+fn nth_entry() {
+	let repo = TempRepo::new();
+	let commits = moves(&repo);
+	assert_eq!(rev(&repo, "HEAD@{0}"), commits[2]);
+	assert_eq!(rev(&repo, "HEAD@{1}"), commits[1]);
+	assert_eq!(rev(&repo, "main@{2}"), commits[0]);
+	// 名前が無ければ今のbranch
+	assert_eq!(rev(&repo, "@{1}"), commits[1]);
+
+	// resetで戻ってもHEAD@{1}は動く前
+	repo.git(&["reset", "--hard", "HEAD~2"]);
+	assert_eq!(rev(&repo, "HEAD@{1}"), commits[2]);
+	assert_eq!(rev(&repo, "HEAD@{1}~1"), commits[1]);
+
+	let err = repo.fail(&["rev-parse", "HEAD@{9}"]);
+	assert!(err.contains("log for 'HEAD' only has 4 entries"), "{}", err);
+}
+
+#[test]
+fn date_spec() {
+	let repo = TempRepo::new();
+	let commits = moves(&repo);
+	// その時にrefが指していたもの。結果はgitと同じ
+	assert_eq!(rev(&repo, "main@{2023-11-14 22:40:00 +0000}"), commits[0]);
+	assert_eq!(rev(&repo, "main@{1700002500 +0000}"), commits[1]);
+	assert_eq!(rev(&repo, "HEAD@{2023-11-15}"), commits[2]);
+	assert_eq!(rev(&repo, "main@{yesterday}"), commits[2]);
+
+	// 最初のentryより前なら、warningを出して最初の値
+	let output = repo.run(&["rev-parse", "@{2023-11-14 22:13:20 +0000}"]);
+	assert!(output.status.success());
+	assert_eq!(String::from_utf8(output.stdout).unwrap().trim(), commits[0]);
+	let err = String::from_utf8(output.stderr).unwrap();
+	assert!(
+		err.contains("warning: log for 'main' only goes back to Tue, 14 Nov 2023 22:30:00 +0000"),
+		"{}",
+		err
+	);
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use common::TempRepo;
+
+// 1つ目のcommit Aを2つ目のcommit Bで置き換えたrepository
+fn replaced() -> (TempRepo, String, String) {
+	let repo = TempRepo::new();
+	let a = repo.commit_file("a", "a\n", "A");
+	let b = repo.commit_file("a", "b\n", "B");
+	repo.git(&["replace", &a, &b]);
+	(repo, a, b)
+}
+
+#[test]
+fn cat_file_reads_replacement() {
+	let (repo, a, b) = replaced();
+	assert_eq!(
+		repo.read(&format!(".git/refs/replace/{}", a)),
+		format!("{}\n", b).as_bytes()
+	);
+	assert_eq!(
+		repo.git(&["cat-file", "-p", &a]),
+		repo.git(&["cat-file", "-p", &b])
+	);
+	assert_eq!(repo.git(&["cat-file", "-p", &format!("{}:a", a)]), "b\n");
+}
+
+#[test]
+fn no_replace_objects() {
+	let (repo, a, _) = replaced();
+	let original = repo.git(&["--no-replace-objects", "cat-file", "-p", &a]);
+	assert!(original.ends_with("\nA\n"), "{}", original);
+	assert!(!original.contains("parent "));
+}
+
+#[test]
+fn log_follows_replacement() {
+	let (repo, a, b) = replaced();
+	// Aの代わりにBの内容を読むので、そのparentのAで止まる
+	assert_eq!(
+		repo.git(&["log", "--format=%H %s"]),
+		format!("{} B\n{} B\n", b, a)
+	);
+}
+
+#[test]
+fn existing_replacement() {
+	let (repo, a, b) = replaced();
+	let err = repo.fail(&["replace", &a, &b]);
+	assert!(err.contains("already exists"), "{}", err);
+	repo.git(&["replace", "-f", &a, &b]);
+}
+
+#[test]
+fn bad_replacement() {
+	let repo = TempRepo::new();
+	let a = repo.commit_file("a", "a\n", "A");
+	let err = repo.fail(&["replace", &a, &format!("{}:a", a)]);
+	assert!(err.contains("Objects must be of the same type"), "{}", err);
+	let err = repo.fail(&["replace", &a, &a]);
+	assert!(
+		err.contains("new object is the same as the old one"),
+		"{}",
+		err
+	);
+	assert!(!repo.path(".git/refs/replace").join(&a).exists());
+}
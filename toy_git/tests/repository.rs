@@ -0,0 +1,98 @@
+mod common;
+
+use common::TempRepo;
+use toy_git::{HashAlgorithm, Repository};
+
+// initで書いたcore.repositoryformatversionを後ろの設定で上書きする
+fn format(repo: &TempRepo, version: u32, extensions: &str) {
+	repo.append(
+		".git/config",
+		&format!(
+			"[core]\n\trepositoryformatversion = {}\n[extensions]\n{}",
+			version, extensions
+		),
+	);
+}
+
+fn open_error(repo: &TempRepo) -> String {
+	match Repository::discover(&repo.dir) {
+		Ok(_) => panic!("opened {:?}", repo.dir),
+		Err(e) => e.to_string(),
+	}
+}
+
+#[test]
+fn unknown_extension() {
+	let repo = TempRepo::new();
+	format(&repo, 1, "\tfuture = true\n");
+	assert_eq!(
+		open_error(&repo),
+		"unknown repository extension found:\n\tfuture"
+	);
+	let err = repo.fail(&["status"]);
+	assert!(err.contains("unknown repository extension"), "{}", err);
+}
+
+#[test]
+fn known_extensions() {
+	let repo = TempRepo::new();
+	format(&repo, 1, "\tnoop = true\n\tobjectFormat = sha256\n");
+	let opened = repo.open();
+	assert_eq!(opened.hash_algo, HashAlgorithm::Sha256);
+}
+
+#[test]
+fn version_0_ignores_extensions() {
+	let repo = TempRepo::new();
+	format(&repo, 0, "\tfuture = true\n");
+	repo.open();
+
+	// v1でしか使えないものはError
+	let repo = TempRepo::new();
+	format(&repo, 0, "\tobjectFormat = sha256\n");
+	assert_eq!(
+		open_error(&repo),
+		"repo version is 0, but v1-only extension found:\n\tobjectformat"
+	);
+}
+
+#[test]
+fn newer_version() {
+	let repo = TempRepo::new();
+	format(&repo, 2, "");
+	assert_eq!(open_error(&repo), "Expected git repo version <= 1, found 2");
+}
+
+// indexの内容をHEADのbranchにcommitする
+#[test]
+fn commit() {
+	let repo = TempRepo::new();
+	let first = repo.commit_file("a", "a\n", "first");
+	repo.write("b", "b\n");
+	repo.git(&["add", "b"]);
+
+	let opened = repo.open();
+	let hash = opened.commit("second").unwrap();
+	assert_eq!(repo.git(&["rev-parse", "main"]), format!("{}\n", hash));
+	assert_eq!(
+		repo.git(&["log", "-n", "1", "--format=%P %an %s"]),
+		format!("{} Test second\n", first)
+	);
+	assert_eq!(repo.git(&["cat-file", "-p", "HEAD:b"]), "b\n");
+	assert_eq!(opened.refs().resolve("HEAD").unwrap(), Some(hash));
+
+	// 変更が無ければcommitしない
+	let err = opened.commit("nothing").unwrap_err();
+	assert_eq!(err.to_string(), "nothing to commit");
+	assert_eq!(repo.git(&["log", "--format=%s"]), "second\nfirst\n");
+}
+
+#[test]
+fn commit_in_bare_repository() {
+	let dir = TempRepo::empty();
+	dir.git(&["init", "-q", "--bare", "bare.git"]);
+	let repo = Repository::open_bare(&dir.path("bare.git")).unwrap();
+	let err = repo.commit("message").unwrap_err();
+	assert_eq!(err.to_string(), "this operation must be run in a work tree");
+	assert!(repo.refs().resolve("HEAD").unwrap().is_none());
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::TempRepo;
+
+// fを1、2、3と変えた3つのcommit。古い順のhash
+fn history() -> (TempRepo, Vec<String>) {
+	let repo = TempRepo::new();
+	let commits = (1..=3)
+		.map(|i| repo.commit_file("f", &format!("{}\n", i), &format!("c{}", i)))
+		.collect();
+	(repo, commits)
+}
+
+fn rev(repo: &TempRepo, rev: &str) -> String {
+	repo.git(&["rev-parse", rev]).trim().to_string()
+}
+
+#[test]
+fn orig_head_after_reset() {
+	let (repo, commits) = history();
+	assert!(!repo.path(".git/ORIG_HEAD").exists());
+
+	repo.git(&["reset", "--hard", &commits[0]]);
+	assert_eq!(repo.read("f"), b"1\n");
+	assert_eq!(
+		repo.read(".git/ORIG_HEAD"),
+		format!("{}\n", commits[2]).as_bytes()
+	);
+	assert_eq!(rev(&repo, "ORIG_HEAD"), commits[2]);
+
+	repo.git(&["reset", "--hard", "ORIG_HEAD"]);
+	assert_eq!(rev(&repo, "HEAD"), commits[2]);
+	assert_eq!(repo.read("f"), b"3\n");
+	assert!(repo.git(&["status"]).contains("nothing to commit"));
+	// 戻したresetもORIG_HEADを書く
+	assert_eq!(rev(&repo, "ORIG_HEAD"), commits[0]);
+
+	// softでも同じ
+	repo.git(&["reset", "--soft", "HEAD~1"]);
+	assert_eq!(rev(&repo, "ORIG_HEAD"), commits[2]);
+}
+
+#[test]
+fn orig_head_after_merge() {
+	let (repo, commits) = history();
+	repo.git(&["branch", "topic"]);
+	repo.git(&["reset", "--hard", &commits[1]]);
+	repo.git(&["merge", "topic"]);
+	assert_eq!(rev(&repo, "ORIG_HEAD"), commits[1]);
+
+	repo.git(&["reset", "--hard", &commits[0]]);
+	repo.git(&["merge", "-s", "ours", "topic"]);
+	assert_eq!(rev(&repo, "ORIG_HEAD"), commits[0]);
+	repo.git(&["reset", "--hard", "ORIG_HEAD"]);
+	assert_eq!(rev(&repo, "HEAD"), commits[0]);
+}
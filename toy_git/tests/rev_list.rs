@@ -0,0 +1,87 @@
+mod common;
+
+use common::TempRepo;
+
+#[test]
+fn objects() {
+	let repo = TempRepo::new();
+	repo.write("a", "a\n");
+	repo.write("dir/b", "b\n");
+	repo.git(&["add", "a", "dir/b"]);
+	repo.git(&["commit", "-m", "first"]);
+	repo.commit_file("dir/c", "c\n", "second");
+	let hash = |rev: &str| repo.git(&["rev-parse", rev]).trim().to_string();
+
+	// commitを新しい順に出してから、各treeの中のものを1度ずつ。rootのtreeのpathは空
+	let expected = [
+		hash("HEAD"),
+		hash("HEAD~1"),
+		format!("{} ", hash("HEAD^{tree}")),
+		format!("{} a", hash("HEAD:a")),
+		format!("{} dir", hash("HEAD:dir")),
+		format!("{} dir/b", hash("HEAD:dir/b")),
+		format!("{} dir/c", hash("HEAD:dir/c")),
+		format!("{} ", hash("HEAD~1^{tree}")),
+		format!("{} dir", hash("HEAD~1:dir")),
+	];
+	let output = repo.git(&["rev-list", "--objects", "HEAD"]);
+	assert_eq!(output.lines().collect::<Vec<_>>(), expected);
+
+	// 除いたcommitから辿れるものは出さない
+	let output = repo.git(&["rev-list", "--objects", "HEAD", "^HEAD~1"]);
+	assert_eq!(
+		output.lines().collect::<Vec<_>>(),
+		[
+			hash("HEAD"),
+			format!("{} ", hash("HEAD^{tree}")),
+			format!("{} dir", hash("HEAD:dir")),
+			format!("{} dir/c", hash("HEAD:dir/c")),
+		]
+	);
+}
+
+// baseからmainにa1、a2、sideにb1、b2、b3
+#[test]
+fn symmetric_difference() {
+	let repo = TempRepo::new();
+	repo.commit_file("base", "base\n", "base");
+	repo.git(&["branch", "side"]);
+	let a: Vec<String> = (1..=2)
+		.map(|i| repo.commit_file("a", &format!("{}\n", i), "a"))
+		.collect();
+	repo.git(&["checkout", "side"]);
+	let b: Vec<String> = (1..=3)
+		.map(|i| repo.commit_file("b", &format!("{}\n", i), "b"))
+		.collect();
+
+	assert_eq!(
+		repo.git(&["rev-list", "--count", "--left-right", "main...side"]),
+		"2\t3\n"
+	);
+	assert_eq!(
+		repo.git(&["rev-list", "--count", "--left-right", "side...main"]),
+		"3\t2\n"
+	);
+	assert_eq!(repo.git(&["rev-list", "--count", "main...side"]), "5\n");
+
+	let sorted = |output: String| {
+		let mut lines: Vec<String> = output.lines().map(String::from).collect();
+		lines.sort();
+		lines
+	};
+	let mut expected: Vec<String> = a
+		.iter()
+		.map(|x| format!("<{}", x))
+		.chain(b.iter().map(|x| format!(">{}", x)))
+		.collect();
+	expected.sort();
+	assert_eq!(
+		sorted(repo.git(&["rev-list", "--left-right", "main...side"])),
+		expected
+	);
+	// baseは両方から辿れるので出さない
+	let mut expected = [a, b].concat();
+	expected.sort();
+	assert_eq!(sorted(repo.git(&["rev-list", "main...side"])), expected);
+	assert_eq!(repo.git(&["rev-list", "main...main"]), "");
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::TempRepo;
+
+// 2つのcommitと、2つ目を指すannotated tag "v1"
+fn tagged() -> (TempRepo, String, String) {
+	let repo = TempRepo::new();
+	let first = repo.commit_file("a", "a\n", "first");
+	let second = repo.commit_file("a", "b\n", "second");
+	repo.git(&["tag", "-a", "-m", "release", "v1"]);
+	(repo, first, second)
+}
+
+fn rev_parse(repo: &TempRepo, spec: &str) -> String {
+	repo.git(&["rev-parse", spec]).trim().to_string()
+}
+
+#[test]
+fn parents() {
+	let (repo, first, second) = tagged();
+	assert_eq!(rev_parse(&repo, "HEAD"), second);
+	assert_eq!(rev_parse(&repo, "HEAD~1"), first);
+	assert_eq!(rev_parse(&repo, "HEAD^"), first);
+	assert_eq!(rev_parse(&repo, "HEAD~0"), second);
+	let err = repo.fail(&["rev-parse", "HEAD~2"]);
+	assert!(!err.is_empty());
+}
+
+#[test]
+fn peel_tag() {
+	let (repo, _, second) = tagged();
+	let tag = rev_parse(&repo, "v1");
+	assert_ne!(tag, second);
+	assert_eq!(repo.git(&["cat-file", "-t", &tag]), "tag\n");
+
+	assert_eq!(rev_parse(&repo, "v1^{commit}"), second);
+	assert_eq!(rev_parse(&repo, "v1^{}"), second);
+	assert_eq!(rev_parse(&repo, "v1^{tag}"), tag);
+	let tree = rev_parse(&repo, "v1^{tree}");
+	assert_eq!(repo.git(&["cat-file", "-t", &tree]), "tree\n");
+	assert_eq!(rev_parse(&repo, "HEAD^{tree}"), tree);
+	// treeはcommitにできない
+	repo.fail(&["rev-parse", "v1^{tree}^{commit}"]);
+}
+
+#[test]
+fn verify() {
+	let (repo, first, _) = tagged();
+	assert_eq!(
+		repo.git(&["rev-parse", "--verify", "HEAD~1"]),
+		format!("{}\n", first)
+	);
+	let err = repo.fail(&["rev-parse", "--verify", "HEAD", "HEAD~1"]);
+	assert!(err.contains("Needed a single revision"), "{}", err);
+	let err = repo.fail(&["rev-parse", "--verify", "nothing"]);
+	assert!(err.contains("Needed a single revision"), "{}", err);
+}
+
+#[test]
+fn tree_path() {
+	let (repo, first, _) = tagged();
+	repo.commit_file("dir/file.txt", "text\n", "third");
+	assert_eq!(repo.git(&["cat-file", "-p", "HEAD:dir/file.txt"]), "text\n");
+	assert_eq!(repo.git(&["cat-file", "-p", "v1:a"]), "b\n");
+	assert_eq!(
+		repo.git(&["cat-file", "-p", &format!("{}:a", first)]),
+		"a\n"
+	);
+	assert_eq!(repo.git(&["cat-file", "-t", "HEAD:dir"]), "tree\n");
+	// pathが空ならtreeそのもの
+	assert_eq!(rev_parse(&repo, "HEAD:"), rev_parse(&repo, "HEAD^{tree}"));
+	assert_eq!(rev_parse(&repo, "HEAD:dir/"), rev_parse(&repo, "HEAD:dir"));
+
+	for path in ["dir/nope", "nope/file.txt", "a/x", "dir/file.txt/x"] {
+		let err = repo.fail(&["cat-file", "-p", &format!("HEAD:{}", path)]);
+		assert!(
+			err.contains(&format!("path '{}' does not exist in 'HEAD'", path)),
+			"{}",
+			err
+		);
+	}
+}
+
+// 入れ子のsubdirectoryから実行する
+#[test]
+fn path_queries() {
+	let repo = TempRepo::new();
+	repo.commit_file("a", "a\n", "first");
+	let sub = repo.path("dir/sub");
+	fs::create_dir_all(&sub).unwrap();
+	let query = |dir: &Path, flag: &str| {
+		let output = repo.run_in(dir, &["rev-parse", flag], None);
+		assert!(output.status.success(), "{}", flag);
+		String::from_utf8(output.stdout).unwrap()
+	};
+	let top = fs::canonicalize(&repo.dir).unwrap();
+	assert_eq!(
+		query(&sub, "--show-toplevel"),
+		format!("{}\n", top.display())
+	);
+	assert_eq!(
+		query(&sub, "--git-dir"),
+		format!("{}\n", top.join(".git").display())
+	);
+	assert_eq!(query(&sub, "--is-inside-work-tree"), "true\n");
+	assert_eq!(query(&sub, "--show-prefix"), "dir/sub/\n");
+
+	// toplevelではgit-dirは相対で、prefixは空
+	assert_eq!(query(&repo.dir, "--git-dir"), ".git\n");
+	assert_eq!(query(&repo.dir, "--show-prefix"), "\n");
+	assert_eq!(
+		query(&repo.path(".git"), "--is-inside-work-tree"),
+		"false\n"
+	);
+}
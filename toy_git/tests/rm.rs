@@ -0,0 +1,50 @@
+mod common;
+
+use common::TempRepo;
+
+// dir/a、dir/sub/b、top.txtをcommitしたもの
+fn tracked() -> TempRepo {
+	let repo = TempRepo::new();
+	repo.write("dir/a", "a\n");
+	repo.write("dir/sub/b", "b\n");
+	repo.write("top.txt", "top\n");
+	repo.git(&["add", "dir", "top.txt"]);
+	repo.git(&["commit", "-m", "first"]);
+	repo
+}
+
+#[test]
+fn recursive() {
+	let repo = tracked();
+	assert_eq!(
+		repo.git(&["rm", "-r", "dir"]),
+		"rm 'dir/a'\nrm 'dir/sub/b'\n"
+	);
+	assert_eq!(repo.git(&["ls-files"]), "top.txt\n");
+	assert!(!repo.path("dir").exists());
+	assert!(repo.path("top.txt").exists());
+}
+
+#[test]
+fn directory_needs_recursive() {
+	let repo = tracked();
+	let err = repo.fail(&["rm", "dir"]);
+	assert!(
+		err.contains("not removing 'dir' recursively without -r"),
+		"{}",
+		err
+	);
+	assert_eq!(repo.git(&["ls-files"]), "dir/a\ndir/sub/b\ntop.txt\n");
+	assert!(repo.path("dir/a").exists());
+}
+
+#[test]
+fn unmatched_pathspec() {
+	let repo = tracked();
+	let err = repo.fail(&["rm", "-r", "nothere"]);
+	assert!(
+		err.contains("pathspec 'nothere' did not match any files"),
+		"{}",
+		err
+	);
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use common::TempRepo;
+
+// Bobのone、Aliceのtwo、Bobのthree
+fn history() -> TempRepo {
+	let repo = TempRepo::new();
+	for (name, subject) in [("Bob", "one"), ("Alice", "two"), ("Bob", "three")] {
+		repo.git(&["config", "user.name", name]);
+		repo.git(&[
+			"config",
+			"user.email",
+			&format!("{}@example.com", name.to_lowercase()),
+		]);
+		repo.commit_file("f", subject, subject);
+	}
+	repo
+}
+
+// 出力はgit shortlogと同じ
+#[test]
+fn grouped() {
+	let repo = history();
+	// 既定は名前の順で、subjectは古い順
+	assert_eq!(
+		repo.git(&["shortlog"]),
+		"Alice (1):\n      two\n\nBob (2):\n      one\n      three\n\n"
+	);
+	assert_eq!(
+		repo.git(&["shortlog", "-n"]),
+		"Bob (2):\n      one\n      three\n\nAlice (1):\n      two\n\n"
+	);
+	assert_eq!(
+		repo.git(&["shortlog", "HEAD~2.."]),
+		"Alice (1):\n      two\n\nBob (1):\n      three\n\n"
+	);
+}
+
+#[test]
+fn summary() {
+	let repo = history();
+	assert_eq!(
+		repo.git(&["shortlog", "-s"]),
+		"     1\tAlice\n     2\tBob\n"
+	);
+	assert_eq!(
+		repo.git(&["shortlog", "-s", "-n"]),
+		"     2\tBob\n     1\tAlice\n"
+	);
+	assert_eq!(
+		repo.git(&["shortlog", "-sne"]),
+		"     2\tBob <bob@example.com>\n     1\tAlice <alice@example.com>\n"
+	);
+}
@@ -0,0 +1,38 @@
+mod common;
+
+use common::TempRepo;
+
+// baseからtopicで2つ、mainで1つ進める。日付は作った順に1日ずつ後
+fn branches() -> TempRepo {
+	let repo = TempRepo::new();
+	let commit = |path: &str, message: &str, day: &str| {
+		repo.write(path, format!("{}\n", path));
+		repo.git(&["add", path]);
+		let date = format!("2024-01-{}T12:00:00+0000", day);
+		repo.git_at(&date, &["commit", "-m", message]);
+	};
+	commit("a", "base", "01");
+	repo.git(&["checkout", "-b", "topic"]);
+	commit("b", "topic one", "02");
+	commit("c", "topic two", "03");
+	repo.git(&["checkout", "main"]);
+	commit("d", "main one", "04");
+	repo
+}
+
+#[test]
+fn two_branches() {
+	let repo = branches();
+	// 現在のbranchは "*"、それ以外は "!" と "+"。merge baseで止まる
+	let expected = "\
+* [main] main one
+ ! [topic] topic two
+--
+*  [main] main one
+ + [topic] topic two
+ + [topic^] topic one
+*+ [main^] base
+";
+	assert_eq!(repo.git(&["show-branch", "main", "topic"]), expected);
+	assert_eq!(repo.git(&["show-branch"]), expected);
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use common::TempRepo;
+
+// main、topic、packed-refsにだけあるold、軽量tagのlightとannotated tagのv1
+fn refs() -> (TempRepo, String, String) {
+	let repo = TempRepo::new();
+	let first = repo.commit_file("a", "a\n", "first");
+	repo.git(&["branch", "topic"]);
+	repo.append(".git/packed-refs", &format!("{} refs/heads/old\n", first));
+	repo.git(&["tag", "light"]);
+	repo.git(&["tag", "-a", "-m", "release", "v1"]);
+	let tag = repo.git(&["rev-parse", "v1"]).trim().to_string();
+	(repo, first, tag)
+}
+
+#[test]
+fn all_refs() {
+	let (repo, commit, tag) = refs();
+	assert_eq!(
+		repo.git(&["show-ref"]),
+		format!(
+			"{0} refs/heads/main\n{0} refs/heads/old\n{0} refs/heads/topic\n\
+			{0} refs/tags/light\n{1} refs/tags/v1\n",
+			commit, tag
+		)
+	);
+	assert_eq!(
+		repo.git(&["show-ref", "--heads"]),
+		format!(
+			"{0} refs/heads/main\n{0} refs/heads/old\n{0} refs/heads/topic\n",
+			commit
+		)
+	);
+}
+
+#[test]
+fn dereference() {
+	let (repo, commit, tag) = refs();
+	let expected = format!(
+		"{0} refs/tags/light\n{1} refs/tags/v1\n{0} refs/tags/v1^{{}}\n",
+		commit, tag
+	);
+	assert_eq!(repo.git(&["show-ref", "--tags", "--dereference"]), expected);
+	assert_eq!(repo.git(&["show-ref", "--tags", "-d"]), expected);
+	// --dereferenceが無ければpeelしない
+	assert!(!repo.git(&["show-ref", "--tags"]).contains("^{}"));
+}
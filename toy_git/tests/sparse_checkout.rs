@@ -0,0 +1,83 @@
+mod common;
+
+use common::TempRepo;
+use toy_git::Index;
+
+// src/main、doc/readme、topをcommitし、src/だけをsparse-checkoutにしたもの
+fn sparse() -> TempRepo {
+	let repo = TempRepo::new();
+	repo.write("src/main", "m\n");
+	repo.write("doc/readme", "r\n");
+	repo.write("top", "t\n");
+	repo.git(&["add", "."]);
+	repo.git(&["commit", "-m", "first"]);
+	repo.git(&["config", "core.sparseCheckout", "true"]);
+	repo.write(".git/info/sparse-checkout", "src/\n");
+	repo
+}
+
+// indexの(path, skip-worktree)
+fn skipped(repo: &TempRepo) -> Vec<(String, bool)> {
+	Index::load(&repo.open())
+		.unwrap()
+		.entries
+		.iter()
+		.map(|x| (x.path.clone(), x.skip_worktree()))
+		.collect()
+}
+
+#[test]
+fn only_matching_paths() {
+	let repo = sparse();
+	repo.git(&["reset", "--hard"]);
+	assert_eq!(repo.read("src/main"), b"m\n");
+	assert!(!repo.path("doc").exists());
+	assert!(!repo.path("top").exists());
+	// 書き出さなかったものもindexには残る
+	assert_eq!(
+		skipped(&repo),
+		vec![
+			(String::from("doc/readme"), true),
+			(String::from("src/main"), false),
+			(String::from("top"), true),
+		]
+	);
+	// 無いファイルを消したとは扱わない
+	assert!(repo.git(&["status"]).contains("nothing to commit"));
+}
+
+#[test]
+fn widen() {
+	let repo = sparse();
+	repo.git(&["reset", "--hard"]);
+	repo.write(".git/info/sparse-checkout", "/*\n");
+	repo.git(&["reset", "--hard"]);
+	assert_eq!(repo.read("doc/readme"), b"r\n");
+	assert_eq!(repo.read("top"), b"t\n");
+	assert!(skipped(&repo).iter().all(|(_, skip)| !skip));
+}
+
+#[test]
+fn disabled() {
+	let repo = sparse();
+	repo.git(&["config", "core.sparseCheckout", "false"]);
+	repo.git(&["reset", "--hard"]);
+	assert!(repo.path("top").exists());
+	assert!(skipped(&repo).iter().all(|(_, skip)| !skip));
+}
+
+#[test]
+fn checkout_branch() {
+	let repo = sparse();
+	repo.git(&["reset", "--hard"]);
+	repo.git(&["checkout", "-b", "other"]);
+	repo.write("src/new", "n\n");
+	repo.git(&["add", "src/new"]);
+	repo.git(&["commit", "-m", "second"]);
+
+	repo.git(&["checkout", "main"]);
+	assert!(!repo.path("src/new").exists());
+	repo.git(&["checkout", "other"]);
+	assert_eq!(repo.read("src/new"), b"n\n");
+	assert!(!repo.path("top").exists());
+}
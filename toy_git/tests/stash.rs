@@ -0,0 +1,79 @@
+mod common;
+
+use common::TempRepo;
+
+// aを "one" にしたものと "two" にしたものを順にstashしたrepository
+fn two_stashes() -> TempRepo {
+	let repo = TempRepo::new();
+	repo.commit_file("a", "a\n", "base");
+	repo.write("a", "one\n");
+	repo.git(&["stash"]);
+	repo.write("a", "two\n");
+	repo.git(&["stash", "push", "-m", "second"]);
+	assert_eq!(repo.read("a"), b"a\n");
+	repo
+}
+
+#[test]
+fn list() {
+	let repo = two_stashes();
+	let base = repo.git(&["log", "-n", "1", "--format=%h %s"]);
+	assert_eq!(
+		repo.git(&["stash", "list"]),
+		format!(
+			"stash@{{0}}: On main: second\nstash@{{1}}: WIP on main: {}",
+			base
+		)
+	);
+}
+
+#[test]
+fn apply_keeps_entry() {
+	let repo = two_stashes();
+	let list = repo.git(&["stash", "list"]);
+	repo.git(&["stash", "apply"]);
+	assert_eq!(repo.read("a"), b"two\n");
+	assert_eq!(repo.git(&["stash", "list"]), list);
+
+	repo.git(&["reset", "--hard"]);
+	repo.git(&["stash", "apply", "stash@{1}"]);
+	assert_eq!(repo.read("a"), b"one\n");
+	assert_eq!(repo.git(&["stash", "list"]), list);
+
+	let err = repo.fail(&["stash", "apply", "2"]);
+	assert!(err.contains("2 is not a valid reference"), "{}", err);
+}
+
+#[test]
+fn drop_older() {
+	let repo = two_stashes();
+	let older = repo.git(&["rev-parse", "stash@{1}"]);
+	let newer = repo.git(&["rev-parse", "stash@{0}"]);
+	assert_eq!(
+		repo.git(&["stash", "drop", "1"]),
+		format!("Dropped refs/stash@{{1}} ({})\n", older.trim())
+	);
+	assert_eq!(repo.git(&["stash", "list"]), "stash@{0}: On main: second\n");
+	assert_eq!(repo.git(&["rev-parse", "refs/stash"]), newer);
+
+	repo.git(&["stash", "pop"]);
+	assert_eq!(repo.read("a"), b"two\n");
+	assert_eq!(repo.git(&["stash", "list"]), "");
+	assert!(!repo.path(".git/refs/stash").exists());
+	let err = repo.fail(&["stash", "drop"]);
+	assert!(err.contains("No stash entries found."), "{}", err);
+}
+
+#[test]
+fn drop_newer() {
+	let repo = two_stashes();
+	let older = repo.git(&["rev-parse", "stash@{1}"]);
+	repo.git(&["stash", "drop", "stash@{0}"]);
+	// 残ったものがstash@{0}になる
+	assert_eq!(repo.git(&["rev-parse", "refs/stash"]), older);
+	assert!(repo
+		.git(&["stash", "list"])
+		.starts_with("stash@{0}: WIP on main: "));
+	repo.git(&["stash", "pop"]);
+	assert_eq!(repo.read("a"), b"one\n");
+}
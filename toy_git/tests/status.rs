@@ -0,0 +1,101 @@
+mod common;
+
+use std::os::unix::fs::symlink;
+
+use common::TempRepo;
+
+// 出力はgit status --shortと同じ
+#[test]
+fn short() {
+	let repo = TempRepo::new();
+	assert_eq!(repo.git(&["status", "-sb"]), "## No commits yet on main\n");
+	repo.write("a", "a\n");
+	repo.write("b", "b\nb\nb\nb\n");
+	repo.write("c", "c\n");
+	symlink("a", repo.path("l")).unwrap();
+	repo.git(&["add", "a", "b", "c", "l"]);
+	repo.git(&["commit", "-m", "first"]);
+	assert_eq!(repo.git(&["status", "--short"]), "");
+	assert_eq!(
+		repo.git(&["status", "--porcelain", "--branch"]),
+		"## main\n"
+	);
+
+	repo.append("a", "staged\n");
+	repo.git(&["add", "a"]);
+	repo.append("a", "unstaged\n");
+	std::fs::rename(repo.path("b"), repo.path("b2")).unwrap();
+	repo.git(&["rm", "--cached", "b"]);
+	repo.git(&["add", "b2"]);
+	std::fs::remove_file(repo.path("c")).unwrap();
+	std::fs::remove_file(repo.path("l")).unwrap();
+	repo.write("l", "file\n");
+	repo.write("n", "new\n");
+	repo.git(&["add", "n"]);
+	repo.write("u", "u\n");
+	repo.write("dir/x", "x\n");
+	let expected = "MM a\nR  b -> b2\n D c\n T l\nA  n\n?? dir/\n?? u\n";
+	assert_eq!(repo.git(&["status", "-s"]), expected);
+	assert_eq!(repo.git(&["status", "--porcelain"]), expected);
+	assert_eq!(
+		repo.git(&["status", "-s", "-b"]),
+		format!("## main\n{}", expected)
+	);
+	// 後の--longが勝つ
+	assert!(repo
+		.git(&["status", "-s", "--long"])
+		.starts_with("On branch main\n"));
+}
+
+#[test]
+fn short_unmerged() {
+	let repo = TempRepo::new();
+	repo.commit_file("f", "base\n", "base");
+	repo.git(&["checkout", "-b", "topic"]);
+	repo.commit_file("f", "topic\n", "topic");
+	repo.git(&["checkout", "main"]);
+	repo.commit_file("f", "main\n", "main");
+	assert!(!repo.run(&["merge", "topic"]).status.success());
+	assert_eq!(repo.git(&["status", "-s"]), "UU f\n");
+}
+
+// upstreamとの差は "[ahead 1, behind 2]" のように出す
+#[test]
+fn short_branch() {
+	let repo = TempRepo::new();
+	repo.commit_file("a", "a\n", "first");
+	repo.git(&["branch", "up"]);
+	repo.git(&["config", "branch.main.remote", "."]);
+	repo.git(&["config", "branch.main.merge", "refs/heads/up"]);
+	let line = || repo.git(&["status", "-sb"]);
+	assert_eq!(line(), "## main...up\n");
+	repo.commit_file("a", "b\n", "ahead");
+	assert_eq!(line(), "## main...up [ahead 1]\n");
+	repo.git(&["checkout", "up"]);
+	repo.commit_file("u", "1\n", "one");
+	repo.commit_file("u", "2\n", "two");
+	repo.git(&["checkout", "main"]);
+	assert_eq!(line(), "## main...up [ahead 1, behind 2]\n");
+	repo.git(&["config", "branch.main.merge", "refs/heads/gone"]);
+	assert_eq!(line(), "## main...gone [gone]\n");
+	repo.git(&["checkout", "HEAD~"]);
+	assert_eq!(line(), "## HEAD (no branch)\n");
+}
+
+#[test]
+fn unknown_arguments() {
+	let repo = TempRepo::new();
+	repo.commit_file("a", "a\n", "first");
+	for args in [
+		&["status", "--bogus"][..],
+		&["status", "-x"],
+		&["status", "a"],
+	] {
+		let output = repo.run(args);
+		assert_eq!(output.status.code(), Some(128), "{:?}", args);
+		assert!(output.stdout.is_empty());
+		assert!(String::from_utf8(output.stderr)
+			.unwrap()
+			.starts_with("fatal: usage: status "));
+	}
+}
@@ -0,0 +1,332 @@
+mod common;
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use common::TempRepo;
+use toy_git::tempfile::TempFile;
+use toy_git::transaction::ObjectTransaction;
+use toy_git::{store, Hash, ObjectKind};
+
+const FOO: &str = "257cc5642cb1a054f08cc83f2d943e56fd3ebe99";
+
+// objectsの下にあるファイル全て
+fn files(dir: &Path) -> Vec<PathBuf> {
+	let mut found = Vec::new();
+	for entry in fs::read_dir(dir).unwrap() {
+		let path = entry.unwrap().path();
+		match path.is_dir() {
+			true => found.extend(files(&path)),
+			false => found.push(path),
+		}
+	}
+	found
+}
+
+// limit byteを渡した後にErrorになるreader(書いている途中でprocessが死ぬ代わり)
+struct Failing {
+	data: io::Cursor<Vec<u8>>,
+	limit: usize,
+}
+
+impl Read for Failing {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if self.data.position() as usize >= self.limit {
+			return Err(io::Error::other("simulated failure"));
+		}
+		let n = buf.len().min(self.limit - self.data.position() as usize);
+		self.data.read(&mut buf[..n])
+	}
+}
+
+#[test]
+fn failed_stream_leaves_no_object() {
+	let repo = TempRepo::new();
+	let open = repo.open();
+	let content = vec![b'x'; 200 * 1024];
+	let mut reader = Failing {
+		data: io::Cursor::new(content.clone()),
+		limit: 100 * 1024,
+	};
+
+	let err = store::write_stream(&open, ObjectKind::Blob, content.len(), &mut reader).unwrap_err();
+	assert_eq!(err.to_string(), "simulated failure");
+	assert_eq!(files(&open.objects_dir()), Vec::<PathBuf>::new());
+
+	// 読めた長さがheaderのsizeと違う場合も同じ
+	let err = store::write_stream(&open, ObjectKind::Blob, 10, &mut &b"short"[..]).unwrap_err();
+	assert!(err.to_string().contains("expected 10"), "{}", err);
+	assert_eq!(files(&open.objects_dir()), Vec::<PathBuf>::new());
+}
+
+#[test]
+fn stream_is_renamed_into_place() {
+	let repo = TempRepo::new();
+	let open = repo.open();
+
+	let hash = store::write_stream(&open, ObjectKind::Blob, 4, &mut &b"foo\n"[..]).unwrap();
+	let hash = Hash(hash);
+	assert_eq!(hash.to_string(), FOO);
+	// 一時ファイルは残らず、objectだけがある
+	assert_eq!(
+		files(&open.objects_dir()),
+		vec![open
+			.objects_dir()
+			.join("25/7cc5642cb1a054f08cc83f2d943e56fd3ebe99")]
+	);
+	let (kind, body) = store::read_object(&open, &hash.0).unwrap();
+	assert_eq!(kind, ObjectKind::Blob);
+	assert_eq!(body, b"foo\n");
+}
+
+#[test]
+fn temp_file_is_removed_on_drop() {
+	let repo = TempRepo::empty();
+	let mut temp = TempFile::new(&repo.dir, "tmp_obj").unwrap();
+	temp.write_all(b"partial").unwrap();
+	let path = temp.path().to_path_buf();
+	assert!(path
+		.file_name()
+		.unwrap()
+		.to_str()
+		.unwrap()
+		.starts_with("tmp_obj_"));
+	assert!(path.exists());
+	drop(temp);
+	assert!(!path.exists());
+
+	let mut temp = TempFile::new(&repo.dir, "tmp_obj").unwrap();
+	temp.write_all(b"done").unwrap();
+	temp.persist(&repo.path("dest")).unwrap();
+	assert_eq!(files(&repo.dir), vec![repo.path("dest")]);
+	assert_eq!(repo.read("dest"), b"done");
+}
+
+// objectsの下のobjectのhash(一時ディレクトリやpackは除く)
+fn loose(dir: &Path) -> Vec<String> {
+	let mut hashes: Vec<String> = files(dir)
+		.iter()
+		.filter_map(|x| {
+			let name = x.file_name()?.to_str()?;
+			let parent = x.parent()?.file_name()?.to_str()?;
+			(parent.len() == 2 && name.len() == 38).then(|| format!("{}{}", parent, name))
+		})
+		.collect();
+	hashes.sort();
+	hashes
+}
+
+#[test]
+fn rolled_back_transaction() {
+	let repo = TempRepo::new();
+	let open = repo.open();
+	let transaction = ObjectTransaction::begin(&open).unwrap();
+	let hash = store::write_object(&open, b"blob 4\0foo\n").unwrap();
+	assert_eq!(Hash(hash.clone()).to_string(), FOO);
+	// 置いてある間も読める
+	assert_eq!(store::read_object(&open, &hash).unwrap().1, b"foo\n");
+	assert!(!open.objects_dir().join("25").exists());
+
+	transaction.rollback();
+	assert_eq!(files(&open.objects_dir()), Vec::<PathBuf>::new());
+	assert!(store::read_object(&open, &hash).is_err());
+}
+
+#[test]
+fn dropped_transaction() {
+	let repo = TempRepo::new();
+	let open = repo.open();
+	{
+		let _transaction = ObjectTransaction::begin(&open).unwrap();
+		store::write_object(&open, b"blob 4\0foo\n").unwrap();
+	}
+	assert_eq!(files(&open.objects_dir()), Vec::<PathBuf>::new());
+}
+
+#[test]
+fn committed_transaction() {
+	let repo = TempRepo::new();
+	let open = repo.open();
+	let existing = store::write_object(&open, b"blob 4\0bar\n").unwrap();
+
+	let transaction = ObjectTransaction::begin(&open).unwrap();
+	let foo = store::write_object(&open, b"blob 4\0foo\n").unwrap();
+	// 外側のtransactionに任せる
+	let nested = ObjectTransaction::begin(&open).unwrap();
+	let baz = store::write_object(&open, b"blob 4\0baz\n").unwrap();
+	nested.commit().unwrap();
+	assert!(!open
+		.objects_dir()
+		.join(&Hash(baz.clone()).to_string()[..2])
+		.exists());
+	store::write_object(&open, b"blob 4\0bar\n").unwrap();
+	transaction.commit().unwrap();
+
+	let mut expected: Vec<String> = [existing, foo, baz]
+		.iter()
+		.map(|x| Hash(x.clone()).to_string())
+		.collect();
+	expected.sort();
+	assert_eq!(loose(&open.objects_dir()), expected);
+	// 一時ディレクトリは残らない
+	assert_eq!(files(&open.objects_dir()).len(), 3);
+}
+
+#[test]
+fn header_of_large_blob() {
+	let repo = TempRepo::new();
+	// 圧縮しなければ64KB以下のblockに分けて書かれるので、途中で切っても先頭のblockは読める
+	repo.git(&["config", "core.looseCompression", "0"]);
+	let content = vec![b'x'; 1 << 20];
+	repo.write("big", &content);
+	let hash = repo.git(&["hash-object", "-w", "big"]);
+	let hash = Hash::from_hex(hash.trim()).unwrap();
+	let open = repo.open();
+	assert_eq!(
+		store::read_object_header(&open, &hash.0).unwrap(),
+		(ObjectKind::Blob, 1 << 20)
+	);
+
+	// 先頭だけを残すと、headerは読めるが内容は読めない
+	let hex = hash.to_hex();
+	let path = repo.path(&format!(".git/objects/{}/{}", &hex[..2], &hex[2..]));
+	let mut compressed = fs::read(&path).unwrap();
+	compressed.truncate(1 << 17);
+	fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+	fs::write(&path, &compressed).unwrap();
+
+	assert_eq!(
+		store::read_object_header(&open, &hash.0).unwrap(),
+		(ObjectKind::Blob, 1 << 20)
+	);
+	assert!(store::read_object(&open, &hash.0).is_err());
+	assert_eq!(repo.git(&["cat-file", "-t", &hex]), "blob\n");
+	assert_eq!(
+		repo.git(&["cat-file", "-s", &hex]),
+		format!("{}\n", 1 << 20)
+	);
+	assert_eq!(
+		repo.git_with(
+			&["cat-file", "--batch-check"],
+			format!("{}\n", hex).as_bytes()
+		),
+		format!("{} blob {}\n", hex, 1 << 20)
+	);
+	assert!(!repo.run(&["cat-file", "-p", &hex]).status.success());
+}
+
+#[test]
+fn header_of_each_kind() {
+	let repo = TempRepo::new();
+	let commit = repo.commit_file("a", "foo\n", "first");
+	repo.git(&["tag", "-a", "v1", "-m", "tag"]);
+	let open = repo.open();
+	let revs = [commit.as_str(), "HEAD^{tree}", FOO, "v1"];
+	for rev in revs {
+		let hash = repo.git(&["rev-parse", rev]);
+		let hash = Hash::from_hex(hash.trim()).unwrap();
+		let (kind, body) = store::read_object(&open, &hash.0).unwrap();
+		assert_eq!(
+			store::read_object_header(&open, &hash.0).unwrap(),
+			(kind, body.len()),
+			"{}",
+			rev
+		);
+	}
+}
+
+// UTF-8でない内容も、loose objectでもpackでもそのまま読み書きできる
+#[test]
+fn binary_round_trip() {
+	let repo = TempRepo::new();
+	let content = b"\xff\xfe\0\x01\x89PNG\r\n\x1a\n\xc3\x28";
+	repo.write("image.png", content);
+	repo.write("latin1.txt", b"caf\xe9\n");
+	repo.git(&["add", "image.png", "latin1.txt"]);
+	repo.git(&["commit", "-m", "binary"]);
+	// gitと同じhash
+	let hash = repo.git(&["rev-parse", "HEAD:image.png"]);
+	assert_eq!(hash, "fb6f1e3856598500eb1e76853fa41bb1246ce6ce\n");
+	let hash = Hash::from_hex(hash.trim()).unwrap();
+
+	let check = |packed: bool| {
+		let open = repo.open();
+		assert_eq!(
+			store::read_object(&open, &hash.0).unwrap(),
+			(ObjectKind::Blob, content.to_vec())
+		);
+		assert_eq!(
+			repo.run(&["cat-file", "-p", "HEAD:image.png"]).stdout,
+			content
+		);
+		assert_eq!(
+			repo.run(&["cat-file", "-p", "HEAD:latin1.txt"]).stdout,
+			b"caf\xe9\n"
+		);
+		fs::remove_file(repo.path("image.png")).unwrap();
+		fs::remove_file(repo.path("latin1.txt")).unwrap();
+		repo.git(&["checkout", "-f", "HEAD"]);
+		assert_eq!(repo.read("image.png"), content, "packed: {}", packed);
+		assert_eq!(repo.read("latin1.txt"), b"caf\xe9\n");
+	};
+	check(false);
+	repo.git(&["gc"]);
+	assert!(!repo.path(".git/objects/fb").exists());
+	check(true);
+}
+
+// levelを設定したrepositoryに同じblobを書き、loose objectの大きさとcat-fileで読んだ中身
+fn compressed(key: &str, level: &str) -> (u64, Vec<u8>) {
+	let repo = TempRepo::new();
+	repo.git(&["config", key, level]);
+	let content = "compressible line\n".repeat(1000);
+	let hash = repo.git_with(&["hash-object", "-w", "--stdin"], content.as_bytes());
+	let hash = hash.trim();
+	let path = repo.path(&format!(".git/objects/{}/{}", &hash[..2], &hash[2..]));
+	let output = repo.run(&["cat-file", "-p", hash]);
+	assert!(output.status.success());
+	assert_eq!(output.stdout, content.as_bytes());
+	(fs::metadata(path).unwrap().len(), output.stdout)
+}
+
+#[test]
+fn loose_compression() {
+	let (stored, content) = compressed("core.looseCompression", "0");
+	let (best, same) = compressed("core.looseCompression", "9");
+	assert_eq!(content, same);
+	assert!(stored > content.len() as u64, "{}", stored);
+	assert!(best < stored / 10, "{} {}", best, stored);
+
+	// core.compressionはcore.looseCompressionが無いときに使う
+	let (fallback, _) = compressed("core.compression", "0");
+	assert_eq!(fallback, stored);
+}
+
+#[test]
+fn bad_compression_level() {
+	let repo = TempRepo::new();
+	repo.git(&["config", "core.looseCompression", "10"]);
+	repo.write("a", "a\n");
+	let err = repo.fail(&["hash-object", "-w", "a"]);
+	assert!(err.contains("bad zlib compression level 10"), "{}", err);
+}
+
+// pack.compressionはpackの中のobjectに使う
+#[test]
+fn pack_compression() {
+	let size = |level: &str| {
+		let repo = TempRepo::new();
+		repo.git(&["config", "pack.compression", level]);
+		repo.commit_file("a", &"compressible line\n".repeat(1000), "first");
+		repo.git(&["gc"]);
+		repo.git(&["fsck"]);
+		let pack = files(&repo.path(".git/objects/pack"))
+			.into_iter()
+			.find(|x| x.extension().is_some_and(|x| x == "pack"))
+			.unwrap();
+		fs::metadata(pack).unwrap().len()
+	};
+	assert!(size("9") < size("0") / 10);
+}
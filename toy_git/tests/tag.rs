@@ -0,0 +1,106 @@
+mod common;
+
+use common::TempRepo;
+
+// lightweightなtagいくつかと、annotatedなv1.0
+fn tagged() -> TempRepo {
+	let repo = TempRepo::new();
+	repo.commit_file("a", "a\n", "first");
+	for name in ["v1.10", "v1.9", "v1.2", "v2.0", "other", "v1.9.1"] {
+		repo.git(&["tag", name]);
+	}
+	repo.git(&["tag", "-a", "-m", "Release one\n\nbody", "v1.0"]);
+	repo
+}
+
+#[test]
+fn version_sort() {
+	let repo = tagged();
+	// 既定は名前の順
+	assert_eq!(
+		repo.git(&["tag"]),
+		"other\nv1.0\nv1.10\nv1.2\nv1.9\nv1.9.1\nv2.0\n"
+	);
+	assert_eq!(
+		repo.git(&["tag", "--sort=version:refname"]),
+		"other\nv1.0\nv1.2\nv1.9\nv1.9.1\nv1.10\nv2.0\n"
+	);
+	assert_eq!(
+		repo.git(&["tag", "-l", "--sort=-version:refname", "v1.*"]),
+		"v1.10\nv1.9.1\nv1.9\nv1.2\nv1.0\n"
+	);
+}
+
+#[test]
+fn pattern() {
+	let repo = tagged();
+	assert_eq!(
+		repo.git(&["tag", "-l", "v1.*"]),
+		"v1.0\nv1.10\nv1.2\nv1.9\nv1.9.1\n"
+	);
+	assert_eq!(
+		repo.git(&["tag", "-l", "v1.9*", "v2*"]),
+		"v1.9\nv1.9.1\nv2.0\n"
+	);
+	assert_eq!(repo.git(&["tag", "-l", "nothing*"]), "");
+	// -lが無ければ新しいtagの名前
+	let err = repo.fail(&["tag", "v1.*"]);
+	assert!(err.contains("'v1.*' is not a valid tag name."), "{}", err);
+}
+
+#[test]
+fn annotations() {
+	let repo = tagged();
+	// lightweightなtagはcommitのsubject。出力はgitと同じ
+	assert_eq!(
+		repo.git(&["tag", "-n", "-l", "v1.0", "v2.0"]),
+		"v1.0            Release one\nv2.0            first\n"
+	);
+	assert_eq!(
+		repo.git(&["tag", "-n3", "-l", "v1.0"]),
+		"v1.0            Release one\n    \n    body\n"
+	);
+}
+
+#[test]
+fn delete() {
+	let repo = TempRepo::new();
+	let commit = repo.commit_file("a", "a\n", "first");
+	repo.git(&["tag", "loose"]);
+	repo.git(&["tag", "keep"]);
+	// packedはpacked-refsにだけあり、bothはloose refもある
+	repo.append(
+		".git/packed-refs",
+		&format!(
+			"# pack-refs with: peeled fully-peeled sorted \n{0} refs/tags/both\n{0} refs/tags/packed\n",
+			commit
+		),
+	);
+	repo.write(".git/refs/tags/both", format!("{}\n", commit));
+
+	let short = &commit[..7];
+	assert_eq!(
+		repo.git(&["tag", "-d", "loose", "packed"]),
+		format!(
+			"Deleted tag 'loose' (was {0})\nDeleted tag 'packed' (was {0})\n",
+			short
+		)
+	);
+	assert!(!repo.path(".git/refs/tags/loose").exists());
+	let packed = String::from_utf8(repo.read(".git/packed-refs")).unwrap();
+	assert!(!packed.contains("refs/tags/packed"), "{}", packed);
+	assert!(packed.contains("refs/tags/both"), "{}", packed);
+
+	repo.git(&["tag", "--delete", "both"]);
+	assert!(!repo.path(".git/refs/tags/both").exists());
+	let packed = String::from_utf8(repo.read(".git/packed-refs")).unwrap();
+	assert!(!packed.contains("refs/tags/both"), "{}", packed);
+	assert_eq!(repo.git(&["tag"]), "keep\n");
+
+	let output = repo.run(&["tag", "-d", "nope"]);
+	assert_eq!(output.status.code(), Some(1));
+	assert_eq!(
+		String::from_utf8(output.stderr).unwrap(),
+		"error: tag 'nope' not found.\n"
+	);
+}
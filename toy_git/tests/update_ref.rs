@@ -0,0 +1,103 @@
+mod common;
+
+use common::TempRepo;
+
+// 2つのcommitのhash
+fn history(repo: &TempRepo) -> (String, String) {
+	let a = repo.commit_file("f", "one\n", "first");
+	let b = repo.commit_file("f", "two\n", "second");
+	(a, b)
+}
+
+fn resolve(repo: &TempRepo, name: &str) -> Option<String> {
+	let output = repo.run(&["rev-parse", "--verify", name]);
+	output
+		.status
+		.success()
+		.then(|| String::from_utf8(output.stdout).unwrap().trim().to_string())
+}
+
+#[test]
+fn transaction() {
+	let repo = TempRepo::new();
+	let (a, b) = history(&repo);
+	let input = format!(
+		"create refs/heads/x {a}\ncreate refs/heads/y {a}\nupdate refs/heads/z {b} {zero}\n",
+		zero = "0".repeat(40)
+	);
+	repo.git_with(&["update-ref", "--stdin"], input.as_bytes());
+	assert_eq!(resolve(&repo, "refs/heads/x"), Some(a.clone()));
+	assert_eq!(resolve(&repo, "refs/heads/y"), Some(a.clone()));
+	assert_eq!(resolve(&repo, "refs/heads/z"), Some(b.clone()));
+
+	let input =
+		format!("update refs/heads/x {b} {a}\ndelete refs/heads/y {a}\nverify refs/heads/z {b}\n");
+	repo.git_with(&["update-ref", "--stdin"], input.as_bytes());
+	assert_eq!(resolve(&repo, "refs/heads/x"), Some(b.clone()));
+	assert_eq!(resolve(&repo, "refs/heads/y"), None);
+
+	// 更新したrefごとにreflogが書かれる
+	let log = String::from_utf8(repo.read(".git/logs/refs/heads/x")).unwrap();
+	let moves: Vec<(&str, &str)> = log
+		.lines()
+		.map(|x| {
+			let mut fields = x.split(' ');
+			(fields.next().unwrap(), fields.next().unwrap())
+		})
+		.collect();
+	assert_eq!(
+		moves,
+		[
+			("0".repeat(40).as_str(), a.as_str()),
+			(a.as_str(), b.as_str())
+		]
+	);
+}
+
+#[test]
+fn failed_check_rolls_back() {
+	let repo = TempRepo::new();
+	let (a, b) = history(&repo);
+	let input = format!("create refs/heads/x {a}\ncreate refs/heads/y {a}\n");
+	repo.git_with(&["update-ref", "--stdin"], input.as_bytes());
+
+	// yのoldが合わないので、xも動かない
+	let input = format!(
+		"update refs/heads/x {b} {a}\ncreate refs/heads/new {b}\nupdate refs/heads/y {b} {b}\n"
+	);
+	let output = repo.run_in(
+		&repo.dir,
+		&["update-ref", "--stdin"],
+		Some(input.as_bytes()),
+	);
+	assert!(!output.status.success());
+	let err = String::from_utf8(output.stderr).unwrap();
+	assert!(
+		err.contains(&format!(
+			"cannot lock ref 'refs/heads/y': is at {} but expected {}",
+			a, b
+		)),
+		"{}",
+		err
+	);
+	assert_eq!(resolve(&repo, "refs/heads/x"), Some(a.clone()));
+	assert_eq!(resolve(&repo, "refs/heads/y"), Some(a.clone()));
+	assert_eq!(resolve(&repo, "refs/heads/new"), None);
+	assert_eq!(
+		repo.read(".git/logs/refs/heads/x")
+			.iter()
+			.filter(|&&x| x == b'\n')
+			.count(),
+		1
+	);
+
+	// 存在しないrefのverifyも同じ
+	let input = format!("delete refs/heads/x {a}\nverify refs/heads/missing {a}\n");
+	let output = repo.run_in(
+		&repo.dir,
+		&["update-ref", "--stdin"],
+		Some(input.as_bytes()),
+	);
+	assert!(!output.status.success());
+	assert_eq!(resolve(&repo, "refs/heads/x"), Some(a));
+}
@@ -0,0 +1,125 @@
+mod common;
+
+use common::TempRepo;
+use toy_git::{upstream, Hash};
+
+// remoteをoriginとしてfetchし、mainとtopicを作ったrepository
+fn tracking() -> (TempRepo, TempRepo) {
+	let remote = TempRepo::new();
+	remote.commit_file("a", "a\n", "first");
+	let repo = TempRepo::new();
+	repo.git(&["config", "remote.origin.url", remote.dir.to_str().unwrap()]);
+	repo.git(&["fetch"]);
+	repo.git(&["reset", "--hard", "origin/main"]);
+	repo.git(&["branch", "topic"]);
+	(remote, repo)
+}
+
+fn rev(repo: &TempRepo, rev: &str) -> String {
+	repo.git(&["rev-parse", rev]).trim().to_string()
+}
+
+#[test]
+fn set_upstream_to() {
+	let (_, repo) = tracking();
+	assert_eq!(
+		repo.git(&["branch", "--set-upstream-to=origin/main", "topic"]),
+		"branch 'topic' set up to track 'origin/main'.\n"
+	);
+	assert_eq!(repo.git(&["config", "branch.topic.remote"]), "origin\n");
+	assert_eq!(
+		repo.git(&["config", "branch.topic.merge"]),
+		"refs/heads/main\n"
+	);
+	let tracked = rev(&repo, "refs/remotes/origin/main");
+	assert_eq!(rev(&repo, "topic@{upstream}"), tracked);
+	assert_eq!(rev(&repo, "topic@{u}"), tracked);
+
+	// 名前の無い@{u}は今のbranchのもの
+	repo.git(&["checkout", "topic"]);
+	assert_eq!(rev(&repo, "@{u}"), tracked);
+	assert!(repo
+		.git(&["status"])
+		.contains("Your branch is up to date with 'origin/main'."));
+}
+
+#[test]
+fn local_upstream() {
+	let (_, repo) = tracking();
+	// branchを指定しなければ今のbranchに設定する
+	repo.git(&["checkout", "topic"]);
+	repo.git(&["branch", "-u", "main"]);
+	assert_eq!(repo.git(&["config", "branch.topic.remote"]), ".\n");
+	assert_eq!(
+		repo.git(&["config", "branch.topic.merge"]),
+		"refs/heads/main\n"
+	);
+	repo.git(&["checkout", "main"]);
+	let tip = repo.commit_file("b", "b\n", "on main");
+	assert_eq!(rev(&repo, "topic@{u}"), tip);
+}
+
+#[test]
+fn missing_upstream() {
+	let (_, repo) = tracking();
+	let err = repo.fail(&["rev-parse", "topic@{u}"]);
+	assert!(
+		err.contains("no upstream configured for branch 'topic'"),
+		"{}",
+		err
+	);
+	let err = repo.fail(&["branch", "-u", "origin/nope", "topic"]);
+	assert!(
+		err.contains("the requested upstream branch 'origin/nope' does not exist"),
+		"{}",
+		err
+	);
+	assert!(repo
+		.run(&["config", "branch.topic.merge"])
+		.stdout
+		.is_empty());
+}
+
+// originより1つ進んで2つ遅れたmain
+fn diverged() -> (TempRepo, TempRepo) {
+	let (remote, repo) = tracking();
+	repo.git(&["branch", "-u", "origin/main"]);
+	remote.commit_file("r", "1\n", "remote 1");
+	remote.commit_file("r", "2\n", "remote 2");
+	repo.git(&["fetch"]);
+	repo.commit_file("l", "1\n", "local");
+	(remote, repo)
+}
+
+#[test]
+fn ahead_and_behind() {
+	let (_, repo) = diverged();
+	let opened = repo.open();
+	let hash = |x: &str| Hash::from_hex(&rev(&repo, x)).unwrap();
+	let (main, origin) = (hash("main"), hash("origin/main"));
+	assert_eq!(
+		upstream::ahead_behind(&opened, &main, &origin).unwrap(),
+		(1, 2)
+	);
+	assert_eq!(
+		upstream::ahead_behind(&opened, &origin, &main).unwrap(),
+		(2, 1)
+	);
+	assert_eq!(
+		upstream::ahead_behind(&opened, &main, &main).unwrap(),
+		(0, 0)
+	);
+
+	assert!(repo.git(&["status"]).contains(
+		"Your branch and 'origin/main' have diverged,\nand have 1 and 2 different commits each, respectively.\n"
+	));
+	repo.git(&["reset", "--hard", "HEAD~1"]);
+	assert!(repo.git(&["status"]).contains(
+		"Your branch is behind 'origin/main' by 2 commits, and can be fast-forwarded.\n"
+	));
+	repo.git(&["reset", "--hard", "origin/main"]);
+	repo.commit_file("l", "1\n", "local");
+	assert!(repo
+		.git(&["status"])
+		.contains("Your branch is ahead of 'origin/main' by 1 commit.\n"));
+}
@@ -0,0 +1,134 @@
+mod common;
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+
+use common::TempRepo;
+use toy_git::worktree::Worktree;
+use toy_git::{Blob, Repository};
+
+#[test]
+fn write_with_exec_bit() {
+	let repo = TempRepo::new();
+	let open = repo.open();
+	let worktree = Worktree::new(&open);
+
+	let blob = Blob::new(b"#!/bin/sh\n".to_vec());
+	worktree.write_file("bin/run", &blob, 0o100755).unwrap();
+	let meta = worktree.stat("bin/run").unwrap();
+	assert_eq!(meta.permissions().mode() & 0o777, 0o755);
+	assert_eq!(worktree.read_file("bin/run").unwrap(), b"#!/bin/sh\n");
+
+	// 実行可能でなくなれば書き直したときにbitも落とす
+	worktree.write_file("bin/run", &blob, 0o100644).unwrap();
+	let meta = worktree.stat("bin/run").unwrap();
+	assert_eq!(meta.permissions().mode() & 0o777, 0o644);
+}
+
+#[test]
+fn symlink() {
+	let repo = TempRepo::new();
+	let open = repo.open();
+	let worktree = Worktree::new(&open);
+
+	worktree
+		.write_file("link", &Blob::new(b"target".to_vec()), 0o120000)
+		.unwrap();
+	assert!(worktree.stat("link").unwrap().file_type().is_symlink());
+	// リンク先が無くてもpathを内容として読む
+	assert_eq!(worktree.read_file("link").unwrap(), b"target");
+
+	// ファイルで置き換える
+	worktree
+		.write_file("link", &Blob::new(b"file\n".to_vec()), 0o100644)
+		.unwrap();
+	assert!(worktree.stat("link").unwrap().is_file());
+	assert_eq!(repo.read("link"), b"file\n");
+}
+
+#[test]
+fn remove_empty_dirs() {
+	let repo = TempRepo::new();
+	let open = repo.open();
+	let worktree = Worktree::new(&open);
+
+	let blob = Blob::new(b"x\n".to_vec());
+	worktree.write_file("a/b/c", &blob, 0o100644).unwrap();
+	worktree.write_file("a/d", &blob, 0o100644).unwrap();
+
+	worktree.remove_file("a/b/c").unwrap();
+	assert!(!repo.path("a/b").exists());
+	// 他のファイルが残るディレクトリは消さない
+	assert!(repo.path("a/d").exists());
+	// 無いファイルを消してもErrorにしない
+	worktree.remove_file("a/b/c").unwrap();
+
+	worktree.remove_file("a/d").unwrap();
+	assert!(!repo.path("a").exists());
+	assert!(fs::metadata(repo.path(".git")).unwrap().is_dir());
+}
+
+// mainとtopicのあるrepositoryと、topicをcheckoutしたlinked worktree
+fn linked() -> (TempRepo, TempRepo) {
+	let repo = TempRepo::new();
+	repo.commit_file("a", "a\n", "first");
+	repo.git(&["branch", "topic"]);
+	let place = TempRepo::empty();
+	let path = place.path("linked");
+	repo.git(&["worktree", "add", path.to_str().unwrap(), "topic"]);
+	(repo, place)
+}
+
+#[test]
+fn worktree_add() {
+	let (repo, place) = linked();
+	let path = place.path("linked");
+	let gitdir = repo.path(".git/worktrees/linked");
+	assert_eq!(
+		fs::read_to_string(path.join(".git")).unwrap(),
+		format!("gitdir: {}\n", gitdir.display())
+	);
+	assert_eq!(
+		fs::read(gitdir.join("HEAD")).unwrap(),
+		b"ref: refs/heads/topic\n"
+	);
+	assert_eq!(fs::read(path.join("a")).unwrap(), b"a\n");
+
+	let open = Repository::discover(&path).unwrap();
+	assert_eq!(open.gitdir, gitdir);
+	assert_eq!(open.commondir, repo.path(".git"));
+	assert_eq!(open.workdir, path);
+}
+
+#[test]
+fn independent_head_and_index() {
+	let (repo, place) = linked();
+	let path = place.path("linked");
+	let git = |args: &[&str]| {
+		let output = repo.run_in(&path, args, None);
+		assert!(output.status.success(), "{:?}", args);
+		String::from_utf8(output.stdout).unwrap()
+	};
+
+	// linked worktreeでのcommitはtopicを進め、mainのHEADとindexは変わらない
+	fs::write(path.join("b"), "b\n").unwrap();
+	git(&["add", "b"]);
+	assert!(!repo.git(&["ls-files", "-s"]).contains("\tb\n"));
+	git(&["commit", "-m", "linked"]);
+	let commit = git(&["rev-parse", "HEAD"]);
+	assert_eq!(repo.git(&["rev-parse", "topic"]), commit);
+	assert_ne!(repo.git(&["rev-parse", "HEAD"]), commit);
+	assert!(repo.git(&["status"]).contains("On branch main"));
+
+	// objectは共有している
+	assert_eq!(repo.git(&["cat-file", "-p", "topic:b"]), "b\n");
+	assert!(!path.join(".git").is_dir());
+
+	let err = repo.fail(&[
+		"worktree",
+		"add",
+		place.path("again").to_str().unwrap(),
+		"topic",
+	]);
+	assert!(err.contains("'topic' is already checked out at"), "{}", err);
+}